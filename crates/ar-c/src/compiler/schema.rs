@@ -39,6 +39,8 @@ pub struct TeamManifest {
     pub name: String,
     pub description: String,
     pub members: Vec<String>, // Names of agents in this team
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>, // How members work together; runtime picks when absent
 }
 
 /// Metadata for a decorated `@pipeline` class.
@@ -46,5 +48,8 @@ pub struct TeamManifest {
 pub struct PipelineManifest {
     pub name: String,
     pub description: String,
-    // Add other pipeline-specific fields here later
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<serde_json::Value>, // Node definitions, validated by the runtime
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edges: Vec<serde_json::Value>, // Edges between nodes, validated by the runtime
 } 
\ No newline at end of file
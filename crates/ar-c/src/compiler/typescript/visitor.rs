@@ -106,6 +106,7 @@ impl AstVisitor {
             name: class.ident.sym.to_string(),
             description: String::new(),
             members: Vec::new(),
+            strategy: None,
         };
 
         if let Some(call) = decorator.expr.as_call() {
@@ -118,6 +119,7 @@ impl AstVisitor {
                                 "name" => manifest.name = self.get_prop_value(kv),
                                 "description" => manifest.description = self.get_prop_value(kv),
                                 "members" => manifest.members = self.get_string_array(kv),
+                                "strategy" => manifest.strategy = Some(self.get_prop_value(kv)),
                                 _ => {}
                             }
                         }
@@ -133,6 +135,8 @@ impl AstVisitor {
         let mut manifest = PipelineManifest {
             name: class.ident.sym.to_string(),
             description: String::new(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
         };
 
         if let Some(call) = decorator.expr.as_call() {
@@ -144,6 +148,8 @@ impl AstVisitor {
                             match key.as_str() {
                                 "name" => manifest.name = self.get_prop_value(kv),
                                 "description" => manifest.description = self.get_prop_value(kv),
+                                "nodes" => manifest.nodes = self.get_json_array(kv),
+                                "edges" => manifest.edges = self.get_json_array(kv),
                                 _ => {}
                             }
                         }
@@ -191,6 +197,51 @@ impl AstVisitor {
         items
     }
 
+    fn get_json_array(&self, kv: &KeyValueProp) -> Vec<serde_json::Value> {
+        match self.expr_to_json(&kv.value) {
+            Some(serde_json::Value::Array(items)) => items,
+            _ => Vec::new(),
+        }
+    }
+
+    /// Convert a literal expression (objects, arrays, strings, numbers, booleans,
+    /// null) to JSON. Anything computed at runtime yields `None`.
+    fn expr_to_json(&self, expr: &Expr) -> Option<serde_json::Value> {
+        match expr {
+            Expr::Lit(Lit::Str(s)) => Some(serde_json::Value::String(s.value.to_string())),
+            Expr::Lit(Lit::Bool(b)) => Some(serde_json::Value::Bool(b.value)),
+            Expr::Lit(Lit::Null(_)) => Some(serde_json::Value::Null),
+            Expr::Lit(Lit::Num(n)) => {
+                // Keep integral numbers integral so they deserialize into integer fields
+                if n.value.fract() == 0.0 && n.value.abs() < i64::MAX as f64 {
+                    Some(serde_json::Value::from(n.value as i64))
+                } else {
+                    serde_json::Number::from_f64(n.value).map(serde_json::Value::Number)
+                }
+            }
+            Expr::Array(array_lit) => Some(serde_json::Value::Array(
+                array_lit.elems.iter()
+                    .flatten()
+                    .filter_map(|elem| self.expr_to_json(&elem.expr))
+                    .collect(),
+            )),
+            Expr::Object(obj) => {
+                let mut map = serde_json::Map::new();
+                for prop in &obj.props {
+                    if let Some(kv) = prop.as_prop().and_then(|p| p.as_key_value()) {
+                        if let Some(value) = self.expr_to_json(&kv.value) {
+                            map.insert(self.get_prop_key(kv), value);
+                        }
+                    }
+                }
+                Some(serde_json::Value::Object(map))
+            }
+            Expr::Paren(paren) => self.expr_to_json(&paren.expr),
+            Expr::TsConstAssertion(assertion) => self.expr_to_json(&assertion.expr),
+            _ => None,
+        }
+    }
+
     fn get_tools_list(&self, kv: &KeyValueProp) -> Vec<String> {
         let mut tools = Vec::new();
         if let Expr::Array(array_lit) = &*kv.value {
@@ -8,6 +8,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .type_attribute("quilt.GetSystemMetricsResponse", "#[derive(serde::Serialize)]")
         .type_attribute("quilt.NetworkNode", "#[derive(serde::Serialize)]")
         .type_attribute("quilt.GetContainerNetworkInfoResponse", "#[derive(serde::Serialize)]")
+        .type_attribute("quilt.NetworkAttachment", "#[derive(serde::Serialize)]")
         .compile_protos(&["../../crates/quilt/proto/quilt.proto"], &["../../crates/quilt/proto/"])?;
 
     // Compile the Aria Runtime API proto (without serde attributes to avoid conflicts)
@@ -1,7 +1,12 @@
+use crate::database::conversations::ConversationOps;
+use crate::engines::tool_registry::{ToolRegistry, ToolRegistryInterface};
 use crate::errors::AriaResult;
 use crate::types::*;
 use serde::{Deserialize, Serialize};
 
+/// Conversation row key for agents running without a stored configuration
+pub const DEFAULT_AGENT_CONFIG_ID: &str = "default";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentConfig {
     pub name: String,
@@ -13,12 +18,103 @@ pub struct AgentConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentResult {
+    pub agent: String,
     pub success: bool,
     pub result: Option<serde_json::Value>,
     pub error: Option<String>,
     pub metrics: ExecutionMetrics,
 }
 
+impl From<&pkg_store::bundle::AgentManifest> for AgentConfig {
+    fn from(manifest: &pkg_store::bundle::AgentManifest) -> Self {
+        Self {
+            name: manifest.name.clone(),
+            description: manifest.description.clone(),
+            tools: manifest.tools.clone(),
+            llm: LLMConfig::default(),
+            capabilities: Vec::new(),
+        }
+    }
+}
+
+impl AgentConfig {
+    /// The configuration `AriaRuntime::execute` takes for this agent
+    pub fn to_runtime_config(&self) -> crate::types::AgentConfig {
+        crate::types::AgentConfig {
+            name: self.name.clone(),
+            system_prompt: (!self.description.is_empty()).then(|| self.description.clone()),
+            directives: None,
+            tools: self.tools.clone(),
+            agents: Vec::new(),
+            llm: self.llm.clone(),
+            max_iterations: None,
+            timeout_ms: None,
+            memory_limit: None,
+            agent_type: None,
+            capabilities: self.capabilities.iter().map(|c| c.name.clone()).collect(),
+            memory_enabled: None,
+        }
+    }
+}
+
+/// Load an agent configuration stored in a user's database: `config_id` when given,
+/// else the user's default, else the built-in assistant. Returns the configuration's ID
+/// with the runtime form of the agent.
+pub async fn load_stored_agent(
+    pool: &sqlx::SqlitePool,
+    tool_registry: &ToolRegistry,
+    user_id: &str,
+    config_id: Option<&str>,
+) -> AriaResult<(String, crate::types::AgentConfig)> {
+    let record = match config_id {
+        Some(config_id) => ConversationOps::get_agent_config(pool, config_id).await?,
+        None => ConversationOps::get_default_agent_config(pool, user_id).await?,
+    };
+    let Some(record) = record else {
+        return Ok((DEFAULT_AGENT_CONFIG_ID.to_string(), crate::types::AgentConfig {
+            name: "assistant".to_string(),
+            ..Default::default()
+        }));
+    };
+
+    let mut agent = crate::types::AgentConfig {
+        name: record.name,
+        system_prompt: record.system_prompt,
+        tools: resolve_tool_scopes(tool_registry, &record.tool_scopes).await,
+        ..Default::default()
+    };
+    agent.llm.provider = record.llm_provider;
+    agent.llm.model = record.llm_model;
+    agent.llm.temperature = Some(record.temperature);
+    agent.llm.max_tokens = Some(record.max_tokens);
+
+    Ok((record.config_id, agent))
+}
+
+/// Expand an agent's tool scopes into tool names; anything that is not a scope names a tool
+pub async fn resolve_tool_scopes(tool_registry: &ToolRegistry, scopes: &[String]) -> Vec<String> {
+    let mut tools: Vec<String> = Vec::new();
+    for scope in scopes {
+        let names = match scope.as_str() {
+            "all" => tool_registry.list_available_tools().await,
+            "primitive" => tool_registry.list_primitive_tools().await,
+            "abstract" => tool_registry.list_abstract_tools().await,
+            name => Ok(vec![name.to_string()]),
+        };
+        match names {
+            Ok(names) => {
+                for name in names {
+                    if !tools.contains(&name) {
+                        tools.push(name);
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("Failed to list tools for scope '{}': {}", scope, e),
+        }
+    }
+    tools
+}
+
 pub struct Agent {
     config: AgentConfig,
 }
@@ -30,6 +126,7 @@ impl Agent {
 
     pub async fn run(&self, task: &str) -> AriaResult<AgentResult> {
         Ok(AgentResult {
+            agent: self.config.name.clone(),
             success: true,
             result: Some(serde_json::json!({"response": "Task completed", "task": task})),
             error: None,
@@ -8,10 +8,11 @@ use aria_runtime::{
     database::{DatabaseManager, DatabaseConfig},
     engines::{
         container::quilt::QuiltService,
-        tool_registry::ToolRegistry,
+        tool_registry::{ToolRegistry, ToolPolicyEngine, PolicyConfig},
         llm::LLMHandler,
         intelligence::IntelligenceEngine,
         observability::ObservabilityManager,
+        AriaEngines,
     },
     grpc::{
         aria::{
@@ -20,14 +21,28 @@ use aria_runtime::{
             container_service_server::ContainerServiceServer,
             notification_service_server::NotificationServiceServer,
             bundle_service_server::BundleServiceServer,
+            approval_service_server::ApprovalServiceServer,
+            tenant_service_server::TenantServiceServer,
+            cost_service_server::CostServiceServer,
+            schedule_service_server::ScheduleServiceServer,
+            trigger_service_server::TriggerServiceServer,
         },
         task_service::TaskServiceImpl,
         session_service::SessionServiceImpl,
         container_service::ContainerServiceImpl,
         notification_service::NotificationServiceImpl,
         bundle_service::BundleServiceImpl,
+        approval_service::ApprovalServiceImpl,
+        tenant_service::TenantServiceImpl,
+        cost_service::CostServiceImpl,
+        schedule_service::ScheduleServiceImpl,
+        trigger_service::TriggerServiceImpl,
+        auth::{self, AuthConfig, CallerAuthenticator, UserRole},
     },
     errors::AriaResult,
+    telemetry::{self, TelemetryConfig},
+    trigger_endpoints::create_trigger_router,
+    AriaRuntime, CostTracker, RuntimeConfiguration, Scheduler, TaskRunner, TriggerEngine, WebhookDispatcher,
 };
 
 /// Configuration for the Aria Runtime gRPC server
@@ -36,15 +51,39 @@ pub struct ServerConfig {
     pub socket_path: String,
     pub database_path: String,
     pub quilt_socket_path: String,
+    /// Devices enrolled at startup: `<user_id> <device_id> ssh-ed25519 <base64>` per line
+    pub authorized_devices_path: String,
+    /// Users who can see and manage every tenant's resources
+    pub admin_users: Vec<String>,
+    /// TCP address for webhook deliveries to triggers; `None` disables them
+    pub http_addr: Option<String>,
 }
 
+/// Overrides `ServerConfig::http_addr`; set but empty disables the listener
+const HTTP_ADDR_ENV: &str = "ARIA_HTTP_ADDR";
+
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             socket_path: "/run/aria/api.sock".to_string(),
             database_path: "./aria.db".to_string(),
             quilt_socket_path: "/run/quilt/api.sock".to_string(),
+            authorized_devices_path: "/etc/aria/authorized_devices".to_string(),
+            admin_users: Vec::new(),
+            http_addr: Some("127.0.0.1:7600".to_string()),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// The defaults, with the trigger listener taken from `ARIA_HTTP_ADDR` when it is set
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Ok(http_addr) = std::env::var(HTTP_ADDR_ENV) {
+            let http_addr = http_addr.trim();
+            config.http_addr = (!http_addr.is_empty()).then(|| http_addr.to_string());
         }
+        config
     }
 }
 
@@ -54,7 +93,15 @@ pub struct AriaServer {
     database: Arc<DatabaseManager>,
     quilt_service: Arc<Mutex<QuiltService>>,
     tool_registry: Arc<ToolRegistry>,
+    policy_engine: Arc<ToolPolicyEngine>,
     intelligence_engine: Arc<IntelligenceEngine>,
+    task_runner: Arc<TaskRunner>,
+    scheduler: Arc<Scheduler>,
+    triggers: Arc<TriggerEngine>,
+    webhooks: Arc<WebhookDispatcher>,
+    observability: Arc<ObservabilityManager>,
+    costs: Arc<CostTracker>,
+    authenticator: CallerAuthenticator,
 }
 
 impl AriaServer {
@@ -75,6 +122,18 @@ impl AriaServer {
         database.initialize().await?;
         info!("Database initialized at: {}", config.database_path);
         
+        // Every call must be signed by an enrolled device key
+        let authenticator = CallerAuthenticator::new(AuthConfig::default());
+        let enrolled = authenticator
+            .enroll_authorized_devices(&database, std::path::Path::new(&config.authorized_devices_path))
+            .await?;
+        for user_id in &config.admin_users {
+            authenticator.grant_role(&database, user_id, UserRole::Admin).await?;
+        }
+        let trusted = authenticator.load(&database).await?;
+        info!("Caller authentication ready: {} devices trusted ({} from {})",
+              trusted, enrolled, config.authorized_devices_path);
+        
         // Initialize Quilt service
         let quilt_config = aria_runtime::engines::config::QuiltConfig {
             socket_path: config.quilt_socket_path.clone(),
@@ -98,29 +157,48 @@ impl AriaServer {
         ).await);
         info!("Tool registry initialized");
         
-        // Initialize observability (requires database)
-        let observability = Arc::new(
-            ObservabilityManager::new(
-                Arc::clone(&database),
-                1000 // buffer size
-            )?
+        // Put tool execution behind the security policy, with the rules saved in the system
+        // database and decisions audited into each user's database
+        let policy_engine = Arc::new(
+            ToolPolicyEngine::new(PolicyConfig::default()).with_database(Arc::clone(&database))
         );
+        policy_engine.load_rules().await?;
+        tool_registry.attach_policy_engine(Arc::clone(&policy_engine)).await;
         
-        // Initialize intelligence engine (requires database and observability)
-        let intelligence_engine = Arc::new(
-            IntelligenceEngine::new(
-                Arc::clone(&database),
-                observability,
-                aria_runtime::engines::intelligence::IntelligenceConfig::default()
-            )
-        );
+        // The runtime that runs tasks shares the server's database, quilt connection and tools
+        let engines = AriaEngines::with_services(
+            Arc::clone(&database),
+            Arc::clone(&quilt_service),
+            Arc::clone(&tool_registry),
+        ).await?;
+        let intelligence_engine = Arc::clone(&engines.intelligence);
+        let observability = Arc::clone(&engines.observability);
+        let costs = Arc::clone(&engines.costs);
+        let runtime = AriaRuntime::with_engines(engines, RuntimeConfiguration::default());
+        
+        // Queue tasks left unfinished by a previous run before accepting new ones
+        let task_runner = Arc::new(TaskRunner::new(runtime));
+        task_runner.recover().await?;
+        info!("Task runner initialized");
+        
+        let scheduler = Arc::new(Scheduler::new(Arc::clone(&database), Arc::clone(&task_runner)));
+        let triggers = Arc::new(TriggerEngine::new(Arc::clone(&database), Arc::clone(&task_runner)));
+        let webhooks = Arc::new(WebhookDispatcher::new(Arc::clone(&database)));
         
         Ok(Self {
             config,
             database,
             quilt_service,
             tool_registry,
+            policy_engine,
             intelligence_engine,
+            task_runner,
+            scheduler,
+            triggers,
+            webhooks,
+            observability,
+            costs,
+            authenticator,
         })
     }
     
@@ -129,24 +207,81 @@ impl AriaServer {
         info!("Starting Aria Runtime gRPC server on: {}", self.config.socket_path);
         
         // Create service implementations
+        // Notifications are posted to webhook subscriptions as well as streamed
+        self.webhooks.start();
+        let notification_service = NotificationServiceImpl::new(
+            Arc::clone(&self.database),
+            Arc::clone(&self.webhooks),
+        );
+        
+        // Attached first, so tasks recovered from a previous run are announced too
+        self.task_runner.attach_notifier(Arc::new(notification_service.clone())).await;
+        self.task_runner.start();
         let task_service = TaskServiceImpl::new(
             Arc::clone(&self.quilt_service),
             Arc::clone(&self.database),
+            Arc::clone(&self.task_runner),
         );
         
         let session_service = SessionServiceImpl::new(
             Arc::clone(&self.database),
             Arc::clone(&self.intelligence_engine),
             Arc::clone(&self.tool_registry),
+            Arc::clone(&self.costs),
         );
         
         let container_service = ContainerServiceImpl::new(
             Arc::clone(&self.quilt_service),
+            Arc::clone(&self.database),
         );
         
-        let notification_service = NotificationServiceImpl::new(
+        // Held tool calls are announced on the notification stream
+        self.policy_engine.attach_notifier(Arc::new(notification_service.clone())).await;
+        let approval_service = ApprovalServiceImpl::new(
+            Arc::clone(&self.policy_engine),
+        );
+        
+        let tenant_service = TenantServiceImpl::new(
             Arc::clone(&self.database),
+            Arc::clone(&self.quilt_service),
+        );
+        
+        let cost_service = CostServiceImpl::new(
+            Arc::clone(&self.database),
+            Arc::clone(&self.costs),
+        );
+        
+        // Schedules fire through the task runner, so they start after it
+        self.scheduler.start();
+        let schedule_service = ScheduleServiceImpl::new(
+            Arc::clone(&self.quilt_service),
+            Arc::clone(&self.database),
+            Arc::clone(&self.scheduler),
+        );
+        
+        // Triggers also launch through the task runner, from events and webhook deliveries
+        self.triggers.start(&self.observability).await?;
+        let trigger_service = TriggerServiceImpl::new(
+            Arc::clone(&self.quilt_service),
+            Arc::clone(&self.database),
+            Arc::clone(&self.triggers),
         );
+        if let Some(http_addr) = &self.config.http_addr {
+            let listener = tokio::net::TcpListener::bind(http_addr).await
+                .map_err(|e| aria_runtime::errors::AriaError::new(
+                    aria_runtime::errors::ErrorCode::NetworkError,
+                    aria_runtime::errors::ErrorCategory::Network,
+                    aria_runtime::errors::ErrorSeverity::High,
+                    &format!("Failed to bind webhook listener: {}", e)
+                ))?;
+            info!("Webhook endpoint listening on: {}", http_addr);
+            let router = create_trigger_router(Arc::clone(&self.triggers));
+            tokio::spawn(async move {
+                if let Err(e) = axum::serve(listener, router).await {
+                    error!("Webhook endpoint error: {}", e);
+                }
+            });
+        }
         
         let bundle_service = BundleServiceImpl::new(
             Arc::clone(&self.quilt_service),
@@ -191,11 +326,19 @@ impl AriaServer {
         
         // Build and start the server
         let result = Server::builder()
-            .add_service(TaskServiceServer::new(task_service))
-            .add_service(SessionServiceServer::new(session_service))
-            .add_service(ContainerServiceServer::new(container_service))
-            .add_service(NotificationServiceServer::new(notification_service))
-            .add_service(BundleServiceServer::new(bundle_service))
+            .trace_fn(telemetry::grpc_span)
+            // Signed requests cover the method path, which interceptors cannot see on their own
+            .layer(tower::util::MapRequestLayer::new(auth::record_request_path))
+            .add_service(TaskServiceServer::with_interceptor(task_service, self.authenticator.clone()))
+            .add_service(SessionServiceServer::with_interceptor(session_service, self.authenticator.clone()))
+            .add_service(ContainerServiceServer::with_interceptor(container_service, self.authenticator.clone()))
+            .add_service(NotificationServiceServer::with_interceptor(notification_service, self.authenticator.clone()))
+            .add_service(BundleServiceServer::with_interceptor(bundle_service, self.authenticator.clone()))
+            .add_service(ApprovalServiceServer::with_interceptor(approval_service, self.authenticator.clone()))
+            .add_service(TenantServiceServer::with_interceptor(tenant_service, self.authenticator.clone()))
+            .add_service(CostServiceServer::with_interceptor(cost_service, self.authenticator.clone()))
+            .add_service(ScheduleServiceServer::with_interceptor(schedule_service, self.authenticator.clone()))
+            .add_service(TriggerServiceServer::with_interceptor(trigger_service, self.authenticator.clone()))
             .serve_with_incoming(incoming)
             .await;
         
@@ -219,15 +362,13 @@ impl AriaServer {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .init();
+    // Initialize tracing, exporting spans when an OTLP endpoint is configured
+    let _telemetry = telemetry::init(&TelemetryConfig::from_env("aria-runtime"))?;
     
     info!("Starting Aria Runtime gRPC Server");
     
-    // Parse command line arguments or use defaults
-    let config = ServerConfig::default();
+    // Use the defaults, overridden from the environment
+    let config = ServerConfig::from_env();
     
     // Create and start server
     let server = AriaServer::new(config).await?;
@@ -36,6 +36,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         filter_by_status: vec![],
         page_size: 10,
         page_token: "".to_string(),
+        parent_task_id: None,
     })).await;
     
     match task_response {
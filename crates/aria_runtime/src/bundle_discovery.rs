@@ -244,7 +244,7 @@ impl BundleToolDiscovery {
     }
 
     /// Load a bundle manifest from storage
-    async fn load_bundle_manifest(&self, bundle_hash: &str) -> AriaResult<AriaManifest> {
+    pub async fn load_bundle_manifest(&self, bundle_hash: &str) -> AriaResult<AriaManifest> {
         let bundle_data = self
             .pkg_store
             .get_bundle(bundle_hash)
@@ -301,33 +301,7 @@ impl BundleToolDiscovery {
         bundle_hash: &str,
     ) -> AriaResult<ToolManifest> {
         // Convert inputs HashMap to JSON schema
-        let parameters = if bundle_manifest.inputs.is_empty() {
-            serde_json::json!({
-                "type": "object",
-                "properties": {},
-                "required": []
-            })
-        } else {
-            let mut properties = serde_json::Map::new();
-            let mut required = Vec::new();
-
-            for (key, type_hint) in &bundle_manifest.inputs {
-                properties.insert(
-                    key.clone(),
-                    serde_json::json!({
-                        "type": type_hint,
-                        "description": format!("Parameter: {}", key)
-                    }),
-                );
-                required.push(key.clone());
-            }
-
-            serde_json::json!({
-                "type": "object",
-                "properties": properties,
-                "required": required
-            })
-        };
+        let parameters = crate::engines::tool_registry::schema::schema_from_inputs(&bundle_manifest.inputs);
 
         Ok(ToolManifest {
             name: bundle_manifest.name.clone(),
@@ -9,6 +9,8 @@ use crate::bundle_discovery::BundleToolDiscovery;
 use crate::engines::container::quilt::QuiltService;
 use crate::engines::tool_registry::bundle_integration::BundleToolRegistry;
 use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::pipelines::PipelineRegistry;
+use crate::teams::TeamRegistry;
 use pkg_store::PackageStore;
 use crate::deep_size::DeepUuid;
 use pkg_store::bundle::{LoadedBundle, AriaManifest, AgentManifest, TeamManifest, PipelineManifest};
@@ -118,6 +120,10 @@ pub struct BundleExecutor {
     active_executions: Arc<Mutex<HashMap<String, BundleExecutionStatus>>>,
     /// Execution history
     execution_history: Arc<Mutex<Vec<BundleExecutionResult>>>,
+    /// Registry that receives the bundle's teams, when attached
+    team_registry: Option<Arc<TeamRegistry>>,
+    /// Registry that receives the bundle's pipelines, when attached
+    pipeline_registry: Option<Arc<PipelineRegistry>>,
 }
 
 impl BundleExecutor {
@@ -135,9 +141,23 @@ impl BundleExecutor {
             discovery,
             active_executions: Arc::new(Mutex::new(HashMap::new())),
             execution_history: Arc::new(Mutex::new(Vec::new())),
+            team_registry: None,
+            pipeline_registry: None,
         }
     }
 
+    /// Register the teams of executed bundles with `registry`
+    pub fn with_team_registry(mut self, registry: Arc<TeamRegistry>) -> Self {
+        self.team_registry = Some(registry);
+        self
+    }
+
+    /// Register the pipelines of executed bundles with `registry`
+    pub fn with_pipeline_registry(mut self, registry: Arc<PipelineRegistry>) -> Self {
+        self.pipeline_registry = Some(registry);
+        self
+    }
+
     /// Execute a complete bundle
     pub async fn execute_bundle(
         &self,
@@ -450,15 +470,27 @@ impl BundleExecutor {
         }
 
         // Register teams
-        for team in &bundle.manifest.teams {
-            // TODO: Register team with team registry
-            summary.teams_registered.push(team.name.clone());
+        match &self.team_registry {
+            Some(registry) => {
+                summary.teams_registered = registry.register_manifest(&bundle.manifest).await;
+            }
+            None => {
+                for team in &bundle.manifest.teams {
+                    summary.teams_registered.push(team.name.clone());
+                }
+            }
         }
 
         // Register pipelines
-        for pipeline in &bundle.manifest.pipelines {
-            // TODO: Register pipeline with pipeline registry
-            summary.pipelines_registered.push(pipeline.name.clone());
+        match &self.pipeline_registry {
+            Some(registry) => {
+                summary.pipelines_registered = registry.register_manifest(&bundle.manifest).await;
+            }
+            None => {
+                for pipeline in &bundle.manifest.pipelines {
+                    summary.pipelines_registered.push(pipeline.name.clone());
+                }
+            }
         }
 
         info!("Registered {} tools, {} agents, {} teams, {} pipelines",
@@ -0,0 +1,11 @@
+//! Wall-clock time as stored in the databases: whole seconds since the UNIX epoch
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since the UNIX epoch; a clock set before the epoch reads as 0
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,541 @@
+/*!
+# Costs
+
+Prices every model call and charges it to the user, session, task, agent and
+tool that made it. Calls are priced from a per-provider, per-model table and
+recorded in the caller's database, so spend can be rolled up by any of those.
+
+Attribution travels with the work rather than through every signature: task
+runs and session turns open a `CostScope`, the runtime and the tool registry
+nest the agent and tool in it, and `LLMHandler` charges whatever scope the call
+runs in. Calls made outside any scope are not charged.
+
+A run also carries its tenant's budget. Once the run's spend, or the tenant's
+spend for the day, reaches a limit, further calls either fail or switch to the
+budget's cheaper model. The daily total is read when the run starts, so runs
+that overlap each see only their own spend on top of it.
+*/
+
+use crate::clock::unix_now;
+use crate::database::costs::{BudgetAction, BudgetRecord, CostOps, CostRecord};
+use crate::database::DatabaseManager;
+use crate::engines::llm::types::{LLMRequest, TokenUsage};
+use crate::engines::observability::ObservabilityManager;
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info, warn};
+
+/// Environment variable naming a JSON pricing file, laid over the built-in prices
+pub const PRICING_PATH_ENV: &str = "ARIA_LLM_PRICING";
+
+/// Built-in prices in USD per million input and output tokens
+const DEFAULT_PRICES: &[(&str, &str, f64, f64)] = &[
+    ("openai", "gpt-4o", 2.50, 10.00),
+    ("openai", "gpt-4o-mini", 0.15, 0.60),
+    ("openai", "gpt-4.1", 2.00, 8.00),
+    ("openai", "gpt-4.1-mini", 0.40, 1.60),
+    ("openai", "gpt-4.1-nano", 0.10, 0.40),
+    ("openai", "gpt-4-turbo", 10.00, 30.00),
+    ("openai", "gpt-4", 30.00, 60.00),
+    ("openai", "gpt-3.5-turbo", 0.50, 1.50),
+    ("openai", "o1", 15.00, 60.00),
+    ("openai", "o1-mini", 1.10, 4.40),
+    ("openai", "o3-mini", 1.10, 4.40),
+];
+
+const SECONDS_PER_DAY: u64 = 24 * 3600;
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.prompt as f64 * self.input_per_million + usage.completion as f64 * self.output_per_million) / 1_000_000.0
+    }
+}
+
+/// Model prices by provider. A dated model such as `gpt-4o-2024-08-06` is priced
+/// as the longest listed model it extends.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PricingTable {
+    providers: HashMap<String, HashMap<String, ModelPrice>>,
+}
+
+impl PricingTable {
+    /// The built-in prices
+    pub fn builtin() -> Self {
+        let mut table = Self::default();
+        for &(provider, model, input_per_million, output_per_million) in DEFAULT_PRICES {
+            table.set(provider, model, ModelPrice { input_per_million, output_per_million });
+        }
+        table
+    }
+
+    /// The built-in prices, overridden by the file named in `ARIA_LLM_PRICING` if set.
+    /// The file maps provider to model to price: `{"openai": {"gpt-4o": {"input_per_million": 2.5, "output_per_million": 10}}}`
+    pub fn load() -> AriaResult<Self> {
+        let mut table = Self::builtin();
+        let Some(path) = std::env::var(PRICING_PATH_ENV).ok().filter(|path| !path.is_empty()) else {
+            return Ok(table);
+        };
+
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| AriaError::new(
+                ErrorCode::ConfigError,
+                ErrorCategory::Configuration,
+                ErrorSeverity::High,
+                &format!("Failed to read pricing file {}: {}", path, e)
+            ))?;
+        let overrides: PricingTable = serde_json::from_str(&contents)
+            .map_err(|e| AriaError::new(
+                ErrorCode::ConfigError,
+                ErrorCategory::Configuration,
+                ErrorSeverity::High,
+                &format!("Invalid pricing file {}: {}", path, e)
+            ))?;
+
+        table.merge(overrides);
+        info!("Loaded LLM prices from {}", path);
+        Ok(table)
+    }
+
+    pub fn set(&mut self, provider: &str, model: &str, price: ModelPrice) {
+        self.providers
+            .entry(provider.to_lowercase())
+            .or_default()
+            .insert(model.to_lowercase(), price);
+    }
+
+    /// Add `other`'s prices, replacing ours where both price a model
+    pub fn merge(&mut self, other: PricingTable) {
+        for (provider, models) in other.providers {
+            for (model, price) in models {
+                self.set(&provider, &model, price);
+            }
+        }
+    }
+
+    pub fn price(&self, provider: &str, model: &str) -> Option<ModelPrice> {
+        let models = self.providers.get(&provider.to_lowercase())?;
+        let model = model.to_lowercase();
+        if let Some(price) = models.get(&model) {
+            return Some(*price);
+        }
+
+        models.iter()
+            .filter(|(name, _)| model.strip_prefix(name.as_str()).is_some_and(|rest| rest.starts_with('-')))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, price)| *price)
+    }
+}
+
+/// Who a model call is charged to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CostAttribution {
+    pub user_id: Option<String>,
+    pub session_id: Option<String>,
+    pub task_id: Option<String>,
+    pub agent: Option<String>,
+    pub tool: Option<String>,
+}
+
+impl CostAttribution {
+    /// Attribution of work nested in this: the run's user, session and task are
+    /// kept, while the innermost agent and tool are the ones charged
+    fn nest(&self, inner: CostAttribution) -> Self {
+        Self {
+            user_id: self.user_id.clone().or(inner.user_id),
+            session_id: self.session_id.clone().or(inner.session_id),
+            task_id: self.task_id.clone().or(inner.task_id),
+            agent: inner.agent.or_else(|| self.agent.clone()),
+            tool: inner.tool.or_else(|| self.tool.clone()),
+        }
+    }
+}
+
+tokio::task_local! {
+    static CURRENT_SCOPE: CostScope;
+}
+
+/// Prices model calls and records them in the caller's database
+pub struct CostTracker {
+    database: Arc<DatabaseManager>,
+    pricing: PricingTable,
+    observability: Option<Arc<ObservabilityManager>>,
+}
+
+impl CostTracker {
+    pub fn new(database: Arc<DatabaseManager>, pricing: PricingTable) -> Self {
+        Self {
+            database,
+            pricing,
+            observability: None,
+        }
+    }
+
+    /// Also add spend to the runtime's LLM metrics
+    pub fn with_observability(mut self, observability: Arc<ObservabilityManager>) -> Self {
+        self.observability = Some(observability);
+        self
+    }
+
+    pub fn pricing(&self) -> &PricingTable {
+        &self.pricing
+    }
+
+    /// What a user has spent since the start of the UTC day
+    pub async fn spent_today(&self, user_id: &str) -> AriaResult<f64> {
+        let user_pool = self.database.get_user_database(user_id).await?;
+        CostOps::spent_since(&user_pool, day_start(unix_now())).await
+    }
+
+    /// Start a run charged to `attribution`, under its user's budget
+    pub async fn open(self: &Arc<Self>, attribution: CostAttribution) -> AriaResult<CostScope> {
+        let mut budget = None;
+        let mut spent_today = 0.0;
+        if let Some(user_id) = &attribution.user_id {
+            let system_pool = self.database.get_system_database().await?;
+            budget = CostOps::get_budget(&system_pool, user_id).await?;
+            if budget.as_ref().is_some_and(|budget| budget.daily_limit_usd.is_some()) {
+                spent_today = self.spent_today(user_id).await?;
+            }
+        }
+
+        Ok(CostScope {
+            tracker: Arc::clone(self),
+            attribution,
+            run: Arc::new(RunSpend {
+                budget,
+                spent_today,
+                spent: Mutex::new(0.0),
+            }),
+        })
+    }
+
+    /// Run `future` charged to `attribution`: nested in the current run if there is one, else as a new run
+    pub async fn scoped<T, F>(self: &Arc<Self>, attribution: CostAttribution, future: F) -> AriaResult<T>
+    where
+        F: Future<Output = AriaResult<T>>,
+    {
+        let scope = match CostScope::current() {
+            Some(current) => current.nested(attribution),
+            None => self.open(attribution).await?,
+        };
+        scope.run(future).await
+    }
+
+    /// Count a response served from the cache in the runtime's LLM metrics, with what the call it replaced cost
+    pub fn record_cache_hit(&self, provider: &str, model: &str, usage: Option<&TokenUsage>) {
+        let saved_usd = usage
+            .and_then(|usage| Some(self.pricing.price(provider, model)?.cost(usage)))
+            .unwrap_or(0.0);
+        let tokens_saved = usage.map(|usage| usage.total as u64).unwrap_or(0);
+        self.record_cache_lookup(true, tokens_saved, saved_usd);
+    }
+
+    /// Count a request the response cache could not serve in the runtime's LLM metrics
+    pub fn record_cache_miss(&self) {
+        self.record_cache_lookup(false, 0, 0.0);
+    }
+
+    fn record_cache_lookup(&self, hit: bool, tokens_saved: u64, saved_usd: f64) {
+        let Some(observability) = self.observability.clone() else {
+            return;
+        };
+        tokio::spawn(async move {
+            if let Err(e) = observability.record_llm_cache_lookup(hit, tokens_saved, saved_usd).await {
+                debug!("Failed to add LLM cache lookup to metrics: {}", e);
+            }
+        });
+    }
+
+    /// Store a priced call in the background, so the model call does not wait on the database
+    fn record(self: &Arc<Self>, user_id: Option<String>, record: CostRecord) {
+        let tracker = Arc::clone(self);
+        tokio::spawn(async move {
+            if let Some(observability) = &tracker.observability {
+                if let Err(e) = observability.record_llm_cost(record.cost_usd).await {
+                    debug!("Failed to add LLM cost to metrics: {}", e);
+                }
+            }
+
+            let Some(user_id) = user_id else {
+                return;
+            };
+            let stored = match tracker.database.get_user_database(&user_id).await {
+                Ok(pool) => CostOps::record_call(&pool, &record).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = stored {
+                warn!("Failed to record cost of LLM call {} for {}: {}", record.call_id, user_id, e);
+            }
+        });
+    }
+}
+
+/// Spend shared by everything nested in one run
+struct RunSpend {
+    budget: Option<BudgetRecord>,
+    /// The user's spend today before the run started
+    spent_today: f64,
+    spent: Mutex<f64>,
+}
+
+/// The run a model call is charged to
+#[derive(Clone)]
+pub struct CostScope {
+    tracker: Arc<CostTracker>,
+    attribution: CostAttribution,
+    run: Arc<RunSpend>,
+}
+
+impl CostScope {
+    /// The scope the current task is running in, if any
+    pub fn current() -> Option<Self> {
+        CURRENT_SCOPE.try_with(Clone::clone).ok()
+    }
+
+    /// Run `future` in this scope
+    pub async fn run<F: Future>(self, future: F) -> F::Output {
+        CURRENT_SCOPE.scope(self, future).await
+    }
+
+    /// A scope for work nested in this one, sharing its spend and budget
+    pub fn nested(&self, attribution: CostAttribution) -> Self {
+        Self {
+            tracker: Arc::clone(&self.tracker),
+            attribution: self.attribution.nest(attribution),
+            run: Arc::clone(&self.run),
+        }
+    }
+
+    /// Run a tool's handler so the model calls it makes are charged to the tool
+    pub async fn with_tool<F: Future>(tool: &str, future: F) -> F::Output {
+        match Self::current() {
+            Some(scope) => {
+                let attribution = CostAttribution { tool: Some(tool.to_string()), ..Default::default() };
+                scope.nested(attribution).run(future).await
+            }
+            None => future.await,
+        }
+    }
+
+    pub fn attribution(&self) -> &CostAttribution {
+        &self.attribution
+    }
+
+    /// What the run has spent so far
+    pub fn spent(&self) -> f64 {
+        *self.run.spent.lock().unwrap()
+    }
+
+    /// Apply the budget to a call about to be made: fails once a limit is reached,
+    /// or switches the call to the downgrade model if the budget has one
+    pub fn admit(&self, request: &mut LLMRequest) -> AriaResult<()> {
+        let Some(budget) = &self.run.budget else {
+            return Ok(());
+        };
+        let spent = self.spent();
+        let Some((limit, limit_usd)) = exceeded_limit(budget, spent, self.run.spent_today + spent) else {
+            return Ok(());
+        };
+
+        match (budget.action, budget.downgrade_model.as_deref()) {
+            (BudgetAction::Downgrade, Some(model)) => {
+                if request.config.model.as_deref() != Some(model) {
+                    debug!("{} LLM budget of ${:.2} reached for {}; using {}", limit, limit_usd, budget.user_id, model);
+                    request.config.model = Some(model.to_string());
+                }
+                Ok(())
+            }
+            _ => Err(AriaError::new(
+                ErrorCode::QuotaExceeded,
+                ErrorCategory::LLM,
+                ErrorSeverity::High,
+                &format!("{} LLM budget of ${:.2} reached for {}", limit, limit_usd, budget.user_id)
+            )),
+        }
+    }
+
+    /// Price a finished call, add it to the run's spend and record it. Returns its cost.
+    pub fn charge(&self, provider: &str, model: &str, usage: &TokenUsage) -> f64 {
+        let price = self.tracker.pricing.price(provider, model);
+        if price.is_none() {
+            debug!("No price for {}/{}; recording the call at no cost", provider, model);
+        }
+        let cost_usd = price.map(|price| price.cost(usage)).unwrap_or(0.0);
+        *self.run.spent.lock().unwrap() += cost_usd;
+
+        let attribution = &self.attribution;
+        self.tracker.record(attribution.user_id.clone(), CostRecord {
+            call_id: uuid::Uuid::new_v4().to_string(),
+            session_id: attribution.session_id.clone(),
+            task_id: attribution.task_id.clone(),
+            agent_name: attribution.agent.clone(),
+            tool_name: attribution.tool.clone(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            prompt_tokens: usage.prompt,
+            completion_tokens: usage.completion,
+            cost_usd,
+            priced: price.is_some(),
+            created_at: unix_now(),
+        });
+        cost_usd
+    }
+}
+
+/// The first limit of `budget` that spend has reached, and its amount
+fn exceeded_limit(budget: &BudgetRecord, run_spent: f64, day_spent: f64) -> Option<(&'static str, f64)> {
+    [("Run", budget.run_limit_usd, run_spent), ("Daily", budget.daily_limit_usd, day_spent)]
+        .into_iter()
+        .find_map(|(name, limit, spent)| limit.filter(|&limit| spent >= limit).map(|limit| (name, limit)))
+}
+
+fn day_start(timestamp: u64) -> u64 {
+    timestamp - timestamp % SECONDS_PER_DAY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+    use crate::engines::llm::types::LLMConfig;
+
+    fn usage(prompt: u32, completion: u32) -> TokenUsage {
+        TokenUsage { prompt, completion, total: prompt + completion }
+    }
+
+    fn scope(budget: Option<BudgetRecord>, spent_today: f64) -> CostScope {
+        let tracker = CostTracker::new(
+            Arc::new(DatabaseManager::new(DatabaseConfig::default())),
+            PricingTable::builtin(),
+        );
+        CostScope {
+            tracker: Arc::new(tracker),
+            attribution: CostAttribution::default(),
+            run: Arc::new(RunSpend { budget, spent_today, spent: Mutex::new(0.0) }),
+        }
+    }
+
+    fn request(model: &str) -> LLMRequest {
+        LLMRequest {
+            messages: Vec::new(),
+            config: LLMConfig { model: Some(model.to_string()), ..Default::default() },
+            provider: None,
+            tools: None,
+            tool_choice: None,
+            stream: None,
+            cache: None,
+        }
+    }
+
+    #[test]
+    fn dated_models_are_priced_as_the_longest_listed_model() {
+        let pricing = PricingTable::builtin();
+        assert_eq!(pricing.price("openai", "gpt-4o-2024-08-06"), pricing.price("openai", "gpt-4o"));
+        assert_eq!(pricing.price("OpenAI", "gpt-4o-mini-2024-07-18"), pricing.price("openai", "gpt-4o-mini"));
+        assert_eq!(pricing.price("openai", "gpt-4-turbo-preview"), pricing.price("openai", "gpt-4-turbo"));
+        // `gpt-4o` is its own model, not a version of `gpt-4`
+        assert_ne!(pricing.price("openai", "gpt-4o"), pricing.price("openai", "gpt-4"));
+        assert!(pricing.price("openai", "text-davinci-003").is_none());
+        assert!(pricing.price("anthropic", "gpt-4o").is_none());
+    }
+
+    #[test]
+    fn cost_is_priced_per_million_tokens() {
+        let price = ModelPrice { input_per_million: 2.0, output_per_million: 8.0 };
+        let cost = price.cost(&usage(500_000, 250_000));
+        assert!((cost - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn pricing_file_overrides_and_extends_builtin_prices() {
+        let overrides: PricingTable = serde_json::from_str(r#"{
+            "openai": {"gpt-4o": {"input_per_million": 1.0, "output_per_million": 2.0}},
+            "local": {"llama-3": {"input_per_million": 0.0, "output_per_million": 0.0}}
+        }"#).unwrap();
+        let mut pricing = PricingTable::builtin();
+        pricing.merge(overrides);
+
+        assert_eq!(pricing.price("openai", "gpt-4o").unwrap().input_per_million, 1.0);
+        assert!(pricing.price("local", "llama-3-8b").is_some());
+        assert!(pricing.price("openai", "gpt-4o-mini").is_some());
+    }
+
+    #[test]
+    fn nested_work_keeps_the_run_and_charges_the_innermost_agent_and_tool() {
+        let run = CostAttribution {
+            user_id: Some("alice".to_string()),
+            task_id: Some("task-1".to_string()),
+            agent: Some("planner".to_string()),
+            ..Default::default()
+        };
+        let nested = run.nest(CostAttribution {
+            session_id: Some("session-1".to_string()),
+            agent: Some("writer".to_string()),
+            ..Default::default()
+        });
+        let in_tool = nested.nest(CostAttribution { tool: Some("ponderTool".to_string()), ..Default::default() });
+
+        assert_eq!(in_tool.user_id.as_deref(), Some("alice"));
+        assert_eq!(in_tool.task_id.as_deref(), Some("task-1"));
+        assert_eq!(in_tool.session_id.as_deref(), Some("session-1"));
+        assert_eq!(in_tool.agent.as_deref(), Some("writer"));
+        assert_eq!(in_tool.tool.as_deref(), Some("ponderTool"));
+    }
+
+    #[test]
+    fn limits_apply_to_the_run_and_the_day() {
+        let budget = BudgetRecord { run_limit_usd: Some(1.0), daily_limit_usd: Some(5.0), ..Default::default() };
+        assert_eq!(exceeded_limit(&budget, 0.5, 4.0), None);
+        assert_eq!(exceeded_limit(&budget, 1.0, 4.0), Some(("Run", 1.0)));
+        assert_eq!(exceeded_limit(&budget, 0.5, 5.5), Some(("Daily", 5.0)));
+        assert_eq!(exceeded_limit(&BudgetRecord::default(), 100.0, 100.0), None);
+    }
+
+    #[tokio::test]
+    async fn exhausted_budget_aborts_or_downgrades_calls() {
+        let abort = BudgetRecord { daily_limit_usd: Some(1.0), ..Default::default() };
+        let scope_over = scope(Some(abort.clone()), 1.5);
+        assert!(scope_over.admit(&mut request("gpt-4o")).is_err());
+        assert!(scope(Some(abort), 0.5).admit(&mut request("gpt-4o")).is_ok());
+
+        let downgrade = BudgetRecord {
+            run_limit_usd: Some(0.01),
+            action: BudgetAction::Downgrade,
+            downgrade_model: Some("gpt-4o-mini".to_string()),
+            ..Default::default()
+        };
+        let scope = scope(Some(downgrade), 0.0);
+        let mut call = request("gpt-4o");
+        scope.admit(&mut call).unwrap();
+        assert_eq!(call.config.model.as_deref(), Some("gpt-4o"));
+
+        // $2.50 of input on gpt-4o puts the run over its limit
+        scope.charge("openai", "gpt-4o", &usage(1_000_000, 0));
+        assert!((scope.spent() - 2.5).abs() < 1e-9);
+        scope.admit(&mut call).unwrap();
+        assert_eq!(call.config.model.as_deref(), Some("gpt-4o-mini"));
+    }
+
+    #[tokio::test]
+    async fn tools_are_charged_within_the_current_run() {
+        let run = scope(None, 0.0);
+        let tool = run.clone().run(CostScope::with_tool("ponderTool", async {
+            CostScope::current().and_then(|scope| scope.attribution().tool.clone())
+        })).await;
+        assert_eq!(tool.as_deref(), Some("ponderTool"));
+
+        // Outside a run there is nothing to charge
+        assert!(CostScope::with_tool("ponderTool", async { CostScope::current() }).await.is_none());
+    }
+}
@@ -15,6 +15,28 @@ pub enum AsyncTaskStatus {
     Failed,
     Cancelled,
     Timeout,
+    Skipped,
+}
+
+impl AsyncTaskStatus {
+    /// Parse a status column value; unknown values read as failed
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "pending" => AsyncTaskStatus::Pending,
+            "running" => AsyncTaskStatus::Running,
+            "completed" => AsyncTaskStatus::Completed,
+            "failed" => AsyncTaskStatus::Failed,
+            "cancelled" => AsyncTaskStatus::Cancelled,
+            "timeout" => AsyncTaskStatus::Timeout,
+            "skipped" => AsyncTaskStatus::Skipped,
+            _ => AsyncTaskStatus::Failed,
+        }
+    }
+
+    /// Whether the task has stopped and will not change again
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, AsyncTaskStatus::Pending | AsyncTaskStatus::Running)
+    }
 }
 
 impl std::fmt::Display for AsyncTaskStatus {
@@ -26,6 +48,7 @@ impl std::fmt::Display for AsyncTaskStatus {
             AsyncTaskStatus::Failed => write!(f, "failed"),
             AsyncTaskStatus::Cancelled => write!(f, "cancelled"),
             AsyncTaskStatus::Timeout => write!(f, "timeout"),
+            AsyncTaskStatus::Skipped => write!(f, "skipped"),
         }
     }
 }
@@ -46,8 +69,74 @@ pub struct AsyncTaskRecord {
     pub exit_code: Option<i32>,
     pub stdout: Option<String>,
     pub stderr: Option<String>,
+    pub parent_task_id: Option<String>,
+    /// 0 means no timeout
+    pub timeout_seconds: u64,
+    /// Higher priorities are dispatched first
+    pub priority: i32,
+    pub started_at: Option<u64>,
+    pub error_message: Option<String>,
+    pub progress_percent: f64,
+    pub current_operation: Option<String>,
+    /// ID of the work in the system that runs the task, e.g. a quilt task
+    pub external_id: Option<String>,
 }
 
+/// A task to be queued through `AsyncTaskOps::insert_task`
+#[derive(Debug, Clone, Default)]
+pub struct NewAsyncTask {
+    pub user_id: String,
+    pub session_id: String,
+    pub task_type: String,
+    pub command: Vec<String>,
+    pub environment: HashMap<String, String>,
+    pub container_id: Option<String>,
+    pub parent_task_id: Option<String>,
+    pub priority: i32,
+    pub timeout_seconds: Option<u64>,
+}
+
+/// How a task ended
+#[derive(Debug, Clone)]
+pub struct TaskOutcome {
+    pub status: AsyncTaskStatus,
+    pub exit_code: Option<i32>,
+    pub stdout: Option<String>,
+    pub stderr: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl TaskOutcome {
+    /// A task that ended with `status` for the given reason and produced no output
+    pub fn ended(status: AsyncTaskStatus, error_message: impl Into<String>) -> Self {
+        Self {
+            status,
+            exit_code: None,
+            stdout: None,
+            stderr: None,
+            error_message: Some(error_message.into()),
+        }
+    }
+}
+
+/// A `task_dependencies` row: `task_id` waits on `depends_on_task_id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskDependencyRecord {
+    pub task_id: String,
+    pub depends_on_task_id: String,
+    /// "success" waits for the dependency to complete; "completion" for it to end in any way
+    pub dependency_type: String,
+}
+
+/// Columns read into an `AsyncTaskRecord`
+const TASK_COLUMNS: &str = r#"
+    task_id, user_id, session_id, container_id, task_type,
+    command, environment, status, created_at, completed_at,
+    exit_code, stdout, stderr, parent_task_id, timeout_seconds,
+    priority, started_at, error_message, progress_percent, current_operation,
+    external_id
+"#;
+
 /// Database operations for async tasks
 pub struct AsyncTaskOps;
 
@@ -113,6 +202,220 @@ impl AsyncTaskOps {
         Ok(task_id)
     }
 
+    /// Queue a task with its parent, priority and timeout
+    pub async fn insert_task(pool: &sqlx::SqlitePool, task: &NewAsyncTask) -> AriaResult<String> {
+        let task_id = Uuid::new_v4().to_string();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let command_json = serde_json::to_string(&task.command)
+            .map_err(|e| AriaError::new(
+                ErrorCode::SerializationError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to serialize command: {}", e)
+            ))?;
+
+        let environment_json = serde_json::to_string(&task.environment)
+            .map_err(|e| AriaError::new(
+                ErrorCode::SerializationError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to serialize environment: {}", e)
+            ))?;
+
+        sqlx::query(r#"
+            INSERT INTO async_tasks (
+                task_id, user_id, session_id, container_id, parent_task_id, task_type,
+                command, environment, timeout_seconds, priority, status, created_at,
+                progress_percent
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 'pending', ?, 0.0)
+        "#)
+        .bind(&task_id)
+        .bind(&task.user_id)
+        .bind(&task.session_id)
+        .bind(&task.container_id)
+        .bind(&task.parent_task_id)
+        .bind(&task.task_type)
+        .bind(command_json)
+        .bind(environment_json)
+        .bind(task.timeout_seconds.unwrap_or(0) as i64)
+        .bind(task.priority as i64)
+        .bind(now as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to create task: {}", e)
+        ))?;
+
+        Ok(task_id)
+    }
+
+    /// Move a pending task to running; false if it was no longer pending
+    pub async fn claim_task(
+        pool: &sqlx::SqlitePool,
+        task_id: &str,
+        current_operation: &str,
+    ) -> AriaResult<bool> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let result = sqlx::query(r#"
+            UPDATE async_tasks SET
+                status = 'running', started_at = COALESCE(started_at, ?), current_operation = ?
+            WHERE task_id = ? AND status = 'pending'
+        "#)
+        .bind(now as i64)
+        .bind(current_operation)
+        .bind(task_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to claim task: {}", e)
+        ))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record how an unfinished task ended; false if it had already ended,
+    /// so a cancellation is never overwritten by the work it interrupted
+    pub async fn finish_task(
+        pool: &sqlx::SqlitePool,
+        task_id: &str,
+        outcome: &TaskOutcome,
+    ) -> AriaResult<bool> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let cancelled_at = (outcome.status == AsyncTaskStatus::Cancelled).then_some(now);
+        let progress = if outcome.status == AsyncTaskStatus::Completed { 100.0 } else { 0.0 };
+
+        let result = sqlx::query(r#"
+            UPDATE async_tasks SET
+                status = ?, completed_at = ?, cancelled_at = ?, exit_code = ?, stdout = ?, stderr = ?,
+                error_message = ?, progress_percent = MAX(progress_percent, ?)
+            WHERE task_id = ? AND status IN ('pending', 'running')
+        "#)
+        .bind(outcome.status.to_string())
+        .bind(now)
+        .bind(cancelled_at)
+        .bind(outcome.exit_code)
+        .bind(&outcome.stdout)
+        .bind(&outcome.stderr)
+        .bind(&outcome.error_message)
+        .bind(progress)
+        .bind(task_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to finish task: {}", e)
+        ))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remember the ID the executing system gave a task
+    pub async fn set_external_id(pool: &sqlx::SqlitePool, task_id: &str, external_id: &str) -> AriaResult<()> {
+        sqlx::query("UPDATE async_tasks SET external_id = ? WHERE task_id = ?")
+            .bind(external_id)
+            .bind(task_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to set external task ID: {}", e)
+            ))?;
+
+        Ok(())
+    }
+
+    /// Record progress of a running task
+    pub async fn update_progress(
+        pool: &sqlx::SqlitePool,
+        task_id: &str,
+        progress_percent: f64,
+        current_operation: &str,
+    ) -> AriaResult<()> {
+        sqlx::query(r#"
+            UPDATE async_tasks SET progress_percent = ?, current_operation = ?
+            WHERE task_id = ? AND status = 'running'
+        "#)
+        .bind(progress_percent)
+        .bind(current_operation)
+        .bind(task_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to update task progress: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// The tasks `task_id` waits on
+    pub async fn get_dependencies(pool: &sqlx::SqlitePool, task_id: &str) -> AriaResult<Vec<TaskDependencyRecord>> {
+        let rows: Vec<(String, String, String)> = sqlx::query_as(r#"
+            SELECT task_id, depends_on_task_id, dependency_type
+            FROM task_dependencies WHERE task_id = ?
+            ORDER BY created_at ASC
+        "#)
+        .bind(task_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to get task dependencies: {}", e)
+        ))?;
+
+        Ok(rows.into_iter().map(|row| TaskDependencyRecord {
+            task_id: row.0,
+            depends_on_task_id: row.1,
+            dependency_type: row.2,
+        }).collect())
+    }
+
+    /// Pending and running tasks the task runner dispatches, i.e. all but pipeline nodes,
+    /// which their run drives, highest priority first
+    pub async fn get_queued_tasks(pool: &sqlx::SqlitePool) -> AriaResult<Vec<AsyncTaskRecord>> {
+        let rows: Vec<TaskRow> = sqlx::query_as(&format!(r#"
+                SELECT {}
+                FROM async_tasks
+                WHERE status IN ('pending', 'running') AND task_type != 'pipeline_node'
+                ORDER BY priority DESC, created_at ASC
+            "#, TASK_COLUMNS))
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to get queued tasks: {}", e)
+            ))?;
+
+        Ok(rows.into_iter().map(record_from_row).collect())
+    }
+
     /// Update task status
     pub async fn update_task_status(
         pool: &sqlx::SqlitePool,
@@ -122,7 +425,7 @@ impl AsyncTaskOps {
         stdout: Option<String>,
         stderr: Option<String>,
     ) -> AriaResult<()> {
-        let now = if matches!(status, AsyncTaskStatus::Completed | AsyncTaskStatus::Failed | AsyncTaskStatus::Cancelled) {
+        let now = if status.is_terminal() {
             Some(std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
@@ -156,13 +459,7 @@ impl AsyncTaskOps {
 
     /// Get task by ID
     pub async fn get_task(pool: &sqlx::SqlitePool, task_id: &str) -> AriaResult<AsyncTaskRecord> {
-        let row: (String, String, String, Option<String>, String, String, String, String, i64, Option<i64>, Option<i32>, Option<String>, Option<String>) = 
-            sqlx::query_as(r#"
-                SELECT task_id, user_id, session_id, container_id, task_type, 
-                       command, environment, status, created_at, completed_at, 
-                       exit_code, stdout, stderr
-                FROM async_tasks WHERE task_id = ?
-            "#)
+        let row: TaskRow = sqlx::query_as(&format!("SELECT {} FROM async_tasks WHERE task_id = ?", TASK_COLUMNS))
             .bind(task_id)
             .fetch_one(pool)
             .await
@@ -173,47 +470,7 @@ impl AsyncTaskOps {
                 &format!("Failed to get task: {}", e)
             ))?;
 
-        let command: Vec<String> = serde_json::from_str(&row.5)
-            .map_err(|e| AriaError::new(
-                ErrorCode::SerializationError,
-                ErrorCategory::System,
-                ErrorSeverity::Medium,
-                &format!("Failed to deserialize command: {}", e)
-            ))?;
-
-        let environment: HashMap<String, String> = serde_json::from_str(&row.6)
-            .map_err(|e| AriaError::new(
-                ErrorCode::SerializationError,
-                ErrorCategory::System,
-                ErrorSeverity::Medium,
-                &format!("Failed to deserialize environment: {}", e)
-            ))?;
-
-        let status = match row.7.as_str() {
-            "pending" => AsyncTaskStatus::Pending,
-            "running" => AsyncTaskStatus::Running,
-            "completed" => AsyncTaskStatus::Completed,
-            "failed" => AsyncTaskStatus::Failed,
-            "cancelled" => AsyncTaskStatus::Cancelled,
-            "timeout" => AsyncTaskStatus::Timeout,
-            _ => AsyncTaskStatus::Failed,
-        };
-
-        Ok(AsyncTaskRecord {
-            task_id: row.0,
-            user_id: row.1,
-            session_id: row.2,
-            container_id: row.3,
-            task_type: row.4,
-            command,
-            environment,
-            status,
-            created_at: row.8 as u64,
-            completed_at: row.9.map(|t| t as u64),
-            exit_code: row.10,
-            stdout: row.11,
-            stderr: row.12,
-        })
+        Ok(record_from_row(row))
     }
 
     /// Get tasks for a user
@@ -229,17 +486,14 @@ impl AsyncTaskOps {
         };
 
         let query = format!(r#"
-            SELECT task_id, user_id, session_id, container_id, task_type, 
-                   command, environment, status, created_at, completed_at,
-                   exit_code, stdout, stderr
+            SELECT {}
             FROM async_tasks 
             WHERE user_id = ? 
             ORDER BY created_at DESC
             {}
-        "#, limit_clause);
+        "#, TASK_COLUMNS, limit_clause);
 
-        let rows: Vec<(String, String, String, Option<String>, String, String, String, String, i64, Option<i64>, Option<i32>, Option<String>, Option<String>)> = 
-            sqlx::query_as(&query)
+        let rows: Vec<TaskRow> = sqlx::query_as(&query)
             .bind(user_id)
             .fetch_all(pool)
             .await
@@ -250,38 +504,222 @@ impl AsyncTaskOps {
                 &format!("Failed to get user tasks: {}", e)
             ))?;
 
-        let mut tasks = Vec::new();
-        for row in rows {
-            let command: Vec<String> = serde_json::from_str(&row.5).unwrap_or_default();
-            let environment: HashMap<String, String> = serde_json::from_str(&row.6).unwrap_or_default();
-            
-            let status = match row.7.as_str() {
-                "pending" => AsyncTaskStatus::Pending,
-                "running" => AsyncTaskStatus::Running,
-                "completed" => AsyncTaskStatus::Completed,
-                "failed" => AsyncTaskStatus::Failed,
-                "cancelled" => AsyncTaskStatus::Cancelled,
-                "timeout" => AsyncTaskStatus::Timeout,
-                _ => AsyncTaskStatus::Failed,
-            };
-
-            tasks.push(AsyncTaskRecord {
-                task_id: row.0,
-                user_id: row.1,
-                session_id: row.2,
-                container_id: row.3,
-                task_type: row.4,
-                command,
-                environment,
-                status,
-                created_at: row.8 as u64,
-                completed_at: row.9.map(|t| t as u64),
-                exit_code: row.10,
-                stdout: row.11,
-                stderr: row.12,
-            });
-        }
+        Ok(rows.into_iter().map(record_from_row).collect())
+    }
+
+    /// Create a task that belongs to `parent_task_id`, e.g. one node of a workflow
+    pub async fn create_subtask(
+        pool: &sqlx::SqlitePool,
+        parent_task_id: &str,
+        user_id: &str,
+        session_id: &str,
+        task_type: &str,
+        command: Vec<String>,
+        timeout_seconds: Option<u64>,
+    ) -> AriaResult<String> {
+        let task_id = Uuid::new_v4().to_string();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
 
-        Ok(tasks)
+        let command_json = serde_json::to_string(&command)
+            .map_err(|e| AriaError::new(
+                ErrorCode::SerializationError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to serialize command: {}", e)
+            ))?;
+
+        sqlx::query(r#"
+            INSERT INTO async_tasks (
+                task_id, user_id, session_id, parent_task_id, task_type,
+                command, environment, timeout_seconds, status, created_at,
+                progress_percent
+            ) VALUES (?, ?, ?, ?, ?, ?, '{}', ?, 'pending', ?, 0.0)
+        "#)
+        .bind(&task_id)
+        .bind(user_id)
+        .bind(session_id)
+        .bind(parent_task_id)
+        .bind(task_type)
+        .bind(command_json)
+        .bind(timeout_seconds.unwrap_or(0) as i64)
+        .bind(now as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to create subtask: {}", e)
+        ))?;
+
+        Ok(task_id)
     }
-} 
\ No newline at end of file
+
+    /// Record that `task_id` waits on `depends_on_task_id`
+    pub async fn add_dependency(
+        pool: &sqlx::SqlitePool,
+        task_id: &str,
+        depends_on_task_id: &str,
+        dependency_type: &str,
+    ) -> AriaResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        sqlx::query(r#"
+            INSERT INTO task_dependencies (dependency_id, task_id, depends_on_task_id, dependency_type, created_at)
+            VALUES (?, ?, ?, ?, ?)
+        "#)
+        .bind(Uuid::new_v4().to_string())
+        .bind(task_id)
+        .bind(depends_on_task_id)
+        .bind(dependency_type)
+        .bind(now as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to record task dependency: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Mark a task running and note what it is doing
+    pub async fn mark_running(
+        pool: &sqlx::SqlitePool,
+        task_id: &str,
+        current_operation: &str,
+    ) -> AriaResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        sqlx::query(r#"
+            UPDATE async_tasks SET
+                status = 'running', started_at = COALESCE(started_at, ?), current_operation = ?
+            WHERE task_id = ?
+        "#)
+        .bind(now as i64)
+        .bind(current_operation)
+        .bind(task_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to mark task running: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Get the tasks created under `parent_task_id`, oldest first
+    pub async fn get_subtasks(
+        pool: &sqlx::SqlitePool,
+        parent_task_id: &str,
+    ) -> AriaResult<Vec<AsyncTaskRecord>> {
+        let rows: Vec<TaskRow> = sqlx::query_as(&format!(r#"
+                SELECT {}
+                FROM async_tasks
+                WHERE parent_task_id = ?
+                ORDER BY created_at ASC, rowid ASC
+            "#, TASK_COLUMNS))
+            .bind(parent_task_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to get subtasks: {}", e)
+            ))?;
+
+        Ok(rows.into_iter().map(record_from_row).collect())
+    }
+
+    /// Get tasks of `task_type` that are still pending or running
+    pub async fn get_unfinished_tasks(
+        pool: &sqlx::SqlitePool,
+        task_type: &str,
+    ) -> AriaResult<Vec<AsyncTaskRecord>> {
+        let rows: Vec<TaskRow> = sqlx::query_as(&format!(r#"
+                SELECT {}
+                FROM async_tasks
+                WHERE task_type = ? AND status IN ('pending', 'running')
+                ORDER BY created_at ASC
+            "#, TASK_COLUMNS))
+            .bind(task_type)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to get unfinished tasks: {}", e)
+            ))?;
+
+        Ok(rows.into_iter().map(record_from_row).collect())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct TaskRow {
+    task_id: String,
+    user_id: String,
+    session_id: String,
+    container_id: Option<String>,
+    task_type: String,
+    command: String,
+    environment: Option<String>,
+    status: String,
+    created_at: i64,
+    completed_at: Option<i64>,
+    exit_code: Option<i32>,
+    stdout: Option<String>,
+    stderr: Option<String>,
+    parent_task_id: Option<String>,
+    timeout_seconds: Option<i64>,
+    priority: i64,
+    started_at: Option<i64>,
+    error_message: Option<String>,
+    progress_percent: Option<f64>,
+    current_operation: Option<String>,
+    external_id: Option<String>,
+}
+
+fn record_from_row(row: TaskRow) -> AsyncTaskRecord {
+    AsyncTaskRecord {
+        task_id: row.task_id,
+        user_id: row.user_id,
+        session_id: row.session_id,
+        container_id: row.container_id,
+        task_type: row.task_type,
+        command: serde_json::from_str(&row.command).unwrap_or_default(),
+        environment: row.environment.as_deref()
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default(),
+        status: AsyncTaskStatus::from_db(&row.status),
+        created_at: row.created_at as u64,
+        completed_at: row.completed_at.map(|t| t as u64),
+        exit_code: row.exit_code,
+        stdout: row.stdout,
+        stderr: row.stderr,
+        parent_task_id: row.parent_task_id,
+        timeout_seconds: row.timeout_seconds.unwrap_or(0).max(0) as u64,
+        priority: row.priority as i32,
+        started_at: row.started_at.map(|t| t as u64),
+        error_message: row.error_message,
+        progress_percent: row.progress_percent.unwrap_or(0.0),
+        current_operation: row.current_operation,
+        external_id: row.external_id,
+    }
+}
@@ -0,0 +1,248 @@
+// Conversation Database Operations
+// Agent configurations, conversations and their messages in a user's database
+
+use crate::clock::unix_now;
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Agent configuration record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentConfigRecord {
+    pub config_id: String,
+    pub user_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub system_prompt: Option<String>,
+    /// Tool names or registry scopes ("primitive", "abstract", "all")
+    pub tool_scopes: Vec<String>,
+    pub llm_provider: String,
+    pub llm_model: String,
+    pub max_tokens: u32,
+    pub temperature: f32,
+    pub is_default: bool,
+}
+
+/// Message record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageRecord {
+    pub message_id: String,
+    pub conversation_id: String,
+    /// user, assistant, system or tool
+    pub role: String,
+    pub content: String,
+    /// JSON array of tool calls made by an assistant message
+    pub tool_calls: Option<String>,
+    /// JSON array of tool results carried by a tool message
+    pub tool_results: Option<String>,
+    pub tokens_used: Option<u32>,
+    pub created_at: u64,
+}
+
+impl MessageRecord {
+    /// A new message in `conversation_id`, timestamped now
+    pub fn new(conversation_id: &str, role: &str, content: &str) -> Self {
+        Self {
+            message_id: Uuid::new_v4().to_string(),
+            conversation_id: conversation_id.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            tool_results: None,
+            tokens_used: None,
+            created_at: unix_now(),
+        }
+    }
+}
+
+type AgentConfigRow = (String, String, String, Option<String>, Option<String>, String, String, String, Option<i64>, Option<f64>, Option<bool>);
+type MessageRow = (String, String, String, String, Option<String>, Option<String>, Option<i64>, i64);
+
+/// Database operations for conversations
+pub struct ConversationOps;
+
+impl ConversationOps {
+    /// Get an agent configuration by ID
+    pub async fn get_agent_config(pool: &sqlx::SqlitePool, config_id: &str) -> AriaResult<Option<AgentConfigRecord>> {
+        let row: Option<AgentConfigRow> = sqlx::query_as(r#"
+            SELECT config_id, user_id, name, description, system_prompt, tool_scopes,
+                   llm_provider, llm_model, max_tokens, temperature, is_default
+            FROM agent_configs WHERE config_id = ?
+        "#)
+        .bind(config_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to get agent config: {}", e)
+        ))?;
+
+        Ok(row.map(convert_agent_config))
+    }
+
+    /// Get the configuration a user marked as their default, if any
+    pub async fn get_default_agent_config(pool: &sqlx::SqlitePool, user_id: &str) -> AriaResult<Option<AgentConfigRecord>> {
+        let row: Option<AgentConfigRow> = sqlx::query_as(r#"
+            SELECT config_id, user_id, name, description, system_prompt, tool_scopes,
+                   llm_provider, llm_model, max_tokens, temperature, is_default
+            FROM agent_configs WHERE user_id = ? AND is_default = TRUE
+            ORDER BY updated_at DESC LIMIT 1
+        "#)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to get default agent config: {}", e)
+        ))?;
+
+        Ok(row.map(convert_agent_config))
+    }
+
+    /// The session's active conversation, started on first use
+    pub async fn get_or_create_conversation(
+        pool: &sqlx::SqlitePool,
+        session_id: &str,
+        agent_config_id: &str,
+    ) -> AriaResult<String> {
+        let existing: Option<(String,)> = sqlx::query_as(r#"
+            SELECT conversation_id FROM conversations
+            WHERE session_id = ? AND status = 'active'
+            ORDER BY created_at DESC LIMIT 1
+        "#)
+        .bind(session_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to get conversation: {}", e)
+        ))?;
+
+        if let Some((conversation_id,)) = existing {
+            return Ok(conversation_id);
+        }
+
+        let conversation_id = Uuid::new_v4().to_string();
+        sqlx::query(r#"
+            INSERT INTO conversations (conversation_id, session_id, agent_config_id, created_at, status)
+            VALUES (?, ?, ?, ?, 'active')
+        "#)
+        .bind(&conversation_id)
+        .bind(session_id)
+        .bind(agent_config_id)
+        .bind(unix_now() as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to create conversation: {}", e)
+        ))?;
+
+        Ok(conversation_id)
+    }
+
+    /// Messages of a conversation in the order they were written
+    pub async fn list_messages(pool: &sqlx::SqlitePool, conversation_id: &str) -> AriaResult<Vec<MessageRecord>> {
+        let rows: Vec<MessageRow> = sqlx::query_as(r#"
+            SELECT message_id, conversation_id, role, content, tool_calls, tool_results, tokens_used, created_at
+            FROM messages WHERE conversation_id = ?
+            ORDER BY created_at, rowid
+        "#)
+        .bind(conversation_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to list messages: {}", e)
+        ))?;
+
+        Ok(rows.into_iter().map(|row| MessageRecord {
+            message_id: row.0,
+            conversation_id: row.1,
+            role: row.2,
+            content: row.3,
+            tool_calls: row.4,
+            tool_results: row.5,
+            tokens_used: row.6.map(|t| t as u32),
+            created_at: row.7 as u64,
+        }).collect())
+    }
+
+    /// Append a message and count it against its conversation
+    pub async fn append_message(pool: &sqlx::SqlitePool, message: &MessageRecord) -> AriaResult<()> {
+        let mut tx = pool.begin().await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to begin transaction: {}", e)
+            ))?;
+
+        sqlx::query(r#"
+            INSERT INTO messages (message_id, conversation_id, role, content, tool_calls, tool_results, tokens_used, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#)
+        .bind(&message.message_id)
+        .bind(&message.conversation_id)
+        .bind(&message.role)
+        .bind(&message.content)
+        .bind(&message.tool_calls)
+        .bind(&message.tool_results)
+        .bind(message.tokens_used.map(|t| t as i64))
+        .bind(message.created_at as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to store message: {}", e)
+        ))?;
+
+        sqlx::query("UPDATE conversations SET total_messages = total_messages + 1 WHERE conversation_id = ?")
+            .bind(&message.conversation_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to update conversation: {}", e)
+            ))?;
+
+        tx.commit().await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to commit message: {}", e)
+            ))
+    }
+}
+
+fn convert_agent_config(row: AgentConfigRow) -> AgentConfigRecord {
+    AgentConfigRecord {
+        config_id: row.0,
+        user_id: row.1,
+        name: row.2,
+        description: row.3,
+        system_prompt: row.4,
+        tool_scopes: serde_json::from_str(&row.5).unwrap_or_default(),
+        llm_provider: row.6,
+        llm_model: row.7,
+        max_tokens: row.8.unwrap_or(4096) as u32,
+        temperature: row.9.unwrap_or(0.1) as f32,
+        is_default: row.10.unwrap_or(false),
+    }
+}
+
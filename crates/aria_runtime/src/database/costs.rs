@@ -0,0 +1,264 @@
+// LLM Cost Database Operations
+// Per-call spend in each user's database and per-tenant budgets in the system database
+
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+
+/// One priced model call and what it is attributed to
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CostRecord {
+    pub call_id: String,
+    pub session_id: Option<String>,
+    pub task_id: Option<String>,
+    pub agent_name: Option<String>,
+    pub tool_name: Option<String>,
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub cost_usd: f64,
+    /// False when the pricing table had no entry for the model, so the cost is 0
+    pub priced: bool,
+    pub created_at: u64,
+}
+
+/// What happens to a run once a budget limit is reached
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BudgetAction {
+    /// Further model calls fail
+    #[default]
+    Abort,
+    /// Further model calls use the budget's downgrade model
+    Downgrade,
+}
+
+impl BudgetAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetAction::Abort => "abort",
+            BudgetAction::Downgrade => "downgrade",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "abort" => Some(BudgetAction::Abort),
+            "downgrade" => Some(BudgetAction::Downgrade),
+            _ => None,
+        }
+    }
+}
+
+/// Per-tenant limits on LLM spend; `None` means unlimited
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetRecord {
+    pub user_id: String,
+    /// Spend allowed in one task run or session turn
+    pub run_limit_usd: Option<f64>,
+    /// Spend allowed per UTC day
+    pub daily_limit_usd: Option<f64>,
+    pub action: BudgetAction,
+    pub downgrade_model: Option<String>,
+    pub updated_at: u64,
+}
+
+/// What spend is grouped by in a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostDimension {
+    Session,
+    Task,
+    Agent,
+    Tool,
+    Model,
+}
+
+impl CostDimension {
+    fn column(&self) -> &'static str {
+        match self {
+            CostDimension::Session => "session_id",
+            CostDimension::Task => "task_id",
+            CostDimension::Agent => "agent_name",
+            CostDimension::Tool => "tool_name",
+            CostDimension::Model => "provider || '/' || model",
+        }
+    }
+}
+
+/// Spend of one group in one time bucket
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostRollup {
+    /// Value of the grouped dimension; `None` for calls without one, or when not grouped
+    pub key: Option<String>,
+    /// Start of the bucket; the report's start when not bucketed
+    pub bucket_start: u64,
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+type BudgetRow = (String, Option<f64>, Option<f64>, String, Option<String>, i64);
+type RollupRow = (Option<String>, i64, i64, i64, i64, f64);
+
+/// Database operations for LLM spend and budgets
+pub struct CostOps;
+
+impl CostOps {
+    /// Record a priced model call in the user's database
+    pub async fn record_call(pool: &sqlx::SqlitePool, record: &CostRecord) -> AriaResult<()> {
+        sqlx::query(r#"
+            INSERT INTO llm_costs (
+                call_id, session_id, task_id, agent_name, tool_name, provider, model,
+                prompt_tokens, completion_tokens, cost_usd, priced, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#)
+        .bind(&record.call_id)
+        .bind(&record.session_id)
+        .bind(&record.task_id)
+        .bind(&record.agent_name)
+        .bind(&record.tool_name)
+        .bind(&record.provider)
+        .bind(&record.model)
+        .bind(record.prompt_tokens as i64)
+        .bind(record.completion_tokens as i64)
+        .bind(record.cost_usd)
+        .bind(record.priced)
+        .bind(record.created_at as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::Medium,
+            &format!("Failed to record LLM call cost: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Total spend recorded since `since`
+    pub async fn spent_since(pool: &sqlx::SqlitePool, since: u64) -> AriaResult<f64> {
+        let (spent,): (Option<f64>,) = sqlx::query_as("SELECT SUM(cost_usd) FROM llm_costs WHERE created_at >= ?")
+            .bind(since as i64)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to sum LLM spend: {}", e)
+            ))?;
+
+        Ok(spent.unwrap_or(0.0))
+    }
+
+    /// Spend in `[start, end)`, grouped by `dimension` and, when `bucket_seconds` is set,
+    /// by time buckets of that length. Buckets come in order, the costliest group first within each.
+    pub async fn rollup(
+        pool: &sqlx::SqlitePool,
+        start: u64,
+        end: u64,
+        dimension: Option<CostDimension>,
+        bucket_seconds: Option<u64>,
+    ) -> AriaResult<Vec<CostRollup>> {
+        let key = dimension.map(|d| d.column()).unwrap_or("NULL");
+        let bucket = match bucket_seconds.filter(|&seconds| seconds > 0) {
+            Some(seconds) => format!("(created_at / {seconds}) * {seconds}"),
+            None => (start as i64).to_string(),
+        };
+
+        let rows: Vec<RollupRow> = sqlx::query_as(&format!(r#"
+            SELECT {key} AS group_key, {bucket} AS bucket_start, COUNT(*),
+                   SUM(prompt_tokens), SUM(completion_tokens), SUM(cost_usd)
+            FROM llm_costs
+            WHERE created_at >= ? AND created_at < ?
+            GROUP BY group_key, bucket_start
+            ORDER BY bucket_start ASC, SUM(cost_usd) DESC
+        "#))
+        .bind(start as i64)
+        .bind(end as i64)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to roll up LLM spend: {}", e)
+        ))?;
+
+        Ok(rows.into_iter().map(|(key, bucket_start, calls, prompt_tokens, completion_tokens, cost_usd)| CostRollup {
+            key,
+            bucket_start: bucket_start as u64,
+            calls: calls as u64,
+            prompt_tokens: prompt_tokens as u64,
+            completion_tokens: completion_tokens as u64,
+            cost_usd,
+        }).collect())
+    }
+
+    /// A tenant's budget, if one is set
+    pub async fn get_budget(pool: &sqlx::SqlitePool, user_id: &str) -> AriaResult<Option<BudgetRecord>> {
+        let row: Option<BudgetRow> = sqlx::query_as(r#"
+            SELECT user_id, run_limit_usd, daily_limit_usd, action, downgrade_model, updated_at
+            FROM llm_budgets WHERE user_id = ?
+        "#)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to get LLM budget: {}", e)
+        ))?;
+
+        Ok(row.map(budget_from_row))
+    }
+
+    /// Set a tenant's budget, replacing any previous one
+    pub async fn set_budget(pool: &sqlx::SqlitePool, budget: &BudgetRecord) -> AriaResult<BudgetRecord> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        sqlx::query(r#"
+            INSERT INTO llm_budgets (user_id, run_limit_usd, daily_limit_usd, action, downgrade_model, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(user_id) DO UPDATE SET
+                run_limit_usd = excluded.run_limit_usd,
+                daily_limit_usd = excluded.daily_limit_usd,
+                action = excluded.action,
+                downgrade_model = excluded.downgrade_model,
+                updated_at = excluded.updated_at
+        "#)
+        .bind(&budget.user_id)
+        .bind(budget.run_limit_usd)
+        .bind(budget.daily_limit_usd)
+        .bind(budget.action.as_str())
+        .bind(&budget.downgrade_model)
+        .bind(now as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to set LLM budget: {}", e)
+        ))?;
+
+        Ok(BudgetRecord { updated_at: now, ..budget.clone() })
+    }
+}
+
+fn budget_from_row(row: BudgetRow) -> BudgetRecord {
+    let (user_id, run_limit_usd, daily_limit_usd, action, downgrade_model, updated_at) = row;
+    BudgetRecord {
+        user_id,
+        run_limit_usd,
+        daily_limit_usd,
+        action: BudgetAction::parse(&action).unwrap_or_default(),
+        downgrade_model,
+        updated_at: updated_at as u64,
+    }
+}
@@ -0,0 +1,120 @@
+// Device Database Operations
+// Enrolled device keys used to authenticate callers
+
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+
+/// Device record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceRecord {
+    pub device_id: String,
+    pub user_id: String,
+    /// OpenSSH-format ed25519 public key
+    pub public_key: String,
+    pub created_at: u64,
+    pub revoked_at: Option<u64>,
+}
+
+/// Database operations for devices
+pub struct DeviceOps;
+
+impl DeviceOps {
+    /// Enroll a device key for a user, replacing the key (and lifting any revocation)
+    /// if the device is already enrolled to the same user
+    pub async fn register_device(
+        pool: &sqlx::SqlitePool,
+        device_id: &str,
+        user_id: &str,
+        public_key: &str,
+    ) -> AriaResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let result = sqlx::query(r#"
+            INSERT INTO user_devices (device_id, user_id, public_key, created_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(device_id) DO UPDATE SET
+                public_key = excluded.public_key,
+                revoked_at = NULL
+            WHERE user_devices.user_id = excluded.user_id
+        "#)
+        .bind(device_id)
+        .bind(user_id)
+        .bind(public_key)
+        .bind(now as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to register device: {}", e)
+        ))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AriaError::new(
+                ErrorCode::PermissionDenied,
+                ErrorCategory::Security,
+                ErrorSeverity::High,
+                &format!("Device {} is enrolled to another user", device_id)
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Unrevoked devices whose user is active
+    pub async fn list_active_devices(pool: &sqlx::SqlitePool) -> AriaResult<Vec<DeviceRecord>> {
+        let rows: Vec<(String, String, String, i64)> = sqlx::query_as(r#"
+            SELECT d.device_id, d.user_id, d.public_key, d.created_at
+            FROM user_devices d
+            JOIN users u ON u.user_id = d.user_id
+            WHERE d.revoked_at IS NULL AND u.status = 'active'
+        "#)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to list devices: {}", e)
+        ))?;
+
+        Ok(rows.into_iter()
+            .map(|(device_id, user_id, public_key, created_at)| DeviceRecord {
+                device_id,
+                user_id,
+                public_key,
+                created_at: created_at as u64,
+                revoked_at: None,
+            })
+            .collect())
+    }
+
+    /// Revoke a device so its key is no longer accepted; returns whether a device was revoked
+    pub async fn revoke_device(pool: &sqlx::SqlitePool, device_id: &str) -> AriaResult<bool> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let result = sqlx::query(r#"
+            UPDATE user_devices SET revoked_at = ?
+            WHERE device_id = ? AND revoked_at IS NULL
+        "#)
+        .bind(now as i64)
+        .bind(device_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to revoke device: {}", e)
+        ))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,249 @@
+// Agent Memory Database Operations
+// Persistence for long-term agent and user memories
+
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+
+/// Memory record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryRecord {
+    pub memory_id: String,
+    pub namespace: String,
+    pub tier: String,
+    pub memory_key: String,
+    pub content: serde_json::Value,
+    pub entry_type: String,
+    pub tags: Vec<String>,
+    pub embedding: Option<Vec<f32>>,
+    pub embedding_model: Option<String>,
+    pub created_at: u64,
+    pub last_accessed: u64,
+    pub access_count: u32,
+    pub expires_at: Option<u64>,
+}
+
+/// Database operations for agent memories
+pub struct MemoryOps;
+
+impl MemoryOps {
+    /// Insert a memory, replacing the content of an existing entry with the same key
+    pub async fn upsert(pool: &sqlx::SqlitePool, record: &MemoryRecord) -> AriaResult<()> {
+        let embedding = record.embedding.as_ref()
+            .map(|e| serde_json::to_string(e).unwrap_or_default());
+
+        sqlx::query(r#"
+            INSERT INTO agent_memories (memory_id, namespace, tier, memory_key, content, entry_type,
+                                        tags, embedding, embedding_model, created_at, last_accessed,
+                                        access_count, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (namespace, tier, memory_key) DO UPDATE SET
+                content = excluded.content,
+                entry_type = excluded.entry_type,
+                tags = excluded.tags,
+                embedding = excluded.embedding,
+                embedding_model = excluded.embedding_model,
+                last_accessed = excluded.last_accessed,
+                expires_at = excluded.expires_at
+        "#)
+        .bind(&record.memory_id)
+        .bind(&record.namespace)
+        .bind(&record.tier)
+        .bind(&record.memory_key)
+        .bind(record.content.to_string())
+        .bind(&record.entry_type)
+        .bind(serde_json::to_string(&record.tags).unwrap_or_else(|_| "[]".to_string()))
+        .bind(embedding)
+        .bind(&record.embedding_model)
+        .bind(record.created_at as i64)
+        .bind(record.last_accessed as i64)
+        .bind(record.access_count as i64)
+        .bind(record.expires_at.map(|t| t as i64))
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to store memory: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Get an unexpired memory by key
+    pub async fn get(
+        pool: &sqlx::SqlitePool,
+        namespace: &str,
+        tier: &str,
+        memory_key: &str,
+        now: u64,
+    ) -> AriaResult<Option<MemoryRecord>> {
+        let row: Option<MemoryRow> = sqlx::query_as(r#"
+                SELECT memory_id, namespace, tier, memory_key, content, entry_type, tags,
+                       embedding, embedding_model, created_at, last_accessed, access_count, expires_at
+                FROM agent_memories
+                WHERE namespace = ? AND tier = ? AND memory_key = ?
+                  AND (expires_at IS NULL OR expires_at > ?)
+            "#)
+            .bind(namespace)
+            .bind(tier)
+            .bind(memory_key)
+            .bind(now as i64)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to get memory: {}", e)
+            ))?;
+
+        Ok(row.map(record_from_row))
+    }
+
+    /// List every unexpired memory in the given namespaces
+    pub async fn list_active(
+        pool: &sqlx::SqlitePool,
+        namespaces: &[String],
+        now: u64,
+    ) -> AriaResult<Vec<MemoryRecord>> {
+        if namespaces.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; namespaces.len()].join(", ");
+        let query = format!(r#"
+                SELECT memory_id, namespace, tier, memory_key, content, entry_type, tags,
+                       embedding, embedding_model, created_at, last_accessed, access_count, expires_at
+                FROM agent_memories
+                WHERE namespace IN ({}) AND (expires_at IS NULL OR expires_at > ?)
+            "#, placeholders);
+
+        let mut statement = sqlx::query_as::<_, MemoryRow>(&query);
+        for namespace in namespaces {
+            statement = statement.bind(namespace);
+        }
+
+        let rows = statement
+            .bind(now as i64)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to list memories: {}", e)
+            ))?;
+
+        Ok(rows.into_iter().map(record_from_row).collect())
+    }
+
+    /// Record an access, which keeps the memory away from LRU eviction
+    pub async fn touch(pool: &sqlx::SqlitePool, memory_id: &str, now: u64) -> AriaResult<()> {
+        sqlx::query(r#"
+            UPDATE agent_memories
+            SET last_accessed = ?, access_count = access_count + 1
+            WHERE memory_id = ?
+        "#)
+        .bind(now as i64)
+        .bind(memory_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to touch memory: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Delete a memory by key
+    pub async fn delete(
+        pool: &sqlx::SqlitePool,
+        namespace: &str,
+        tier: &str,
+        memory_key: &str,
+    ) -> AriaResult<bool> {
+        let result = sqlx::query(r#"
+            DELETE FROM agent_memories WHERE namespace = ? AND tier = ? AND memory_key = ?
+        "#)
+        .bind(namespace)
+        .bind(tier)
+        .bind(memory_key)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to delete memory: {}", e)
+        ))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete every memory whose TTL has elapsed
+    pub async fn delete_expired(pool: &sqlx::SqlitePool, now: u64) -> AriaResult<u64> {
+        let result = sqlx::query(r#"
+            DELETE FROM agent_memories WHERE expires_at IS NOT NULL AND expires_at <= ?
+        "#)
+        .bind(now as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to delete expired memories: {}", e)
+        ))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Evict least recently used memories until the namespace holds at most `max_entries`
+    pub async fn evict_lru(pool: &sqlx::SqlitePool, namespace: &str, max_entries: usize) -> AriaResult<u64> {
+        let result = sqlx::query(r#"
+            DELETE FROM agent_memories
+            WHERE memory_id IN (
+                SELECT memory_id FROM agent_memories
+                WHERE namespace = ?
+                ORDER BY last_accessed DESC, access_count DESC
+                LIMIT -1 OFFSET ?
+            )
+        "#)
+        .bind(namespace)
+        .bind(max_entries as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to evict memories: {}", e)
+        ))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+type MemoryRow = (String, String, String, String, String, String, String, Option<String>, Option<String>, i64, i64, i64, Option<i64>);
+
+fn record_from_row(row: MemoryRow) -> MemoryRecord {
+    MemoryRecord {
+        memory_id: row.0,
+        namespace: row.1,
+        tier: row.2,
+        memory_key: row.3,
+        content: serde_json::from_str(&row.4).unwrap_or(serde_json::Value::String(row.4)),
+        entry_type: row.5,
+        tags: serde_json::from_str(&row.6).unwrap_or_default(),
+        embedding: row.7.and_then(|e| serde_json::from_str(&e).ok()),
+        embedding_model: row.8,
+        created_at: row.9 as u64,
+        last_accessed: row.10 as u64,
+        access_count: row.11 as u32,
+        expires_at: row.12.map(|t| t as u64),
+    }
+}
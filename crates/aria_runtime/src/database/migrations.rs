@@ -7,10 +7,10 @@ use sqlx::SqlitePool;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Current schema version for system database
-pub const SYSTEM_SCHEMA_VERSION: i32 = 1;
+pub const SYSTEM_SCHEMA_VERSION: i32 = 6;
 
 /// Current schema version for user databases
-pub const USER_SCHEMA_VERSION: i32 = 2;
+pub const USER_SCHEMA_VERSION: i32 = 8;
 
 /// Migration metadata
 #[derive(Debug, Clone)]
@@ -176,6 +176,145 @@ fn get_system_migrations() -> Vec<Migration> {
             sql: SYSTEM_SCHEMA.to_string(),
             applied_at: None,
         },
+        Migration {
+            version: 2,
+            description: "Device keys for caller authentication".to_string(),
+            sql: r#"
+-- Ed25519 public keys of the devices each user has enrolled
+CREATE TABLE IF NOT EXISTS user_devices (
+    device_id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(user_id),
+    public_key TEXT NOT NULL, -- OpenSSH format: ssh-ed25519 <base64> [comment]
+    created_at INTEGER NOT NULL,
+    revoked_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_devices_user_id ON user_devices(user_id);
+"#.to_string(),
+            applied_at: None,
+        },
+        Migration {
+            version: 3,
+            description: "Tenant roles, resource ownership and quotas".to_string(),
+            sql: r#"
+-- Admins can see and manage every tenant's resources
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'user'; -- user, admin
+
+-- Owner of each session and task, so requests can be checked and routed to the owner's database
+CREATE TABLE IF NOT EXISTS tenant_resources (
+    resource_type TEXT NOT NULL, -- session, task
+    resource_id TEXT NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(user_id),
+    session_id TEXT,
+    container_id TEXT,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (resource_type, resource_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_tenant_resources_user_id ON tenant_resources(user_id, resource_type);
+
+-- Per-tenant limits; 0 means unlimited. Container limits are enforced by quilt as owner quotas
+CREATE TABLE IF NOT EXISTS tenant_quotas (
+    user_id TEXT PRIMARY KEY REFERENCES users(user_id),
+    max_sessions INTEGER NOT NULL DEFAULT 0,
+    max_containers INTEGER NOT NULL DEFAULT 0,
+    max_memory_mb INTEGER NOT NULL DEFAULT 0,
+    updated_at INTEGER NOT NULL
+);
+"#.to_string(),
+            applied_at: None,
+        },
+        Migration {
+            version: 4,
+            description: "Notification log and outbound webhook delivery".to_string(),
+            sql: r#"
+-- Every notification, in order; seq is the cursor reconnecting clients replay from
+CREATE TABLE IF NOT EXISTS notifications (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    id TEXT NOT NULL UNIQUE,
+    user_id TEXT, -- user the notification concerns
+    event_type TEXT NOT NULL, -- task_status, bundle_upload, tool_approval
+    payload BLOB NOT NULL, -- encoded Notification message
+    body TEXT NOT NULL, -- JSON document posted to webhook subscriptions
+    created_at INTEGER NOT NULL
+);
+
+-- URLs notifications are posted to, signed with the subscription's secret
+CREATE TABLE IF NOT EXISTS webhook_subscriptions (
+    subscription_id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL, -- HMAC-SHA256 key
+    event_types TEXT NOT NULL, -- JSON array; empty means every event type
+    created_at INTEGER NOT NULL
+);
+
+-- Deliveries not yet accepted by their subscription's URL
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    delivery_id TEXT PRIMARY KEY,
+    subscription_id TEXT NOT NULL,
+    notification_id TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at INTEGER NOT NULL,
+    last_error TEXT,
+    last_status_code INTEGER,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (subscription_id) REFERENCES webhook_subscriptions(subscription_id) ON DELETE CASCADE
+);
+
+-- Deliveries that ran out of attempts, kept until redelivered
+CREATE TABLE IF NOT EXISTS webhook_dead_letters (
+    delivery_id TEXT PRIMARY KEY,
+    subscription_id TEXT NOT NULL,
+    notification_id TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    last_error TEXT,
+    last_status_code INTEGER,
+    failed_at INTEGER NOT NULL,
+    FOREIGN KEY (subscription_id) REFERENCES webhook_subscriptions(subscription_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_user ON notifications(user_id, seq);
+CREATE INDEX IF NOT EXISTS idx_notifications_created ON notifications(created_at);
+CREATE INDEX IF NOT EXISTS idx_webhook_subscriptions_user ON webhook_subscriptions(user_id);
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_due ON webhook_deliveries(next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_webhook_dead_letters_subscription ON webhook_dead_letters(subscription_id, failed_at);
+"#.to_string(),
+            applied_at: None,
+        },
+        Migration {
+            version: 5,
+            description: "LLM spend budgets".to_string(),
+            sql: r#"
+-- Per-tenant limits on LLM spend; NULL means unlimited
+CREATE TABLE IF NOT EXISTS llm_budgets (
+    user_id TEXT PRIMARY KEY REFERENCES users(user_id),
+    run_limit_usd REAL, -- spend allowed in one task run or session turn
+    daily_limit_usd REAL, -- spend allowed per UTC day
+    action TEXT NOT NULL DEFAULT 'abort', -- abort, downgrade
+    downgrade_model TEXT, -- model calls switch to once a limit is reached
+    updated_at INTEGER NOT NULL
+);
+"#.to_string(),
+            applied_at: None,
+        },
+        Migration {
+            version: 6,
+            description: "Tool policy rules".to_string(),
+            sql: r#"
+-- Authorization rules loaded into the tool policy engine at startup
+CREATE TABLE IF NOT EXISTS tool_policy_rules (
+    rule_id TEXT PRIMARY KEY,
+    scope_kind TEXT NOT NULL, -- global, user, agent, session
+    scope_id TEXT, -- NULL for global rules
+    rule_json TEXT NOT NULL, -- the full rule, as the policy engine serializes it
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_tool_policy_rules_scope ON tool_policy_rules(scope_kind, scope_id);
+"#.to_string(),
+            applied_at: None,
+        },
         // Future migrations will be added here
     ]
 }
@@ -293,6 +432,179 @@ CREATE INDEX IF NOT EXISTS idx_intelligence_queries_type ON intelligence_queries
             "#.to_string(),
             applied_at: None,
         },
+        Migration {
+            version: 3,
+            description: "Long-term agent memory".to_string(),
+            sql: r#"
+-- Agent and user memories (recalled by embedding similarity)
+CREATE TABLE IF NOT EXISTS agent_memories (
+    memory_id TEXT PRIMARY KEY,
+    namespace TEXT NOT NULL,                -- "user" or "agent:<name>"
+    tier TEXT NOT NULL,                     -- "short_term", "long_term"
+    memory_key TEXT NOT NULL,
+    content TEXT NOT NULL,                  -- JSON value
+    entry_type TEXT NOT NULL,               -- MemoryEntryType variant
+    tags TEXT NOT NULL DEFAULT '[]',        -- JSON array of tags
+    embedding TEXT,                         -- JSON array of f32
+    embedding_model TEXT,
+    created_at INTEGER NOT NULL,
+    last_accessed INTEGER NOT NULL,
+    access_count INTEGER NOT NULL DEFAULT 0,
+    expires_at INTEGER,
+    UNIQUE (namespace, tier, memory_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_memories_namespace ON agent_memories(namespace, last_accessed DESC);
+CREATE INDEX IF NOT EXISTS idx_agent_memories_expires ON agent_memories(expires_at);
+            "#.to_string(),
+            applied_at: None,
+        },
+        Migration {
+            version: 4,
+            description: "Task scheduling priority and external task IDs".to_string(),
+            sql: r#"
+-- Higher priority tasks are dispatched first
+ALTER TABLE async_tasks ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+-- ID of the work in the system that runs it, e.g. the quilt task of a container exec
+ALTER TABLE async_tasks ADD COLUMN external_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_async_tasks_queue ON async_tasks(status, priority DESC, created_at);
+            "#.to_string(),
+            applied_at: None,
+        },
+        Migration {
+            version: 5,
+            description: "Task schedules".to_string(),
+            sql: r#"
+-- Cron or interval schedules that launch a task from a template on every fire
+CREATE TABLE IF NOT EXISTS task_schedules (
+    schedule_id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    cron_expression TEXT, -- exactly one of cron_expression and interval_seconds is set
+    interval_seconds INTEGER,
+    timezone TEXT NOT NULL DEFAULT 'UTC', -- IANA name the cron expression is read in
+    jitter_seconds INTEGER NOT NULL DEFAULT 0,
+    overlap_policy TEXT NOT NULL DEFAULT 'skip', -- skip, queue, cancel_previous
+    catch_up_policy TEXT NOT NULL DEFAULT 'run_once', -- skip, run_once, run_all
+
+    -- Task template
+    task_type TEXT NOT NULL,
+    command TEXT NOT NULL, -- JSON array
+    environment TEXT, -- JSON object
+    container_id TEXT,
+    session_id TEXT,
+    priority INTEGER NOT NULL DEFAULT 0,
+    timeout_seconds INTEGER NOT NULL DEFAULT 0,
+
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    next_run_at INTEGER, -- unix timestamp of the next fire before jitter
+    last_run_at INTEGER,
+    last_task_id TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_schedules_due ON task_schedules(enabled, next_run_at);
+            "#.to_string(),
+            applied_at: None,
+        },
+        Migration {
+            version: 6,
+            description: "Webhook and event triggers".to_string(),
+            sql: r#"
+-- Webhooks and observability events that launch a task from a template
+CREATE TABLE IF NOT EXISTS task_triggers (
+    trigger_id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL, -- webhook, event
+    secret TEXT, -- HMAC-SHA256 key webhook payloads are signed with
+    event_type TEXT, -- observability event type an event trigger listens to
+    conditions TEXT, -- JSON object of payload path -> expected value
+    dedupe_key TEXT, -- template of the key repeated deliveries share
+    dedupe_window_seconds INTEGER NOT NULL DEFAULT 0,
+    max_fires_per_minute INTEGER NOT NULL DEFAULT 0, -- 0 means unlimited
+
+    -- Task template; command and environment values may hold {{path}} placeholders
+    task_type TEXT NOT NULL,
+    command TEXT NOT NULL, -- JSON array
+    environment TEXT, -- JSON object
+    container_id TEXT,
+    session_id TEXT,
+    priority INTEGER NOT NULL DEFAULT 0,
+    timeout_seconds INTEGER NOT NULL DEFAULT 0,
+
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    last_fired_at INTEGER,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+-- Tasks launched by triggers, for rate limiting and dedupe
+CREATE TABLE IF NOT EXISTS trigger_fires (
+    fire_id TEXT PRIMARY KEY,
+    trigger_id TEXT NOT NULL,
+    dedupe_key TEXT,
+    task_id TEXT NOT NULL,
+    fired_at INTEGER NOT NULL,
+    FOREIGN KEY (trigger_id) REFERENCES task_triggers(trigger_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_triggers_event ON task_triggers(kind, event_type, enabled);
+CREATE INDEX IF NOT EXISTS idx_trigger_fires_recent ON trigger_fires(trigger_id, fired_at);
+CREATE INDEX IF NOT EXISTS idx_trigger_fires_dedupe ON trigger_fires(trigger_id, dedupe_key, fired_at);
+            "#.to_string(),
+            applied_at: None,
+        },
+        Migration {
+            version: 7,
+            description: "LLM call costs".to_string(),
+            sql: r#"
+-- Every priced model call, attributed to what made it
+CREATE TABLE IF NOT EXISTS llm_costs (
+    call_id TEXT PRIMARY KEY,
+    session_id TEXT,
+    task_id TEXT,
+    agent_name TEXT,
+    tool_name TEXT, -- tool whose handler made the call, if any
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt_tokens INTEGER NOT NULL,
+    completion_tokens INTEGER NOT NULL,
+    cost_usd REAL NOT NULL,
+    priced BOOLEAN NOT NULL, -- false when the pricing table has no entry for the model
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_llm_costs_created ON llm_costs(created_at);
+CREATE INDEX IF NOT EXISTS idx_llm_costs_session ON llm_costs(session_id);
+CREATE INDEX IF NOT EXISTS idx_llm_costs_task ON llm_costs(task_id);
+            "#.to_string(),
+            applied_at: None,
+        },
+        Migration {
+            version: 8,
+            description: "LLM response cache".to_string(),
+            sql: r#"
+-- Model responses reused for repeated requests
+CREATE TABLE IF NOT EXISTS llm_response_cache (
+    cache_key TEXT PRIMARY KEY, -- hash of the normalized request
+    scope_key TEXT NOT NULL, -- hash of the request without its message contents
+    response TEXT NOT NULL, -- JSON
+    embedding TEXT, -- JSON array of the prompt's embedding, for similarity hits
+    embedding_model TEXT,
+    created_at INTEGER NOT NULL,
+    last_hit_at INTEGER NOT NULL,
+    hits INTEGER NOT NULL DEFAULT 0,
+    expires_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_llm_response_cache_scope ON llm_response_cache(scope_key);
+CREATE INDEX IF NOT EXISTS idx_llm_response_cache_expires ON llm_response_cache(expires_at);
+            "#.to_string(),
+            applied_at: None,
+        },
         // Future migrations will be added here
     ]
 }
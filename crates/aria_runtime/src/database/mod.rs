@@ -12,10 +12,20 @@ use uuid::Uuid;
 pub mod schema;
 pub mod migrations;
 pub mod async_tasks;
+pub mod schedules;
+pub mod triggers;
 pub mod sessions;
+pub mod conversations;
 pub mod users;
+pub mod devices;
+pub mod notifications;
+pub mod costs;
+pub mod policies;
+pub mod tenancy;
 pub mod containers;
 pub mod audit;
+pub mod memories;
+pub mod response_cache;
 
 /// Database configuration for Aria Runtime
 #[derive(Debug, Clone)]
@@ -0,0 +1,584 @@
+// Notification Database Operations
+// The notification log, webhook subscriptions and their deliveries, all in the system database
+
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+
+/// A logged notification
+#[derive(Debug, Clone, Default)]
+pub struct NotificationEntry {
+    /// Position in the log, assigned when the notification is appended
+    pub seq: u64,
+    pub id: String,
+    pub user_id: Option<String>,
+    pub event_type: String,
+    /// Encoded `Notification` message
+    pub payload: Vec<u8>,
+    /// JSON document posted to webhook subscriptions
+    pub body: String,
+    pub created_at: u64,
+}
+
+/// Webhook subscription record
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubscriptionRecord {
+    pub subscription_id: String,
+    pub user_id: String,
+    pub url: String,
+    /// HMAC-SHA256 key deliveries are signed with
+    pub secret: String,
+    /// Empty means every event type
+    pub event_types: Vec<String>,
+    pub created_at: u64,
+}
+
+impl SubscriptionRecord {
+    pub fn wants(&self, event_type: &str) -> bool {
+        self.event_types.is_empty() || self.event_types.iter().any(|t| t == event_type)
+    }
+}
+
+/// A delivery due to be attempted, with what it posts and where
+#[derive(Debug, Clone)]
+pub struct PendingDelivery {
+    pub delivery_id: String,
+    pub subscription_id: String,
+    pub notification_id: String,
+    pub url: String,
+    pub secret: String,
+    pub event_type: String,
+    pub body: String,
+    pub attempts: u32,
+}
+
+/// A delivery that ran out of attempts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterRecord {
+    pub delivery_id: String,
+    pub subscription_id: String,
+    /// Owner of the subscription
+    pub user_id: String,
+    pub notification_id: String,
+    pub event_type: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub last_status_code: Option<u16>,
+    pub failed_at: u64,
+}
+
+type NotificationRow = (i64, String, Option<String>, String, Vec<u8>, String, i64);
+type SubscriptionRow = (String, String, String, String, String, i64);
+type DeadLetterRow = (String, String, String, String, String, i64, Option<String>, Option<i64>, i64);
+
+fn notification_from_row(row: NotificationRow) -> NotificationEntry {
+    let (seq, id, user_id, event_type, payload, body, created_at) = row;
+    NotificationEntry {
+        seq: seq as u64,
+        id,
+        user_id,
+        event_type,
+        payload,
+        body,
+        created_at: created_at as u64,
+    }
+}
+
+fn subscription_from_row(row: SubscriptionRow) -> SubscriptionRecord {
+    let (subscription_id, user_id, url, secret, event_types, created_at) = row;
+    SubscriptionRecord {
+        subscription_id,
+        user_id,
+        url,
+        secret,
+        event_types: serde_json::from_str(&event_types).unwrap_or_default(),
+        created_at: created_at as u64,
+    }
+}
+
+fn dead_letter_from_row(row: DeadLetterRow) -> DeadLetterRecord {
+    let (delivery_id, subscription_id, user_id, notification_id, event_type, attempts, last_error, last_status_code, failed_at) = row;
+    DeadLetterRecord {
+        delivery_id,
+        subscription_id,
+        user_id,
+        notification_id,
+        event_type,
+        attempts: attempts.max(0) as u32,
+        last_error,
+        last_status_code: last_status_code.map(|code| code as u16),
+        failed_at: failed_at as u64,
+    }
+}
+
+/// Database operations for notifications and their webhook deliveries
+pub struct NotificationOps;
+
+impl NotificationOps {
+    /// Append a notification to the log, returning its cursor
+    pub async fn append_notification(pool: &sqlx::SqlitePool, entry: &NotificationEntry) -> AriaResult<u64> {
+        let result = sqlx::query(r#"
+            INSERT INTO notifications (id, user_id, event_type, payload, body, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+        "#)
+        .bind(&entry.id)
+        .bind(&entry.user_id)
+        .bind(&entry.event_type)
+        .bind(&entry.payload)
+        .bind(&entry.body)
+        .bind(entry.created_at as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to store notification: {}", e)
+        ))?;
+
+        Ok(result.last_insert_rowid() as u64)
+    }
+
+    /// Up to `limit` notifications logged after cursor `after`, oldest first,
+    /// only those concerning `user_id` if given
+    pub async fn notifications_since(
+        pool: &sqlx::SqlitePool,
+        after: u64,
+        user_id: Option<&str>,
+        limit: u32,
+    ) -> AriaResult<Vec<NotificationEntry>> {
+        let rows: Vec<NotificationRow> = sqlx::query_as(r#"
+            SELECT seq, id, user_id, event_type, payload, body, created_at
+            FROM notifications
+            WHERE seq > ? AND (? IS NULL OR user_id = ?)
+            ORDER BY seq ASC LIMIT ?
+        "#)
+        .bind(after as i64)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(limit as i64)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to read notifications: {}", e)
+        ))?;
+
+        Ok(rows.into_iter().map(notification_from_row).collect())
+    }
+
+    /// Drop notifications logged before `before` that no delivery still needs
+    pub async fn prune_notifications(pool: &sqlx::SqlitePool, before: u64) -> AriaResult<u64> {
+        let result = sqlx::query(r#"
+            DELETE FROM notifications
+            WHERE created_at < ?
+              AND id NOT IN (SELECT notification_id FROM webhook_deliveries)
+              AND id NOT IN (SELECT notification_id FROM webhook_dead_letters)
+        "#)
+        .bind(before as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::Medium,
+            &format!("Failed to prune notifications: {}", e)
+        ))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Store a new webhook subscription
+    pub async fn create_subscription(pool: &sqlx::SqlitePool, subscription: &SubscriptionRecord) -> AriaResult<()> {
+        let event_types_json = serde_json::to_string(&subscription.event_types)
+            .map_err(|e| AriaError::new(
+                ErrorCode::SerializationError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to serialize event types: {}", e)
+            ))?;
+
+        sqlx::query(r#"
+            INSERT INTO webhook_subscriptions (subscription_id, user_id, url, secret, event_types, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+        "#)
+        .bind(&subscription.subscription_id)
+        .bind(&subscription.user_id)
+        .bind(&subscription.url)
+        .bind(&subscription.secret)
+        .bind(event_types_json)
+        .bind(subscription.created_at as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to create webhook subscription: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Get a webhook subscription by ID
+    pub async fn get_subscription(pool: &sqlx::SqlitePool, subscription_id: &str) -> AriaResult<Option<SubscriptionRecord>> {
+        let row: Option<SubscriptionRow> = sqlx::query_as(r#"
+            SELECT subscription_id, user_id, url, secret, event_types, created_at
+            FROM webhook_subscriptions WHERE subscription_id = ?
+        "#)
+        .bind(subscription_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to get webhook subscription: {}", e)
+        ))?;
+
+        Ok(row.map(subscription_from_row))
+    }
+
+    /// Webhook subscriptions of a user, oldest first
+    pub async fn list_subscriptions(pool: &sqlx::SqlitePool, user_id: &str) -> AriaResult<Vec<SubscriptionRecord>> {
+        let rows: Vec<SubscriptionRow> = sqlx::query_as(r#"
+            SELECT subscription_id, user_id, url, secret, event_types, created_at
+            FROM webhook_subscriptions WHERE user_id = ?
+            ORDER BY created_at ASC
+        "#)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to list webhook subscriptions: {}", e)
+        ))?;
+
+        Ok(rows.into_iter().map(subscription_from_row).collect())
+    }
+
+    /// Delete a webhook subscription with its pending and dead deliveries
+    pub async fn delete_subscription(pool: &sqlx::SqlitePool, subscription_id: &str) -> AriaResult<bool> {
+        let mut tx = pool.begin().await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to start transaction: {}", e)
+            ))?;
+
+        for table in ["webhook_deliveries", "webhook_dead_letters"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE subscription_id = ?", table))
+                .bind(subscription_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| AriaError::new(
+                    ErrorCode::DatabaseError,
+                    ErrorCategory::System,
+                    ErrorSeverity::High,
+                    &format!("Failed to delete webhook deliveries: {}", e)
+                ))?;
+        }
+
+        let result = sqlx::query("DELETE FROM webhook_subscriptions WHERE subscription_id = ?")
+            .bind(subscription_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to delete webhook subscription: {}", e)
+            ))?;
+
+        tx.commit().await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to commit transaction: {}", e)
+            ))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Queue delivery of a notification to a subscription, due at `at`
+    pub async fn enqueue_delivery(
+        pool: &sqlx::SqlitePool,
+        delivery_id: &str,
+        subscription_id: &str,
+        notification_id: &str,
+        at: u64,
+    ) -> AriaResult<()> {
+        sqlx::query(r#"
+            INSERT INTO webhook_deliveries (delivery_id, subscription_id, notification_id, next_attempt_at, created_at)
+            VALUES (?, ?, ?, ?, ?)
+        "#)
+        .bind(delivery_id)
+        .bind(subscription_id)
+        .bind(notification_id)
+        .bind(at as i64)
+        .bind(at as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to queue webhook delivery: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Up to `limit` deliveries due at `now`, oldest notification first
+    pub async fn due_deliveries(pool: &sqlx::SqlitePool, now: u64, limit: u32) -> AriaResult<Vec<PendingDelivery>> {
+        let rows: Vec<(String, String, String, String, String, String, String, i64)> = sqlx::query_as(r#"
+            SELECT d.delivery_id, d.subscription_id, d.notification_id, s.url, s.secret,
+                   n.event_type, n.body, d.attempts
+            FROM webhook_deliveries d
+            JOIN webhook_subscriptions s ON s.subscription_id = d.subscription_id
+            JOIN notifications n ON n.id = d.notification_id
+            WHERE d.next_attempt_at <= ?
+            ORDER BY n.seq ASC LIMIT ?
+        "#)
+        .bind(now as i64)
+        .bind(limit as i64)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to get due webhook deliveries: {}", e)
+        ))?;
+
+        Ok(rows.into_iter()
+            .map(|(delivery_id, subscription_id, notification_id, url, secret, event_type, body, attempts)| PendingDelivery {
+                delivery_id,
+                subscription_id,
+                notification_id,
+                url,
+                secret,
+                event_type,
+                body,
+                attempts: attempts.max(0) as u32,
+            })
+            .collect())
+    }
+
+    /// Remove a delivery its URL accepted
+    pub async fn complete_delivery(pool: &sqlx::SqlitePool, delivery_id: &str) -> AriaResult<()> {
+        sqlx::query("DELETE FROM webhook_deliveries WHERE delivery_id = ?")
+            .bind(delivery_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to complete webhook delivery: {}", e)
+            ))?;
+
+        Ok(())
+    }
+
+    /// Record a failed attempt and when to try again
+    pub async fn retry_delivery(
+        pool: &sqlx::SqlitePool,
+        delivery_id: &str,
+        error: &str,
+        status_code: Option<u16>,
+        next_attempt_at: u64,
+    ) -> AriaResult<()> {
+        sqlx::query(r#"
+            UPDATE webhook_deliveries
+            SET attempts = attempts + 1, last_error = ?, last_status_code = ?, next_attempt_at = ?
+            WHERE delivery_id = ?
+        "#)
+        .bind(error)
+        .bind(status_code.map(|code| code as i64))
+        .bind(next_attempt_at as i64)
+        .bind(delivery_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to reschedule webhook delivery: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Move a delivery whose last attempt failed to the dead letters
+    pub async fn dead_letter(
+        pool: &sqlx::SqlitePool,
+        delivery_id: &str,
+        error: &str,
+        status_code: Option<u16>,
+        failed_at: u64,
+    ) -> AriaResult<()> {
+        let mut tx = pool.begin().await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to start transaction: {}", e)
+            ))?;
+
+        sqlx::query(r#"
+            INSERT INTO webhook_dead_letters (
+                delivery_id, subscription_id, notification_id, attempts, last_error, last_status_code, failed_at
+            )
+            SELECT delivery_id, subscription_id, notification_id, attempts + 1, ?, ?, ?
+            FROM webhook_deliveries WHERE delivery_id = ?
+        "#)
+        .bind(error)
+        .bind(status_code.map(|code| code as i64))
+        .bind(failed_at as i64)
+        .bind(delivery_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to dead-letter webhook delivery: {}", e)
+        ))?;
+
+        sqlx::query("DELETE FROM webhook_deliveries WHERE delivery_id = ?")
+            .bind(delivery_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to dead-letter webhook delivery: {}", e)
+            ))?;
+
+        tx.commit().await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to commit transaction: {}", e)
+            ))?;
+
+        Ok(())
+    }
+
+    /// Dead letters of a user's subscriptions, or of one subscription, newest first
+    pub async fn list_dead_letters(
+        pool: &sqlx::SqlitePool,
+        user_id: &str,
+        subscription_id: Option<&str>,
+    ) -> AriaResult<Vec<DeadLetterRecord>> {
+        let rows: Vec<DeadLetterRow> = sqlx::query_as(r#"
+            SELECT l.delivery_id, l.subscription_id, s.user_id, l.notification_id, n.event_type,
+                   l.attempts, l.last_error, l.last_status_code, l.failed_at
+            FROM webhook_dead_letters l
+            JOIN webhook_subscriptions s ON s.subscription_id = l.subscription_id
+            JOIN notifications n ON n.id = l.notification_id
+            WHERE s.user_id = ? AND (? IS NULL OR l.subscription_id = ?)
+            ORDER BY l.failed_at DESC
+        "#)
+        .bind(user_id)
+        .bind(subscription_id)
+        .bind(subscription_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to list dead letters: {}", e)
+        ))?;
+
+        Ok(rows.into_iter().map(dead_letter_from_row).collect())
+    }
+
+    /// Get a dead letter by its delivery ID
+    pub async fn get_dead_letter(pool: &sqlx::SqlitePool, delivery_id: &str) -> AriaResult<Option<DeadLetterRecord>> {
+        let row: Option<DeadLetterRow> = sqlx::query_as(r#"
+            SELECT l.delivery_id, l.subscription_id, s.user_id, l.notification_id, n.event_type,
+                   l.attempts, l.last_error, l.last_status_code, l.failed_at
+            FROM webhook_dead_letters l
+            JOIN webhook_subscriptions s ON s.subscription_id = l.subscription_id
+            JOIN notifications n ON n.id = l.notification_id
+            WHERE l.delivery_id = ?
+        "#)
+        .bind(delivery_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to get dead letter: {}", e)
+        ))?;
+
+        Ok(row.map(dead_letter_from_row))
+    }
+
+    /// Queue a dead letter for delivery again as `new_delivery_id`, with fresh attempts.
+    /// False if there was no such dead letter.
+    pub async fn redeliver(
+        pool: &sqlx::SqlitePool,
+        delivery_id: &str,
+        new_delivery_id: &str,
+        at: u64,
+    ) -> AriaResult<bool> {
+        let mut tx = pool.begin().await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to start transaction: {}", e)
+            ))?;
+
+        let queued = sqlx::query(r#"
+            INSERT INTO webhook_deliveries (delivery_id, subscription_id, notification_id, next_attempt_at, created_at)
+            SELECT ?, subscription_id, notification_id, ?, ?
+            FROM webhook_dead_letters WHERE delivery_id = ?
+        "#)
+        .bind(new_delivery_id)
+        .bind(at as i64)
+        .bind(at as i64)
+        .bind(delivery_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to queue redelivery: {}", e)
+        ))?;
+
+        sqlx::query("DELETE FROM webhook_dead_letters WHERE delivery_id = ?")
+            .bind(delivery_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to remove dead letter: {}", e)
+            ))?;
+
+        tx.commit().await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to commit transaction: {}", e)
+            ))?;
+
+        Ok(queued.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,107 @@
+// Tool Policy Database Operations
+// Authorization rules for the tool policy engine, kept in the system database
+
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+
+/// A stored policy rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyRuleRecord {
+    pub rule_id: String,
+    /// global, user, agent or session
+    pub scope_kind: String,
+    /// User, agent or session the rule is scoped to; `None` for global rules
+    pub scope_id: Option<String>,
+    /// The full rule, as the policy engine serializes it
+    pub rule: serde_json::Value,
+    pub updated_at: u64,
+}
+
+/// Database operations for tool policy rules
+pub struct PolicyOps;
+
+impl PolicyOps {
+    /// Save a rule, replacing any stored rule with the same id
+    pub async fn save_rule(pool: &sqlx::SqlitePool, record: &PolicyRuleRecord) -> AriaResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        sqlx::query(r#"
+            INSERT INTO tool_policy_rules (rule_id, scope_kind, scope_id, rule_json, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(rule_id) DO UPDATE SET
+                scope_kind = excluded.scope_kind,
+                scope_id = excluded.scope_id,
+                rule_json = excluded.rule_json,
+                updated_at = excluded.updated_at
+        "#)
+        .bind(&record.rule_id)
+        .bind(&record.scope_kind)
+        .bind(&record.scope_id)
+        .bind(record.rule.to_string())
+        .bind(now as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to save policy rule {}: {}", record.rule_id, e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Every stored rule, oldest first
+    pub async fn list_rules(pool: &sqlx::SqlitePool) -> AriaResult<Vec<PolicyRuleRecord>> {
+        let rows: Vec<(String, String, Option<String>, String, i64)> = sqlx::query_as(r#"
+            SELECT rule_id, scope_kind, scope_id, rule_json, updated_at
+            FROM tool_policy_rules
+            ORDER BY updated_at, rule_id
+        "#)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to list policy rules: {}", e)
+        ))?;
+
+        rows.into_iter()
+            .map(|(rule_id, scope_kind, scope_id, rule_json, updated_at)| {
+                let rule = serde_json::from_str(&rule_json).map_err(|e| AriaError::new(
+                    ErrorCode::DatabaseError,
+                    ErrorCategory::System,
+                    ErrorSeverity::High,
+                    &format!("Stored policy rule {} is not valid JSON: {}", rule_id, e)
+                ))?;
+                Ok(PolicyRuleRecord {
+                    rule_id,
+                    scope_kind,
+                    scope_id,
+                    rule,
+                    updated_at: updated_at as u64,
+                })
+            })
+            .collect()
+    }
+
+    /// Delete a rule; returns whether a rule was deleted
+    pub async fn delete_rule(pool: &sqlx::SqlitePool, rule_id: &str) -> AriaResult<bool> {
+        let result = sqlx::query("DELETE FROM tool_policy_rules WHERE rule_id = ?")
+            .bind(rule_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to delete policy rule {}: {}", rule_id, e)
+            ))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,180 @@
+// LLM Response Cache Database Operations
+// Model responses kept in each user's database for reuse by repeated requests
+
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+
+/// A cached model response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponseRecord {
+    pub cache_key: String,
+    pub scope_key: String,
+    pub response: serde_json::Value,
+    pub embedding: Option<Vec<f32>>,
+    pub embedding_model: Option<String>,
+    pub created_at: u64,
+    pub last_hit_at: u64,
+    pub hits: u32,
+    pub expires_at: u64,
+}
+
+/// Database operations for cached model responses
+pub struct ResponseCacheOps;
+
+impl ResponseCacheOps {
+    /// Store a response, replacing any previous one for the same request
+    pub async fn upsert(pool: &sqlx::SqlitePool, record: &CachedResponseRecord) -> AriaResult<()> {
+        let embedding = record.embedding.as_ref()
+            .map(|e| serde_json::to_string(e).unwrap_or_default());
+
+        sqlx::query(r#"
+            INSERT INTO llm_response_cache (cache_key, scope_key, response, embedding, embedding_model,
+                                            created_at, last_hit_at, hits, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (cache_key) DO UPDATE SET
+                response = excluded.response,
+                embedding = excluded.embedding,
+                embedding_model = excluded.embedding_model,
+                created_at = excluded.created_at,
+                last_hit_at = excluded.last_hit_at,
+                hits = 0,
+                expires_at = excluded.expires_at
+        "#)
+        .bind(&record.cache_key)
+        .bind(&record.scope_key)
+        .bind(record.response.to_string())
+        .bind(embedding)
+        .bind(&record.embedding_model)
+        .bind(record.created_at as i64)
+        .bind(record.last_hit_at as i64)
+        .bind(record.hits as i64)
+        .bind(record.expires_at as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::Medium,
+            &format!("Failed to cache LLM response: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Get an unexpired response by request key
+    pub async fn get(pool: &sqlx::SqlitePool, cache_key: &str, now: u64) -> AriaResult<Option<CachedResponseRecord>> {
+        let row: Option<CachedResponseRow> = sqlx::query_as(r#"
+                SELECT cache_key, scope_key, response, embedding, embedding_model,
+                       created_at, last_hit_at, hits, expires_at
+                FROM llm_response_cache
+                WHERE cache_key = ? AND expires_at > ?
+            "#)
+            .bind(cache_key)
+            .bind(now as i64)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to get cached LLM response: {}", e)
+            ))?;
+
+        Ok(row.map(record_from_row))
+    }
+
+    /// List the unexpired responses in a scope that have an embedding from `embedding_model`
+    pub async fn list_embedded(
+        pool: &sqlx::SqlitePool,
+        scope_key: &str,
+        embedding_model: &str,
+        now: u64,
+    ) -> AriaResult<Vec<CachedResponseRecord>> {
+        let rows: Vec<CachedResponseRow> = sqlx::query_as(r#"
+                SELECT cache_key, scope_key, response, embedding, embedding_model,
+                       created_at, last_hit_at, hits, expires_at
+                FROM llm_response_cache
+                WHERE scope_key = ? AND embedding_model = ? AND expires_at > ?
+            "#)
+            .bind(scope_key)
+            .bind(embedding_model)
+            .bind(now as i64)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to list cached LLM responses: {}", e)
+            ))?;
+
+        Ok(rows.into_iter().map(record_from_row).collect())
+    }
+
+    /// Count a hit on a cached response
+    pub async fn record_hit(pool: &sqlx::SqlitePool, cache_key: &str, now: u64) -> AriaResult<()> {
+        sqlx::query("UPDATE llm_response_cache SET hits = hits + 1, last_hit_at = ? WHERE cache_key = ?")
+            .bind(now as i64)
+            .bind(cache_key)
+            .execute(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::Low,
+                &format!("Failed to record LLM cache hit: {}", e)
+            ))?;
+
+        Ok(())
+    }
+
+    /// Drop expired responses, then the least recently used beyond `max_entries`
+    pub async fn evict(pool: &sqlx::SqlitePool, max_entries: usize, now: u64) -> AriaResult<u64> {
+        let expired = sqlx::query("DELETE FROM llm_response_cache WHERE expires_at <= ?")
+            .bind(now as i64)
+            .execute(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to expire cached LLM responses: {}", e)
+            ))?;
+
+        let evicted = sqlx::query(r#"
+            DELETE FROM llm_response_cache
+            WHERE cache_key IN (
+                SELECT cache_key FROM llm_response_cache
+                ORDER BY last_hit_at DESC, hits DESC
+                LIMIT -1 OFFSET ?
+            )
+        "#)
+        .bind(max_entries as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::Medium,
+            &format!("Failed to evict cached LLM responses: {}", e)
+        ))?;
+
+        Ok(expired.rows_affected() + evicted.rows_affected())
+    }
+}
+
+type CachedResponseRow = (String, String, String, Option<String>, Option<String>, i64, i64, i64, i64);
+
+fn record_from_row(row: CachedResponseRow) -> CachedResponseRecord {
+    CachedResponseRecord {
+        cache_key: row.0,
+        scope_key: row.1,
+        response: serde_json::from_str(&row.2).unwrap_or(serde_json::Value::Null),
+        embedding: row.3.and_then(|e| serde_json::from_str(&e).ok()),
+        embedding_model: row.4,
+        created_at: row.5 as u64,
+        last_hit_at: row.6 as u64,
+        hits: row.7 as u32,
+        expires_at: row.8 as u64,
+    }
+}
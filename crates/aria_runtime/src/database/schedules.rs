@@ -0,0 +1,353 @@
+// Schedule Database Operations
+// Cron and interval schedules that launch tasks, kept in their owner's database
+
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// What a fire does while the task of the previous fire is still unfinished
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// Launch nothing for this fire
+    #[default]
+    Skip,
+    /// Launch a task that waits for the previous one to end
+    Queue,
+    /// Cancel the previous task and launch a new one
+    CancelPrevious,
+}
+
+impl OverlapPolicy {
+    /// Parse an overlap_policy column value; unknown values read as skip
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "queue" => OverlapPolicy::Queue,
+            "cancel_previous" => OverlapPolicy::CancelPrevious,
+            _ => OverlapPolicy::Skip,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverlapPolicy::Skip => "skip",
+            OverlapPolicy::Queue => "queue",
+            OverlapPolicy::CancelPrevious => "cancel_previous",
+        }
+    }
+}
+
+/// What happens to fires that were missed while aria was down
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CatchUpPolicy {
+    /// Drop them and wait for the next fire
+    Skip,
+    /// Launch one task for all of them
+    #[default]
+    RunOnce,
+    /// Launch a task for each of them, up to a limit
+    RunAll,
+}
+
+impl CatchUpPolicy {
+    /// Parse a catch_up_policy column value; unknown values read as run_once
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "skip" => CatchUpPolicy::Skip,
+            "run_all" => CatchUpPolicy::RunAll,
+            _ => CatchUpPolicy::RunOnce,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CatchUpPolicy::Skip => "skip",
+            CatchUpPolicy::RunOnce => "run_once",
+            CatchUpPolicy::RunAll => "run_all",
+        }
+    }
+}
+
+/// Schedule record
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScheduleRecord {
+    pub schedule_id: String,
+    pub user_id: String,
+    pub name: String,
+    /// Five-field (minute first) or six-field (second first) cron expression
+    pub cron_expression: Option<String>,
+    pub interval_seconds: Option<u64>,
+    /// IANA timezone the cron expression is read in
+    pub timezone: String,
+    /// Each fire is delayed by up to this many seconds
+    pub jitter_seconds: u64,
+    pub overlap_policy: OverlapPolicy,
+    pub catch_up_policy: CatchUpPolicy,
+
+    // Template of the task launched on every fire
+    pub task_type: String,
+    pub command: Vec<String>,
+    pub environment: HashMap<String, String>,
+    pub container_id: Option<String>,
+    pub session_id: Option<String>,
+    pub priority: i32,
+    /// 0 means no timeout
+    pub timeout_seconds: u64,
+
+    pub enabled: bool,
+    /// When the schedule next fires, before jitter
+    pub next_run_at: Option<u64>,
+    pub last_run_at: Option<u64>,
+    pub last_task_id: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Columns read into a `ScheduleRecord`
+const SCHEDULE_COLUMNS: &str = r#"
+    schedule_id, user_id, name, cron_expression, interval_seconds,
+    timezone, jitter_seconds, overlap_policy, catch_up_policy, task_type,
+    command, environment, container_id, session_id, priority,
+    timeout_seconds, enabled, next_run_at, last_run_at, last_task_id,
+    created_at, updated_at
+"#;
+
+/// Database operations for schedules
+pub struct ScheduleOps;
+
+impl ScheduleOps {
+    /// Store a new schedule, or replace the definition of an existing one
+    pub async fn save_schedule(pool: &sqlx::SqlitePool, schedule: &ScheduleRecord) -> AriaResult<()> {
+        let command_json = serde_json::to_string(&schedule.command)
+            .map_err(|e| AriaError::new(
+                ErrorCode::SerializationError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to serialize command: {}", e)
+            ))?;
+
+        let environment_json = serde_json::to_string(&schedule.environment)
+            .map_err(|e| AriaError::new(
+                ErrorCode::SerializationError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to serialize environment: {}", e)
+            ))?;
+
+        sqlx::query(r#"
+            INSERT INTO task_schedules (
+                schedule_id, user_id, name, cron_expression, interval_seconds,
+                timezone, jitter_seconds, overlap_policy, catch_up_policy, task_type,
+                command, environment, container_id, session_id, priority,
+                timeout_seconds, enabled, next_run_at, last_run_at, last_task_id,
+                created_at, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(schedule_id) DO UPDATE SET
+                name = excluded.name,
+                cron_expression = excluded.cron_expression,
+                interval_seconds = excluded.interval_seconds,
+                timezone = excluded.timezone,
+                jitter_seconds = excluded.jitter_seconds,
+                overlap_policy = excluded.overlap_policy,
+                catch_up_policy = excluded.catch_up_policy,
+                task_type = excluded.task_type,
+                command = excluded.command,
+                environment = excluded.environment,
+                container_id = excluded.container_id,
+                session_id = excluded.session_id,
+                priority = excluded.priority,
+                timeout_seconds = excluded.timeout_seconds,
+                enabled = excluded.enabled,
+                next_run_at = excluded.next_run_at,
+                updated_at = excluded.updated_at
+        "#)
+        .bind(&schedule.schedule_id)
+        .bind(&schedule.user_id)
+        .bind(&schedule.name)
+        .bind(&schedule.cron_expression)
+        .bind(schedule.interval_seconds.map(|s| s as i64))
+        .bind(&schedule.timezone)
+        .bind(schedule.jitter_seconds as i64)
+        .bind(schedule.overlap_policy.as_str())
+        .bind(schedule.catch_up_policy.as_str())
+        .bind(&schedule.task_type)
+        .bind(command_json)
+        .bind(environment_json)
+        .bind(&schedule.container_id)
+        .bind(&schedule.session_id)
+        .bind(schedule.priority as i64)
+        .bind(schedule.timeout_seconds as i64)
+        .bind(schedule.enabled)
+        .bind(schedule.next_run_at.map(|t| t as i64))
+        .bind(schedule.last_run_at.map(|t| t as i64))
+        .bind(&schedule.last_task_id)
+        .bind(schedule.created_at as i64)
+        .bind(schedule.updated_at as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to save schedule: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Get a schedule by ID
+    pub async fn get_schedule(pool: &sqlx::SqlitePool, schedule_id: &str) -> AriaResult<Option<ScheduleRecord>> {
+        let row: Option<ScheduleRow> = sqlx::query_as(&format!("SELECT {} FROM task_schedules WHERE schedule_id = ?", SCHEDULE_COLUMNS))
+            .bind(schedule_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to get schedule: {}", e)
+            ))?;
+
+        Ok(row.map(record_from_row))
+    }
+
+    /// Schedules of a user, by name
+    pub async fn list_schedules(pool: &sqlx::SqlitePool, user_id: &str) -> AriaResult<Vec<ScheduleRecord>> {
+        let rows: Vec<ScheduleRow> = sqlx::query_as(&format!(r#"
+                SELECT {}
+                FROM task_schedules WHERE user_id = ?
+                ORDER BY name ASC, created_at ASC
+            "#, SCHEDULE_COLUMNS))
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to list schedules: {}", e)
+            ))?;
+
+        Ok(rows.into_iter().map(record_from_row).collect())
+    }
+
+    /// Enabled schedules whose next fire is at or before `now`, before jitter
+    pub async fn get_due_schedules(pool: &sqlx::SqlitePool, now: u64) -> AriaResult<Vec<ScheduleRecord>> {
+        let rows: Vec<ScheduleRow> = sqlx::query_as(&format!(r#"
+                SELECT {}
+                FROM task_schedules
+                WHERE enabled = TRUE AND next_run_at IS NOT NULL AND next_run_at <= ?
+                ORDER BY next_run_at ASC
+            "#, SCHEDULE_COLUMNS))
+            .bind(now as i64)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to get due schedules: {}", e)
+            ))?;
+
+        Ok(rows.into_iter().map(record_from_row).collect())
+    }
+
+    /// Record a fire: when it ran, the task it launched if any, and when the schedule fires next
+    pub async fn record_fire(
+        pool: &sqlx::SqlitePool,
+        schedule_id: &str,
+        last_run_at: u64,
+        last_task_id: Option<&str>,
+        next_run_at: Option<u64>,
+    ) -> AriaResult<()> {
+        sqlx::query(r#"
+            UPDATE task_schedules SET
+                last_run_at = ?, last_task_id = COALESCE(?, last_task_id), next_run_at = ?
+            WHERE schedule_id = ?
+        "#)
+        .bind(last_run_at as i64)
+        .bind(last_task_id)
+        .bind(next_run_at.map(|t| t as i64))
+        .bind(schedule_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to record schedule fire: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Delete a schedule; tasks it launched are kept
+    pub async fn delete_schedule(pool: &sqlx::SqlitePool, schedule_id: &str) -> AriaResult<bool> {
+        let result = sqlx::query("DELETE FROM task_schedules WHERE schedule_id = ?")
+            .bind(schedule_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to delete schedule: {}", e)
+            ))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ScheduleRow {
+    schedule_id: String,
+    user_id: String,
+    name: String,
+    cron_expression: Option<String>,
+    interval_seconds: Option<i64>,
+    timezone: String,
+    jitter_seconds: i64,
+    overlap_policy: String,
+    catch_up_policy: String,
+    task_type: String,
+    command: String,
+    environment: Option<String>,
+    container_id: Option<String>,
+    session_id: Option<String>,
+    priority: i64,
+    timeout_seconds: i64,
+    enabled: bool,
+    next_run_at: Option<i64>,
+    last_run_at: Option<i64>,
+    last_task_id: Option<String>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+fn record_from_row(row: ScheduleRow) -> ScheduleRecord {
+    ScheduleRecord {
+        schedule_id: row.schedule_id,
+        user_id: row.user_id,
+        name: row.name,
+        cron_expression: row.cron_expression,
+        interval_seconds: row.interval_seconds.map(|s| s.max(0) as u64),
+        timezone: row.timezone,
+        jitter_seconds: row.jitter_seconds.max(0) as u64,
+        overlap_policy: OverlapPolicy::from_db(&row.overlap_policy),
+        catch_up_policy: CatchUpPolicy::from_db(&row.catch_up_policy),
+        task_type: row.task_type,
+        command: serde_json::from_str(&row.command).unwrap_or_default(),
+        environment: row.environment.as_deref()
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default(),
+        container_id: row.container_id,
+        session_id: row.session_id,
+        priority: row.priority as i32,
+        timeout_seconds: row.timeout_seconds.max(0) as u64,
+        enabled: row.enabled,
+        next_run_at: row.next_run_at.map(|t| t as u64),
+        last_run_at: row.last_run_at.map(|t| t as u64),
+        last_task_id: row.last_task_id,
+        created_at: row.created_at as u64,
+        updated_at: row.updated_at as u64,
+    }
+}
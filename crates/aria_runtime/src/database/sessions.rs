@@ -14,6 +14,8 @@ pub struct SessionRecord {
     pub created_at: u64,
     pub ended_at: Option<u64>,
     pub session_type: String,
+    /// JSON blob for session state
+    pub context_data: Option<String>,
     pub status: String,
     pub total_tool_calls: u32,
     pub total_tokens_used: u32,
@@ -83,10 +85,10 @@ impl SessionOps {
 
     /// Get session by ID
     pub async fn get_session(pool: &sqlx::SqlitePool, session_id: &str) -> AriaResult<SessionRecord> {
-        let row: (String, String, Option<String>, i64, Option<i64>, String, String, i32, i32) = 
+        let row: (String, String, Option<String>, i64, Option<i64>, String, Option<String>, String, i32, i32) = 
             sqlx::query_as(r#"
                 SELECT session_id, user_id, agent_config_id, created_at, ended_at,
-                       session_type, status, total_tool_calls, total_tokens_used
+                       session_type, context_data, status, total_tool_calls, total_tokens_used
                 FROM sessions WHERE session_id = ?
             "#)
             .bind(session_id)
@@ -106,9 +108,52 @@ impl SessionOps {
             created_at: row.3 as u64,
             ended_at: row.4.map(|t| t as u64),
             session_type: row.5,
-            status: row.6,
-            total_tool_calls: row.7 as u32,
-            total_tokens_used: row.8 as u32,
+            context_data: row.6,
+            status: row.7,
+            total_tool_calls: row.8 as u32,
+            total_tokens_used: row.9 as u32,
         })
     }
+
+    /// Add a turn's tool calls and tokens to the session totals
+    pub async fn record_usage(
+        pool: &sqlx::SqlitePool,
+        session_id: &str,
+        tool_calls: u32,
+        tokens_used: u32,
+    ) -> AriaResult<()> {
+        sqlx::query(r#"
+            UPDATE sessions
+            SET total_tool_calls = total_tool_calls + ?, total_tokens_used = total_tokens_used + ?
+            WHERE session_id = ?
+        "#)
+        .bind(tool_calls as i64)
+        .bind(tokens_used as i64)
+        .bind(session_id)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to record session usage: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Number of sessions still active
+    pub async fn count_active_sessions(pool: &sqlx::SqlitePool) -> AriaResult<i64> {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM sessions WHERE status = 'active'")
+            .fetch_one(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to count sessions: {}", e)
+            ))?;
+
+        Ok(count)
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,234 @@
+// Tenant Database Operations
+// Ownership index and quotas for the tenants sharing an aria host
+
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+
+/// Kinds of resource whose owner is recorded in the system database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TenantResource {
+    Session,
+    Task,
+    Schedule,
+    Trigger,
+}
+
+impl TenantResource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TenantResource::Session => "session",
+            TenantResource::Task => "task",
+            TenantResource::Schedule => "schedule",
+            TenantResource::Trigger => "trigger",
+        }
+    }
+}
+
+/// Ownership record for a session or task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantResourceRecord {
+    pub resource_id: String,
+    pub user_id: String,
+    pub session_id: Option<String>,
+    pub container_id: Option<String>,
+    pub created_at: u64,
+}
+
+/// Per-tenant limits; 0 means unlimited
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TenantQuotaRecord {
+    pub user_id: String,
+    pub max_sessions: i64,
+    pub max_containers: i64,
+    pub max_memory_mb: i64,
+    pub updated_at: u64,
+}
+
+/// Database operations for tenant ownership and quotas
+pub struct TenantOps;
+
+impl TenantOps {
+    /// Record who owns a session or task
+    pub async fn record_resource(
+        pool: &sqlx::SqlitePool,
+        kind: TenantResource,
+        resource_id: &str,
+        user_id: &str,
+        session_id: Option<&str>,
+        container_id: Option<&str>,
+    ) -> AriaResult<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        sqlx::query(r#"
+            INSERT INTO tenant_resources (resource_type, resource_id, user_id, session_id, container_id, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+        "#)
+        .bind(kind.as_str())
+        .bind(resource_id)
+        .bind(user_id)
+        .bind(session_id)
+        .bind(container_id)
+        .bind(now as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to record {} owner: {}", kind.as_str(), e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Ownership record of a session or task, if aria created it
+    pub async fn get_resource(
+        pool: &sqlx::SqlitePool,
+        kind: TenantResource,
+        resource_id: &str,
+    ) -> AriaResult<Option<TenantResourceRecord>> {
+        let row: Option<(String, String, Option<String>, Option<String>, i64)> = sqlx::query_as(r#"
+            SELECT resource_id, user_id, session_id, container_id, created_at
+            FROM tenant_resources WHERE resource_type = ? AND resource_id = ?
+        "#)
+        .bind(kind.as_str())
+        .bind(resource_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to get {} owner: {}", kind.as_str(), e)
+        ))?;
+
+        Ok(row.map(Self::resource_from_row))
+    }
+
+    /// Forget the owner of a resource that no longer exists
+    pub async fn remove_resource(pool: &sqlx::SqlitePool, kind: TenantResource, resource_id: &str) -> AriaResult<()> {
+        sqlx::query("DELETE FROM tenant_resources WHERE resource_type = ? AND resource_id = ?")
+            .bind(kind.as_str())
+            .bind(resource_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to remove {} owner: {}", kind.as_str(), e)
+            ))?;
+
+        Ok(())
+    }
+
+    /// Sessions or tasks owned by a user, or by everyone if `user_id` is None
+    pub async fn list_resources(
+        pool: &sqlx::SqlitePool,
+        kind: TenantResource,
+        user_id: Option<&str>,
+    ) -> AriaResult<Vec<TenantResourceRecord>> {
+        let rows: Vec<(String, String, Option<String>, Option<String>, i64)> = sqlx::query_as(r#"
+            SELECT resource_id, user_id, session_id, container_id, created_at
+            FROM tenant_resources
+            WHERE resource_type = ? AND (? IS NULL OR user_id = ?)
+            ORDER BY created_at
+        "#)
+        .bind(kind.as_str())
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to list {} owners: {}", kind.as_str(), e)
+        ))?;
+
+        Ok(rows.into_iter().map(Self::resource_from_row).collect())
+    }
+
+    /// Set a tenant's quota, replacing any previous one
+    pub async fn set_quota(
+        pool: &sqlx::SqlitePool,
+        user_id: &str,
+        max_sessions: i64,
+        max_containers: i64,
+        max_memory_mb: i64,
+    ) -> AriaResult<TenantQuotaRecord> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        sqlx::query(r#"
+            INSERT INTO tenant_quotas (user_id, max_sessions, max_containers, max_memory_mb, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(user_id) DO UPDATE SET
+                max_sessions = excluded.max_sessions,
+                max_containers = excluded.max_containers,
+                max_memory_mb = excluded.max_memory_mb,
+                updated_at = excluded.updated_at
+        "#)
+        .bind(user_id)
+        .bind(max_sessions)
+        .bind(max_containers)
+        .bind(max_memory_mb)
+        .bind(now as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to set tenant quota: {}", e)
+        ))?;
+
+        Ok(TenantQuotaRecord {
+            user_id: user_id.to_string(),
+            max_sessions,
+            max_containers,
+            max_memory_mb,
+            updated_at: now,
+        })
+    }
+
+    /// A tenant's quota, if one has been set
+    pub async fn get_quota(pool: &sqlx::SqlitePool, user_id: &str) -> AriaResult<Option<TenantQuotaRecord>> {
+        let row: Option<(String, i64, i64, i64, i64)> = sqlx::query_as(r#"
+            SELECT user_id, max_sessions, max_containers, max_memory_mb, updated_at
+            FROM tenant_quotas WHERE user_id = ?
+        "#)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to get tenant quota: {}", e)
+        ))?;
+
+        Ok(row.map(|(user_id, max_sessions, max_containers, max_memory_mb, updated_at)| TenantQuotaRecord {
+            user_id,
+            max_sessions,
+            max_containers,
+            max_memory_mb,
+            updated_at: updated_at as u64,
+        }))
+    }
+
+    fn resource_from_row(row: (String, String, Option<String>, Option<String>, i64)) -> TenantResourceRecord {
+        TenantResourceRecord {
+            resource_id: row.0,
+            user_id: row.1,
+            session_id: row.2,
+            container_id: row.3,
+            created_at: row.4 as u64,
+        }
+    }
+}
@@ -0,0 +1,403 @@
+// Trigger Database Operations
+// Webhook and event triggers that launch tasks, kept in their owner's database
+
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// What fires a trigger
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TriggerKind {
+    /// A signed HTTP request to the trigger's webhook URL
+    #[default]
+    Webhook,
+    /// An observability event of the trigger's event type
+    Event,
+}
+
+impl TriggerKind {
+    /// Parse a kind column value; unknown values read as webhook
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "event" => TriggerKind::Event,
+            _ => TriggerKind::Webhook,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TriggerKind::Webhook => "webhook",
+            TriggerKind::Event => "event",
+        }
+    }
+}
+
+/// Trigger record
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TriggerRecord {
+    pub trigger_id: String,
+    pub user_id: String,
+    pub name: String,
+    pub kind: TriggerKind,
+    /// HMAC-SHA256 key webhook payloads must be signed with
+    pub secret: Option<String>,
+    /// Observability event type an event trigger listens to, e.g. "container"
+    pub event_type: Option<String>,
+    /// Payload path -> expected value; all must match for the trigger to fire
+    pub conditions: HashMap<String, String>,
+    /// Template of the key repeated deliveries share
+    pub dedupe_key: Option<String>,
+    pub dedupe_window_seconds: u64,
+    /// 0 means unlimited
+    pub max_fires_per_minute: u32,
+
+    // Template of the task launched on every fire
+    pub task_type: String,
+    pub command: Vec<String>,
+    pub environment: HashMap<String, String>,
+    pub container_id: Option<String>,
+    pub session_id: Option<String>,
+    pub priority: i32,
+    /// 0 means no timeout
+    pub timeout_seconds: u64,
+
+    pub enabled: bool,
+    pub last_fired_at: Option<u64>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Columns read into a `TriggerRecord`
+const TRIGGER_COLUMNS: &str = r#"
+    trigger_id, user_id, name, kind, secret, event_type, conditions,
+    dedupe_key, dedupe_window_seconds, max_fires_per_minute, task_type,
+    command, environment, container_id, session_id, priority,
+    timeout_seconds, enabled, last_fired_at, created_at, updated_at
+"#;
+
+/// Database operations for triggers
+pub struct TriggerOps;
+
+impl TriggerOps {
+    /// Store a new trigger, or replace the definition of an existing one
+    pub async fn save_trigger(pool: &sqlx::SqlitePool, trigger: &TriggerRecord) -> AriaResult<()> {
+        let conditions_json = serde_json::to_string(&trigger.conditions)
+            .map_err(|e| AriaError::new(
+                ErrorCode::SerializationError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to serialize conditions: {}", e)
+            ))?;
+
+        let command_json = serde_json::to_string(&trigger.command)
+            .map_err(|e| AriaError::new(
+                ErrorCode::SerializationError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to serialize command: {}", e)
+            ))?;
+
+        let environment_json = serde_json::to_string(&trigger.environment)
+            .map_err(|e| AriaError::new(
+                ErrorCode::SerializationError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to serialize environment: {}", e)
+            ))?;
+
+        sqlx::query(r#"
+            INSERT INTO task_triggers (
+                trigger_id, user_id, name, kind, secret, event_type, conditions,
+                dedupe_key, dedupe_window_seconds, max_fires_per_minute, task_type,
+                command, environment, container_id, session_id, priority,
+                timeout_seconds, enabled, last_fired_at, created_at, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(trigger_id) DO UPDATE SET
+                name = excluded.name,
+                kind = excluded.kind,
+                secret = excluded.secret,
+                event_type = excluded.event_type,
+                conditions = excluded.conditions,
+                dedupe_key = excluded.dedupe_key,
+                dedupe_window_seconds = excluded.dedupe_window_seconds,
+                max_fires_per_minute = excluded.max_fires_per_minute,
+                task_type = excluded.task_type,
+                command = excluded.command,
+                environment = excluded.environment,
+                container_id = excluded.container_id,
+                session_id = excluded.session_id,
+                priority = excluded.priority,
+                timeout_seconds = excluded.timeout_seconds,
+                enabled = excluded.enabled,
+                updated_at = excluded.updated_at
+        "#)
+        .bind(&trigger.trigger_id)
+        .bind(&trigger.user_id)
+        .bind(&trigger.name)
+        .bind(trigger.kind.as_str())
+        .bind(&trigger.secret)
+        .bind(&trigger.event_type)
+        .bind(conditions_json)
+        .bind(&trigger.dedupe_key)
+        .bind(trigger.dedupe_window_seconds as i64)
+        .bind(trigger.max_fires_per_minute as i64)
+        .bind(&trigger.task_type)
+        .bind(command_json)
+        .bind(environment_json)
+        .bind(&trigger.container_id)
+        .bind(&trigger.session_id)
+        .bind(trigger.priority as i64)
+        .bind(trigger.timeout_seconds as i64)
+        .bind(trigger.enabled)
+        .bind(trigger.last_fired_at.map(|t| t as i64))
+        .bind(trigger.created_at as i64)
+        .bind(trigger.updated_at as i64)
+        .execute(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to save trigger: {}", e)
+        ))?;
+
+        Ok(())
+    }
+
+    /// Get a trigger by ID
+    pub async fn get_trigger(pool: &sqlx::SqlitePool, trigger_id: &str) -> AriaResult<Option<TriggerRecord>> {
+        let row: Option<TriggerRow> = sqlx::query_as(&format!("SELECT {} FROM task_triggers WHERE trigger_id = ?", TRIGGER_COLUMNS))
+            .bind(trigger_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to get trigger: {}", e)
+            ))?;
+
+        Ok(row.map(record_from_row))
+    }
+
+    /// Triggers of a user, by name
+    pub async fn list_triggers(pool: &sqlx::SqlitePool, user_id: &str) -> AriaResult<Vec<TriggerRecord>> {
+        let rows: Vec<TriggerRow> = sqlx::query_as(&format!(r#"
+                SELECT {}
+                FROM task_triggers WHERE user_id = ?
+                ORDER BY name ASC, created_at ASC
+            "#, TRIGGER_COLUMNS))
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to list triggers: {}", e)
+            ))?;
+
+        Ok(rows.into_iter().map(record_from_row).collect())
+    }
+
+    /// Enabled event triggers listening to `event_type`
+    pub async fn get_event_triggers(pool: &sqlx::SqlitePool, event_type: &str) -> AriaResult<Vec<TriggerRecord>> {
+        let rows: Vec<TriggerRow> = sqlx::query_as(&format!(r#"
+                SELECT {}
+                FROM task_triggers
+                WHERE kind = 'event' AND event_type = ? AND enabled = TRUE
+                ORDER BY created_at ASC
+            "#, TRIGGER_COLUMNS))
+            .bind(event_type)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to get event triggers: {}", e)
+            ))?;
+
+        Ok(rows.into_iter().map(record_from_row).collect())
+    }
+
+    /// Number of tasks a trigger launched at or after `since`
+    pub async fn count_fires_since(pool: &sqlx::SqlitePool, trigger_id: &str, since: u64) -> AriaResult<u64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM trigger_fires WHERE trigger_id = ? AND fired_at >= ?")
+            .bind(trigger_id)
+            .bind(since as i64)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to count trigger fires: {}", e)
+            ))?;
+
+        Ok(count.max(0) as u64)
+    }
+
+    /// The task a trigger launched for `dedupe_key` at or after `since`, if any
+    pub async fn find_fire(
+        pool: &sqlx::SqlitePool,
+        trigger_id: &str,
+        dedupe_key: &str,
+        since: u64,
+    ) -> AriaResult<Option<String>> {
+        sqlx::query_scalar(r#"
+                SELECT task_id FROM trigger_fires
+                WHERE trigger_id = ? AND dedupe_key = ? AND fired_at >= ?
+                ORDER BY fired_at DESC LIMIT 1
+            "#)
+            .bind(trigger_id)
+            .bind(dedupe_key)
+            .bind(since as i64)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to find trigger fire: {}", e)
+            ))
+    }
+
+    /// Whether a trigger launched the task `task_id`
+    pub async fn find_task(pool: &sqlx::SqlitePool, trigger_id: &str, task_id: &str) -> AriaResult<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM trigger_fires WHERE trigger_id = ? AND task_id = ?")
+            .bind(trigger_id)
+            .bind(task_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to find trigger task: {}", e)
+            ))?;
+
+        Ok(count > 0)
+    }
+
+    /// Record the task a trigger launched
+    pub async fn record_fire(
+        pool: &sqlx::SqlitePool,
+        trigger_id: &str,
+        dedupe_key: Option<&str>,
+        task_id: &str,
+        fired_at: u64,
+    ) -> AriaResult<()> {
+        sqlx::query("INSERT INTO trigger_fires (fire_id, trigger_id, dedupe_key, task_id, fired_at) VALUES (?, ?, ?, ?, ?)")
+            .bind(Uuid::new_v4().to_string())
+            .bind(trigger_id)
+            .bind(dedupe_key)
+            .bind(task_id)
+            .bind(fired_at as i64)
+            .execute(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to record trigger fire: {}", e)
+            ))?;
+
+        sqlx::query("UPDATE task_triggers SET last_fired_at = ? WHERE trigger_id = ?")
+            .bind(fired_at as i64)
+            .bind(trigger_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to update trigger: {}", e)
+            ))?;
+
+        Ok(())
+    }
+
+    /// Delete a trigger and its fire history; tasks it launched are kept
+    pub async fn delete_trigger(pool: &sqlx::SqlitePool, trigger_id: &str) -> AriaResult<bool> {
+        sqlx::query("DELETE FROM trigger_fires WHERE trigger_id = ?")
+            .bind(trigger_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to delete trigger fires: {}", e)
+            ))?;
+
+        let result = sqlx::query("DELETE FROM task_triggers WHERE trigger_id = ?")
+            .bind(trigger_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to delete trigger: {}", e)
+            ))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct TriggerRow {
+    trigger_id: String,
+    user_id: String,
+    name: String,
+    kind: String,
+    secret: Option<String>,
+    event_type: Option<String>,
+    conditions: Option<String>,
+    dedupe_key: Option<String>,
+    dedupe_window_seconds: i64,
+    max_fires_per_minute: i64,
+    task_type: String,
+    command: String,
+    environment: Option<String>,
+    container_id: Option<String>,
+    session_id: Option<String>,
+    priority: i64,
+    timeout_seconds: i64,
+    enabled: bool,
+    last_fired_at: Option<i64>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+fn record_from_row(row: TriggerRow) -> TriggerRecord {
+    TriggerRecord {
+        trigger_id: row.trigger_id,
+        user_id: row.user_id,
+        name: row.name,
+        kind: TriggerKind::from_db(&row.kind),
+        secret: row.secret,
+        event_type: row.event_type,
+        conditions: row.conditions.as_deref()
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default(),
+        dedupe_key: row.dedupe_key,
+        dedupe_window_seconds: row.dedupe_window_seconds.max(0) as u64,
+        max_fires_per_minute: row.max_fires_per_minute.clamp(0, u32::MAX as i64) as u32,
+        task_type: row.task_type,
+        command: serde_json::from_str(&row.command).unwrap_or_default(),
+        environment: row.environment.as_deref()
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default(),
+        container_id: row.container_id,
+        session_id: row.session_id,
+        priority: row.priority as i32,
+        timeout_seconds: row.timeout_seconds.max(0) as u64,
+        enabled: row.enabled,
+        last_fired_at: row.last_fired_at.map(|t| t as u64),
+        created_at: row.created_at as u64,
+        updated_at: row.updated_at as u64,
+    }
+}
@@ -13,6 +13,8 @@ pub struct UserRecord {
     pub created_at: u64,
     pub last_active: Option<u64>,
     pub status: String,
+    /// user or admin; admins see every tenant's resources
+    pub role: String,
 }
 
 /// Database operations for users
@@ -53,9 +55,9 @@ impl UserOps {
 
     /// Get user by ID
     pub async fn get_user(pool: &sqlx::SqlitePool, user_id: &str) -> AriaResult<UserRecord> {
-        let row: (String, String, Option<String>, i64, Option<i64>, String) = 
+        let row: (String, String, Option<String>, i64, Option<i64>, String, String) = 
             sqlx::query_as(r#"
-                SELECT user_id, username, email, created_at, last_active, status
+                SELECT user_id, username, email, created_at, last_active, status, role
                 FROM users WHERE user_id = ?
             "#)
             .bind(user_id)
@@ -75,6 +77,57 @@ impl UserOps {
             created_at: row.3 as u64,
             last_active: row.4.map(|t| t as u64),
             status: row.5,
+            role: row.6,
         })
     }
-} 
\ No newline at end of file
+
+    /// Set a user's role; returns whether the user exists
+    pub async fn set_role(pool: &sqlx::SqlitePool, user_id: &str, role: &str) -> AriaResult<bool> {
+        let result = sqlx::query("UPDATE users SET role = ? WHERE user_id = ?")
+            .bind(role)
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to set user role: {}", e)
+            ))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// IDs of active users holding a role
+    pub async fn list_users_with_role(pool: &sqlx::SqlitePool, role: &str) -> AriaResult<Vec<String>> {
+        let rows: Vec<(String,)> = sqlx::query_as(r#"
+            SELECT user_id FROM users WHERE role = ? AND status = 'active'
+        "#)
+        .bind(role)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AriaError::new(
+            ErrorCode::DatabaseError,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to list users: {}", e)
+        ))?;
+
+        Ok(rows.into_iter().map(|(user_id,)| user_id).collect())
+    }
+
+    /// IDs of every user, including deactivated ones
+    pub async fn list_user_ids(pool: &sqlx::SqlitePool) -> AriaResult<Vec<String>> {
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT user_id FROM users ORDER BY user_id")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| AriaError::new(
+                ErrorCode::DatabaseError,
+                ErrorCategory::System,
+                ErrorSeverity::High,
+                &format!("Failed to list users: {}", e)
+            ))?;
+
+        Ok(rows.into_iter().map(|(user_id,)| user_id).collect())
+    }
+}
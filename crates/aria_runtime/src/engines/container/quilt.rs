@@ -1,9 +1,11 @@
 use std::collections::HashMap;
+use tonic::service::interceptor::InterceptedService;
 use tonic::transport::{Channel, Endpoint, Uri};
 use tower::service_fn;
 use hyper_util::rt::TokioIo;
 use crate::engines::config::QuiltConfig;
 use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::telemetry::TraceContextInterceptor;
 use crate::types::{ContainerExecutionResult, ContainerState, ContainerStatus, ResourceUsage};
 
 // This module will be generated by `tonic-build` in `build.rs`
@@ -24,9 +26,10 @@ use quilt_proto::{
 ///
 /// This service acts as a high-level wrapper around the gRPC client,
 /// providing a convenient API for the Aria runtime to manage container lifecycles.
+/// Calls carry the caller's trace context, so quiltd's spans join the same trace.
 #[derive(Clone)]
 pub struct QuiltService {
-    client: QuiltServiceClient<Channel>,
+    client: QuiltServiceClient<InterceptedService<Channel, TraceContextInterceptor>>,
 }
 
 impl QuiltService {
@@ -46,11 +49,12 @@ impl QuiltService {
             }))
             .await?;
 
-        let client = QuiltServiceClient::new(channel);
+        let client = QuiltServiceClient::with_interceptor(channel, TraceContextInterceptor);
         Ok(Self { client })
     }
 
     /// Creates a new container (agent must explicitly start it).
+    #[tracing::instrument(name = "quilt.create_container", skip_all)]
     pub async fn create_container(
         &mut self,
         image_path: String,
@@ -65,6 +69,13 @@ impl QuiltService {
             ..Default::default()
         };
 
+        self.create_container_with(request).await
+    }
+
+    /// Creates a container from a fully specified request, for callers that need
+    /// resource limits, volumes or labels beyond the basic `create_container` call.
+    #[tracing::instrument(name = "quilt.create_container_with", skip_all)]
+    pub async fn create_container_with(&mut self, request: CreateContainerRequest) -> AriaResult<String> {
         let response = self.client.create_container(request).await.map_err(to_aria_error)?;
         let res = response.into_inner();
 
@@ -81,6 +92,7 @@ impl QuiltService {
     }
 
     /// Starts a created container.
+    #[tracing::instrument(name = "quilt.start_container", skip_all, fields(container_id = %container_id))]
     pub async fn start_container(&mut self, container_id: String) -> AriaResult<()> {
         let request = StartContainerRequest { container_id };
         let response = self.client.start_container(request).await.map_err(to_aria_error)?;
@@ -99,6 +111,7 @@ impl QuiltService {
     }
 
     /// Stops a running container.
+    #[tracing::instrument(name = "quilt.stop_container", skip_all, fields(container_id = %container_id))]
     pub async fn stop_container(&mut self, container_id: String) -> AriaResult<()> {
         let request = StopContainerRequest { container_id, timeout_seconds: 10 };
         let response = self.client.stop_container(request).await.map_err(to_aria_error)?;
@@ -117,6 +130,7 @@ impl QuiltService {
     }
 
     /// Removes a container.
+    #[tracing::instrument(name = "quilt.remove_container", skip_all, fields(container_id = %container_id))]
     pub async fn remove_container(&mut self, container_id: String) -> AriaResult<()> {
         let request = RemoveContainerRequest {
             container_id,
@@ -138,6 +152,7 @@ impl QuiltService {
     }
 
     /// Executes a command inside a running container.
+    #[tracing::instrument(name = "quilt.exec_in_container", skip_all, fields(container_id = %container_id))]
     pub async fn exec_in_container(
         &mut self,
         container_id: String,
@@ -171,7 +186,46 @@ impl QuiltService {
         }
     }
 
+    /// Runs a command inside a running container and returns its output whatever
+    /// the exit code. Only failures to run the command at all are errors, so
+    /// callers can inspect stderr and the exit code of commands that failed.
+    #[tracing::instrument(name = "quilt.run_in_container", skip_all, fields(container_id = %container_id))]
+    pub async fn run_in_container(
+        &mut self,
+        container_id: String,
+        command: Vec<String>,
+    ) -> AriaResult<ContainerExecutionResult> {
+        let request = ExecContainerRequest {
+            container_id,
+            command,
+            capture_output: true,
+            ..Default::default()
+        };
+
+        let started = std::time::Instant::now();
+        let response = self.client.exec_container(request).await.map_err(to_aria_error)?;
+        let res = response.into_inner();
+
+        if !res.success && res.exit_code < 0 {
+            return Err(AriaError::new(
+                ErrorCode::ContainerOperationFailed,
+                ErrorCategory::Container,
+                ErrorSeverity::High,
+                &format!("Failed to run command in container: {}", res.error_message),
+            ));
+        }
+
+        Ok(ContainerExecutionResult {
+            exit_code: res.exit_code,
+            stdout: res.stdout,
+            stderr: res.stderr,
+            execution_time_ms: started.elapsed().as_millis() as u64,
+            resource_usage: None,
+        })
+    }
+
     /// Retrieves the status of a container.
+    #[tracing::instrument(name = "quilt.get_container_status", skip_all, fields(container_id = %container_id))]
     pub async fn get_container_status(&mut self, container_id: String) -> AriaResult<ContainerStatus> {
         let request = GetContainerStatusRequest { container_id };
         let response = self.client.get_container_status(request).await.map_err(to_aria_error)?;
@@ -202,7 +256,40 @@ impl QuiltService {
         })
     }
 
+    /// Polls a started container until it is running. Fails with the container's
+    /// logs if it exits during startup, or once `timeout` has passed.
+    pub async fn wait_until_running(&mut self, container_id: &str, timeout: std::time::Duration) -> AriaResult<()> {
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            let status = self.get_container_status(container_id.to_string()).await?;
+            match status.state {
+                ContainerState::Running => return Ok(()),
+                ContainerState::Exited | ContainerState::Failed => {
+                    let logs = self.get_container_logs(container_id.to_string()).await.unwrap_or_default();
+                    return Err(AriaError::new(
+                        ErrorCode::ContainerOperationFailed,
+                        ErrorCategory::Container,
+                        ErrorSeverity::High,
+                        &format!("Container {} entered state {:?} during startup: {}", container_id, status.state, logs.trim()),
+                    ));
+                }
+                _ => {}
+            }
+
+            if std::time::Instant::now() >= deadline {
+                return Err(AriaError::new(
+                    ErrorCode::Timeout,
+                    ErrorCategory::Container,
+                    ErrorSeverity::High,
+                    &format!("Container {} did not start within {}s", container_id, timeout.as_secs()),
+                ));
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        }
+    }
+
     /// Retrieves logs from a container.
+    #[tracing::instrument(name = "quilt.get_container_logs", skip_all, fields(container_id = %container_id))]
     pub async fn get_container_logs(&mut self, container_id: String) -> AriaResult<String> {
         let request = GetContainerLogsRequest { container_id };
         let response = self.client.get_container_logs(request).await.map_err(to_aria_error)?;
@@ -210,6 +297,7 @@ impl QuiltService {
         Ok(logs.join("\n"))
     }
 
+    #[tracing::instrument(name = "quilt.list_containers", skip_all)]
     pub async fn list_containers(&mut self) -> AriaResult<Vec<quilt_proto::ContainerInfo>> {
         let request = ListContainersRequest {
             state_filter: quilt_proto::ContainerStatus::Unspecified.into(),
@@ -218,18 +306,54 @@ impl QuiltService {
         Ok(response.into_inner().containers)
     }
 
+    // === Owner Quotas ===
+
+    /// Set aggregate container limits for an owner (0 = unlimited)
+    #[tracing::instrument(name = "quilt.set_owner_quota", skip_all, fields(owner = %owner))]
+    pub async fn set_owner_quota(
+        &mut self,
+        owner: String,
+        max_containers: i64,
+        max_memory_mb: i64,
+    ) -> AriaResult<quilt_proto::OwnerQuotaInfo> {
+        let request = quilt_proto::SetOwnerQuotaRequest { owner, max_containers, max_memory_mb };
+        let response = self.client.set_owner_quota(request).await.map_err(to_aria_error)?;
+        let res = response.into_inner();
+
+        match res.quota {
+            Some(quota) if res.success => Ok(quota),
+            _ => Err(AriaError::new(
+                ErrorCode::ContainerOperationFailed,
+                ErrorCategory::Container,
+                ErrorSeverity::Medium,
+                &format!("Failed to set owner quota: {}", res.error_message),
+            )),
+        }
+    }
+
+    /// Get an owner's quota and the containers currently charged to it
+    #[tracing::instrument(name = "quilt.get_owner_quota", skip_all, fields(owner = %owner))]
+    pub async fn get_owner_quota(&mut self, owner: String) -> AriaResult<quilt_proto::GetOwnerQuotaResponse> {
+        let request = quilt_proto::GetOwnerQuotaRequest { owner };
+        let response = self.client.get_owner_quota(request).await.map_err(to_aria_error)?;
+        Ok(response.into_inner())
+    }
+
+    #[tracing::instrument(name = "quilt.get_system_metrics", skip_all)]
     pub async fn get_system_metrics(&mut self) -> AriaResult<quilt_proto::GetSystemMetricsResponse> {
         let request = GetSystemMetricsRequest {};
         let response = self.client.get_system_metrics(request).await.map_err(to_aria_error)?;
         Ok(response.into_inner())
     }
 
+    #[tracing::instrument(name = "quilt.get_network_topology", skip_all)]
     pub async fn get_network_topology(&mut self) -> AriaResult<Vec<quilt_proto::NetworkNode>> {
         let request = GetNetworkTopologyRequest {};
         let response = self.client.get_network_topology(request).await.map_err(to_aria_error)?;
         Ok(response.into_inner().nodes)
     }
 
+    #[tracing::instrument(name = "quilt.get_container_network_info", skip_all, fields(container_id = %container_id))]
     pub async fn get_container_network_info(&mut self, container_id: String) -> AriaResult<quilt_proto::GetContainerNetworkInfoResponse> {
         let request = GetContainerNetworkInfoRequest { container_id };
         let response = self.client.get_container_network_info(request).await.map_err(to_aria_error)?;
@@ -239,6 +363,7 @@ impl QuiltService {
     // === Async Task Management ===
 
     /// Execute a command asynchronously in a container
+    #[tracing::instrument(name = "quilt.exec_container_async", skip_all, fields(container_id = %container_id))]
     pub async fn exec_container_async(
         &mut self,
         container_id: String,
@@ -270,6 +395,7 @@ impl QuiltService {
     }
 
     /// Get the status of an async task
+    #[tracing::instrument(name = "quilt.get_task_status", skip_all, fields(task_id = %task_id))]
     pub async fn get_task_status(&mut self, task_id: String) -> AriaResult<quilt_proto::GetTaskStatusResponse> {
         let request = quilt_proto::GetTaskStatusRequest { task_id };
         let response = self.client.get_task_status(request).await.map_err(to_aria_error)?;
@@ -277,6 +403,7 @@ impl QuiltService {
     }
 
     /// Get the result of a completed async task
+    #[tracing::instrument(name = "quilt.get_task_result", skip_all, fields(task_id = %task_id))]
     pub async fn get_task_result(&mut self, task_id: String) -> AriaResult<quilt_proto::GetTaskResultResponse> {
         let request = quilt_proto::GetTaskResultRequest { task_id };
         let response = self.client.get_task_result(request).await.map_err(to_aria_error)?;
@@ -284,6 +411,7 @@ impl QuiltService {
     }
 
     /// List tasks for a container
+    #[tracing::instrument(name = "quilt.list_tasks", skip_all, fields(container_id = %container_id))]
     pub async fn list_tasks(&mut self, container_id: String, status_filter: Option<quilt_proto::TaskStatus>) -> AriaResult<Vec<quilt_proto::TaskInfo>> {
         let request = quilt_proto::ListTasksRequest {
             container_id,
@@ -294,6 +422,7 @@ impl QuiltService {
     }
 
     /// Cancel a running async task
+    #[tracing::instrument(name = "quilt.cancel_task", skip_all, fields(task_id = %task_id))]
     pub async fn cancel_task(&mut self, task_id: String) -> AriaResult<bool> {
         let request = quilt_proto::CancelTaskRequest { task_id };
         let response = self.client.cancel_task(request).await.map_err(to_aria_error)?;
@@ -312,6 +441,7 @@ impl QuiltService {
     }
 
     /// Upload a bundle via streaming
+    #[tracing::instrument(name = "quilt.upload_bundle", skip_all)]
     pub async fn upload_bundle<S>(&mut self, stream: S) -> AriaResult<quilt_proto::UploadBundleResponse>
     where
         S: tonic::codegen::tokio_stream::Stream<Item = quilt_proto::UploadBundleRequest> + Send + 'static,
@@ -323,6 +453,15 @@ impl QuiltService {
 
 /// Helper function to convert tonic::Status to AriaError
 fn to_aria_error(status: tonic::Status) -> AriaError {
+    if status.code() == tonic::Code::ResourceExhausted {
+        // quiltd refuses containers past their owner's quota with this code
+        return AriaError::new(
+            ErrorCode::QuotaExceeded,
+            ErrorCategory::Security,
+            ErrorSeverity::Medium,
+            status.message(),
+        );
+    }
     AriaError::new(
         ErrorCode::UpstreamServiceError,
         ErrorCategory::Network,
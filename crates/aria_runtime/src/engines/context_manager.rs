@@ -32,6 +32,7 @@ impl ContextManagerEngine {
         let max_memory_size = agent_config.memory_limit.unwrap_or(512 * 1024 * 1024); // 512MB default
         let context = RuntimeContext {
             session_id: crate::deep_size::DeepUuid(uuid::Uuid::new_v4()),
+            user_id: None,
             agent_config,
             created_at: std::time::SystemTime::now()
                 .duration_since(std::time::SystemTime::UNIX_EPOCH)
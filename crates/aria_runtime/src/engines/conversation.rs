@@ -1,10 +1,12 @@
 use crate::deep_size::DeepUuid;
 use crate::engines::llm::types::{LLMConfig, LLMMessage, LLMRequest};
+use crate::engines::llm::context_window::{ContextWindow, SummarizeMiddle};
 use crate::engines::llm::{LLMHandler, LLMHandlerInterface};
 use crate::engines::{ConversationEngineInterface, Engine};
 use crate::errors::AriaResult;
 use crate::types::*;
 use async_trait::async_trait;
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// Conversation engine for managing conversational flow with LLM integration
@@ -49,6 +51,7 @@ impl ConversationEngine {
                 tools: None,
                 tool_choice: None,
                 stream: Some(false),
+                cache: None,
             };
 
             match llm_handler.complete(request).await {
@@ -63,45 +66,37 @@ impl ConversationEngine {
     /// Generate final summary using LLM (matching Symphony's conclude method)
     async fn generate_final_summary(&self, conversation: &ConversationJSON, context: &RuntimeContext) -> AriaResult<String> {
         if let Some(ref llm_handler) = self.llm_handler {
-            // Create conversation history for summary
-            let history = conversation.turns.iter()
-                .map(|turn| format!("{}: {}", 
-                    match turn.role {
-                        ConversationRole::User => "user",
-                        ConversationRole::Assistant => "assistant",
-                        ConversationRole::System => "system",
-                        ConversationRole::Tool => "tool",
-                        ConversationRole::Agent => "agent",
-                        ConversationRole::Container => "container",
-                    },
-                    turn.content
-                ))
-                .collect::<Vec<_>>()
-                .join("\n");
-
             let summary_prompt = format!(
-                "Based on the following conversation and execution context, provide a concise summary of what was accomplished:\n\n{}\n\nExecution Summary:\n- Steps completed: {}\n- Success rate: {:.1}%\n- Agent: {}",
-                history,
+                "Based on the conversation above and the execution context, provide a concise summary of what was accomplished.\n\nExecution Summary:\n- Steps completed: {}\n- Success rate: {:.1}%\n- Agent: {}",
                 context.execution_history.len(),
                 (context.execution_history.iter().filter(|s| s.success).count() as f32 / context.execution_history.len().max(1) as f32) * 100.0,
                 context.agent_config.name
             );
 
+            // One message per turn so long sessions can be summarized down to the model's context window
+            let mut messages = vec![LLMMessage {
+                role: "system".to_string(),
+                content: "You are an expert at summarizing task completion. Provide a clear, concise summary of what was accomplished.".to_string(),
+                tool_calls: None,
+                tool_call_id: None,
+            }];
+            messages.extend(conversation.turns.iter().map(turn_to_message));
+            messages.push(LLMMessage {
+                role: "user".to_string(),
+                content: summary_prompt,
+                tool_calls: None,
+                tool_call_id: None,
+            });
+
+            let window = ContextWindow::for_model(&context.agent_config.llm.model, 300)
+                .with_strategy(Arc::new(SummarizeMiddle::extractive()));
+            let messages = match window.fit(messages).await {
+                Ok(messages) => messages,
+                Err(_) => return Ok("I was unable to summarize my work, but the task execution is complete.".to_string()),
+            };
+
             let request = LLMRequest {
-                messages: vec![
-                    LLMMessage {
-                        role: "system".to_string(),
-                        content: "You are an expert at summarizing task completion. Provide a clear, concise summary of what was accomplished.".to_string(),
-                        tool_calls: None,
-                        tool_call_id: None,
-                    },
-                    LLMMessage {
-                        role: "user".to_string(),
-                        content: summary_prompt,
-                        tool_calls: None,
-                        tool_call_id: None,
-                    },
-                ],
+                messages,
                 config: LLMConfig {
                     model: Some(context.agent_config.llm.model.clone()),
                     temperature: 0.5,
@@ -114,6 +109,7 @@ impl ConversationEngine {
                 tools: None,
                 tool_choice: None,
                 stream: Some(false),
+                cache: None,
             };
 
             match llm_handler.complete(request).await {
@@ -126,6 +122,25 @@ impl ConversationEngine {
     }
 }
 
+/// Map a conversation turn onto a chat message; roles the provider does not
+/// accept as plain messages are folded into assistant turns with a label
+fn turn_to_message(turn: &ConversationTurn) -> LLMMessage {
+    let (role, content) = match turn.role {
+        ConversationRole::User => ("user", turn.content.clone()),
+        ConversationRole::Assistant => ("assistant", turn.content.clone()),
+        ConversationRole::System => ("system", turn.content.clone()),
+        ConversationRole::Tool => ("assistant", format!("[tool] {}", turn.content)),
+        ConversationRole::Agent => ("assistant", format!("[agent] {}", turn.content)),
+        ConversationRole::Container => ("assistant", format!("[container] {}", turn.content)),
+    };
+    LLMMessage {
+        role: role.to_string(),
+        content,
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
+
 impl Engine for ConversationEngine {
     fn initialize(&self) -> bool {
         true
@@ -1,8 +1,8 @@
 use async_trait::async_trait;
 use crate::engines::{ExecutionEngineInterface, ContainerManagerInterface, Engine};
-use crate::engines::llm::types::{LLMConfig, LLMMessage, LLMRequest};
-use crate::engines::llm::LLMHandler;
-use crate::engines::tool_registry::ToolRegistryInterface;
+use crate::engines::llm::types::{LLMConfig, LLMMessage, LLMRequest, Tool, ToolCall, ToolChoice};
+use crate::engines::llm::{LLMHandler, LLMProvider};
+use crate::engines::tool_registry::{ToolCallContext, ToolRegistryInterface};
 use crate::engines::system_prompt::SystemPromptService;
 use crate::engines::container::quilt::QuiltService;
 use crate::types::*;
@@ -17,6 +17,9 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use crate::deep_size::{DeepUuid, DeepValue};
 use futures::future::BoxFuture;
 
+/// Model turns allowed in the function-calling loop when the agent sets no `max_iterations`
+const DEFAULT_MAX_TOOL_ITERATIONS: usize = 10;
+
 /// The ExecutionEngine is responsible for the core "magic" of tool execution.
 /// It preserves Symphony's brilliant unconscious tool execution logic while
 /// adding container orchestration capabilities.
@@ -215,13 +218,205 @@ impl ExecutionEngine {
         env
     }
 
+    /// Memories the runtime recalled into working memory for this task
+    async fn recalled_memories(agent_config: &AgentConfig, context: &RuntimeContext) -> Vec<WorkingMemoryEntry> {
+        if !agent_config.memory_enabled.unwrap_or(false) {
+            return Vec::new();
+        }
+        let working_memory = context.working_memory.read().await;
+        let mut memories: Vec<WorkingMemoryEntry> = working_memory.iter()
+            .filter(|(key, _)| key.starts_with(crate::memory::RECALLED_MEMORY_PREFIX))
+            .filter_map(|(_, value)| serde_json::from_value(value.0.clone()).ok())
+            .collect();
+        memories.sort_by(|a, b| a.key.cmp(&b.key));
+        memories
+    }
+
+    /// Whether the default provider accepts tools as native function definitions.
+    /// Providers register lazily, so an unregistered default is assumed capable
+    /// and any real failure surfaces from `complete`.
+    fn supports_native_tools(&self) -> bool {
+        self.llm_handler.get_provider(None)
+            .map(|provider| provider.supports_functions())
+            .unwrap_or(true)
+    }
+
+    /// Describe the agent's registry tools as JSON-schema functions
+    async fn tool_definitions(&self, agent_config: &AgentConfig) -> Vec<Tool> {
+        let mut tools = Vec::with_capacity(agent_config.tools.len());
+        for name in &agent_config.tools {
+            match self.tool_registry.get_tool_info(name).await {
+                Ok(Some(entry)) => tools.push(tool_definition(&entry)),
+                Ok(None) => tracing::warn!("Tool '{}' is not registered; not advertising it", name),
+                Err(e) => tracing::warn!("Failed to describe tool '{}': {}", name, e),
+            }
+        }
+        tools
+    }
+
+    /// Run one tool call returned by the model and record its outcome.
+    /// Failures are reported back to the model rather than aborting the loop.
+    #[tracing::instrument(name = "aria.tool_call", skip_all, fields(tool = %call.name))]
+    async fn execute_tool_call(&self, call: &ToolCall, agent_config: &AgentConfig, caller: &ToolCallContext) -> Value {
+        let arguments = parse_tool_arguments(&call.arguments);
+        let outcome = match &arguments {
+            _ if !agent_config.tools.contains(&call.name) => Err(format!(
+                "Agent '{}' is not authorized to use tool '{}'", agent_config.name, call.name
+            )),
+            Err(e) => Err(e.clone()),
+            Ok(parameters) => self.tool_registry
+                .execute_tool_as(&call.name, parameters.clone().into(), caller)
+                .await
+                .map_err(|e| e.to_string()),
+        };
+
+        let parameters = arguments.unwrap_or_else(|_| Value::String(call.arguments.clone()));
+        match outcome {
+            Ok(result) => serde_json::json!({
+                "id": call.id,
+                "name": call.name,
+                "parameters": parameters,
+                "success": result.success,
+                "result": result.result,
+                "error": result.error,
+            }),
+            Err(error) => serde_json::json!({
+                "id": call.id,
+                "name": call.name,
+                "parameters": parameters,
+                "success": false,
+                "result": Value::Null,
+                "error": error,
+            }),
+        }
+    }
+
+    /// Agent loop over native function calling: advertise the agent's tools,
+    /// execute the calls the model returns, feed results back as `tool`
+    /// messages, and stop at a final answer or after `max_iterations` turns
+    async fn execute_with_native_tools(
+        &self,
+        task: &str,
+        agent_config: &AgentConfig,
+        context: &RuntimeContext,
+    ) -> AriaResult<ToolResult> {
+        let started = std::time::Instant::now();
+        let memories = Self::recalled_memories(agent_config, context).await;
+        // Tools travel as function definitions, so the prompt leaves out the JSON tool protocol
+        let system_prompt = self.system_prompt_service
+            .generate_system_prompt_with_memories(agent_config, false, &memories);
+        let tools = self.tool_definitions(agent_config).await;
+        let caller = ToolCallContext::from_runtime_context(context);
+        let max_iterations = agent_config.max_iterations
+            .map(|n| n.max(1) as usize)
+            .unwrap_or(DEFAULT_MAX_TOOL_ITERATIONS);
+
+        let mut messages = vec![
+            LLMMessage {
+                role: "system".to_string(),
+                content: system_prompt,
+                tool_calls: None,
+                tool_call_id: None,
+            },
+            LLMMessage {
+                role: "user".to_string(),
+                content: task.to_string(),
+                tool_calls: None,
+                tool_call_id: None,
+            },
+        ];
+        let mut tools_executed: Vec<Value> = Vec::new();
+        let mut final_response: Option<String> = None;
+        let mut iterations = 0;
+
+        while iterations < max_iterations {
+            iterations += 1;
+
+            let request = LLMRequest {
+                messages: messages.clone(),
+                config: LLMConfig {
+                    model: Some(agent_config.llm.model.clone()),
+                    temperature: agent_config.llm.temperature.unwrap_or(0.7),
+                    max_tokens: agent_config.llm.max_tokens.unwrap_or(2000),
+                    top_p: None,
+                    frequency_penalty: None,
+                    presence_penalty: None,
+                },
+                provider: None,
+                tools: if tools.is_empty() { None } else { Some(tools.clone()) },
+                tool_choice: if tools.is_empty() { None } else { Some(ToolChoice::Auto) },
+                stream: Some(false),
+                cache: None,
+            };
+            let response = self.llm_handler.complete(request).await?;
+
+            let calls = response.tool_calls.clone().unwrap_or_default();
+            if calls.is_empty() {
+                final_response = Some(response.content);
+                break;
+            }
+
+            messages.push(LLMMessage {
+                role: "assistant".to_string(),
+                content: response.content,
+                tool_calls: Some(calls.clone()),
+                tool_call_id: None,
+            });
+
+            // Calls returned in the same turn cannot depend on each other, so run them concurrently
+            let outcomes = futures::future::join_all(
+                calls.iter().map(|call| self.execute_tool_call(call, agent_config, &caller))
+            ).await;
+
+            for (call, outcome) in calls.iter().zip(outcomes) {
+                messages.push(LLMMessage {
+                    role: "tool".to_string(),
+                    content: serde_json::json!({
+                        "success": outcome["success"],
+                        "result": outcome["result"],
+                        "error": outcome["error"],
+                    }).to_string(),
+                    tool_calls: None,
+                    tool_call_id: Some(call.id.clone()),
+                });
+                tools_executed.push(outcome);
+            }
+        }
+
+        let success = final_response.is_some();
+        let error = if success {
+            None
+        } else {
+            Some(format!("Tool loop reached max_iterations ({}) without a final answer", max_iterations))
+        };
+
+        Ok(ToolResult {
+            success,
+            result: Some(serde_json::json!({
+                "response": final_response.unwrap_or_default(),
+                "reasoning": format!(
+                    "Completed in {} model turns with {} tool calls",
+                    iterations,
+                    tools_executed.len()
+                ),
+                "agent": agent_config.name,
+                "tools_executed": tools_executed,
+                "iterations": iterations,
+            }).into()),
+            error,
+            metadata: HashMap::new(),
+            execution_time_ms: started.elapsed().as_millis() as u64,
+            resource_usage: None,
+        })
+    }
+
     /// Execute with orchestration for multi-tool workflows
     /// This preserves Symphony's brilliant multi-tool orchestration logic
     async fn execute_with_orchestration(
         &self,
         task: &str,
         agent_config: &AgentConfig,
-        _context: &RuntimeContext,
+        context: &RuntimeContext,
     ) -> AriaResult<ToolResult> {
         let mut conversation_history = Vec::new();
         let mut all_tool_results = Vec::new();
@@ -229,9 +424,12 @@ impl ExecutionEngine {
         let mut primary_error: Option<String> = None;
         let mut final_response = String::new();
         let mut orchestration_step = 0;
+        let caller = ToolCallContext::from_runtime_context(context);
 
         // Generate sophisticated orchestration prompt using SystemPromptService
-        let orchestration_prompt = self.system_prompt_service.generate_orchestration_prompt(task, agent_config);
+        let memories = Self::recalled_memories(agent_config, context).await;
+        let orchestration_prompt = self.system_prompt_service
+            .generate_orchestration_prompt_with_memories(task, agent_config, &memories);
         
         conversation_history.push(LLMMessage {
             role: "system".to_string(),
@@ -254,6 +452,7 @@ impl ExecutionEngine {
             let step_result = self.execute_single_orchestration_step(
                 &conversation_history,
                 agent_config,
+                &caller,
             ).await?;
             
             if !step_result.success {
@@ -343,10 +542,12 @@ impl ExecutionEngine {
     }
 
     /// Execute a single step in the orchestration flow
+    #[tracing::instrument(name = "aria.step", skip_all, fields(agent = %agent_config.name))]
     async fn execute_single_orchestration_step(
         &self,
         conversation_history: &[LLMMessage],
         agent_config: &AgentConfig,
+        caller: &ToolCallContext,
     ) -> AriaResult<OrchestrationStepResult> {
         let llm_request = LLMRequest {
             messages: conversation_history.to_vec(),
@@ -362,6 +563,7 @@ impl ExecutionEngine {
             tools: None,
             tool_choice: None,
             stream: Some(false),
+            cache: None,
         };
 
         let llm_response = self.llm_handler.complete(llm_request).await?;
@@ -385,9 +587,10 @@ impl ExecutionEngine {
             if tool_name != "none" {
                 if let Some(parameters) = parameters {
                     // Execute the tool
-                    let tool_result = self.tool_registry.execute_tool(
+                    let tool_result = self.tool_registry.execute_tool_as(
                         tool_name,
                         parameters.clone().into(),
+                        caller,
                     ).await?;
                     
                     return Ok(OrchestrationStepResult {
@@ -440,14 +643,16 @@ impl ExecutionEngine {
         &self,
         task: &str,
         agent_config: &AgentConfig,
-        _context: &RuntimeContext,
+        context: &RuntimeContext,
     ) -> AriaResult<ToolResult> {
         println!("🔍 DEBUG: Starting execute_single_step");
         println!("🔍 DEBUG: Task: {}", task);
         println!("🔍 DEBUG: Agent: {}", agent_config.name);
         println!("🔍 DEBUG: Agent tools: {:?}", agent_config.tools);
         
-        let system_prompt = self.system_prompt_service.generate_system_prompt(agent_config, !agent_config.tools.is_empty());
+        let memories = Self::recalled_memories(agent_config, context).await;
+        let system_prompt = self.system_prompt_service
+            .generate_system_prompt_with_memories(agent_config, !agent_config.tools.is_empty(), &memories);
         let has_tools = !agent_config.tools.is_empty();
         
         println!("🔍 DEBUG: Has tools: {}", has_tools);
@@ -484,6 +689,7 @@ impl ExecutionEngine {
             tools: None,
             tool_choice: None,
             stream: Some(false),
+            cache: None,
         };
 
         println!("🔍 DEBUG: Calling LLM handler...");
@@ -498,7 +704,7 @@ impl ExecutionEngine {
         if has_tools {
             println!("🔍 DEBUG: Agent has tools, attempting to parse response for tool execution");
             // Parse JSON response and potentially execute tools
-            self.parse_and_execute_tools(&llm_response.content, agent_config).await
+            self.parse_and_execute_tools(&llm_response.content, agent_config, &ToolCallContext::from_runtime_context(context)).await
         } else {
             println!("🔍 DEBUG: Agent has no tools, returning direct LLM response");
             // Direct LLM response
@@ -528,6 +734,7 @@ impl ExecutionEngine {
         &self,
         content: &str,
         agent_config: &AgentConfig,
+        caller: &ToolCallContext,
     ) -> AriaResult<ToolResult> {
         let parsed_json: Value = serde_json::from_str(content)
             .map_err(|e| AriaError::new(
@@ -573,7 +780,7 @@ impl ExecutionEngine {
                 }
                 
                 // Execute the specified tool
-                let tool_result = self.tool_registry.execute_tool(tool_name, parameters.clone().into()).await?;
+                let tool_result = self.tool_registry.execute_tool_as(tool_name, parameters.clone().into(), caller).await?;
                 
                 let response = if tool_result.success {
                     format!("Tool {} executed successfully. Result: {}", 
@@ -740,13 +947,18 @@ impl Engine for ExecutionEngine {
 
 #[async_trait]
 impl ExecutionEngineInterface for ExecutionEngine {
+    #[tracing::instrument(name = "aria.execution", skip_all, fields(agent = %agent_config.name))]
     async fn execute(
         &self,
         task: &str,
         agent_config: &AgentConfig,
         context: &RuntimeContext,
     ) -> AriaResult<ToolResult> {
-        // Check if task requires multi-tool orchestration
+        if !agent_config.tools.is_empty() && self.supports_native_tools() {
+            return self.execute_with_native_tools(task, agent_config, context).await;
+        }
+
+        // Providers without function calling fall back to the JSON tool protocol
         let requires_orchestration = self.detect_multi_tool_requirement(task);
         
         if requires_orchestration && !agent_config.tools.is_empty() {
@@ -756,6 +968,11 @@ impl ExecutionEngineInterface for ExecutionEngine {
         }
     }
 
+    #[tracing::instrument(
+        name = "aria.step",
+        skip_all,
+        fields(step = %step.description, tool = step.tool_name.as_deref().unwrap_or("none"))
+    )]
     async fn execute_step(
         &self,
         step: &PlannedStep,
@@ -784,7 +1001,8 @@ impl ExecutionEngineInterface for ExecutionEngine {
         } else if let Some(tool_name) = &step.tool_name {
             // Execute the tool
             let params_value = serde_json::to_value(&resolved_parameters).unwrap_or(Value::Null);
-            self.tool_registry.execute_tool(tool_name, params_value.into()).await?
+            let caller = ToolCallContext::from_runtime_context(context);
+            self.tool_registry.execute_tool_as(tool_name, params_value.into(), &caller).await?
         } else {
             return Err(AriaError::new(
                 ErrorCode::StepExecutionError,
@@ -844,6 +1062,36 @@ impl ExecutionEngineInterface for ExecutionEngine {
     }
 }
 
+/// Function definition for a registry tool; tools without an object schema accept any object
+pub(crate) fn tool_definition(entry: &RegistryEntry) -> Tool {
+    let description = entry.metadata.get("description")
+        .and_then(|d| d.0.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let parameters = entry.metadata.get("parameters")
+        .map(|p| p.0.clone())
+        .filter(|p| p.get("type").and_then(Value::as_str) == Some("object"))
+        .unwrap_or_else(|| serde_json::json!({ "type": "object", "properties": {} }));
+
+    Tool {
+        name: entry.name.clone(),
+        description,
+        parameters,
+    }
+}
+
+/// Parse the JSON argument string of a tool call; an empty string means no arguments
+pub(crate) fn parse_tool_arguments(raw: &str) -> Result<Value, String> {
+    if raw.trim().is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+    match serde_json::from_str::<Value>(raw) {
+        Ok(value) if value.is_object() => Ok(value),
+        Ok(other) => Err(format!("Tool arguments must be a JSON object, got: {}", other)),
+        Err(e) => Err(format!("Tool arguments are not valid JSON: {}", e)),
+    }
+}
+
 /// Result of a single orchestration step
 pub struct OrchestrationStepResult {
     pub success: bool,
@@ -854,4 +1102,43 @@ pub struct OrchestrationStepResult {
 
 // ExecutionEngineInterface is defined in engines/mod.rs
 
- 
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tool_arguments_must_be_a_json_object() {
+        assert_eq!(parse_tool_arguments("").unwrap(), serde_json::json!({}));
+        assert_eq!(parse_tool_arguments(r#"{"query":"rust"}"#).unwrap()["query"], "rust");
+        assert!(parse_tool_arguments("[1, 2]").is_err());
+        assert!(parse_tool_arguments("{not json").is_err());
+    }
+
+    #[test]
+    fn tool_definition_uses_registry_schema() {
+        let mut metadata = HashMap::new();
+        metadata.insert("description".to_string(), DeepValue::string("Search the web".to_string()));
+        metadata.insert("parameters".to_string(), DeepValue(serde_json::json!({
+            "type": "object",
+            "properties": { "query": { "type": "string" } },
+            "required": ["query"]
+        })));
+        let entry = RegistryEntry {
+            name: "webSearchTool".to_string(),
+            entry_type: RegistryEntryType::Tool,
+            bundle_id: None,
+            version: "1.0.0".to_string(),
+            metadata,
+            created_at: 0,
+            updated_at: 0,
+        };
+
+        let tool = tool_definition(&entry);
+        assert_eq!(tool.name, "webSearchTool");
+        assert_eq!(tool.description, "Search the web");
+        assert_eq!(tool.parameters["required"][0], "query");
+
+        let bare = RegistryEntry { metadata: HashMap::new(), ..entry };
+        assert_eq!(tool_definition(&bare).parameters, serde_json::json!({ "type": "object", "properties": {} }));
+    }
+}
@@ -5,7 +5,7 @@ use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeveri
 use crate::types::{AgentConfig, RuntimeContext, ToolResult};
 use crate::engines::{Engine, ICCToolHandler, ICCAgentHandler, ICCServerStatus, ICCConnection};
 use crate::engines::tool_registry::{ToolRegistry, ToolRegistryInterface};
-use crate::engines::llm::{LLMHandler, types::{LLMRequest, LLMResponse}};
+use crate::engines::llm::{LLMHandler, types::{CacheMode, LLMRequest, LLMResponse}};
 use crate::deep_size::DeepUuid;
 
 use std::collections::HashMap;
@@ -89,6 +89,7 @@ pub struct LLMCompletionRequest {
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
     pub stream: Option<bool>,
+    pub cache: Option<CacheMode>,
 }
 
 /// LLM message for ICC requests
@@ -391,6 +392,7 @@ impl ICCEngine {
             tools: None,
             tool_choice: None,
             stream: Some(request.stream.unwrap_or(false)),
+            cache: request.cache,
         };
 
         // Execute LLM request
@@ -0,0 +1,533 @@
+//! Token-budgeted assembly of LLM message lists.
+//!
+//! A [`ContextWindow`] knows how many prompt tokens a model accepts, counts the
+//! tokens of a message list with a [`TokenCounter`], and asks a
+//! [`TruncationStrategy`] to shrink the list when it does not fit. System
+//! messages, tool exchanges and the latest message can be pinned so no
+//! strategy ever drops them.
+
+use crate::engines::llm::types::{LLMMessage, LLMRequest};
+use crate::engines::llm::{LLMHandler, LLMRequestConfig};
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Context window assumed for models missing from the known-model table
+pub const DEFAULT_CONTEXT_WINDOW: u32 = 8192;
+
+/// Tokens every message costs on top of its content (role and separators)
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// Tokens the provider adds to prime the assistant reply
+const REPLY_PRIMING_TOKENS: usize = 3;
+
+/// Known context windows, most specific prefix first
+const KNOWN_CONTEXT_WINDOWS: &[(&str, u32)] = &[
+    ("gpt-4o-mini", 128_000),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-1106", 128_000),
+    ("gpt-4-0125", 128_000),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo-instruct", 4_096),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+];
+
+/// Context window of a known model, matched by name prefix
+pub fn known_context_window(model: &str) -> Option<u32> {
+    let model = model.to_lowercase();
+    KNOWN_CONTEXT_WINDOWS.iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|(_, window)| *window)
+}
+
+/// Counts prompt tokens for a model family
+pub trait TokenCounter: Send + Sync {
+    /// Tokens in a piece of text
+    fn count_text(&self, text: &str) -> usize;
+
+    /// Tokens a message costs, including role overhead and tool calls
+    fn count_message(&self, message: &LLMMessage) -> usize {
+        let mut tokens = MESSAGE_OVERHEAD_TOKENS + self.count_text(&message.content);
+        if let Some(calls) = &message.tool_calls {
+            for call in calls {
+                tokens += MESSAGE_OVERHEAD_TOKENS + self.count_text(&call.name) + self.count_text(&call.arguments);
+            }
+        }
+        if let Some(id) = &message.tool_call_id {
+            tokens += self.count_text(id);
+        }
+        tokens
+    }
+
+    /// Tokens a whole message list costs as a prompt
+    fn count_messages(&self, messages: &[LLMMessage]) -> usize {
+        REPLY_PRIMING_TOKENS + messages.iter().map(|m| self.count_message(m)).sum::<usize>()
+    }
+}
+
+/// BPE-shaped estimate that errs on the high side: alphanumeric runs cost one
+/// token per four bytes and every other symbol costs one token. Non-ASCII text
+/// is counted by UTF-8 bytes, which tracks how BPE vocabularies split it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicTokenCounter;
+
+impl TokenCounter for HeuristicTokenCounter {
+    fn count_text(&self, text: &str) -> usize {
+        let flush = |run: usize| (run + 3) / 4;
+        let mut tokens = 0;
+        for word in text.split_whitespace() {
+            let mut run = 0;
+            for c in word.chars() {
+                if c.is_alphanumeric() {
+                    run += c.len_utf8();
+                } else {
+                    tokens += flush(run) + 1;
+                    run = 0;
+                }
+            }
+            tokens += flush(run);
+        }
+        tokens
+    }
+}
+
+/// Messages a strategy keeps or drops together
+#[derive(Debug, Clone)]
+pub struct ContextUnit {
+    pub messages: Vec<LLMMessage>,
+    pub tokens: usize,
+    pub pinned: bool,
+}
+
+/// Shrinks a message list to a token budget
+#[async_trait]
+pub trait TruncationStrategy: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Return units totalling at most `budget` tokens where possible; pinned
+    /// units must be kept and relative order preserved
+    async fn truncate(
+        &self,
+        units: Vec<ContextUnit>,
+        budget: usize,
+        counter: &dyn TokenCounter,
+    ) -> AriaResult<Vec<ContextUnit>>;
+}
+
+/// Drop the oldest unpinned units first
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DropOldest;
+
+#[async_trait]
+impl TruncationStrategy for DropOldest {
+    fn name(&self) -> &str {
+        "drop_oldest"
+    }
+
+    async fn truncate(
+        &self,
+        mut units: Vec<ContextUnit>,
+        budget: usize,
+        _counter: &dyn TokenCounter,
+    ) -> AriaResult<Vec<ContextUnit>> {
+        let mut total = total_tokens(&units);
+        let mut index = 0;
+        while total > budget && index < units.len() {
+            if units[index].pinned {
+                index += 1;
+            } else {
+                total -= units.remove(index).tokens;
+            }
+        }
+        Ok(units)
+    }
+}
+
+/// Replace the oldest unpinned units with a single summary message, keeping the
+/// most recent exchanges verbatim. Summaries come from the LLM when a handler
+/// is attached and from an extractive digest otherwise.
+#[derive(Clone)]
+pub struct SummarizeMiddle {
+    llm: Option<Arc<LLMHandler>>,
+    /// Tokens set aside for the summary message
+    pub summary_tokens: usize,
+}
+
+impl SummarizeMiddle {
+    pub fn new(llm: Arc<LLMHandler>) -> Self {
+        Self { llm: Some(llm), summary_tokens: 512 }
+    }
+
+    /// Summarize without calling an LLM
+    pub fn extractive() -> Self {
+        Self { llm: None, summary_tokens: 512 }
+    }
+
+    async fn summarize(&self, dropped: &[LLMMessage], counter: &dyn TokenCounter) -> String {
+        let transcript = dropped.iter()
+            .map(|m| format!("{}: {}", m.role, m.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if let Some(llm) = &self.llm {
+            let prompt = format!(
+                "Summarize this earlier part of a conversation so it can replace the original messages. \
+                 Keep facts, decisions, tool results and open questions. Be brief.\n\n{}",
+                clip_to_tokens(&transcript, DEFAULT_CONTEXT_WINDOW as usize / 2, counter)
+            );
+            let config = LLMRequestConfig {
+                model: None,
+                temperature: Some(0.2),
+                max_tokens: Some(self.summary_tokens as u32),
+                timeout: None,
+            };
+            match llm.inference(&prompt, Some(config)).await {
+                Ok(summary) if !summary.trim().is_empty() => return summary.trim().to_string(),
+                Ok(_) => {}
+                Err(e) => warn!("Falling back to extractive context summary: {}", e),
+            }
+        }
+
+        // Extractive digest: the first line of each dropped message
+        let digest = dropped.iter()
+            .filter_map(|m| m.content.lines().find(|l| !l.trim().is_empty()).map(|l| format!("{}: {}", m.role, l.trim())))
+            .collect::<Vec<_>>()
+            .join("\n");
+        clip_to_tokens(&digest, self.summary_tokens, counter)
+    }
+}
+
+#[async_trait]
+impl TruncationStrategy for SummarizeMiddle {
+    fn name(&self) -> &str {
+        "summarize_middle"
+    }
+
+    async fn truncate(
+        &self,
+        mut units: Vec<ContextUnit>,
+        budget: usize,
+        counter: &dyn TokenCounter,
+    ) -> AriaResult<Vec<ContextUnit>> {
+        let target = budget.saturating_sub(self.summary_tokens + MESSAGE_OVERHEAD_TOKENS);
+        let mut total = total_tokens(&units);
+        let mut dropped = Vec::new();
+        let mut insert_at = None;
+        let mut index = 0;
+
+        while total > target && index < units.len() {
+            if units[index].pinned {
+                index += 1;
+                continue;
+            }
+            insert_at.get_or_insert(index);
+            let unit = units.remove(index);
+            total -= unit.tokens;
+            dropped.extend(unit.messages);
+        }
+
+        if let Some(position) = insert_at {
+            let summary = self.summarize(&dropped, counter).await;
+            let message = LLMMessage {
+                role: "system".to_string(),
+                content: format!("Summary of {} earlier messages:\n{}", dropped.len(), summary),
+                tool_calls: None,
+                tool_call_id: None,
+            };
+            let tokens = counter.count_message(&message);
+            units.insert(position, ContextUnit { messages: vec![message], tokens, pinned: false });
+        }
+
+        Ok(units)
+    }
+}
+
+/// Token budget and truncation policy for one model
+#[derive(Clone)]
+pub struct ContextWindow {
+    context_limit: usize,
+    completion_tokens: usize,
+    counter: Arc<dyn TokenCounter>,
+    strategy: Arc<dyn TruncationStrategy>,
+    pin_system: bool,
+    pin_tool_messages: bool,
+}
+
+impl ContextWindow {
+    pub fn new(context_limit: u32, completion_tokens: u32) -> Self {
+        Self {
+            context_limit: context_limit as usize,
+            completion_tokens: completion_tokens as usize,
+            counter: Arc::new(HeuristicTokenCounter),
+            strategy: Arc::new(DropOldest),
+            pin_system: true,
+            pin_tool_messages: false,
+        }
+    }
+
+    /// Window for a model name, reserving `completion_tokens` for the reply
+    pub fn for_model(model: &str, completion_tokens: u32) -> Self {
+        Self::new(known_context_window(model).unwrap_or(DEFAULT_CONTEXT_WINDOW), completion_tokens)
+    }
+
+    pub fn with_strategy(mut self, strategy: Arc<dyn TruncationStrategy>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+        self.counter = counter;
+        self
+    }
+
+    /// Choose which message kinds truncation must keep
+    pub fn with_pinning(mut self, system: bool, tool_messages: bool) -> Self {
+        self.pin_system = system;
+        self.pin_tool_messages = tool_messages;
+        self
+    }
+
+    /// Prompt tokens available once the completion is reserved
+    pub fn budget(&self) -> usize {
+        self.context_limit.saturating_sub(self.completion_tokens)
+    }
+
+    pub fn count(&self, messages: &[LLMMessage]) -> usize {
+        self.counter.count_messages(messages)
+    }
+
+    /// Shrink `messages` to the prompt budget. Messages that fit are returned
+    /// untouched; otherwise the strategy runs and, as a last resort, the largest
+    /// remaining messages are clipped in the middle.
+    pub async fn fit(&self, messages: Vec<LLMMessage>) -> AriaResult<Vec<LLMMessage>> {
+        let budget = self.budget().saturating_sub(REPLY_PRIMING_TOKENS);
+        if budget == 0 {
+            return Err(AriaError::new(
+                ErrorCode::LLMTokenLimitExceeded,
+                ErrorCategory::LLM,
+                ErrorSeverity::High,
+                &format!(
+                    "Completion reserve of {} tokens leaves no room in a {} token context",
+                    self.completion_tokens, self.context_limit
+                )
+            ));
+        }
+
+        if self.count(&messages) <= self.budget() {
+            return Ok(messages);
+        }
+
+        let before = messages.len();
+        let units = self.group(messages);
+        let units = self.strategy.truncate(units, budget, self.counter.as_ref()).await?;
+        let mut messages: Vec<LLMMessage> = units.into_iter().flat_map(|u| u.messages).collect();
+
+        while self.count(&messages) > self.budget() {
+            let overflow = self.count(&messages) - self.budget();
+            let (index, tokens) = messages.iter()
+                .enumerate()
+                .map(|(i, m)| (i, self.counter.count_text(&m.content)))
+                .max_by_key(|(_, tokens)| *tokens)
+                .unwrap_or((0, 0));
+            if tokens == 0 {
+                return Err(AriaError::new(
+                    ErrorCode::LLMTokenLimitExceeded,
+                    ErrorCategory::LLM,
+                    ErrorSeverity::High,
+                    &format!("Messages cannot be reduced below {} tokens", self.count(&messages))
+                ));
+            }
+            let keep = tokens.saturating_sub(overflow + 16);
+            messages[index].content = clip_to_tokens(&messages[index].content, keep, self.counter.as_ref());
+        }
+
+        debug!(
+            "Context window ({}) reduced {} messages to {} within {} tokens",
+            self.strategy.name(), before, messages.len(), self.budget()
+        );
+        Ok(messages)
+    }
+
+    /// Fit a request's messages, reserving its `max_tokens` for the reply
+    pub async fn fit_request(&self, mut request: LLMRequest) -> AriaResult<LLMRequest> {
+        let window = Self {
+            completion_tokens: request.config.max_tokens as usize,
+            ..self.clone()
+        };
+        request.messages = window.fit(std::mem::take(&mut request.messages)).await?;
+        Ok(request)
+    }
+
+    /// Group messages into units: an assistant tool call travels with its tool
+    /// results, and the final message is always pinned
+    fn group(&self, messages: Vec<LLMMessage>) -> Vec<ContextUnit> {
+        let last = messages.len().saturating_sub(1);
+        let mut units: Vec<ContextUnit> = Vec::new();
+
+        for (index, message) in messages.into_iter().enumerate() {
+            let tokens = self.counter.count_message(&message);
+            let is_tool_result = message.role == "tool";
+            let extends_exchange = is_tool_result
+                && units.last().map_or(false, |u| {
+                    u.messages.first().map_or(false, |m| m.tool_calls.is_some())
+                });
+
+            if extends_exchange {
+                let unit = units.last_mut().expect("exchange unit exists");
+                unit.tokens += tokens;
+                unit.messages.push(message);
+                unit.pinned |= index == last;
+                continue;
+            }
+
+            let pinned = index == last
+                || (self.pin_system && message.role == "system")
+                || (self.pin_tool_messages && (is_tool_result || message.tool_calls.is_some()));
+            units.push(ContextUnit { messages: vec![message], tokens, pinned });
+        }
+
+        units
+    }
+}
+
+fn total_tokens(units: &[ContextUnit]) -> usize {
+    units.iter().map(|u| u.tokens).sum()
+}
+
+/// Keep the head and tail of `text` within roughly `max_tokens`, marking the cut
+pub fn clip_to_tokens(text: &str, max_tokens: usize, counter: &dyn TokenCounter) -> String {
+    if counter.count_text(text) <= max_tokens {
+        return text.to_string();
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut keep = chars.len();
+    loop {
+        keep = keep * 3 / 4;
+        let head: String = chars[..keep / 2].iter().collect();
+        let tail: String = chars[chars.len() - keep / 2..].iter().collect();
+        let clipped = format!("{}\n…[truncated]…\n{}", head, tail);
+        if keep == 0 || counter.count_text(&clipped) <= max_tokens {
+            return if keep == 0 { String::new() } else { clipped };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::llm::types::ToolCall;
+
+    fn message(role: &str, content: &str) -> LLMMessage {
+        LLMMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    fn words(n: usize) -> String {
+        vec!["word"; n].join(" ")
+    }
+
+    #[test]
+    fn known_models_resolve_by_prefix() {
+        assert_eq!(known_context_window("gpt-4o-mini-2024-07-18"), Some(128_000));
+        assert_eq!(known_context_window("GPT-4"), Some(8_192));
+        assert_eq!(known_context_window("gpt-4-32k-0613"), Some(32_768));
+        assert_eq!(known_context_window("claude-unknown"), None);
+    }
+
+    #[test]
+    fn heuristic_counter_tracks_text_length() {
+        let counter = HeuristicTokenCounter;
+        assert_eq!(counter.count_text(""), 0);
+        assert_eq!(counter.count_text("hello"), 2);
+        assert_eq!(counter.count_text("a, b"), 3);
+        assert!(counter.count_text(&words(100)) >= 100);
+        assert_eq!(counter.count_message(&message("user", "hello")), 2 + MESSAGE_OVERHEAD_TOKENS);
+    }
+
+    #[tokio::test]
+    async fn messages_within_budget_are_untouched() {
+        let window = ContextWindow::new(1000, 100);
+        let messages = vec![message("system", "be brief"), message("user", "hi")];
+        assert_eq!(window.fit(messages.clone()).await.unwrap(), messages);
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_keeps_system_and_latest() {
+        let window = ContextWindow::new(300, 50);
+        let messages = vec![
+            message("system", "system prompt"),
+            message("user", &words(150)),
+            message("assistant", &words(150)),
+            message("user", "latest question"),
+        ];
+        let fitted = window.fit(messages).await.unwrap();
+        assert_eq!(fitted.first().unwrap().role, "system");
+        assert_eq!(fitted.last().unwrap().content, "latest question");
+        assert!(window.count(&fitted) <= window.budget());
+        assert!(fitted.len() < 4);
+    }
+
+    #[tokio::test]
+    async fn tool_results_stay_with_their_call() {
+        let call = LLMMessage {
+            role: "assistant".to_string(),
+            content: String::new(),
+            tool_calls: Some(vec![ToolCall { id: "c1".into(), name: "search".into(), arguments: "{}".into() }]),
+            tool_call_id: None,
+        };
+        let mut result = message("tool", &words(200));
+        result.tool_call_id = Some("c1".to_string());
+
+        let window = ContextWindow::new(200, 20);
+        let fitted = window.fit(vec![
+            message("system", "sys"),
+            call,
+            result,
+            message("user", "next"),
+        ]).await.unwrap();
+        assert!(fitted.iter().all(|m| m.role != "tool" && m.tool_calls.is_none()));
+    }
+
+    #[tokio::test]
+    async fn summarize_middle_replaces_old_turns() {
+        let window = ContextWindow::new(2000, 100)
+            .with_strategy(Arc::new(SummarizeMiddle::extractive()));
+        let mut messages = vec![message("system", "sys")];
+        for i in 0..20 {
+            messages.push(message("user", &format!("turn {}\n{}", i, words(100))));
+        }
+        messages.push(message("user", "latest"));
+
+        let fitted = window.fit(messages).await.unwrap();
+        assert!(window.count(&fitted) <= window.budget());
+        assert!(fitted[1].content.starts_with("Summary of"));
+        assert!(fitted[1].content.contains("turn 0"));
+        assert_eq!(fitted.last().unwrap().content, "latest");
+    }
+
+    #[tokio::test]
+    async fn oversized_pinned_message_is_clipped() {
+        let window = ContextWindow::new(200, 20);
+        let fitted = window.fit(vec![message("user", &words(1000))]).await.unwrap();
+        assert!(window.count(&fitted) <= window.budget());
+        assert!(fitted[0].content.contains("[truncated]"));
+    }
+
+    #[tokio::test]
+    async fn completion_reserve_larger_than_window_errors() {
+        let window = ContextWindow::new(100, 200);
+        assert!(window.fit(vec![message("user", "hi")]).await.is_err());
+    }
+}
@@ -1,11 +1,18 @@
 pub mod types;
 pub mod providers;
+pub mod context_window;
+pub mod response_cache;
 
+use crate::clock::unix_now;
 use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::time::Duration;
 use types::*;
+use crate::costs::{CostScope, CostTracker};
+use crate::memory::{local_embedding, DEFAULT_MEMORY_USER, LOCAL_EMBEDDING_DIMENSIONS, LOCAL_EMBEDDING_MODEL};
+use response_cache::{CacheBackendKind, CacheEntry, CacheKey, MemoryResponseCache, ResponseCacheBackend, SimilarityQuery};
+use futures::StreamExt;
 use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Instant};
 
@@ -58,17 +65,32 @@ pub struct LLMHandler {
     providers: Arc<Mutex<HashMap<String, Box<dyn LLMProvider>>>>,
     /// Default provider name
     default_provider: Arc<Mutex<Option<String>>>,
-    /// Response cache for cost optimization
-    response_cache: Arc<Mutex<HashMap<String, CachedResponse>>>,
+    /// Responses reused for repeated requests; `None` when caching is off
+    response_cache: Arc<Mutex<Option<Arc<dyn ResponseCacheBackend>>>>,
+    /// Told about cache hits and misses, for the runtime's LLM metrics
+    cost_tracker: Arc<Mutex<Option<Arc<CostTracker>>>>,
     /// Configuration
     config: LLMHandlerConfig,
 }
 
+/// Environment variable choosing the response cache: `memory` (the default), `sqlite` or `off`
+pub const CACHE_ENV: &str = "ARIA_LLM_CACHE";
+
+/// Environment variable that, when `true`, gives temperature-0 requests similarity hits by default
+pub const SEMANTIC_CACHE_ENV: &str = "ARIA_LLM_CACHE_SEMANTIC";
+
 #[derive(Debug, Clone)]
 pub struct LLMHandlerConfig {
     pub cache_enabled: bool,
     pub cache_ttl_seconds: u64,
+    /// Cached responses kept per user
     pub max_cache_size: usize,
+    /// Where cached responses are kept; the SQLite backend is attached once the runtime's database is up
+    pub cache_backend: CacheBackendKind,
+    /// Whether temperature-0 requests that set no cache mode also take similarity hits
+    pub semantic_cache: bool,
+    /// Cosine similarity a cached prompt needs to serve a similarity hit
+    pub semantic_threshold: f32,
     pub default_timeout_seconds: u64,
     pub max_retries: u32,
     pub retry_delay_ms: u64,
@@ -80,6 +102,9 @@ impl Default for LLMHandlerConfig {
             cache_enabled: true,
             cache_ttl_seconds: 3600, // 1 hour
             max_cache_size: 1000,
+            cache_backend: CacheBackendKind::Memory,
+            semantic_cache: false,
+            semantic_threshold: 0.95,
             default_timeout_seconds: 30,
             max_retries: 3,
             retry_delay_ms: 1000,
@@ -87,11 +112,30 @@ impl Default for LLMHandlerConfig {
     }
 }
 
-#[derive(Debug, Clone)]
-struct CachedResponse {
-    response: LLMResponse,
-    cached_at: std::time::SystemTime,
-    ttl_seconds: u64,
+impl LLMHandlerConfig {
+    /// The defaults, with the response cache configured from the environment
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Ok(value) = std::env::var(CACHE_ENV) {
+            match CacheBackendKind::parse(&value) {
+                Some(backend) => config.cache_backend = backend,
+                None if matches!(value.to_lowercase().as_str(), "off" | "false" | "none") => config.cache_enabled = false,
+                None => tracing::warn!("Ignoring unknown {} value '{}'", CACHE_ENV, value),
+            }
+        }
+        config.semantic_cache = std::env::var(SEMANTIC_CACHE_ENV)
+            .is_ok_and(|value| value == "1" || value.eq_ignore_ascii_case("true"));
+        config
+    }
+}
+
+/// What the response cache has for a request
+enum CacheLookup {
+    /// The request does not use the cache
+    Skipped,
+    Hit(LLMResponse),
+    /// Nothing yet; the response is stored under the key, with the prompt's embedding if one was made
+    Miss(Arc<dyn ResponseCacheBackend>, CacheKey, Option<(String, Vec<f32>)>),
 }
 
 // Singleton instance (matches Symphony SDK pattern)
@@ -117,14 +161,36 @@ impl LLMHandler {
 
     /// Create new handler instance (private, use get_instance)
     fn new() -> Self {
+        let config = LLMHandlerConfig::from_env();
+        // Responses are held in memory until a persistent backend is attached
+        let response_cache = config.cache_enabled
+            .then(|| Arc::new(MemoryResponseCache::new(config.max_cache_size)) as Arc<dyn ResponseCacheBackend>);
+
         Self {
             providers: Arc::new(Mutex::new(HashMap::new())),
             default_provider: Arc::new(Mutex::new(None)),
-            response_cache: Arc::new(Mutex::new(HashMap::new())),
-            config: LLMHandlerConfig::default(),
+            response_cache: Arc::new(Mutex::new(response_cache)),
+            cost_tracker: Arc::new(Mutex::new(None)),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &LLMHandlerConfig {
+        &self.config
+    }
+
+    /// Keep cached responses in `backend` from now on; ignored when caching is off
+    pub fn attach_response_cache(&self, backend: Arc<dyn ResponseCacheBackend>) {
+        if self.config.cache_enabled {
+            *self.response_cache.lock().unwrap() = Some(backend);
         }
     }
 
+    /// Report cache hits and what they saved through `costs`
+    pub fn attach_cost_tracker(&self, costs: Arc<CostTracker>) {
+        *self.cost_tracker.lock().unwrap() = Some(costs);
+    }
+
     /// Initialize default providers based on environment (matches Symphony pattern)
     async fn initialize_default_providers(&self) -> AriaResult<()> {
         // Initialize OpenAI if API key is provided (exactly like Symphony SDK)
@@ -222,7 +288,17 @@ impl LLMHandler {
     }
 
     /// Complete an LLM request (matches Symphony pattern)
-    pub async fn complete(&self, request: types::LLMRequest) -> AriaResult<types::LLMResponse> {
+    #[tracing::instrument(
+        name = "llm.complete",
+        skip_all,
+        fields(
+            provider = tracing::field::Empty,
+            model = request.config.model.as_deref().unwrap_or_default(),
+            total_tokens = tracing::field::Empty,
+            cache = tracing::field::Empty,
+        )
+    )]
+    pub async fn complete(&self, mut request: types::LLMRequest) -> AriaResult<types::LLMResponse> {
         println!("🔍 DEBUG: LLMHandler::complete called");
         println!("🔍 DEBUG: Request provider: {:?}", request.provider);
         println!("🔍 DEBUG: Request messages count: {}", request.messages.len());
@@ -244,35 +320,52 @@ impl LLMHandler {
         }
 
         let provider_name = target_provider_name.unwrap();
+        tracing::Span::current().record("provider", provider_name.as_str());
         println!("🔍 DEBUG: Using provider: {}", provider_name);
-        
-        // On-demand provider initialization (matches Symphony pattern)
-        if !self.has_provider(&provider_name) {
-            println!("🔍 DEBUG: Provider {} not found, attempting to register", provider_name);
-            if provider_name == "openai" {
-                if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
-                    if !api_key.is_empty() {
-                        println!("🔍 DEBUG: Found OpenAI API key, registering provider");
-                        let config = LLMConfig {
-                            provider: "openai".to_string(),
-                            api_key,
-                            model: request.config.model.clone(),
-                            temperature: Some(request.config.temperature),
-                            max_tokens: Some(request.config.max_tokens),
-                            timeout: Some(30),
-                        };
-                        self.register_provider(config).await?;
-                        println!("🔍 DEBUG: OpenAI provider registered successfully");
-                    } else {
-                        println!("🔍 DEBUG: OpenAI API key is empty");
-                    }
-                } else {
-                    println!("🔍 DEBUG: OpenAI API key not found in environment");
+
+        // Apply the run's budget before anything else looks at the model
+        let scope = CostScope::current();
+        if let Some(scope) = &scope {
+            scope.admit(&mut request)?;
+        }
+        let requested_model = request.config.model.clone().unwrap_or_default();
+
+        // Serve repeated requests from the caller's cache
+        let namespace = scope.as_ref()
+            .and_then(|scope| scope.attribution().user_id.clone())
+            .unwrap_or_else(|| DEFAULT_MEMORY_USER.to_string());
+        let cache = self.lookup_cached(&namespace, &provider_name, &request).await;
+        let costs = self.cost_tracker.lock().unwrap().clone();
+        match &cache {
+            CacheLookup::Hit(response) => {
+                tracing::Span::current().record("cache", "hit");
+                if let Some(costs) = &costs {
+                    let model = if response.model.is_empty() { &requested_model } else { &response.model };
+                    costs.record_cache_hit(&provider_name, model, response.token_usage.as_ref());
                 }
             }
-        } else {
-            println!("🔍 DEBUG: Provider {} already exists", provider_name);
+            CacheLookup::Miss(..) => {
+                tracing::Span::current().record("cache", "miss");
+                if let Some(costs) = &costs {
+                    costs.record_cache_miss();
+                }
+            }
+            CacheLookup::Skipped => {}
         }
+        let (backend, cache_key, embedding) = match cache {
+            CacheLookup::Hit(response) => return Ok(response),
+            CacheLookup::Miss(backend, key, embedding) => (Some(backend), Some(key), embedding),
+            CacheLookup::Skipped => (None, None, None),
+        };
+        
+        self.ensure_provider(&provider_name, &request.config).await?;
+
+        // Keep the prompt inside the model's context window instead of letting the provider reject it
+        let window = context_window::ContextWindow::for_model(
+            request.config.model.as_deref().unwrap_or_default(),
+            request.config.max_tokens,
+        );
+        let request = window.fit_request(request).await?;
 
         println!("🔍 DEBUG: Getting provider instance");
         let provider = self.get_provider(Some(&provider_name))?;
@@ -282,6 +375,13 @@ impl LLMHandler {
         
         match &result {
             Ok(response) => {
+                if let Some(usage) = &response.token_usage {
+                    tracing::Span::current().record("total_tokens", usage.total);
+                    if let Some(scope) = &scope {
+                        let model = if response.model.is_empty() { &requested_model } else { &response.model };
+                        scope.charge(&provider_name, model, usage);
+                    }
+                }
                 println!("🔍 DEBUG: Provider returned success!");
                 println!("🔍 DEBUG: Response content length: {}", response.content.len());
                 println!("🔍 DEBUG: Response content (first 200 chars): {}", 
@@ -292,10 +392,158 @@ impl LLMHandler {
                 println!("🔍 DEBUG: Provider returned error: {:?}", e);
             }
         }
+
+        if let (Some(backend), Some(key), Ok(response)) = (backend, cache_key, &result) {
+            let answered = !response.content.is_empty() || response.tool_calls.as_ref().is_some_and(|calls| !calls.is_empty());
+            if answered {
+                let now = unix_now();
+                let entry = CacheEntry {
+                    key,
+                    response: response.clone(),
+                    embedding,
+                    created_at: now,
+                    expires_at: now + self.config.cache_ttl_seconds,
+                };
+                if let Err(e) = backend.put(&namespace, entry).await {
+                    tracing::warn!("Failed to cache LLM response: {}", e);
+                }
+            }
+        }
         
         result
     }
 
+    /// Look a request up in the response cache: by its exact key, then, for
+    /// temperature-0 requests in semantic mode, by the similarity of its prompt
+    async fn lookup_cached(&self, namespace: &str, provider_name: &str, request: &types::LLMRequest) -> CacheLookup {
+        let Some(backend) = self.response_cache.lock().unwrap().clone() else {
+            return CacheLookup::Skipped;
+        };
+        let default_mode = if self.config.semantic_cache { CacheMode::Semantic } else { CacheMode::Exact };
+        let mode = request.cache.unwrap_or(default_mode);
+        if mode == CacheMode::Bypass {
+            return CacheLookup::Skipped;
+        }
+
+        let key = CacheKey::for_request(provider_name, request);
+        let now = unix_now();
+        match backend.get(namespace, &key.key, now).await {
+            Ok(Some(response)) => return CacheLookup::Hit(response),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("LLM cache lookup failed: {}", e),
+        }
+
+        if mode != CacheMode::Semantic || request.config.temperature != 0.0 {
+            return CacheLookup::Miss(backend, key, None);
+        }
+        let (embedding_model, embedding) = self.prompt_embedding(&key.prompt).await;
+        let query = SimilarityQuery {
+            scope: &key.scope,
+            embedding_model: &embedding_model,
+            embedding: &embedding,
+            min_similarity: self.config.semantic_threshold,
+        };
+        match backend.nearest(namespace, query, now).await {
+            Ok(Some((similarity, response))) => {
+                tracing::debug!("Reusing cached response to a prompt {:.3} similar", similarity);
+                return CacheLookup::Hit(response);
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("LLM cache similarity lookup failed: {}", e),
+        }
+        CacheLookup::Miss(backend, key, Some((embedding_model, embedding)))
+    }
+
+    /// Embed a prompt with the default provider, falling back to the local hashed embedding
+    async fn prompt_embedding(&self, prompt: &str) -> (String, Vec<f32>) {
+        match self.embed(&[prompt.to_string()], None).await {
+            Ok(mut response) if response.embeddings.len() == 1 => {
+                return (format!("{}:{}", response.provider, response.model), response.embeddings.remove(0));
+            }
+            Ok(_) => tracing::warn!("Embedding provider returned an unexpected number of vectors"),
+            Err(e) => tracing::debug!("Falling back to local prompt embedding: {}", e),
+        }
+
+        (LOCAL_EMBEDDING_MODEL.to_string(), local_embedding(prompt, LOCAL_EMBEDDING_DIMENSIONS))
+    }
+
+    /// On-demand provider initialization (matches Symphony pattern)
+    async fn ensure_provider(&self, provider_name: &str, config: &types::LLMConfig) -> AriaResult<()> {
+        if !self.has_provider(provider_name) {
+            println!("🔍 DEBUG: Provider {} not found, attempting to register", provider_name);
+            if provider_name == "openai" {
+                if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
+                    if !api_key.is_empty() {
+                        println!("🔍 DEBUG: Found OpenAI API key, registering provider");
+                        let provider_config = LLMConfig {
+                            provider: "openai".to_string(),
+                            api_key,
+                            model: config.model.clone(),
+                            temperature: Some(config.temperature),
+                            max_tokens: Some(config.max_tokens),
+                            timeout: Some(30),
+                        };
+                        self.register_provider(provider_config).await?;
+                        println!("🔍 DEBUG: OpenAI provider registered successfully");
+                    } else {
+                        println!("🔍 DEBUG: OpenAI API key is empty");
+                    }
+                } else {
+                    println!("🔍 DEBUG: OpenAI API key not found in environment");
+                }
+            }
+        } else {
+            println!("🔍 DEBUG: Provider {} already exists", provider_name);
+        }
+        Ok(())
+    }
+
+    /// Stream an LLM request, registering the provider on demand and fitting the
+    /// context window like `complete`. Providers without streaming yield their
+    /// complete response as a single chunk.
+    pub async fn complete_stream(&self, mut request: types::LLMRequest) -> AriaResult<Box<dyn futures::Stream<Item = AriaResult<types::LLMResponse>> + Unpin + Send>> {
+        let provider_name = request.provider.clone()
+            .or_else(|| self.get_default_provider_sync())
+            .ok_or_else(|| AriaError::new(
+                ErrorCode::LLMProviderNotFound,
+                ErrorCategory::LLM,
+                ErrorSeverity::High,
+                "No provider specified in request and no default provider set"
+            ))?;
+
+        let scope = CostScope::current();
+        if let Some(scope) = &scope {
+            scope.admit(&mut request)?;
+        }
+        let requested_model = request.config.model.clone().unwrap_or_default();
+        self.ensure_provider(&provider_name, &request.config).await?;
+
+        let window = context_window::ContextWindow::for_model(
+            request.config.model.as_deref().unwrap_or_default(),
+            request.config.max_tokens,
+        );
+        let request = window.fit_request(request).await?;
+
+        let provider = self.get_provider(Some(&provider_name))?;
+        let stream: Box<dyn futures::Stream<Item = AriaResult<types::LLMResponse>> + Unpin + Send> = if provider.supports_streaming() {
+            provider.complete_stream(request).await?
+        } else {
+            let response = provider.complete(request).await;
+            Box::new(futures::stream::iter(std::iter::once(response)))
+        };
+
+        let Some(scope) = scope else {
+            return Ok(stream);
+        };
+        // Usage arrives on the stream's last chunk
+        Ok(Box::new(stream.inspect(move |chunk| {
+            if let Ok(LLMResponse { token_usage: Some(usage), model, .. }) = chunk {
+                let model = if model.is_empty() { &requested_model } else { model };
+                scope.charge(&provider_name, model, usage);
+            }
+        })))
+    }
+
     /// Simple inference method (matches Symphony pattern)
     pub async fn inference(&self, prompt: &str, llm_config: Option<LLMRequestConfig>) -> AriaResult<String> {
         let config = if let Some(req_config) = llm_config {
@@ -323,12 +571,19 @@ impl LLMHandler {
             tools: None,
             tool_choice: None,
             stream: None,
+            cache: None,
         };
         
         let response = self.complete(request).await?;
         Ok(response.content)
     }
 
+    /// Embed texts with the named provider, or the default provider when none is given
+    pub async fn embed(&self, inputs: &[String], provider: Option<&str>) -> AriaResult<types::EmbeddingResponse> {
+        let provider = self.get_provider(provider)?;
+        provider.embed(inputs).await
+    }
+
     /// Check if provider exists
     fn has_provider(&self, name: &str) -> bool {
         let providers = self.providers.lock().unwrap();
@@ -389,6 +644,16 @@ pub trait LLMProvider: Send + Sync {
         ))
     }
     
+    /// Embed a batch of texts, one vector per input
+    async fn embed(&self, _inputs: &[String]) -> AriaResult<types::EmbeddingResponse> {
+        Err(AriaError::new(
+            ErrorCode::NotSupported,
+            ErrorCategory::LLM,
+            ErrorSeverity::Medium,
+            &format!("Provider {} does not support embeddings", self.name())
+        ))
+    }
+    
     /// Health check
     async fn health_check(&self) -> AriaResult<bool>;
     
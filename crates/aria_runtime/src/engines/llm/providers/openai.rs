@@ -4,6 +4,7 @@ use futures::Stream;
 use reqwest::{Client, header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE}};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use tokio::time::{timeout, Duration};
@@ -18,6 +19,7 @@ pub struct OpenAIProvider {
     api_key: String,
     base_url: String,
     default_model: String,
+    embedding_model: String,
     timeout_seconds: u64,
 }
 
@@ -29,6 +31,8 @@ struct OpenAIRequest {
     max_tokens: Option<u32>,
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<OpenAITool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tool_choice: Option<Value>,
@@ -103,15 +107,56 @@ struct OpenAIStreamChunk {
     created: u64,
     model: String,
     choices: Vec<OpenAIStreamChoice>,
+    #[serde(default)]
+    usage: Option<OpenAIUsage>,
 }
 
 #[derive(Debug, Deserialize)]
 struct OpenAIStreamChoice {
     index: u32,
-    delta: OpenAIMessage,
+    delta: OpenAIStreamDelta,
     finish_reason: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct OpenAIStreamDelta {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<OpenAIToolCallDelta>>,
+}
+
+/// A fragment of a streamed tool call; only the first fragment of a call carries its id and name
+#[derive(Debug, Deserialize)]
+struct OpenAIToolCallDelta {
+    index: u32,
+    id: Option<String>,
+    function: Option<OpenAIFunctionCallDelta>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OpenAIFunctionCallDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAIEmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbeddingResponse {
+    model: String,
+    data: Vec<OpenAIEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
 #[derive(Debug, Deserialize)]
 struct OpenAIError {
     error: OpenAIErrorDetails,
@@ -146,6 +191,7 @@ impl OpenAIProvider {
             api_key,
             base_url: "https://api.openai.com/v1".to_string(),
             default_model: "gpt-4".to_string(),
+            embedding_model: "text-embedding-3-small".to_string(),
             timeout_seconds: 60,
         }
     }
@@ -155,6 +201,11 @@ impl OpenAIProvider {
         self
     }
 
+    pub fn with_embedding_model(mut self, model: String) -> Self {
+        self.embedding_model = model;
+        self
+    }
+
     pub fn with_timeout(mut self, timeout_seconds: u64) -> Self {
         self.timeout_seconds = timeout_seconds;
         self
@@ -214,6 +265,7 @@ impl OpenAIProvider {
             temperature: request.config.temperature,
             max_tokens: Some(request.config.max_tokens),
             stream: false,
+            stream_options: None,
             tools,
             tool_choice,
         }
@@ -452,6 +504,8 @@ impl LLMProvider for OpenAIProvider {
     async fn complete_stream(&self, request: LLMRequest) -> AriaResult<Box<dyn Stream<Item = AriaResult<LLMResponse>> + Unpin + Send>> {
         let mut openai_request = self.convert_request(&request);
         openai_request.stream = true;
+        // Ask for a final usage chunk so streamed calls are metered like complete ones
+        openai_request.stream_options = Some(json!({ "include_usage": true }));
 
         let response = tokio::time::timeout(
             Duration::from_secs(self.timeout_seconds),
@@ -526,6 +580,62 @@ impl LLMProvider for OpenAIProvider {
         }
     }
 
+    async fn embed(&self, inputs: &[String]) -> AriaResult<EmbeddingResponse> {
+        let request = OpenAIEmbeddingRequest {
+            model: &self.embedding_model,
+            input: inputs,
+        };
+
+        let response = timeout(
+            Duration::from_secs(self.timeout_seconds),
+            self.client.post(&format!("{}/embeddings", self.base_url))
+                .json(&request)
+                .send()
+        ).await
+            .map_err(|_| AriaError::new(
+                ErrorCode::LLMTimeout,
+                ErrorCategory::LLM,
+                ErrorSeverity::High,
+                &format!("OpenAI embedding request timeout after {} seconds", self.timeout_seconds)
+            ))?
+            .map_err(|e| AriaError::new(
+                ErrorCode::LLMApiError,
+                ErrorCategory::LLM,
+                ErrorSeverity::High,
+                &format!("OpenAI embedding request failed: {}", e)
+            ))?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.handle_api_error(status, &body));
+        }
+
+        let mut parsed: OpenAIEmbeddingResponse = response.json().await
+            .map_err(|e| AriaError::new(
+                ErrorCode::LLMInvalidResponse,
+                ErrorCategory::LLM,
+                ErrorSeverity::High,
+                &format!("Failed to parse OpenAI embedding response: {}", e)
+            ))?;
+
+        if parsed.data.len() != inputs.len() {
+            return Err(AriaError::new(
+                ErrorCode::LLMInvalidResponse,
+                ErrorCategory::LLM,
+                ErrorSeverity::High,
+                &format!("OpenAI returned {} embeddings for {} inputs", parsed.data.len(), inputs.len())
+            ));
+        }
+
+        parsed.data.sort_by_key(|item| item.index);
+        Ok(EmbeddingResponse {
+            model: parsed.model,
+            provider: "openai".to_string(),
+            embeddings: parsed.data.into_iter().map(|item| item.embedding).collect(),
+        })
+    }
+
     fn clone_box(&self) -> Box<dyn LLMProvider> {
         Box::new(self.clone())
     }
@@ -535,6 +645,9 @@ impl LLMProvider for OpenAIProvider {
 pub struct OpenAIStreamWrapper {
     stream: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>,
     buffer: String,
+    /// Call ids by tool-call index, so later fragments can be tagged with their call
+    tool_call_ids: HashMap<u32, String>,
+    done: bool,
 }
 
 impl OpenAIStreamWrapper {
@@ -542,58 +655,102 @@ impl OpenAIStreamWrapper {
         Self {
             stream,
             buffer: String::new(),
+            tool_call_ids: HashMap::new(),
+            done: false,
         }
     }
 
+    /// Parse the next complete event in the buffer, skipping comments,
+    /// keep-alives and chunks that carry nothing
     fn parse_chunk(&mut self) -> Option<AriaResult<LLMResponse>> {
-        // Find the first complete message ("\n\n")
-        if let Some(end_of_message) = self.buffer.find("\n\n") {
+        // Messages are separated by a blank line ("\n\n")
+        while let Some(end_of_message) = self.buffer.find("\n\n") {
             let message_str = self.buffer[..end_of_message].to_string();
             // Remove the processed message from the buffer
             self.buffer.drain(..end_of_message + 2);
 
-            if message_str.starts_with("data: ") {
-                let data = &message_str[6..];
-                if data.trim() == "[DONE]" {
-                    // End of stream signal from OpenAI
-                    return None;
-                }
+            let Some(data) = message_str.strip_prefix("data: ") else {
+                continue;
+            };
+            if data.trim() == "[DONE]" {
+                // End of stream signal from OpenAI
+                self.done = true;
+                return None;
+            }
 
-                match serde_json::from_str::<OpenAIStreamChunk>(data) {
-                    Ok(stream_chunk) => {
-                        if let Some(choice) = stream_chunk.choices.into_iter().next() {
-                            let response = LLMResponse {
-                                content: choice.delta.content.unwrap_or_default(),
-                                model: stream_chunk.model,
-                                provider: "openai".to_string(),
-                                token_usage: None, // Usage is typically only in the final chunk which is not handled here
-                                finish_reason: choice.finish_reason.unwrap_or_else(|| "streaming".to_string()),
-                                tool_calls: choice.delta.tool_calls.map(|calls| {
-                                    calls.into_iter().map(|call| ToolCall {
-                                        id: call.id,
-                                        name: call.function.name,
-                                        arguments: call.function.arguments.clone(),
-                                    }).collect()
-                                }),
-                            };
-                            return Some(Ok(response));
-                        }
-                    }
-                    Err(e) => {
-                        // Failed to parse a chunk, which is a stream error
-                        return Some(Err(AriaError::new(
-                            ErrorCode::LLMInvalidResponse,
-                            ErrorCategory::LLM,
-                            ErrorSeverity::Medium,
-                            &format!("Failed to parse stream chunk: {}", e),
-                        )));
+            match serde_json::from_str::<OpenAIStreamChunk>(data) {
+                Ok(stream_chunk) => {
+                    if let Some(response) = self.convert_chunk(stream_chunk) {
+                        return Some(Ok(response));
                     }
                 }
+                Err(e) => {
+                    // Failed to parse a chunk, which is a stream error
+                    return Some(Err(AriaError::new(
+                        ErrorCode::LLMInvalidResponse,
+                        ErrorCategory::LLM,
+                        ErrorSeverity::Medium,
+                        &format!("Failed to parse stream chunk: {}", e),
+                    )));
+                }
             }
         }
         // No complete message in buffer yet
         None
     }
+
+    /// Convert a stream chunk into a partial response.
+    /// Tool-call fragments are indexed and only the first carries the call id, so
+    /// every fragment is tagged with its call's id for `StreamedResponse` to reassemble.
+    fn convert_chunk(&mut self, chunk: OpenAIStreamChunk) -> Option<LLMResponse> {
+        let token_usage = chunk.usage.map(|u| TokenUsage {
+            prompt: u.prompt_tokens,
+            completion: u.completion_tokens,
+            total: u.total_tokens,
+        });
+
+        let Some(choice) = chunk.choices.into_iter().next() else {
+            // The usage chunk requested through stream_options has no choices
+            return token_usage.map(|usage| LLMResponse {
+                content: String::new(),
+                model: chunk.model,
+                provider: "openai".to_string(),
+                token_usage: Some(usage),
+                finish_reason: "streaming".to_string(),
+                tool_calls: None,
+            });
+        };
+
+        let tool_call_ids = &mut self.tool_call_ids;
+        let tool_calls = choice.delta.tool_calls.map(|calls| {
+            calls.into_iter().map(|call| {
+                let id = match call.id.filter(|id| !id.is_empty()) {
+                    Some(id) => {
+                        tool_call_ids.insert(call.index, id.clone());
+                        id
+                    }
+                    None => tool_call_ids.get(&call.index)
+                        .cloned()
+                        .unwrap_or_else(|| format!("call_{}", call.index)),
+                };
+                let function = call.function.unwrap_or_default();
+                ToolCall {
+                    id,
+                    name: function.name.unwrap_or_default(),
+                    arguments: function.arguments.unwrap_or_default(),
+                }
+            }).collect()
+        });
+
+        Some(LLMResponse {
+            content: choice.delta.content.unwrap_or_default(),
+            model: chunk.model,
+            provider: "openai".to_string(),
+            token_usage,
+            finish_reason: choice.finish_reason.unwrap_or_else(|| "streaming".to_string()),
+            tool_calls,
+        })
+    }
 }
 
 impl Stream for OpenAIStreamWrapper {
@@ -605,6 +762,9 @@ impl Stream for OpenAIStreamWrapper {
             if let Some(message_result) = self.parse_chunk() {
                 return Poll::Ready(Some(message_result));
             }
+            if self.done {
+                return Poll::Ready(None);
+            }
 
             // If no full message is in the buffer, poll the byte stream for more data
             match self.stream.as_mut().poll_next(cx) {
@@ -625,7 +785,7 @@ impl Stream for OpenAIStreamWrapper {
                 Poll::Ready(None) => {
                     // The byte stream is finished. If the buffer is empty, the stream is done.
                     // If the buffer is not empty, it means there's a partial message left, which is an error.
-                    return if self.buffer.is_empty() {
+                    return if self.buffer.trim().is_empty() {
                         Poll::Ready(None)
                     } else {
                         Poll::Ready(Some(Err(AriaError::new(
@@ -645,4 +805,50 @@ impl Stream for OpenAIStreamWrapper {
     }
 }
 
-impl Unpin for OpenAIStreamWrapper {} 
\ No newline at end of file
+impl Unpin for OpenAIStreamWrapper {} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn sse(events: &[&str]) -> OpenAIStreamWrapper {
+        // Split mid-event to make sure the buffer reassembles partial reads
+        let body: String = events.iter().map(|event| format!("data: {}\n\n", event)).collect();
+        let (head, tail) = body.split_at(body.len() / 2);
+        let chunks: Vec<reqwest::Result<Bytes>> = vec![Ok(Bytes::from(head.to_string())), Ok(Bytes::from(tail.to_string()))];
+        OpenAIStreamWrapper::new(Box::pin(futures::stream::iter(chunks)))
+    }
+
+    #[tokio::test]
+    async fn stream_reassembles_tool_call_fragments() {
+        let mut stream = sse(&[
+            r#"{"id":"c","object":"chunk","created":1,"model":"gpt-4o","choices":[{"index":0,"delta":{"role":"assistant","content":"Look"},"finish_reason":null}]}"#,
+            r#"{"id":"c","object":"chunk","created":1,"model":"gpt-4o","choices":[{"index":0,"delta":{"content":"ing"},"finish_reason":null}]}"#,
+            r#"{"id":"c","object":"chunk","created":1,"model":"gpt-4o","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_a","type":"function","function":{"name":"search","arguments":""}}]},"finish_reason":null}]}"#,
+            r#"{"id":"c","object":"chunk","created":1,"model":"gpt-4o","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"q\":"}}]},"finish_reason":null}]}"#,
+            r#"{"id":"c","object":"chunk","created":1,"model":"gpt-4o","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"rust\"}"}}]},"finish_reason":null}]}"#,
+            r#"{"id":"c","object":"chunk","created":1,"model":"gpt-4o","choices":[{"index":0,"delta":{"tool_calls":[{"index":1,"id":"call_b","type":"function","function":{"name":"clock","arguments":"{}"}}]},"finish_reason":null}]}"#,
+            r#"{"id":"c","object":"chunk","created":1,"model":"gpt-4o","choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}]}"#,
+            r#"{"id":"c","object":"chunk","created":1,"model":"gpt-4o","choices":[],"usage":{"prompt_tokens":12,"completion_tokens":8,"total_tokens":20}}"#,
+            "[DONE]",
+        ]);
+
+        let mut streamed = StreamedResponse::default();
+        let mut deltas = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.unwrap();
+            deltas.push(chunk.content.clone());
+            streamed.push(&chunk);
+        }
+
+        assert_eq!(deltas[..2], ["Look".to_string(), "ing".to_string()]);
+        let response = streamed.into_response();
+        assert_eq!(response.content, "Looking");
+        assert_eq!(response.finish_reason, "tool_calls");
+        assert_eq!(response.token_usage.unwrap().total, 20);
+        assert_eq!(response.tool_calls.unwrap(), vec![
+            ToolCall { id: "call_a".to_string(), name: "search".to_string(), arguments: r#"{"q":"rust"}"#.to_string() },
+            ToolCall { id: "call_b".to_string(), name: "clock".to_string(), arguments: "{}".to_string() },
+        ]);
+    }
+}
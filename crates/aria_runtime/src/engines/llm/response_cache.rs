@@ -0,0 +1,503 @@
+//! Reuse of model responses for repeated requests.
+//!
+//! A [`CacheKey`] identifies a request by a hash of its normalized form: fields
+//! that cannot change the response (streaming, tool call ids, message
+//! whitespace, parameters left at their defaults) are dropped or canonicalized
+//! first, so re-issuing a prompt hits even when incidental details differ.
+//! Temperature-0 requests can also opt into similarity hits, which reuse the
+//! response to the closest embedded prompt in the same scope: the same
+//! provider, model, parameters, tools and message roles.
+//!
+//! Responses are kept per user by a [`ResponseCacheBackend`], either in memory
+//! or in each user's database so they survive restarts.
+
+use crate::database::response_cache::{CachedResponseRecord, ResponseCacheOps};
+use crate::database::DatabaseManager;
+use crate::engines::llm::types::{LLMMessage, LLMRequest, LLMResponse, Tool, ToolChoice};
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::memory::cosine_similarity;
+use async_trait::async_trait;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Where cached responses are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheBackendKind {
+    /// In the process; lost on restart
+    Memory,
+    /// In each user's database
+    Sqlite,
+}
+
+impl CacheBackendKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "memory" => Some(CacheBackendKind::Memory),
+            "sqlite" => Some(CacheBackendKind::Sqlite),
+            _ => None,
+        }
+    }
+}
+
+/// Identity of a request in the cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    /// Hash of the normalized request
+    pub key: String,
+    /// Hash of the normalized request without its message contents; similarity hits stay within it
+    pub scope: String,
+    /// The normalized messages as text, embedded for similarity hits
+    pub prompt: String,
+}
+
+#[derive(Serialize)]
+struct NormalizedRequest<'a> {
+    provider: String,
+    model: String,
+    temperature: i64,
+    max_tokens: u32,
+    top_p: Option<i64>,
+    frequency_penalty: Option<i64>,
+    presence_penalty: Option<i64>,
+    messages: Vec<NormalizedMessage>,
+    tools: Vec<&'a Tool>,
+    tool_choice: Option<&'a ToolChoice>,
+}
+
+#[derive(Serialize)]
+struct NormalizedMessage {
+    role: String,
+    content: Option<String>,
+    tool_calls: Vec<(String, String, String)>,
+    tool_call_id: Option<String>,
+}
+
+impl CacheKey {
+    pub fn for_request(provider: &str, request: &LLMRequest) -> Self {
+        let config = &request.config;
+        // Sampling parameters are compared to three decimals, and defaults are the same as unset
+        let milli = |value: f32| (value * 1000.0).round() as i64;
+        let non_default = |value: Option<f32>, default: f32| value.filter(|&v| milli(v) != milli(default)).map(milli);
+
+        let mut tools: Vec<&Tool> = request.tools.iter().flatten().collect();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        // Without tools the choice is moot; with them, unset means auto
+        let tool_choice = if tools.is_empty() { None } else { Some(request.tool_choice.as_ref().unwrap_or(&ToolChoice::Auto)) };
+
+        let mut normalized = NormalizedRequest {
+            provider: provider.to_lowercase(),
+            model: config.model.as_deref().unwrap_or_default().to_lowercase(),
+            temperature: milli(config.temperature),
+            max_tokens: config.max_tokens,
+            top_p: non_default(config.top_p, 1.0),
+            frequency_penalty: non_default(config.frequency_penalty, 0.0),
+            presence_penalty: non_default(config.presence_penalty, 0.0),
+            messages: normalize_messages(&request.messages),
+            tools,
+            tool_choice,
+        };
+        let key = digest(&normalized);
+
+        let prompt = normalized.messages.iter_mut()
+            .map(|message| format!("{}: {}", message.role, message.content.take().unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let scope = digest(&normalized);
+
+        Self { key, scope, prompt }
+    }
+}
+
+/// Messages with their content's whitespace canonicalized and tool call ids,
+/// which providers generate afresh every time, replaced by their order of appearance
+fn normalize_messages(messages: &[LLMMessage]) -> Vec<NormalizedMessage> {
+    let mut call_ids: HashMap<&str, String> = HashMap::new();
+    messages.iter()
+        .map(|message| {
+            let tool_calls = message.tool_calls.iter().flatten()
+                .map(|call| (ordinal(&mut call_ids, &call.id), call.name.clone(), call.arguments.clone()))
+                .collect();
+            NormalizedMessage {
+                role: message.role.to_lowercase(),
+                content: Some(normalize_text(&message.content)),
+                tool_calls,
+                tool_call_id: message.tool_call_id.as_deref().map(|id| ordinal(&mut call_ids, id)),
+            }
+        })
+        .collect()
+}
+
+fn ordinal<'a>(call_ids: &mut HashMap<&'a str, String>, id: &'a str) -> String {
+    let next = call_ids.len();
+    call_ids.entry(id).or_insert_with(|| format!("call_{}", next)).clone()
+}
+
+/// Line endings unified, trailing whitespace of each line and blank edges dropped
+fn normalize_text(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+fn digest(value: &impl Serialize) -> String {
+    let json = serde_json::to_string(value).unwrap_or_default();
+    hex::encode(Sha256::digest(json.as_bytes()))
+}
+
+/// A response to store
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub key: CacheKey,
+    pub response: LLMResponse,
+    /// Model and vector of the prompt's embedding, when it may serve similarity hits
+    pub embedding: Option<(String, Vec<f32>)>,
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+/// A prompt embedding to find the closest cached prompt to
+#[derive(Debug, Clone, Copy)]
+pub struct SimilarityQuery<'a> {
+    pub scope: &'a str,
+    pub embedding_model: &'a str,
+    pub embedding: &'a [f32],
+    /// Cached prompts less similar than this are never used
+    pub min_similarity: f32,
+}
+
+/// Storage for cached responses, partitioned by user
+#[async_trait]
+pub trait ResponseCacheBackend: Send + Sync {
+    /// The unexpired response to the request with this key
+    async fn get(&self, namespace: &str, key: &str, now: u64) -> AriaResult<Option<LLMResponse>>;
+
+    /// The unexpired response whose prompt is most similar to the query, with its similarity
+    async fn nearest(&self, namespace: &str, query: SimilarityQuery<'_>, now: u64) -> AriaResult<Option<(f32, LLMResponse)>>;
+
+    /// Store a response, replacing any previous one for the same request
+    async fn put(&self, namespace: &str, entry: CacheEntry) -> AriaResult<()>;
+}
+
+/// The entry most similar to `query` among `candidates`, if any reaches the threshold
+fn closest<'a, T>(query: &SimilarityQuery<'_>, candidates: impl Iterator<Item = (&'a [f32], T)>) -> Option<(f32, T)> {
+    candidates
+        .map(|(embedding, item)| (cosine_similarity(query.embedding, embedding), item))
+        .filter(|(score, _)| *score >= query.min_similarity)
+        .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+struct MemoryEntry {
+    entry: CacheEntry,
+    last_hit_at: u64,
+}
+
+/// Cached responses held in the process, least recently used evicted first
+pub struct MemoryResponseCache {
+    entries: Mutex<HashMap<(String, String), MemoryEntry>>,
+    max_entries: usize,
+}
+
+impl MemoryResponseCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            max_entries,
+        }
+    }
+}
+
+#[async_trait]
+impl ResponseCacheBackend for MemoryResponseCache {
+    async fn get(&self, namespace: &str, key: &str, now: u64) -> AriaResult<Option<LLMResponse>> {
+        let mut entries = self.entries.lock().unwrap();
+        Ok(entries.get_mut(&(namespace.to_string(), key.to_string()))
+            .filter(|cached| cached.entry.expires_at > now)
+            .map(|cached| {
+                cached.last_hit_at = now;
+                cached.entry.response.clone()
+            }))
+    }
+
+    async fn nearest(&self, namespace: &str, query: SimilarityQuery<'_>, now: u64) -> AriaResult<Option<(f32, LLMResponse)>> {
+        let mut entries = self.entries.lock().unwrap();
+        let candidates = entries.iter()
+            .filter(|((entry_namespace, _), cached)| {
+                entry_namespace == namespace && cached.entry.key.scope == query.scope && cached.entry.expires_at > now
+            })
+            .filter_map(|(id, cached)| match &cached.entry.embedding {
+                Some((model, embedding)) if model == query.embedding_model => Some((embedding.as_slice(), id)),
+                _ => None,
+            });
+        let Some((score, id)) = closest(&query, candidates).map(|(score, id)| (score, id.clone())) else {
+            return Ok(None);
+        };
+
+        Ok(entries.get_mut(&id).map(|cached| {
+            cached.last_hit_at = now;
+            (score, cached.entry.response.clone())
+        }))
+    }
+
+    async fn put(&self, namespace: &str, entry: CacheEntry) -> AriaResult<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = entry.created_at;
+        entries.insert((namespace.to_string(), entry.key.key.clone()), MemoryEntry { entry, last_hit_at: now });
+
+        if entries.len() > self.max_entries {
+            entries.retain(|_, cached| cached.entry.expires_at > now);
+        }
+        while entries.len() > self.max_entries {
+            let Some(oldest) = entries.iter().min_by_key(|(_, cached)| cached.last_hit_at).map(|(id, _)| id.clone()) else {
+                break;
+            };
+            entries.remove(&oldest);
+        }
+        Ok(())
+    }
+}
+
+/// Cached responses kept in each user's database
+pub struct SqliteResponseCache {
+    database: Arc<DatabaseManager>,
+    /// Upper bound on cached responses per user
+    max_entries: usize,
+}
+
+impl SqliteResponseCache {
+    pub fn new(database: Arc<DatabaseManager>, max_entries: usize) -> Self {
+        Self { database, max_entries }
+    }
+}
+
+fn response_from_record(record: CachedResponseRecord) -> AriaResult<LLMResponse> {
+    serde_json::from_value(record.response).map_err(|e| AriaError::new(
+        ErrorCode::SerializationError,
+        ErrorCategory::System,
+        ErrorSeverity::Low,
+        &format!("Unreadable cached LLM response {}: {}", record.cache_key, e)
+    ))
+}
+
+#[async_trait]
+impl ResponseCacheBackend for SqliteResponseCache {
+    async fn get(&self, namespace: &str, key: &str, now: u64) -> AriaResult<Option<LLMResponse>> {
+        let pool = self.database.get_user_database(namespace).await?;
+        let Some(record) = ResponseCacheOps::get(&pool, key, now).await? else {
+            return Ok(None);
+        };
+
+        ResponseCacheOps::record_hit(&pool, key, now).await?;
+        response_from_record(record).map(Some)
+    }
+
+    async fn nearest(&self, namespace: &str, query: SimilarityQuery<'_>, now: u64) -> AriaResult<Option<(f32, LLMResponse)>> {
+        let pool = self.database.get_user_database(namespace).await?;
+        let records = ResponseCacheOps::list_embedded(&pool, query.scope, query.embedding_model, now).await?;
+        let candidates = records.iter()
+            .filter_map(|record| Some((record.embedding.as_deref()?, record)));
+        let Some((score, record)) = closest(&query, candidates) else {
+            return Ok(None);
+        };
+
+        ResponseCacheOps::record_hit(&pool, &record.cache_key, now).await?;
+        Ok(Some((score, response_from_record(record.clone())?)))
+    }
+
+    async fn put(&self, namespace: &str, entry: CacheEntry) -> AriaResult<()> {
+        let pool = self.database.get_user_database(namespace).await?;
+        let response = serde_json::to_value(&entry.response).map_err(|e| AriaError::new(
+            ErrorCode::SerializationError,
+            ErrorCategory::System,
+            ErrorSeverity::Low,
+            &format!("Failed to serialize LLM response: {}", e)
+        ))?;
+        let (embedding_model, embedding) = entry.embedding.unzip();
+
+        ResponseCacheOps::upsert(&pool, &CachedResponseRecord {
+            cache_key: entry.key.key,
+            scope_key: entry.key.scope,
+            response,
+            embedding,
+            embedding_model,
+            created_at: entry.created_at,
+            last_hit_at: entry.created_at,
+            hits: 0,
+            expires_at: entry.expires_at,
+        }).await?;
+        ResponseCacheOps::evict(&pool, self.max_entries, entry.created_at).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::llm::types::{CacheMode, LLMConfig, ToolCall};
+
+    fn message(role: &str, content: &str) -> LLMMessage {
+        LLMMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    fn request(messages: Vec<LLMMessage>) -> LLMRequest {
+        LLMRequest {
+            messages,
+            config: LLMConfig { model: Some("gpt-4o".to_string()), temperature: 0.0, ..Default::default() },
+            provider: None,
+            tools: None,
+            tool_choice: None,
+            stream: None,
+            cache: None,
+        }
+    }
+
+    fn tool(name: &str) -> Tool {
+        Tool {
+            name: name.to_string(),
+            description: String::new(),
+            parameters: serde_json::json!({"type": "object"}),
+        }
+    }
+
+    fn response(content: &str) -> LLMResponse {
+        LLMResponse {
+            content: content.to_string(),
+            model: "gpt-4o".to_string(),
+            provider: "openai".to_string(),
+            token_usage: None,
+            finish_reason: "stop".to_string(),
+            tool_calls: None,
+        }
+    }
+
+    fn entry(key: CacheKey, content: &str, embedding: Option<Vec<f32>>, now: u64) -> CacheEntry {
+        CacheEntry {
+            key,
+            response: response(content),
+            embedding: embedding.map(|vector| ("test".to_string(), vector)),
+            created_at: now,
+            expires_at: now + 60,
+        }
+    }
+
+    /// A conversation with one tool exchange whose call id is `call_id`
+    fn tool_exchange(call_id: &str) -> Vec<LLMMessage> {
+        vec![
+            message("user", "List the files"),
+            LLMMessage {
+                tool_calls: Some(vec![ToolCall { id: call_id.to_string(), name: "listFilesTool".to_string(), arguments: "{}".to_string() }]),
+                ..message("assistant", "")
+            },
+            LLMMessage { tool_call_id: Some(call_id.to_string()), ..message("tool", "a.txt") },
+        ]
+    }
+
+    #[test]
+    fn keys_ignore_what_cannot_change_the_response() {
+        let base = CacheKey::for_request("openai", &request(vec![message("user", "Summarize the report.")]));
+
+        let mut noisy = request(vec![message("User", "  Summarize the report.  \r\n")]);
+        noisy.stream = Some(true);
+        noisy.cache = Some(CacheMode::Semantic);
+        noisy.config.top_p = Some(1.0);
+        noisy.config.presence_penalty = Some(0.0);
+        assert_eq!(CacheKey::for_request("OpenAI", &noisy), base);
+
+        let mut tools_one_way = request(vec![message("user", "hi")]);
+        tools_one_way.tools = Some(vec![tool("a"), tool("b")]);
+        let mut tools_other_way = tools_one_way.clone();
+        tools_other_way.tools = Some(vec![tool("b"), tool("a")]);
+        tools_other_way.tool_choice = Some(ToolChoice::Auto);
+        assert_eq!(CacheKey::for_request("openai", &tools_one_way), CacheKey::for_request("openai", &tools_other_way));
+
+        assert_eq!(
+            CacheKey::for_request("openai", &request(tool_exchange("call_abc"))),
+            CacheKey::for_request("openai", &request(tool_exchange("call_xyz")))
+        );
+    }
+
+    #[test]
+    fn keys_change_with_what_can_change_the_response() {
+        let base = request(vec![message("user", "Summarize the report.")]);
+        let key = CacheKey::for_request("openai", &base);
+
+        let reworded = request(vec![message("user", "Summarise the report.")]);
+        let reworded_key = CacheKey::for_request("openai", &reworded);
+        assert_ne!(reworded_key.key, key.key);
+        // Only the content differs, so similarity hits may cross between them
+        assert_eq!(reworded_key.scope, key.scope);
+
+        let mut warmer = base.clone();
+        warmer.config.temperature = 0.7;
+        let mut other_model = base.clone();
+        other_model.config.model = Some("gpt-4o-mini".to_string());
+        let mut shorter = base.clone();
+        shorter.config.max_tokens = 16;
+        let mut as_system = base.clone();
+        as_system.messages[0].role = "system".to_string();
+
+        for changed in [warmer, other_model, shorter, as_system] {
+            let changed_key = CacheKey::for_request("openai", &changed);
+            assert_ne!(changed_key.key, key.key);
+            assert_ne!(changed_key.scope, key.scope);
+        }
+    }
+
+    #[tokio::test]
+    async fn memory_cache_serves_unexpired_responses_per_user() {
+        let cache = MemoryResponseCache::new(10);
+        let key = CacheKey::for_request("openai", &request(vec![message("user", "hi")]));
+        cache.put("alice", entry(key.clone(), "hello", None, 100)).await.unwrap();
+
+        assert_eq!(cache.get("alice", &key.key, 120).await.unwrap().unwrap().content, "hello");
+        assert!(cache.get("bob", &key.key, 120).await.unwrap().is_none());
+        assert!(cache.get("alice", &key.key, 160).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn memory_cache_evicts_the_least_recently_used() {
+        let cache = MemoryResponseCache::new(2);
+        let keys: Vec<CacheKey> = ["one", "two", "three"].iter()
+            .map(|prompt| CacheKey::for_request("openai", &request(vec![message("user", prompt)])))
+            .collect();
+
+        cache.put("alice", entry(keys[0].clone(), "1", None, 100)).await.unwrap();
+        cache.put("alice", entry(keys[1].clone(), "2", None, 101)).await.unwrap();
+        cache.get("alice", &keys[0].key, 102).await.unwrap();
+        cache.put("alice", entry(keys[2].clone(), "3", None, 103)).await.unwrap();
+
+        assert!(cache.get("alice", &keys[0].key, 104).await.unwrap().is_some());
+        assert!(cache.get("alice", &keys[1].key, 104).await.unwrap().is_none());
+        assert!(cache.get("alice", &keys[2].key, 104).await.unwrap().is_some());
+    }
+
+    fn query<'a>(scope: &'a str, embedding: &'a [f32]) -> SimilarityQuery<'a> {
+        SimilarityQuery { scope, embedding_model: "test", embedding, min_similarity: 0.95 }
+    }
+
+    #[tokio::test]
+    async fn similar_prompts_hit_within_their_scope() {
+        let cache = MemoryResponseCache::new(10);
+        let key = CacheKey::for_request("openai", &request(vec![message("user", "What is the capital of France?")]));
+        cache.put("alice", entry(key.clone(), "Paris", Some(vec![1.0, 0.0, 0.1]), 100)).await.unwrap();
+
+        let scope = key.scope.as_str();
+        let (similarity, hit) = cache.nearest("alice", query(scope, &[1.0, 0.0, 0.12]), 110).await.unwrap().unwrap();
+        assert!(similarity > 0.99);
+        assert_eq!(hit.content, "Paris");
+
+        assert!(cache.nearest("alice", query(scope, &[0.0, 1.0, 0.0]), 110).await.unwrap().is_none());
+        assert!(cache.nearest("alice", query("other-scope", &[1.0, 0.0, 0.1]), 110).await.unwrap().is_none());
+        assert!(cache.nearest("bob", query(scope, &[1.0, 0.0, 0.1]), 110).await.unwrap().is_none());
+    }
+}
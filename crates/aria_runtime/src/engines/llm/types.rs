@@ -80,6 +80,21 @@ pub struct LLMRequest {
     pub tool_choice: Option<ToolChoice>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
+    /// How the request uses the response cache; the handler's default when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheMode>,
+}
+
+/// How a request uses the response cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheMode {
+    /// Reuse the response to an identical request
+    Exact,
+    /// Also reuse the response to a similar prompt; only applies at temperature 0
+    Semantic,
+    /// Neither reuse nor store a response
+    Bypass,
 }
 
 /// Token usage information
@@ -103,6 +118,75 @@ pub struct LLMResponse {
     pub tool_calls: Option<Vec<ToolCall>>,
 }
 
+/// Reassembles a streamed response from its chunks.
+/// Content is concatenated and tool-call fragments sharing an id extend one call;
+/// a chunk's `finish_reason` is "streaming" until the provider reports a real one.
+#[derive(Debug, Clone, Default)]
+pub struct StreamedResponse {
+    content: String,
+    model: String,
+    provider: String,
+    finish_reason: Option<String>,
+    token_usage: Option<TokenUsage>,
+    tool_calls: Vec<ToolCall>,
+}
+
+impl StreamedResponse {
+    /// Fold one chunk into the response
+    pub fn push(&mut self, chunk: &LLMResponse) {
+        self.content.push_str(&chunk.content);
+        if !chunk.model.is_empty() {
+            self.model = chunk.model.clone();
+        }
+        if !chunk.provider.is_empty() {
+            self.provider = chunk.provider.clone();
+        }
+        if chunk.finish_reason != "streaming" {
+            self.finish_reason = Some(chunk.finish_reason.clone());
+        }
+        if let Some(usage) = &chunk.token_usage {
+            self.token_usage = Some(usage.clone());
+        }
+
+        for fragment in chunk.tool_calls.iter().flatten() {
+            match self.tool_calls.iter_mut().find(|call| call.id == fragment.id) {
+                Some(call) => {
+                    if call.name.is_empty() {
+                        call.name = fragment.name.clone();
+                    }
+                    call.arguments.push_str(&fragment.arguments);
+                }
+                None => self.tool_calls.push(fragment.clone()),
+            }
+        }
+    }
+
+    /// Text received so far
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// The complete response
+    pub fn into_response(self) -> LLMResponse {
+        LLMResponse {
+            content: self.content,
+            model: self.model,
+            provider: self.provider,
+            token_usage: self.token_usage,
+            finish_reason: self.finish_reason.unwrap_or_else(|| "stop".to_string()),
+            tool_calls: if self.tool_calls.is_empty() { None } else { Some(self.tool_calls) },
+        }
+    }
+}
+
+/// Embedding response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingResponse {
+    pub model: String,
+    pub provider: String,
+    pub embeddings: Vec<Vec<f32>>,
+}
+
 /// Provider capabilities
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderCapabilities {
@@ -112,6 +196,19 @@ pub struct ProviderCapabilities {
     pub supports_vision: bool,
     pub max_tokens: u32,
     pub rate_limits: Option<RateLimits>,
+    /// Prompt context window per model, in tokens
+    #[serde(default)]
+    pub context_windows: std::collections::HashMap<String, u32>,
+}
+
+impl ProviderCapabilities {
+    /// Context window for `model`, falling back to the known-model table
+    pub fn context_limit(&self, model: &str) -> u32 {
+        self.context_windows.get(model)
+            .copied()
+            .or_else(|| super::context_window::known_context_window(model))
+            .unwrap_or(super::context_window::DEFAULT_CONTEXT_WINDOW)
+    }
 }
 
 /// Rate limiting information
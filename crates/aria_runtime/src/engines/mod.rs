@@ -20,6 +20,7 @@ use crate::engines::icc::ICCEngine;
 use crate::engines::intelligence::{IntelligenceEngine, IntelligenceConfig};
 use crate::engines::observability::ObservabilityManager;
 use crate::engines::streaming::StreamingService;
+use crate::costs::CostTracker;
 
 use crate::database::{DatabaseManager, DatabaseConfig};
 use std::collections::HashMap;
@@ -61,6 +62,7 @@ pub struct AriaEngines {
     pub streaming: Arc<StreamingService>,
     pub intelligence: Arc<IntelligenceEngine>,
     pub pkg_store: Arc<pkg_store::PackageStore>,
+    pub costs: Arc<CostTracker>,
 }
 
 impl AriaEngines {
@@ -171,6 +173,10 @@ impl AriaEngines {
             shutdown_errors.push("Execution engine shutdown failed");
         }
         
+        // Remove warm tool containers before the services they report to go away
+        self.tool_registry.bundle_runner().shutdown().await;
+        self.tool_registry.container_tools().shutdown().await;
+        
         // Shutdown observability and streaming services last
         if let Err(e) = self.streaming.stop().await {
             shutdown_errors.push("Streaming service shutdown failed");
@@ -321,14 +327,9 @@ impl LLMHandlerInterface for LLMHandlerWrapper {
         self.handler.complete(request).await
     }
     
-    async fn stream_complete(&self, _request: crate::engines::llm::types::LLMRequest) -> AriaResult<crate::engines::llm::types::LLMStreamResponse> {
-        // For now, return an error since streaming isn't fully implemented
-        Err(crate::errors::AriaError::new(
-            crate::errors::ErrorCode::LLMApiError,
-            crate::errors::ErrorCategory::LLM,
-            crate::errors::ErrorSeverity::Medium,
-            "Streaming not yet implemented"
-        ))
+    async fn stream_complete(&self, request: crate::engines::llm::types::LLMRequest) -> AriaResult<crate::engines::llm::types::LLMStreamResponse> {
+        let stream = self.handler.complete_stream(request).await?;
+        Ok(crate::engines::llm::types::LLMStreamResponse { stream })
     }
     
     fn get_providers(&self) -> Vec<String> {
@@ -343,8 +344,12 @@ impl LLMHandlerInterface for LLMHandlerWrapper {
     
     async fn get_provider_capabilities(&self, _provider: &str) -> AriaResult<crate::engines::llm::types::ProviderCapabilities> {
         // TODO: Implement when provider capabilities are added
+        let models = vec!["gpt-4o".to_string()];
+        let context_windows = models.iter()
+            .filter_map(|m| crate::engines::llm::context_window::known_context_window(m).map(|w| (m.clone(), w)))
+            .collect();
         Ok(crate::engines::llm::types::ProviderCapabilities {
-            models: vec!["gpt-4o".to_string()],
+            models,
             supports_streaming: true,
             supports_functions: true,
             supports_vision: false,
@@ -354,6 +359,7 @@ impl LLMHandlerInterface for LLMHandlerWrapper {
                 tokens_per_minute: 600000,
                 requests_per_day: Some(100000),
             }),
+            context_windows,
         })
     }
     
@@ -150,6 +150,10 @@ pub struct LlmStats {
     pub tokens_cached: u64,
     pub avg_response_time_ms: f64,
     pub cost_estimate_usd: f64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    /// What the calls served from the response cache would have cost
+    pub cache_savings_usd: f64,
 }
 
 /// Health status for system components
@@ -307,6 +311,9 @@ impl ObservabilityManager {
                 tokens_cached: 0,
                 avg_response_time_ms: 0.0,
                 cost_estimate_usd: 0.0,
+                cache_hits: 0,
+                cache_misses: 0,
+                cache_savings_usd: 0.0,
             },
         };
 
@@ -465,6 +472,38 @@ impl ObservabilityManager {
         Ok(())
     }
 
+    /// Add the cost of a model call to the LLM metrics
+    pub async fn record_llm_cost(&self, cost_usd: f64) -> Result<(), AriaError> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let mut metrics = self.metrics.write().await;
+        metrics.llm.cost_estimate_usd += cost_usd;
+        metrics.timestamp = current_timestamp();
+
+        Ok(())
+    }
+
+    /// Count a response cache lookup; a hit adds the tokens and spend it saved
+    pub async fn record_llm_cache_lookup(&self, hit: bool, tokens_saved: u64, saved_usd: f64) -> Result<(), AriaError> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let mut metrics = self.metrics.write().await;
+        if hit {
+            metrics.llm.cache_hits += 1;
+            metrics.llm.tokens_cached += tokens_saved;
+            metrics.llm.cache_savings_usd += saved_usd;
+        } else {
+            metrics.llm.cache_misses += 1;
+        }
+        metrics.timestamp = current_timestamp();
+
+        Ok(())
+    }
+
     /// Record an error event
     pub async fn record_error(
         &self,
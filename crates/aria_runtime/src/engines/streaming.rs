@@ -461,7 +461,19 @@ impl StreamingService {
 
 /// Convert observability event to SSE event
 fn convert_to_sse_event(event: ObservabilityEvent) -> SseEvent {
-    let (event_type, data, timestamp) = match &event {
+    let (event_type, data, timestamp) = event_data(&event);
+
+    SseEvent {
+        id: Uuid::new_v4().to_string(),
+        event: event_type.to_string(),
+        data,
+        timestamp,
+    }
+}
+
+/// Type, JSON payload and timestamp of an observability event, as streamed to clients
+pub fn event_data(event: &ObservabilityEvent) -> (&'static str, serde_json::Value, u64) {
+    match event {
         ObservabilityEvent::MetricsUpdate { timestamp, metrics } => {
             ("metrics", serde_json::to_value(metrics).unwrap(), *timestamp)
         },
@@ -534,13 +546,6 @@ fn convert_to_sse_event(event: ObservabilityEvent) -> SseEvent {
             });
             ("context", context_data, *timestamp)
         },
-    };
-
-    SseEvent {
-        id: Uuid::new_v4().to_string(),
-        event: event_type.to_string(),
-        data,
-        timestamp,
     }
 }
 
@@ -129,6 +129,16 @@ impl SystemPromptService {
 
     /// Generate a complete system prompt for an agent configuration
     pub fn generate_system_prompt(&self, agent_config: &AgentConfig, has_tools: bool) -> String {
+        self.generate_system_prompt_with_memories(agent_config, has_tools, &[])
+    }
+
+    /// Generate a system prompt that includes memories recalled for the current task
+    pub fn generate_system_prompt_with_memories(
+        &self,
+        agent_config: &AgentConfig,
+        has_tools: bool,
+        memories: &[WorkingMemoryEntry],
+    ) -> String {
         let mut prompt = String::new();
 
         // Start with base prompt
@@ -177,6 +187,17 @@ impl SystemPromptService {
         // Add memory context if available
         if agent_config.memory_enabled.unwrap_or(false) {
             prompt.push_str("\n\nMEMORY: You have access to conversation history and can reference previous interactions.");
+
+            if !memories.is_empty() {
+                prompt.push_str("\n\nRELEVANT MEMORIES FROM PREVIOUS SESSIONS:");
+                for memory in memories {
+                    let value = match &memory.value.0 {
+                        serde_json::Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    prompt.push_str(&format!("\n- {}: {}", memory.key, value));
+                }
+            }
         }
 
         prompt
@@ -184,7 +205,17 @@ impl SystemPromptService {
 
     /// Generate orchestration-specific system prompt
     pub fn generate_orchestration_prompt(&self, task: &str, agent_config: &AgentConfig) -> String {
-        let mut prompt = self.generate_system_prompt(agent_config, true);
+        self.generate_orchestration_prompt_with_memories(task, agent_config, &[])
+    }
+
+    /// Generate an orchestration prompt that includes recalled memories
+    pub fn generate_orchestration_prompt_with_memories(
+        &self,
+        task: &str,
+        agent_config: &AgentConfig,
+        memories: &[WorkingMemoryEntry],
+    ) -> String {
+        let mut prompt = self.generate_system_prompt_with_memories(agent_config, true, memories);
         
         // Add orchestration-specific guidance
         if let Some(orchestration_guidance) = self.tool_guidance.get("orchestration") {
@@ -371,6 +402,34 @@ mod tests {
         assert!(prompt.contains("FIRST tool"));
     }
 
+    #[test]
+    fn test_prompt_includes_recalled_memories() {
+        let service = SystemPromptService::new();
+        let mut agent_config = AgentConfig::default();
+        agent_config.name = "Assistant".to_string();
+        agent_config.memory_enabled = Some(true);
+
+        let memory = WorkingMemoryEntry {
+            id: crate::deep_size::DeepUuid(uuid::Uuid::new_v4()),
+            key: "preferred_language".to_string(),
+            value: crate::deep_size::DeepValue(serde_json::json!("Rust")),
+            entry_type: MemoryEntryType::Learning,
+            created_at: crate::deep_size::DeepSystemTime(std::time::SystemTime::now()),
+            last_accessed: crate::deep_size::DeepSystemTime(std::time::SystemTime::now()),
+            access_count: 0,
+            ttl: None,
+            tags: vec![],
+        };
+
+        let prompt = service.generate_system_prompt_with_memories(&agent_config, false, &[memory.clone()]);
+        assert!(prompt.contains("RELEVANT MEMORIES"));
+        assert!(prompt.contains("- preferred_language: Rust"));
+
+        agent_config.memory_enabled = Some(false);
+        let prompt = service.generate_system_prompt_with_memories(&agent_config, false, &[memory]);
+        assert!(!prompt.contains("RELEVANT MEMORIES"));
+    }
+
     #[test]
     fn test_reflection_prompt() {
         let service = SystemPromptService::new();
@@ -0,0 +1,642 @@
+/*!
+# Bundle Tool Runner
+
+Executes `ToolType::Bundle` tools inside warm Quilt containers. Each bundle is
+materialized once into a host workspace that is bind-mounted into a long-lived
+container running Bun; tool calls exchange JSON parameters and results through
+per-call files in that workspace rather than through the command line.
+*/
+
+use crate::deep_size::DeepValue;
+use crate::engines::container::quilt::quilt_proto::CreateContainerRequest;
+use crate::engines::tool_registry::container_pool::{ContainerLimits, ToolConcurrency, WarmContainerInfo, WarmContainerPool};
+use crate::engines::container::quilt::QuiltService;
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::types::{ResourceRequirements, ResourceUsage, ToolResult};
+use pkg_store::bundle::LoadedBundle;
+use pkg_store::PackageStore;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{debug, info, warn};
+
+/// Script that loads a tool entry point, calls it with the call's parameters and
+/// writes the outcome next to them. It is copied into every bundle workspace.
+const TOOL_RUNNER_SCRIPT: &str = r#"const [entry, callDir] = process.argv.slice(2);
+const params = JSON.parse(await Bun.file(`${callDir}/params.json`).text());
+let outcome;
+try {
+  const mod = await import(`/workspace/${entry}`);
+  const fn = typeof mod.default === "function" ? mod.default : (mod.execute ?? mod.run);
+  if (typeof fn !== "function") {
+    throw new Error(`${entry} does not export a default, execute or run function`);
+  }
+  outcome = { success: true, result: (await fn(params)) ?? null };
+} catch (err) {
+  outcome = { success: false, error: err instanceof Error ? (err.stack ?? err.message) : String(err) };
+}
+const cpu = process.cpuUsage();
+outcome.usage = { cpu_time_us: cpu.user + cpu.system, rss_bytes: process.memoryUsage().rss };
+await Bun.write(`${callDir}/result.json`, JSON.stringify(outcome));
+process.exit(outcome.success ? 0 : 1);
+"#;
+
+/// Name of the runner script inside the workspace's `.aria` directory
+const TOOL_RUNNER_FILE: &str = "tool-runner.ts";
+
+/// Longest stderr/stdout excerpt attached to a tool result
+const MAX_OUTPUT_EXCERPT: usize = 4096;
+
+/// Configuration for bundle tool containers
+#[derive(Debug, Clone)]
+pub struct BundleToolRunnerConfig {
+    /// Image with Bun on its PATH used for every bundle container
+    pub image_path: String,
+    /// Extra setup commands applied to the image (served from quilt's layer cache)
+    pub setup_commands: Vec<String>,
+    /// Host directory bundles are materialized into
+    pub workspace_root: PathBuf,
+    /// Bun executable inside the container
+    pub bun_executable: String,
+    /// Timeout for tools whose manifest does not declare one
+    pub default_timeout_seconds: u64,
+    /// Warm containers unused for this long are removed
+    pub idle_timeout_seconds: u64,
+    /// How long to wait for a new container to reach the running state
+    pub startup_timeout_seconds: u64,
+}
+
+impl Default for BundleToolRunnerConfig {
+    fn default() -> Self {
+        Self {
+            image_path: "/var/lib/quilt/images/bun.tar.gz".to_string(),
+            setup_commands: Vec::new(),
+            workspace_root: PathBuf::from("/tmp/aria-bundle-tools"),
+            bun_executable: "bun".to_string(),
+            default_timeout_seconds: 60,
+            idle_timeout_seconds: 600,
+            startup_timeout_seconds: 30,
+        }
+    }
+}
+
+/// What the runner script wrote to `result.json`
+#[derive(Debug, Default, Deserialize)]
+struct RunnerOutcome {
+    success: bool,
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    usage: RunnerUsage,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RunnerUsage {
+    #[serde(default)]
+    cpu_time_us: u64,
+    #[serde(default)]
+    rss_bytes: u64,
+}
+
+/// Runs bundle tools in warm per-bundle containers
+pub struct BundleToolRunner {
+    config: BundleToolRunnerConfig,
+    quilt_service: Arc<Mutex<QuiltService>>,
+    pkg_store: RwLock<Option<Arc<PackageStore>>>,
+    /// Warm containers keyed by bundle hash and limits
+    containers: WarmContainerPool,
+    concurrency: ToolConcurrency,
+}
+
+impl BundleToolRunner {
+    pub fn new(quilt_service: Arc<Mutex<QuiltService>>, config: BundleToolRunnerConfig) -> Self {
+        Self {
+            config,
+            quilt_service,
+            pkg_store: RwLock::new(None),
+            containers: WarmContainerPool::new(),
+            concurrency: ToolConcurrency::new(),
+        }
+    }
+
+    /// Provide the package store bundles are loaded from
+    pub async fn attach_package_store(&self, pkg_store: Arc<PackageStore>) {
+        *self.pkg_store.write().await = Some(pkg_store);
+    }
+
+    /// Execute `entry_point` from the bundle `bundle_hash` with `parameters`
+    pub async fn execute(
+        &self,
+        tool_name: &str,
+        bundle_hash: &str,
+        entry_point: &str,
+        parameters: &Value,
+        requirements: &ResourceRequirements,
+    ) -> AriaResult<ToolResult> {
+        validate_entry_point(entry_point)?;
+        self.reap_idle_containers().await;
+
+        let limits = ContainerLimits::from_requirements(requirements, self.config.default_timeout_seconds);
+        let _permit = self.concurrency.acquire(tool_name, &limits).await?;
+
+        let workspace = self.ensure_workspace(bundle_hash).await?;
+        let call_id = uuid::Uuid::new_v4().simple().to_string();
+        let call_dir = workspace.join(".aria").join("calls").join(&call_id);
+        write_file(&call_dir.join("params.json"), serde_json::to_vec(parameters).unwrap_or_default()).await?;
+
+        let command = vec![format!(
+            "cd /workspace && {} run .aria/{} {} .aria/calls/{}",
+            self.config.bun_executable, TOOL_RUNNER_FILE, entry_point, call_id
+        )];
+
+        let start_time = Instant::now();
+        let outcome = self.run_with_retry(bundle_hash, &workspace, &limits, command).await;
+        let execution_time_ms = start_time.elapsed().as_millis() as u64;
+
+        let result = match outcome {
+            Ok((container_id, exec)) => {
+                let runner_outcome = match tokio::fs::read(call_dir.join("result.json")).await {
+                    Ok(bytes) => serde_json::from_slice::<RunnerOutcome>(&bytes).ok(),
+                    Err(_) => None,
+                };
+                let mut result = tool_result_from_outcome(runner_outcome, exec.exit_code, &exec.stderr, &limits);
+                result.execution_time_ms = execution_time_ms;
+                result.metadata.insert("bundle_hash".to_string(), DeepValue::string(bundle_hash.to_string()));
+                result.metadata.insert("container_id".to_string(), DeepValue::string(container_id));
+                result.metadata.insert("exit_code".to_string(), DeepValue::number(exec.exit_code as i64));
+                if !exec.stdout.trim().is_empty() {
+                    result.metadata.insert("stdout".to_string(), DeepValue::string(excerpt(&exec.stdout)));
+                }
+                if !exec.stderr.trim().is_empty() {
+                    result.metadata.insert("stderr".to_string(), DeepValue::string(excerpt(&exec.stderr)));
+                }
+                Ok(result)
+            }
+            Err(e) => Err(e),
+        };
+
+        let _ = tokio::fs::remove_dir_all(&call_dir).await;
+
+        match &result {
+            Ok(result) => info!("Bundle tool '{}' finished in {}ms (success: {})", tool_name, execution_time_ms, result.success),
+            Err(e) => warn!("Bundle tool '{}' failed: {}", tool_name, e),
+        }
+        result
+    }
+
+    /// Start the warm container for a bundle ahead of its first tool call
+    pub async fn prewarm(&self, bundle_hash: &str, requirements: &ResourceRequirements) -> AriaResult<String> {
+        let limits = ContainerLimits::from_requirements(requirements, self.config.default_timeout_seconds);
+        let workspace = self.ensure_workspace(bundle_hash).await?;
+        self.acquire_container(bundle_hash, &workspace, &limits).await
+    }
+
+    /// List warm containers and how long they have been idle
+    pub async fn list_warm_containers(&self) -> Vec<WarmContainerInfo> {
+        self.containers.list().await
+    }
+
+    /// Remove every warm container, e.g. on runtime shutdown
+    pub async fn shutdown(&self) {
+        for container_id in self.containers.drain().await {
+            self.remove_container(&container_id).await;
+        }
+    }
+
+    /// Remove warm containers that have been idle longer than the configured timeout
+    pub async fn reap_idle_containers(&self) {
+        for container_id in self.containers.take_idle().await {
+            debug!("Removing idle bundle container {}", container_id);
+            self.remove_container(&container_id).await;
+        }
+    }
+
+    /// Run `command` in the bundle's warm container. A container that died since
+    /// it was last used is replaced once before giving up.
+    async fn run_with_retry(
+        &self,
+        bundle_hash: &str,
+        workspace: &Path,
+        limits: &ContainerLimits,
+        command: Vec<String>,
+    ) -> AriaResult<(String, crate::types::ContainerExecutionResult)> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let container_id = self.acquire_container(bundle_hash, workspace, limits).await?;
+            // Clone the client so a long-running tool doesn't hold the shared service lock
+            let mut quilt = self.quilt_service.lock().await.clone();
+
+            match tokio::time::timeout(limits.timeout, quilt.run_in_container(container_id.clone(), command.clone())).await {
+                Ok(Ok(exec)) => return Ok((container_id, exec)),
+                Ok(Err(e)) => {
+                    self.discard_container(bundle_hash, limits, &container_id).await;
+                    if attempt >= 2 {
+                        return Err(e);
+                    }
+                    warn!("Bundle container {} is unusable, replacing it: {}", container_id, e);
+                }
+                Err(_) => {
+                    // The tool may still be running; removing the container is the only way to stop it
+                    self.discard_container(bundle_hash, limits, &container_id).await;
+                    return Err(AriaError::new(
+                        ErrorCode::Timeout,
+                        ErrorCategory::Tool,
+                        ErrorSeverity::Medium,
+                        &format!("Bundle tool timed out after {}s", limits.timeout.as_secs()),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Return the warm container for this bundle and limits, starting one if needed
+    async fn acquire_container(&self, bundle_hash: &str, workspace: &Path, limits: &ContainerLimits) -> AriaResult<String> {
+        let idle_timeout = Duration::from_secs(self.config.idle_timeout_seconds);
+        self.containers
+            .get_or_start(&limits.container_key(bundle_hash), idle_timeout, || self.start_container(bundle_hash, workspace, limits))
+            .await
+    }
+
+    /// Forget and remove a container, unless the pool already moved on to a new one
+    async fn discard_container(&self, bundle_hash: &str, limits: &ContainerLimits, container_id: &str) {
+        if self.containers.discard(&limits.container_key(bundle_hash), container_id).await {
+            self.remove_container(container_id).await;
+        }
+    }
+
+    async fn start_container(&self, bundle_hash: &str, workspace: &Path, limits: &ContainerLimits) -> AriaResult<String> {
+        let mut environment = HashMap::new();
+        environment.insert("ARIA_BUNDLE_HASH".to_string(), bundle_hash.to_string());
+        environment.insert("NODE_ENV".to_string(), "production".to_string());
+
+        let mut labels = HashMap::new();
+        labels.insert("aria.bundle".to_string(), bundle_hash.to_string());
+        labels.insert("aria.role".to_string(), "bundle-tools".to_string());
+
+        let request = CreateContainerRequest {
+            image_path: self.config.image_path.clone(),
+            command: vec!["tail".to_string(), "-f".to_string(), "/dev/null".to_string()],
+            environment,
+            working_directory: "/workspace".to_string(),
+            setup_commands: self.config.setup_commands.clone(),
+            memory_limit_mb: limits.memory_limit_mb,
+            cpu_limit_percent: limits.cpu_limit_percent,
+            enable_pid_namespace: true,
+            enable_mount_namespace: true,
+            enable_uts_namespace: true,
+            enable_ipc_namespace: true,
+            enable_network_namespace: true,
+            auto_start: true,
+            volumes: vec![format!("{}:/workspace", workspace.display())],
+            labels,
+            ..Default::default()
+        };
+
+        let container_id = self.quilt_service.lock().await.create_container_with(request).await?;
+        info!("Started bundle container {} for bundle {}", container_id, bundle_hash);
+
+        let startup_timeout = Duration::from_secs(self.config.startup_timeout_seconds);
+        let mut quilt = self.quilt_service.lock().await.clone();
+        if let Err(e) = quilt.wait_until_running(&container_id, startup_timeout).await {
+            self.remove_container(&container_id).await;
+            return Err(e);
+        }
+
+        if workspace_has_dependencies(workspace).await {
+            let install = format!("cd /workspace && {} install --production", self.config.bun_executable);
+            let exec = quilt.run_in_container(container_id.clone(), vec![install]).await;
+            match exec {
+                Ok(exec) if exec.exit_code == 0 => {}
+                Ok(exec) => {
+                    self.remove_container(&container_id).await;
+                    return Err(AriaError::new(
+                        ErrorCode::BundleLoadError,
+                        ErrorCategory::Bundle,
+                        ErrorSeverity::High,
+                        &format!("Installing dependencies for bundle {} failed: {}", bundle_hash, excerpt(&exec.stderr)),
+                    ));
+                }
+                Err(e) => {
+                    self.remove_container(&container_id).await;
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(container_id)
+    }
+
+    async fn remove_container(&self, container_id: &str) {
+        if let Err(e) = self.quilt_service.lock().await.remove_container(container_id.to_string()).await {
+            warn!("Failed to remove bundle container {}: {}", container_id, e);
+        }
+    }
+
+    /// Materialize the bundle into `{workspace_root}/{bundle_hash}`. Bundles are
+    /// content addressed, so an existing workspace never needs rebuilding; new ones
+    /// are staged in a temporary directory and renamed into place.
+    async fn ensure_workspace(&self, bundle_hash: &str) -> AriaResult<PathBuf> {
+        if bundle_hash.is_empty() || !bundle_hash.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(AriaError::new(
+                ErrorCode::BundleValidationError,
+                ErrorCategory::Bundle,
+                ErrorSeverity::Medium,
+                &format!("Invalid bundle hash '{}'", bundle_hash),
+            ));
+        }
+
+        let workspace = self.config.workspace_root.join(bundle_hash);
+        if tokio::fs::metadata(workspace.join(".aria").join(TOOL_RUNNER_FILE)).await.is_ok() {
+            return Ok(workspace);
+        }
+
+        let bundle = self.load_bundle(bundle_hash).await?;
+        let staging = self.config.workspace_root.join(format!(".build-{}-{}", bundle_hash, uuid::Uuid::new_v4().simple()));
+
+        let staged = async {
+            for (path, content) in &bundle.source_files {
+                if !is_safe_relative_path(path) {
+                    warn!("Skipping bundle file with unsafe path: {}", path.display());
+                    continue;
+                }
+                write_file(&staging.join(path), content.as_bytes().to_vec()).await?;
+            }
+            write_file(&staging.join("package.json"), bundle.generate_package_json().into_bytes()).await?;
+            write_file(&staging.join(".aria").join(TOOL_RUNNER_FILE), TOOL_RUNNER_SCRIPT.as_bytes().to_vec()).await?;
+            tokio::fs::create_dir_all(staging.join(".aria").join("calls")).await.map_err(io_error)
+        }.await;
+
+        if let Err(e) = staged {
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+            return Err(e);
+        }
+
+        if tokio::fs::rename(&staging, &workspace).await.is_err() {
+            // Another call finished materializing the same bundle first
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+        }
+
+        info!("Materialized bundle {} ({}) at {}", bundle.manifest.name, bundle_hash, workspace.display());
+        Ok(workspace)
+    }
+
+    async fn load_bundle(&self, bundle_hash: &str) -> AriaResult<LoadedBundle> {
+        let pkg_store = self.pkg_store.read().await.clone().ok_or_else(|| AriaError::new(
+            ErrorCode::SystemNotReady,
+            ErrorCategory::Bundle,
+            ErrorSeverity::High,
+            "No package store attached to the bundle tool runner",
+        ))?;
+
+        let bundle_data = pkg_store.get_bundle(bundle_hash).await
+            .map_err(|e| AriaError::new(
+                ErrorCode::StorageError,
+                ErrorCategory::Bundle,
+                ErrorSeverity::High,
+                &format!("Failed to get bundle from storage: {}", e),
+            ))?
+            .ok_or_else(|| AriaError::new(
+                ErrorCode::BundleNotFound,
+                ErrorCategory::Bundle,
+                ErrorSeverity::Medium,
+                &format!("Bundle not found: {}", bundle_hash),
+            ))?;
+
+        let temp_path = self.config.workspace_root.join(format!(".bundle-{}-{}.aria", bundle_hash, uuid::Uuid::new_v4().simple()));
+        write_file(&temp_path, bundle_data).await?;
+
+        let bundle = LoadedBundle::load_from_file(&temp_path.to_string_lossy()).await
+            .map_err(|e| AriaError::new(
+                ErrorCode::BundleLoadError,
+                ErrorCategory::Bundle,
+                ErrorSeverity::High,
+                &format!("Failed to load bundle: {}", e),
+            ));
+
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        bundle
+    }
+}
+
+/// Entry points are spliced into a shell command, so only plain relative paths are accepted
+pub fn validate_entry_point(entry_point: &str) -> AriaResult<()> {
+    let valid = !entry_point.is_empty()
+        && entry_point.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '.' | '_' | '-' | '@'))
+        && is_safe_relative_path(Path::new(entry_point));
+
+    if !valid {
+        return Err(AriaError::new(
+            ErrorCode::ToolInvalidParameters,
+            ErrorCategory::Tool,
+            ErrorSeverity::Medium,
+            &format!("Invalid bundle tool entry point '{}'", entry_point),
+        ));
+    }
+    Ok(())
+}
+
+fn is_safe_relative_path(path: &Path) -> bool {
+    path.components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Build the `ToolResult` for a finished call. A tool may return a plain value,
+/// or an object shaped like a tool result (`success`, `result`, `error`,
+/// `metadata`) to report its own failures and metadata.
+fn tool_result_from_outcome(
+    outcome: Option<RunnerOutcome>,
+    exit_code: i32,
+    stderr: &str,
+    limits: &ContainerLimits,
+) -> ToolResult {
+    let Some(outcome) = outcome else {
+        // The runner never wrote a result: the process crashed or was killed
+        let mut error = format!("Bundle tool exited with code {} without producing a result", exit_code);
+        if exit_code == 137 && limits.memory_limit_mb > 0 {
+            error.push_str(&format!(" (killed, possibly exceeding its {}MB memory limit)", limits.memory_limit_mb));
+        }
+        if !stderr.trim().is_empty() {
+            error.push_str(&format!(": {}", excerpt(stderr)));
+        }
+        return ToolResult {
+            success: false,
+            error: Some(error),
+            resource_usage: Some(ResourceUsage::default()),
+            ..Default::default()
+        };
+    };
+
+    let resource_usage = Some(ResourceUsage {
+        cpu_time_ms: outcome.usage.cpu_time_us / 1000,
+        memory_peak_mb: outcome.usage.rss_bytes / (1024 * 1024),
+        ..Default::default()
+    });
+
+    if !outcome.success {
+        let mut error = outcome.error.unwrap_or_else(|| "Bundle tool failed".to_string());
+        if !stderr.trim().is_empty() {
+            error.push_str(&format!("\n{}", excerpt(stderr)));
+        }
+        return ToolResult {
+            success: false,
+            error: Some(error),
+            resource_usage,
+            ..Default::default()
+        };
+    }
+
+    match outcome.result {
+        Some(Value::Object(mut object)) if matches!(object.get("success"), Some(Value::Bool(_))) => {
+            let success = object.get("success").and_then(Value::as_bool).unwrap_or(false);
+            let error = object.remove("error").and_then(|e| match e {
+                Value::Null => None,
+                Value::String(s) => Some(s),
+                other => Some(other.to_string()),
+            });
+            let metadata = match object.remove("metadata") {
+                Some(Value::Object(map)) => map.into_iter().map(|(k, v)| (k, DeepValue(v))).collect(),
+                _ => HashMap::new(),
+            };
+            ToolResult {
+                success,
+                result: object.remove("result").map(DeepValue),
+                error: if success { error } else { error.or_else(|| Some("Bundle tool reported failure".to_string())) },
+                metadata,
+                execution_time_ms: 0,
+                resource_usage,
+            }
+        }
+        result => ToolResult {
+            success: true,
+            result: result.map(DeepValue),
+            error: None,
+            metadata: HashMap::new(),
+            execution_time_ms: 0,
+            resource_usage,
+        },
+    }
+}
+
+async fn workspace_has_dependencies(workspace: &Path) -> bool {
+    let Ok(bytes) = tokio::fs::read(workspace.join("package.json")).await else {
+        return false;
+    };
+    serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .and_then(|package| package.get("dependencies").and_then(Value::as_object).map(|deps| !deps.is_empty()))
+        .unwrap_or(false)
+}
+
+/// Keep the tail of long output, where errors usually are
+fn excerpt(output: &str) -> String {
+    let output = output.trim();
+    if output.len() <= MAX_OUTPUT_EXCERPT {
+        return output.to_string();
+    }
+    let mut start = output.len() - MAX_OUTPUT_EXCERPT;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("...{}", &output[start..])
+}
+
+async fn write_file(path: &Path, contents: Vec<u8>) -> AriaResult<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+    }
+    tokio::fs::write(path, contents).await.map_err(io_error)
+}
+
+fn io_error(e: std::io::Error) -> AriaError {
+    AriaError::new(
+        ErrorCode::IoError,
+        ErrorCategory::Bundle,
+        ErrorSeverity::High,
+        &format!("Bundle workspace I/O failed: {}", e),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> ContainerLimits {
+        ContainerLimits::from_requirements(&ResourceRequirements::default(), 60)
+    }
+
+    #[test]
+    fn test_entry_point_validation() {
+        assert!(validate_entry_point("implementations/tools/fetch.js").is_ok());
+        assert!(validate_entry_point("tools/@scope/run-it_v2.ts").is_ok());
+        assert!(validate_entry_point("").is_err());
+        assert!(validate_entry_point("/etc/passwd").is_err());
+        assert!(validate_entry_point("../outside.js").is_err());
+        assert!(validate_entry_point("tool.js; rm -rf /").is_err());
+        assert!(validate_entry_point("$(whoami).js").is_err());
+    }
+
+    #[test]
+    fn test_plain_and_structured_results() {
+        let plain = RunnerOutcome {
+            success: true,
+            result: Some(serde_json::json!({"count": 3})),
+            usage: RunnerUsage { cpu_time_us: 12_000, rss_bytes: 64 * 1024 * 1024 },
+            ..Default::default()
+        };
+        let result = tool_result_from_outcome(Some(plain), 0, "", &limits());
+        assert!(result.success);
+        assert_eq!(result.result.unwrap().0, serde_json::json!({"count": 3}));
+        let usage = result.resource_usage.unwrap();
+        assert_eq!(usage.cpu_time_ms, 12);
+        assert_eq!(usage.memory_peak_mb, 64);
+
+        let structured = RunnerOutcome {
+            success: true,
+            result: Some(serde_json::json!({
+                "success": false,
+                "error": "quota exceeded",
+                "metadata": {"retry_after": 30}
+            })),
+            ..Default::default()
+        };
+        let result = tool_result_from_outcome(Some(structured), 0, "", &limits());
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some("quota exceeded"));
+        assert_eq!(result.metadata.get("retry_after").unwrap().0, serde_json::json!(30));
+    }
+
+    #[test]
+    fn test_failures_surface_stderr() {
+        let thrown = RunnerOutcome {
+            success: false,
+            error: Some("Error: boom".to_string()),
+            ..Default::default()
+        };
+        let result = tool_result_from_outcome(Some(thrown), 1, "warning: deprecated api", &limits());
+        assert!(!result.success);
+        let error = result.error.unwrap();
+        assert!(error.contains("boom"));
+        assert!(error.contains("deprecated api"));
+
+        let killed = tool_result_from_outcome(None, 137, "Killed", &limits());
+        let error = killed.error.unwrap();
+        assert!(error.contains("137"));
+        assert!(error.contains("memory limit"));
+        assert!(error.contains("Killed"));
+    }
+
+    #[test]
+    fn test_excerpt_keeps_tail() {
+        let long = format!("{}END", "x".repeat(MAX_OUTPUT_EXCERPT * 2));
+        let cut = excerpt(&long);
+        assert!(cut.starts_with("..."));
+        assert!(cut.ends_with("END"));
+        assert!(cut.len() <= MAX_OUTPUT_EXCERPT + 3);
+    }
+}
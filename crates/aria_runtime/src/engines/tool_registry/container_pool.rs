@@ -0,0 +1,260 @@
+/*!
+# Warm Container Pool
+
+Bookkeeping shared by the bundle and container tool executors: resource limits
+derived from a tool's requirements, and a keyed pool of started containers that
+are reused across calls until they sit idle for too long. The pool only tracks
+container IDs; callers own creating and removing the containers through Quilt.
+*/
+
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::types::ResourceRequirements;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+
+/// Container limits derived from a tool's resource requirements
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerLimits {
+    pub memory_limit_mb: i32,
+    pub cpu_limit_percent: f32,
+    pub timeout: Duration,
+    pub max_concurrent: Option<u32>,
+}
+
+impl ContainerLimits {
+    pub fn from_requirements(requirements: &ResourceRequirements, default_timeout_seconds: u64) -> Self {
+        // cpu_cores wins over cpu_millis when both are declared; 1000 millis is one core
+        let cpu_limit_percent = match requirements.cpu_cores {
+            Some(cores) => cores as f32 * 100.0,
+            None => requirements.cpu_millis as f32 / 10.0,
+        };
+
+        Self {
+            memory_limit_mb: requirements.memory_mb.min(i32::MAX as u64) as i32,
+            cpu_limit_percent,
+            timeout: Duration::from_secs(requirements.timeout_seconds.unwrap_or(default_timeout_seconds)),
+            max_concurrent: requirements.max_concurrent.filter(|limit| *limit > 0),
+        }
+    }
+
+    /// Pool key for containers started with these limits. Callers only share a
+    /// warm container when their limits match, so every tool runs under exactly
+    /// the limits its manifest asked for.
+    pub fn container_key(&self, prefix: &str) -> String {
+        format!("{}:{}m:{}c", prefix, self.memory_limit_mb, self.cpu_limit_percent.round() as u32)
+    }
+}
+
+/// A started container kept around for reuse
+#[derive(Debug, Clone)]
+struct WarmContainer {
+    container_id: String,
+    last_used: Instant,
+    idle_timeout: Duration,
+}
+
+/// Snapshot of a warm container for status reporting
+#[derive(Debug, Clone)]
+pub struct WarmContainerInfo {
+    pub key: String,
+    pub container_id: String,
+    pub idle_seconds: u64,
+}
+
+type Slot = Arc<Mutex<Option<WarmContainer>>>;
+
+/// Keyed pool of warm containers. Each key has its own slot lock, so starting a
+/// container for one key never blocks calls that use another.
+#[derive(Default)]
+pub struct WarmContainerPool {
+    slots: Mutex<HashMap<String, Slot>>,
+}
+
+impl WarmContainerPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn slot(&self, key: &str) -> Slot {
+        self.slots.lock().await
+            .entry(key.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(None)))
+            .clone()
+    }
+
+    /// Return the warm container for `key`, calling `start` to create one when
+    /// the slot is empty. Concurrent callers for the same key wait for that start.
+    pub async fn get_or_start<F, Fut>(&self, key: &str, idle_timeout: Duration, start: F) -> AriaResult<String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = AriaResult<String>>,
+    {
+        let slot = self.slot(key).await;
+        let mut guard = slot.lock().await;
+
+        if let Some(container) = guard.as_mut() {
+            container.last_used = Instant::now();
+            return Ok(container.container_id.clone());
+        }
+
+        let container_id = start().await?;
+        *guard = Some(WarmContainer {
+            container_id: container_id.clone(),
+            last_used: Instant::now(),
+            idle_timeout,
+        });
+        Ok(container_id)
+    }
+
+    /// Forget `container_id` if it is still the warm container for `key`.
+    /// Returns whether it was, in which case the caller should remove it.
+    pub async fn discard(&self, key: &str, container_id: &str) -> bool {
+        let slot = self.slot(key).await;
+        let mut guard = slot.lock().await;
+        if guard.as_ref().is_some_and(|c| c.container_id == container_id) {
+            guard.take();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Take every container idle past its timeout out of the pool and return
+    /// their IDs. Slots that are busy starting a container are skipped.
+    pub async fn take_idle(&self) -> Vec<String> {
+        let slots: Vec<Slot> = self.slots.lock().await.values().cloned().collect();
+
+        let mut expired = Vec::new();
+        for slot in slots {
+            if let Ok(mut guard) = slot.try_lock() {
+                if guard.as_ref().is_some_and(|c| c.last_used.elapsed() > c.idle_timeout) {
+                    if let Some(container) = guard.take() {
+                        expired.push(container.container_id);
+                    }
+                }
+            }
+        }
+        expired
+    }
+
+    /// Empty the pool and return the IDs of every container it held
+    pub async fn drain(&self) -> Vec<String> {
+        let slots: Vec<Slot> = self.slots.lock().await.drain().map(|(_, slot)| slot).collect();
+
+        let mut drained = Vec::new();
+        for slot in slots {
+            if let Some(container) = slot.lock().await.take() {
+                drained.push(container.container_id);
+            }
+        }
+        drained
+    }
+
+    pub async fn list(&self) -> Vec<WarmContainerInfo> {
+        let slots: Vec<(String, Slot)> = self.slots.lock().await
+            .iter()
+            .map(|(key, slot)| (key.clone(), slot.clone()))
+            .collect();
+
+        let mut infos = Vec::new();
+        for (key, slot) in slots {
+            if let Some(container) = slot.lock().await.as_ref() {
+                infos.push(WarmContainerInfo {
+                    key,
+                    container_id: container.container_id.clone(),
+                    idle_seconds: container.last_used.elapsed().as_secs(),
+                });
+            }
+        }
+        infos
+    }
+}
+
+/// Per-tool permits enforcing `max_concurrent` from a tool's requirements
+#[derive(Default)]
+pub struct ToolConcurrency {
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl ToolConcurrency {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait for a permit when the tool declares a limit; tools without one never wait
+    pub async fn acquire(&self, tool_name: &str, limits: &ContainerLimits) -> AriaResult<Option<OwnedSemaphorePermit>> {
+        let Some(limit) = limits.max_concurrent else {
+            return Ok(None);
+        };
+
+        let semaphore = self.semaphores.lock().await
+            .entry(tool_name.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(limit as usize)))
+            .clone();
+
+        semaphore.acquire_owned().await.map(Some).map_err(|_| AriaError::new(
+            ErrorCode::InternalError,
+            ErrorCategory::Tool,
+            ErrorSeverity::Medium,
+            &format!("Concurrency limiter for tool '{}' was closed", tool_name),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_from_requirements() {
+        let requirements = ResourceRequirements {
+            cpu_millis: 500,
+            memory_mb: 256,
+            timeout_seconds: Some(5),
+            max_concurrent: Some(0),
+            ..Default::default()
+        };
+        let limits = ContainerLimits::from_requirements(&requirements, 60);
+        assert_eq!(limits.memory_limit_mb, 256);
+        assert_eq!(limits.cpu_limit_percent, 50.0);
+        assert_eq!(limits.timeout, Duration::from_secs(5));
+        assert_eq!(limits.max_concurrent, None);
+
+        let cores = ResourceRequirements { cpu_cores: Some(2), timeout_seconds: None, ..requirements.clone() };
+        let core_limits = ContainerLimits::from_requirements(&cores, 60);
+        assert_eq!(core_limits.cpu_limit_percent, 200.0);
+        assert_eq!(core_limits.timeout, Duration::from_secs(60));
+        assert_ne!(core_limits.container_key("abc"), limits.container_key("abc"));
+    }
+
+    #[tokio::test]
+    async fn test_pool_reuses_and_expires() {
+        let pool = WarmContainerPool::new();
+        let counter = std::sync::atomic::AtomicUsize::new(0);
+        let starts = &counter;
+        let start = move || async move {
+            let n = starts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(format!("container-{}", n))
+        };
+
+        let first = pool.get_or_start("tool", Duration::from_secs(600), start).await.unwrap();
+        let again = pool.get_or_start("tool", Duration::from_secs(600), start).await.unwrap();
+        assert_eq!(first, again);
+        assert_eq!(starts.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        assert!(!pool.discard("tool", "container-other").await);
+        assert!(pool.discard("tool", &first).await);
+        let replaced = pool.get_or_start("tool", Duration::ZERO, start).await.unwrap();
+        assert_ne!(replaced, first);
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(pool.take_idle().await, vec![replaced]);
+        assert!(pool.list().await.is_empty());
+
+        pool.get_or_start("other", Duration::from_secs(600), start).await.unwrap();
+        assert_eq!(pool.drain().await.len(), 1);
+    }
+}
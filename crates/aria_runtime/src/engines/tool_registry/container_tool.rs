@@ -0,0 +1,698 @@
+/*!
+# Container Tool Executor
+
+Executes `ToolType::Container` tools through Quilt. Abstract container tools
+wrap a CLI binary from an image: call parameters are templated into the command
+line, environment and stdin, the process's exit code and output become the
+`ToolResult`, and the container is cleaned up according to the tool's policy.
+Primitive container tools (`createContainer`, `execInContainer`, ...) map
+directly onto the matching `QuiltService` calls.
+*/
+
+use crate::deep_size::DeepValue;
+use crate::engines::container::quilt::quilt_proto::CreateContainerRequest;
+use crate::engines::container::quilt::QuiltService;
+use crate::engines::tool_registry::container_pool::{ContainerLimits, ToolConcurrency, WarmContainerInfo, WarmContainerPool};
+use crate::engines::tool_registry::{RegistryEntry, SecurityLevel, ToolScope, ToolType};
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::types::{ContainerExecutionResult, ResourceRequirements, ResourceUsage, ToolResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// Timeout for container tools that don't declare one
+const DEFAULT_TIMEOUT_SECONDS: u64 = 120;
+
+/// How long a new container may take to reach the running state
+const STARTUP_TIMEOUT_SECONDS: u64 = 30;
+
+/// What happens to a tool's container after a call
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum CleanupPolicy {
+    /// Start a fresh container for every call and remove it afterwards
+    #[default]
+    Remove,
+    /// Like `Remove`, but keep the container of a failed call for inspection
+    KeepOnFailure,
+    /// Keep one warm container per tool and reuse it until it sits idle this long
+    Reuse { idle_timeout_seconds: u64 },
+}
+
+/// How an abstract container tool receives its parameters and is cleaned up.
+///
+/// Parameters are referenced with `{{name}}` placeholders in the tool's command,
+/// in `environment` values and in `stdin`. `{{name|default}}` supplies a default
+/// for a missing parameter, `{{name?}}` makes it optional, and `{{params}}`
+/// expands to all parameters as JSON. A command argument that is exactly one
+/// placeholder for an array parameter expands into one argument per element.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContainerToolOptions {
+    /// Environment variables set for the tool process
+    pub environment: HashMap<String, String>,
+    /// Template written to the process's stdin
+    pub stdin: Option<String>,
+    pub working_directory: Option<String>,
+    /// Setup commands applied to the image (served from quilt's layer cache)
+    pub setup_commands: Vec<String>,
+    pub cleanup: CleanupPolicy,
+    /// Parse stdout as JSON and return it as the tool result
+    pub json_output: bool,
+}
+
+/// Declarative definition of a container tool, so a CLI binary can be
+/// registered as an agent tool from JSON without writing Rust
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the tool's parameters
+    #[serde(default = "empty_parameters_schema")]
+    pub parameters: Value,
+    pub image: String,
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub options: ContainerToolOptions,
+    #[serde(default)]
+    pub resource_requirements: ResourceRequirements,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default = "default_version")]
+    pub version: String,
+}
+
+fn empty_parameters_schema() -> Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+fn default_version() -> String {
+    "1.0.0".to_string()
+}
+
+impl ContainerToolDefinition {
+    pub fn into_registry_entry(self) -> AriaResult<RegistryEntry> {
+        if self.name.trim().is_empty() || self.image.trim().is_empty() || self.command.is_empty() {
+            return Err(invalid_parameters("Container tool definitions need a name, an image and a command"));
+        }
+
+        Ok(RegistryEntry {
+            name: self.name,
+            description: self.description,
+            parameters: self.parameters,
+            tool_type: ToolType::Container {
+                image: self.image,
+                command: self.command,
+                options: self.options,
+            },
+            scope: ToolScope::Abstract,
+            bundle_id: None,
+            version: self.version,
+            capabilities: self.capabilities,
+            resource_requirements: self.resource_requirements,
+            // The wrapped process only sees its own isolated container
+            security_level: SecurityLevel::Limited,
+        })
+    }
+}
+
+/// Runs container tools through Quilt
+pub struct ContainerToolExecutor {
+    quilt_service: Arc<Mutex<QuiltService>>,
+    /// Warm containers of tools with `CleanupPolicy::Reuse`, keyed by tool and limits
+    containers: WarmContainerPool,
+    concurrency: ToolConcurrency,
+}
+
+impl ContainerToolExecutor {
+    pub fn new(quilt_service: Arc<Mutex<QuiltService>>) -> Self {
+        Self {
+            quilt_service,
+            containers: WarmContainerPool::new(),
+            concurrency: ToolConcurrency::new(),
+        }
+    }
+
+    /// Run an abstract container tool: `command` from `image`, templated with `parameters`
+    pub async fn execute(
+        &self,
+        tool_name: &str,
+        image: &str,
+        command: &[String],
+        options: &ContainerToolOptions,
+        parameters: &Value,
+        requirements: &ResourceRequirements,
+    ) -> AriaResult<ToolResult> {
+        if image.is_empty() || command.is_empty() {
+            return Err(AriaError::new(
+                ErrorCode::ToolExecutionError,
+                ErrorCategory::Tool,
+                ErrorSeverity::Medium,
+                &format!("Container tool '{}' has no image or command configured", tool_name),
+            ));
+        }
+
+        let params = parameter_map(parameters)?;
+        let shell_command = build_shell_command(command, options, &params)?;
+        debug!("Container tool '{}' command: {}", tool_name, shell_command);
+
+        self.reap_idle_containers().await;
+        let limits = ContainerLimits::from_requirements(requirements, DEFAULT_TIMEOUT_SECONDS);
+        let _permit = self.concurrency.acquire(tool_name, &limits).await?;
+
+        let pool_key = limits.container_key(tool_name);
+        let container_id = match &options.cleanup {
+            CleanupPolicy::Reuse { idle_timeout_seconds } => {
+                self.containers
+                    .get_or_start(&pool_key, Duration::from_secs(*idle_timeout_seconds), || {
+                        self.start_container(tool_name, image, options, &limits)
+                    })
+                    .await?
+            }
+            _ => self.start_container(tool_name, image, options, &limits).await?,
+        };
+
+        let start_time = Instant::now();
+        // Clone the client so a long-running tool doesn't hold the shared service lock
+        let mut quilt = self.quilt_service.lock().await.clone();
+        let outcome = tokio::time::timeout(limits.timeout, quilt.run_in_container(container_id.clone(), vec![shell_command])).await;
+        let execution_time_ms = start_time.elapsed().as_millis() as u64;
+
+        let exec = match outcome {
+            Ok(Ok(exec)) => exec,
+            Ok(Err(e)) => {
+                self.discard(&options.cleanup, &pool_key, &container_id).await;
+                return Err(e);
+            }
+            Err(_) => {
+                // The process may still be running; removing the container is the only way to stop it
+                self.discard(&options.cleanup, &pool_key, &container_id).await;
+                return Err(AriaError::new(
+                    ErrorCode::Timeout,
+                    ErrorCategory::Tool,
+                    ErrorSeverity::Medium,
+                    &format!("Container tool '{}' timed out after {}s", tool_name, limits.timeout.as_secs()),
+                ));
+            }
+        };
+
+        let resource_usage = match quilt.get_container_status(container_id.clone()).await {
+            Ok(status) => status.resource_usage,
+            Err(_) => None,
+        };
+
+        let mut result = tool_result_from_exec(&exec, options.json_output);
+        result.execution_time_ms = execution_time_ms;
+        result.resource_usage = Some(resource_usage.unwrap_or_default());
+        result.metadata.insert("container_id".to_string(), DeepValue::string(container_id.clone()));
+        result.metadata.insert("image".to_string(), DeepValue::string(image.to_string()));
+        result.metadata.insert("exit_code".to_string(), DeepValue::number(exec.exit_code as i64));
+
+        let keep = match &options.cleanup {
+            CleanupPolicy::Remove => false,
+            CleanupPolicy::KeepOnFailure => !result.success,
+            CleanupPolicy::Reuse { .. } => true,
+        };
+        if keep {
+            result.metadata.insert("container_kept".to_string(), DeepValue::boolean(true));
+        } else {
+            self.remove_container(&container_id).await;
+        }
+
+        info!("Container tool '{}' exited with {} in {}ms", tool_name, exec.exit_code, execution_time_ms);
+        Ok(result)
+    }
+
+    /// Run a primitive container tool by mapping it onto the matching Quilt call
+    pub async fn execute_primitive(&self, tool_name: &str, parameters: &Value) -> AriaResult<ToolResult> {
+        let start_time = Instant::now();
+        let mut quilt = self.quilt_service.lock().await.clone();
+
+        let result = match tool_name {
+            "createContainer" => {
+                let image = required_str(parameters, "image")?;
+                let command = parameters.get("command")
+                    .and_then(Value::as_array)
+                    .map(|args| args.iter().map(value_to_arg).collect())
+                    .unwrap_or_default();
+                let env = parameters.get("env")
+                    .and_then(Value::as_object)
+                    .map(|env| env.iter().map(|(k, v)| (k.clone(), value_to_arg(v))).collect())
+                    .unwrap_or_default();
+                let container_id = quilt.create_container(image.to_string(), command, env).await?;
+                success(serde_json::json!({ "containerId": container_id }))
+            }
+            "startContainer" => {
+                let container_id = required_str(parameters, "containerId")?;
+                quilt.start_container(container_id.to_string()).await?;
+                success(serde_json::json!({ "containerId": container_id, "status": "started" }))
+            }
+            "stopContainer" => {
+                let container_id = required_str(parameters, "containerId")?;
+                quilt.stop_container(container_id.to_string()).await?;
+                success(serde_json::json!({ "containerId": container_id, "status": "stopped" }))
+            }
+            "removeContainer" => {
+                let container_id = required_str(parameters, "containerId")?;
+                quilt.remove_container(container_id.to_string()).await?;
+                success(serde_json::json!({ "containerId": container_id, "status": "removed" }))
+            }
+            "execInContainer" => {
+                let container_id = required_str(parameters, "containerId")?;
+                let command: Vec<String> = parameters.get("command")
+                    .and_then(Value::as_array)
+                    .map(|args| args.iter().map(value_to_arg).collect())
+                    .unwrap_or_default();
+                if command.is_empty() {
+                    return Err(invalid_parameters("execInContainer requires a non-empty 'command'"));
+                }
+                let exec = quilt.run_in_container(container_id.to_string(), command).await?;
+                tool_result_from_exec(&exec, false)
+            }
+            "listContainers" => success(to_json(&quilt.list_containers().await?)),
+            "getContainerStatus" => {
+                let container_id = required_str(parameters, "containerId")?;
+                success(to_json(&quilt.get_container_status(container_id.to_string()).await?))
+            }
+            "getContainerLogs" => {
+                let container_id = required_str(parameters, "containerId")?;
+                let logs = quilt.get_container_logs(container_id.to_string()).await?;
+                success(serde_json::json!({ "containerId": container_id, "logs": logs }))
+            }
+            "getSystemMetrics" => success(to_json(&quilt.get_system_metrics().await?)),
+            "getNetworkTopology" => success(to_json(&quilt.get_network_topology().await?)),
+            "getContainerNetworkInfo" => {
+                let container_id = required_str(parameters, "containerId")?;
+                success(to_json(&quilt.get_container_network_info(container_id.to_string()).await?))
+            }
+            _ => {
+                return Err(AriaError::new(
+                    ErrorCode::NotSupported,
+                    ErrorCategory::Tool,
+                    ErrorSeverity::Medium,
+                    &format!("Primitive container tool '{}' is not supported", tool_name),
+                ));
+            }
+        };
+
+        Ok(ToolResult {
+            execution_time_ms: start_time.elapsed().as_millis() as u64,
+            ..result
+        })
+    }
+
+    /// List warm containers kept by tools with `CleanupPolicy::Reuse`
+    pub async fn list_warm_containers(&self) -> Vec<WarmContainerInfo> {
+        self.containers.list().await
+    }
+
+    /// Remove every warm container, e.g. on runtime shutdown
+    pub async fn shutdown(&self) {
+        for container_id in self.containers.drain().await {
+            self.remove_container(&container_id).await;
+        }
+    }
+
+    /// Remove warm containers that have been idle longer than their tool allows
+    pub async fn reap_idle_containers(&self) {
+        for container_id in self.containers.take_idle().await {
+            debug!("Removing idle container tool container {}", container_id);
+            self.remove_container(&container_id).await;
+        }
+    }
+
+    async fn start_container(
+        &self,
+        tool_name: &str,
+        image: &str,
+        options: &ContainerToolOptions,
+        limits: &ContainerLimits,
+    ) -> AriaResult<String> {
+        let mut labels = HashMap::new();
+        labels.insert("aria.tool".to_string(), tool_name.to_string());
+        labels.insert("aria.role".to_string(), "container-tool".to_string());
+
+        let request = CreateContainerRequest {
+            image_path: image.to_string(),
+            command: vec!["tail".to_string(), "-f".to_string(), "/dev/null".to_string()],
+            working_directory: options.working_directory.clone().unwrap_or_default(),
+            setup_commands: options.setup_commands.clone(),
+            memory_limit_mb: limits.memory_limit_mb,
+            cpu_limit_percent: limits.cpu_limit_percent,
+            enable_pid_namespace: true,
+            enable_mount_namespace: true,
+            enable_uts_namespace: true,
+            enable_ipc_namespace: true,
+            enable_network_namespace: true,
+            auto_start: true,
+            labels,
+            ..Default::default()
+        };
+
+        let container_id = self.quilt_service.lock().await.create_container_with(request).await?;
+        let mut quilt = self.quilt_service.lock().await.clone();
+        if let Err(e) = quilt.wait_until_running(&container_id, Duration::from_secs(STARTUP_TIMEOUT_SECONDS)).await {
+            self.remove_container(&container_id).await;
+            return Err(e);
+        }
+
+        debug!("Started container {} for tool '{}'", container_id, tool_name);
+        Ok(container_id)
+    }
+
+    /// Drop a container that failed mid-call, taking it out of the pool when reused
+    async fn discard(&self, cleanup: &CleanupPolicy, pool_key: &str, container_id: &str) {
+        let owned = match cleanup {
+            CleanupPolicy::Reuse { .. } => self.containers.discard(pool_key, container_id).await,
+            _ => true,
+        };
+        if owned {
+            self.remove_container(container_id).await;
+        }
+    }
+
+    async fn remove_container(&self, container_id: &str) {
+        if let Err(e) = self.quilt_service.lock().await.remove_container(container_id.to_string()).await {
+            warn!("Failed to remove tool container {}: {}", container_id, e);
+        }
+    }
+}
+
+fn parameter_map(parameters: &Value) -> AriaResult<Map<String, Value>> {
+    match parameters {
+        Value::Object(map) => Ok(map.clone()),
+        Value::Null => Ok(Map::new()),
+        _ => Err(invalid_parameters("Container tool parameters must be a JSON object")),
+    }
+}
+
+/// Compose the shell line run inside the container. Quilt runs exec commands
+/// through `sh -c`, so every templated value is single-quoted.
+fn build_shell_command(command: &[String], options: &ContainerToolOptions, params: &Map<String, Value>) -> AriaResult<String> {
+    let mut parts = Vec::new();
+
+    if let Some(dir) = &options.working_directory {
+        parts.push(format!("cd {} &&", shell_quote(dir)));
+    }
+
+    if let Some(stdin) = &options.stdin {
+        parts.push(format!("printf '%s' {} |", shell_quote(&render_template(stdin, params)?)));
+    }
+
+    let mut environment: Vec<(&String, &String)> = options.environment.iter().collect();
+    environment.sort();
+    for (key, value) in environment {
+        let valid_key = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_key {
+            return Err(invalid_parameters(&format!("Invalid environment variable name '{}'", key)));
+        }
+        parts.push(format!("{}={}", key, shell_quote(&render_template(value, params)?)));
+    }
+
+    for arg in render_command(command, params)? {
+        parts.push(shell_quote(&arg));
+    }
+
+    Ok(parts.join(" "))
+}
+
+/// Render command arguments, expanding whole-argument array placeholders and
+/// dropping whole-argument optional placeholders with no value
+fn render_command(command: &[String], params: &Map<String, Value>) -> AriaResult<Vec<String>> {
+    let mut args = Vec::new();
+    for arg in command {
+        if let Some(placeholder) = whole_placeholder(arg) {
+            let (name, default, optional) = parse_placeholder(placeholder);
+            match params.get(name) {
+                Some(Value::Array(items)) => {
+                    args.extend(items.iter().map(value_to_arg));
+                    continue;
+                }
+                None | Some(Value::Null) if optional && default.is_none() => continue,
+                _ => {}
+            }
+        }
+        args.push(render_template(arg, params)?);
+    }
+    Ok(args)
+}
+
+fn whole_placeholder(arg: &str) -> Option<&str> {
+    let inner = arg.strip_prefix("{{")?.strip_suffix("}}")?;
+    if inner.contains("{{") || inner.contains("}}") {
+        None
+    } else {
+        Some(inner)
+    }
+}
+
+/// Split `name|default` / `name?` into the name, its default and whether it is optional
+fn parse_placeholder(placeholder: &str) -> (&str, Option<&str>, bool) {
+    let placeholder = placeholder.trim();
+    if let Some((name, default)) = placeholder.split_once('|') {
+        return (name.trim(), Some(default), true);
+    }
+    match placeholder.strip_suffix('?') {
+        Some(name) => (name.trim(), None, true),
+        None => (placeholder, None, false),
+    }
+}
+
+/// Replace every `{{...}}` placeholder in `template` with its parameter value
+fn render_template(template: &str, params: &Map<String, Value>) -> AriaResult<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            return Err(invalid_parameters(&format!("Unterminated placeholder in '{}'", template)));
+        };
+
+        let (name, default, optional) = parse_placeholder(&after[..end]);
+        if name == "params" {
+            rendered.push_str(&Value::Object(params.clone()).to_string());
+        } else {
+            match params.get(name) {
+                Some(Value::Null) | None => match default {
+                    Some(default) => rendered.push_str(default),
+                    None if optional => {}
+                    None => return Err(invalid_parameters(&format!("Missing required parameter '{}'", name))),
+                },
+                Some(value) => rendered.push_str(&value_to_arg(value)),
+            }
+        }
+        rest = &after[end + 2..];
+    }
+
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Strings are passed through as-is; everything else as JSON
+fn value_to_arg(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Shape an exec result the way the execution engine reports container workloads
+fn tool_result_from_exec(exec: &ContainerExecutionResult, json_output: bool) -> ToolResult {
+    let mut success = exec.exit_code == 0;
+    let mut error = if success {
+        None
+    } else if exec.stderr.trim().is_empty() {
+        Some(format!("Process exited with code {}", exec.exit_code))
+    } else {
+        Some(exec.stderr.clone())
+    };
+
+    let result = if json_output && success {
+        match serde_json::from_str::<Value>(exec.stdout.trim()) {
+            Ok(value) => value,
+            Err(e) => {
+                success = false;
+                error = Some(format!("Tool output is not valid JSON: {}", e));
+                Value::String(exec.stdout.clone())
+            }
+        }
+    } else {
+        serde_json::json!({
+            "exit_code": exec.exit_code,
+            "stdout": exec.stdout,
+            "stderr": exec.stderr,
+            "execution_time_ms": exec.execution_time_ms
+        })
+    };
+
+    let mut metadata = HashMap::new();
+    if json_output && !exec.stderr.trim().is_empty() {
+        metadata.insert("stderr".to_string(), DeepValue::string(exec.stderr.clone()));
+    }
+
+    ToolResult {
+        success,
+        result: Some(DeepValue(result)),
+        error,
+        metadata,
+        execution_time_ms: exec.execution_time_ms,
+        resource_usage: exec.resource_usage.clone(),
+    }
+}
+
+fn success(result: Value) -> ToolResult {
+    ToolResult {
+        success: true,
+        result: Some(DeepValue(result)),
+        error: None,
+        metadata: HashMap::new(),
+        execution_time_ms: 0,
+        resource_usage: Some(ResourceUsage::default()),
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+fn required_str<'a>(parameters: &'a Value, key: &str) -> AriaResult<&'a str> {
+    parameters.get(key)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| invalid_parameters(&format!("Missing required parameter '{}'", key)))
+}
+
+fn invalid_parameters(message: &str) -> AriaError {
+    AriaError::new(
+        ErrorCode::ToolInvalidParameters,
+        ErrorCategory::Tool,
+        ErrorSeverity::Medium,
+        message,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(value: Value) -> Map<String, Value> {
+        parameter_map(&value).unwrap()
+    }
+
+    #[test]
+    fn test_render_template() {
+        let p = params(json!({"path": "/data", "depth": 2, "flags": {"a": true}}));
+        assert_eq!(render_template("--path={{path}}", &p).unwrap(), "--path=/data");
+        assert_eq!(render_template("{{ depth }}", &p).unwrap(), "2");
+        assert_eq!(render_template("{{flags}}", &p).unwrap(), r#"{"a":true}"#);
+        assert_eq!(render_template("{{missing|10}}", &p).unwrap(), "10");
+        assert_eq!(render_template("x{{missing?}}y", &p).unwrap(), "xy");
+        assert!(render_template("{{missing}}", &p).is_err());
+        assert!(render_template("{{path", &p).is_err());
+
+        let all: Value = serde_json::from_str(&render_template("{{params}}", &p).unwrap()).unwrap();
+        assert_eq!(all["path"], "/data");
+    }
+
+    #[test]
+    fn test_render_command_expands_arrays_and_drops_optional() {
+        let p = params(json!({"files": ["a.txt", "b c.txt"], "pattern": "TODO"}));
+        let command = vec![
+            "grep".to_string(),
+            "-n".to_string(),
+            "{{pattern}}".to_string(),
+            "{{files}}".to_string(),
+            "{{context?}}".to_string(),
+        ];
+        assert_eq!(
+            render_command(&command, &p).unwrap(),
+            vec!["grep", "-n", "TODO", "a.txt", "b c.txt"]
+        );
+    }
+
+    #[test]
+    fn test_build_shell_command_quotes_everything() {
+        let p = params(json!({"query": "it's $(rm -rf /)", "token": "s3cr3t"}));
+        let mut options = ContainerToolOptions {
+            stdin: Some("{{params}}".to_string()),
+            working_directory: Some("/work".to_string()),
+            ..Default::default()
+        };
+        options.environment.insert("API_TOKEN".to_string(), "{{token}}".to_string());
+
+        let command = vec!["search".to_string(), "{{query}}".to_string()];
+        let line = build_shell_command(&command, &options, &p).unwrap();
+        assert!(line.starts_with("cd '/work' && printf '%s' '{"));
+        assert!(line.contains("| API_TOKEN='s3cr3t' 'search' 'it'\\''s $(rm -rf /)'"));
+
+        options.environment.insert("BAD-NAME".to_string(), "x".to_string());
+        assert!(build_shell_command(&command, &options, &p).is_err());
+    }
+
+    #[test]
+    fn test_tool_result_from_exec() {
+        let exec = ContainerExecutionResult {
+            exit_code: 0,
+            stdout: "{\"count\": 3}\n".to_string(),
+            stderr: String::new(),
+            execution_time_ms: 5,
+            resource_usage: None,
+        };
+        let parsed = tool_result_from_exec(&exec, true);
+        assert!(parsed.success);
+        assert_eq!(parsed.result.unwrap().0, json!({"count": 3}));
+
+        let raw = tool_result_from_exec(&exec, false);
+        assert_eq!(raw.result.unwrap().0["exit_code"], 0);
+
+        let failed = ContainerExecutionResult {
+            exit_code: 2,
+            stderr: "no such file".to_string(),
+            ..exec
+        };
+        let result = tool_result_from_exec(&failed, true);
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some("no such file"));
+    }
+
+    #[test]
+    fn test_definition_into_registry_entry() {
+        let definition: ContainerToolDefinition = serde_json::from_value(json!({
+            "name": "jq",
+            "description": "Run a jq filter over JSON input",
+            "image": "/images/jq.tar.gz",
+            "command": ["jq", "{{filter}}"],
+            "options": {"stdin": "{{input}}", "json_output": true}
+        })).unwrap();
+
+        let entry = definition.clone().into_registry_entry().unwrap();
+        assert_eq!(entry.scope, ToolScope::Abstract);
+        assert!(matches!(entry.tool_type, ToolType::Container { ref image, .. } if image == "/images/jq.tar.gz"));
+
+        let missing_command = ContainerToolDefinition { command: vec![], ..definition };
+        assert!(missing_command.into_registry_entry().is_err());
+    }
+
+    #[test]
+    fn test_options_deserialize_with_defaults() {
+        let options: ContainerToolOptions = serde_json::from_value(json!({
+            "cleanup": {"Reuse": {"idle_timeout_seconds": 300}},
+            "json_output": true
+        })).unwrap();
+        assert_eq!(options.cleanup, CleanupPolicy::Reuse { idle_timeout_seconds: 300 });
+        assert!(options.json_output);
+        assert!(options.environment.is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::deep_size::DeepValue;
+use crate::costs::CostScope;
 use crate::engines::llm::types::{LLMConfig, LLMMessage, LLMRequest};
 use crate::engines::llm::LLMHandler;
 use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
@@ -11,6 +12,10 @@ use crate::tools::standard::{
     read_file_tool_handler,
     parse_document_tool_handler,
     write_code_tool_handler,
+    list_files_tool_handler,
+    edit_file_tool_handler,
+    WorkspaceConfig,
+    WorkspaceManager,
 };
 use async_trait::async_trait;
 use serde_json::Value;
@@ -21,15 +26,29 @@ use crate::engines::container::quilt::QuiltService;
 use tokio::sync::Mutex;
 
 pub mod bundle_integration;
+pub mod bundle_runner;
+pub mod container_pool;
+pub mod container_tool;
+pub mod policy;
+pub mod schema;
 
 pub use bundle_integration::{
     BundleToolRegistry, BundleToolRegistration, ToolSourceInfo, 
     CustomToolEntry, BundleToolStats
 };
+pub use bundle_runner::{BundleToolRunner, BundleToolRunnerConfig};
+pub use container_pool::{ContainerLimits, WarmContainerInfo};
+pub use container_tool::{CleanupPolicy, ContainerToolDefinition, ContainerToolExecutor, ContainerToolOptions};
+pub use policy::{
+    ApprovalNotifier, ApprovalRequest, ApprovalResolution, PolicyConfig, PolicyDecision,
+    PolicyEffect, PolicyRule, PolicyScope, ToolCallContext, ToolPolicyEngine,
+};
 
 #[async_trait]
 pub trait ToolRegistryInterface: Send + Sync {
     async fn execute_tool(&self, name: &str, parameters: DeepValue) -> AriaResult<ToolResult>;
+    /// Execute a tool on behalf of a user, agent and session, subject to the attached policy
+    async fn execute_tool_as(&self, name: &str, parameters: DeepValue, caller: &ToolCallContext) -> AriaResult<ToolResult>;
     async fn get_tool_info(&self, name: &str) -> AriaResult<Option<types::RegistryEntry>>;
     async fn list_available_tools(&self) -> AriaResult<Vec<String>>;
     async fn is_tool_available(&self, tool_name: &str) -> bool;
@@ -77,6 +96,10 @@ pub struct ToolRegistry {
     bundle_store: Option<Arc<dyn BundleStoreInterface>>,
     llm_handler: Arc<LLMHandler>,
     quilt_service: Arc<Mutex<QuiltService>>,
+    bundle_runner: Arc<BundleToolRunner>,
+    container_tools: Arc<ContainerToolExecutor>,
+    policy: Arc<RwLock<Option<Arc<ToolPolicyEngine>>>>,
+    workspaces: Arc<WorkspaceManager>,
 }
 
 #[derive(Debug, Clone)]
@@ -103,6 +126,7 @@ pub enum ToolType {
     Container {
         image: String,
         command: Vec<String>,
+        options: ContainerToolOptions,
     },
     LLM {
         provider: String,
@@ -119,7 +143,7 @@ pub struct ToolExecutionStats {
     pub error_rate: f64,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum SecurityLevel {
     Safe,
     Limited,
@@ -127,6 +151,27 @@ pub enum SecurityLevel {
     Dangerous,
 }
 
+impl SecurityLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecurityLevel::Safe => "safe",
+            SecurityLevel::Limited => "limited",
+            SecurityLevel::Elevated => "elevated",
+            SecurityLevel::Dangerous => "dangerous",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "safe" => Some(SecurityLevel::Safe),
+            "limited" => Some(SecurityLevel::Limited),
+            "elevated" => Some(SecurityLevel::Elevated),
+            "dangerous" => Some(SecurityLevel::Dangerous),
+            _ => None,
+        }
+    }
+}
+
 impl ToolRegistry {
     pub async fn new(
         llm_handler: Arc<LLMHandler>,
@@ -137,7 +182,11 @@ impl ToolRegistry {
             execution_stats: Arc::new(RwLock::new(HashMap::new())),
             bundle_store: None,
             llm_handler,
+            bundle_runner: Arc::new(BundleToolRunner::new(quilt_service.clone(), BundleToolRunnerConfig::default())),
+            container_tools: Arc::new(ContainerToolExecutor::new(quilt_service.clone())),
+            workspaces: Arc::new(WorkspaceManager::new(WorkspaceConfig::default(), quilt_service.clone())),
             quilt_service,
+            policy: Arc::new(RwLock::new(None)),
         };
         
         registry.register_builtin_tools().await;
@@ -147,6 +196,39 @@ impl ToolRegistry {
         registry
     }
 
+    /// Runner that executes bundle tools in warm per-bundle containers
+    pub fn bundle_runner(&self) -> &Arc<BundleToolRunner> {
+        &self.bundle_runner
+    }
+
+    /// Executor for container-backed tools
+    pub fn container_tools(&self) -> &Arc<ContainerToolExecutor> {
+        &self.container_tools
+    }
+
+    /// Per-session workspaces that filesystem tools are confined to
+    pub fn workspaces(&self) -> &Arc<WorkspaceManager> {
+        &self.workspaces
+    }
+
+    /// Put every subsequent tool call behind `engine`'s authorization rules
+    pub async fn attach_policy_engine(&self, engine: Arc<ToolPolicyEngine>) {
+        *self.policy.write().await = Some(engine);
+    }
+
+    /// Policy engine consulted before tools run, if one is attached
+    pub async fn policy_engine(&self) -> Option<Arc<ToolPolicyEngine>> {
+        self.policy.read().await.clone()
+    }
+
+    /// Register a CLI binary from an image as an abstract container tool
+    pub async fn register_container_tool(&self, definition: ContainerToolDefinition) -> AriaResult<()> {
+        let entry = definition.into_registry_entry()?;
+        tracing::info!("Registering container tool: {}", entry.name);
+        self.tools.write().await.insert(entry.name.clone(), entry);
+        Ok(())
+    }
+
     async fn register_builtin_tools(&self) {
         // Builtin tools are deprecated in favor of agent sovereignty
         // Agents should use primitive tools directly or LLM tools for cognitive tasks
@@ -155,6 +237,9 @@ impl ToolRegistry {
             Self::create_create_plan_tool_static(),
             Self::create_web_search_tool_static(),
             Self::create_read_file_tool_static(),
+            Self::create_write_file_tool_static(),
+            Self::create_list_files_tool_static(),
+            Self::create_edit_file_tool_static(),
             Self::create_parse_document_tool_static(),
             Self::create_write_code_tool_static(),
             Self::create_calculator_tool_static(),
@@ -189,7 +274,7 @@ impl ToolRegistry {
 
         let mut tools = self.tools.write().await;
         for tool in container_tools {
-            println!("📦 Registering container tool: {}", tool.name);
+            tracing::debug!("Registering container tool: {}", tool.name);
             tools.insert(tool.name.clone(), tool);
         }
     }
@@ -336,7 +421,7 @@ impl ToolRegistry {
     fn create_read_file_tool_static() -> RegistryEntry {
         RegistryEntry {
             name: "readFileTool".to_string(),
-            description: "Read file contents with format detection and comprehensive metadata".to_string(),
+            description: "Read a text file from the session workspace with format detection and metadata".to_string(),
             parameters: serde_json::json!({ 
                 "type": "object", 
                 "properties": { 
@@ -356,6 +441,87 @@ impl ToolRegistry {
         }
     }
 
+    fn create_write_file_tool_static() -> RegistryEntry {
+        RegistryEntry {
+            name: "writeFileTool".to_string(),
+            description: "Write a text file into the session workspace, creating parent directories as needed".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "File path (legacy)" },
+                    "filePath": { "type": "string", "description": "Workspace-relative file path to write" },
+                    "content": { "type": "string", "description": "Text content to write" },
+                    "encoding": { "type": "string", "description": "Content encoding", "enum": ["utf-8"], "default": "utf-8" }
+                },
+                "required": ["content"]
+            }),
+            tool_type: ToolType::LLM { provider: "openai".to_string(), model: "gpt-4".to_string() },
+            scope: ToolScope::Abstract,
+            bundle_id: None,
+            version: "1.0.0".to_string(),
+            capabilities: vec!["file_operations".to_string(), "content_writing".to_string()],
+            resource_requirements: ResourceRequirements::default(),
+            security_level: SecurityLevel::Limited,
+        }
+    }
+
+    fn create_list_files_tool_static() -> RegistryEntry {
+        RegistryEntry {
+            name: "listFilesTool".to_string(),
+            description: "List files in the session workspace, optionally filtered by a glob such as 'src/**/*.rs'".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "pattern": { "type": "string", "description": "Glob over workspace-relative paths (*, **, ?, {a,b})" }
+                },
+                "required": []
+            }),
+            tool_type: ToolType::LLM { provider: "openai".to_string(), model: "gpt-4".to_string() },
+            scope: ToolScope::Abstract,
+            bundle_id: None,
+            version: "1.0.0".to_string(),
+            capabilities: vec!["file_operations".to_string(), "file_discovery".to_string()],
+            resource_requirements: ResourceRequirements::default(),
+            security_level: SecurityLevel::Safe,
+        }
+    }
+
+    fn create_edit_file_tool_static() -> RegistryEntry {
+        RegistryEntry {
+            name: "editFileTool".to_string(),
+            description: "Apply search-and-replace edits to a workspace file and return a unified diff".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "filePath": { "type": "string", "description": "Workspace-relative file path to edit" },
+                    "edits": {
+                        "type": "array",
+                        "description": "Edits applied in order; all must succeed or none are written",
+                        "minItems": 1,
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "oldText": { "type": "string", "description": "Exact text to replace", "minLength": 1 },
+                                "newText": { "type": "string", "description": "Replacement text" },
+                                "replaceAll": { "type": "boolean", "description": "Replace every occurrence", "default": false }
+                            },
+                            "required": ["oldText", "newText"]
+                        }
+                    },
+                    "dryRun": { "type": "boolean", "description": "Return the diff without writing", "default": false }
+                },
+                "required": ["filePath", "edits"]
+            }),
+            tool_type: ToolType::LLM { provider: "openai".to_string(), model: "gpt-4".to_string() },
+            scope: ToolScope::Abstract,
+            bundle_id: None,
+            version: "1.0.0".to_string(),
+            capabilities: vec!["file_operations".to_string(), "content_editing".to_string()],
+            resource_requirements: ResourceRequirements::default(),
+            security_level: SecurityLevel::Limited,
+        }
+    }
+
     fn create_parse_document_tool_static() -> RegistryEntry {
         RegistryEntry {
             name: "parseDocumentTool".to_string(),
@@ -611,6 +777,10 @@ impl ToolRegistry {
 #[async_trait]
 impl ToolRegistryInterface for ToolRegistry {
     async fn execute_tool(&self, name: &str, parameters: DeepValue) -> AriaResult<ToolResult> {
+        self.execute_tool_as(name, parameters, &ToolCallContext::default()).await
+    }
+
+    async fn execute_tool_as(&self, name: &str, parameters: DeepValue, caller: &ToolCallContext) -> AriaResult<ToolResult> {
         let tool_entry = self.tools.read().await.get(name).cloned().ok_or_else(|| {
             AriaError::new(
                 ErrorCode::ToolNotFound,
@@ -620,47 +790,73 @@ impl ToolRegistryInterface for ToolRegistry {
             )
         })?;
 
-        match &tool_entry.tool_type {
-            ToolType::Builtin => {
-                // Builtin tools are deprecated in favor of agent sovereignty
-                // Agents should use primitive tools directly for full control
-                Err(AriaError::new(
-                    ErrorCode::NotSupported,
-                    ErrorCategory::Tool,
-                    ErrorSeverity::Medium,
-                    &format!("Builtin tool '{}' is deprecated. Use primitive container tools for full agent control.", name),
-                ))
+        // Validate against the declared schema so handlers receive coerced, defaulted parameters
+        let parameters = match schema::validate_parameters(&tool_entry.parameters, parameters.0) {
+            Ok(validated) => DeepValue(validated),
+            Err(issues) => {
+                tracing::debug!("Rejected call to '{}' with {} parameter issue(s)", name, issues.len());
+                return Ok(schema::validation_failure(name, &issues));
+            }
+        };
+
+        if let Some(policy) = self.policy_engine().await {
+            let decision = policy.authorize(&tool_entry, &parameters.0, caller).await;
+            if !decision.is_allowed() {
+                return Ok(policy_denial(name, &decision));
             }
-            ToolType::LLM { provider: _, model: _ } => {
-                // Execute LLM-based tools using the handler
-                match name {
-                    "ponderTool" => ponder_tool_handler(parameters, &self.llm_handler).await,
-                    "createPlanTool" => create_plan_tool_handler(parameters, &self.llm_handler).await,
-                    "webSearchTool" => web_search_tool_handler(parameters, &self.llm_handler).await,
-                    "readFileTool" => read_file_tool_handler(parameters, &self.llm_handler).await,
-                    "parseDocumentTool" => parse_document_tool_handler(parameters, &self.llm_handler).await,
-                    "writeCodeTool" => write_code_tool_handler(parameters, &self.llm_handler).await,
-                    _ => {
-                        // For other LLM tools, use a generic handler
-                        self.execute_llm_tool(name, parameters, &tool_entry).await
+        }
+
+        // Model calls the tool's handler makes are charged to the tool
+        CostScope::with_tool(name, async {
+            match &tool_entry.tool_type {
+                ToolType::Builtin => {
+                    // Builtin tools are deprecated in favor of agent sovereignty
+                    // Agents should use primitive tools directly for full control
+                    Err(AriaError::new(
+                        ErrorCode::NotSupported,
+                        ErrorCategory::Tool,
+                        ErrorSeverity::Medium,
+                        &format!("Builtin tool '{}' is deprecated. Use primitive container tools for full agent control.", name),
+                    ))
+                }
+                ToolType::LLM { provider: _, model: _ } => {
+                    // Execute LLM-based tools using the handler
+                    match name {
+                        "ponderTool" => ponder_tool_handler(parameters, &self.llm_handler).await,
+                        "createPlanTool" => create_plan_tool_handler(parameters, &self.llm_handler).await,
+                        "webSearchTool" => web_search_tool_handler(parameters, &self.llm_handler).await,
+                        "parseDocumentTool" => parse_document_tool_handler(parameters, &self.llm_handler).await,
+                        "readFileTool" | "writeFileTool" | "listFilesTool" | "editFileTool" | "writeCodeTool" => {
+                            // Filesystem tools only ever see the caller's own workspace
+                            let workspace = self.workspaces
+                                .workspace_for(caller.user_id.as_deref(), caller.session_id.as_deref())
+                                .await?;
+                            match name {
+                                "readFileTool" => read_file_tool_handler(parameters, &workspace).await,
+                                "writeFileTool" => write_file_tool_handler(parameters, &workspace).await,
+                                "listFilesTool" => list_files_tool_handler(parameters, &workspace).await,
+                                "editFileTool" => edit_file_tool_handler(parameters, &workspace).await,
+                                _ => write_code_tool_handler(parameters, &self.llm_handler, &workspace).await,
+                            }
+                        }
+                        _ => {
+                            // For other LLM tools, use a generic handler
+                            self.execute_llm_tool(name, parameters, &tool_entry).await
+                        }
                     }
                 }
+                ToolType::Container { .. } => {
+                    // Container tools use the quilt service for execution
+                    self.execute_container_tool(name, parameters, &tool_entry).await
+                }
+                ToolType::Bundle { bundle_path, entry_point } => {
+                    // Bundle tools run in a warm container for their bundle
+                    self.bundle_runner
+                        .execute(name, bundle_path, entry_point, &parameters, &tool_entry.resource_requirements)
+                        .await
+                }
             }
-            ToolType::Container { .. } => {
-                // Container tools use the quilt service for execution
-                self.execute_container_tool(name, parameters, &tool_entry).await
-            }
-            ToolType::Bundle { .. } => {
-                // Bundle tools would be executed in their respective containers
-                // For now, return an error as bundle execution is not yet implemented
-                Err(AriaError::new(
-                    ErrorCode::NotSupported,
-                    ErrorCategory::Tool,
-                    ErrorSeverity::Medium,
-                    &format!("Bundle tool '{}' execution not yet implemented", name),
-                ))
-            }
-        }
+        }).await
     }
 
     async fn get_tool_info(&self, name: &str) -> AriaResult<Option<types::RegistryEntry>> {
@@ -678,6 +874,7 @@ impl ToolRegistryInterface for ToolRegistry {
                     metadata.insert("capabilities".to_string(), DeepValue::array(
                         entry.capabilities.iter().map(|c| DeepValue::string(c.clone())).collect()
                     ));
+                    metadata.insert("parameters".to_string(), DeepValue(entry.parameters.clone()));
                     metadata
                 },
                 created_at: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs(),
@@ -769,18 +966,41 @@ impl ToolRegistry {
     }
 
     async fn execute_container_tool(&self, name: &str, parameters: DeepValue, entry: &RegistryEntry) -> AriaResult<ToolResult> {
-        // Container tool execution using quilt service
-        let start_time = std::time::Instant::now();
-        
-        // For now, return a placeholder result
-        // TODO: Implement actual container tool execution
-        Ok(ToolResult {
-            success: true,
-            result: Some(DeepValue::string(format!("Container tool '{}' executed", name))),
-            error: None,
-            metadata: HashMap::new(),
-            execution_time_ms: start_time.elapsed().as_millis() as u64,
-            resource_usage: Some(types::ResourceUsage::default()),
-        })
-    }
-} 
\ No newline at end of file
+        let ToolType::Container { image, command, options } = &entry.tool_type else {
+            return Err(AriaError::new(
+                ErrorCode::InternalError,
+                ErrorCategory::Tool,
+                ErrorSeverity::Medium,
+                &format!("Tool '{}' is not a container tool", name),
+            ));
+        };
+
+        // Primitive tools drive the container lifecycle themselves; abstract
+        // ones wrap a command that runs in a container managed for them
+        if entry.scope == ToolScope::Primitive {
+            return self.container_tools.execute_primitive(name, &parameters).await;
+        }
+
+        self.container_tools
+            .execute(name, image, command, options, &parameters, &entry.resource_requirements)
+            .await
+    }
+} 
+
+/// Tool result reported back to the caller when the policy refuses a call
+fn policy_denial(tool_name: &str, decision: &PolicyDecision) -> ToolResult {
+    let mut metadata = HashMap::new();
+    metadata.insert("error_code".to_string(), DeepValue::string("PermissionDenied".to_string()));
+    if let Some(rule_id) = &decision.rule_id {
+        metadata.insert("policy_rule".to_string(), DeepValue::string(rule_id.clone()));
+    }
+
+    ToolResult {
+        success: false,
+        result: None,
+        error: Some(format!("Tool '{}' was not permitted to run: {}", tool_name, decision.reason)),
+        metadata,
+        execution_time_ms: 0,
+        resource_usage: None,
+    }
+}
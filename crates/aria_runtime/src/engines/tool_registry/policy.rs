@@ -0,0 +1,782 @@
+/*!
+# Tool Execution Policy
+
+Authorization layer consulted by the `ToolRegistry` before a tool runs. Every
+`RegistryEntry` carries a `SecurityLevel`; the policy engine combines it with
+rules scoped to a user, agent or session to decide whether a call is allowed,
+denied, or held until a human approves it.
+
+Rules are matched on tool name (with `*` wildcards), security level and regular
+expressions over parameter values. When several rules match, the most specific
+scope wins (session, then agent, then user, then global) and within a scope the
+most restrictive effect wins. Calls no rule matches fall back to the configured
+approval levels. Every decision is written to the caller's `audit_logs`.
+
+When the engine has a database, rules are kept in the system database's
+`tool_policy_rules` table: adding or removing a rule writes through, and
+`load_rules` restores them at startup.
+*/
+
+use crate::clock::unix_now;
+use crate::database::audit::AuditOps;
+use crate::database::policies::{PolicyOps, PolicyRuleRecord};
+use crate::database::DatabaseManager;
+use crate::engines::tool_registry::{RegistryEntry, SecurityLevel};
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::types::RuntimeContext;
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, Mutex, RwLock};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Audit database used when a call is not attributed to a user
+pub const SYSTEM_AUDIT_USER: &str = "system";
+
+/// Who is asking for a tool to run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallContext {
+    pub user_id: Option<String>,
+    pub agent_name: Option<String>,
+    pub session_id: Option<String>,
+}
+
+impl ToolCallContext {
+    /// Caller for a tool invoked by an agent during runtime execution
+    pub fn from_runtime_context(context: &RuntimeContext) -> Self {
+        Self {
+            user_id: context.user_id.clone(),
+            agent_name: Some(context.agent_config.name.clone()),
+            session_id: Some(context.session_id.to_string()),
+        }
+    }
+}
+
+/// Which callers a rule applies to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum PolicyScope {
+    Global,
+    User(String),
+    Agent(String),
+    Session(String),
+}
+
+impl PolicyScope {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PolicyScope::Global => "global",
+            PolicyScope::User(_) => "user",
+            PolicyScope::Agent(_) => "agent",
+            PolicyScope::Session(_) => "session",
+        }
+    }
+
+    /// The user, agent or session the scope names; `None` for global
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            PolicyScope::Global => None,
+            PolicyScope::User(id) | PolicyScope::Agent(id) | PolicyScope::Session(id) => Some(id),
+        }
+    }
+
+    fn applies_to(&self, caller: &ToolCallContext) -> bool {
+        match self {
+            PolicyScope::Global => true,
+            PolicyScope::User(id) => caller.user_id.as_deref() == Some(id.as_str()),
+            PolicyScope::Agent(name) => caller.agent_name.as_deref() == Some(name.as_str()),
+            PolicyScope::Session(id) => caller.session_id.as_deref() == Some(id.as_str()),
+        }
+    }
+
+    fn specificity(&self) -> u8 {
+        match self {
+            PolicyScope::Global => 0,
+            PolicyScope::User(_) => 1,
+            PolicyScope::Agent(_) => 2,
+            PolicyScope::Session(_) => 3,
+        }
+    }
+}
+
+/// What happens to a matching call, ordered from least to most restrictive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyEffect {
+    Allow,
+    RequireApproval,
+    Deny,
+}
+
+impl PolicyEffect {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyEffect::Allow => "allow",
+            PolicyEffect::RequireApproval => "require_approval",
+            PolicyEffect::Deny => "deny",
+        }
+    }
+}
+
+/// A single authorization rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyRule {
+    pub id: String,
+    pub scope: PolicyScope,
+    /// Tool name pattern, `*` matches any run of characters; `None` matches every tool
+    pub tool_pattern: Option<String>,
+    /// Security levels the rule covers; empty covers all levels
+    pub security_levels: Vec<SecurityLevel>,
+    /// Parameter path (dotted) to a regex its value must match; all must match
+    pub parameter_patterns: HashMap<String, String>,
+    pub effect: PolicyEffect,
+    pub reason: Option<String>,
+}
+
+impl PolicyRule {
+    pub fn new(id: &str, scope: PolicyScope, effect: PolicyEffect) -> Self {
+        Self {
+            id: id.to_string(),
+            scope,
+            tool_pattern: None,
+            security_levels: Vec::new(),
+            parameter_patterns: HashMap::new(),
+            effect,
+            reason: None,
+        }
+    }
+
+    pub fn for_tool(mut self, pattern: &str) -> Self {
+        self.tool_pattern = Some(pattern.to_string());
+        self
+    }
+
+    pub fn at_levels(mut self, levels: Vec<SecurityLevel>) -> Self {
+        self.security_levels = levels;
+        self
+    }
+
+    pub fn when_parameter(mut self, path: &str, pattern: &str) -> Self {
+        self.parameter_patterns.insert(path.to_string(), pattern.to_string());
+        self
+    }
+
+    pub fn with_reason(mut self, reason: &str) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+
+    fn compile(&self) -> AriaResult<CompiledRule> {
+        let tool_pattern = match &self.tool_pattern {
+            Some(pattern) => Some(compile_pattern(&self.id, &glob_to_regex(pattern))?),
+            None => None,
+        };
+        let parameter_patterns = self.parameter_patterns
+            .iter()
+            .map(|(path, pattern)| Ok((path.clone(), compile_pattern(&self.id, pattern)?)))
+            .collect::<AriaResult<Vec<_>>>()?;
+
+        Ok(CompiledRule { rule: self.clone(), tool_pattern, parameter_patterns })
+    }
+}
+
+/// Policy defaults for calls no rule matches
+#[derive(Debug, Clone)]
+pub struct PolicyConfig {
+    /// Security levels that need a human approval unless a rule says otherwise
+    pub approval_levels: Vec<SecurityLevel>,
+    /// How long a held call waits for a decision before it is denied
+    pub approval_timeout_secs: u64,
+    /// Record allowed calls in the audit log, not only denials and approvals
+    pub audit_allowed: bool,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            approval_levels: vec![SecurityLevel::Elevated, SecurityLevel::Dangerous],
+            approval_timeout_secs: 300,
+            audit_allowed: true,
+        }
+    }
+}
+
+/// Outcome of evaluating a call against the policy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyDecision {
+    pub effect: PolicyEffect,
+    pub rule_id: Option<String>,
+    pub reason: String,
+}
+
+impl PolicyDecision {
+    pub fn is_allowed(&self) -> bool {
+        self.effect == PolicyEffect::Allow
+    }
+}
+
+/// A tool call held until a human approves or rejects it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    pub approval_id: String,
+    pub tool_name: String,
+    pub security_level: SecurityLevel,
+    pub parameters: Value,
+    pub caller: ToolCallContext,
+    pub reason: String,
+    pub requested_at: u64,
+    pub expires_at: u64,
+}
+
+/// A human decision on a held call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalResolution {
+    pub approved: bool,
+    pub approver: Option<String>,
+    pub comment: Option<String>,
+}
+
+/// Delivers approval requests to the humans who can resolve them
+#[async_trait]
+pub trait ApprovalNotifier: Send + Sync {
+    async fn approval_requested(&self, request: &ApprovalRequest) -> AriaResult<()>;
+}
+
+struct CompiledRule {
+    rule: PolicyRule,
+    tool_pattern: Option<Regex>,
+    parameter_patterns: Vec<(String, Regex)>,
+}
+
+impl CompiledRule {
+    fn matches(&self, tool_name: &str, level: &SecurityLevel, parameters: &Value, caller: &ToolCallContext) -> bool {
+        self.rule.scope.applies_to(caller)
+            && !matches!(&self.tool_pattern, Some(p) if !p.is_match(tool_name))
+            && (self.rule.security_levels.is_empty() || self.rule.security_levels.contains(level))
+            && self.parameter_patterns.iter().all(|(path, pattern)| {
+                parameter_text(parameters, path).is_some_and(|text| pattern.is_match(&text))
+            })
+    }
+}
+
+struct PendingApproval {
+    request: ApprovalRequest,
+    responder: oneshot::Sender<ApprovalResolution>,
+}
+
+/// Evaluates tool calls against the configured rules and manages approval holds
+pub struct ToolPolicyEngine {
+    config: PolicyConfig,
+    rules: RwLock<Vec<CompiledRule>>,
+    pending: Mutex<HashMap<String, PendingApproval>>,
+    notifiers: RwLock<Vec<Arc<dyn ApprovalNotifier>>>,
+    database: Option<Arc<DatabaseManager>>,
+}
+
+impl ToolPolicyEngine {
+    pub fn new(config: PolicyConfig) -> Self {
+        Self {
+            config,
+            rules: RwLock::new(Vec::new()),
+            pending: Mutex::new(HashMap::new()),
+            notifiers: RwLock::new(Vec::new()),
+            database: None,
+        }
+    }
+
+    /// Write decisions to the per-user `audit_logs` tables and keep rules in the system database
+    pub fn with_database(mut self, database: Arc<DatabaseManager>) -> Self {
+        self.database = Some(database);
+        self
+    }
+
+    pub fn config(&self) -> &PolicyConfig {
+        &self.config
+    }
+
+    /// Add a notifier that is told about every new approval hold
+    pub async fn attach_notifier(&self, notifier: Arc<dyn ApprovalNotifier>) {
+        self.notifiers.write().await.push(notifier);
+    }
+
+    /// Add a rule, replacing any existing rule with the same id
+    pub async fn add_rule(&self, rule: PolicyRule) -> AriaResult<()> {
+        let compiled = rule.compile()?;
+        if let Some(database) = &self.database {
+            let record = PolicyRuleRecord {
+                rule_id: rule.id.clone(),
+                scope_kind: rule.scope.kind().to_string(),
+                scope_id: rule.scope.id().map(str::to_string),
+                rule: serde_json::to_value(&rule).map_err(|e| AriaError::new(
+                    ErrorCode::ConfigError,
+                    ErrorCategory::Configuration,
+                    ErrorSeverity::Medium,
+                    &format!("Policy rule {} could not be serialized: {}", rule.id, e),
+                ))?,
+                updated_at: 0,
+            };
+            PolicyOps::save_rule(&database.get_system_database().await?, &record).await?;
+        }
+
+        let mut rules = self.rules.write().await;
+        rules.retain(|r| r.rule.id != compiled.rule.id);
+        rules.push(compiled);
+        Ok(())
+    }
+
+    /// Remove a rule; returns whether a rule was removed
+    pub async fn remove_rule(&self, rule_id: &str) -> AriaResult<bool> {
+        let mut stored = false;
+        if let Some(database) = &self.database {
+            stored = PolicyOps::delete_rule(&database.get_system_database().await?, rule_id).await?;
+        }
+
+        let mut rules = self.rules.write().await;
+        let before = rules.len();
+        rules.retain(|r| r.rule.id != rule_id);
+        Ok(stored || rules.len() != before)
+    }
+
+    /// Replace the in-memory rules with those stored in the system database; returns how
+    /// many were loaded. Stored rules that no longer parse or compile are skipped.
+    pub async fn load_rules(&self) -> AriaResult<usize> {
+        let Some(database) = &self.database else {
+            return Ok(0);
+        };
+
+        let records = PolicyOps::list_rules(&database.get_system_database().await?).await?;
+        let mut loaded = Vec::with_capacity(records.len());
+        for record in records {
+            let compiled = serde_json::from_value::<PolicyRule>(record.rule)
+                .map_err(|e| e.to_string())
+                .and_then(|rule| rule.compile().map_err(|e| e.to_string()));
+            match compiled {
+                Ok(compiled) => loaded.push(compiled),
+                Err(e) => warn!("Skipping stored policy rule {}: {}", record.rule_id, e),
+            }
+        }
+
+        let count = loaded.len();
+        *self.rules.write().await = loaded;
+        info!("Loaded {} tool policy rules", count);
+        Ok(count)
+    }
+
+    pub async fn rules(&self) -> Vec<PolicyRule> {
+        self.rules.read().await.iter().map(|r| r.rule.clone()).collect()
+    }
+
+    /// Evaluate a call without holding it or writing to the audit log
+    pub async fn evaluate(
+        &self,
+        tool_name: &str,
+        level: &SecurityLevel,
+        parameters: &Value,
+        caller: &ToolCallContext,
+    ) -> PolicyDecision {
+        let rules = self.rules.read().await;
+        let decisive = rules
+            .iter()
+            .filter(|r| r.matches(tool_name, level, parameters, caller))
+            .max_by_key(|r| (r.rule.scope.specificity(), r.rule.effect));
+
+        match decisive {
+            Some(compiled) => PolicyDecision {
+                effect: compiled.rule.effect,
+                rule_id: Some(compiled.rule.id.clone()),
+                reason: compiled.rule.reason.clone()
+                    .unwrap_or_else(|| format!("matched policy rule '{}'", compiled.rule.id)),
+            },
+            None if self.config.approval_levels.contains(level) => PolicyDecision {
+                effect: PolicyEffect::RequireApproval,
+                rule_id: None,
+                reason: format!("{} tools require approval", level.as_str()),
+            },
+            None => PolicyDecision {
+                effect: PolicyEffect::Allow,
+                rule_id: None,
+                reason: format!("{} tools are allowed by default", level.as_str()),
+            },
+        }
+    }
+
+    /// Decide whether a call may run, holding it for approval when the policy requires.
+    ///
+    /// The returned decision is always `Allow` or `Deny`.
+    pub async fn authorize(
+        &self,
+        entry: &RegistryEntry,
+        parameters: &Value,
+        caller: &ToolCallContext,
+    ) -> PolicyDecision {
+        let decision = self.evaluate(&entry.name, &entry.security_level, parameters, caller).await;
+        if decision.effect != PolicyEffect::Allow || self.config.audit_allowed {
+            self.audit(caller, "tool_policy_decision", &entry.name, &entry.security_level, &decision).await;
+        }
+
+        if decision.effect != PolicyEffect::RequireApproval {
+            return decision;
+        }
+
+        let resolved = self.hold_for_approval(entry, parameters, caller, &decision).await;
+        self.audit(caller, "tool_approval_resolved", &entry.name, &entry.security_level, &resolved).await;
+        resolved
+    }
+
+    /// Calls currently waiting for a human decision
+    pub async fn pending_approvals(&self) -> Vec<ApprovalRequest> {
+        let mut pending: Vec<ApprovalRequest> = self.pending.lock().await
+            .values()
+            .map(|p| p.request.clone())
+            .collect();
+        pending.sort_by_key(|r| r.requested_at);
+        pending
+    }
+
+    /// Approve or reject a held call, releasing it
+    pub async fn resolve_approval(
+        &self,
+        approval_id: &str,
+        resolution: ApprovalResolution,
+    ) -> AriaResult<ApprovalRequest> {
+        let pending = self.pending.lock().await.remove(approval_id).ok_or_else(|| AriaError::new(
+            ErrorCode::ExecutionError,
+            ErrorCategory::Security,
+            ErrorSeverity::Low,
+            &format!("No pending approval with id '{}'", approval_id),
+        ))?;
+
+        info!(
+            "Approval '{}' for tool '{}' {} by {}",
+            approval_id,
+            pending.request.tool_name,
+            if resolution.approved { "granted" } else { "rejected" },
+            resolution.approver.as_deref().unwrap_or("unknown approver"),
+        );
+        // The waiting call may already have timed out; the resolution is still recorded by the caller
+        let _ = pending.responder.send(resolution);
+        Ok(pending.request)
+    }
+
+    async fn hold_for_approval(
+        &self,
+        entry: &RegistryEntry,
+        parameters: &Value,
+        caller: &ToolCallContext,
+        decision: &PolicyDecision,
+    ) -> PolicyDecision {
+        let notifiers = self.notifiers.read().await.clone();
+        if notifiers.is_empty() {
+            return PolicyDecision {
+                effect: PolicyEffect::Deny,
+                rule_id: decision.rule_id.clone(),
+                reason: format!("{}, but no approval channel is configured", decision.reason),
+            };
+        }
+
+        let now = unix_now();
+        let request = ApprovalRequest {
+            approval_id: Uuid::new_v4().to_string(),
+            tool_name: entry.name.clone(),
+            security_level: entry.security_level.clone(),
+            parameters: parameters.clone(),
+            caller: caller.clone(),
+            reason: decision.reason.clone(),
+            requested_at: now,
+            expires_at: now + self.config.approval_timeout_secs,
+        };
+
+        let (responder, receiver) = oneshot::channel();
+        self.pending.lock().await.insert(
+            request.approval_id.clone(),
+            PendingApproval { request: request.clone(), responder },
+        );
+
+        for notifier in &notifiers {
+            if let Err(e) = notifier.approval_requested(&request).await {
+                warn!("Failed to deliver approval request '{}': {}", request.approval_id, e);
+            }
+        }
+
+        info!("Holding call to '{}' for approval '{}'", entry.name, request.approval_id);
+        let timeout = Duration::from_secs(self.config.approval_timeout_secs);
+        let outcome = tokio::time::timeout(timeout, receiver).await;
+        self.pending.lock().await.remove(&request.approval_id);
+
+        match outcome {
+            Ok(Ok(resolution)) => {
+                let approver = resolution.approver.as_deref().unwrap_or("unknown approver");
+                let comment = resolution.comment.as_deref()
+                    .map(|c| format!(": {}", c))
+                    .unwrap_or_default();
+                PolicyDecision {
+                    effect: if resolution.approved { PolicyEffect::Allow } else { PolicyEffect::Deny },
+                    rule_id: decision.rule_id.clone(),
+                    reason: if resolution.approved {
+                        format!("approved by {}{}", approver, comment)
+                    } else {
+                        format!("rejected by {}{}", approver, comment)
+                    },
+                }
+            }
+            Ok(Err(_)) => PolicyDecision {
+                effect: PolicyEffect::Deny,
+                rule_id: decision.rule_id.clone(),
+                reason: "approval request was dropped".to_string(),
+            },
+            Err(_) => PolicyDecision {
+                effect: PolicyEffect::Deny,
+                rule_id: decision.rule_id.clone(),
+                reason: format!("approval timed out after {}s", self.config.approval_timeout_secs),
+            },
+        }
+    }
+
+    async fn audit(
+        &self,
+        caller: &ToolCallContext,
+        event_type: &str,
+        tool_name: &str,
+        level: &SecurityLevel,
+        decision: &PolicyDecision,
+    ) {
+        let Some(database) = &self.database else {
+            return;
+        };
+
+        let severity = match decision.effect {
+            PolicyEffect::Allow => "info",
+            PolicyEffect::RequireApproval | PolicyEffect::Deny => "warning",
+        };
+        let event_data = serde_json::json!({
+            "tool_name": tool_name,
+            "security_level": level.as_str(),
+            "agent_name": caller.agent_name,
+            "effect": decision.effect.as_str(),
+            "rule_id": decision.rule_id,
+            "reason": decision.reason,
+        });
+
+        let user = caller.user_id.as_deref().unwrap_or(SYSTEM_AUDIT_USER);
+        let result = match database.get_user_database(user).await {
+            Ok(pool) => AuditOps::log_event(
+                &pool,
+                caller.user_id.clone(),
+                caller.session_id.clone(),
+                event_type,
+                Some(event_data),
+                severity,
+            ).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!("Failed to audit policy decision for '{}': {}", tool_name, e);
+        }
+    }
+}
+
+fn glob_to_regex(pattern: &str) -> String {
+    format!("^{}$", regex::escape(pattern).replace(r"\*", ".*"))
+}
+
+fn compile_pattern(rule_id: &str, pattern: &str) -> AriaResult<Regex> {
+    Regex::new(pattern).map_err(|e| AriaError::new(
+        ErrorCode::ConfigError,
+        ErrorCategory::Security,
+        ErrorSeverity::Medium,
+        &format!("Invalid pattern '{}' in policy rule '{}': {}", pattern, rule_id, e),
+    ))
+}
+
+/// Text form of the parameter at a dotted path, used for regex matching
+fn parameter_text(parameters: &Value, path: &str) -> Option<String> {
+    let value = path.split('.').try_fold(parameters, |value, key| value.get(key))?;
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::tool_registry::{ToolScope, ToolType};
+    use crate::types::ResourceRequirements;
+    use serde_json::json;
+
+    fn entry(name: &str, security_level: SecurityLevel) -> RegistryEntry {
+        RegistryEntry {
+            name: name.to_string(),
+            description: String::new(),
+            parameters: json!({}),
+            tool_type: ToolType::Builtin,
+            scope: ToolScope::Primitive,
+            bundle_id: None,
+            version: "1.0.0".to_string(),
+            capabilities: Vec::new(),
+            resource_requirements: ResourceRequirements::default(),
+            security_level,
+        }
+    }
+
+    fn caller(user: &str, agent: &str, session: &str) -> ToolCallContext {
+        ToolCallContext {
+            user_id: Some(user.to_string()),
+            agent_name: Some(agent.to_string()),
+            session_id: Some(session.to_string()),
+        }
+    }
+
+    struct RecordingNotifier {
+        requests: Mutex<Vec<ApprovalRequest>>,
+    }
+
+    #[async_trait]
+    impl ApprovalNotifier for RecordingNotifier {
+        async fn approval_requested(&self, request: &ApprovalRequest) -> AriaResult<()> {
+            self.requests.lock().await.push(request.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_defaults_follow_security_level() {
+        let engine = ToolPolicyEngine::new(PolicyConfig::default());
+        let who = ToolCallContext::default();
+
+        let safe = engine.evaluate("ponderTool", &SecurityLevel::Safe, &json!({}), &who).await;
+        assert_eq!(safe.effect, PolicyEffect::Allow);
+
+        let dangerous = engine.evaluate("createContainer", &SecurityLevel::Dangerous, &json!({}), &who).await;
+        assert_eq!(dangerous.effect, PolicyEffect::RequireApproval);
+        assert!(dangerous.rule_id.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_most_specific_scope_wins() {
+        let engine = ToolPolicyEngine::new(PolicyConfig::default());
+        engine.add_rule(
+            PolicyRule::new("no-containers", PolicyScope::Global, PolicyEffect::Deny).for_tool("*Container")
+        ).await.unwrap();
+        engine.add_rule(
+            PolicyRule::new("ops-agent", PolicyScope::Agent("ops".to_string()), PolicyEffect::Allow).for_tool("*Container")
+        ).await.unwrap();
+        engine.add_rule(
+            PolicyRule::new("alice-hold", PolicyScope::User("alice".to_string()), PolicyEffect::RequireApproval)
+        ).await.unwrap();
+
+        let ops = engine.evaluate("createContainer", &SecurityLevel::Dangerous, &json!({}), &caller("alice", "ops", "s1")).await;
+        assert_eq!(ops.effect, PolicyEffect::Allow);
+        assert_eq!(ops.rule_id.as_deref(), Some("ops-agent"));
+
+        let other = engine.evaluate("createContainer", &SecurityLevel::Dangerous, &json!({}), &caller("alice", "writer", "s1")).await;
+        assert_eq!(other.effect, PolicyEffect::RequireApproval);
+
+        let bob = engine.evaluate("createContainer", &SecurityLevel::Dangerous, &json!({}), &caller("bob", "writer", "s1")).await;
+        assert_eq!(bob.effect, PolicyEffect::Deny);
+    }
+
+    #[tokio::test]
+    async fn test_parameter_patterns_and_levels() {
+        let engine = ToolPolicyEngine::new(PolicyConfig::default());
+        engine.add_rule(
+            PolicyRule::new("no-etc", PolicyScope::Global, PolicyEffect::Deny)
+                .when_parameter("filePath", r"^/etc/")
+                .with_reason("system configuration is off limits")
+        ).await.unwrap();
+        engine.add_rule(
+            PolicyRule::new("trust-limited", PolicyScope::Global, PolicyEffect::Allow)
+                .at_levels(vec![SecurityLevel::Elevated])
+                .when_parameter("request.timeout_seconds", r"^[0-9]{1,2}$")
+        ).await.unwrap();
+
+        let who = ToolCallContext::default();
+        let denied = engine.evaluate("readFileTool", &SecurityLevel::Safe, &json!({ "filePath": "/etc/shadow" }), &who).await;
+        assert_eq!(denied.effect, PolicyEffect::Deny);
+        assert_eq!(denied.reason, "system configuration is off limits");
+
+        let allowed = engine.evaluate("readFileTool", &SecurityLevel::Safe, &json!({ "filePath": "/tmp/a" }), &who).await;
+        assert_eq!(allowed.effect, PolicyEffect::Allow);
+
+        let short = engine.evaluate("execInContainer", &SecurityLevel::Elevated, &json!({ "request": { "timeout_seconds": 30 } }), &who).await;
+        assert_eq!(short.effect, PolicyEffect::Allow);
+        let long = engine.evaluate("execInContainer", &SecurityLevel::Elevated, &json!({ "request": { "timeout_seconds": 3600 } }), &who).await;
+        assert_eq!(long.effect, PolicyEffect::RequireApproval);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_rule_pattern_is_rejected() {
+        let engine = ToolPolicyEngine::new(PolicyConfig::default());
+        let result = engine.add_rule(
+            PolicyRule::new("broken", PolicyScope::Global, PolicyEffect::Deny).when_parameter("path", "([")
+        ).await;
+        assert!(result.is_err());
+        assert!(engine.rules().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_approval_without_channel_is_denied() {
+        let engine = ToolPolicyEngine::new(PolicyConfig::default());
+        let decision = engine.authorize(&entry("createContainer", SecurityLevel::Dangerous), &json!({}), &ToolCallContext::default()).await;
+        assert_eq!(decision.effect, PolicyEffect::Deny);
+        assert!(decision.reason.contains("no approval channel"));
+    }
+
+    #[tokio::test]
+    async fn test_approval_hold_is_released_by_resolution() {
+        let engine = Arc::new(ToolPolicyEngine::new(PolicyConfig::default()));
+        let notifier = Arc::new(RecordingNotifier { requests: Mutex::new(Vec::new()) });
+        engine.attach_notifier(notifier.clone()).await;
+
+        let waiting = {
+            let engine = engine.clone();
+            tokio::spawn(async move {
+                engine.authorize(
+                    &entry("createContainer", SecurityLevel::Dangerous),
+                    &json!({ "image": "ubuntu" }),
+                    &caller("alice", "ops", "s1"),
+                ).await
+            })
+        };
+
+        let approval_id = loop {
+            if let Some(request) = notifier.requests.lock().await.first() {
+                break request.approval_id.clone();
+            }
+            tokio::task::yield_now().await;
+        };
+        assert_eq!(engine.pending_approvals().await.len(), 1);
+
+        engine.resolve_approval(&approval_id, ApprovalResolution {
+            approved: true,
+            approver: Some("bob".to_string()),
+            comment: None,
+        }).await.unwrap();
+
+        let decision = waiting.await.unwrap();
+        assert_eq!(decision.effect, PolicyEffect::Allow);
+        assert_eq!(decision.reason, "approved by bob");
+        assert!(engine.pending_approvals().await.is_empty());
+        assert!(engine.resolve_approval(&approval_id, ApprovalResolution {
+            approved: false,
+            approver: None,
+            comment: None,
+        }).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_approval_times_out() {
+        let engine = ToolPolicyEngine::new(PolicyConfig { approval_timeout_secs: 0, ..PolicyConfig::default() });
+        engine.attach_notifier(Arc::new(RecordingNotifier { requests: Mutex::new(Vec::new()) })).await;
+
+        let decision = engine.authorize(&entry("createContainer", SecurityLevel::Dangerous), &json!({}), &ToolCallContext::default()).await;
+        assert_eq!(decision.effect, PolicyEffect::Deny);
+        assert!(decision.reason.contains("timed out"));
+    }
+}
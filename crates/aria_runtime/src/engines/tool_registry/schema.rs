@@ -0,0 +1,523 @@
+/*!
+# Tool Parameter Schemas
+
+Validation of tool call parameters against the JSON schema each tool declares
+in its `RegistryEntry`. Parameters are checked once, centrally, before the
+registry dispatches to a handler: loosely typed values from the model are
+coerced to the declared type where that is unambiguous, declared defaults are
+filled in, and everything that cannot be repaired is reported as a list of
+structured issues the model can use to correct its next call.
+
+Bundle tools declare their inputs as `name -> type hint` pairs; these are
+lifted into the same schema form by [`schema_from_inputs`].
+*/
+
+use crate::deep_size::DeepValue;
+use crate::types::ToolResult;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A single reason a tool call's parameters were rejected
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    /// Dotted path to the offending parameter, empty for the parameters object itself
+    pub path: String,
+    /// What the schema expected at that path
+    pub expected: String,
+    /// Human and model readable description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Validate `params` against `schema`, returning the coerced and defaulted parameters
+pub fn validate_parameters(schema: &Value, params: Value) -> Result<Value, Vec<ValidationIssue>> {
+    // A call without arguments is an empty parameters object
+    let params = match (params, declared_types(schema).contains(&"object")) {
+        (Value::Null, true) => Value::Object(Map::new()),
+        (params, _) => params,
+    };
+
+    let mut issues = Vec::new();
+    let validated = validate_value(schema, params, "", &mut issues);
+    if issues.is_empty() {
+        Ok(validated)
+    } else {
+        Err(issues)
+    }
+}
+
+/// Tool result reported back to the caller when parameter validation fails
+pub fn validation_failure(tool_name: &str, issues: &[ValidationIssue]) -> ToolResult {
+    let summary = issues.iter().map(|i| i.to_string()).collect::<Vec<_>>().join("; ");
+    let mut metadata = HashMap::new();
+    metadata.insert("error_code".to_string(), DeepValue::string("ToolInvalidParameters".to_string()));
+
+    ToolResult {
+        success: false,
+        result: Some(DeepValue(serde_json::json!({
+            "validation_errors": issues,
+        }))),
+        error: Some(format!("Invalid parameters for tool '{}': {}", tool_name, summary)),
+        metadata,
+        execution_time_ms: 0,
+        resource_usage: None,
+    }
+}
+
+/// Build an object schema from bundle manifest inputs.
+///
+/// Type hints are JSON schema type names or common aliases (`str`, `int`,
+/// `float`, `bool`, `list`, `dict`); a trailing `?` marks the input optional
+/// and a trailing `[]` declares an array of that type. Unknown hints accept
+/// any value.
+pub fn schema_from_inputs(inputs: &HashMap<String, String>) -> Value {
+    let mut names: Vec<&String> = inputs.keys().collect();
+    names.sort();
+
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for name in names {
+        let (mut property, optional) = property_from_hint(&inputs[name]);
+        property.insert("description".to_string(), Value::String(format!("Parameter: {}", name)));
+        properties.insert(name.clone(), Value::Object(property));
+        if !optional {
+            required.push(Value::String(name.clone()));
+        }
+    }
+
+    serde_json::json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+fn property_from_hint(hint: &str) -> (Map<String, Value>, bool) {
+    let hint = hint.trim();
+    let (hint, optional) = match hint.strip_suffix('?') {
+        Some(rest) => (rest.trim(), true),
+        None => (hint, false),
+    };
+
+    let mut property = Map::new();
+    if let Some(item) = hint.strip_suffix("[]") {
+        property.insert("type".to_string(), Value::String("array".to_string()));
+        let (items, _) = property_from_hint(item);
+        property.insert("items".to_string(), Value::Object(items));
+    } else if let Some(ty) = canonical_type(hint) {
+        property.insert("type".to_string(), Value::String(ty.to_string()));
+    }
+    (property, optional)
+}
+
+fn canonical_type(hint: &str) -> Option<&'static str> {
+    match hint.to_lowercase().as_str() {
+        "string" | "str" | "text" => Some("string"),
+        "number" | "float" | "double" => Some("number"),
+        "integer" | "int" => Some("integer"),
+        "boolean" | "bool" => Some("boolean"),
+        "array" | "list" => Some("array"),
+        "object" | "dict" | "map" | "json" => Some("object"),
+        _ => None,
+    }
+}
+
+fn declared_types(schema: &Value) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::String(ty)) => vec![ty.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(|t| t.as_str()).collect(),
+        // Schemas that only list properties describe an object
+        None if schema.get("properties").is_some() => vec!["object"],
+        _ => Vec::new(),
+    }
+}
+
+fn validate_value(schema: &Value, value: Value, path: &str, issues: &mut Vec<ValidationIssue>) -> Value {
+    if !schema.is_object() {
+        return value;
+    }
+
+    let types = declared_types(schema);
+    let value = if types.is_empty() || types.iter().any(|ty| matches_type(ty, &value)) {
+        normalize(&types, value)
+    } else if let Some(coerced) = types.iter().find_map(|ty| coerce(ty, &value)) {
+        coerced
+    } else {
+        issues.push(ValidationIssue {
+            path: path.to_string(),
+            expected: types.join(" or "),
+            message: format!("expected {}, got {}", types.join(" or "), type_name(&value)),
+        });
+        return value;
+    };
+
+    if let Some(allowed) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !allowed.contains(&value) {
+            let options = allowed.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
+            issues.push(ValidationIssue {
+                path: path.to_string(),
+                expected: format!("one of [{}]", options),
+                message: format!("{} is not one of [{}]", value, options),
+            });
+            return value;
+        }
+    }
+
+    match value {
+        Value::Object(object) => Value::Object(validate_object(schema, object, path, issues)),
+        Value::Array(items) => Value::Array(validate_array(schema, items, path, issues)),
+        Value::String(s) => {
+            check_length(schema, s.chars().count(), "minLength", "maxLength", "characters", path, issues);
+            Value::String(s)
+        }
+        Value::Number(n) => {
+            check_range(schema, n.as_f64().unwrap_or_default(), path, issues);
+            Value::Number(n)
+        }
+        other => other,
+    }
+}
+
+fn validate_object(
+    schema: &Value,
+    mut object: Map<String, Value>,
+    path: &str,
+    issues: &mut Vec<ValidationIssue>,
+) -> Map<String, Value> {
+    let empty = Map::new();
+    let properties = schema.get("properties").and_then(|p| p.as_object()).unwrap_or(&empty);
+
+    for (name, property) in properties {
+        if !object.contains_key(name) {
+            if let Some(default) = property.get("default") {
+                object.insert(name.clone(), default.clone());
+            }
+        }
+    }
+
+    if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+        for name in required.iter().filter_map(|r| r.as_str()) {
+            if matches!(object.get(name), None | Some(Value::Null)) {
+                let expected = properties.get(name)
+                    .map(|p| declared_types(p).join(" or "))
+                    .filter(|t| !t.is_empty())
+                    .unwrap_or_else(|| "a value".to_string());
+                issues.push(ValidationIssue {
+                    path: join_path(path, name),
+                    expected,
+                    message: "required parameter is missing".to_string(),
+                });
+            }
+        }
+    }
+
+    let additional = schema.get("additionalProperties");
+    let mut validated = Map::new();
+    for (name, value) in object {
+        let child_path = join_path(path, &name);
+        let value = match (properties.get(&name), additional) {
+            (Some(property), _) => validate_value(property, value, &child_path, issues),
+            (None, Some(Value::Bool(false))) => {
+                let known = properties.keys().cloned().collect::<Vec<_>>().join(", ");
+                issues.push(ValidationIssue {
+                    path: child_path,
+                    expected: format!("one of [{}]", known),
+                    message: "unknown parameter".to_string(),
+                });
+                continue;
+            }
+            (None, Some(extra @ Value::Object(_))) => validate_value(extra, value, &child_path, issues),
+            (None, _) => value,
+        };
+        validated.insert(name, value);
+    }
+    validated
+}
+
+fn validate_array(schema: &Value, items: Vec<Value>, path: &str, issues: &mut Vec<ValidationIssue>) -> Vec<Value> {
+    check_length(schema, items.len(), "minItems", "maxItems", "items", path, issues);
+    match schema.get("items") {
+        Some(item_schema) => items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| validate_value(item_schema, item, &format!("{}[{}]", path, i), issues))
+            .collect(),
+        None => items,
+    }
+}
+
+fn check_length(
+    schema: &Value,
+    len: usize,
+    min_key: &str,
+    max_key: &str,
+    unit: &str,
+    path: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    if let Some(min) = schema.get(min_key).and_then(|m| m.as_u64()) {
+        if (len as u64) < min {
+            issues.push(ValidationIssue {
+                path: path.to_string(),
+                expected: format!("at least {} {}", min, unit),
+                message: format!("has {} {}, expected at least {}", len, unit, min),
+            });
+        }
+    }
+    if let Some(max) = schema.get(max_key).and_then(|m| m.as_u64()) {
+        if len as u64 > max {
+            issues.push(ValidationIssue {
+                path: path.to_string(),
+                expected: format!("at most {} {}", max, unit),
+                message: format!("has {} {}, expected at most {}", len, unit, max),
+            });
+        }
+    }
+}
+
+fn check_range(schema: &Value, n: f64, path: &str, issues: &mut Vec<ValidationIssue>) {
+    if let Some(min) = schema.get("minimum").and_then(|m| m.as_f64()) {
+        if n < min {
+            issues.push(ValidationIssue {
+                path: path.to_string(),
+                expected: format!(">= {}", min),
+                message: format!("{} is less than the minimum of {}", n, min),
+            });
+        }
+    }
+    if let Some(max) = schema.get("maximum").and_then(|m| m.as_f64()) {
+        if n > max {
+            issues.push(ValidationIssue {
+                path: path.to_string(),
+                expected: format!("<= {}", max),
+                message: format!("{} is greater than the maximum of {}", n, max),
+            });
+        }
+    }
+}
+
+fn matches_type(ty: &str, value: &Value) -> bool {
+    match ty {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|f| f.fract() == 0.0),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        // Unknown type names are not ours to enforce
+        _ => true,
+    }
+}
+
+/// Bring an already matching value into canonical form, e.g. `3.0` as integer `3`
+fn normalize(types: &[&str], value: Value) -> Value {
+    let is_float = value.is_f64();
+    if is_float && types.contains(&"integer") && !types.contains(&"number") {
+        if let Some(f) = value.as_f64() {
+            return Value::from(f as i64);
+        }
+    }
+    value
+}
+
+/// Convert `value` to `ty` where the conversion loses nothing
+fn coerce(ty: &str, value: &Value) -> Option<Value> {
+    match (ty, value) {
+        ("number", Value::String(s)) => s.trim().parse::<f64>().ok()
+            .filter(|f| f.is_finite())
+            .and_then(|f| match s.trim().parse::<i64>() {
+                Ok(i) => Some(Value::from(i)),
+                Err(_) => serde_json::Number::from_f64(f).map(Value::Number),
+            }),
+        ("integer", Value::String(s)) => s.trim().parse::<i64>().ok().map(Value::from),
+        ("boolean", Value::String(s)) => match s.trim().to_lowercase().as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        ("string", Value::Number(n)) => Some(Value::String(n.to_string())),
+        ("string", Value::Bool(b)) => Some(Value::String(b.to_string())),
+        ("object", Value::String(s)) => serde_json::from_str::<Value>(s).ok().filter(|v| v.is_object()),
+        ("array", Value::String(s)) => match serde_json::from_str::<Value>(s) {
+            Ok(parsed @ Value::Array(_)) => Some(parsed),
+            _ => Some(Value::Array(vec![value.clone()])),
+        },
+        ("array", Value::Null) => None,
+        ("array", other) => Some(Value::Array(vec![other.clone()])),
+        _ => None,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn join_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", parent, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn search_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string" },
+                "num_results": { "type": "integer", "default": 5, "minimum": 1 },
+                "safe": { "type": "boolean" },
+                "mode": { "type": "string", "enum": ["web", "news"] },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["query"]
+        })
+    }
+
+    #[test]
+    fn test_coerces_and_defaults() {
+        let validated = validate_parameters(&search_schema(), json!({
+            "query": 42,
+            "safe": "true",
+            "tags": "rust"
+        })).unwrap();
+
+        assert_eq!(validated["query"], json!("42"));
+        assert_eq!(validated["num_results"], json!(5));
+        assert_eq!(validated["safe"], json!(true));
+        assert_eq!(validated["tags"], json!(["rust"]));
+    }
+
+    #[test]
+    fn test_numeric_strings_become_numbers() {
+        let validated = validate_parameters(&search_schema(), json!({
+            "query": "aria",
+            "num_results": "10"
+        })).unwrap();
+        assert_eq!(validated["num_results"], json!(10));
+
+        let schema = json!({ "type": "object", "properties": { "ratio": { "type": "number" } } });
+        let validated = validate_parameters(&schema, json!({ "ratio": " 0.5 " })).unwrap();
+        assert_eq!(validated["ratio"], json!(0.5));
+    }
+
+    #[test]
+    fn test_reports_structured_issues() {
+        let issues = validate_parameters(&search_schema(), json!({
+            "num_results": "lots",
+            "mode": "images"
+        })).unwrap_err();
+
+        let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert!(paths.contains(&"query"));
+        assert!(paths.contains(&"num_results"));
+        assert!(paths.contains(&"mode"));
+
+        let missing = issues.iter().find(|i| i.path == "query").unwrap();
+        assert_eq!(missing.expected, "string");
+        assert_eq!(missing.message, "required parameter is missing");
+    }
+
+    #[test]
+    fn test_range_and_additional_properties() {
+        let mut schema = search_schema();
+        schema["additionalProperties"] = json!(false);
+
+        let issues = validate_parameters(&schema, json!({
+            "query": "aria",
+            "num_results": 0,
+            "verbose": true
+        })).unwrap_err();
+
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().any(|i| i.path == "num_results"));
+        assert!(issues.iter().any(|i| i.path == "verbose" && i.message == "unknown parameter"));
+    }
+
+    #[test]
+    fn test_nested_paths_and_json_strings() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "request": {
+                    "type": "object",
+                    "properties": { "limits": { "type": "array", "items": { "type": "integer" } } }
+                }
+            }
+        });
+
+        let validated = validate_parameters(&schema, json!({ "request": "{\"limits\": [1, \"2\"]}" })).unwrap();
+        assert_eq!(validated["request"]["limits"], json!([1, 2]));
+
+        let issues = validate_parameters(&schema, json!({ "request": { "limits": [1, "x"] } })).unwrap_err();
+        assert_eq!(issues[0].path, "request.limits[1]");
+    }
+
+    #[test]
+    fn test_null_parameters_and_permissive_schemas() {
+        let schema = json!({ "type": "object", "properties": {}, "required": [] });
+        assert_eq!(validate_parameters(&schema, Value::Null).unwrap(), json!({}));
+        assert_eq!(validate_parameters(&json!({}), json!({ "any": 1 })).unwrap(), json!({ "any": 1 }));
+    }
+
+    #[test]
+    fn test_validation_failure_result() {
+        let issues = validate_parameters(&search_schema(), json!({})).unwrap_err();
+        let result = validation_failure("webSearchTool", &issues);
+
+        assert!(!result.success);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Invalid parameters for tool 'webSearchTool': query: required parameter is missing")
+        );
+        let errors = &result.result.unwrap().0["validation_errors"];
+        assert_eq!(errors[0]["path"], json!("query"));
+    }
+
+    #[test]
+    fn test_schema_from_inputs() {
+        let mut inputs = HashMap::new();
+        inputs.insert("path".to_string(), "string".to_string());
+        inputs.insert("limit".to_string(), "int?".to_string());
+        inputs.insert("ids".to_string(), "number[]".to_string());
+        inputs.insert("payload".to_string(), "any".to_string());
+
+        let schema = schema_from_inputs(&inputs);
+        assert_eq!(schema["properties"]["path"]["type"], json!("string"));
+        assert_eq!(schema["properties"]["limit"]["type"], json!("integer"));
+        assert_eq!(schema["properties"]["ids"]["items"]["type"], json!("number"));
+        assert!(schema["properties"]["payload"].get("type").is_none());
+        assert_eq!(schema["required"], json!(["ids", "path", "payload"]));
+
+        let validated = validate_parameters(&schema, json!({
+            "path": "/tmp/a", "ids": "[1, 2.5]", "payload": [1], "limit": "3"
+        })).unwrap();
+        assert_eq!(validated["limit"], json!(3));
+        assert_eq!(validated["ids"], json!([1, 2.5]));
+    }
+}
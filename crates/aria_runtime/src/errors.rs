@@ -23,6 +23,10 @@ pub enum ErrorCode {
     // Execution Errors
     ExecutionError,
     ExecutionCancelled,
+    TaskValidationError,
+    ScheduleValidationError,
+    TriggerValidationError,
+    TriggerNotFound,
     StepExecutionError,
     ParameterResolutionError,
 
@@ -85,6 +89,7 @@ pub enum ErrorCode {
     // Security Errors
     AuthenticationFailed,
     PermissionDenied,
+    QuotaExceeded,
     
     // Tool Execution Errors
     ToolExecutionFailed,
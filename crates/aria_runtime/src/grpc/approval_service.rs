@@ -0,0 +1,225 @@
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+
+use super::aria::{
+    approval_service_server::ApprovalService,
+    DeletePolicyRuleRequest, DeletePolicyRuleResponse,
+    ListPendingApprovalsRequest, ListPendingApprovalsResponse,
+    ListPolicyRulesRequest, ListPolicyRulesResponse,
+    ResolveApprovalRequest, ResolveApprovalResponse, ToolApprovalEvent,
+    ToolPolicyEffect, ToolPolicyRule, ToolPolicyScope,
+};
+
+use crate::engines::tool_registry::{
+    ApprovalRequest, ApprovalResolution, PolicyEffect, PolicyRule, PolicyScope,
+    SecurityLevel, ToolPolicyEngine,
+};
+use crate::errors::ErrorCode;
+use super::auth;
+use super::tenancy;
+
+/// Implementation of the ApprovalService
+/// Lets users release or reject tool calls held by the tool policy engine on their behalf,
+/// and admins manage the rules the engine applies
+pub struct ApprovalServiceImpl {
+    policy: Arc<ToolPolicyEngine>,
+}
+
+impl ApprovalServiceImpl {
+    pub fn new(policy: Arc<ToolPolicyEngine>) -> Self {
+        Self { policy }
+    }
+}
+
+/// Convert a held tool call into its wire representation
+pub fn approval_event(request: &ApprovalRequest) -> ToolApprovalEvent {
+    ToolApprovalEvent {
+        approval_id: request.approval_id.clone(),
+        tool_name: request.tool_name.clone(),
+        security_level: request.security_level.as_str().to_string(),
+        parameters_json: request.parameters.to_string(),
+        user_id: request.caller.user_id.clone(),
+        agent_name: request.caller.agent_name.clone(),
+        session_id: request.caller.session_id.clone(),
+        reason: request.reason.clone(),
+        expires_at: Some(prost_types::Timestamp {
+            seconds: request.expires_at as i64,
+            nanos: 0,
+        }),
+    }
+}
+
+/// Convert a policy rule into its wire representation
+pub fn policy_rule(rule: &PolicyRule) -> ToolPolicyRule {
+    let scope = match rule.scope {
+        PolicyScope::Global => ToolPolicyScope::Global,
+        PolicyScope::User(_) => ToolPolicyScope::User,
+        PolicyScope::Agent(_) => ToolPolicyScope::Agent,
+        PolicyScope::Session(_) => ToolPolicyScope::Session,
+    };
+    let effect = match rule.effect {
+        PolicyEffect::Allow => ToolPolicyEffect::Allow,
+        PolicyEffect::RequireApproval => ToolPolicyEffect::RequireApproval,
+        PolicyEffect::Deny => ToolPolicyEffect::Deny,
+    };
+
+    ToolPolicyRule {
+        rule_id: rule.id.clone(),
+        scope: scope as i32,
+        scope_id: rule.scope.id().map(str::to_string),
+        tool_pattern: rule.tool_pattern.clone(),
+        security_levels: rule.security_levels.iter().map(|level| level.as_str().to_string()).collect(),
+        parameter_patterns: rule.parameter_patterns.clone(),
+        effect: effect as i32,
+        reason: rule.reason.clone(),
+    }
+}
+
+/// Convert a wire rule into a policy rule, generating an id when none is given
+fn parse_policy_rule(rule: ToolPolicyRule) -> Result<PolicyRule, Status> {
+    let scope_id = rule.scope_id.filter(|id| !id.is_empty());
+    let scoped = |scope: fn(String) -> PolicyScope| {
+        scope_id.clone()
+            .map(scope)
+            .ok_or_else(|| Status::invalid_argument("User, agent and session rules need a scope_id"))
+    };
+    let scope = match ToolPolicyScope::try_from(rule.scope) {
+        Ok(ToolPolicyScope::Global) => PolicyScope::Global,
+        Ok(ToolPolicyScope::User) => scoped(PolicyScope::User)?,
+        Ok(ToolPolicyScope::Agent) => scoped(PolicyScope::Agent)?,
+        Ok(ToolPolicyScope::Session) => scoped(PolicyScope::Session)?,
+        _ => return Err(Status::invalid_argument("Policy rules need a scope")),
+    };
+    let effect = match ToolPolicyEffect::try_from(rule.effect) {
+        Ok(ToolPolicyEffect::Allow) => PolicyEffect::Allow,
+        Ok(ToolPolicyEffect::RequireApproval) => PolicyEffect::RequireApproval,
+        Ok(ToolPolicyEffect::Deny) => PolicyEffect::Deny,
+        _ => return Err(Status::invalid_argument("Policy rules need an effect")),
+    };
+    let security_levels = rule.security_levels
+        .iter()
+        .map(|level| SecurityLevel::parse(level)
+            .ok_or_else(|| Status::invalid_argument(format!("Unknown security level: {}", level))))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let rule_id = if rule.rule_id.is_empty() { uuid::Uuid::new_v4().to_string() } else { rule.rule_id };
+    Ok(PolicyRule {
+        id: rule_id,
+        scope,
+        tool_pattern: rule.tool_pattern.filter(|pattern| !pattern.is_empty()),
+        security_levels,
+        parameter_patterns: rule.parameter_patterns,
+        effect,
+        reason: rule.reason.filter(|reason| !reason.is_empty()),
+    })
+}
+
+#[tonic::async_trait]
+impl ApprovalService for ApprovalServiceImpl {
+    async fn list_pending_approvals(
+        &self,
+        request: Request<ListPendingApprovalsRequest>,
+    ) -> Result<Response<ListPendingApprovalsResponse>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        let approvals = self.policy.pending_approvals().await
+            .iter()
+            .filter(|r| tenancy::can_access(&caller, r.caller.user_id.as_deref()))
+            .filter(|r| req.session_id.is_none() || r.caller.session_id == req.session_id)
+            .map(approval_event)
+            .collect();
+
+        Ok(Response::new(ListPendingApprovalsResponse { approvals }))
+    }
+
+    async fn resolve_approval(
+        &self,
+        request: Request<ResolveApprovalRequest>,
+    ) -> Result<Response<ResolveApprovalResponse>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        // Only the user a call was made for (or an admin) may release it
+        let owned = self.policy.pending_approvals().await
+            .iter()
+            .any(|r| r.approval_id == req.approval_id && tenancy::can_access(&caller, r.caller.user_id.as_deref()));
+        if !owned {
+            return Err(Status::not_found(format!("No pending approval {}", req.approval_id)));
+        }
+
+        // The authenticated caller is recorded as the approver, whatever the request claims
+        let resolution = ApprovalResolution {
+            approved: req.approved,
+            approver: Some(caller.user_id),
+            comment: req.comment,
+        };
+
+        match self.policy.resolve_approval(&req.approval_id, resolution).await {
+            Ok(held) => Ok(Response::new(ResolveApprovalResponse {
+                approval: Some(approval_event(&held)),
+            })),
+            Err(e) => Err(Status::not_found(e.to_string())),
+        }
+    }
+
+    async fn list_policy_rules(
+        &self,
+        request: Request<ListPolicyRulesRequest>,
+    ) -> Result<Response<ListPolicyRulesResponse>, Status> {
+        let caller = auth::caller(&request)?;
+
+        // Non-admins see the global rules and those scoped to them
+        let rules = self.policy.rules().await
+            .iter()
+            .filter(|rule| match &rule.scope {
+                PolicyScope::Global => true,
+                PolicyScope::User(user_id) => tenancy::can_access(&caller, Some(user_id.as_str())),
+                PolicyScope::Agent(_) | PolicyScope::Session(_) => caller.is_admin(),
+            })
+            .map(policy_rule)
+            .collect();
+
+        Ok(Response::new(ListPolicyRulesResponse { rules }))
+    }
+
+    async fn set_policy_rule(
+        &self,
+        request: Request<ToolPolicyRule>,
+    ) -> Result<Response<ToolPolicyRule>, Status> {
+        let caller = auth::caller(&request)?;
+        if !caller.is_admin() {
+            return Err(Status::permission_denied("Only admins may manage tool policy rules"));
+        }
+        let rule = parse_policy_rule(request.into_inner())?;
+
+        tracing::info!("User {} setting tool policy rule {}: scope={:?}, tool={:?}, effect={}",
+                      caller.user_id, rule.id, rule.scope, rule.tool_pattern, rule.effect.as_str());
+
+        let response = policy_rule(&rule);
+        self.policy.add_rule(rule).await.map_err(|e| match e.code {
+            ErrorCode::ConfigError => Status::invalid_argument(e.to_string()),
+            _ => Status::internal(e.to_string()),
+        })?;
+
+        Ok(Response::new(response))
+    }
+
+    async fn delete_policy_rule(
+        &self,
+        request: Request<DeletePolicyRuleRequest>,
+    ) -> Result<Response<DeletePolicyRuleResponse>, Status> {
+        let caller = auth::caller(&request)?;
+        if !caller.is_admin() {
+            return Err(Status::permission_denied("Only admins may manage tool policy rules"));
+        }
+        let req = request.into_inner();
+
+        tracing::info!("User {} deleting tool policy rule {}", caller.user_id, req.rule_id);
+
+        let deleted = self.policy.remove_rule(&req.rule_id).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(DeletePolicyRuleResponse { deleted }))
+    }
+}
@@ -0,0 +1,578 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use ed25519_dalek::{Signature, VerifyingKey};
+use tonic::body::BoxBody;
+use tonic::codegen::http;
+use tonic::metadata::MetadataMap;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+use crate::clock::unix_now;
+use crate::database::devices::DeviceOps;
+use crate::database::users::UserOps;
+use crate::database::DatabaseManager;
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+
+/// Metadata carrying the enrolled device id of a signed request
+pub const DEVICE_ID_HEADER: &str = "x-aria-device-id";
+/// Metadata carrying the unix time (seconds) a request was signed at
+pub const TIMESTAMP_HEADER: &str = "x-aria-timestamp";
+/// Metadata carrying a single-use value chosen by the client
+pub const NONCE_HEADER: &str = "x-aria-nonce";
+/// Metadata carrying the base64 ed25519 signature of `request_signing_payload`
+pub const SIGNATURE_HEADER: &str = "x-aria-signature";
+/// Metadata carrying `Bearer <token>` as an alternative to per-request signatures
+pub const AUTHORIZATION_HEADER: &str = "authorization";
+
+/// Longest nonce accepted, to bound the replay cache
+const MAX_NONCE_LEN: usize = 128;
+
+/// What an authenticated caller may see beyond their own resources
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserRole {
+    /// Sees only resources they own
+    User,
+    /// Sees and manages every tenant's resources
+    Admin,
+}
+
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::User => "user",
+            UserRole::Admin => "admin",
+        }
+    }
+}
+
+/// Identity of an authenticated caller, placed in request extensions by `CallerAuthenticator`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallerIdentity {
+    pub user_id: String,
+    pub device_id: String,
+    pub role: UserRole,
+}
+
+impl CallerIdentity {
+    pub fn is_admin(&self) -> bool {
+        self.role == UserRole::Admin
+    }
+}
+
+/// Tolerances for signed requests and tokens
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    /// How far a request timestamp may drift from the server clock
+    pub max_clock_skew_secs: u64,
+    /// Longest lifetime a client may give a bearer token
+    pub max_token_ttl_secs: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            max_clock_skew_secs: 60,
+            max_token_ttl_secs: 900,
+        }
+    }
+}
+
+/// A device key enrolled from an authorized devices file
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthorizedDevice {
+    pub user_id: String,
+    pub device_id: String,
+    pub public_key: String,
+}
+
+/// Bytes a client signs for a single request to the gRPC method at `path`
+/// (e.g. `/aria.TaskService/LaunchTask`), so a signature only authorizes that method
+pub fn request_signing_payload(device_id: &str, timestamp: u64, nonce: &str, path: &str) -> Vec<u8> {
+    format!("aria-request\n{}\n{}\n{}\n{}", device_id, timestamp, nonce, path).into_bytes()
+}
+
+/// Bytes a client signs to mint a bearer token valid until `expires_at`
+pub fn token_signing_payload(device_id: &str, expires_at: u64) -> Vec<u8> {
+    format!("aria-token\n{}\n{}", device_id, expires_at).into_bytes()
+}
+
+/// Bearer token for `authorization: Bearer <token>`
+pub fn encode_token(device_id: &str, expires_at: u64, signature: &[u8]) -> String {
+    format!("{}.{}.{}", device_id, expires_at, STANDARD.encode(signature))
+}
+
+/// Device ids are the first field of a bearer token, so they may not contain its `.` separator
+pub fn validate_device_id(device_id: &str) -> AriaResult<()> {
+    if device_id.is_empty() || !device_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(key_error(&format!(
+            "Invalid device id '{}': use letters, digits, '-' or '_'", device_id
+        )));
+    }
+    Ok(())
+}
+
+/// The gRPC method path of a call, recorded by `record_request_path` for signature checks
+#[derive(Debug, Clone)]
+pub struct RequestPath(pub String);
+
+/// Server layer function that exposes each call's `:path` to `CallerAuthenticator`,
+/// which only sees metadata and extensions
+pub fn record_request_path(mut request: http::Request<BoxBody>) -> http::Request<BoxBody> {
+    let path = RequestPath(request.uri().path().to_string());
+    request.extensions_mut().insert(path);
+    request
+}
+
+/// Parse an OpenSSH ed25519 public key (`ssh-ed25519 <base64> [comment]`)
+pub fn parse_public_key(text: &str) -> AriaResult<VerifyingKey> {
+    let mut parts = text.split_whitespace();
+    if parts.next() != Some("ssh-ed25519") {
+        return Err(key_error("Public key must be in 'ssh-ed25519 <base64>' format"));
+    }
+    let blob = parts.next()
+        .and_then(|encoded| STANDARD.decode(encoded).ok())
+        .ok_or_else(|| key_error("Public key is not valid base64"))?;
+
+    let (algorithm, rest) = read_ssh_string(&blob).ok_or_else(|| key_error("Public key blob is truncated"))?;
+    let (key, rest) = read_ssh_string(rest).ok_or_else(|| key_error("Public key blob is truncated"))?;
+    if algorithm != b"ssh-ed25519" || !rest.is_empty() {
+        return Err(key_error("Public key blob is not an ed25519 key"));
+    }
+
+    let key: [u8; 32] = key.try_into().map_err(|_| key_error("Ed25519 public keys are 32 bytes"))?;
+    VerifyingKey::from_bytes(&key).map_err(|e| key_error(&format!("Invalid ed25519 public key: {}", e)))
+}
+
+/// Parse an authorized devices file: one `<user_id> <device_id> ssh-ed25519 <base64> [comment]` per line
+pub fn parse_authorized_devices(contents: &str) -> AriaResult<Vec<AuthorizedDevice>> {
+    contents.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(number, line)| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next()) {
+                (Some(user_id), Some(device_id)) => {
+                    validate_device_id(device_id)
+                        .map_err(|e| key_error(&format!("Line {}: {}", number + 1, e.message)))?;
+                    let public_key = fields.collect::<Vec<_>>().join(" ");
+                    parse_public_key(&public_key)
+                        .map_err(|e| key_error(&format!("Line {}: {}", number + 1, e.message)))?;
+                    Ok(AuthorizedDevice {
+                        user_id: user_id.to_string(),
+                        device_id: device_id.to_string(),
+                        public_key,
+                    })
+                }
+                _ => Err(key_error(&format!("Line {}: expected '<user_id> <device_id> ssh-ed25519 <base64>'", number + 1))),
+            }
+        })
+        .collect()
+}
+
+struct DeviceKey {
+    user_id: String,
+    key: VerifyingKey,
+}
+
+/// Tonic interceptor that authenticates every call against enrolled device keys.
+///
+/// A call authenticates in one of two ways:
+/// - per request: `x-aria-device-id`, `x-aria-timestamp`, `x-aria-nonce` and
+///   `x-aria-signature` over `request_signing_payload` for the called method;
+///   each nonce is accepted once;
+/// - with a token: `authorization: Bearer <device_id>.<expires_at>.<signature>`, the
+///   signature covering `token_signing_payload`, valid until `expires_at`.
+///
+/// Keys and admin grants are held in memory so the interceptor never waits on the
+/// database; enrolment, revocation and role changes through this type update both.
+#[derive(Clone)]
+pub struct CallerAuthenticator {
+    config: AuthConfig,
+    devices: Arc<RwLock<HashMap<String, DeviceKey>>>,
+    admins: Arc<RwLock<HashSet<String>>>,
+    /// `device:nonce` -> unix time after which the nonce's timestamp is stale anyway
+    seen_nonces: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl CallerAuthenticator {
+    pub fn new(config: AuthConfig) -> Self {
+        Self {
+            config,
+            devices: Arc::new(RwLock::new(HashMap::new())),
+            admins: Arc::new(RwLock::new(HashSet::new())),
+            seen_nonces: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Replace the in-memory keys and admin grants with those in the system database
+    pub async fn load(&self, database: &DatabaseManager) -> AriaResult<usize> {
+        let pool = database.get_system_database().await?;
+        let mut devices = HashMap::new();
+        for record in DeviceOps::list_active_devices(&pool).await? {
+            match parse_public_key(&record.public_key) {
+                Ok(key) => {
+                    devices.insert(record.device_id, DeviceKey { user_id: record.user_id, key });
+                }
+                Err(e) => tracing::warn!("Skipping device {} with unusable key: {}", record.device_id, e),
+            }
+        }
+
+        let admins = UserOps::list_users_with_role(&pool, UserRole::Admin.as_str()).await?;
+
+        let count = devices.len();
+        *self.devices.write().unwrap_or_else(PoisonError::into_inner) = devices;
+        *self.admins.write().unwrap_or_else(PoisonError::into_inner) = admins.into_iter().collect();
+        Ok(count)
+    }
+
+    /// Enroll a device key for a user, creating the user if needed
+    pub async fn enroll_device(
+        &self,
+        database: &DatabaseManager,
+        user_id: &str,
+        device_id: &str,
+        public_key: &str,
+    ) -> AriaResult<()> {
+        validate_device_id(device_id)?;
+        let key = parse_public_key(public_key)?;
+        let pool = database.get_system_database().await?;
+
+        if UserOps::get_user(&pool, user_id).await.is_err() {
+            UserOps::create_user(&pool, user_id, user_id, None).await?;
+        }
+        DeviceOps::register_device(&pool, device_id, user_id, public_key).await?;
+
+        self.trust_device(user_id, device_id, key);
+        Ok(())
+    }
+
+    /// Enroll every device listed in an authorized devices file; a missing file enrolls nothing
+    pub async fn enroll_authorized_devices(&self, database: &DatabaseManager, path: &Path) -> AriaResult<usize> {
+        let contents = match tokio::fs::read_to_string(path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::warn!("No authorized devices file at {}", path.display());
+                return Ok(0);
+            }
+            Err(e) => return Err(AriaError::new(
+                ErrorCode::IoError,
+                ErrorCategory::System,
+                ErrorSeverity::Medium,
+                &format!("Failed to read {}: {}", path.display(), e),
+            )),
+        };
+
+        let devices = parse_authorized_devices(&contents)?;
+        for device in &devices {
+            self.enroll_device(database, &device.user_id, &device.device_id, &device.public_key).await?;
+        }
+        Ok(devices.len())
+    }
+
+    /// Revoke a device; its signatures and tokens stop working immediately
+    pub async fn revoke_device(&self, database: &DatabaseManager, device_id: &str) -> AriaResult<bool> {
+        let pool = database.get_system_database().await?;
+        let revoked = DeviceOps::revoke_device(&pool, device_id).await?;
+        self.devices.write().unwrap_or_else(PoisonError::into_inner).remove(device_id);
+        Ok(revoked)
+    }
+
+    /// Give a user a role, creating the user if needed; takes effect on their next call
+    pub async fn grant_role(&self, database: &DatabaseManager, user_id: &str, role: UserRole) -> AriaResult<()> {
+        let pool = database.get_system_database().await?;
+
+        if UserOps::get_user(&pool, user_id).await.is_err() {
+            UserOps::create_user(&pool, user_id, user_id, None).await?;
+        }
+        UserOps::set_role(&pool, user_id, role.as_str()).await?;
+
+        self.trust_role(user_id, role);
+        Ok(())
+    }
+
+    /// Apply a role in memory without touching the database
+    pub fn trust_role(&self, user_id: &str, role: UserRole) {
+        let mut admins = self.admins.write().unwrap_or_else(PoisonError::into_inner);
+        match role {
+            UserRole::Admin => admins.insert(user_id.to_string()),
+            UserRole::User => admins.remove(user_id),
+        };
+    }
+
+    /// Accept a device key without touching the database
+    pub fn trust_device(&self, user_id: &str, device_id: &str, key: VerifyingKey) {
+        self.devices.write().unwrap_or_else(PoisonError::into_inner).insert(
+            device_id.to_string(),
+            DeviceKey { user_id: user_id.to_string(), key },
+        );
+    }
+
+    /// Authenticate a call to the method at `path` from its metadata
+    pub fn authenticate(&self, metadata: &MetadataMap, path: Option<&str>) -> Result<CallerIdentity, Status> {
+        self.authenticate_at(metadata, path, unix_now())
+    }
+
+    fn authenticate_at(&self, metadata: &MetadataMap, path: Option<&str>, now: u64) -> Result<CallerIdentity, Status> {
+        match header(metadata, AUTHORIZATION_HEADER)? {
+            Some(authorization) => {
+                let token = authorization.strip_prefix("Bearer ")
+                    .ok_or_else(|| Status::unauthenticated("Authorization must be a bearer token"))?;
+                self.verify_token(token.trim(), now)
+            }
+            None => {
+                // Without the path a signature could not be tied to one method
+                let path = path.ok_or_else(|| Status::internal("Request path was not recorded for signature checks"))?;
+                self.verify_signed_request(metadata, path, now)
+            }
+        }
+    }
+
+    fn verify_token(&self, token: &str, now: u64) -> Result<CallerIdentity, Status> {
+        let mut parts = token.splitn(3, '.');
+        let (device_id, expires_at, signature) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(device_id), Some(expires_at), Some(signature)) => (device_id, expires_at, signature),
+            _ => return Err(Status::unauthenticated("Malformed bearer token")),
+        };
+        let expires_at: u64 = expires_at.parse()
+            .map_err(|_| Status::unauthenticated("Malformed bearer token"))?;
+
+        if expires_at <= now {
+            return Err(Status::unauthenticated("Bearer token has expired"));
+        }
+        if expires_at - now > self.config.max_token_ttl_secs {
+            return Err(Status::unauthenticated(format!(
+                "Bearer tokens may live at most {} seconds", self.config.max_token_ttl_secs
+            )));
+        }
+
+        self.verify(device_id, &token_signing_payload(device_id, expires_at), signature)
+    }
+
+    fn verify_signed_request(&self, metadata: &MetadataMap, path: &str, now: u64) -> Result<CallerIdentity, Status> {
+        let required = |name: &str| {
+            header(metadata, name)?.ok_or_else(|| Status::unauthenticated("Request is not signed"))
+        };
+        let device_id = required(DEVICE_ID_HEADER)?;
+        let timestamp = required(TIMESTAMP_HEADER)?;
+        let nonce = required(NONCE_HEADER)?;
+        let signature = required(SIGNATURE_HEADER)?;
+
+        let timestamp: u64 = timestamp.parse()
+            .map_err(|_| Status::unauthenticated("Malformed request timestamp"))?;
+        if timestamp.abs_diff(now) > self.config.max_clock_skew_secs {
+            return Err(Status::unauthenticated("Request timestamp is outside the allowed clock skew"));
+        }
+        if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
+            return Err(Status::unauthenticated("Malformed request nonce"));
+        }
+
+        let payload = request_signing_payload(device_id, timestamp, nonce, path);
+        let identity = self.verify(device_id, &payload, signature)?;
+
+        // Only checked once the signature is valid, so unauthenticated callers cannot fill the cache
+        let mut seen = self.seen_nonces.lock().unwrap_or_else(PoisonError::into_inner);
+        seen.retain(|_, stale_after| *stale_after >= now);
+        let key = format!("{}:{}", device_id, nonce);
+        if seen.contains_key(&key) {
+            return Err(Status::unauthenticated("Request nonce has already been used"));
+        }
+        seen.insert(key, timestamp + self.config.max_clock_skew_secs);
+
+        Ok(identity)
+    }
+
+    fn verify(&self, device_id: &str, payload: &[u8], signature: &str) -> Result<CallerIdentity, Status> {
+        let devices = self.devices.read().unwrap_or_else(PoisonError::into_inner);
+        let device = devices.get(device_id).ok_or_else(|| {
+            tracing::warn!("Rejected call from unknown device {}", device_id);
+            Status::unauthenticated("Unknown or revoked device")
+        })?;
+
+        let signature = STANDARD.decode(signature).ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or_else(|| Status::unauthenticated("Malformed signature"))?;
+
+        device.key.verify_strict(payload, &signature).map_err(|_| {
+            tracing::warn!("Rejected call with a bad signature for device {}", device_id);
+            Status::unauthenticated("Signature verification failed")
+        })?;
+
+        let is_admin = self.admins.read().unwrap_or_else(PoisonError::into_inner).contains(&device.user_id);
+        Ok(CallerIdentity {
+            user_id: device.user_id.clone(),
+            device_id: device_id.to_string(),
+            role: if is_admin { UserRole::Admin } else { UserRole::User },
+        })
+    }
+}
+
+impl Interceptor for CallerAuthenticator {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let path = request.extensions().get::<RequestPath>().map(|path| path.0.clone());
+        let identity = self.authenticate(request.metadata(), path.as_deref())?;
+        request.extensions_mut().insert(identity);
+        Ok(request)
+    }
+}
+
+/// The authenticated caller of a request that passed through `CallerAuthenticator`
+pub fn caller<T>(request: &Request<T>) -> Result<CallerIdentity, Status> {
+    request.extensions()
+        .get::<CallerIdentity>()
+        .cloned()
+        .ok_or_else(|| Status::unauthenticated("Caller is not authenticated"))
+}
+
+fn header<'a>(metadata: &'a MetadataMap, name: &str) -> Result<Option<&'a str>, Status> {
+    metadata.get(name)
+        .map(|value| value.to_str().map_err(|_| Status::unauthenticated(format!("Invalid {} header", name))))
+        .transpose()
+}
+
+/// Split one length-prefixed string off an OpenSSH key blob
+fn read_ssh_string(blob: &[u8]) -> Option<(&[u8], &[u8])> {
+    let length = u32::from_be_bytes(blob.get(..4)?.try_into().ok()?) as usize;
+    let rest = &blob[4..];
+    (rest.len() >= length).then(|| rest.split_at(length))
+}
+
+fn key_error(message: &str) -> AriaError {
+    AriaError::new(ErrorCode::AuthenticationFailed, ErrorCategory::Security, ErrorSeverity::Medium, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const NOW: u64 = 1_700_000_000;
+    const PATH: &str = "/aria.TaskService/LaunchTask";
+
+    fn ssh_public_key(signing_key: &SigningKey) -> String {
+        let mut blob = Vec::new();
+        for part in [&b"ssh-ed25519"[..], signing_key.verifying_key().as_bytes()] {
+            blob.extend_from_slice(&(part.len() as u32).to_be_bytes());
+            blob.extend_from_slice(part);
+        }
+        format!("ssh-ed25519 {} alice@laptop", STANDARD.encode(blob))
+    }
+
+    fn setup() -> (CallerAuthenticator, SigningKey) {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let authenticator = CallerAuthenticator::new(AuthConfig::default());
+        authenticator.trust_device("alice", "laptop", signing_key.verifying_key());
+        (authenticator, signing_key)
+    }
+
+    fn signed(signing_key: &SigningKey, device_id: &str, timestamp: u64, nonce: &str) -> MetadataMap {
+        let signature = signing_key.sign(&request_signing_payload(device_id, timestamp, nonce, PATH));
+        let mut metadata = MetadataMap::new();
+        metadata.insert(DEVICE_ID_HEADER, device_id.parse().unwrap());
+        metadata.insert(TIMESTAMP_HEADER, timestamp.to_string().parse().unwrap());
+        metadata.insert(NONCE_HEADER, nonce.parse().unwrap());
+        metadata.insert(SIGNATURE_HEADER, STANDARD.encode(signature.to_bytes()).parse().unwrap());
+        metadata
+    }
+
+    fn bearer(signing_key: &SigningKey, device_id: &str, expires_at: u64) -> MetadataMap {
+        let signature = signing_key.sign(&token_signing_payload(device_id, expires_at));
+        let token = encode_token(device_id, expires_at, &signature.to_bytes());
+        let mut metadata = MetadataMap::new();
+        metadata.insert(AUTHORIZATION_HEADER, format!("Bearer {}", token).parse().unwrap());
+        metadata
+    }
+
+    #[test]
+    fn test_signed_request_identifies_caller() {
+        let (auth, key) = setup();
+
+        let identity = auth.authenticate_at(&signed(&key, "laptop", NOW, "n1"), Some(PATH), NOW + 5).unwrap();
+        assert_eq!(identity, CallerIdentity {
+            user_id: "alice".to_string(),
+            device_id: "laptop".to_string(),
+            role: UserRole::User,
+        });
+    }
+
+    #[test]
+    fn test_admin_role_applies_to_every_device() {
+        let (auth, key) = setup();
+
+        auth.trust_role("alice", UserRole::Admin);
+        assert!(auth.authenticate_at(&signed(&key, "laptop", NOW, "n1"), Some(PATH), NOW).unwrap().is_admin());
+
+        auth.trust_role("alice", UserRole::User);
+        assert!(!auth.authenticate_at(&signed(&key, "laptop", NOW, "n2"), Some(PATH), NOW).unwrap().is_admin());
+    }
+
+    #[test]
+    fn test_signed_request_rejections() {
+        let (auth, key) = setup();
+        let other = SigningKey::from_bytes(&[9u8; 32]);
+
+        assert!(auth.authenticate_at(&MetadataMap::new(), Some(PATH), NOW).is_err());
+        assert!(auth.authenticate_at(&signed(&other, "laptop", NOW, "n1"), Some(PATH), NOW).is_err());
+        assert!(auth.authenticate_at(&signed(&key, "phone", NOW, "n1"), Some(PATH), NOW).is_err());
+        assert!(auth.authenticate_at(&signed(&key, "laptop", NOW - 120, "n1"), Some(PATH), NOW).is_err());
+
+        assert!(auth.authenticate_at(&signed(&key, "laptop", NOW, "n2"), Some(PATH), NOW).is_ok());
+        let replay = auth.authenticate_at(&signed(&key, "laptop", NOW, "n2"), Some(PATH), NOW + 1).unwrap_err();
+        assert!(replay.message().contains("already been used"));
+    }
+
+    #[test]
+    fn test_signature_is_bound_to_method() {
+        let (auth, key) = setup();
+
+        let metadata = signed(&key, "laptop", NOW, "n1");
+        assert!(auth.authenticate_at(&metadata, Some("/aria.TaskService/CancelTask"), NOW).is_err());
+        assert!(auth.authenticate_at(&metadata, None, NOW).is_err());
+        assert!(auth.authenticate_at(&metadata, Some(PATH), NOW).is_ok());
+    }
+
+    #[test]
+    fn test_bearer_tokens() {
+        let (auth, key) = setup();
+
+        assert_eq!(auth.authenticate_at(&bearer(&key, "laptop", NOW + 300), Some(PATH), NOW).unwrap().user_id, "alice");
+        // Tokens are reusable until they expire
+        assert!(auth.authenticate_at(&bearer(&key, "laptop", NOW + 300), Some(PATH), NOW + 10).is_ok());
+        assert!(auth.authenticate_at(&bearer(&key, "laptop", NOW + 300), Some(PATH), NOW + 300).is_err());
+        assert!(auth.authenticate_at(&bearer(&key, "laptop", NOW + 86_400), Some(PATH), NOW).is_err());
+
+        // A request signature cannot be replayed as a token signature
+        let signature = key.sign(&request_signing_payload("laptop", NOW + 300, "x", PATH));
+        let mut forged = MetadataMap::new();
+        let token = encode_token("laptop", NOW + 300, &signature.to_bytes());
+        forged.insert(AUTHORIZATION_HEADER, format!("Bearer {}", token).parse().unwrap());
+        assert!(auth.authenticate_at(&forged, Some(PATH), NOW).is_err());
+    }
+
+    #[test]
+    fn test_parse_openssh_public_key() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        assert_eq!(parse_public_key(&ssh_public_key(&key)).unwrap(), key.verifying_key());
+
+        assert!(parse_public_key("ssh-rsa AAAAB3NzaC1yc2E").is_err());
+        assert!(parse_public_key("ssh-ed25519 not-base64!").is_err());
+        assert!(parse_public_key(&format!("ssh-ed25519 {}", STANDARD.encode([0u8, 0, 0, 11]))).is_err());
+    }
+
+    #[test]
+    fn test_parse_authorized_devices() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let contents = format!("# enrolled devices\n\nalice laptop {}\n", ssh_public_key(&key));
+
+        let devices = parse_authorized_devices(&contents).unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!((devices[0].user_id.as_str(), devices[0].device_id.as_str()), ("alice", "laptop"));
+        assert!(devices[0].public_key.ends_with("alice@laptop"));
+
+        assert!(parse_authorized_devices("alice laptop ssh-ed25519 AAAA").is_err());
+        assert!(parse_authorized_devices(&format!("alice lap.top {}", ssh_public_key(&key))).is_err());
+        assert!(parse_authorized_devices("alice").is_err());
+    }
+}
@@ -13,6 +13,7 @@ use crate::engines::container::quilt::quilt_proto;
 use crate::engines::tool_registry::{ToolRegistry, ToolRegistryInterface};
 use crate::database::DatabaseManager;
 use crate::errors::AriaResult;
+use super::auth;
 
 /// Implementation of the high-level BundleService
 /// This service acts as a proxy to the lower-level Quilt bundle management
@@ -32,9 +33,10 @@ impl BundleService for BundleServiceImpl {
         &self,
         request: Request<Streaming<UploadBundleRequest>>,
     ) -> Result<Response<UploadBundleResponse>, Status> {
+        let caller = auth::caller(&request)?;
         let mut stream = request.into_inner();
         
-        tracing::info!("Starting bundle upload stream");
+        tracing::info!("Starting bundle upload stream for user {}", caller.user_id);
         
         // First message should contain metadata
         let first_message = match stream.next().await {
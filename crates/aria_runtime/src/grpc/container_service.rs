@@ -15,21 +15,27 @@ use super::aria::{
 
 use crate::engines::container::quilt::QuiltService;
 use crate::engines::container::quilt::quilt_proto;
+use crate::database::tenancy::TenantResource;
 use crate::database::DatabaseManager;
-use crate::errors::{AriaError, AriaResult};
+use crate::errors::{AriaError, AriaResult, ErrorCode};
+use super::auth;
+use super::tenancy::{self, OWNER_LABEL, SESSION_LABEL};
 
 /// Implementation of the high-level ContainerService
-/// This service wraps the underlying Quilt daemon for container management
+/// This service wraps the underlying Quilt daemon for container management.
+/// Containers are labeled with their owner and session; callers only see their own
+/// unless they are admins.
 pub struct ContainerServiceImpl {
     quilt_service: Arc<Mutex<QuiltService>>,
+    database: Arc<DatabaseManager>,
 }
 
 impl ContainerServiceImpl {
-    pub fn new(quilt_service: Arc<Mutex<QuiltService>>) -> Self {
-        Self { quilt_service }
+    pub fn new(quilt_service: Arc<Mutex<QuiltService>>, database: Arc<DatabaseManager>) -> Self {
+        Self { quilt_service, database }
     }
 
-    /// Convert Quilt ContainerInfo to Aria Container
+    /// Convert Quilt ContainerInfo to Aria Container, taking owner and session from its labels
     fn convert_quilt_container_to_aria(quilt_container: &quilt_proto::ContainerInfo) -> Container {
         let status = match quilt_container.status {
             1 => TaskStatus::Pending,   // PENDING
@@ -39,11 +45,17 @@ impl ContainerServiceImpl {
             _ => TaskStatus::Pending,   // Default
         };
 
+        let name = if quilt_container.name.is_empty() {
+            format!("container-{}", quilt_container.container_id.chars().take(8).collect::<String>())
+        } else {
+            quilt_container.name.clone()
+        };
+
         Container {
             id: quilt_container.container_id.clone(),
-            user_id: "system".to_string(), // TODO: Extract from context
-            session_id: None, // TODO: Link to session if available
-            name: format!("container-{}", &quilt_container.container_id[..8]),
+            user_id: tenancy::label(quilt_container, OWNER_LABEL).unwrap_or_default().to_string(),
+            session_id: tenancy::label(quilt_container, SESSION_LABEL).map(str::to_string),
+            name,
             image_path: quilt_container.image_path.clone(),
             status: status as i32,
             created_at: Some(prost_types::Timestamp {
@@ -67,9 +79,16 @@ impl ContainerService for ContainerServiceImpl {
         &self,
         request: Request<CreateContainerRequest>,
     ) -> Result<Response<Container>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
         
-        tracing::info!("Creating container with image: {}", req.image_path);
+        tracing::info!("Creating container with image {} for user {}", req.image_path, caller.user_id);
+        
+        // A container can only be attached to one of the caller's own sessions
+        let session_id = req.session_id.clone().filter(|id| !id.is_empty());
+        if let Some(session_id) = &session_id {
+            tenancy::authorize_resource(&self.database, &caller, TenantResource::Session, session_id).await?;
+        }
         
         // Convert environment variables
         let environment = Self::convert_env_vars(&req.environment);
@@ -83,62 +102,61 @@ impl ContainerService for ContainerServiceImpl {
             vec!["sh".to_string(), "-c".to_string(), "echo 'Container ready'".to_string()]
         };
         
+        // Charged to the caller so quilt applies their owner quota
+        let quilt_request = quilt_proto::CreateContainerRequest {
+            image_path: req.image_path.clone(),
+            command,
+            environment,
+            auto_start: false, // Agent must explicitly start
+            owner: caller.user_id.clone(),
+            name: req.name.clone(),
+            labels: tenancy::container_labels(&caller.user_id, session_id.as_deref()),
+            ..Default::default()
+        };
+        
         let mut quilt_service = self.quilt_service.lock().await;
         
-        match quilt_service.create_container(req.image_path.clone(), command, environment).await {
+        match quilt_service.create_container_with(quilt_request).await {
             Ok(container_id) => {
                 tracing::info!("Container created successfully: {}", container_id);
                 
                 // Get container details to return full Container object
-                match quilt_service.get_container_status(container_id.clone()).await {
+                let (status, created_at) = match quilt_service.get_container_status(container_id.clone()).await {
                     Ok(status) => {
-                        let container = Container {
-                            id: container_id,
-                            user_id: "system".to_string(),
-                            session_id: None,
-                            name: if req.name.is_empty() { 
-                                "unnamed".to_string() 
-                            } else { 
-                                req.name 
-                            },
-                            image_path: req.image_path,
-                            status: match status.state {
-                                crate::types::ContainerState::Created => TaskStatus::Pending as i32,
-                                crate::types::ContainerState::Running => TaskStatus::Running as i32,
-                                crate::types::ContainerState::Exited => TaskStatus::Completed as i32,
-                                crate::types::ContainerState::Failed => TaskStatus::Failed as i32,
-                            },
-                            created_at: Some(prost_types::Timestamp {
-                                seconds: status.created_at as i64,
-                                nanos: 0,
-                            }),
+                        let state = match status.state {
+                            crate::types::ContainerState::Created => TaskStatus::Pending,
+                            crate::types::ContainerState::Running => TaskStatus::Running,
+                            crate::types::ContainerState::Exited => TaskStatus::Completed,
+                            crate::types::ContainerState::Failed => TaskStatus::Failed,
                         };
-                        
-                        Ok(Response::new(container))
+                        (state, status.created_at as i64)
                     }
                     Err(e) => {
                         tracing::error!("Failed to get container status after creation: {}", e);
-                        // Return a basic container object
-                        let container = Container {
-                            id: container_id,
-                            user_id: "system".to_string(),
-                            session_id: None,
-                            name: if req.name.is_empty() { 
-                                "unnamed".to_string() 
-                            } else { 
-                                req.name 
-                            },
-                            image_path: req.image_path,
-                            status: TaskStatus::Pending as i32,
-                            created_at: Some(prost_types::Timestamp {
-                                seconds: chrono::Utc::now().timestamp(),
-                                nanos: 0,
-                            }),
-                        };
-                        
-                        Ok(Response::new(container))
+                        (TaskStatus::Pending, chrono::Utc::now().timestamp())
                     }
-                }
+                };
+                
+                Ok(Response::new(Container {
+                    id: container_id,
+                    user_id: caller.user_id,
+                    session_id,
+                    name: if req.name.is_empty() { 
+                        "unnamed".to_string() 
+                    } else { 
+                        req.name 
+                    },
+                    image_path: req.image_path,
+                    status: status as i32,
+                    created_at: Some(prost_types::Timestamp {
+                        seconds: created_at,
+                        nanos: 0,
+                    }),
+                }))
+            }
+            Err(e) if e.code == ErrorCode::QuotaExceeded => {
+                tracing::warn!("Container quota reached for user {}: {}", caller.user_id, e);
+                Err(Status::resource_exhausted(e.message))
             }
             Err(e) => {
                 tracing::error!("Failed to create container: {}", e);
@@ -151,11 +169,13 @@ impl ContainerService for ContainerServiceImpl {
         &self,
         request: Request<StartContainerRequest>,
     ) -> Result<Response<StartContainerResponse>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
         
-        tracing::info!("Starting container: {}", req.container_id);
+        tracing::info!("Starting container {} for user {}", req.container_id, caller.user_id);
         
         let mut quilt_service = self.quilt_service.lock().await;
+        tenancy::authorize_container(&mut quilt_service, &caller, &req.container_id).await?;
         
         match quilt_service.start_container(req.container_id).await {
             Ok(()) => {
@@ -173,11 +193,13 @@ impl ContainerService for ContainerServiceImpl {
         &self,
         request: Request<StopContainerRequest>,
     ) -> Result<Response<StopContainerResponse>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
         
-        tracing::info!("Stopping container: {}", req.container_id);
+        tracing::info!("Stopping container {} for user {}", req.container_id, caller.user_id);
         
         let mut quilt_service = self.quilt_service.lock().await;
+        tenancy::authorize_container(&mut quilt_service, &caller, &req.container_id).await?;
         
         match quilt_service.stop_container(req.container_id).await {
             Ok(()) => {
@@ -195,11 +217,13 @@ impl ContainerService for ContainerServiceImpl {
         &self,
         request: Request<RemoveContainerRequest>,
     ) -> Result<Response<RemoveContainerResponse>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
         
-        tracing::info!("Removing container: {}", req.container_id);
+        tracing::info!("Removing container {} for user {}", req.container_id, caller.user_id);
         
         let mut quilt_service = self.quilt_service.lock().await;
+        tenancy::authorize_container(&mut quilt_service, &caller, &req.container_id).await?;
         
         match quilt_service.remove_container(req.container_id).await {
             Ok(()) => {
@@ -217,69 +241,40 @@ impl ContainerService for ContainerServiceImpl {
         &self,
         request: Request<GetContainerRequest>,
     ) -> Result<Response<Container>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
         
-        tracing::info!("Getting container: {}", req.container_id);
+        tracing::info!("Getting container {} for user {}", req.container_id, caller.user_id);
         
         let mut quilt_service = self.quilt_service.lock().await;
+        let container = tenancy::authorize_container(&mut quilt_service, &caller, &req.container_id).await?;
         
-        match quilt_service.get_container_status(req.container_id.clone()).await {
-            Ok(status) => {
-                let container = Container {
-                    id: req.container_id,
-                    user_id: "system".to_string(),
-                    session_id: None,
-                    name: format!("container-{}", &status.id[..8]),
-                    image_path: "unknown".to_string(), // Not available in status response
-                    status: match status.state {
-                        crate::types::ContainerState::Created => TaskStatus::Pending as i32,
-                        crate::types::ContainerState::Running => TaskStatus::Running as i32,
-                        crate::types::ContainerState::Exited => TaskStatus::Completed as i32,
-                        crate::types::ContainerState::Failed => TaskStatus::Failed as i32,
-                    },
-                    created_at: Some(prost_types::Timestamp {
-                        seconds: status.created_at as i64,
-                        nanos: 0,
-                    }),
-                };
-                
-                Ok(Response::new(container))
-            }
-            Err(e) => {
-                tracing::error!("Failed to get container: {}", e);
-                Err(Status::not_found(format!("Container not found: {}", e)))
-            }
-        }
+        Ok(Response::new(Self::convert_quilt_container_to_aria(&container)))
     }
 
     async fn list_containers(
         &self,
         request: Request<ListContainersRequest>,
     ) -> Result<Response<ListContainersResponse>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
         
-        tracing::info!("Listing containers with session filter: {:?}", req.session_id);
+        tracing::info!("Listing containers for user {} with session filter: {:?}", caller.user_id, req.session_id);
         
         let mut quilt_service = self.quilt_service.lock().await;
         
         match quilt_service.list_containers().await {
             Ok(quilt_containers) => {
-                let mut containers = Vec::new();
+                let session_filter = req.session_id.filter(|id| !id.is_empty());
                 
-                for quilt_container in quilt_containers {
-                    let container = Self::convert_quilt_container_to_aria(&quilt_container);
-                    
-                    // Apply session filter if provided
-                    if let Some(ref session_id) = req.session_id {
-                        if !session_id.is_empty() {
-                            // For now, we don't have session linking, so we skip this filter
-                            // TODO: Implement session-container linking
-                            continue;
-                        }
-                    }
-                    
-                    containers.push(container);
-                }
+                let containers: Vec<Container> = tenancy::visible_containers(&caller, quilt_containers)
+                    .iter()
+                    .filter(|container| match &session_filter {
+                        Some(session_id) => tenancy::label(container, SESSION_LABEL) == Some(session_id.as_str()),
+                        None => true,
+                    })
+                    .map(Self::convert_quilt_container_to_aria)
+                    .collect();
                 
                 tracing::info!("Found {} containers", containers.len());
                 Ok(Response::new(ListContainersResponse { containers }))
@@ -295,9 +290,15 @@ impl ContainerService for ContainerServiceImpl {
         &self,
         request: Request<StreamContainerLogsRequest>,
     ) -> Result<Response<Self::StreamContainerLogsStream>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
         
-        tracing::info!("Streaming logs for container: {}, follow={}", req.container_id, req.follow);
+        tracing::info!("Streaming logs for container: {}, follow={}, user={}", req.container_id, req.follow, caller.user_id);
+        
+        {
+            let mut quilt_service = self.quilt_service.lock().await;
+            tenancy::authorize_container(&mut quilt_service, &caller, &req.container_id).await?;
+        }
         
         let (tx, rx) = tokio::sync::mpsc::channel(100);
         
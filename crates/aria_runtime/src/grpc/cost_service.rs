@@ -0,0 +1,189 @@
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+
+use super::aria::{
+    cost_service_server::CostService,
+    CostBudget, CostBudgetAction, CostGroupBy, CostReport, CostReportRow,
+    GetCostBudgetRequest, GetCostReportRequest, SetCostBudgetRequest,
+};
+
+use crate::clock::unix_now;
+use crate::costs::CostTracker;
+use crate::database::costs::{BudgetAction, BudgetRecord, CostDimension, CostOps};
+use crate::database::users::UserOps;
+use crate::database::DatabaseManager;
+use super::auth;
+use super::timestamp;
+use super::tenancy;
+
+/// Implementation of the CostService
+/// Spend is rolled up from each tenant's database; budgets live in the system database
+/// and are applied by the cost tracker when a run starts.
+pub struct CostServiceImpl {
+    database: Arc<DatabaseManager>,
+    costs: Arc<CostTracker>,
+}
+
+impl CostServiceImpl {
+    pub fn new(database: Arc<DatabaseManager>, costs: Arc<CostTracker>) -> Self {
+        Self { database, costs }
+    }
+
+    /// A tenant's budget together with what they have spent today
+    async fn cost_budget(&self, budget: BudgetRecord) -> Result<CostBudget, Status> {
+        let spent_today_usd = self.costs.spent_today(&budget.user_id).await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+
+        Ok(CostBudget {
+            action: match budget.action {
+                BudgetAction::Abort => CostBudgetAction::CostBudgetAbort,
+                BudgetAction::Downgrade => CostBudgetAction::CostBudgetDowngrade,
+            } as i32,
+            user_id: budget.user_id,
+            run_limit_usd: budget.run_limit_usd,
+            daily_limit_usd: budget.daily_limit_usd,
+            downgrade_model: budget.downgrade_model,
+            spent_today_usd,
+        })
+    }
+}
+
+fn dimension(group_by: i32) -> Option<CostDimension> {
+    match CostGroupBy::try_from(group_by) {
+        Ok(CostGroupBy::Session) => Some(CostDimension::Session),
+        Ok(CostGroupBy::Task) => Some(CostDimension::Task),
+        Ok(CostGroupBy::Agent) => Some(CostDimension::Agent),
+        Ok(CostGroupBy::Tool) => Some(CostDimension::Tool),
+        Ok(CostGroupBy::Model) => Some(CostDimension::Model),
+        _ => None,
+    }
+}
+
+#[tonic::async_trait]
+impl CostService for CostServiceImpl {
+    async fn get_cost_report(
+        &self,
+        request: Request<GetCostReportRequest>,
+    ) -> Result<Response<CostReport>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        let start = req.start_time.map(|t| t.seconds.max(0) as u64).unwrap_or(0);
+        let end = match req.end_time {
+            Some(t) => t.seconds.max(0) as u64,
+            None => unix_now(),
+        };
+        if end <= start {
+            return Err(Status::invalid_argument("The report's end must be after its start"));
+        }
+
+        let system_pool = self.database.get_system_database().await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        let user_ids = if req.all_users {
+            if !caller.is_admin() {
+                return Err(Status::permission_denied("Only admins may report on all tenants"));
+            }
+            UserOps::list_user_ids(&system_pool).await
+                .map_err(|e| Status::internal(e.to_string()))?
+        } else {
+            let user_id = req.user_id.filter(|id| !id.is_empty()).unwrap_or_else(|| caller.user_id.clone());
+            if !tenancy::can_access(&caller, Some(user_id.as_str())) {
+                return Err(Status::not_found(format!("Tenant not found: {}", user_id)));
+            }
+            vec![user_id]
+        };
+
+        let dimension = dimension(req.group_by);
+        let bucket_seconds = Some(req.bucket_seconds).filter(|&seconds| seconds > 0);
+        let mut report = CostReport::default();
+        for user_id in user_ids {
+            let pool = self.database.get_user_database(&user_id).await
+                .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+            let rollups = CostOps::rollup(&pool, start, end, dimension, bucket_seconds).await
+                .map_err(|e| Status::internal(e.to_string()))?;
+
+            for rollup in rollups {
+                report.total_calls += rollup.calls;
+                report.total_prompt_tokens += rollup.prompt_tokens;
+                report.total_completion_tokens += rollup.completion_tokens;
+                report.total_cost_usd += rollup.cost_usd;
+                report.rows.push(CostReportRow {
+                    user_id: user_id.clone(),
+                    key: rollup.key,
+                    bucket_start: Some(timestamp(rollup.bucket_start)),
+                    calls: rollup.calls,
+                    prompt_tokens: rollup.prompt_tokens,
+                    completion_tokens: rollup.completion_tokens,
+                    cost_usd: rollup.cost_usd,
+                });
+            }
+        }
+
+        Ok(Response::new(report))
+    }
+
+    async fn get_cost_budget(
+        &self,
+        request: Request<GetCostBudgetRequest>,
+    ) -> Result<Response<CostBudget>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        let user_id = req.user_id.filter(|id| !id.is_empty()).unwrap_or_else(|| caller.user_id.clone());
+        if !tenancy::can_access(&caller, Some(user_id.as_str())) {
+            return Err(Status::not_found(format!("Tenant not found: {}", user_id)));
+        }
+
+        let pool = self.database.get_system_database().await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        let budget = CostOps::get_budget(&pool, &user_id).await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .unwrap_or_else(|| BudgetRecord { user_id, ..Default::default() });
+
+        Ok(Response::new(self.cost_budget(budget).await?))
+    }
+
+    async fn set_cost_budget(
+        &self,
+        request: Request<SetCostBudgetRequest>,
+    ) -> Result<Response<CostBudget>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        if !caller.is_admin() {
+            return Err(Status::permission_denied("Only admins may set cost budgets"));
+        }
+        if [req.run_limit_usd, req.daily_limit_usd].iter().flatten().any(|&limit| limit.is_nan() || limit < 0.0) {
+            return Err(Status::invalid_argument("Budget limits must be unset (unlimited) or not negative"));
+        }
+        let action = match CostBudgetAction::try_from(req.action) {
+            Ok(CostBudgetAction::CostBudgetDowngrade) => BudgetAction::Downgrade,
+            _ => BudgetAction::Abort,
+        };
+        let downgrade_model = req.downgrade_model.filter(|model| !model.is_empty());
+        if action == BudgetAction::Downgrade && downgrade_model.is_none() {
+            return Err(Status::invalid_argument("Downgrading budgets need a downgrade model"));
+        }
+
+        let pool = self.database.get_system_database().await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        if UserOps::get_user(&pool, &req.user_id).await.is_err() {
+            return Err(Status::not_found(format!("Tenant not found: {}", req.user_id)));
+        }
+
+        tracing::info!("User {} setting cost budget for {}: run={:?}, daily={:?}, action={}",
+                      caller.user_id, req.user_id, req.run_limit_usd, req.daily_limit_usd, action.as_str());
+
+        let budget = CostOps::set_budget(&pool, &BudgetRecord {
+            user_id: req.user_id,
+            run_limit_usd: req.run_limit_usd,
+            daily_limit_usd: req.daily_limit_usd,
+            action,
+            downgrade_model,
+            updated_at: 0,
+        }).await
+        .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(self.cost_budget(budget).await?))
+    }
+}
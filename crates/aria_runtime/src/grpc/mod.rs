@@ -3,12 +3,33 @@ pub mod session_service;
 pub mod container_service;
 pub mod notification_service;
 pub mod bundle_service;
+pub mod approval_service;
+pub mod tenant_service;
+pub mod cost_service;
+pub mod schedule_service;
+pub mod trigger_service;
+pub mod auth;
+pub mod tenancy;
 
 pub use task_service::TaskServiceImpl;
 pub use session_service::SessionServiceImpl;
 pub use container_service::ContainerServiceImpl;
 pub use notification_service::NotificationServiceImpl;
 pub use bundle_service::BundleServiceImpl;
+pub use approval_service::ApprovalServiceImpl;
+pub use tenant_service::TenantServiceImpl;
+pub use cost_service::CostServiceImpl;
+pub use schedule_service::ScheduleServiceImpl;
+pub use trigger_service::TriggerServiceImpl;
+pub use auth::{AuthConfig, CallerAuthenticator, CallerIdentity, UserRole};
+
+/// API timestamp for a time stored as UNIX seconds
+pub(crate) fn timestamp(seconds: u64) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: seconds as i64,
+        nanos: 0,
+    }
+}
 
 // Re-export the generated protobuf types
 pub mod aria {
@@ -1,5 +1,8 @@
+use prost::Message;
+use std::collections::HashSet;
 use std::pin::Pin;
 use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::Mutex;
 use tokio_stream::{wrappers::ReceiverStream, Stream};
 use tonic::{Request, Response, Status};
@@ -7,139 +10,293 @@ use uuid::Uuid;
 
 use super::aria::{
     notification_service_server::NotificationService,
+    notification::EventPayload,
     Notification, StreamNotificationsRequest,
     BundleUploadEvent, TaskStatusEvent, TaskStatus,
+    WebhookSubscription, CreateWebhookSubscriptionRequest, ListWebhookSubscriptionsRequest,
+    ListWebhookSubscriptionsResponse, DeleteWebhookSubscriptionRequest, DeleteWebhookSubscriptionResponse,
+    DeadLetter, ListDeadLettersRequest, ListDeadLettersResponse, RedeliverWebhookRequest, RedeliverWebhookResponse,
 };
+use super::approval_service::approval_event;
+use super::auth::{self, CallerIdentity};
+use super::task_service::convert_status;
+use super::tenancy;
+use super::timestamp;
 
+use crate::database::async_tasks::AsyncTaskRecord;
+use crate::database::notifications::{DeadLetterRecord, NotificationEntry, NotificationOps, SubscriptionRecord};
 use crate::database::DatabaseManager;
-use crate::errors::{AriaError, AriaResult};
+use crate::engines::tool_registry::{ApprovalNotifier, ApprovalRequest};
+use crate::errors::AriaResult;
+use crate::task_runner::TaskNotifier;
+use crate::triggers;
+use crate::webhooks::WebhookDispatcher;
+
+/// Event types webhook subscriptions can ask for
+pub const NOTIFICATION_EVENT_TYPES: &[&str] = &["task_status", "bundle_upload", "tool_approval"];
+
+/// Notifications read from the log at a time when replaying
+const REPLAY_PAGE_SIZE: u32 = 500;
 
 /// Implementation of the high-level NotificationService
+#[derive(Clone)]
 pub struct NotificationServiceImpl {
     database: Arc<DatabaseManager>,
+    webhooks: Arc<WebhookDispatcher>,
     // Channel for broadcasting notifications to subscribers
     notification_broadcaster: Arc<Mutex<tokio::sync::broadcast::Sender<Notification>>>,
 }
 
 impl NotificationServiceImpl {
-    pub fn new(database: Arc<DatabaseManager>) -> Self {
+    pub fn new(database: Arc<DatabaseManager>, webhooks: Arc<WebhookDispatcher>) -> Self {
         let (tx, _) = tokio::sync::broadcast::channel(1000);
-        Self { 
+        Self {
             database,
+            webhooks,
             notification_broadcaster: Arc::new(Mutex::new(tx)),
         }
     }
 
-    /// Store notification in database (simplified for event-based notifications)
-    async fn store_notification(&self, notification: &Notification) -> AriaResult<()> {
-        let query = r#"
-            INSERT INTO notifications (id, timestamp, event_type, event_data)
-            VALUES (?, ?, ?, ?)
-        "#;
-        
-        let timestamp = notification.timestamp.as_ref()
-            .map(|ts| ts.seconds)
-            .unwrap_or_else(|| chrono::Utc::now().timestamp());
-        
-        let (event_type, event_data) = match &notification.event_payload {
-            Some(super::aria::notification::EventPayload::BundleUpload(event)) => {
-                ("bundle_upload", format!("bundle_name: {}, progress: {}, success: {}", 
-                    event.bundle_name, event.progress_percent, event.success))
-            }
-            Some(super::aria::notification::EventPayload::TaskStatus(event)) => {
-                ("task_status", format!("task_id: {}, status: {}, message: {}", 
-                    event.task_id, event.new_status, event.status_message))
-            }
-            None => ("unknown", "{}".to_string()),
+    /// Append a notification to the log, giving it its cursor, and queue its webhook deliveries
+    async fn store_notification(&self, notification: &mut Notification) -> AriaResult<()> {
+        let owner = notification_owner(notification).map(str::to_string);
+        let event_type = event_type(notification);
+        let entry = NotificationEntry {
+            seq: 0,
+            id: notification.id.clone(),
+            user_id: owner.clone(),
+            event_type: event_type.to_string(),
+            payload: notification.encode_to_vec(),
+            body: notification_body(notification).to_string(),
+            created_at: notification.timestamp.as_ref().map(|ts| ts.seconds.max(0) as u64).unwrap_or_default(),
         };
-        
+
         let pool = self.database.pool().await?;
-        sqlx::query(query)
-            .bind(&notification.id)
-            .bind(timestamp)
-            .bind(event_type)
-            .bind(event_data)
-            .execute(&pool)
-            .await
-            .map_err(|e| AriaError::database_error(&format!("Failed to store notification: {}", e)))?;
-        
+        notification.cursor = NotificationOps::append_notification(&pool, &entry).await?;
+
+        if let Err(e) = self.webhooks.enqueue(&notification.id, owner.as_deref(), event_type).await {
+            tracing::warn!("Failed to queue webhook deliveries of notification {}: {}", notification.id, e);
+        }
         Ok(())
     }
 
-    /// Broadcast a notification to all subscribers
-    async fn broadcast_notification(&self, notification: &Notification) {
+    /// Store a new notification and send it to connected clients
+    async fn publish(&self, event_payload: EventPayload) -> AriaResult<()> {
+        let mut notification = Notification {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Some(prost_types::Timestamp {
+                seconds: chrono::Utc::now().timestamp(),
+                nanos: 0,
+            }),
+            event_payload: Some(event_payload),
+            cursor: 0,
+        };
+
+        // Held while storing, so subscribers receive notifications in cursor order
         let broadcaster = self.notification_broadcaster.lock().await;
-        if let Err(e) = broadcaster.send(notification.clone()) {
+
+        // Store in database
+        self.store_notification(&mut notification).await?;
+
+        // Broadcast to subscribers
+        if let Err(e) = broadcaster.send(notification) {
             tracing::warn!("Failed to broadcast notification: {}", e);
         }
+
+        Ok(())
     }
 
-    /// Create a bundle upload notification
+    /// Create a bundle upload notification for the uploading user
     pub async fn notify_bundle_upload(
         &self,
+        user_id: String,
         bundle_name: String,
         progress_percent: f64,
         status_message: String,
         success: bool,
         error_message: Option<String>,
     ) -> AriaResult<()> {
-        let event = BundleUploadEvent {
+        self.publish(EventPayload::BundleUpload(BundleUploadEvent {
             bundle_name,
             progress_percent,
             status_message,
             success,
             error_message,
-        };
-
-        let notification = Notification {
-            id: Uuid::new_v4().to_string(),
-            timestamp: Some(prost_types::Timestamp {
-                seconds: chrono::Utc::now().timestamp(),
-                nanos: 0,
-            }),
-            event_payload: Some(super::aria::notification::EventPayload::BundleUpload(event)),
-        };
-
-        // Store in database
-        self.store_notification(&notification).await?;
-        
-        // Broadcast to subscribers
-        self.broadcast_notification(&notification).await;
-        
-        Ok(())
+            user_id: Some(user_id),
+        })).await
     }
 
-    /// Create a task status notification
+    /// Create a task status notification for the task's owner
     pub async fn notify_task_status(
         &self,
+        user_id: String,
         task_id: String,
         new_status: TaskStatus,
         status_message: String,
         exit_code: Option<i32>,
     ) -> AriaResult<()> {
-        let event = TaskStatusEvent {
+        self.publish(EventPayload::TaskStatus(TaskStatusEvent {
             task_id,
             new_status: new_status as i32,
             status_message,
             exit_code,
-        };
+            user_id: Some(user_id),
+        })).await
+    }
 
-        let notification = Notification {
-            id: Uuid::new_v4().to_string(),
-            timestamp: Some(prost_types::Timestamp {
-                seconds: chrono::Utc::now().timestamp(),
-                nanos: 0,
-            }),
-            event_payload: Some(super::aria::notification::EventPayload::TaskStatus(event)),
-        };
+    /// Create a notification asking a human to approve a held tool call
+    pub async fn notify_tool_approval(&self, request: &ApprovalRequest) -> AriaResult<()> {
+        self.publish(EventPayload::ToolApproval(approval_event(request))).await
+    }
 
-        // Store in database
-        self.store_notification(&notification).await?;
-        
-        // Broadcast to subscribers
-        self.broadcast_notification(&notification).await;
-        
-        Ok(())
+    /// Load a webhook subscription the caller may see
+    async fn authorize_subscription(&self, caller: &CallerIdentity, subscription_id: &str) -> Result<SubscriptionRecord, Status> {
+        let pool = self.database.pool().await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        NotificationOps::get_subscription(&pool, subscription_id).await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .filter(|subscription| tenancy::can_access(caller, Some(subscription.user_id.as_str())))
+            .ok_or_else(|| Status::not_found(format!("Webhook subscription not found: {}", subscription_id)))
+    }
+}
+
+/// The user a notification concerns
+fn notification_owner(notification: &Notification) -> Option<&str> {
+    match &notification.event_payload {
+        Some(EventPayload::BundleUpload(event)) => event.user_id.as_deref(),
+        Some(EventPayload::TaskStatus(event)) => event.user_id.as_deref(),
+        Some(EventPayload::ToolApproval(event)) => event.user_id.as_deref(),
+        None => None,
+    }
+}
+
+fn event_type(notification: &Notification) -> &'static str {
+    match &notification.event_payload {
+        Some(EventPayload::BundleUpload(_)) => "bundle_upload",
+        Some(EventPayload::TaskStatus(_)) => "task_status",
+        Some(EventPayload::ToolApproval(_)) => "tool_approval",
+        None => "unknown",
+    }
+}
+
+/// JSON document a notification is posted to webhook subscriptions as
+fn notification_body(notification: &Notification) -> serde_json::Value {
+    let data = match &notification.event_payload {
+        Some(EventPayload::BundleUpload(event)) => serde_json::json!({
+            "bundle_name": event.bundle_name,
+            "progress_percent": event.progress_percent,
+            "status_message": event.status_message,
+            "success": event.success,
+            "error_message": event.error_message,
+        }),
+        Some(EventPayload::TaskStatus(event)) => serde_json::json!({
+            "task_id": event.task_id,
+            "status": TaskStatus::try_from(event.new_status).unwrap_or(TaskStatus::Unspecified).as_str_name().to_lowercase(),
+            "status_message": event.status_message,
+            "exit_code": event.exit_code,
+        }),
+        Some(EventPayload::ToolApproval(event)) => serde_json::json!({
+            "approval_id": event.approval_id,
+            "tool_name": event.tool_name,
+            "security_level": event.security_level,
+            "parameters": serde_json::from_str::<serde_json::Value>(&event.parameters_json).unwrap_or_default(),
+            "agent_name": event.agent_name,
+            "session_id": event.session_id,
+            "reason": event.reason,
+            "expires_at": event.expires_at.as_ref().map(|ts| ts.seconds),
+        }),
+        None => serde_json::Value::Null,
+    };
+
+    serde_json::json!({
+        "id": notification.id,
+        "event_type": event_type(notification),
+        "timestamp": notification.timestamp.as_ref().map(|ts| ts.seconds),
+        "user_id": notification_owner(notification),
+        "data": data,
+    })
+}
+
+/// Send the logged notifications after cursor `after` that `user_id` may see (all if `None`).
+/// Returns the cursor of the last one sent, or `None` if the client went away.
+async fn replay(
+    database: &DatabaseManager,
+    mut after: u64,
+    user_id: Option<&str>,
+    tx: &tokio::sync::mpsc::Sender<Result<Notification, Status>>,
+) -> AriaResult<Option<u64>> {
+    let pool = database.pool().await?;
+    loop {
+        let page = NotificationOps::notifications_since(&pool, after, user_id, REPLAY_PAGE_SIZE).await?;
+        for entry in &page {
+            after = entry.seq;
+            let Ok(mut notification) = Notification::decode(entry.payload.as_slice()) else {
+                tracing::warn!("Skipping undecodable notification {}", entry.id);
+                continue;
+            };
+            notification.cursor = entry.seq;
+            if tx.send(Ok(notification)).await.is_err() {
+                return Ok(None);
+            }
+        }
+        if (page.len() as u32) < REPLAY_PAGE_SIZE {
+            return Ok(Some(after));
+        }
+    }
+}
+
+fn convert_subscription(subscription: SubscriptionRecord, reveal_secret: bool) -> WebhookSubscription {
+    WebhookSubscription {
+        id: subscription.subscription_id,
+        user_id: subscription.user_id,
+        url: subscription.url,
+        event_types: subscription.event_types,
+        secret: Some(subscription.secret).filter(|_| reveal_secret),
+        created_at: Some(timestamp(subscription.created_at)),
+    }
+}
+
+fn convert_dead_letter(dead_letter: DeadLetterRecord) -> DeadLetter {
+    DeadLetter {
+        delivery_id: dead_letter.delivery_id,
+        subscription_id: dead_letter.subscription_id,
+        notification_id: dead_letter.notification_id,
+        event_type: dead_letter.event_type,
+        attempts: dead_letter.attempts,
+        last_error: dead_letter.last_error.unwrap_or_default(),
+        last_status_code: dead_letter.last_status_code.map(u32::from),
+        failed_at: Some(timestamp(dead_letter.failed_at)),
+    }
+}
+
+/// The user a request names, defaulting to the caller; only admins may name someone else
+fn target_user(caller: &CallerIdentity, user_id: Option<String>) -> Result<String, Status> {
+    let user_id = user_id.filter(|id| !id.is_empty()).unwrap_or_else(|| caller.user_id.clone());
+    if !tenancy::can_access(caller, Some(user_id.as_str())) {
+        return Err(Status::not_found(format!("Tenant not found: {}", user_id)));
+    }
+    Ok(user_id)
+}
+
+#[async_trait::async_trait]
+impl ApprovalNotifier for NotificationServiceImpl {
+    async fn approval_requested(&self, request: &ApprovalRequest) -> AriaResult<()> {
+        self.notify_tool_approval(request).await
+    }
+}
+
+#[async_trait::async_trait]
+impl TaskNotifier for NotificationServiceImpl {
+    async fn task_finished(&self, task: &AsyncTaskRecord) -> AriaResult<()> {
+        let status_message = task.error_message.clone()
+            .unwrap_or_else(|| format!("Task {}", task.status));
+        self.notify_task_status(
+            task.user_id.clone(),
+            task.task_id.clone(),
+            convert_status(&task.status),
+            status_message,
+            task.exit_code,
+        ).await
     }
 }
 
@@ -149,28 +306,225 @@ impl NotificationService for NotificationServiceImpl {
         &self,
         request: Request<StreamNotificationsRequest>,
     ) -> Result<Response<Self::StreamNotificationsStream>, Status> {
-        let _req = request.into_inner();
-        
-        tracing::info!("Starting notification stream");
-        
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        tracing::info!("Starting notification stream for user {}", caller.user_id);
+
+        // Subscribed before replaying, so nothing logged in between is missed
         let broadcaster = self.notification_broadcaster.lock().await;
         let mut receiver = broadcaster.subscribe();
         drop(broadcaster); // Release the lock
-        
+
         let (tx, rx) = tokio::sync::mpsc::channel(100);
-        
+        let database = Arc::clone(&self.database);
+
         // Spawn task to forward notifications
         tokio::spawn(async move {
-            while let Ok(notification) = receiver.recv().await {
+            let visible_user = (!caller.is_admin()).then(|| caller.user_id.clone());
+            let mut last_cursor = req.since_cursor;
+            if let Some(since) = last_cursor {
+                match replay(&database, since, visible_user.as_deref(), &tx).await {
+                    Ok(Some(cursor)) => last_cursor = Some(cursor),
+                    Ok(None) => return, // Client disconnected
+                    Err(e) => {
+                        let _ = tx.send(Err(Status::internal(format!("Failed to replay notifications: {}", e)))).await;
+                        return;
+                    }
+                }
+            }
+
+            loop {
+                let notification = match receiver.recv().await {
+                    Ok(notification) => notification,
+                    // Whatever was dropped is still in the log
+                    Err(RecvError::Lagged(skipped)) => {
+                        let Some(since) = last_cursor else {
+                            tracing::warn!("Notification stream for user {} skipped {} notifications", caller.user_id, skipped);
+                            continue;
+                        };
+                        match replay(&database, since, visible_user.as_deref(), &tx).await {
+                            Ok(Some(cursor)) => last_cursor = Some(cursor),
+                            Ok(None) => break,
+                            Err(e) => tracing::warn!("Failed to catch up notification stream: {}", e),
+                        }
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                // Already sent while replaying
+                if last_cursor.is_some_and(|cursor| notification.cursor <= cursor) {
+                    continue;
+                }
+                // Events are only shown to the user they concern, and to admins
+                if !tenancy::can_access(&caller, notification_owner(&notification)) {
+                    continue;
+                }
+                last_cursor = Some(notification.cursor);
                 if tx.send(Ok(notification)).await.is_err() {
                     break; // Client disconnected
                 }
             }
         });
-        
+
         let stream = ReceiverStream::new(rx);
         Ok(Response::new(Box::pin(stream) as Self::StreamNotificationsStream))
     }
 
     type StreamNotificationsStream = Pin<Box<dyn Stream<Item = Result<Notification, Status>> + Send>>;
-} 
\ No newline at end of file
+
+    async fn create_webhook_subscription(
+        &self,
+        request: Request<CreateWebhookSubscriptionRequest>,
+    ) -> Result<Response<WebhookSubscription>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+        let user_id = target_user(&caller, req.user_id)?;
+
+        let url = reqwest::Url::parse(&req.url)
+            .map_err(|e| Status::invalid_argument(format!("Invalid URL '{}': {}", req.url, e)))?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(Status::invalid_argument("Webhook URLs must be http or https"));
+        }
+        self.webhooks.check_destination(&url).await
+            .map_err(|e| Status::invalid_argument(e.message))?;
+
+        let mut seen = HashSet::new();
+        let mut event_types = Vec::new();
+        for event_type in req.event_types {
+            if !NOTIFICATION_EVENT_TYPES.contains(&event_type.as_str()) {
+                return Err(Status::invalid_argument(format!(
+                    "Unknown event type '{}'; expected one of {}", event_type, NOTIFICATION_EVENT_TYPES.join(", ")
+                )));
+            }
+            if seen.insert(event_type.clone()) {
+                event_types.push(event_type);
+            }
+        }
+
+        let subscription = SubscriptionRecord {
+            subscription_id: Uuid::new_v4().to_string(),
+            user_id,
+            url: url.to_string(),
+            secret: triggers::new_secret(),
+            event_types,
+            created_at: chrono::Utc::now().timestamp().max(0) as u64,
+        };
+        let pool = self.database.pool().await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        NotificationOps::create_subscription(&pool, &subscription).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        tracing::info!("User {} subscribed {} to notifications of user {}",
+                       caller.user_id, subscription.url, subscription.user_id);
+
+        Ok(Response::new(convert_subscription(subscription, true)))
+    }
+
+    async fn list_webhook_subscriptions(
+        &self,
+        request: Request<ListWebhookSubscriptionsRequest>,
+    ) -> Result<Response<ListWebhookSubscriptionsResponse>, Status> {
+        let caller = auth::caller(&request)?;
+        let user_id = target_user(&caller, request.into_inner().user_id)?;
+
+        let pool = self.database.pool().await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        let subscriptions = NotificationOps::list_subscriptions(&pool, &user_id).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(ListWebhookSubscriptionsResponse {
+            subscriptions: subscriptions.into_iter()
+                .map(|subscription| convert_subscription(subscription, false))
+                .collect(),
+        }))
+    }
+
+    async fn delete_webhook_subscription(
+        &self,
+        request: Request<DeleteWebhookSubscriptionRequest>,
+    ) -> Result<Response<DeleteWebhookSubscriptionResponse>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        let subscription = self.authorize_subscription(&caller, &req.subscription_id).await?;
+        let pool = self.database.pool().await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        let deleted = NotificationOps::delete_subscription(&pool, &subscription.subscription_id).await
+            .map_err(|e| Status::internal(format!("Failed to delete webhook subscription: {}", e)))?;
+        tracing::info!("User {} deleted webhook subscription {}", caller.user_id, req.subscription_id);
+
+        Ok(Response::new(DeleteWebhookSubscriptionResponse { deleted }))
+    }
+
+    async fn list_dead_letters(
+        &self,
+        request: Request<ListDeadLettersRequest>,
+    ) -> Result<Response<ListDeadLettersResponse>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+        let user_id = target_user(&caller, req.user_id)?;
+        let subscription_id = req.subscription_id.filter(|id| !id.is_empty());
+
+        let pool = self.database.pool().await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        let dead_letters = NotificationOps::list_dead_letters(&pool, &user_id, subscription_id.as_deref()).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(ListDeadLettersResponse {
+            dead_letters: dead_letters.into_iter().map(convert_dead_letter).collect(),
+        }))
+    }
+
+    async fn redeliver_webhook(
+        &self,
+        request: Request<RedeliverWebhookRequest>,
+    ) -> Result<Response<RedeliverWebhookResponse>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+        let not_found = || Status::not_found(format!("Dead letter not found: {}", req.delivery_id));
+
+        let pool = self.database.pool().await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        NotificationOps::get_dead_letter(&pool, &req.delivery_id).await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .filter(|dead_letter| tenancy::can_access(&caller, Some(dead_letter.user_id.as_str())))
+            .ok_or_else(not_found)?;
+
+        let delivery_id = self.webhooks.redeliver(&req.delivery_id).await
+            .map_err(|e| Status::internal(format!("Failed to redeliver: {}", e)))?
+            .ok_or_else(not_found)?;
+        tracing::info!("User {} redelivered dead letter {}", caller.user_id, req.delivery_id);
+
+        Ok(Response::new(RedeliverWebhookResponse { delivery_id }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_body_names_the_event_and_its_owner() {
+        let notification = Notification {
+            id: "n1".to_string(),
+            timestamp: Some(timestamp(1_700_000_000)),
+            event_payload: Some(EventPayload::TaskStatus(TaskStatusEvent {
+                task_id: "t1".to_string(),
+                new_status: TaskStatus::Failed as i32,
+                status_message: "exit 2".to_string(),
+                exit_code: Some(2),
+                user_id: Some("alice".to_string()),
+            })),
+            cursor: 7,
+        };
+
+        let body = notification_body(&notification);
+        assert_eq!(body["event_type"], "task_status");
+        assert_eq!(body["user_id"], "alice");
+        assert_eq!(body["timestamp"], 1_700_000_000);
+        assert_eq!(body["data"]["task_id"], "t1");
+        assert_eq!(body["data"]["status"], "failed");
+        assert_eq!(body["data"]["exit_code"], 2);
+    }
+}
@@ -0,0 +1,243 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tonic::{Request, Response, Status};
+
+use super::aria::{
+    schedule_service_server::ScheduleService,
+    schedule_spec::Cadence,
+    CatchUpPolicy as ApiCatchUpPolicy, OverlapPolicy as ApiOverlapPolicy,
+    Schedule, ScheduleSpec, CreateScheduleRequest, GetScheduleRequest, ListSchedulesRequest,
+    ListSchedulesResponse, UpdateScheduleRequest, DeleteScheduleRequest, DeleteScheduleResponse,
+};
+
+use crate::database::schedules::{CatchUpPolicy, OverlapPolicy, ScheduleOps, ScheduleRecord};
+use crate::database::tenancy::TenantResource;
+use crate::database::DatabaseManager;
+use crate::engines::container::quilt::QuiltService;
+use crate::errors::{AriaError, ErrorCode};
+use crate::scheduler::{self, Scheduler};
+use super::auth::{self, CallerIdentity};
+use super::task_service::{launch_request, launch_target, task_owner};
+use super::tenancy;
+use super::timestamp;
+
+/// Implementation of the ScheduleService
+/// Schedules are stored in their owner's database and fired by the scheduler; the
+/// task a schedule launches is checked like a launch request when the schedule is saved.
+pub struct ScheduleServiceImpl {
+    quilt_service: Arc<Mutex<QuiltService>>,
+    database: Arc<DatabaseManager>,
+    scheduler: Arc<Scheduler>,
+}
+
+impl ScheduleServiceImpl {
+    pub fn new(quilt_service: Arc<Mutex<QuiltService>>, database: Arc<DatabaseManager>, scheduler: Arc<Scheduler>) -> Self {
+        Self {
+            quilt_service,
+            database,
+            scheduler,
+        }
+    }
+
+    /// Load a schedule the caller may see
+    async fn authorize_schedule(&self, caller: &CallerIdentity, schedule_id: &str) -> Result<ScheduleRecord, Status> {
+        let owner = tenancy::authorize_resource(&self.database, caller, TenantResource::Schedule, schedule_id).await?
+            .ok_or_else(|| Status::not_found(format!("Schedule not found: {}", schedule_id)))?;
+        let pool = self.database.get_user_database(&owner.user_id).await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        ScheduleOps::get_schedule(&pool, schedule_id).await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .ok_or_else(|| Status::not_found(format!("Schedule not found: {}", schedule_id)))
+    }
+
+    /// Apply a spec to `schedule`, taking its owner from the task it launches
+    async fn apply_spec(
+        &self,
+        caller: &CallerIdentity,
+        spec: ScheduleSpec,
+        mut schedule: ScheduleRecord,
+    ) -> Result<ScheduleRecord, Status> {
+        let task = spec.task.ok_or_else(|| Status::invalid_argument("Schedules need a task to launch"))?;
+        if task.parent_task_id.as_deref().is_some_and(|id| !id.is_empty()) || !task.depends_on.is_empty() {
+            return Err(Status::invalid_argument("Scheduled tasks cannot have a parent or dependencies"));
+        }
+        if task.timeout_seconds < 0 {
+            return Err(Status::invalid_argument("Timeout must be zero (none) or positive"));
+        }
+
+        let (task_type, command, container_id) = launch_target(&task)?;
+        let owner = task_owner(&self.database, &self.quilt_service, caller, &task, container_id.as_deref()).await?;
+        if !schedule.user_id.is_empty() && schedule.user_id != owner {
+            return Err(Status::invalid_argument("A schedule's task must belong to the schedule's owner"));
+        }
+
+        let (cron_expression, interval_seconds) = match spec.cadence {
+            Some(Cadence::CronExpression(expression)) => (Some(expression), None),
+            Some(Cadence::IntervalSeconds(seconds)) => (None, Some(seconds)),
+            None => (None, None),
+        };
+
+        schedule.user_id = owner;
+        schedule.name = spec.name;
+        schedule.cron_expression = cron_expression;
+        schedule.interval_seconds = interval_seconds;
+        schedule.timezone = if spec.timezone.is_empty() { "UTC".to_string() } else { spec.timezone };
+        schedule.jitter_seconds = spec.jitter_seconds;
+        schedule.overlap_policy = overlap_policy(spec.overlap_policy);
+        schedule.catch_up_policy = catch_up_policy(spec.catch_up_policy);
+        schedule.task_type = task_type;
+        schedule.command = command;
+        schedule.environment = task.environment;
+        schedule.container_id = container_id;
+        schedule.session_id = Some(task.session_id).filter(|id| !id.is_empty());
+        schedule.priority = task.priority;
+        schedule.timeout_seconds = task.timeout_seconds as u64;
+        schedule.enabled = spec.enabled.unwrap_or(true);
+        Ok(schedule)
+    }
+}
+
+fn overlap_policy(value: i32) -> OverlapPolicy {
+    match ApiOverlapPolicy::try_from(value) {
+        Ok(ApiOverlapPolicy::OverlapQueue) => OverlapPolicy::Queue,
+        Ok(ApiOverlapPolicy::OverlapCancelPrevious) => OverlapPolicy::CancelPrevious,
+        _ => OverlapPolicy::Skip,
+    }
+}
+
+fn catch_up_policy(value: i32) -> CatchUpPolicy {
+    match ApiCatchUpPolicy::try_from(value) {
+        Ok(ApiCatchUpPolicy::CatchUpSkip) => CatchUpPolicy::Skip,
+        Ok(ApiCatchUpPolicy::CatchUpRunAll) => CatchUpPolicy::RunAll,
+        _ => CatchUpPolicy::RunOnce,
+    }
+}
+
+/// Convert a stored schedule to its API form
+fn convert_schedule(record: ScheduleRecord) -> Schedule {
+    let task = launch_request(&scheduler::template(&record));
+    let cadence = match (record.cron_expression, record.interval_seconds) {
+        (Some(expression), _) => Some(Cadence::CronExpression(expression)),
+        (None, Some(seconds)) => Some(Cadence::IntervalSeconds(seconds)),
+        (None, None) => None,
+    };
+    let overlap_policy = match record.overlap_policy {
+        OverlapPolicy::Skip => ApiOverlapPolicy::OverlapSkip,
+        OverlapPolicy::Queue => ApiOverlapPolicy::OverlapQueue,
+        OverlapPolicy::CancelPrevious => ApiOverlapPolicy::OverlapCancelPrevious,
+    };
+    let catch_up_policy = match record.catch_up_policy {
+        CatchUpPolicy::Skip => ApiCatchUpPolicy::CatchUpSkip,
+        CatchUpPolicy::RunOnce => ApiCatchUpPolicy::CatchUpRunOnce,
+        CatchUpPolicy::RunAll => ApiCatchUpPolicy::CatchUpRunAll,
+    };
+
+    Schedule {
+        id: record.schedule_id,
+        user_id: record.user_id,
+        spec: Some(ScheduleSpec {
+            name: record.name,
+            cadence,
+            timezone: record.timezone,
+            jitter_seconds: record.jitter_seconds,
+            overlap_policy: overlap_policy as i32,
+            catch_up_policy: catch_up_policy as i32,
+            task: Some(task),
+            enabled: Some(record.enabled),
+        }),
+        next_run_at: record.next_run_at.map(timestamp),
+        last_run_at: record.last_run_at.map(timestamp),
+        last_task_id: record.last_task_id,
+        created_at: Some(timestamp(record.created_at)),
+        updated_at: Some(timestamp(record.updated_at)),
+    }
+}
+
+fn schedule_error(e: AriaError) -> Status {
+    match e.code {
+        ErrorCode::ScheduleValidationError | ErrorCode::TaskValidationError => Status::invalid_argument(e.message),
+        _ => Status::internal(e.to_string()),
+    }
+}
+
+#[tonic::async_trait]
+impl ScheduleService for ScheduleServiceImpl {
+    async fn create_schedule(
+        &self,
+        request: Request<CreateScheduleRequest>,
+    ) -> Result<Response<Schedule>, Status> {
+        let caller = auth::caller(&request)?;
+        let spec = request.into_inner().spec
+            .ok_or_else(|| Status::invalid_argument("Schedule spec required"))?;
+
+        let schedule = self.apply_spec(&caller, spec, ScheduleRecord::default()).await?;
+        let schedule = self.scheduler.save(schedule).await.map_err(schedule_error)?;
+        tracing::info!("User {} created schedule {} for user {}", caller.user_id, schedule.schedule_id, schedule.user_id);
+
+        Ok(Response::new(convert_schedule(schedule)))
+    }
+
+    async fn get_schedule(
+        &self,
+        request: Request<GetScheduleRequest>,
+    ) -> Result<Response<Schedule>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        let schedule = self.authorize_schedule(&caller, &req.schedule_id).await?;
+        Ok(Response::new(convert_schedule(schedule)))
+    }
+
+    async fn list_schedules(
+        &self,
+        request: Request<ListSchedulesRequest>,
+    ) -> Result<Response<ListSchedulesResponse>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        let user_id = req.user_id.filter(|id| !id.is_empty()).unwrap_or_else(|| caller.user_id.clone());
+        if !tenancy::can_access(&caller, Some(user_id.as_str())) {
+            return Err(Status::not_found(format!("Tenant not found: {}", user_id)));
+        }
+
+        let pool = self.database.get_user_database(&user_id).await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        let schedules = ScheduleOps::list_schedules(&pool, &user_id).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(ListSchedulesResponse {
+            schedules: schedules.into_iter().map(convert_schedule).collect(),
+        }))
+    }
+
+    async fn update_schedule(
+        &self,
+        request: Request<UpdateScheduleRequest>,
+    ) -> Result<Response<Schedule>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+        let spec = req.spec.ok_or_else(|| Status::invalid_argument("Schedule spec required"))?;
+
+        let existing = self.authorize_schedule(&caller, &req.schedule_id).await?;
+        let schedule = self.apply_spec(&caller, spec, existing).await?;
+        let schedule = self.scheduler.save(schedule).await.map_err(schedule_error)?;
+        tracing::info!("User {} updated schedule {}", caller.user_id, schedule.schedule_id);
+
+        Ok(Response::new(convert_schedule(schedule)))
+    }
+
+    async fn delete_schedule(
+        &self,
+        request: Request<DeleteScheduleRequest>,
+    ) -> Result<Response<DeleteScheduleResponse>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        let schedule = self.authorize_schedule(&caller, &req.schedule_id).await?;
+        let deleted = self.scheduler.delete(&schedule.user_id, &schedule.schedule_id).await
+            .map_err(|e| Status::internal(format!("Failed to delete schedule: {}", e)))?;
+        tracing::info!("User {} deleted schedule {}", caller.user_id, req.schedule_id);
+
+        Ok(Response::new(DeleteScheduleResponse { deleted }))
+    }
+}
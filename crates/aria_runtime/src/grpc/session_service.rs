@@ -1,186 +1,446 @@
 use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use futures::StreamExt;
+use serde_json::Value;
+use tokio::sync::mpsc;
 use tokio_stream::{wrappers::ReceiverStream, Stream};
 use tonic::{Request, Response, Status};
-use uuid::Uuid;
-use sqlx::Row;
 
 use super::aria::{
     session_service_server::SessionService,
+    turn_output::Event,
     Session, CreateSessionRequest, GetSessionRequest,
     ExecuteTurnRequest, TurnOutput, Message, ToolCall, ToolResult,
     MessageRole,
 };
 
+use crate::agents::load_stored_agent;
+use crate::costs::{CostAttribution, CostTracker};
+use crate::database::conversations::{ConversationOps, MessageRecord};
+use crate::database::sessions::{SessionOps, SessionRecord};
+use crate::database::tenancy::{TenantOps, TenantResource};
 use crate::database::DatabaseManager;
+use crate::deep_size::DeepValue;
+use crate::engines::execution::{parse_tool_arguments, tool_definition};
 use crate::engines::intelligence::IntelligenceEngine;
-use crate::engines::tool_registry::{ToolRegistry, ToolRegistryInterface};
-use crate::errors::{AriaError, AriaResult};
+use crate::engines::llm::types::{LLMConfig, LLMMessage, LLMRequest, StreamedResponse, Tool, ToolCall as LLMToolCall, ToolChoice};
+use crate::engines::llm::LLMHandler;
+use crate::engines::system_prompt::SystemPromptService;
+use crate::engines::tool_registry::{ToolCallContext, ToolRegistry, ToolRegistryInterface};
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::types::AgentConfig;
+use super::auth::{self, CallerIdentity};
+use super::tenancy;
+
+/// Model calls allowed in one turn when the agent sets no `max_iterations`
+const DEFAULT_MAX_TURN_ITERATIONS: usize = 10;
 
 /// Implementation of the high-level SessionService
 pub struct SessionServiceImpl {
     database: Arc<DatabaseManager>,
     intelligence: Arc<IntelligenceEngine>,
     tool_registry: Arc<ToolRegistry>,
+    costs: Arc<CostTracker>,
 }
 
 impl SessionServiceImpl {
     pub fn new(
         database: Arc<DatabaseManager>,
         intelligence: Arc<IntelligenceEngine>, 
-        tool_registry: Arc<ToolRegistry>
+        tool_registry: Arc<ToolRegistry>,
+        costs: Arc<CostTracker>,
     ) -> Self {
         Self { 
             database,
             intelligence,
             tool_registry,
+            costs,
         }
     }
 
-    /// Generate a unique session ID
-    fn generate_session_id() -> String {
-        Uuid::new_v4().to_string()
-    }
-
-    /// Store session in database
-    async fn store_session(&self, session: &Session) -> AriaResult<()> {
-        let query = r#"
-            INSERT INTO sessions (id, user_id, created_at, status, context_data)
-            VALUES (?, ?, ?, ?, ?)
-        "#;
+    /// Create a session in the caller's database, within their session quota
+    async fn store_session(&self, user_id: &str) -> AriaResult<SessionRecord> {
+        let system_pool = self.database.get_system_database().await?;
+        let user_pool = self.database.get_user_database(user_id).await?;
         
-        let context_json = serde_json::to_string(&session.context_data)
-            .map_err(|e| AriaError::database_error(&format!("Failed to serialize context: {}", e)))?;
-        
-        let created_at = session.created_at.as_ref()
-            .map(|ts| ts.seconds)
-            .unwrap_or_else(|| chrono::Utc::now().timestamp());
+        if let Some(quota) = TenantOps::get_quota(&system_pool, user_id).await? {
+            let active = SessionOps::count_active_sessions(&user_pool).await?;
+            if quota.max_sessions > 0 && active >= quota.max_sessions {
+                return Err(AriaError::new(
+                    ErrorCode::QuotaExceeded,
+                    ErrorCategory::Security,
+                    ErrorSeverity::Medium,
+                    &format!("Session quota reached: {} of {} active sessions", active, quota.max_sessions),
+                ));
+            }
+        }
         
-        let pool = self.database.pool().await?;
-        sqlx::query(query)
-            .bind(&session.id)
-            .bind(&session.user_id)
-            .bind(created_at)
-            .bind(&session.status)
-            .bind(context_json)
-            .execute(&pool)
-            .await
-            .map_err(|e| AriaError::database_error(&format!("Failed to store session: {}", e)))?;
+        let session_id = SessionOps::create_session(&user_pool, user_id, "interactive", None).await?;
+        TenantOps::record_resource(&system_pool, TenantResource::Session, &session_id, user_id, None, None).await?;
         
-        Ok(())
+        SessionOps::get_session(&user_pool, &session_id).await
     }
 
-    /// Retrieve session from database
-    async fn get_session_from_db(&self, session_id: &str) -> AriaResult<Session> {
-        let query = r#"
-            SELECT id, user_id, created_at, status, context_data
-            FROM sessions 
-            WHERE id = ?
-        "#;
-        
-        let pool = self.database.pool().await?;
-        let row = sqlx::query(query)
-            .bind(session_id)
-            .fetch_optional(&pool)
-            .await
-            .map_err(|e| AriaError::database_error(&format!("Failed to query session: {}", e)))?
-            .ok_or_else(|| AriaError::not_found(&format!("Session not found: {}", session_id)))?;
-        
-        let context_json: String = row.get("context_data");
-        let context_data: std::collections::HashMap<String, String> = 
-            serde_json::from_str(&context_json)
-                .map_err(|e| AriaError::database_error(&format!("Failed to deserialize context: {}", e)))?;
+    /// Load a session the caller may see from its owner's database
+    async fn load_session(&self, caller: &CallerIdentity, session_id: &str) -> Result<SessionRecord, Status> {
+        let owner = tenancy::authorize_resource(&self.database, caller, TenantResource::Session, session_id).await?
+            .ok_or_else(|| Status::not_found(format!("Session not found: {}", session_id)))?;
         
-        let created_at: i64 = row.get("created_at");
+        async {
+            let pool = self.database.get_user_database(&owner.user_id).await?;
+            SessionOps::get_session(&pool, session_id).await
+        }.await.map_err(|e| {
+            tracing::error!("Failed to load session {}: {}", session_id, e);
+            Status::not_found(format!("Session not found: {}", session_id))
+        })
+    }
+
+    /// Retrieve a session the caller may see from its owner's database
+    async fn get_session_from_db(&self, caller: &CallerIdentity, session_id: &str) -> Result<Session, Status> {
+        let record = self.load_session(caller, session_id).await?;
+        Ok(Self::convert_session_record(record))
+    }
+
+    /// Convert a stored session to its API form
+    fn convert_session_record(record: SessionRecord) -> Session {
+        let context_data = record.context_data.as_deref()
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default();
         
-        Ok(Session {
-            id: row.get("id"),
-            user_id: row.get("user_id"),
+        Session {
+            id: record.session_id,
+            user_id: record.user_id,
             created_at: Some(prost_types::Timestamp {
-                seconds: created_at,
+                seconds: record.created_at as i64,
                 nanos: 0,
             }),
             context_data,
-            status: row.get("status"),
-        })
+            status: record.status,
+        }
     }
 
-    /// Store a message in the database
-    async fn store_message(&self, session_id: &str, message: &Message) -> AriaResult<()> {
-        let query = r#"
-            INSERT INTO messages (id, session_id, role, content, created_at)
-            VALUES (?, ?, ?, ?, ?)
-        "#;
-        
-        let created_at = message.created_at.as_ref()
-            .map(|ts| ts.seconds)
-            .unwrap_or_else(|| chrono::Utc::now().timestamp());
-        
-        let role_str = match MessageRole::try_from(message.role).unwrap_or(MessageRole::Unspecified) {
-            MessageRole::System => "system",
-            MessageRole::User => "user", 
-            MessageRole::Assistant => "assistant",
-            MessageRole::Tool => "tool",
-            MessageRole::Unspecified => "unspecified",
+    /// Describe the agent's registry tools as JSON-schema functions
+    async fn tool_definitions(&self, agent: &AgentConfig) -> Vec<Tool> {
+        let mut tools = Vec::with_capacity(agent.tools.len());
+        for name in &agent.tools {
+            match self.tool_registry.get_tool_info(name).await {
+                Ok(Some(entry)) => tools.push(tool_definition(&entry)),
+                Ok(None) => tracing::warn!("Tool '{}' is not registered; not advertising it", name),
+                Err(e) => tracing::warn!("Failed to describe tool '{}': {}", name, e),
+            }
+        }
+        tools
+    }
+
+    /// Everything a turn needs, loaded before the response stream opens so setup failures surface as statuses
+    async fn prepare_turn(
+        &self,
+        session: &SessionRecord,
+        tx: mpsc::Sender<Result<TurnOutput, Status>>,
+    ) -> AriaResult<(TurnRunner, Vec<LLMMessage>)> {
+        let pool = self.database.get_user_database(&session.user_id).await?;
+        // The session's own configuration, else the owner's default, else the built-in assistant
+        let (config_id, agent) = load_stored_agent(
+            &pool,
+            &self.tool_registry,
+            &session.user_id,
+            session.agent_config_id.as_deref(),
+        ).await?;
+        let tools = self.tool_definitions(&agent).await;
+
+        let conversation_id = ConversationOps::get_or_create_conversation(&pool, &session.session_id, &config_id).await?;
+        let history = ConversationOps::list_messages(&pool, &conversation_id).await?
+            .iter()
+            .map(history_message)
+            .collect();
+
+        let runner = TurnRunner {
+            tool_registry: Arc::clone(&self.tool_registry),
+            costs: Arc::clone(&self.costs),
+            caller: ToolCallContext {
+                user_id: Some(session.user_id.clone()),
+                agent_name: Some(agent.name.clone()),
+                session_id: Some(session.session_id.clone()),
+            },
+            pool,
+            session_id: session.session_id.clone(),
+            conversation_id,
+            agent,
+            tools,
+            tx,
+            tool_calls: 0,
+            tokens_used: 0,
         };
-        
-        let pool = self.database.pool().await?;
-        sqlx::query(query)
-            .bind(&message.id)
-            .bind(session_id)
-            .bind(role_str)
-            .bind(&message.content)
-            .bind(created_at)
-            .execute(&pool)
-            .await
-            .map_err(|e| AriaError::database_error(&format!("Failed to store message: {}", e)))?;
-        
-        Ok(())
+        Ok((runner, history))
     }
+}
 
-    /// Execute a tool call and return the result
-    async fn execute_tool(&self, tool_call: &ToolCall) -> ToolResult {
-        tracing::info!("Executing tool: {}", tool_call.tool_name);
-        
-        // Parse tool parameters
-        let parameters: serde_json::Value = match serde_json::from_str(&tool_call.parameters_json) {
-            Ok(params) => params,
+/// Runs one turn of a session's conversation through the agent loop, streaming
+/// token deltas, tool calls and results to the client and persisting every message
+struct TurnRunner {
+    tool_registry: Arc<ToolRegistry>,
+    costs: Arc<CostTracker>,
+    pool: sqlx::SqlitePool,
+    session_id: String,
+    conversation_id: String,
+    agent: AgentConfig,
+    tools: Vec<Tool>,
+    caller: ToolCallContext,
+    tx: mpsc::Sender<Result<TurnOutput, Status>>,
+    tool_calls: u32,
+    tokens_used: u32,
+}
+
+impl TurnRunner {
+    async fn run(mut self, history: Vec<LLMMessage>, input: String) {
+        let attribution = CostAttribution {
+            user_id: self.caller.user_id.clone(),
+            session_id: Some(self.session_id.clone()),
+            agent: Some(self.agent.name.clone()),
+            ..Default::default()
+        };
+        let costs = Arc::clone(&self.costs);
+        let result = costs.scoped(attribution, self.converse(history, input)).await;
+
+        if let Err(e) = SessionOps::record_usage(&self.pool, &self.session_id, self.tool_calls, self.tokens_used).await {
+            tracing::error!("Failed to record usage for session {}: {}", self.session_id, e);
+        }
+
+        match result {
+            Ok(()) => tracing::info!("Turn execution completed for session: {}", self.session_id),
             Err(e) => {
-                tracing::error!("Failed to parse tool parameters: {}", e);
-                return ToolResult {
-                    tool_name: tool_call.tool_name.clone(),
-                    result_json: "{}".to_string(),
-                    success: false,
-                    error_message: Some(format!("Invalid parameters: {}", e)),
-                };
+                tracing::error!("Turn failed for session {}: {}", self.session_id, e);
+                let _ = self.tx.send(Err(Status::internal(format!("Turn failed: {}", e)))).await;
             }
-        };
-        
-        // Execute the tool via the tool registry
-        match self.tool_registry.execute_tool(&tool_call.tool_name, crate::deep_size::DeepValue(parameters)).await {
-            Ok(result) => {
-                let result_json = serde_json::to_string(&result)
-                    .unwrap_or_else(|_| "{}".to_string());
-                
-                ToolResult {
-                    tool_name: tool_call.tool_name.clone(),
-                    result_json,
-                    success: true,
-                    error_message: None,
+        }
+    }
+
+    /// The agent loop: stream a reply, run the tools it calls, feed their results
+    /// back and repeat until the model answers or `max_iterations` is reached.
+    /// A client that disconnects stops the turn at the next model call; tools
+    /// already running are left to finish and are recorded, since they may have side effects.
+    async fn converse(&mut self, mut history: Vec<LLMMessage>, input: String) -> AriaResult<()> {
+        let user_message = MessageRecord::new(&self.conversation_id, "user", &input);
+        self.persist(&user_message, &mut history).await?;
+        if !self.emit(message_event(&user_message)).await {
+            return Ok(());
+        }
+
+        // Tools travel as function definitions, so the prompt leaves out the JSON tool protocol
+        let system_prompt = SystemPromptService::new().generate_system_prompt(&self.agent, false);
+        let max_iterations = self.agent.max_iterations
+            .map(|n| n.max(1) as usize)
+            .unwrap_or(DEFAULT_MAX_TURN_ITERATIONS);
+
+        for _ in 0..max_iterations {
+            let mut messages = vec![LLMMessage {
+                role: "system".to_string(),
+                content: system_prompt.clone(),
+                tool_calls: None,
+                tool_call_id: None,
+            }];
+            messages.extend(history.iter().cloned());
+
+            let request = LLMRequest {
+                messages,
+                config: LLMConfig {
+                    model: Some(self.agent.llm.model.clone()),
+                    temperature: self.agent.llm.temperature.unwrap_or(0.7),
+                    max_tokens: self.agent.llm.max_tokens.unwrap_or(2000),
+                    top_p: None,
+                    frequency_penalty: None,
+                    presence_penalty: None,
+                },
+                provider: Some(self.agent.llm.provider.clone()).filter(|p| !p.is_empty()),
+                tools: if self.tools.is_empty() { None } else { Some(self.tools.clone()) },
+                tool_choice: if self.tools.is_empty() { None } else { Some(ToolChoice::Auto) },
+                stream: Some(true),
+                cache: None,
+            };
+
+            let (streamed, cancelled) = self.stream_reply(request).await?;
+            if cancelled {
+                // Keep what the client already saw so the next turn has the same history
+                if !streamed.content().is_empty() {
+                    let partial = MessageRecord::new(&self.conversation_id, "assistant", streamed.content());
+                    self.persist(&partial, &mut history).await?;
                 }
+                tracing::info!("Client disconnected; turn cancelled for session {}", self.session_id);
+                return Ok(());
             }
-            Err(e) => {
-                tracing::error!("Tool execution failed: {}", e);
+
+            let response = streamed.into_response();
+            let calls = response.tool_calls.clone().unwrap_or_default();
+            let mut reply = MessageRecord::new(&self.conversation_id, "assistant", &response.content);
+            if let Some(usage) = &response.token_usage {
+                self.tokens_used = self.tokens_used.saturating_add(usage.total);
+                reply.tokens_used = Some(usage.completion);
+            }
+            if !calls.is_empty() {
+                reply.tool_calls = serde_json::to_string(&calls).ok();
+            }
+            self.persist(&reply, &mut history).await?;
+
+            if calls.is_empty() {
+                if self.emit(message_event(&reply)).await {
+                    self.emit(Event::FinalResponse(response.content)).await;
+                }
+                return Ok(());
+            }
+
+            for call in &calls {
+                self.emit(Event::ToolCall(ToolCall {
+                    tool_name: call.name.clone(),
+                    parameters_json: call.arguments.clone(),
+                    call_id: call.id.clone(),
+                })).await;
+            }
+
+            // Calls returned in the same reply cannot depend on each other, so run them concurrently
+            let results = futures::future::join_all(calls.iter().map(|call| self.execute_tool(call))).await;
+            self.tool_calls += calls.len() as u32;
+
+            for result in results {
+                let output = serde_json::from_str::<Value>(&result.result_json).unwrap_or(Value::Null);
+                let content = serde_json::json!({
+                    "success": result.success,
+                    "result": output,
+                    "error": result.error_message,
+                }).to_string();
+
+                let mut record = MessageRecord::new(&self.conversation_id, "tool", &content);
+                record.tool_results = Some(serde_json::json!([{
+                    "call_id": result.call_id,
+                    "tool_name": result.tool_name,
+                    "success": result.success,
+                    "result": output,
+                    "error": result.error_message,
+                }]).to_string());
+                self.persist(&record, &mut history).await?;
+                self.emit(Event::ToolResult(result)).await;
+            }
+
+            if self.tx.is_closed() {
+                tracing::info!("Client disconnected; turn cancelled for session {}", self.session_id);
+                return Ok(());
+            }
+        }
+
+        self.tx.send(Err(Status::aborted(format!(
+            "Turn stopped after {} model calls without a final answer", max_iterations
+        )))).await.ok();
+        Ok(())
+    }
+
+    /// Stream the model's reply to the client as it is generated. Returns the
+    /// reply so far and whether the client went away, which drops the provider stream.
+    async fn stream_reply(&self, request: LLMRequest) -> AriaResult<(StreamedResponse, bool)> {
+        let mut stream = LLMHandler::get_instance().complete_stream(request).await?;
+        let mut streamed = StreamedResponse::default();
+
+        loop {
+            let chunk = tokio::select! {
+                _ = self.tx.closed() => return Ok((streamed, true)),
+                chunk = stream.next() => chunk,
+            };
+            let Some(chunk) = chunk else {
+                return Ok((streamed, false));
+            };
+            let chunk = chunk?;
+
+            if !chunk.content.is_empty() && !self.emit(Event::TokenDelta(chunk.content.clone())).await {
+                streamed.push(&chunk);
+                return Ok((streamed, true));
+            }
+            streamed.push(&chunk);
+        }
+    }
+
+    /// Run a tool the model called. Failures are reported back to the model rather than ending the turn.
+    async fn execute_tool(&self, call: &LLMToolCall) -> ToolResult {
+        tracing::info!("Executing tool: {}", call.name);
+
+        let outcome = match parse_tool_arguments(&call.arguments) {
+            _ if !self.agent.tools.contains(&call.name) => Err(format!(
+                "Agent '{}' is not authorized to use tool '{}'", self.agent.name, call.name
+            )),
+            Err(e) => Err(e),
+            Ok(parameters) => self.tool_registry
+                .execute_tool_as(&call.name, DeepValue(parameters), &self.caller)
+                .await
+                .map_err(|e| e.to_string()),
+        };
+
+        match outcome {
+            Ok(result) => ToolResult {
+                tool_name: call.name.clone(),
+                result_json: result.result.map(|value| value.0).unwrap_or(Value::Null).to_string(),
+                success: result.success,
+                error_message: result.error,
+                call_id: call.id.clone(),
+            },
+            Err(error) => {
+                tracing::error!("Tool execution failed: {}", error);
                 ToolResult {
-                    tool_name: tool_call.tool_name.clone(),
-                    result_json: "{}".to_string(),
+                    tool_name: call.name.clone(),
+                    result_json: Value::Null.to_string(),
                     success: false,
-                    error_message: Some(e.to_string()),
+                    error_message: Some(error),
+                    call_id: call.id.clone(),
                 }
             }
         }
     }
+
+    /// Store a message and add it to the conversation the model sees
+    async fn persist(&self, message: &MessageRecord, history: &mut Vec<LLMMessage>) -> AriaResult<()> {
+        ConversationOps::append_message(&self.pool, message).await?;
+        history.push(history_message(message));
+        Ok(())
+    }
+
+    /// Send an event to the client; false once the client has gone away
+    async fn emit(&self, event: Event) -> bool {
+        self.tx.send(Ok(TurnOutput { event: Some(event) })).await.is_ok()
+    }
+}
+
+/// A stored message as the model sees it
+fn history_message(record: &MessageRecord) -> LLMMessage {
+    let tool_calls = record.tool_calls.as_deref()
+        .and_then(|json| serde_json::from_str::<Vec<LLMToolCall>>(json).ok())
+        .filter(|calls| !calls.is_empty());
+    let tool_call_id = record.tool_results.as_deref()
+        .and_then(|json| serde_json::from_str::<Value>(json).ok())
+        .and_then(|results| results[0]["call_id"].as_str().map(str::to_string));
+
+    LLMMessage {
+        role: record.role.clone(),
+        content: record.content.clone(),
+        tool_calls,
+        tool_call_id,
+    }
+}
+
+/// A stored message as a stream event
+fn message_event(record: &MessageRecord) -> Event {
+    let role = match record.role.as_str() {
+        "system" => MessageRole::System,
+        "user" => MessageRole::User,
+        "assistant" => MessageRole::Assistant,
+        "tool" => MessageRole::Tool,
+        _ => MessageRole::Unspecified,
+    };
+
+    Event::Message(Message {
+        id: record.message_id.clone(),
+        role: role as i32,
+        content: record.content.clone(),
+        created_at: Some(prost_types::Timestamp {
+            seconds: record.created_at as i64,
+            nanos: 0,
+        }),
+    })
 }
 
 #[tonic::async_trait]
@@ -189,194 +449,94 @@ impl SessionService for SessionServiceImpl {
         &self,
         request: Request<CreateSessionRequest>,
     ) -> Result<Response<Session>, Status> {
+        let caller = auth::caller(&request)?;
         let _req = request.into_inner();
         
-        let session_id = Self::generate_session_id();
-        let now = chrono::Utc::now().timestamp();
-        
-        tracing::info!("Creating new session: {}", session_id);
-        
-        let session = Session {
-            id: session_id,
-            user_id: "default".to_string(), // TODO: Extract from authentication context
-            created_at: Some(prost_types::Timestamp {
-                seconds: now,
-                nanos: 0,
-            }),
-            context_data: std::collections::HashMap::new(),
-            status: "active".to_string(),
-        };
+        tracing::info!("Creating new session for user {}", caller.user_id);
         
-        // Store in database
-        if let Err(e) = self.store_session(&session).await {
-            tracing::error!("Failed to store session: {}", e);
-            return Err(Status::internal("Failed to create session"));
+        match self.store_session(&caller.user_id).await {
+            Ok(record) => {
+                tracing::info!("Session created successfully: {}", record.session_id);
+                Ok(Response::new(Self::convert_session_record(record)))
+            }
+            Err(e) if e.code == ErrorCode::QuotaExceeded => {
+                tracing::warn!("Refused session for user {}: {}", caller.user_id, e);
+                Err(Status::resource_exhausted(e.message))
+            }
+            Err(e) => {
+                tracing::error!("Failed to store session: {}", e);
+                Err(Status::internal("Failed to create session"))
+            }
         }
-        
-        tracing::info!("Session created successfully: {}", session.id);
-        Ok(Response::new(session))
     }
 
     async fn get_session(
         &self,
         request: Request<GetSessionRequest>,
     ) -> Result<Response<Session>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
         
         tracing::info!("Getting session: {}", req.session_id);
         
-        match self.get_session_from_db(&req.session_id).await {
-            Ok(session) => Ok(Response::new(session)),
-            Err(e) => {
-                tracing::error!("Failed to get session: {}", e);
-                Err(Status::not_found(format!("Session not found: {}", e)))
-            }
-        }
+        // Other tenants' sessions are reported as missing rather than forbidden
+        let session = self.get_session_from_db(&caller, &req.session_id).await?;
+        Ok(Response::new(session))
     }
 
     async fn execute_turn(
         &self,
         request: Request<ExecuteTurnRequest>,
     ) -> Result<Response<Self::ExecuteTurnStream>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
         
         tracing::info!("Executing turn for session: {}", req.session_id);
         
-        // Verify session exists
-        let session = match self.get_session_from_db(&req.session_id).await {
-            Ok(session) => session,
-            Err(e) => {
-                tracing::error!("Session not found: {}", e);
-                return Err(Status::not_found("Session not found"));
-            }
-        };
+        // Verify session exists and the caller may use it
+        let session = self.load_session(&caller, &req.session_id).await?;
+        if session.status != "active" {
+            return Err(Status::failed_precondition(format!("Session {} is {}", session.session_id, session.status)));
+        }
         
-        let (tx, rx) = tokio::sync::mpsc::channel(100);
+        let (tx, rx) = mpsc::channel(100);
+        let (runner, history) = self.prepare_turn(&session, tx).await
+            .map_err(|e| {
+                tracing::error!("Failed to prepare turn for session {}: {}", session.session_id, e);
+                Status::internal(format!("Failed to prepare turn: {}", e))
+            })?;
         
-        // Clone necessary data for the async task
-        let session_id = req.session_id.clone();
-        let user_input = req.input.clone();
-        let database = Arc::clone(&self.database);
-        let intelligence = Arc::clone(&self.intelligence);
-        let tool_registry = Arc::clone(&self.tool_registry);
-        let service_self = Self {
-            database: database.clone(),
-            intelligence: intelligence.clone(),
-            tool_registry: tool_registry.clone(),
-        };
-        
-        // Spawn the conversation execution task
-        tokio::spawn(async move {
-            // Store the user message
-            let user_message = Message {
-                id: Uuid::new_v4().to_string(),
-                role: MessageRole::User as i32,
-                content: user_input.clone(),
-                created_at: Some(prost_types::Timestamp {
-                    seconds: chrono::Utc::now().timestamp(),
-                    nanos: 0,
-                }),
-            };
-            
-            // Send user message to stream
-            let user_turn = TurnOutput {
-                event: Some(super::aria::turn_output::Event::Message(user_message.clone())),
-            };
-            
-            if tx.send(Ok(user_turn)).await.is_err() {
-                return; // Client disconnected
-            }
-            
-            // Store user message in database
-            if let Err(e) = service_self.store_message(&session_id, &user_message).await {
-                tracing::error!("Failed to store user message: {}", e);
-            }
-            
-            // Process the input with the intelligence engine
-            // For now, we'll simulate a simple conversation flow
-            
-            // Simulate thinking/processing
-            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-            
-            // Check if we need to use tools
-            let needs_tool = user_input.to_lowercase().contains("weather") || 
-                           user_input.to_lowercase().contains("search") ||
-                           user_input.to_lowercase().contains("calculate");
-            
-            if needs_tool {
-                // Simulate tool call
-                let tool_call = ToolCall {
-                    tool_name: if user_input.to_lowercase().contains("weather") {
-                        "get_weather".to_string()
-                    } else if user_input.to_lowercase().contains("search") {
-                        "web_search".to_string()
-                    } else {
-                        "calculator".to_string()
-                    },
-                    parameters_json: serde_json::json!({
-                        "query": user_input
-                    }).to_string(),
-                };
-                
-                // Send tool call to stream
-                let tool_call_turn = TurnOutput {
-                    event: Some(super::aria::turn_output::Event::ToolCall(tool_call.clone())),
-                };
-                
-                if tx.send(Ok(tool_call_turn)).await.is_err() {
-                    return; // Client disconnected
-                }
-                
-                // Execute the tool
-                let tool_result = service_self.execute_tool(&tool_call).await;
-                
-                // Send tool result to stream
-                let tool_result_turn = TurnOutput {
-                    event: Some(super::aria::turn_output::Event::ToolResult(tool_result)),
-                };
-                
-                if tx.send(Ok(tool_result_turn)).await.is_err() {
-                    return; // Client disconnected
-                }
-            }
-            
-            // Generate final response
-            let response_content = if needs_tool {
-                format!("I've processed your request about '{}' using the appropriate tools. Based on the results, here's what I found...", user_input)
-            } else {
-                format!("I understand you're asking about '{}'. Let me help you with that.", user_input)
-            };
-            
-            // Send final response
-            let final_response_turn = TurnOutput {
-                event: Some(super::aria::turn_output::Event::FinalResponse(response_content.clone())),
-            };
-            
-            if tx.send(Ok(final_response_turn)).await.is_err() {
-                return; // Client disconnected
-            }
-            
-            // Store assistant message in database
-            let assistant_message = Message {
-                id: Uuid::new_v4().to_string(),
-                role: MessageRole::Assistant as i32,
-                content: response_content,
-                created_at: Some(prost_types::Timestamp {
-                    seconds: chrono::Utc::now().timestamp(),
-                    nanos: 0,
-                }),
-            };
-            
-            if let Err(e) = service_self.store_message(&session_id, &assistant_message).await {
-                tracing::error!("Failed to store assistant message: {}", e);
-            }
-            
-            tracing::info!("Turn execution completed for session: {}", session_id);
-        });
+        // The turn runs until it finishes or the client drops the stream
+        tokio::spawn(runner.run(history, req.input));
         
         let stream = ReceiverStream::new(rx);
         Ok(Response::new(Box::pin(stream) as Self::ExecuteTurnStream))
     }
 
     type ExecuteTurnStream = Pin<Box<dyn Stream<Item = Result<TurnOutput, Status>> + Send>>;
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_message_restores_tool_links() {
+        let calls = vec![LLMToolCall {
+            id: "call_1".to_string(),
+            name: "search".to_string(),
+            arguments: r#"{"q":"rust"}"#.to_string(),
+        }];
+        let mut reply = MessageRecord::new("conv", "assistant", "");
+        reply.tool_calls = serde_json::to_string(&calls).ok();
+        assert_eq!(history_message(&reply).tool_calls, Some(calls));
+
+        let mut result = MessageRecord::new("conv", "tool", r#"{"success":true}"#);
+        result.tool_results = Some(r#"[{"call_id":"call_1","tool_name":"search"}]"#.to_string());
+        let message = history_message(&result);
+        assert_eq!(message.role, "tool");
+        assert_eq!(message.tool_call_id.as_deref(), Some("call_1"));
+
+        let user = history_message(&MessageRecord::new("conv", "user", "hi"));
+        assert!(user.tool_calls.is_none() && user.tool_call_id.is_none());
+    }
+}
@@ -1,173 +1,250 @@
 use std::pin::Pin;
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::HashSet;
+use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio_stream::{wrappers::ReceiverStream, Stream};
-use tonic::{Request, Response, Status, Streaming};
+use tonic::{Request, Response, Status};
 
 use super::aria::{
     task_service_server::TaskService,
-    Task, TaskStatus, LaunchTaskRequest, LaunchTaskResponse, GetTaskRequest,
-    ListTasksRequest, ListTasksResponse, StreamTaskOutputRequest, TaskOutput,
+    launch_task_request::Spec,
+    AgentTaskSpec, BundleTaskSpec, PipelineTaskSpec,
+    task_output::Output,
+    Task, TaskStatus, TaskDependency, LaunchTaskRequest, LaunchTaskResponse, GetTaskRequest,
+    ListTasksRequest, ListTasksResponse, StreamTaskOutputRequest, TaskOutput, ProgressUpdate,
     CancelTaskRequest, CancelTaskResponse,
 };
 
 use crate::engines::container::quilt::QuiltService;
-use crate::engines::container::quilt::quilt_proto;
+use crate::database::async_tasks::{AsyncTaskOps, AsyncTaskRecord, AsyncTaskStatus, NewAsyncTask};
+use crate::database::tenancy::{TenantOps, TenantResource};
 use crate::database::DatabaseManager;
-use crate::errors::{AriaError, AriaResult};
+use crate::errors::{AriaError, ErrorCode};
+use crate::pipelines::PIPELINE_TASK_TYPE;
+use crate::task_runner::{TaskRunner, AGENT_TASK_TYPE, BUNDLE_TASK_TYPE, CONTAINER_TASK_TYPE, DEPENDS_ON_SUCCESS};
+use super::auth::{self, CallerIdentity};
+use super::tenancy::{self, OWNER_LABEL};
+use super::timestamp;
+
+/// How often a followed task is checked for progress
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 /// Implementation of the high-level TaskService
+/// Tasks are queued through the task runner and read back from their `async_tasks`
+/// rows in the owner's database; callers only see their own tasks, unless they are admins.
 pub struct TaskServiceImpl {
     quilt_service: Arc<Mutex<QuiltService>>,
     database: Arc<DatabaseManager>,
+    runner: Arc<TaskRunner>,
 }
 
 impl TaskServiceImpl {
-    pub fn new(quilt_service: Arc<Mutex<QuiltService>>, database: Arc<DatabaseManager>) -> Self {
-        Self { 
+    pub fn new(quilt_service: Arc<Mutex<QuiltService>>, database: Arc<DatabaseManager>, runner: Arc<TaskRunner>) -> Self {
+        Self {
             quilt_service,
             database,
+            runner,
         }
     }
 
-    /// Convert from Quilt's TaskInfo to our high-level Task
-    fn convert_quilt_task_info_to_aria_task(quilt_task: &quilt_proto::TaskInfo) -> Task {
-        let status = match quilt_task.status {
-            1 => TaskStatus::Pending,      // TASK_PENDING
-            2 => TaskStatus::Running,      // TASK_RUNNING  
-            3 => TaskStatus::Completed,    // TASK_COMPLETED
-            4 => TaskStatus::Failed,       // TASK_FAILED
-            5 => TaskStatus::Cancelled,    // TASK_CANCELLED
-            6 => TaskStatus::Timeout,      // TASK_TIMEOUT
-            _ => TaskStatus::Pending,      // Default fallback
-        };
+    /// Convert a task record and the tasks it waits on to the API form
+    async fn convert_task(&self, record: AsyncTaskRecord) -> Result<Task, Status> {
+        let pool = self.database.get_user_database(&record.user_id).await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        let depends_on = AsyncTaskOps::get_dependencies(&pool, &record.task_id).await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .into_iter()
+            .map(|dependency| TaskDependency {
+                task_id: dependency.depends_on_task_id,
+                condition: dependency.dependency_type,
+            })
+            .collect();
 
-        Task {
-            id: quilt_task.task_id.clone(),
-            user_id: "system".to_string(), // System-level tasks for now
-            session_id: "".to_string(),    // TODO: Link to session when available
-            container_id: quilt_task.container_id.clone(),
-            parent_task_id: None,
-            
-            r#type: "container:exec".to_string(),
-            command_json: serde_json::to_string(&quilt_task.command).unwrap_or_else(|_| "[]".to_string()),
-            environment: HashMap::new(), // Quilt TaskInfo doesn't include environment
-            timeout_seconds: 0, // Not available in TaskInfo
-            
-            status: status as i32,
-            created_at: if quilt_task.started_at > 0 {
-                Some(prost_types::Timestamp {
-                    seconds: quilt_task.started_at as i64,
-                    nanos: 0,
-                })
-            } else {
-                Some(prost_types::Timestamp::default())
-            },
-            started_at: if quilt_task.started_at > 0 {
-                Some(prost_types::Timestamp {
-                    seconds: quilt_task.started_at as i64,
-                    nanos: 0,
-                })
-            } else {
-                None
-            },
-            completed_at: if quilt_task.completed_at > 0 {
-                Some(prost_types::Timestamp {
-                    seconds: quilt_task.completed_at as i64,
-                    nanos: 0,
-                })
-            } else {
-                None
-            },
-            
-            exit_code: None, // Available in GetTaskResult, not TaskInfo
-            error_message: None, // Available in GetTaskResult, not TaskInfo
-            progress_percent: 0.0, // Available in GetTaskStatus, not TaskInfo
-            current_operation: "".to_string(), // Available in GetTaskStatus, not TaskInfo
-        }
+        Ok(convert_task_record(record, depends_on))
     }
+}
 
-    /// Convert from Quilt's GetTaskStatusResponse to our high-level Task
-    fn convert_quilt_task_status_to_aria_task(
-        task_id: String, 
-        container_id: String,
-        status_response: &quilt_proto::GetTaskStatusResponse
-    ) -> Task {
-        let status = match status_response.status {
-            1 => TaskStatus::Pending,      // TASK_PENDING
-            2 => TaskStatus::Running,      // TASK_RUNNING  
-            3 => TaskStatus::Completed,    // TASK_COMPLETED
-            4 => TaskStatus::Failed,       // TASK_FAILED
-            5 => TaskStatus::Cancelled,    // TASK_CANCELLED
-            6 => TaskStatus::Timeout,      // TASK_TIMEOUT
-            _ => TaskStatus::Pending,      // Default fallback
-        };
+/// Load a task the caller may see: one in their own database, or for admins one in its owner's
+pub(super) async fn authorize_task(
+    database: &DatabaseManager,
+    caller: &CallerIdentity,
+    task_id: &str,
+) -> Result<AsyncTaskRecord, Status> {
+    let own_pool = database.get_user_database(&caller.user_id).await
+        .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+    if let Ok(task) = AsyncTaskOps::get_task(&own_pool, task_id).await {
+        return Ok(task);
+    }
 
-        Task {
-            id: task_id,
-            user_id: "system".to_string(),
-            session_id: "".to_string(),
-            container_id,
-            parent_task_id: None,
-            
-            r#type: "container:exec".to_string(),
-            command_json: "[]".to_string(), // Not available in status response
-            environment: HashMap::new(),
-            timeout_seconds: 0,
-            
-            status: status as i32,
-            created_at: if status_response.started_at > 0 {
-                Some(prost_types::Timestamp {
-                    seconds: status_response.started_at as i64,
-                    nanos: 0,
-                })
-            } else {
-                Some(prost_types::Timestamp::default())
-            },
-            started_at: if status_response.started_at > 0 {
-                Some(prost_types::Timestamp {
-                    seconds: status_response.started_at as i64,
-                    nanos: 0,
-                })
-            } else {
-                None
-            },
-            completed_at: if status_response.completed_at > 0 {
-                Some(prost_types::Timestamp {
-                    seconds: status_response.completed_at as i64,
-                    nanos: 0,
-                })
-            } else {
-                None
-            },
-            
-            exit_code: if status_response.exit_code != 0 {
-                Some(status_response.exit_code)
-            } else {
-                None
-            },
-            error_message: if !status_response.error_message.is_empty() {
-                Some(status_response.error_message.clone())
-            } else {
-                None
-            },
-            progress_percent: status_response.progress_percent,
-            current_operation: status_response.current_operation.clone(),
-        }
+    let owner = tenancy::authorize_resource(database, caller, TenantResource::Task, task_id).await?
+        .ok_or_else(|| Status::not_found(format!("Task not found: {}", task_id)))?;
+    let pool = database.get_user_database(&owner.user_id).await
+        .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+    AsyncTaskOps::get_task(&pool, task_id).await
+        .map_err(|_| Status::not_found(format!("Task not found: {}", task_id)))
+}
+
+/// The user a new task belongs to: the owner of the session, container, parent and
+/// dependencies it names, who must all be the same, else the caller
+pub(super) async fn task_owner(
+    database: &DatabaseManager,
+    quilt_service: &Mutex<QuiltService>,
+    caller: &CallerIdentity,
+    req: &LaunchTaskRequest,
+    container_id: Option<&str>,
+) -> Result<String, Status> {
+    let mut owners = Vec::new();
+
+    if !req.session_id.is_empty() {
+        let record = tenancy::authorize_resource(database, caller, TenantResource::Session, &req.session_id).await?
+            .ok_or_else(|| Status::not_found(format!("Session not found: {}", req.session_id)))?;
+        owners.push(record.user_id);
+    }
+    if let Some(container_id) = container_id {
+        let mut quilt_service = quilt_service.lock().await;
+        let container = tenancy::authorize_container(&mut quilt_service, caller, container_id).await?;
+        // An admin acting on another tenant's container launches the task on their behalf
+        owners.push(tenancy::label(&container, OWNER_LABEL).unwrap_or(caller.user_id.as_str()).to_string());
+    }
+    let related = req.parent_task_id.iter().chain(req.depends_on.iter().map(|dependency| &dependency.task_id));
+    for task_id in related {
+        owners.push(authorize_task(database, caller, task_id).await?.user_id);
     }
 
-    /// Convert Aria TaskStatus filter to Quilt TaskStatus
-    fn convert_aria_status_to_quilt_status(status: TaskStatus) -> quilt_proto::TaskStatus {
-        match status {
-            TaskStatus::Unspecified => quilt_proto::TaskStatus::TaskUnspecified,
-            TaskStatus::Pending => quilt_proto::TaskStatus::TaskPending,
-            TaskStatus::Running => quilt_proto::TaskStatus::TaskRunning,
-            TaskStatus::Completed => quilt_proto::TaskStatus::TaskCompleted,
-            TaskStatus::Failed => quilt_proto::TaskStatus::TaskFailed,
-            TaskStatus::Cancelled => quilt_proto::TaskStatus::TaskCancelled,
-            TaskStatus::Timeout => quilt_proto::TaskStatus::TaskTimeout,
+    let owner = owners.first().cloned().unwrap_or_else(|| caller.user_id.clone());
+    if owners.iter().any(|user_id| *user_id != owner) {
+        return Err(Status::invalid_argument("A task's session, container, parent and dependencies must belong to the same user"));
+    }
+    Ok(owner)
+}
+
+/// Task type, command and container of a launch request
+pub(super) fn launch_target(req: &LaunchTaskRequest) -> Result<(String, Vec<String>, Option<String>), Status> {
+    let Some(spec) = &req.spec else {
+        if !req.r#type.starts_with(CONTAINER_TASK_TYPE) {
+            return Err(Status::invalid_argument(format!("Unsupported task type '{}'", req.r#type)));
+        }
+
+        let command: Vec<String> = serde_json::from_str(&req.command_json)
+            .map_err(|e| Status::invalid_argument(format!("Invalid command JSON: {}", e)))?;
+        // The legacy form names the container in the type, as in "container:exec:<container_id>"
+        let container_id = req.container_id.clone()
+            .filter(|id| !id.is_empty())
+            .or_else(|| req.r#type.strip_prefix(CONTAINER_TASK_TYPE)?.strip_prefix(':').map(str::to_string))
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| Status::invalid_argument("Container ID required for container tasks"))?;
+        return Ok((CONTAINER_TASK_TYPE.to_string(), command, Some(container_id)));
+    };
+
+    let (task_type, command) = match spec {
+        Spec::Agent(agent) => {
+            let mut command = vec![agent.prompt.clone()];
+            command.extend(agent.agent_config_id.clone().filter(|id| !id.is_empty()));
+            (AGENT_TASK_TYPE, command)
         }
+        Spec::Bundle(bundle) => (BUNDLE_TASK_TYPE, vec![bundle.bundle_hash.clone()]),
+        Spec::Pipeline(pipeline) => {
+            let input = if pipeline.input_json.is_empty() { "null".to_string() } else { pipeline.input_json.clone() };
+            (PIPELINE_TASK_TYPE, vec![pipeline.pipeline_name.clone(), input])
+        }
+    };
+
+    if !req.r#type.is_empty() && req.r#type != task_type {
+        return Err(Status::invalid_argument(format!("Task type '{}' does not match its {} spec", req.r#type, task_type)));
+    }
+    Ok((task_type.to_string(), command, None))
+}
+
+/// The launch request that would queue `task`; the inverse of `launch_target`
+pub(super) fn launch_request(task: &NewAsyncTask) -> LaunchTaskRequest {
+    let arg = |index: usize| task.command.get(index).cloned().unwrap_or_default();
+    let spec = match task.task_type.as_str() {
+        AGENT_TASK_TYPE => Some(Spec::Agent(AgentTaskSpec { prompt: arg(0), agent_config_id: task.command.get(1).cloned() })),
+        BUNDLE_TASK_TYPE => Some(Spec::Bundle(BundleTaskSpec { bundle_hash: arg(0) })),
+        PIPELINE_TASK_TYPE => Some(Spec::Pipeline(PipelineTaskSpec { pipeline_name: arg(0), input_json: arg(1) })),
+        _ => None,
+    };
+
+    LaunchTaskRequest {
+        session_id: task.session_id.clone(),
+        r#type: task.task_type.clone(),
+        command_json: if spec.is_some() {
+            String::new()
+        } else {
+            serde_json::to_string(&task.command).unwrap_or_else(|_| "[]".to_string())
+        },
+        environment: task.environment.clone(),
+        timeout_seconds: task.timeout_seconds.unwrap_or(0) as i32,
+        container_id: task.container_id.clone(),
+        priority: task.priority,
+        parent_task_id: task.parent_task_id.clone(),
+        depends_on: Vec::new(),
+        spec,
+    }
+}
+
+pub(super) fn convert_status(status: &AsyncTaskStatus) -> TaskStatus {
+    match status {
+        AsyncTaskStatus::Pending => TaskStatus::Pending,
+        AsyncTaskStatus::Running => TaskStatus::Running,
+        AsyncTaskStatus::Completed => TaskStatus::Completed,
+        AsyncTaskStatus::Failed => TaskStatus::Failed,
+        AsyncTaskStatus::Cancelled => TaskStatus::Cancelled,
+        AsyncTaskStatus::Timeout => TaskStatus::Timeout,
+        AsyncTaskStatus::Skipped => TaskStatus::Skipped,
+    }
+}
+
+/// Convert a stored task to its API form
+fn convert_task_record(record: AsyncTaskRecord, depends_on: Vec<TaskDependency>) -> Task {
+    Task {
+        id: record.task_id,
+        user_id: record.user_id,
+        session_id: record.session_id,
+        container_id: record.container_id.unwrap_or_default(),
+        parent_task_id: record.parent_task_id,
+
+        r#type: record.task_type,
+        command_json: serde_json::to_string(&record.command).unwrap_or_else(|_| "[]".to_string()),
+        environment: record.environment,
+        timeout_seconds: record.timeout_seconds as i32,
+
+        status: convert_status(&record.status) as i32,
+        created_at: Some(timestamp(record.created_at)),
+        started_at: record.started_at.map(timestamp),
+        completed_at: record.completed_at.map(timestamp),
+
+        exit_code: record.exit_code,
+        error_message: record.error_message,
+        progress_percent: record.progress_percent,
+        current_operation: record.current_operation.unwrap_or_default(),
+
+        priority: record.priority,
+        depends_on,
+        output: record.stdout,
+    }
+}
+
+/// Output events for a finished task: its stdout lines, then its stderr lines
+fn output_events(task: &AsyncTaskRecord) -> Vec<TaskOutput> {
+    let at = Some(timestamp(task.completed_at.unwrap_or(task.created_at)));
+    let stdout = task.stdout.iter().flat_map(|text| text.lines()).map(|line| Output::StdoutLine(line.to_string()));
+    let stderr = task.stderr.iter().flat_map(|text| text.lines()).map(|line| Output::StderrLine(line.to_string()));
+
+    stdout.chain(stderr)
+        .map(|output| TaskOutput {
+            task_id: task.task_id.clone(),
+            timestamp: at,
+            output: Some(output),
+        })
+        .collect()
+}
+
+fn task_error(e: AriaError) -> Status {
+    match e.code {
+        ErrorCode::TaskValidationError => Status::invalid_argument(e.message),
+        _ => Status::internal(e.to_string()),
     }
 }
 
@@ -177,144 +254,98 @@ impl TaskService for TaskServiceImpl {
         &self,
         request: Request<LaunchTaskRequest>,
     ) -> Result<Response<LaunchTaskResponse>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
-        
-        tracing::info!("Launching task for session {}: {}", req.session_id, req.r#type);
-        
-        // Parse the command from JSON
-        let command: Vec<String> = serde_json::from_str(&req.command_json)
-            .map_err(|e| Status::invalid_argument(format!("Invalid command JSON: {}", e)))?;
-        
-        if command.is_empty() {
-            return Err(Status::invalid_argument("Command cannot be empty"));
+
+        let (task_type, command, container_id) = launch_target(&req)?;
+        tracing::info!("Launching {} task for session {} (user {})", task_type, req.session_id, caller.user_id);
+
+        if req.timeout_seconds < 0 {
+            return Err(Status::invalid_argument("Timeout must be zero (none) or positive"));
         }
-        
-        // For now, we need a container_id to launch a task. In the future, this could be
-        // derived from the session_id or we could have a default execution environment.
-        // For MVP, we'll require that the type includes the container_id.
-        let container_id = if req.r#type.starts_with("container:") {
-            // Extract container_id from type like "container:exec:container_id_here"
-            let parts: Vec<&str> = req.r#type.split(':').collect();
-            if parts.len() >= 3 {
-                parts[2].to_string()
-            } else {
-                return Err(Status::invalid_argument("Container ID required for container tasks"));
-            }
-        } else {
-            return Err(Status::unimplemented("Only container tasks are currently supported"));
-        };
-        
-        // Launch the async task via Quilt
-        let mut quilt_service = self.quilt_service.lock().await;
-        let timeout_seconds = if req.timeout_seconds > 0 {
-            Some(req.timeout_seconds)
-        } else {
-            None
+
+        // Tasks may only refer to the owner's containers, sessions and tasks
+        let owner = task_owner(&self.database, &self.quilt_service, &caller, &req, container_id.as_deref()).await?;
+        let depends_on = req.depends_on.iter()
+            .map(|dependency| {
+                let condition = if dependency.condition.is_empty() { DEPENDS_ON_SUCCESS } else { dependency.condition.as_str() };
+                (dependency.task_id.clone(), condition.to_string())
+            })
+            .collect();
+
+        let task = NewAsyncTask {
+            user_id: owner,
+            session_id: req.session_id,
+            task_type,
+            command,
+            environment: req.environment,
+            container_id,
+            parent_task_id: req.parent_task_id.filter(|id| !id.is_empty()),
+            priority: req.priority,
+            timeout_seconds: (req.timeout_seconds > 0).then_some(req.timeout_seconds as u64),
         };
-        
-        match quilt_service.exec_container_async(container_id, command, timeout_seconds).await {
-            Ok(task_id) => {
-                tracing::info!("Successfully launched task: {}", task_id);
-                Ok(Response::new(LaunchTaskResponse { task_id }))
-            }
-            Err(e) => {
-                tracing::error!("Failed to launch task: {}", e);
-                Err(Status::internal(format!("Failed to launch task: {}", e)))
-            }
-        }
+
+        let task_id = self.runner.submit(task, depends_on).await.map_err(task_error)?;
+        tracing::info!("Queued task {} for user {}", task_id, caller.user_id);
+
+        Ok(Response::new(LaunchTaskResponse { task_id }))
     }
 
     async fn get_task(
         &self,
         request: Request<GetTaskRequest>,
     ) -> Result<Response<Task>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
-        
-        tracing::info!("Getting task: {}", req.task_id);
-        
-        let mut quilt_service = self.quilt_service.lock().await;
-        
-        // Get detailed task status from Quilt
-        match quilt_service.get_task_status(req.task_id.clone()).await {
-            Ok(status_response) => {
-                // We need the container_id to build a complete Task response
-                // For now, we'll try to get it from the task result if available
-                let container_id = match quilt_service.get_task_result(req.task_id.clone()).await {
-                    Ok(_result) => "unknown".to_string(), // TaskResult doesn't include container_id
-                    Err(_) => "unknown".to_string(),
-                };
-                
-                let task = Self::convert_quilt_task_status_to_aria_task(
-                    req.task_id,
-                    container_id,
-                    &status_response
-                );
-                
-                Ok(Response::new(task))
-            }
-            Err(e) => {
-                tracing::error!("Failed to get task status: {}", e);
-                Err(Status::not_found(format!("Task not found: {}", e)))
-            }
-        }
+
+        tracing::info!("Getting task {} for user {}", req.task_id, caller.user_id);
+
+        let record = authorize_task(&self.database, &caller, &req.task_id).await?;
+        Ok(Response::new(self.convert_task(record).await?))
     }
 
-    /// The critical ListTasks implementation from our INTEGRATIONTODO.md
     async fn list_tasks(
         &self,
         request: Request<ListTasksRequest>,
     ) -> Result<Response<ListTasksResponse>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
-        
-        tracing::info!("Listing tasks with filters: session_id={:?}, statuses={:?}", 
-                      req.session_id, req.filter_by_status);
-        
-        let mut quilt_service = self.quilt_service.lock().await;
-        
-        // Get all containers first
-        let containers = match quilt_service.list_containers().await {
-            Ok(containers) => containers,
-            Err(e) => {
-                tracing::error!("Failed to list containers: {}", e);
-                return Err(Status::internal(format!("Failed to list containers: {}", e)));
-            }
-        };
-        
-        let mut all_tasks = Vec::new();
-        
-        // For each container, list its tasks
-        for container in containers {
-            // Convert status filter if provided
-            let status_filter = if !req.filter_by_status.is_empty() {
-                // Use the first status filter for now
-                Some(Self::convert_aria_status_to_quilt_status(
-                    TaskStatus::try_from(req.filter_by_status[0]).unwrap_or(TaskStatus::Unspecified)
-                ))
-            } else {
-                None
-            };
-            
-            match quilt_service.list_tasks(container.container_id.clone(), status_filter).await {
-                Ok(tasks) => {
-                    for task_info in tasks {
-                        let aria_task = Self::convert_quilt_task_info_to_aria_task(&task_info);
-                        all_tasks.push(aria_task);
-                    }
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to list tasks for container {}: {}", container.container_id, e);
-                    // Continue with other containers
-                }
-            }
+
+        tracing::info!("Listing tasks for user {} with filters: session_id={:?}, statuses={:?}, parent={:?}",
+                      caller.user_id, req.session_id, req.filter_by_status, req.parent_task_id);
+
+        // Admins see the tasks of every user that has launched one
+        let mut owners = HashSet::from([caller.user_id.clone()]);
+        if caller.is_admin() {
+            let pool = self.database.get_system_database().await
+                .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+            owners.extend(TenantOps::list_resources(&pool, TenantResource::Task, None).await
+                .map_err(|e| Status::internal(format!("Failed to list task owners: {}", e)))?
+                .into_iter()
+                .map(|record| record.user_id));
         }
-        
-        // Apply session_id filter if provided
-        if let Some(session_id) = &req.session_id {
-            if !session_id.is_empty() {
-                all_tasks.retain(|task| task.session_id == *session_id);
-            }
+
+        let statuses: Vec<i32> = req.filter_by_status.iter()
+            .copied()
+            .filter(|status| *status != TaskStatus::Unspecified as i32)
+            .collect();
+        let session_id = req.session_id.as_deref().filter(|id| !id.is_empty());
+        let parent_task_id = req.parent_task_id.as_deref().filter(|id| !id.is_empty());
+
+        let mut all_tasks = Vec::new();
+        for owner in owners {
+            let pool = self.database.get_user_database(&owner).await
+                .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+            let records = AsyncTaskOps::get_user_tasks(&pool, &owner, None).await
+                .map_err(|e| Status::internal(e.to_string()))?;
+            all_tasks.extend(records.into_iter().filter(|record| {
+                (statuses.is_empty() || statuses.contains(&(convert_status(&record.status) as i32)))
+                    && !session_id.is_some_and(|id| record.session_id != id)
+                    && !parent_task_id.is_some_and(|id| record.parent_task_id.as_deref() != Some(id))
+            }));
         }
-        
+        all_tasks.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| a.task_id.cmp(&b.task_id)));
+
         // Apply pagination (simple implementation for now)
         let page_size = if req.page_size > 0 { req.page_size as usize } else { 50 };
         let start_index = if req.page_token.is_empty() {
@@ -322,18 +353,22 @@ impl TaskService for TaskServiceImpl {
         } else {
             req.page_token.parse::<usize>().unwrap_or(0)
         };
-        
+
+        let start_index = std::cmp::min(start_index, all_tasks.len());
         let end_index = std::cmp::min(start_index + page_size, all_tasks.len());
-        let page_tasks = all_tasks[start_index..end_index].to_vec();
-        
         let next_page_token = if end_index < all_tasks.len() {
             end_index.to_string()
         } else {
             "".to_string()
         };
-        
+
+        let mut page_tasks = Vec::with_capacity(end_index - start_index);
+        for record in all_tasks.drain(start_index..end_index) {
+            page_tasks.push(self.convert_task(record).await?);
+        }
+
         tracing::info!("Returning {} tasks (page {}-{})", page_tasks.len(), start_index, end_index);
-        
+
         Ok(Response::new(ListTasksResponse {
             tasks: page_tasks,
             next_page_token,
@@ -344,73 +379,60 @@ impl TaskService for TaskServiceImpl {
         &self,
         request: Request<StreamTaskOutputRequest>,
     ) -> Result<Response<Self::StreamTaskOutputStream>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
-        
-        tracing::info!("Streaming output for task: {}, follow={}", req.task_id, req.follow);
-        
+
+        tracing::info!("Streaming output for task: {}, follow={}, user={}", req.task_id, req.follow, caller.user_id);
+
+        let task = authorize_task(&self.database, &caller, &req.task_id).await?;
+        let pool = self.database.get_user_database(&task.user_id).await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+
         // Create a channel for streaming task output
         let (tx, rx) = tokio::sync::mpsc::channel(100);
-        
-        let task_id = req.task_id.clone();
-        let quilt_service = Arc::clone(&self.quilt_service);
-        
-        // Spawn a task to fetch and stream the output
+
+        // Output is recorded when a task ends; a followed task reports its progress until then
         tokio::spawn(async move {
-            let mut quilt = quilt_service.lock().await;
-            
-            // Get the task result which includes stdout/stderr
-            match quilt.get_task_result(task_id.clone()).await {
-                Ok(result) => {
-                    // Send stdout if available
-                    if !result.stdout.is_empty() {
-                        for line in result.stdout.lines() {
-                            let output = TaskOutput {
-                                task_id: task_id.clone(),
-                                timestamp: Some(prost_types::Timestamp {
-                                    seconds: result.completed_at as i64,
-                                    nanos: 0,
-                                }),
-                                output: Some(super::aria::task_output::Output::StdoutLine(line.to_string())),
-                            };
-                            
-                            if tx.send(Ok(output)).await.is_err() {
-                                break; // Client disconnected
-                            }
-                        }
-                    }
-                    
-                    // Send stderr if available
-                    if !result.stderr.is_empty() {
-                        for line in result.stderr.lines() {
-                            let output = TaskOutput {
-                                task_id: task_id.clone(),
-                                timestamp: Some(prost_types::Timestamp {
-                                    seconds: result.completed_at as i64,
-                                    nanos: 0,
-                                }),
-                                output: Some(super::aria::task_output::Output::StderrLine(line.to_string())),
-                            };
-                            
-                            if tx.send(Ok(output)).await.is_err() {
-                                break; // Client disconnected
-                            }
-                        }
-                    }
-                    
-                    // If follow is true and task is still running, we would need to implement
-                    // real-time streaming. For now, we just send what we have.
-                    if req.follow {
-                        // TODO: Implement real-time streaming for running tasks
-                        tracing::warn!("Real-time task output streaming not yet implemented");
+            let mut task = task;
+            let mut last_progress = None;
+
+            while req.follow && !task.status.is_terminal() {
+                let progress = (task.progress_percent, task.current_operation.clone().unwrap_or_default());
+                if last_progress.as_ref() != Some(&progress) {
+                    let output = TaskOutput {
+                        task_id: task.task_id.clone(),
+                        timestamp: Some(timestamp(chrono::Utc::now().timestamp() as u64)),
+                        output: Some(Output::Progress(ProgressUpdate {
+                            percent_complete: progress.0,
+                            operation_description: progress.1.clone(),
+                        })),
+                    };
+                    if tx.send(Ok(output)).await.is_err() {
+                        return; // Client disconnected
                     }
+                    last_progress = Some(progress);
+                }
+
+                tokio::select! {
+                    _ = tx.closed() => return,
+                    _ = tokio::time::sleep(FOLLOW_POLL_INTERVAL) => {}
                 }
-                Err(e) => {
-                    tracing::error!("Failed to get task result: {}", e);
-                    let _ = tx.send(Err(Status::internal(format!("Failed to get task output: {}", e)))).await;
+                task = match AsyncTaskOps::get_task(&pool, &task.task_id).await {
+                    Ok(task) => task,
+                    Err(e) => {
+                        let _ = tx.send(Err(Status::internal(format!("Failed to get task output: {}", e)))).await;
+                        return;
+                    }
+                };
+            }
+
+            for output in output_events(&task) {
+                if tx.send(Ok(output)).await.is_err() {
+                    break; // Client disconnected
                 }
             }
         });
-        
+
         let stream = ReceiverStream::new(rx);
         Ok(Response::new(Box::pin(stream) as Self::StreamTaskOutputStream))
     }
@@ -419,24 +441,80 @@ impl TaskService for TaskServiceImpl {
         &self,
         request: Request<CancelTaskRequest>,
     ) -> Result<Response<CancelTaskResponse>, Status> {
+        let caller = auth::caller(&request)?;
         let req = request.into_inner();
-        
-        tracing::info!("Cancelling task: {}", req.task_id);
-        
-        let mut quilt_service = self.quilt_service.lock().await;
-        
-        match quilt_service.cancel_task(req.task_id).await {
-            Ok(cancelled) => {
-                Ok(Response::new(CancelTaskResponse {
-                    cancellation_initiated: cancelled,
-                }))
-            }
-            Err(e) => {
+
+        tracing::info!("Cancelling task {} for user {}", req.task_id, caller.user_id);
+
+        let task = authorize_task(&self.database, &caller, &req.task_id).await?;
+        let cancelled = self.runner.cancel(&task.user_id, &task.task_id, &format!("Cancelled by {}", caller.user_id)).await
+            .map_err(|e| {
                 tracing::error!("Failed to cancel task: {}", e);
-                Err(Status::internal(format!("Failed to cancel task: {}", e)))
-            }
-        }
+                Status::internal(format!("Failed to cancel task: {}", e))
+            })?;
+
+        Ok(Response::new(CancelTaskResponse {
+            cancellation_initiated: cancelled,
+        }))
     }
 
     type StreamTaskOutputStream = Pin<Box<dyn Stream<Item = Result<TaskOutput, Status>> + Send>>;
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_target_accepts_specs_and_legacy_container_type() {
+        let legacy = LaunchTaskRequest {
+            r#type: "container:exec:c1".to_string(),
+            command_json: r#"["ls", "-la"]"#.to_string(),
+            ..Default::default()
+        };
+        let (task_type, command, container_id) = launch_target(&legacy).unwrap();
+        assert_eq!(task_type, CONTAINER_TASK_TYPE);
+        assert_eq!(command, vec!["ls", "-la"]);
+        assert_eq!(container_id.as_deref(), Some("c1"));
+
+        let agent = LaunchTaskRequest {
+            spec: Some(Spec::Agent(AgentTaskSpec { prompt: "summarize".to_string(), agent_config_id: Some("cfg".to_string()) })),
+            ..Default::default()
+        };
+        let (task_type, command, container_id) = launch_target(&agent).unwrap();
+        assert_eq!(task_type, AGENT_TASK_TYPE);
+        assert_eq!(command, vec!["summarize", "cfg"]);
+        assert!(container_id.is_none());
+
+        let mismatched = LaunchTaskRequest { r#type: BUNDLE_TASK_TYPE.to_string(), ..agent };
+        assert!(launch_target(&mismatched).is_err());
+        assert!(launch_target(&LaunchTaskRequest { r#type: "container:exec".to_string(), command_json: "[]".to_string(), ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_launch_request_round_trips_through_launch_target() {
+        let tasks = [
+            NewAsyncTask {
+                task_type: CONTAINER_TASK_TYPE.to_string(),
+                command: vec!["echo".to_string(), "hi".to_string()],
+                container_id: Some("c1".to_string()),
+                ..Default::default()
+            },
+            NewAsyncTask {
+                task_type: AGENT_TASK_TYPE.to_string(),
+                command: vec!["summarize".to_string()],
+                ..Default::default()
+            },
+            NewAsyncTask {
+                task_type: PIPELINE_TASK_TYPE.to_string(),
+                command: vec!["nightly".to_string(), r#"{"day":1}"#.to_string()],
+                ..Default::default()
+            },
+        ];
+
+        for task in tasks {
+            let (task_type, command, container_id) = launch_target(&launch_request(&task)).unwrap();
+            assert_eq!((task_type, command, container_id), (task.task_type, task.command, task.container_id));
+        }
+    }
+}
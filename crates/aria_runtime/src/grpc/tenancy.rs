@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use tonic::Status;
+
+use super::auth::CallerIdentity;
+use crate::database::tenancy::{TenantOps, TenantResource, TenantResourceRecord};
+use crate::database::DatabaseManager;
+use crate::engines::container::quilt::{quilt_proto, QuiltService};
+
+/// Quilt label naming the aria user that owns a container
+pub const OWNER_LABEL: &str = "aria.owner";
+/// Quilt label naming the session a container was created for
+pub const SESSION_LABEL: &str = "aria.session";
+
+/// Labels tying a new container to its owner and, optionally, a session
+pub fn container_labels(user_id: &str, session_id: Option<&str>) -> HashMap<String, String> {
+    let mut labels = HashMap::from([(OWNER_LABEL.to_string(), user_id.to_string())]);
+    if let Some(session_id) = session_id.filter(|id| !id.is_empty()) {
+        labels.insert(SESSION_LABEL.to_string(), session_id.to_string());
+    }
+    labels
+}
+
+/// A non-empty label on a quilt container
+pub fn label<'a>(container: &'a quilt_proto::ContainerInfo, key: &str) -> Option<&'a str> {
+    container.labels.get(key).map(String::as_str).filter(|value| !value.is_empty())
+}
+
+/// Whether the caller may see a resource owned by `owner`; unowned resources are admin-only
+pub fn can_access(caller: &CallerIdentity, owner: Option<&str>) -> bool {
+    caller.is_admin() || owner == Some(caller.user_id.as_str())
+}
+
+/// The containers the caller may see
+pub fn visible_containers(
+    caller: &CallerIdentity,
+    containers: Vec<quilt_proto::ContainerInfo>,
+) -> Vec<quilt_proto::ContainerInfo> {
+    containers.into_iter()
+        .filter(|container| can_access(caller, label(container, OWNER_LABEL)))
+        .collect()
+}
+
+/// Look up a container the caller may act on; anything else is reported as missing
+pub async fn authorize_container(
+    quilt: &mut QuiltService,
+    caller: &CallerIdentity,
+    container_id: &str,
+) -> Result<quilt_proto::ContainerInfo, Status> {
+    let containers = quilt.list_containers().await
+        .map_err(|e| Status::internal(format!("Failed to list containers: {}", e)))?;
+
+    containers.into_iter()
+        .find(|container| container.container_id == container_id)
+        .filter(|container| can_access(caller, label(container, OWNER_LABEL)))
+        .ok_or_else(|| Status::not_found(format!("Container not found: {}", container_id)))
+}
+
+/// Look up the owner of a session or task the caller may act on.
+///
+/// Returns None only for admins acting on something aria has no record of (e.g. a
+/// task started directly through quilt); anything else the caller may not see is
+/// reported as missing.
+pub async fn authorize_resource(
+    database: &DatabaseManager,
+    caller: &CallerIdentity,
+    kind: TenantResource,
+    resource_id: &str,
+) -> Result<Option<TenantResourceRecord>, Status> {
+    let pool = database.get_system_database().await
+        .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+    let record = TenantOps::get_resource(&pool, kind, resource_id).await
+        .map_err(|e| Status::internal(e.to_string()))?;
+
+    if can_access(caller, record.as_ref().map(|record| record.user_id.as_str())) {
+        Ok(record)
+    } else {
+        Err(Status::not_found(format!("{} not found: {}", kind.as_str(), resource_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::auth::UserRole;
+
+    fn caller(user_id: &str, role: UserRole) -> CallerIdentity {
+        CallerIdentity { user_id: user_id.to_string(), device_id: "laptop".to_string(), role }
+    }
+
+    fn container(id: &str, labels: HashMap<String, String>) -> quilt_proto::ContainerInfo {
+        quilt_proto::ContainerInfo { container_id: id.to_string(), labels, ..Default::default() }
+    }
+
+    #[test]
+    fn test_container_labels() {
+        let labels = container_labels("alice", Some("s1"));
+        let info = container("c1", labels);
+        assert_eq!(label(&info, OWNER_LABEL), Some("alice"));
+        assert_eq!(label(&info, SESSION_LABEL), Some("s1"));
+
+        assert!(!container_labels("alice", Some("")).contains_key(SESSION_LABEL));
+        assert!(!container_labels("alice", None).contains_key(SESSION_LABEL));
+    }
+
+    #[test]
+    fn test_access_is_limited_to_owner_unless_admin() {
+        let alice = caller("alice", UserRole::User);
+        let root = caller("root", UserRole::Admin);
+
+        assert!(can_access(&alice, Some("alice")));
+        assert!(!can_access(&alice, Some("bob")));
+        assert!(!can_access(&alice, None));
+        assert!(can_access(&root, Some("bob")));
+        assert!(can_access(&root, None));
+    }
+
+    #[test]
+    fn test_visible_containers() {
+        let containers = vec![
+            container("mine", container_labels("alice", None)),
+            container("theirs", container_labels("bob", None)),
+            container("unlabeled", HashMap::new()),
+        ];
+
+        let ids = |visible: Vec<quilt_proto::ContainerInfo>| {
+            visible.into_iter().map(|c| c.container_id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(visible_containers(&caller("alice", UserRole::User), containers.clone())), vec!["mine"]);
+        assert_eq!(ids(visible_containers(&caller("root", UserRole::Admin), containers)).len(), 3);
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tonic::{Request, Response, Status};
+
+use super::aria::{
+    tenant_service_server::TenantService,
+    GetTenantQuotaRequest, SetTenantQuotaRequest, TenantQuota,
+};
+
+use crate::database::sessions::SessionOps;
+use crate::database::tenancy::{TenantOps, TenantQuotaRecord};
+use crate::database::users::UserOps;
+use crate::database::DatabaseManager;
+use crate::engines::container::quilt::QuiltService;
+use super::auth;
+use super::tenancy;
+
+/// Implementation of the TenantService
+/// Session limits are enforced by aria; container and memory limits are handed to
+/// quilt as owner quotas so they apply however a container is created.
+pub struct TenantServiceImpl {
+    database: Arc<DatabaseManager>,
+    quilt_service: Arc<Mutex<QuiltService>>,
+}
+
+impl TenantServiceImpl {
+    pub fn new(database: Arc<DatabaseManager>, quilt_service: Arc<Mutex<QuiltService>>) -> Self {
+        Self { database, quilt_service }
+    }
+
+    /// A tenant's limits together with what they currently use
+    async fn tenant_quota(&self, quota: TenantQuotaRecord) -> Result<TenantQuota, Status> {
+        let user_pool = self.database.get_user_database(&quota.user_id).await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        let active_sessions = SessionOps::count_active_sessions(&user_pool).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let usage = self.quilt_service.lock().await
+            .get_owner_quota(quota.user_id.clone()).await
+            .map_err(|e| Status::unavailable(format!("Failed to get container usage: {}", e)))?;
+
+        Ok(TenantQuota {
+            user_id: quota.user_id,
+            max_sessions: quota.max_sessions,
+            max_containers: quota.max_containers,
+            max_memory_mb: quota.max_memory_mb,
+            active_sessions,
+            live_containers: usage.live_containers,
+            memory_mb: usage.memory_mb,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl TenantService for TenantServiceImpl {
+    async fn get_tenant_quota(
+        &self,
+        request: Request<GetTenantQuotaRequest>,
+    ) -> Result<Response<TenantQuota>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        let user_id = req.user_id.filter(|id| !id.is_empty()).unwrap_or_else(|| caller.user_id.clone());
+        if !tenancy::can_access(&caller, Some(user_id.as_str())) {
+            return Err(Status::not_found(format!("Tenant not found: {}", user_id)));
+        }
+
+        let pool = self.database.get_system_database().await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        let quota = TenantOps::get_quota(&pool, &user_id).await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .unwrap_or_else(|| TenantQuotaRecord { user_id, ..Default::default() });
+
+        Ok(Response::new(self.tenant_quota(quota).await?))
+    }
+
+    async fn set_tenant_quota(
+        &self,
+        request: Request<SetTenantQuotaRequest>,
+    ) -> Result<Response<TenantQuota>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        if !caller.is_admin() {
+            return Err(Status::permission_denied("Only admins may set tenant quotas"));
+        }
+        if req.max_sessions < 0 || req.max_containers < 0 || req.max_memory_mb < 0 {
+            return Err(Status::invalid_argument("Quota limits must be zero (unlimited) or positive"));
+        }
+
+        let pool = self.database.get_system_database().await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        if UserOps::get_user(&pool, &req.user_id).await.is_err() {
+            return Err(Status::not_found(format!("Tenant not found: {}", req.user_id)));
+        }
+
+        tracing::info!("User {} setting quota for {}: sessions={}, containers={}, memory_mb={}",
+                      caller.user_id, req.user_id, req.max_sessions, req.max_containers, req.max_memory_mb);
+
+        // Quilt enforces the container limits, so it must accept them before they are recorded
+        self.quilt_service.lock().await
+            .set_owner_quota(req.user_id.clone(), req.max_containers, req.max_memory_mb).await
+            .map_err(|e| Status::unavailable(format!("Failed to set container quota: {}", e)))?;
+
+        let quota = TenantOps::set_quota(&pool, &req.user_id, req.max_sessions, req.max_containers, req.max_memory_mb).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(self.tenant_quota(quota).await?))
+    }
+}
@@ -0,0 +1,225 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tonic::{Request, Response, Status};
+
+use super::aria::{
+    trigger_service_server::TriggerService,
+    trigger_spec::Source,
+    Trigger, TriggerSpec, WebhookSource, EventSource, CreateTriggerRequest, GetTriggerRequest,
+    ListTriggersRequest, ListTriggersResponse, UpdateTriggerRequest, DeleteTriggerRequest, DeleteTriggerResponse,
+};
+
+use crate::database::tenancy::TenantResource;
+use crate::database::triggers::{TriggerKind, TriggerOps, TriggerRecord};
+use crate::database::DatabaseManager;
+use crate::engines::container::quilt::QuiltService;
+use crate::errors::{AriaError, ErrorCode};
+use crate::triggers::{self, TriggerEngine};
+use super::auth::{self, CallerIdentity};
+use super::task_service::{launch_request, launch_target, task_owner};
+use super::tenancy;
+use super::timestamp;
+
+/// Implementation of the TriggerService
+/// Triggers are stored in their owner's database; webhook deliveries arrive over
+/// HTTP and events from observability, both handled by the trigger engine.
+pub struct TriggerServiceImpl {
+    quilt_service: Arc<Mutex<QuiltService>>,
+    database: Arc<DatabaseManager>,
+    triggers: Arc<TriggerEngine>,
+}
+
+impl TriggerServiceImpl {
+    pub fn new(quilt_service: Arc<Mutex<QuiltService>>, database: Arc<DatabaseManager>, triggers: Arc<TriggerEngine>) -> Self {
+        Self {
+            quilt_service,
+            database,
+            triggers,
+        }
+    }
+
+    /// Load a trigger the caller may see
+    async fn authorize_trigger(&self, caller: &CallerIdentity, trigger_id: &str) -> Result<TriggerRecord, Status> {
+        let owner = tenancy::authorize_resource(&self.database, caller, TenantResource::Trigger, trigger_id).await?
+            .ok_or_else(|| Status::not_found(format!("Trigger not found: {}", trigger_id)))?;
+        let pool = self.database.get_user_database(&owner.user_id).await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        TriggerOps::get_trigger(&pool, trigger_id).await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .ok_or_else(|| Status::not_found(format!("Trigger not found: {}", trigger_id)))
+    }
+
+    /// Apply a spec to `trigger`, taking its owner from the task it launches
+    async fn apply_spec(
+        &self,
+        caller: &CallerIdentity,
+        spec: TriggerSpec,
+        mut trigger: TriggerRecord,
+    ) -> Result<TriggerRecord, Status> {
+        let task = spec.task.ok_or_else(|| Status::invalid_argument("Triggers need a task to launch"))?;
+        if task.parent_task_id.as_deref().is_some_and(|id| !id.is_empty()) || !task.depends_on.is_empty() {
+            return Err(Status::invalid_argument("Triggered tasks cannot have a parent or dependencies"));
+        }
+        if task.timeout_seconds < 0 {
+            return Err(Status::invalid_argument("Timeout must be zero (none) or positive"));
+        }
+
+        let (task_type, command, container_id) = launch_target(&task)?;
+        let owner = task_owner(&self.database, &self.quilt_service, caller, &task, container_id.as_deref()).await?;
+        if !trigger.user_id.is_empty() && trigger.user_id != owner {
+            return Err(Status::invalid_argument("A trigger's task must belong to the trigger's owner"));
+        }
+
+        match spec.source {
+            Some(Source::Webhook(_)) => {
+                trigger.kind = TriggerKind::Webhook;
+                trigger.event_type = None;
+            }
+            Some(Source::Event(source)) => {
+                trigger.kind = TriggerKind::Event;
+                trigger.event_type = Some(source.event_type);
+            }
+            None => return Err(Status::invalid_argument("Triggers need a webhook or event source")),
+        }
+
+        trigger.user_id = owner;
+        trigger.name = spec.name;
+        trigger.conditions = spec.conditions;
+        trigger.dedupe_key = Some(spec.dedupe_key).filter(|key| !key.is_empty());
+        trigger.dedupe_window_seconds = spec.dedupe_window_seconds;
+        trigger.max_fires_per_minute = spec.max_fires_per_minute;
+        trigger.task_type = task_type;
+        trigger.command = command;
+        trigger.environment = task.environment;
+        trigger.container_id = container_id;
+        trigger.session_id = Some(task.session_id).filter(|id| !id.is_empty());
+        trigger.priority = task.priority;
+        trigger.timeout_seconds = task.timeout_seconds as u64;
+        trigger.enabled = spec.enabled.unwrap_or(true);
+        Ok(trigger)
+    }
+}
+
+/// Convert a stored trigger to its API form, with its secret only if `reveal_secret`
+fn convert_trigger(record: TriggerRecord, reveal_secret: bool) -> Trigger {
+    let task = launch_request(&triggers::template(&record));
+    let (source, webhook_path) = match record.kind {
+        TriggerKind::Webhook => (Source::Webhook(WebhookSource {}), Some(format!("/triggers/{}", record.trigger_id))),
+        TriggerKind::Event => (Source::Event(EventSource { event_type: record.event_type.unwrap_or_default() }), None),
+    };
+
+    Trigger {
+        id: record.trigger_id,
+        user_id: record.user_id,
+        spec: Some(TriggerSpec {
+            name: record.name,
+            source: Some(source),
+            conditions: record.conditions,
+            dedupe_key: record.dedupe_key.unwrap_or_default(),
+            dedupe_window_seconds: record.dedupe_window_seconds,
+            max_fires_per_minute: record.max_fires_per_minute,
+            task: Some(task),
+            enabled: Some(record.enabled),
+        }),
+        webhook_path,
+        secret: record.secret.filter(|_| reveal_secret),
+        last_fired_at: record.last_fired_at.map(timestamp),
+        created_at: Some(timestamp(record.created_at)),
+        updated_at: Some(timestamp(record.updated_at)),
+    }
+}
+
+fn trigger_error(e: AriaError) -> Status {
+    match e.code {
+        ErrorCode::TriggerValidationError | ErrorCode::TaskValidationError => Status::invalid_argument(e.message),
+        _ => Status::internal(e.to_string()),
+    }
+}
+
+#[tonic::async_trait]
+impl TriggerService for TriggerServiceImpl {
+    async fn create_trigger(
+        &self,
+        request: Request<CreateTriggerRequest>,
+    ) -> Result<Response<Trigger>, Status> {
+        let caller = auth::caller(&request)?;
+        let spec = request.into_inner().spec
+            .ok_or_else(|| Status::invalid_argument("Trigger spec required"))?;
+
+        let trigger = self.apply_spec(&caller, spec, TriggerRecord::default()).await?;
+        let trigger = self.triggers.save(trigger).await.map_err(trigger_error)?;
+        tracing::info!("User {} created trigger {} for user {}", caller.user_id, trigger.trigger_id, trigger.user_id);
+
+        Ok(Response::new(convert_trigger(trigger, true)))
+    }
+
+    async fn get_trigger(
+        &self,
+        request: Request<GetTriggerRequest>,
+    ) -> Result<Response<Trigger>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        let trigger = self.authorize_trigger(&caller, &req.trigger_id).await?;
+        Ok(Response::new(convert_trigger(trigger, false)))
+    }
+
+    async fn list_triggers(
+        &self,
+        request: Request<ListTriggersRequest>,
+    ) -> Result<Response<ListTriggersResponse>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        let user_id = req.user_id.filter(|id| !id.is_empty()).unwrap_or_else(|| caller.user_id.clone());
+        if !tenancy::can_access(&caller, Some(user_id.as_str())) {
+            return Err(Status::not_found(format!("Tenant not found: {}", user_id)));
+        }
+
+        let pool = self.database.get_user_database(&user_id).await
+            .map_err(|e| Status::internal(format!("Database unavailable: {}", e)))?;
+        let triggers = TriggerOps::list_triggers(&pool, &user_id).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(ListTriggersResponse {
+            triggers: triggers.into_iter().map(|trigger| convert_trigger(trigger, false)).collect(),
+        }))
+    }
+
+    async fn update_trigger(
+        &self,
+        request: Request<UpdateTriggerRequest>,
+    ) -> Result<Response<Trigger>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+        let spec = req.spec.ok_or_else(|| Status::invalid_argument("Trigger spec required"))?;
+
+        let existing = self.authorize_trigger(&caller, &req.trigger_id).await?;
+        let mut trigger = self.apply_spec(&caller, spec, existing).await?;
+        // The engine gives webhook triggers without a secret a new one, which must be shown
+        let new_secret = req.rotate_secret || trigger.kind != TriggerKind::Webhook || trigger.secret.is_none();
+        if new_secret {
+            trigger.secret = None;
+        }
+
+        let trigger = self.triggers.save(trigger).await.map_err(trigger_error)?;
+        tracing::info!("User {} updated trigger {}", caller.user_id, trigger.trigger_id);
+
+        Ok(Response::new(convert_trigger(trigger, new_secret)))
+    }
+
+    async fn delete_trigger(
+        &self,
+        request: Request<DeleteTriggerRequest>,
+    ) -> Result<Response<DeleteTriggerResponse>, Status> {
+        let caller = auth::caller(&request)?;
+        let req = request.into_inner();
+
+        let trigger = self.authorize_trigger(&caller, &req.trigger_id).await?;
+        let deleted = self.triggers.delete(&trigger.user_id, &trigger.trigger_id).await
+            .map_err(|e| Status::internal(format!("Failed to delete trigger: {}", e)))?;
+        tracing::info!("User {} deleted trigger {}", caller.user_id, req.trigger_id);
+
+        Ok(Response::new(DeleteTriggerResponse { deleted }))
+    }
+}
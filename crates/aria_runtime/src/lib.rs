@@ -22,7 +22,9 @@ The runtime consists of several key components:
 
 #![doc = include_str!("../../../ARIARUNTIME.md")]
 
+pub mod clock;
 pub mod context;
+pub mod costs;
 pub mod database;
 pub mod deep_size;
 pub mod engines;
@@ -35,6 +37,14 @@ pub mod runtime;
 pub mod tools;
 pub mod types;
 pub mod agents;
+pub mod teams;
+pub mod pipelines;
+pub mod task_runner;
+pub mod telemetry;
+pub mod scheduler;
+pub mod triggers;
+pub mod trigger_endpoints;
+pub mod webhooks;
 pub mod bundle_discovery;
 pub mod bundle_executor;
 
@@ -46,6 +56,14 @@ use tokio::sync::Mutex;
 pub use errors::{AriaError, AriaResult};
 pub use types::{RuntimeConfiguration, RuntimeResult, ContainerSpec, ToolResult, RuntimeContext};
 pub use runtime::AriaRuntime;
+pub use teams::{Team, TeamConfig, TeamRegistry, TeamResult};
+pub use pipelines::{PipelineDefinition, PipelineExecutor, PipelineRegistry, PipelineRunResult};
+pub use task_runner::TaskRunner;
+pub use scheduler::Scheduler;
+pub use triggers::TriggerEngine;
+pub use webhooks::WebhookDispatcher;
+pub use costs::{CostScope, CostTracker, PricingTable};
+pub use memory::{MemoryConfig, MemorySystem, MemoryTier, RecalledMemory};
 pub use deep_size::DeepUuid;
 // Re-export bundle types from pkg_store
 pub use pkg_store::bundle::{LoadedBundle, AriaManifest, ToolManifest, AgentManifest, TeamManifest, PipelineManifest, BundleError, BundleMetadata};
@@ -58,9 +76,10 @@ use crate::engines::{
     planning::PlanningEngine,
     conversation::ConversationEngine,
     reflection::ReflectionEngine,
-    tool_registry::{ToolRegistry, ToolRegistryInterface},
+    tool_registry::{PolicyConfig, ToolPolicyEngine, ToolRegistry, ToolRegistryInterface},
     context_manager::ContextManagerEngine,
     llm::LLMHandler,
+    llm::response_cache::{CacheBackendKind, SqliteResponseCache},
     system_prompt::SystemPromptService,
     container::quilt::QuiltService,
     config::QuiltConfig,
@@ -106,7 +125,6 @@ impl AriaEngines {
                 .await
                 .expect("Failed to connect to Quilt daemon"),
         ));
-        let system_prompt = Arc::new(SystemPromptService::new());
         
         // 1.5. Initialize database manager
         let database_config = DatabaseConfig::default(); // Use default config for now
@@ -118,6 +136,28 @@ impl AriaEngines {
             llm_handler.clone(),
             quilt_service.clone(),
         ).await);
+        // No approval channel is attached here, so Elevated and Dangerous calls are denied
+        // unless a policy rule allows them
+        let policy_engine = Arc::new(
+            ToolPolicyEngine::new(PolicyConfig::default()).with_database(database_manager.clone())
+        );
+        policy_engine.load_rules().await.expect("Failed to load tool policy rules");
+        tool_registry.attach_policy_engine(policy_engine).await;
+
+        Self::with_services(database_manager, quilt_service, tool_registry)
+            .await
+            .expect("Failed to initialize engines")
+    }
+
+    /// Build the engines around an existing database, quilt connection and tool registry,
+    /// so a host process and the runtime it embeds share them
+    pub async fn with_services(
+        database_manager: Arc<DatabaseManager>,
+        quilt_service: Arc<Mutex<QuiltService>>,
+        tool_registry: Arc<ToolRegistry>,
+    ) -> AriaResult<Self> {
+        let llm_handler = LLMHandler::get_instance();
+        let system_prompt = Arc::new(SystemPromptService::new());
 
         // 3. Other engines, which depend on tool registry and core services
         let execution = Arc::new(ExecutionEngine::new(
@@ -150,13 +190,25 @@ impl AriaEngines {
         let observability = Arc::new(engines::observability::ObservabilityManager::new(
             database_manager.clone(),
             10000 // Event buffer size
-        ).expect("Failed to create observability manager"));
+        )?);
         
         let streaming = Arc::new(engines::streaming::StreamingService::new(
             observability.clone(),
             engines::streaming::StreamingConfig::default()
         ));
 
+        let costs = Arc::new(
+            CostTracker::new(database_manager.clone(), PricingTable::load()?)
+                .with_observability(observability.clone())
+        );
+        llm_handler.attach_cost_tracker(costs.clone());
+        if llm_handler.config().cache_backend == CacheBackendKind::Sqlite {
+            llm_handler.attach_response_cache(Arc::new(SqliteResponseCache::new(
+                database_manager.clone(),
+                llm_handler.config().max_cache_size,
+            )));
+        }
+
         // 6. Intelligence Engine
         let intelligence = Arc::new(engines::intelligence::IntelligenceEngine::new(
             database_manager.clone(),
@@ -165,10 +217,16 @@ impl AriaEngines {
         ));
 
         // 7. Package Store for bundle management
-        let pkg_store = Arc::new(pkg_store::PackageStore::new().await.expect("Failed to initialize package store"));
+        let pkg_store = Arc::new(pkg_store::PackageStore::new().await.map_err(|e| AriaError::new(
+            errors::ErrorCode::InitializationFailed,
+            errors::ErrorCategory::System,
+            errors::ErrorSeverity::Critical,
+            &format!("Failed to initialize package store: {}", e),
+        ))?);
+        tool_registry.bundle_runner().attach_package_store(pkg_store.clone()).await;
 
         // 8. Assemble the final struct
-        Self {
+        Ok(Self {
             execution,
             planning,
             conversation,
@@ -184,7 +242,8 @@ impl AriaEngines {
             streaming,
             intelligence,
             pkg_store,
-        }
+            costs,
+        })
     }
 }
 
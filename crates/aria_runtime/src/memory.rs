@@ -1,18 +1,45 @@
-use crate::deep_size::{DeepUuid, DeepValue, DeepSystemTime};
-use crate::errors::AriaResult;
-use crate::types::{AgentConfig, ConversationJSON, ExecutionStatus, RuntimeContext, WorkingMemoryEntry, MemoryEntryType};
+use crate::clock::unix_now;
+use crate::database::memories::{MemoryOps, MemoryRecord};
+use crate::deep_size::{DeepDuration, DeepUuid, DeepValue, DeepSystemTime};
+use crate::engines::llm::{LLMHandler, LLMRequestConfig};
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::types::{AgentConfig, ConversationJSON, ConversationTurn, ExecutionStatus, RuntimeContext, WorkingMemoryEntry, MemoryEntryType};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
+use tracing::warn;
 use uuid::Uuid;
 
+/// User whose database holds memories when the caller does not name one
+pub const DEFAULT_MEMORY_USER: &str = "default";
+
+/// Namespace shared by every agent acting on behalf of the same user
+pub const USER_NAMESPACE: &str = "user";
+
+/// Dimensions of the hashed bag-of-words embedding used when no provider can embed
+pub const LOCAL_EMBEDDING_DIMENSIONS: usize = 256;
+
+/// Model name recorded with local hashed embeddings
+pub const LOCAL_EMBEDDING_MODEL: &str = "local:hashed-bow-256";
+
+/// Working-memory key prefix for entries recalled into a runtime context
+pub const RECALLED_MEMORY_PREFIX: &str = "recalled_memory:";
+
+/// Tag attached to memories produced by conversation summarization
+pub const CONVERSATION_SUMMARY_TAG: &str = "conversation_summary";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryConfig {
     pub short_term_ttl: std::time::Duration,
     pub long_term_ttl: std::time::Duration,
+    /// Upper bound on entries per namespace, enforced by LRU eviction
     pub max_entries: usize,
+    /// Number of memories injected into prompts by `recall`
+    pub recall_limit: usize,
+    /// Memories scoring below this cosine similarity are never recalled
+    pub min_similarity: f32,
 }
 
 impl Default for MemoryConfig {
@@ -21,65 +48,404 @@ impl Default for MemoryConfig {
             short_term_ttl: std::time::Duration::from_secs(3600), // 1 hour
             long_term_ttl: std::time::Duration::from_secs(30 * 24 * 3600), // 30 days
             max_entries: 10000,
+            recall_limit: 5,
+            min_similarity: 0.2,
+        }
+    }
+}
+
+/// Retention tier of a memory; decides which TTL applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryTier {
+    ShortTerm,
+    LongTerm,
+}
+
+impl MemoryTier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryTier::ShortTerm => "short_term",
+            MemoryTier::LongTerm => "long_term",
+        }
+    }
+
+    pub fn parse(memory_type: &str) -> AriaResult<Self> {
+        match memory_type {
+            "short_term" => Ok(MemoryTier::ShortTerm),
+            "long_term" => Ok(MemoryTier::LongTerm),
+            _ => Err(AriaError::new(
+                ErrorCode::ContextInitializationFailed,
+                ErrorCategory::Context,
+                ErrorSeverity::Medium,
+                &format!("Invalid memory type: {}", memory_type)
+            )),
         }
     }
 }
 
+/// A memory returned by `recall`, with its similarity to the query
+#[derive(Debug, Clone)]
+pub struct RecalledMemory {
+    pub namespace: String,
+    pub score: f32,
+    pub entry: WorkingMemoryEntry,
+}
+
+/// Persistent memory for one agent, stored in the owning user's database.
+///
+/// Entries live either in the agent's own namespace (`agent:<name>`) or in the
+/// user namespace shared by all of that user's agents. Retrieval and recall
+/// read both, preferring the agent namespace on key collisions.
 pub struct MemorySystem {
     config: MemoryConfig,
-    short_term: HashMap<String, WorkingMemoryEntry>,
-    long_term: HashMap<String, WorkingMemoryEntry>,
+    pool: sqlx::SqlitePool,
+    namespace: String,
+    llm: Option<Arc<LLMHandler>>,
 }
 
 impl MemorySystem {
-    pub fn new(config: MemoryConfig) -> Self {
+    pub fn new(config: MemoryConfig, pool: sqlx::SqlitePool, agent_name: &str) -> Self {
         Self {
             config,
-            short_term: HashMap::new(),
-            long_term: HashMap::new(),
+            pool,
+            namespace: Self::agent_namespace(agent_name),
+            llm: None,
         }
     }
 
-    pub async fn store(&mut self, key: &str, value: DeepValue, memory_type: &str) -> AriaResult<()> {
-        let entry = WorkingMemoryEntry {
-            id: DeepUuid(uuid::Uuid::new_v4()),
-            key: key.to_string(),
-            value,
-            entry_type: MemoryEntryType::Learning,
-            created_at: DeepSystemTime(std::time::SystemTime::now()),
-            last_accessed: DeepSystemTime(std::time::SystemTime::now()),
+    /// Use the LLM handler for embeddings and summaries instead of the local fallbacks
+    pub fn with_llm(mut self, llm: Arc<LLMHandler>) -> Self {
+        self.llm = Some(llm);
+        self
+    }
+
+    pub fn agent_namespace(agent_name: &str) -> String {
+        format!("agent:{}", agent_name)
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn config(&self) -> &MemoryConfig {
+        &self.config
+    }
+
+    /// Store a memory private to this agent
+    pub async fn store(
+        &self,
+        key: &str,
+        value: DeepValue,
+        memory_type: &str,
+        entry_type: MemoryEntryType,
+        tags: Vec<String>,
+    ) -> AriaResult<WorkingMemoryEntry> {
+        let namespace = self.namespace.clone();
+        self.store_in(&namespace, key, value, MemoryTier::parse(memory_type)?, entry_type, tags).await
+    }
+
+    /// Store a memory visible to every agent of the same user
+    pub async fn store_for_user(
+        &self,
+        key: &str,
+        value: DeepValue,
+        memory_type: &str,
+        entry_type: MemoryEntryType,
+        tags: Vec<String>,
+    ) -> AriaResult<WorkingMemoryEntry> {
+        self.store_in(USER_NAMESPACE, key, value, MemoryTier::parse(memory_type)?, entry_type, tags).await
+    }
+
+    /// Look a memory up by key, checking the agent namespace before the user namespace
+    pub async fn retrieve(&self, key: &str, memory_type: &str) -> AriaResult<Option<DeepValue>> {
+        let tier = MemoryTier::parse(memory_type)?;
+        let now = unix_now();
+
+        for namespace in [self.namespace.as_str(), USER_NAMESPACE] {
+            if let Some(record) = MemoryOps::get(&self.pool, namespace, tier.as_str(), key, now).await? {
+                MemoryOps::touch(&self.pool, &record.memory_id, now).await?;
+                return Ok(Some(DeepValue(record.content)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Remove a memory from the agent namespace
+    pub async fn forget(&self, key: &str, memory_type: &str) -> AriaResult<bool> {
+        let tier = MemoryTier::parse(memory_type)?;
+        MemoryOps::delete(&self.pool, &self.namespace, tier.as_str(), key).await
+    }
+
+    /// Return the memories most similar to `query`, best match first
+    pub async fn recall(&self, query: &str, limit: usize) -> AriaResult<Vec<RecalledMemory>> {
+        let now = unix_now();
+        let namespaces = vec![self.namespace.clone(), USER_NAMESPACE.to_string()];
+        let records = MemoryOps::list_active(&self.pool, &namespaces, now).await?;
+        if records.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let (query_model, query_embedding) = self.embed(query).await;
+        let local_query = local_embedding(query, LOCAL_EMBEDDING_DIMENSIONS);
+
+        let mut scored: Vec<(f32, MemoryRecord)> = records.into_iter()
+            .filter_map(|record| {
+                let embedding = record.embedding.as_ref()?;
+                // Vectors from different models are not comparable; local ones always are
+                let score = match record.embedding_model.as_deref() {
+                    Some(model) if model == query_model => cosine_similarity(&query_embedding, embedding),
+                    Some(LOCAL_EMBEDDING_MODEL) => cosine_similarity(&local_query, embedding),
+                    _ => return None,
+                };
+                Some((score, record))
+            })
+            .filter(|(score, _)| *score >= self.config.min_similarity)
+            .collect();
+
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| namespace_rank(&a.1.namespace).cmp(&namespace_rank(&b.1.namespace))));
+        scored.truncate(limit);
+
+        let mut recalled = Vec::with_capacity(scored.len());
+        for (score, record) in scored {
+            MemoryOps::touch(&self.pool, &record.memory_id, now).await?;
+            recalled.push(RecalledMemory {
+                namespace: record.namespace.clone(),
+                score,
+                entry: entry_from_record(record),
+            });
+        }
+
+        Ok(recalled)
+    }
+
+    /// Condense all but the last `keep_recent` turns of a conversation into one
+    /// long-term memory keyed by the conversation id
+    pub async fn summarize_conversation(
+        &self,
+        conversation: &ConversationJSON,
+        keep_recent: usize,
+    ) -> AriaResult<Option<WorkingMemoryEntry>> {
+        let cutoff = conversation.turns.len().saturating_sub(keep_recent);
+        let old_turns = &conversation.turns[..cutoff];
+        if old_turns.is_empty() {
+            return Ok(None);
+        }
+
+        let transcript = render_transcript(old_turns);
+        let summary = match &self.llm {
+            Some(llm) => {
+                let prompt = format!(
+                    "Summarize the following conversation into a few short factual statements worth \
+                     remembering for future sessions: user preferences, decisions, outcomes and open \
+                     questions. Reply with the statements only.\n\nTASK: {}\n\nCONVERSATION:\n{}",
+                    conversation.original_task, transcript
+                );
+                let config = LLMRequestConfig {
+                    model: None,
+                    temperature: Some(0.2),
+                    max_tokens: Some(300),
+                    timeout: None,
+                };
+                match llm.inference(&prompt, Some(config)).await {
+                    Ok(summary) if !summary.trim().is_empty() => summary.trim().to_string(),
+                    Ok(_) => extractive_summary(old_turns),
+                    Err(e) => {
+                        warn!("Falling back to extractive memory summary: {}", e);
+                        extractive_summary(old_turns)
+                    }
+                }
+            }
+            None => extractive_summary(old_turns),
+        };
+
+        let value = serde_json::json!({
+            "task": conversation.original_task,
+            "summary": summary,
+            "turns": old_turns.len(),
+        });
+
+        let entry = self.store(
+            &format!("conversation:{}", conversation.id.0),
+            DeepValue(value),
+            MemoryTier::LongTerm.as_str(),
+            MemoryEntryType::Learning,
+            vec![CONVERSATION_SUMMARY_TAG.to_string()],
+        ).await?;
+
+        Ok(Some(entry))
+    }
+
+    /// Drop expired memories and evict least recently used ones above `max_entries`
+    pub async fn enforce_limits(&self) -> AriaResult<u64> {
+        let expired = MemoryOps::delete_expired(&self.pool, unix_now()).await?;
+        let evicted = MemoryOps::evict_lru(&self.pool, &self.namespace, self.config.max_entries).await?
+            + MemoryOps::evict_lru(&self.pool, USER_NAMESPACE, self.config.max_entries).await?;
+        Ok(expired + evicted)
+    }
+
+    async fn store_in(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: DeepValue,
+        tier: MemoryTier,
+        entry_type: MemoryEntryType,
+        tags: Vec<String>,
+    ) -> AriaResult<WorkingMemoryEntry> {
+        let now = unix_now();
+        let ttl = match tier {
+            MemoryTier::ShortTerm => self.config.short_term_ttl,
+            MemoryTier::LongTerm => self.config.long_term_ttl,
+        };
+        let (embedding_model, embedding) = self.embed(&memory_text(key, &value.0)).await;
+
+        let record = MemoryRecord {
+            memory_id: Uuid::new_v4().to_string(),
+            namespace: namespace.to_string(),
+            tier: tier.as_str().to_string(),
+            memory_key: key.to_string(),
+            content: value.0,
+            entry_type: entry_type_name(&entry_type),
+            tags,
+            embedding: Some(embedding),
+            embedding_model: Some(embedding_model),
+            created_at: now,
+            last_accessed: now,
             access_count: 0,
-            ttl: None,
-            tags: vec![],
+            expires_at: Some(now + ttl.as_secs()),
         };
+        MemoryOps::upsert(&self.pool, &record).await?;
+        MemoryOps::evict_lru(&self.pool, namespace, self.config.max_entries).await?;
 
-        match memory_type {
-            "short_term" => { self.short_term.insert(key.to_string(), entry); },
-            "long_term" => { self.long_term.insert(key.to_string(), entry); },
-            _ => return Err(crate::errors::AriaError::new(
-                crate::errors::ErrorCode::ContextInitializationFailed,
-                crate::errors::ErrorCategory::Context,
-                crate::errors::ErrorSeverity::Medium,
-                "Invalid memory type"
-            )),
+        Ok(entry_from_record(record))
+    }
+
+    /// Embed with the LLM provider when available, otherwise with the local hashed embedding
+    async fn embed(&self, text: &str) -> (String, Vec<f32>) {
+        if let Some(llm) = &self.llm {
+            match llm.embed(&[text.to_string()], None).await {
+                Ok(mut response) if response.embeddings.len() == 1 => {
+                    let model = format!("{}:{}", response.provider, response.model);
+                    return (model, response.embeddings.remove(0));
+                }
+                Ok(_) => warn!("Embedding provider returned an unexpected number of vectors"),
+                Err(e) => warn!("Falling back to local memory embedding: {}", e),
+            }
         }
 
-        Ok(())
+        (LOCAL_EMBEDDING_MODEL.to_string(), local_embedding(text, LOCAL_EMBEDDING_DIMENSIONS))
     }
+}
 
-    pub async fn retrieve(&self, key: &str, memory_type: &str) -> AriaResult<Option<DeepValue>> {
-        let entry = match memory_type {
-            "short_term" => self.short_term.get(key),
-            "long_term" => self.long_term.get(key),
-            _ => return Err(crate::errors::AriaError::new(
-                crate::errors::ErrorCode::ContextInitializationFailed,
-                crate::errors::ErrorCategory::Context,
-                crate::errors::ErrorSeverity::Medium,
-                "Invalid memory type"
-            )),
-        };
+/// Hashed bag-of-words embedding; deterministic so stored vectors stay comparable across runs
+pub fn local_embedding(text: &str, dimensions: usize) -> Vec<f32> {
+    let mut vector = vec![0.0f32; dimensions.max(1)];
+    for token in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.len() > 1)
+        .map(|t| t.to_lowercase())
+    {
+        let hash = fnv1a(token.as_bytes());
+        let bucket = (hash % vector.len() as u64) as usize;
+        let sign = if (hash >> 63) & 1 == 0 { 1.0 } else { -1.0 };
+        vector[bucket] += sign;
+    }
+
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+/// Cosine similarity of two vectors; mismatched or zero vectors score 0
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn namespace_rank(namespace: &str) -> u8 {
+    if namespace == USER_NAMESPACE { 1 } else { 0 }
+}
+
+fn memory_text(key: &str, value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => format!("{} {}", key, s),
+        other => format!("{} {}", key, other),
+    }
+}
+
+fn render_transcript(turns: &[ConversationTurn]) -> String {
+    turns.iter()
+        .map(|turn| format!("{:?}: {}", turn.role, turn.content))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Summary used when no LLM is available: the opening of each turn, capped in length
+fn extractive_summary(turns: &[ConversationTurn]) -> String {
+    const TURN_CHARS: usize = 160;
+    const TOTAL_CHARS: usize = 1200;
+
+    let mut summary = String::new();
+    for turn in turns {
+        let content: String = turn.content.chars().take(TURN_CHARS).collect();
+        let line = format!("{:?}: {}", turn.role, content.trim());
+        if summary.len() + line.len() > TOTAL_CHARS {
+            break;
+        }
+        if !summary.is_empty() {
+            summary.push('\n');
+        }
+        summary.push_str(&line);
+    }
+    summary
+}
+
+fn entry_type_name(entry_type: &MemoryEntryType) -> String {
+    serde_json::to_value(entry_type)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| "Learning".to_string())
+}
+
+fn parse_entry_type(name: &str) -> MemoryEntryType {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .unwrap_or(MemoryEntryType::Learning)
+}
 
-        Ok(entry.map(|e| e.value.clone()))
+fn entry_from_record(record: MemoryRecord) -> WorkingMemoryEntry {
+    let to_time = |secs: u64| DeepSystemTime(UNIX_EPOCH + Duration::from_secs(secs));
+    WorkingMemoryEntry {
+        id: DeepUuid(Uuid::parse_str(&record.memory_id).unwrap_or_else(|_| Uuid::new_v4())),
+        key: record.memory_key,
+        value: DeepValue(record.content),
+        entry_type: parse_entry_type(&record.entry_type),
+        created_at: to_time(record.created_at),
+        last_accessed: to_time(record.last_accessed),
+        access_count: record.access_count,
+        ttl: record.expires_at
+            .map(|expires| DeepDuration(Duration::from_secs(expires.saturating_sub(record.created_at)))),
+        tags: record.tags,
     }
 }
 
@@ -88,6 +454,7 @@ impl Default for RuntimeContext {
     fn default() -> Self {
         RuntimeContext {
             session_id: DeepUuid(Uuid::new_v4()),
+            user_id: None,
             agent_config: AgentConfig::default(),
             created_at: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
@@ -108,4 +475,93 @@ impl Default for RuntimeContext {
             conversation: None,
         }
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ConversationRole;
+
+    fn turn(role: ConversationRole, content: &str) -> ConversationTurn {
+        ConversationTurn {
+            id: DeepUuid(Uuid::new_v4()),
+            role,
+            content: content.to_string(),
+            timestamp: 0,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn local_embedding_is_normalized_and_deterministic() {
+        let a = local_embedding("The user prefers dark mode", LOCAL_EMBEDDING_DIMENSIONS);
+        let b = local_embedding("the USER prefers dark mode!", LOCAL_EMBEDDING_DIMENSIONS);
+        assert_eq!(a, b);
+        let norm = a.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-5);
+        assert!(local_embedding("", 8).iter().all(|v| *v == 0.0));
+    }
+
+    #[test]
+    fn related_text_scores_higher_than_unrelated() {
+        let query = local_embedding("which editor theme does the user like", LOCAL_EMBEDDING_DIMENSIONS);
+        let related = local_embedding("user likes the solarized editor theme", LOCAL_EMBEDDING_DIMENSIONS);
+        let unrelated = local_embedding("deploy the container to staging cluster", LOCAL_EMBEDDING_DIMENSIONS);
+        assert!(cosine_similarity(&query, &related) > cosine_similarity(&query, &unrelated));
+    }
+
+    #[test]
+    fn cosine_similarity_handles_mismatched_vectors() {
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[1.0, 0.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[], &[]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+        assert!((cosine_similarity(&[1.0, 1.0], &[2.0, 2.0]) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn memory_tier_parsing() {
+        assert_eq!(MemoryTier::parse("short_term").unwrap(), MemoryTier::ShortTerm);
+        assert_eq!(MemoryTier::parse("long_term").unwrap().as_str(), "long_term");
+        assert!(MemoryTier::parse("forever").is_err());
+    }
+
+    #[test]
+    fn entry_type_round_trips_through_storage_name() {
+        let name = entry_type_name(&MemoryEntryType::ToolPreference);
+        assert_eq!(name, "ToolPreference");
+        assert!(matches!(parse_entry_type(&name), MemoryEntryType::ToolPreference));
+        assert!(matches!(parse_entry_type("Unknown"), MemoryEntryType::Learning));
+    }
+
+    #[test]
+    fn extractive_summary_is_bounded() {
+        let long = "x".repeat(500);
+        let turns: Vec<_> = (0..50).map(|_| turn(ConversationRole::User, &long)).collect();
+        let summary = extractive_summary(&turns);
+        assert!(summary.len() <= 1200);
+        assert!(summary.starts_with("User: "));
+    }
+
+    #[test]
+    fn record_conversion_derives_ttl() {
+        let record = MemoryRecord {
+            memory_id: Uuid::new_v4().to_string(),
+            namespace: USER_NAMESPACE.to_string(),
+            tier: "long_term".to_string(),
+            memory_key: "timezone".to_string(),
+            content: serde_json::json!("Europe/Berlin"),
+            entry_type: "Constraint".to_string(),
+            tags: vec!["profile".to_string()],
+            embedding: None,
+            embedding_model: None,
+            created_at: 100,
+            last_accessed: 150,
+            access_count: 3,
+            expires_at: Some(400),
+        };
+        let entry = entry_from_record(record);
+        assert_eq!(entry.ttl.map(|t| t.0), Some(Duration::from_secs(300)));
+        assert_eq!(entry.access_count, 3);
+        assert!(matches!(entry.entry_type, MemoryEntryType::Constraint));
+    }
+}
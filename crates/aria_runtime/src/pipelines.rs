@@ -0,0 +1,1008 @@
+/*!
+# Pipelines
+
+DAG workflows declared in bundle manifests. Each node runs a tool, an agent or
+a team; edges order the nodes and carry outputs into downstream inputs. Every
+run is an `async_tasks` row with one child row per node and a
+`task_dependencies` row per edge, so a run interrupted by a crash can be picked
+up again from whatever its nodes had finished.
+
+Nodes act for the run's owner: tools are called in the run's session, so
+policy rules, approvals and workspaces are the owner's, and agents remember
+in the owner's memory.
+*/
+
+use crate::agents::AgentConfig;
+use crate::database::async_tasks::{AsyncTaskOps, AsyncTaskRecord, AsyncTaskStatus};
+use crate::database::sessions::SessionOps;
+use crate::deep_size::DeepValue;
+use crate::engines::tool_registry::{ToolCallContext, ToolRegistryInterface};
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::runtime::AriaRuntime;
+use crate::teams::agent_output;
+use futures::stream::{FuturesUnordered, StreamExt};
+use pkg_store::bundle::{
+    AgentManifest, AriaManifest, PipelineCondition, PipelineEdgeKind, PipelineEdgeManifest,
+    PipelineManifest, PipelineNodeKind, PipelineNodeManifest,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// `async_tasks.task_type` of a pipeline run
+pub const PIPELINE_TASK_TYPE: &str = "pipeline";
+/// `async_tasks.task_type` of a single node within a run
+pub const PIPELINE_NODE_TASK_TYPE: &str = "pipeline_node";
+
+/// A validated pipeline, ready to run
+#[derive(Debug, Clone)]
+pub struct PipelineDefinition {
+    pub name: String,
+    pub description: String,
+    pub nodes: Vec<PipelineNodeManifest>,
+    pub edges: Vec<PipelineEdgeManifest>,
+    /// Agents run by agent nodes, resolved from the declaring bundle
+    pub agents: HashMap<String, AgentConfig>,
+}
+
+impl PipelineDefinition {
+    /// Validate a bundle's `PipelineManifest`: unique node IDs, edges between
+    /// known nodes, no cycles, and agent nodes naming agents from the same bundle
+    pub fn from_manifest(pipeline: &PipelineManifest, agents: &[AgentManifest]) -> AriaResult<Self> {
+        let invalid = |message: String| AriaError::new(
+            ErrorCode::BundleValidationError,
+            ErrorCategory::Bundle,
+            ErrorSeverity::Medium,
+            &format!("Pipeline '{}': {}", pipeline.name, message),
+        );
+
+        if pipeline.nodes.is_empty() {
+            return Err(invalid("has no nodes".to_string()));
+        }
+
+        let mut ids = HashSet::new();
+        let mut resolved_agents = HashMap::new();
+        for node in &pipeline.nodes {
+            if node.id.is_empty() || !ids.insert(node.id.as_str()) {
+                return Err(invalid(format!("node ID '{}' is empty or duplicated", node.id)));
+            }
+            if !matches!(node.input, Value::Null | Value::Object(_)) {
+                return Err(invalid(format!("input of node '{}' must be an object", node.id)));
+            }
+            if node.kind == PipelineNodeKind::Agent {
+                let agent = agents.iter()
+                    .find(|agent| agent.name == node.target)
+                    .ok_or_else(|| invalid(format!("node '{}' references unknown agent '{}'", node.id, node.target)))?;
+                resolved_agents.insert(agent.name.clone(), AgentConfig::from(agent));
+            }
+        }
+
+        let mut seen_edges = HashSet::new();
+        for edge in &pipeline.edges {
+            if !ids.contains(edge.from.as_str()) || !ids.contains(edge.to.as_str()) {
+                return Err(invalid(format!("edge {} -> {} references an unknown node", edge.from, edge.to)));
+            }
+            if edge.from == edge.to || !seen_edges.insert((edge.from.as_str(), edge.to.as_str())) {
+                return Err(invalid(format!("edge {} -> {} is a self-loop or duplicate", edge.from, edge.to)));
+            }
+        }
+
+        let definition = Self {
+            name: pipeline.name.clone(),
+            description: pipeline.description.clone(),
+            nodes: pipeline.nodes.clone(),
+            edges: pipeline.edges.clone(),
+            agents: resolved_agents,
+        };
+
+        if definition.topological_order().is_none() {
+            return Err(invalid("edges form a cycle".to_string()));
+        }
+        Ok(definition)
+    }
+
+    /// Node indices in dependency order, or `None` if the edges form a cycle
+    pub fn topological_order(&self) -> Option<Vec<usize>> {
+        let index: HashMap<&str, usize> = self.nodes.iter()
+            .enumerate()
+            .map(|(i, node)| (node.id.as_str(), i))
+            .collect();
+
+        let mut in_degree = vec![0usize; self.nodes.len()];
+        for edge in &self.edges {
+            in_degree[index[edge.to.as_str()]] += 1;
+        }
+
+        let mut queue: VecDeque<usize> = (0..self.nodes.len()).filter(|&i| in_degree[i] == 0).collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(i) = queue.pop_front() {
+            order.push(i);
+            for edge in self.edges.iter().filter(|edge| edge.from == self.nodes[i].id) {
+                let target = index[edge.to.as_str()];
+                in_degree[target] -= 1;
+                if in_degree[target] == 0 {
+                    queue.push_back(target);
+                }
+            }
+        }
+
+        (order.len() == self.nodes.len()).then_some(order)
+    }
+
+    fn incoming<'a>(&'a self, node_id: &'a str) -> impl Iterator<Item = &'a PipelineEdgeManifest> + 'a {
+        self.edges.iter().filter(move |edge| edge.to == node_id)
+    }
+
+    /// Nodes nothing depends on; their outputs make up the run's output
+    fn sinks(&self) -> Vec<&PipelineNodeManifest> {
+        self.nodes.iter()
+            .filter(|node| !self.edges.iter().any(|edge| edge.from == node.id))
+            .collect()
+    }
+}
+
+/// Pipelines known to the runtime, keyed by name
+#[derive(Default)]
+pub struct PipelineRegistry {
+    pipelines: RwLock<HashMap<String, PipelineDefinition>>,
+}
+
+impl PipelineRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a pipeline, replacing any existing pipeline with the same name
+    pub async fn register(&self, definition: PipelineDefinition) {
+        debug!("Registering pipeline '{}' ({} nodes)", definition.name, definition.nodes.len());
+        self.pipelines.write().await.insert(definition.name.clone(), definition);
+    }
+
+    /// Register every pipeline declared in a bundle manifest and return their names.
+    /// Pipelines without nodes are descriptive only and are not registered.
+    pub async fn register_manifest(&self, manifest: &AriaManifest) -> Vec<String> {
+        let mut registered = Vec::new();
+        for pipeline in manifest.pipelines.iter().filter(|pipeline| !pipeline.nodes.is_empty()) {
+            match PipelineDefinition::from_manifest(pipeline, &manifest.agents) {
+                Ok(definition) => {
+                    registered.push(definition.name.clone());
+                    self.register(definition).await;
+                }
+                Err(e) => warn!("Skipping pipeline '{}' from bundle '{}': {}", pipeline.name, manifest.name, e),
+            }
+        }
+        registered
+    }
+
+    pub async fn get(&self, name: &str) -> Option<PipelineDefinition> {
+        self.pipelines.read().await.get(name).cloned()
+    }
+
+    pub async fn unregister(&self, name: &str) -> bool {
+        self.pipelines.write().await.remove(name).is_some()
+    }
+
+    pub async fn list(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.pipelines.read().await.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+/// Final state of one node in a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineNodeResult {
+    pub node_id: String,
+    pub task_id: String,
+    pub status: AsyncTaskStatus,
+    pub output: Option<Value>,
+    pub error: Option<String>,
+}
+
+/// Outcome of a pipeline run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineRunResult {
+    pub run_id: String,
+    pub pipeline: String,
+    pub success: bool,
+    /// Output of the single sink node, or an object of sink outputs keyed by node ID
+    pub output: Option<Value>,
+    pub error: Option<String>,
+    pub nodes: Vec<PipelineNodeResult>,
+}
+
+#[derive(Debug, Clone)]
+struct NodeState {
+    task_id: String,
+    status: AsyncTaskStatus,
+    output: Option<Value>,
+    error: Option<String>,
+}
+
+enum Readiness {
+    Waiting,
+    Ready,
+    Blocked(String),
+}
+
+/// Runs pipelines from the runtime's registry, persisting progress per node
+pub struct PipelineExecutor {
+    runtime: AriaRuntime,
+}
+
+impl PipelineExecutor {
+    pub fn new(runtime: AriaRuntime) -> Self {
+        Self { runtime }
+    }
+
+    /// Start a new run of `pipeline_name` for `user_id`
+    pub async fn start(&self, pipeline_name: &str, input: Value, user_id: &str) -> AriaResult<PipelineRunResult> {
+        let definition = self.definition(pipeline_name).await?;
+        let pool = self.runtime.engines.database.get_user_database(user_id).await?;
+        let session_id = SessionOps::create_session(&pool, user_id, PIPELINE_TASK_TYPE, None).await?;
+
+        let input_json = serde_json::to_string(&input).map_err(|e| AriaError::new(
+            ErrorCode::SerializationError,
+            ErrorCategory::System,
+            ErrorSeverity::Medium,
+            &format!("Failed to serialize pipeline input: {}", e),
+        ))?;
+        let run_id = AsyncTaskOps::create_task(
+            &pool,
+            user_id,
+            &session_id,
+            PIPELINE_TASK_TYPE,
+            vec![definition.name.clone(), input_json],
+            HashMap::new(),
+            None,
+        ).await?;
+
+        let states = plan_nodes(&pool, &definition, &run_id, user_id, &session_id).await?;
+
+        info!("Started pipeline '{}' as run {}", definition.name, run_id);
+        self.drive(&pool, &definition, &run_id, user_id, &session_id, input, states).await
+    }
+
+    /// Continue an interrupted run. Finished nodes keep their persisted outputs;
+    /// nodes that were running when the run stopped start over.
+    pub async fn resume(&self, run_id: &str, user_id: &str) -> AriaResult<PipelineRunResult> {
+        let pool = self.runtime.engines.database.get_user_database(user_id).await?;
+        let run = AsyncTaskOps::get_task(&pool, run_id).await?;
+        if run.task_type != PIPELINE_TASK_TYPE || run.status.is_terminal() {
+            return Err(AriaError::new(
+                ErrorCode::ExecutionError,
+                ErrorCategory::Execution,
+                ErrorSeverity::Medium,
+                &format!("Task {} is not an unfinished pipeline run", run_id),
+            ));
+        }
+
+        let (pipeline_name, input) = run_command(&run)?;
+        let definition = self.definition(&pipeline_name).await?;
+
+        let mut states: HashMap<String, NodeState> = AsyncTaskOps::get_subtasks(&pool, run_id).await?
+            .into_iter()
+            .filter(|task| task.task_type == PIPELINE_NODE_TASK_TYPE)
+            .filter_map(|task| {
+                let node_id = task.command.first()?.clone();
+                let status = match task.status {
+                    AsyncTaskStatus::Running => AsyncTaskStatus::Pending,
+                    status => status,
+                };
+                let output = task.stdout.as_deref().and_then(|stdout| serde_json::from_str(stdout).ok());
+                Some((node_id, NodeState { task_id: task.task_id, status, output, error: task.stderr }))
+            })
+            .collect();
+
+        if let Some(missing) = definition.nodes.iter().find(|node| !states.contains_key(&node.id)) {
+            return Err(AriaError::new(
+                ErrorCode::BundleValidationError,
+                ErrorCategory::Bundle,
+                ErrorSeverity::Medium,
+                &format!("Pipeline '{}' changed since run {} started: node '{}' has no task", pipeline_name, run_id, missing.id),
+            ));
+        }
+        states.retain(|node_id, _| definition.nodes.iter().any(|node| &node.id == node_id));
+
+        info!("Resuming pipeline '{}' run {}", pipeline_name, run_id);
+        self.drive(&pool, &definition, run_id, user_id, &run.session_id, input, states).await
+    }
+
+    /// Run a pipeline run row queued by the task runner, with the command
+    /// `[pipeline_name, input_json]`. Its nodes are planned on first run;
+    /// a run that already has nodes resumes from them.
+    pub async fn run_task(&self, run_id: &str, user_id: &str) -> AriaResult<PipelineRunResult> {
+        let pool = self.runtime.engines.database.get_user_database(user_id).await?;
+        let planned = AsyncTaskOps::get_subtasks(&pool, run_id).await?
+            .iter()
+            .any(|task| task.task_type == PIPELINE_NODE_TASK_TYPE);
+        if !planned {
+            let run = AsyncTaskOps::get_task(&pool, run_id).await?;
+            let (pipeline_name, input) = run_command(&run)?;
+            let definition = self.definition(&pipeline_name).await?;
+
+            let states = plan_nodes(&pool, &definition, run_id, user_id, &run.session_id).await?;
+            info!("Started pipeline '{}' as task {}", definition.name, run_id);
+            return self.drive(&pool, &definition, run_id, user_id, &run.session_id, input, states).await;
+        }
+        self.resume(run_id, user_id).await
+    }
+
+    /// Resume every unfinished run belonging to `user_id`
+    pub async fn resume_unfinished(&self, user_id: &str) -> AriaResult<Vec<PipelineRunResult>> {
+        let pool = self.runtime.engines.database.get_user_database(user_id).await?;
+        let runs = AsyncTaskOps::get_unfinished_tasks(&pool, PIPELINE_TASK_TYPE).await?;
+
+        let mut results = Vec::new();
+        for run in runs {
+            match self.resume(&run.task_id, user_id).await {
+                Ok(result) => results.push(result),
+                Err(e) => warn!("Could not resume pipeline run {}: {}", run.task_id, e),
+            }
+        }
+        Ok(results)
+    }
+
+    async fn definition(&self, pipeline_name: &str) -> AriaResult<PipelineDefinition> {
+        self.runtime.pipelines.get(pipeline_name).await
+            .ok_or_else(|| AriaError::not_found(&format!("Pipeline not found: {}", pipeline_name)))
+    }
+
+    /// Schedule nodes as their dependencies finish until nothing is left to run.
+    /// Nodes run as `user_id` in the run's session.
+    #[allow(clippy::too_many_arguments)]
+    async fn drive(
+        &self,
+        pool: &sqlx::SqlitePool,
+        definition: &PipelineDefinition,
+        run_id: &str,
+        user_id: &str,
+        session_id: &str,
+        input: Value,
+        mut states: HashMap<String, NodeState>,
+    ) -> AriaResult<PipelineRunResult> {
+        AsyncTaskOps::mark_running(pool, run_id, &format!("pipeline {}", definition.name)).await?;
+
+        let mut in_flight = HashSet::new();
+        let mut running = FuturesUnordered::new();
+
+        loop {
+            // Settle everything that can be decided now: skip blocked nodes,
+            // which may in turn unblock or block others, and launch ready ones
+            let mut settled = true;
+            while settled {
+                settled = false;
+                for node in &definition.nodes {
+                    if states[&node.id].status != AsyncTaskStatus::Pending || in_flight.contains(&node.id) {
+                        continue;
+                    }
+
+                    let skip_reason = match readiness(definition, node, &states) {
+                        Readiness::Waiting => continue,
+                        Readiness::Blocked(reason) => Some(reason),
+                        Readiness::Ready => {
+                            let document = state_document(&input, &states);
+                            match &node.condition {
+                                Some(condition) if !condition_holds(condition, &document) => {
+                                    Some(format!("condition on {} not met", condition.path))
+                                }
+                                _ => None,
+                            }
+                        }
+                    };
+
+                    if let Some(reason) = skip_reason {
+                        debug!("Skipping pipeline node '{}': {}", node.id, reason);
+                        let state = states.get_mut(&node.id).expect("state exists for every node");
+                        record_outcome(pool, state, AsyncTaskStatus::Skipped, None, Some(reason)).await?;
+                        settled = true;
+                        continue;
+                    }
+
+                    let document = state_document(&input, &states);
+                    let node_input = build_input(definition, node, &input, &states, &document);
+                    in_flight.insert(node.id.clone());
+                    running.push(self.run_node(pool, definition, node, user_id, session_id, states[&node.id].task_id.clone(), node_input));
+                }
+            }
+
+            let Some((node_id, status, output, error)) = running.next().await else {
+                break;
+            };
+            in_flight.remove(&node_id);
+            let state = states.get_mut(&node_id).expect("state exists for every node");
+            record_outcome(pool, state, status, output, error).await?;
+        }
+
+        let result = run_result(definition, run_id, &states);
+        let (status, stdout) = if result.success {
+            (AsyncTaskStatus::Completed, result.output.as_ref().map(Value::to_string))
+        } else {
+            (AsyncTaskStatus::Failed, None)
+        };
+        AsyncTaskOps::update_task_status(pool, run_id, status, None, stdout, result.error.clone()).await?;
+        // Runs queued into an existing session leave it open for its owner
+        let own_session = match SessionOps::get_session(pool, session_id).await {
+            Ok(session) => session.session_type == PIPELINE_TASK_TYPE,
+            Err(_) => false,
+        };
+        if own_session {
+            if let Err(e) = SessionOps::end_session(pool, session_id).await {
+                warn!("Failed to end session {} for pipeline run {}: {}", session_id, run_id, e);
+            }
+        }
+
+        info!("Pipeline '{}' run {} finished (success: {})", definition.name, run_id, result.success);
+        Ok(result)
+    }
+
+    /// Run one node with its retries and per-attempt timeout
+    #[allow(clippy::too_many_arguments)]
+    async fn run_node(
+        &self,
+        pool: &sqlx::SqlitePool,
+        definition: &PipelineDefinition,
+        node: &PipelineNodeManifest,
+        user_id: &str,
+        session_id: &str,
+        task_id: String,
+        input: Value,
+    ) -> (String, AsyncTaskStatus, Option<Value>, Option<String>) {
+        let attempts = node.retries + 1;
+        let limit = node.timeout_seconds.map(Duration::from_secs);
+        let mut failure = (AsyncTaskStatus::Failed, String::new());
+
+        for attempt in 1..=attempts {
+            if let Err(e) = AsyncTaskOps::mark_running(pool, &task_id, &format!("attempt {}/{}", attempt, attempts)).await {
+                warn!("Failed to record progress for pipeline node '{}': {}", node.id, e);
+            }
+
+            let invocation = self.invoke(definition, node, user_id, session_id, input.clone());
+            let result = match limit {
+                Some(limit) => match tokio::time::timeout(limit, invocation).await {
+                    Ok(result) => result.map_err(|e| (AsyncTaskStatus::Failed, e.to_string())),
+                    Err(_) => Err((AsyncTaskStatus::Timeout, format!("timed out after {}s", limit.as_secs()))),
+                },
+                None => invocation.await.map_err(|e| (AsyncTaskStatus::Failed, e.to_string())),
+            };
+
+            match result {
+                Ok(output) => return (node.id.clone(), AsyncTaskStatus::Completed, Some(output), None),
+                Err(error) => {
+                    warn!("Pipeline node '{}' attempt {}/{} failed: {}", node.id, attempt, attempts, error.1);
+                    failure = error;
+                }
+            }
+
+            if attempt < attempts {
+                tokio::time::sleep(Duration::from_millis(500 * 2u64.pow((attempt - 1).min(6)))).await;
+            }
+        }
+
+        (node.id.clone(), failure.0, None, Some(failure.1))
+    }
+
+    /// Run a node's tool, agent or team for `user_id`. Tool nodes are called
+    /// in the run's session with the pipeline standing in for the agent.
+    async fn invoke(
+        &self,
+        definition: &PipelineDefinition,
+        node: &PipelineNodeManifest,
+        user_id: &str,
+        session_id: &str,
+        input: Value,
+    ) -> AriaResult<Value> {
+        match node.kind {
+            PipelineNodeKind::Tool => {
+                let caller = ToolCallContext {
+                    user_id: Some(user_id.to_string()),
+                    agent_name: Some(definition.name.clone()),
+                    session_id: Some(session_id.to_string()),
+                };
+                let result = self.runtime.engines.tool_registry
+                    .execute_tool_as(&node.target, DeepValue(input), &caller)
+                    .await?;
+                if result.success {
+                    Ok(result.result.map(|value| value.0).unwrap_or(Value::Null))
+                } else {
+                    Err(AriaError::new(
+                        ErrorCode::ToolExecutionError,
+                        ErrorCategory::Tool,
+                        ErrorSeverity::Medium,
+                        &result.error.unwrap_or_else(|| format!("Tool '{}' failed", node.target)),
+                    ))
+                }
+            }
+            PipelineNodeKind::Agent => {
+                let agent = definition.agents.get(&node.target)
+                    .ok_or_else(|| AriaError::not_found(&format!("Agent not found: {}", node.target)))?;
+                let result = self.runtime.execute_for_user(&task_from_input(&input), agent.to_runtime_config(), user_id).await?;
+                if result.success {
+                    Ok(agent_output(&result))
+                } else {
+                    Err(AriaError::new(
+                        ErrorCode::ExecutionError,
+                        ErrorCategory::Execution,
+                        ErrorSeverity::Medium,
+                        &result.error.unwrap_or_else(|| format!("Agent '{}' failed", node.target)),
+                    ))
+                }
+            }
+            PipelineNodeKind::Team => {
+                let result = self.runtime.execute_team(&node.target, &task_from_input(&input), user_id).await?;
+                if result.success {
+                    Ok(result.result.unwrap_or(Value::Null))
+                } else {
+                    Err(AriaError::new(
+                        ErrorCode::ExecutionError,
+                        ErrorCategory::Execution,
+                        ErrorSeverity::Medium,
+                        &result.error.unwrap_or_else(|| format!("Team '{}' failed", node.target)),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+async fn record_outcome(
+    pool: &sqlx::SqlitePool,
+    state: &mut NodeState,
+    status: AsyncTaskStatus,
+    output: Option<Value>,
+    error: Option<String>,
+) -> AriaResult<()> {
+    let exit_code = match status {
+        AsyncTaskStatus::Completed | AsyncTaskStatus::Skipped => 0,
+        _ => 1,
+    };
+    AsyncTaskOps::update_task_status(
+        pool,
+        &state.task_id,
+        status.clone(),
+        Some(exit_code),
+        output.as_ref().map(Value::to_string),
+        error.clone(),
+    ).await?;
+
+    state.status = status;
+    state.output = output;
+    state.error = error;
+    Ok(())
+}
+
+/// Create a pending node task under `run_id` for every node and a dependency row for every edge
+async fn plan_nodes(
+    pool: &sqlx::SqlitePool,
+    definition: &PipelineDefinition,
+    run_id: &str,
+    user_id: &str,
+    session_id: &str,
+) -> AriaResult<HashMap<String, NodeState>> {
+    let mut states = HashMap::new();
+    for node in &definition.nodes {
+        let task_id = AsyncTaskOps::create_subtask(
+            pool,
+            run_id,
+            user_id,
+            session_id,
+            PIPELINE_NODE_TASK_TYPE,
+            vec![node.id.clone(), node_kind_name(node.kind).to_string(), node.target.clone()],
+            node.timeout_seconds,
+        ).await?;
+        states.insert(node.id.clone(), NodeState {
+            task_id,
+            status: AsyncTaskStatus::Pending,
+            output: None,
+            error: None,
+        });
+    }
+    for edge in &definition.edges {
+        AsyncTaskOps::add_dependency(
+            pool,
+            &states[&edge.to].task_id,
+            &states[&edge.from].task_id,
+            edge_kind_name(edge.kind),
+        ).await?;
+    }
+    Ok(states)
+}
+
+fn run_command(run: &AsyncTaskRecord) -> AriaResult<(String, Value)> {
+    match run.command.as_slice() {
+        [name, input] => Ok((name.clone(), serde_json::from_str(input).unwrap_or(Value::Null))),
+        _ => Err(AriaError::new(
+            ErrorCode::DeserializationError,
+            ErrorCategory::System,
+            ErrorSeverity::Medium,
+            &format!("Pipeline run {} has a malformed command", run.task_id),
+        )),
+    }
+}
+
+fn node_kind_name(kind: PipelineNodeKind) -> &'static str {
+    match kind {
+        PipelineNodeKind::Tool => "tool",
+        PipelineNodeKind::Agent => "agent",
+        PipelineNodeKind::Team => "team",
+    }
+}
+
+fn edge_kind_name(kind: PipelineEdgeKind) -> &'static str {
+    match kind {
+        PipelineEdgeKind::Success => "success",
+        PipelineEdgeKind::Completion => "completion",
+    }
+}
+
+fn readiness(definition: &PipelineDefinition, node: &PipelineNodeManifest, states: &HashMap<String, NodeState>) -> Readiness {
+    let incoming: Vec<&PipelineEdgeManifest> = definition.incoming(&node.id).collect();
+    if incoming.iter().any(|edge| !states[&edge.from].status.is_terminal()) {
+        return Readiness::Waiting;
+    }
+
+    match incoming.iter().find(|edge| {
+        edge.kind == PipelineEdgeKind::Success && states[&edge.from].status != AsyncTaskStatus::Completed
+    }) {
+        Some(edge) => Readiness::Blocked(format!("upstream node '{}' did not complete", edge.from)),
+        None => Readiness::Ready,
+    }
+}
+
+/// `{"input": <pipeline input>, "nodes": {<id>: <output>}}`, the document
+/// conditions and input templates point into
+fn state_document(input: &Value, states: &HashMap<String, NodeState>) -> Value {
+    let nodes: serde_json::Map<String, Value> = states.iter()
+        .filter_map(|(id, state)| state.output.clone().map(|output| (id.clone(), output)))
+        .collect();
+    json!({"input": input, "nodes": nodes})
+}
+
+/// A node's input: its static input with templates resolved, plus the outputs
+/// of completed upstream nodes. A root node with no static input receives the
+/// pipeline input, and a node fed by a single unmapped edge receives that
+/// upstream output as-is.
+fn build_input(
+    definition: &PipelineDefinition,
+    node: &PipelineNodeManifest,
+    pipeline_input: &Value,
+    states: &HashMap<String, NodeState>,
+    document: &Value,
+) -> Value {
+    let incoming: Vec<&PipelineEdgeManifest> = definition.incoming(&node.id).collect();
+    let completed: Vec<&PipelineEdgeManifest> = incoming.iter()
+        .copied()
+        .filter(|edge| states[&edge.from].status == AsyncTaskStatus::Completed)
+        .collect();
+    let output_of = |edge: &PipelineEdgeManifest| states[&edge.from].output.clone().unwrap_or(Value::Null);
+
+    let mut input = resolve_templates(&node.input, document);
+    if input.is_null() {
+        if incoming.is_empty() {
+            return pipeline_input.clone();
+        }
+        if let [edge] = completed.as_slice() {
+            if edge.mapping.is_empty() {
+                return output_of(*edge);
+            }
+        }
+        input = Value::Object(serde_json::Map::new());
+    }
+
+    if let Value::Object(fields) = &mut input {
+        for edge in completed {
+            let output = output_of(edge);
+            if edge.mapping.is_empty() {
+                fields.insert(edge.from.clone(), output);
+            } else {
+                for (field, pointer) in &edge.mapping {
+                    fields.insert(field.clone(), output.pointer(pointer).cloned().unwrap_or(Value::Null));
+                }
+            }
+        }
+    }
+    input
+}
+
+/// Replace string values of the form `{{/json/pointer}}` with the value they
+/// point to in `document`
+fn resolve_templates(value: &Value, document: &Value) -> Value {
+    match value {
+        Value::String(text) => {
+            let trimmed = text.trim();
+            match trimmed.strip_prefix("{{").and_then(|rest| rest.strip_suffix("}}")) {
+                Some(pointer) if pointer.trim().starts_with('/') => {
+                    document.pointer(pointer.trim()).cloned().unwrap_or(Value::Null)
+                }
+                _ => value.clone(),
+            }
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|item| resolve_templates(item, document)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields.iter().map(|(key, item)| (key.clone(), resolve_templates(item, document))).collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn condition_holds(condition: &PipelineCondition, document: &Value) -> bool {
+    let value = document.pointer(&condition.path);
+
+    if let Some(exists) = condition.exists {
+        if value.is_some_and(|value| !value.is_null()) != exists {
+            return false;
+        }
+    }
+    if let Some(expected) = &condition.equals {
+        if value != Some(expected) {
+            return false;
+        }
+    }
+    if let Some(unexpected) = &condition.not_equals {
+        if value == Some(unexpected) {
+            return false;
+        }
+    }
+
+    if condition.exists.is_none() && condition.equals.is_none() && condition.not_equals.is_none() {
+        return value.is_some_and(is_truthy);
+    }
+    true
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(flag) => *flag,
+        Value::Number(number) => number.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(text) => !text.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(fields) => !fields.is_empty(),
+    }
+}
+
+/// The task text given to agent and team nodes: a `task` field when present,
+/// with any remaining fields attached as inputs
+fn task_from_input(input: &Value) -> String {
+    let pretty = |value: &Value| serde_json::to_string_pretty(value).unwrap_or_default();
+    match input {
+        Value::String(text) => text.clone(),
+        Value::Object(fields) => match fields.get("task").and_then(Value::as_str) {
+            Some(task) if fields.len() == 1 => task.to_string(),
+            Some(task) => {
+                let mut rest = fields.clone();
+                rest.remove("task");
+                format!("{}\n\nInputs:\n{}", task, pretty(&Value::Object(rest)))
+            }
+            None => pretty(input),
+        },
+        other => pretty(other),
+    }
+}
+
+fn run_result(definition: &PipelineDefinition, run_id: &str, states: &HashMap<String, NodeState>) -> PipelineRunResult {
+    let nodes: Vec<PipelineNodeResult> = definition.nodes.iter()
+        .map(|node| {
+            let state = &states[&node.id];
+            PipelineNodeResult {
+                node_id: node.id.clone(),
+                task_id: state.task_id.clone(),
+                status: state.status.clone(),
+                output: state.output.clone(),
+                error: state.error.clone(),
+            }
+        })
+        .collect();
+
+    let failures: Vec<String> = nodes.iter()
+        .filter(|node| matches!(node.status, AsyncTaskStatus::Failed | AsyncTaskStatus::Timeout | AsyncTaskStatus::Cancelled))
+        .map(|node| format!("{}: {}", node.node_id, node.error.as_deref().unwrap_or("failed")))
+        .collect();
+
+    let sink_outputs: Vec<(String, Value)> = definition.sinks().into_iter()
+        .filter_map(|node| states[&node.id].output.clone().map(|output| (node.id.clone(), output)))
+        .collect();
+    let output = match sink_outputs.len() {
+        0 => None,
+        1 => sink_outputs.into_iter().next().map(|(_, output)| output),
+        _ => Some(Value::Object(sink_outputs.into_iter().collect())),
+    };
+
+    PipelineRunResult {
+        run_id: run_id.to_string(),
+        pipeline: definition.name.clone(),
+        success: failures.is_empty(),
+        output,
+        error: (!failures.is_empty()).then(|| failures.join("; ")),
+        nodes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, kind: PipelineNodeKind, target: &str) -> PipelineNodeManifest {
+        PipelineNodeManifest {
+            id: id.to_string(),
+            kind,
+            target: target.to_string(),
+            input: Value::Null,
+            condition: None,
+            retries: 0,
+            timeout_seconds: None,
+        }
+    }
+
+    fn edge(from: &str, to: &str) -> PipelineEdgeManifest {
+        PipelineEdgeManifest {
+            from: from.to_string(),
+            to: to.to_string(),
+            kind: PipelineEdgeKind::Success,
+            mapping: HashMap::new(),
+        }
+    }
+
+    fn manifest(nodes: Vec<PipelineNodeManifest>, edges: Vec<PipelineEdgeManifest>) -> PipelineManifest {
+        PipelineManifest {
+            name: "report".to_string(),
+            description: String::new(),
+            nodes,
+            edges,
+        }
+    }
+
+    fn state(status: AsyncTaskStatus, output: Option<Value>) -> NodeState {
+        NodeState { task_id: "task".to_string(), status, output, error: None }
+    }
+
+    /// fetch fans out to summarize and classify, which fan back in to publish
+    fn diamond() -> PipelineDefinition {
+        let pipeline = manifest(
+            vec![
+                node("fetch", PipelineNodeKind::Tool, "readFileTool"),
+                node("summarize", PipelineNodeKind::Agent, "writer"),
+                node("classify", PipelineNodeKind::Tool, "classifyTool"),
+                node("publish", PipelineNodeKind::Tool, "writeFileTool"),
+            ],
+            vec![edge("fetch", "summarize"), edge("fetch", "classify"), edge("summarize", "publish"), edge("classify", "publish")],
+        );
+        let agents = vec![AgentManifest { name: "writer".to_string(), description: String::new(), tools: Vec::new() }];
+        PipelineDefinition::from_manifest(&pipeline, &agents).unwrap()
+    }
+
+    #[test]
+    fn test_definition_validation() {
+        let definition = diamond();
+        assert!(definition.agents.contains_key("writer"));
+        let order = definition.topological_order().unwrap();
+        assert_eq!(order.first(), Some(&0));
+        assert_eq!(order.last(), Some(&3));
+        assert_eq!(definition.sinks().len(), 1);
+
+        let cyclic = manifest(
+            vec![node("a", PipelineNodeKind::Tool, "t"), node("b", PipelineNodeKind::Tool, "t")],
+            vec![edge("a", "b"), edge("b", "a")],
+        );
+        assert!(PipelineDefinition::from_manifest(&cyclic, &[]).is_err());
+
+        let dangling = manifest(vec![node("a", PipelineNodeKind::Tool, "t")], vec![edge("a", "missing")]);
+        assert!(PipelineDefinition::from_manifest(&dangling, &[]).is_err());
+
+        let unknown_agent = manifest(vec![node("a", PipelineNodeKind::Agent, "ghost")], vec![]);
+        assert!(PipelineDefinition::from_manifest(&unknown_agent, &[]).is_err());
+
+        let mut bad_input = node("a", PipelineNodeKind::Tool, "t");
+        bad_input.input = json!("not an object");
+        assert!(PipelineDefinition::from_manifest(&manifest(vec![bad_input], vec![]), &[]).is_err());
+    }
+
+    #[test]
+    fn test_manifest_deserializes_with_defaults() {
+        let pipeline: PipelineManifest = serde_json::from_value(json!({
+            "name": "p",
+            "description": "",
+            "nodes": [{"id": "a", "kind": "tool", "target": "t", "retries": 2}],
+            "edges": [{"from": "a", "to": "a", "kind": "completion", "mapping": {"text": "/body"}}]
+        })).unwrap();
+        assert_eq!(pipeline.nodes[0].retries, 2);
+        assert_eq!(pipeline.edges[0].kind, PipelineEdgeKind::Completion);
+
+        let legacy: PipelineManifest = serde_json::from_value(json!({"name": "p", "description": ""})).unwrap();
+        assert!(legacy.nodes.is_empty());
+    }
+
+    #[test]
+    fn test_readiness_follows_edge_kinds() {
+        let definition = diamond();
+        let publish = &definition.nodes[3];
+        let mut states: HashMap<String, NodeState> = definition.nodes.iter()
+            .map(|node| (node.id.clone(), state(AsyncTaskStatus::Completed, Some(json!(1)))))
+            .collect();
+
+        states.get_mut("classify").unwrap().status = AsyncTaskStatus::Running;
+        assert!(matches!(readiness(&definition, publish, &states), Readiness::Waiting));
+
+        states.get_mut("classify").unwrap().status = AsyncTaskStatus::Failed;
+        assert!(matches!(readiness(&definition, publish, &states), Readiness::Blocked(_)));
+
+        let mut lenient = definition.clone();
+        lenient.edges[3].kind = PipelineEdgeKind::Completion;
+        assert!(matches!(readiness(&lenient, publish, &states), Readiness::Ready));
+    }
+
+    #[test]
+    fn test_build_input_maps_outputs() {
+        let mut definition = diamond();
+        let mut states: HashMap<String, NodeState> = HashMap::new();
+        states.insert("fetch".to_string(), state(AsyncTaskStatus::Completed, Some(json!({"body": "text", "size": 4}))));
+        states.insert("summarize".to_string(), state(AsyncTaskStatus::Completed, Some(json!("short"))));
+        states.insert("classify".to_string(), state(AsyncTaskStatus::Completed, Some(json!({"label": "news"}))));
+        states.insert("publish".to_string(), state(AsyncTaskStatus::Pending, None));
+        let input = json!({"path": "/tmp/in.txt"});
+        let document = state_document(&input, &states);
+
+        // Root nodes get the pipeline input; single unmapped edges pass output through
+        assert_eq!(build_input(&definition, &definition.nodes[0], &input, &states, &document), input);
+        assert_eq!(
+            build_input(&definition, &definition.nodes[1], &input, &states, &document),
+            json!({"body": "text", "size": 4}),
+        );
+
+        // Fan-in gathers outputs by source, or by mapping, next to templated static input
+        definition.edges[3].mapping.insert("label".to_string(), "/label".to_string());
+        definition.nodes[3].input = json!({"path": "{{/input/path}}", "mode": "append"});
+        assert_eq!(
+            build_input(&definition, &definition.nodes[3], &input, &states, &document),
+            json!({"path": "/tmp/in.txt", "mode": "append", "summarize": "short", "label": "news"}),
+        );
+    }
+
+    #[test]
+    fn test_conditions() {
+        let document = json!({"input": {"mode": "fast"}, "nodes": {"classify": {"label": "urgent", "score": 0}}});
+        let condition = |path: &str| PipelineCondition { path: path.to_string(), equals: None, not_equals: None, exists: None };
+
+        assert!(condition_holds(&PipelineCondition { equals: Some(json!("urgent")), ..condition("/nodes/classify/label") }, &document));
+        assert!(!condition_holds(&PipelineCondition { not_equals: Some(json!("fast")), ..condition("/input/mode") }, &document));
+        assert!(condition_holds(&PipelineCondition { exists: Some(false), ..condition("/nodes/missing") }, &document));
+        assert!(!condition_holds(&condition("/nodes/classify/score"), &document));
+        assert!(condition_holds(&condition("/input/mode"), &document));
+    }
+
+    #[test]
+    fn test_task_from_input() {
+        assert_eq!(task_from_input(&json!("plain")), "plain");
+        assert_eq!(task_from_input(&json!({"task": "summarize"})), "summarize");
+        let task = task_from_input(&json!({"task": "summarize", "text": "body"}));
+        assert!(task.starts_with("summarize\n\nInputs:"));
+        assert!(task.contains("\"text\": \"body\""));
+    }
+
+    #[test]
+    fn test_run_result_reports_failures_and_sinks() {
+        let definition = diamond();
+        let mut states: HashMap<String, NodeState> = definition.nodes.iter()
+            .map(|node| (node.id.clone(), state(AsyncTaskStatus::Completed, Some(json!(node.id)))))
+            .collect();
+
+        let result = run_result(&definition, "run", &states);
+        assert!(result.success);
+        assert_eq!(result.output, Some(json!("publish")));
+
+        let classify = states.get_mut("classify").unwrap();
+        classify.status = AsyncTaskStatus::Timeout;
+        classify.error = Some("timed out after 5s".to_string());
+        let publish = states.get_mut("publish").unwrap();
+        publish.status = AsyncTaskStatus::Skipped;
+        publish.output = None;
+
+        let result = run_result(&definition, "run", &states);
+        assert!(!result.success);
+        assert_eq!(result.output, None);
+        assert_eq!(result.error.as_deref(), Some("classify: timed out after 5s"));
+    }
+}
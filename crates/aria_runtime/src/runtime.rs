@@ -6,8 +6,9 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 use tokio::sync::RwLock;
 use crate::deep_size::DeepUuid;
+use crate::costs::CostAttribution;
 use std::path::PathBuf;
-use tracing::{debug, info, warn};
+use tracing::{debug, info, info_span, warn, Instrument};
 
 use crate::errors::{AriaError, ErrorCode, ErrorCategory, ErrorSeverity};
 use crate::engines::{AriaEngines, ExecutionEngineInterface, PlanningEngineInterface, ConversationEngineInterface, ReflectionEngineInterface, ContextManagerInterface};
@@ -23,6 +24,9 @@ use crate::bundle_discovery::BundleToolDiscovery;
 use crate::bundle_executor::{BundleExecutor, BundleExecutionResult, BundleExecutionConfig};
 use crate::engines::tool_registry::bundle_integration::BundleToolRegistry;
 use crate::tools::management::custom_tools::CustomToolManager;
+use crate::teams::{Team, TeamRegistry, TeamResult};
+use crate::pipelines::{PipelineExecutor, PipelineRegistry, PipelineRunResult};
+use crate::memory::{MemoryConfig, MemorySystem, RECALLED_MEMORY_PREFIX};
 
 /// Main Aria Runtime orchestrator - preserves Symphony's cognitive architecture
 /// while adding container orchestration capabilities
@@ -37,6 +41,12 @@ pub struct AriaRuntime {
     
     // TODO: Implement full session management with persistence and cleanup
     pub active_sessions: Arc<RwLock<HashMap<uuid::Uuid, RuntimeContext>>>,
+
+    /// Teams available to `execute_team`, including those declared in bundles
+    pub teams: Arc<TeamRegistry>,
+
+    /// Pipelines available to `run_pipeline`, including those declared in bundles
+    pub pipelines: Arc<PipelineRegistry>,
 }
 
 impl AriaRuntime {
@@ -49,6 +59,8 @@ impl AriaRuntime {
             status: Arc::new(RwLock::new(RuntimeStatus::Ready)),
             metrics: Arc::new(RwLock::new(Self::create_initial_metrics())),
             active_sessions: Arc::new(RwLock::new(HashMap::new())),
+            teams: Arc::new(TeamRegistry::new()),
+            pipelines: Arc::new(PipelineRegistry::new()),
         })
     }
 
@@ -60,24 +72,67 @@ impl AriaRuntime {
             status: Arc::new(RwLock::new(RuntimeStatus::Ready)),
             metrics: Arc::new(RwLock::new(Self::create_initial_metrics())),
             active_sessions: Arc::new(RwLock::new(HashMap::new())),
+            teams: Arc::new(TeamRegistry::new()),
+            pipelines: Arc::new(PipelineRegistry::new()),
         }
     }
 
     /// Executes a task based on the provided configuration and context.
+    /// Without a user there is nobody to keep memories for, so agent memory stays off.
     pub async fn execute(
         &self,
         task: &str,
         agent_config: AgentConfig,
+    ) -> AriaResult<RuntimeResult> {
+        self.execute_as(task, agent_config, None).await
+    }
+
+    /// Executes a task on behalf of `user_id`, whose database holds the agent's memories.
+    pub async fn execute_for_user(
+        &self,
+        task: &str,
+        agent_config: AgentConfig,
+        user_id: &str,
+    ) -> AriaResult<RuntimeResult> {
+        self.execute_as(task, agent_config, Some(user_id)).await
+    }
+
+    /// Executes a task for its owner, if it has one
+    #[tracing::instrument(
+        name = "aria.execute",
+        skip_all,
+        fields(agent = %agent_config.name, user_id = user_id.unwrap_or_default(), session_id = tracing::field::Empty)
+    )]
+    pub async fn execute_as(
+        &self,
+        task: &str,
+        agent_config: AgentConfig,
+        user_id: Option<&str>,
     ) -> AriaResult<RuntimeResult> {
         println!("🔍 DEBUG: AriaRuntime::execute called");
         println!("🔍 DEBUG: Task: {}", task);
         println!("🔍 DEBUG: Agent: {}", agent_config.name);
         
         let session_id = Uuid::new_v4();
+        tracing::Span::current().record("session_id", tracing::field::display(session_id));
         println!("🔍 DEBUG: Created session ID: {}", session_id);
         
+        let memory = match (agent_config.memory_enabled.unwrap_or(false), user_id) {
+            (true, Some(user_id)) => self.open_memory(user_id, &agent_config.name).await,
+            (true, None) => {
+                warn!("Agent '{}' runs without a user; its memory is disabled", agent_config.name);
+                None
+            }
+            (false, _) => None,
+        };
+
         let mut context = self.create_runtime_context(agent_config, session_id);
+        context.user_id = user_id.map(str::to_string);
         println!("🔍 DEBUG: Created runtime context");
+
+        if let Some(memory) = &memory {
+            self.recall_into_context(memory, task, &context).await;
+        }
         
         // Track session
         {
@@ -92,9 +147,19 @@ impl AriaRuntime {
             .as_millis() as u64;
 
         println!("🔍 DEBUG: Calling execute_with_context...");
-        let result = self.execute_with_context(task, &mut context).await;
+        let attribution = CostAttribution {
+            user_id: user_id.map(str::to_string),
+            session_id: Some(session_id.to_string()),
+            agent: Some(context.agent_config.name.clone()),
+            ..Default::default()
+        };
+        let result = self.engines.costs.scoped(attribution, self.execute_with_context(task, &mut context)).await;
         println!("🔍 DEBUG: execute_with_context returned");
 
+        if let (Some(memory), Ok(runtime_result)) = (&memory, &result) {
+            self.remember_execution(memory, task, runtime_result).await;
+        }
+
         // Clean up session
         {
             let mut sessions = self.active_sessions.write().await;
@@ -109,6 +174,67 @@ impl AriaRuntime {
         result
     }
 
+    /// Open the agent's memory in the user's database; memory is best-effort and
+    /// never fails the execution
+    async fn open_memory(&self, user_id: &str, agent_name: &str) -> Option<MemorySystem> {
+        match self.engines.database.get_user_database(user_id).await {
+            Ok(pool) => Some(
+                MemorySystem::new(MemoryConfig::default(), pool, agent_name)
+                    .with_llm(self.engines.llm_handler.clone())
+            ),
+            Err(e) => {
+                warn!("Agent memory unavailable for user {}: {}", user_id, e);
+                None
+            }
+        }
+    }
+
+    /// Load memories relevant to the task into working memory for prompt construction
+    async fn recall_into_context(&self, memory: &MemorySystem, task: &str, context: &RuntimeContext) {
+        match memory.recall(task, memory.config().recall_limit).await {
+            Ok(recalled) => {
+                let mut working_memory = context.working_memory.write().await;
+                for recalled in recalled {
+                    if let Ok(value) = serde_json::to_value(&recalled.entry) {
+                        working_memory.insert(
+                            format!("{}{}", RECALLED_MEMORY_PREFIX, recalled.entry.key),
+                            crate::deep_size::DeepValue(value),
+                        );
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to recall agent memories: {}", e),
+        }
+    }
+
+    /// Keep the outcome as a short-term memory and fold the conversation into long-term memory
+    async fn remember_execution(&self, memory: &MemorySystem, task: &str, result: &RuntimeResult) {
+        if let Some(conversation) = &result.conversation {
+            let outcome = serde_json::json!({
+                "task": task,
+                "success": result.success,
+                "response": conversation.final_response,
+            });
+            if let Err(e) = memory.store(
+                &format!("task:{}", conversation.id.0),
+                crate::deep_size::DeepValue(outcome),
+                "short_term",
+                MemoryEntryType::Evidence,
+                vec!["task_outcome".to_string()],
+            ).await {
+                warn!("Failed to store task outcome memory: {}", e);
+            }
+
+            if let Err(e) = memory.summarize_conversation(conversation, 0).await {
+                warn!("Failed to summarize conversation into memory: {}", e);
+            }
+        }
+
+        if let Err(e) = memory.enforce_limits().await {
+            warn!("Failed to enforce memory limits: {}", e);
+        }
+    }
+
     /// Initialize the runtime and all engines
     pub async fn initialize(&self) -> AriaResult<()> {
         *self.status.write().await = RuntimeStatus::Initializing;
@@ -146,6 +272,7 @@ impl AriaRuntime {
         // Phase 2: Task Analysis & Planning
         let task_analysis = self.engines.planning
             .analyze_task(task, context)
+            .instrument(info_span!("aria.plan.analyze"))
             .await?;
 
         let execution_mode = self.determine_execution_mode(&task_analysis, &context.agent_config);
@@ -190,6 +317,7 @@ impl AriaRuntime {
         // Create execution plan
         let plan = self.engines.planning
             .create_execution_plan(task, &context.agent_config, context)
+            .instrument(info_span!("aria.plan.create"))
             .await?;
 
         self.engines.context_manager.set_plan(plan.clone()).await?;
@@ -443,6 +571,7 @@ impl AriaRuntime {
 
         RuntimeContext {
             session_id: crate::deep_size::DeepUuid(session_id),
+            user_id: None,
             agent_config,
             created_at: now,
             conversation: None,
@@ -745,13 +874,63 @@ impl AriaRuntime {
             self.engines.pkg_store.clone(),
             bundle_registry,
             discovery,
-        );
+        )
+        .with_team_registry(self.teams.clone())
+        .with_pipeline_registry(self.pipelines.clone());
 
         // Execute bundle with provided or default configuration
         let execution_config = config.unwrap_or_default();
         executor.execute_bundle(bundle_hash, session_id, Some(execution_config)).await
     }
 
+    /// Register the teams declared in a bundle
+    pub async fn register_teams_from_bundle(&self, bundle_hash: &str) -> AriaResult<Vec<String>> {
+        info!("Registering teams from bundle: {}", bundle_hash);
+
+        let discovery = BundleToolDiscovery::new(self.engines.pkg_store.clone());
+        let manifest = discovery.load_bundle_manifest(bundle_hash).await?;
+        let registered = self.teams.register_manifest(&manifest).await;
+
+        info!("Registered {} teams from bundle '{}'", registered.len(), bundle_hash);
+        Ok(registered)
+    }
+
+    /// Run a registered team on a task for `user_id`, whose memory its members use
+    pub async fn execute_team(&self, team_name: &str, task: &str, user_id: &str) -> AriaResult<TeamResult> {
+        let config = self.teams.get(team_name).await.ok_or_else(|| {
+            AriaError::not_found(&format!("Team not found: {}", team_name))
+        })?;
+
+        Team::new(config, self.clone()).with_owner(user_id).run(task).await
+    }
+
+    /// Register the pipelines declared in a bundle
+    pub async fn register_pipelines_from_bundle(&self, bundle_hash: &str) -> AriaResult<Vec<String>> {
+        info!("Registering pipelines from bundle: {}", bundle_hash);
+
+        let discovery = BundleToolDiscovery::new(self.engines.pkg_store.clone());
+        let manifest = discovery.load_bundle_manifest(bundle_hash).await?;
+        let registered = self.pipelines.register_manifest(&manifest).await;
+
+        info!("Registered {} pipelines from bundle '{}'", registered.len(), bundle_hash);
+        Ok(registered)
+    }
+
+    /// Run a registered pipeline, persisting its progress in the user's task tables
+    pub async fn run_pipeline(&self, pipeline_name: &str, input: serde_json::Value, user_id: &str) -> AriaResult<PipelineRunResult> {
+        PipelineExecutor::new(self.clone()).start(pipeline_name, input, user_id).await
+    }
+
+    /// Continue a pipeline run that was interrupted before it finished
+    pub async fn resume_pipeline(&self, run_id: &str, user_id: &str) -> AriaResult<PipelineRunResult> {
+        PipelineExecutor::new(self.clone()).resume(run_id, user_id).await
+    }
+
+    /// Continue every unfinished pipeline run belonging to `user_id`
+    pub async fn resume_unfinished_pipelines(&self, user_id: &str) -> AriaResult<Vec<PipelineRunResult>> {
+        PipelineExecutor::new(self.clone()).resume_unfinished(user_id).await
+    }
+
     /// Discover a tool in available bundles
     pub async fn discover_tool_in_bundles(&self, tool_name: &str) -> AriaResult<Option<String>> {
         debug!("Discovering tool in bundles: {}", tool_name);
@@ -0,0 +1,402 @@
+/*!
+# Scheduler
+
+Launches tasks on cron or interval schedules. Schedules live in their owner's
+database next to the tasks they launch; every fire submits an ordinary task
+to the `TaskRunner`, so scheduled work is listed, streamed and cancelled like
+any other task.
+
+Fires are computed from the stored `next_run_at`, so fires missed while aria
+was down are found on the first tick after a restart and handled by the
+schedule's catch-up policy. A fire whose previous task is still unfinished is
+handled by the schedule's overlap policy.
+*/
+
+use crate::clock::unix_now;
+use crate::database::async_tasks::{AsyncTaskOps, NewAsyncTask};
+use crate::database::schedules::{CatchUpPolicy, OverlapPolicy, ScheduleOps, ScheduleRecord};
+use crate::database::tenancy::{TenantOps, TenantResource};
+use crate::database::DatabaseManager;
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::task_runner::{TaskRunner, DEPENDS_ON_COMPLETION};
+use chrono::{TimeZone, Utc};
+use chrono_tz::Tz;
+use std::collections::{HashSet, VecDeque};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// How often due schedules are looked for
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+/// How late, beyond its jitter, a fire may start and still count as on time
+const MISSED_GRACE_SECONDS: u64 = 60;
+/// Most missed fires launched at once by the run-all catch-up policy
+const MAX_CATCH_UP_RUNS: usize = 100;
+
+/// When a schedule fires
+#[derive(Debug, Clone)]
+pub enum Cadence {
+    /// A cron expression read in a timezone
+    Cron(Box<cron::Schedule>, Tz),
+    /// A fixed number of seconds between fires
+    Interval(u64),
+}
+
+impl Cadence {
+    /// Parse a cadence from exactly one of a cron expression and an interval.
+    /// Five-field cron expressions are minute first; a seconds field of 0 is added.
+    pub fn parse(cron_expression: Option<&str>, interval_seconds: Option<u64>, timezone: &str) -> AriaResult<Self> {
+        match (cron_expression, interval_seconds) {
+            (Some(expression), None) => {
+                let timezone = if timezone.is_empty() { "UTC" } else { timezone };
+                let tz = Tz::from_str(timezone)
+                    .map_err(|e| invalid(format!("Unknown timezone '{}': {}", timezone, e)))?;
+
+                let expression = expression.trim();
+                let expression = if expression.split_whitespace().count() == 5 {
+                    format!("0 {}", expression)
+                } else {
+                    expression.to_string()
+                };
+                let schedule = cron::Schedule::from_str(&expression)
+                    .map_err(|e| invalid(format!("Invalid cron expression '{}': {}", expression, e)))?;
+                Ok(Cadence::Cron(Box::new(schedule), tz))
+            }
+            (None, Some(0)) => Err(invalid("Interval must be at least one second".to_string())),
+            (None, Some(seconds)) => Ok(Cadence::Interval(seconds)),
+            _ => Err(invalid("Schedules need either a cron expression or an interval".to_string())),
+        }
+    }
+
+    /// The cadence of a stored schedule
+    pub fn of(schedule: &ScheduleRecord) -> AriaResult<Self> {
+        Self::parse(schedule.cron_expression.as_deref(), schedule.interval_seconds, &schedule.timezone)
+    }
+
+    /// The first fire strictly after `after`, in unix seconds; `None` if there is none
+    pub fn next_after(&self, after: u64) -> Option<u64> {
+        match self {
+            Cadence::Cron(schedule, tz) => {
+                let after = Utc.timestamp_opt(after as i64, 0).single()?.with_timezone(tz);
+                schedule.after(&after).next().map(|at| at.timestamp().max(0) as u64)
+            }
+            Cadence::Interval(seconds) => after.checked_add(*seconds),
+        }
+    }
+}
+
+/// Delay of a fire, up to `jitter_seconds`; the same every time for the same fire
+fn jitter(schedule_id: &str, nominal: u64, jitter_seconds: u64) -> u64 {
+    if jitter_seconds == 0 {
+        return 0;
+    }
+    let hash = blake3::hash(format!("{}:{}", schedule_id, nominal).as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash.as_bytes()[..8]);
+    u64::from_le_bytes(bytes) % (jitter_seconds + 1)
+}
+
+/// Fires from `first` up to `now`, oldest first, and the fire after them.
+/// Only the newest `MAX_CATCH_UP_RUNS` are returned, so the latest, possibly
+/// on-time fire is always among them; older ones are passed over.
+fn due_fires(cadence: &Cadence, first: u64, now: u64) -> (Vec<u64>, Option<u64>) {
+    // Intervals skip straight to the newest fires instead of walking a long outage
+    let start = match cadence {
+        Cadence::Interval(seconds) if first <= now => {
+            let due = (now - first) / seconds + 1;
+            first + due.saturating_sub(MAX_CATCH_UP_RUNS as u64) * seconds
+        }
+        _ => first,
+    };
+
+    let mut fires = VecDeque::with_capacity(MAX_CATCH_UP_RUNS);
+    let mut next = Some(start);
+    while let Some(at) = next.filter(|at| *at <= now) {
+        if fires.len() == MAX_CATCH_UP_RUNS {
+            fires.pop_front();
+        }
+        fires.push_back(at);
+        next = cadence.next_after(at);
+    }
+    (fires.into(), next)
+}
+
+/// How many of the due `fires` launch a task. The newest fire is on time if it is
+/// at most `grace` seconds old; older ones were missed and follow the catch-up policy.
+fn fires_to_run(fires: &[u64], now: u64, grace: u64, policy: CatchUpPolicy) -> usize {
+    let Some(newest) = fires.last() else {
+        return 0;
+    };
+    let on_time = now.saturating_sub(*newest) <= grace;
+    match policy {
+        CatchUpPolicy::Skip => usize::from(on_time),
+        CatchUpPolicy::RunOnce => 1,
+        CatchUpPolicy::RunAll => fires.len(),
+    }
+}
+
+/// The task a schedule launches on every fire
+pub fn template(schedule: &ScheduleRecord) -> NewAsyncTask {
+    NewAsyncTask {
+        user_id: schedule.user_id.clone(),
+        session_id: schedule.session_id.clone().unwrap_or_default(),
+        task_type: schedule.task_type.clone(),
+        command: schedule.command.clone(),
+        environment: schedule.environment.clone(),
+        container_id: schedule.container_id.clone(),
+        parent_task_id: None,
+        priority: schedule.priority,
+        timeout_seconds: (schedule.timeout_seconds > 0).then_some(schedule.timeout_seconds),
+    }
+}
+
+/// Fires due schedules of every user and keeps schedules consistent with their owners
+pub struct Scheduler {
+    database: Arc<DatabaseManager>,
+    runner: Arc<TaskRunner>,
+}
+
+impl Scheduler {
+    pub fn new(database: Arc<DatabaseManager>, runner: Arc<TaskRunner>) -> Self {
+        Self { database, runner }
+    }
+
+    /// Start firing due schedules in the background
+    pub fn start(self: &Arc<Self>) {
+        let scheduler = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = scheduler.tick().await {
+                    warn!("Failed to run due schedules: {}", e);
+                }
+                tokio::time::sleep(TICK_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Validate and store a schedule, creating it if it has no ID yet.
+    /// Its next fire is computed again from now. Returns the stored schedule.
+    pub async fn save(&self, mut schedule: ScheduleRecord) -> AriaResult<ScheduleRecord> {
+        let cadence = Cadence::of(&schedule)?;
+        self.runner.validate_task(&template(&schedule)).await?;
+
+        let now = unix_now();
+        let created = schedule.schedule_id.is_empty();
+        if created {
+            schedule.schedule_id = uuid::Uuid::new_v4().to_string();
+            schedule.created_at = now;
+        }
+        schedule.updated_at = now;
+        schedule.next_run_at = if schedule.enabled { cadence.next_after(now) } else { None };
+
+        let pool = self.database.get_user_database(&schedule.user_id).await?;
+        ScheduleOps::save_schedule(&pool, &schedule).await?;
+        if created {
+            let system_pool = self.database.get_system_database().await?;
+            TenantOps::record_resource(
+                &system_pool,
+                TenantResource::Schedule,
+                &schedule.schedule_id,
+                &schedule.user_id,
+                schedule.session_id.as_deref(),
+                schedule.container_id.as_deref(),
+            ).await?;
+        }
+
+        info!("Saved schedule {} ({}) for user {}, next run at {:?}",
+              schedule.schedule_id, schedule.name, schedule.user_id, schedule.next_run_at);
+        ScheduleOps::get_schedule(&pool, &schedule.schedule_id).await?
+            .ok_or_else(|| invalid(format!("Schedule not found: {}", schedule.schedule_id)))
+    }
+
+    /// Delete a schedule of `user_id`. Returns false if it did not exist.
+    pub async fn delete(&self, user_id: &str, schedule_id: &str) -> AriaResult<bool> {
+        let pool = self.database.get_user_database(user_id).await?;
+        let deleted = ScheduleOps::delete_schedule(&pool, schedule_id).await?;
+
+        let system_pool = self.database.get_system_database().await?;
+        TenantOps::remove_resource(&system_pool, TenantResource::Schedule, schedule_id).await?;
+        Ok(deleted)
+    }
+
+    async fn tick(&self) -> AriaResult<()> {
+        let system_pool = self.database.get_system_database().await?;
+        let owners: HashSet<String> = TenantOps::list_resources(&system_pool, TenantResource::Schedule, None).await?
+            .into_iter()
+            .map(|record| record.user_id)
+            .collect();
+
+        let now = unix_now();
+        for user_id in owners {
+            if let Err(e) = self.run_due(&user_id, now).await {
+                warn!("Failed to run due schedules of user {}: {}", user_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fire the schedules of `user_id` that are due at `now`, jitter included
+    async fn run_due(&self, user_id: &str, now: u64) -> AriaResult<()> {
+        let pool = self.database.get_user_database(user_id).await?;
+        for schedule in ScheduleOps::get_due_schedules(&pool, now).await? {
+            let Some(first) = schedule.next_run_at else {
+                continue;
+            };
+            if now < first + jitter(&schedule.schedule_id, first, schedule.jitter_seconds) {
+                continue;
+            }
+
+            let cadence = match Cadence::of(&schedule) {
+                Ok(cadence) => cadence,
+                Err(e) => {
+                    warn!("Stopping schedule {}: {}", schedule.schedule_id, e);
+                    let stopped = ScheduleRecord { next_run_at: None, ..schedule };
+                    ScheduleOps::save_schedule(&pool, &stopped).await?;
+                    continue;
+                }
+            };
+
+            let (fires, next_run_at) = due_fires(&cadence, first, now);
+            let runs = fires_to_run(&fires, now, MISSED_GRACE_SECONDS + schedule.jitter_seconds, schedule.catch_up_policy);
+            if fires.len() > runs {
+                info!("Schedule {} passed over {} missed runs", schedule.schedule_id, fires.len() - runs);
+            }
+
+            // Missed runs launched together wait for each other rather than overlap
+            let mut previous = schedule.last_task_id.clone();
+            let mut launched = None;
+            for run in 0..runs {
+                let policy = if run == 0 { schedule.overlap_policy } else { OverlapPolicy::Queue };
+                match self.fire(&pool, &schedule, previous.as_deref(), policy).await {
+                    Ok(Some(task_id)) => {
+                        previous = Some(task_id.clone());
+                        launched = Some(task_id);
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!("Schedule {} failed to launch its task: {}", schedule.schedule_id, e);
+                        break;
+                    }
+                }
+            }
+
+            ScheduleOps::record_fire(&pool, &schedule.schedule_id, now, launched.as_deref(), next_run_at).await?;
+        }
+        Ok(())
+    }
+
+    /// Launch the schedule's task, unless the overlap policy says otherwise while
+    /// `previous` is unfinished. Returns the launched task's ID.
+    async fn fire(
+        &self,
+        pool: &sqlx::SqlitePool,
+        schedule: &ScheduleRecord,
+        previous: Option<&str>,
+        policy: OverlapPolicy,
+    ) -> AriaResult<Option<String>> {
+        let mut depends_on = Vec::new();
+        if let Some(previous) = previous {
+            let unfinished = AsyncTaskOps::get_task(pool, previous).await
+                .is_ok_and(|task| !task.status.is_terminal());
+            if unfinished {
+                match policy {
+                    OverlapPolicy::Skip => {
+                        debug!("Schedule {} skipped a run: task {} is unfinished", schedule.schedule_id, previous);
+                        return Ok(None);
+                    }
+                    OverlapPolicy::Queue => {
+                        depends_on.push((previous.to_string(), DEPENDS_ON_COMPLETION.to_string()));
+                    }
+                    OverlapPolicy::CancelPrevious => {
+                        let reason = format!("superseded by the next run of schedule {}", schedule.schedule_id);
+                        self.runner.cancel(&schedule.user_id, previous, &reason).await?;
+                    }
+                }
+            }
+        }
+
+        let task_id = self.runner.submit(template(schedule), depends_on).await?;
+        debug!("Schedule {} launched task {}", schedule.schedule_id, task_id);
+        Ok(Some(task_id))
+    }
+}
+
+fn invalid(message: String) -> AriaError {
+    AriaError::new(
+        ErrorCode::ScheduleValidationError,
+        ErrorCategory::Execution,
+        ErrorSeverity::Low,
+        &message,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> u64 {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap().timestamp() as u64
+    }
+
+    #[test]
+    fn cadence_requires_exactly_one_valid_form() {
+        assert!(Cadence::parse(None, None, "").is_err());
+        assert!(Cadence::parse(Some("* * * * *"), Some(60), "").is_err());
+        assert!(Cadence::parse(None, Some(0), "").is_err());
+        assert!(Cadence::parse(Some("not a cron"), None, "").is_err());
+        assert!(Cadence::parse(Some("* * * * *"), None, "Mars/Olympus").is_err());
+        assert!(Cadence::parse(Some("30 * * * * *"), None, "").is_ok());
+    }
+
+    #[test]
+    fn cadence_next_follows_interval_and_timezone() {
+        let interval = Cadence::parse(None, Some(90), "").unwrap();
+        assert_eq!(interval.next_after(1_000), Some(1_090));
+
+        // 09:00 on weekdays in Berlin is 08:00 UTC in winter; Friday noon moves to Monday
+        let cron = Cadence::parse(Some("0 9 * * MON-FRI"), None, "Europe/Berlin").unwrap();
+        assert_eq!(cron.next_after(at(2024, 1, 5, 12, 0)), Some(at(2024, 1, 8, 8, 0)));
+        assert_eq!(cron.next_after(at(2024, 1, 8, 7, 0)), Some(at(2024, 1, 8, 8, 0)));
+    }
+
+    #[test]
+    fn due_fires_stop_at_now_and_cap_catch_up() {
+        let cadence = Cadence::Interval(10);
+        assert_eq!(due_fires(&cadence, 100, 135), (vec![100, 110, 120, 130], Some(140)));
+        assert_eq!(due_fires(&cadence, 100, 99), (vec![], Some(100)));
+
+        let (fires, next) = due_fires(&Cadence::Interval(1), 0, 1_000);
+        assert_eq!(fires.len(), MAX_CATCH_UP_RUNS);
+        assert_eq!((fires[0], fires.last().copied()), (901, Some(1_000)));
+        assert_eq!(next, Some(1_001));
+
+        // The on-time fire survives the cap, so it still runs under every policy
+        let (fires, _) = due_fires(&Cadence::Interval(10), 5, 10_000);
+        assert_eq!(fires.last(), Some(&9_995));
+        assert_eq!(fires_to_run(&fires, 10_000, 60, CatchUpPolicy::Skip), 1);
+
+        let hourly = Cadence::parse(Some("0 * * * *"), None, "UTC").unwrap();
+        let (fires, next) = due_fires(&hourly, 0, 200 * 3600 + 30);
+        assert_eq!(fires.len(), MAX_CATCH_UP_RUNS);
+        assert_eq!(fires.last(), Some(&(200 * 3600)));
+        assert_eq!(next, Some(201 * 3600));
+    }
+
+    #[test]
+    fn catch_up_policy_decides_missed_runs() {
+        let missed = [100, 110, 120];
+        assert_eq!(fires_to_run(&missed, 1_000, 60, CatchUpPolicy::Skip), 0);
+        assert_eq!(fires_to_run(&missed, 1_000, 60, CatchUpPolicy::RunOnce), 1);
+        assert_eq!(fires_to_run(&missed, 1_000, 60, CatchUpPolicy::RunAll), 3);
+        assert_eq!(fires_to_run(&[990], 1_000, 60, CatchUpPolicy::Skip), 1);
+        assert_eq!(fires_to_run(&[], 1_000, 60, CatchUpPolicy::RunAll), 0);
+    }
+
+    #[test]
+    fn jitter_is_stable_and_bounded() {
+        assert_eq!(jitter("schedule", 100, 0), 0);
+        assert_eq!(jitter("schedule", 100, 30), jitter("schedule", 100, 30));
+        assert!((0..50).all(|fire| jitter("schedule", fire, 30) <= 30));
+    }
+}
@@ -0,0 +1,694 @@
+/*!
+# Task Runner
+
+Queues and runs the general tasks launched through the TaskService: container
+commands, agent prompts, bundle executions and pipeline runs. Each task is an
+`async_tasks` row in its owner's database, which stays the single record of
+its status and results; the runner only keeps what is queued and what is
+running in memory, and rebuilds that from the rows after a restart.
+
+Queued tasks are dispatched highest priority first, up to a concurrency
+limit, once the tasks they depend on allow it. A task that repeatedly cannot
+be dispatched is retried with backoff and then failed, which releases the
+tasks waiting on it. Cancelling a task cancels the
+tasks created under it. Attached `TaskNotifier`s are told about every task
+that ends, however it ended.
+*/
+
+use crate::agents::load_stored_agent;
+use crate::bundle_executor::BundleExecutionConfig;
+use crate::costs::CostAttribution;
+use crate::database::async_tasks::{AsyncTaskOps, AsyncTaskRecord, AsyncTaskStatus, NewAsyncTask, TaskOutcome};
+use crate::database::conversations::ConversationOps;
+use crate::database::sessions::SessionOps;
+use crate::database::tenancy::{TenantOps, TenantResource};
+use crate::deep_size::DeepUuid;
+use crate::engines::container::quilt::quilt_proto;
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::pipelines::{PipelineExecutor, PIPELINE_TASK_TYPE};
+use crate::runtime::AriaRuntime;
+use crate::teams::agent_output;
+use futures::future::BoxFuture;
+use serde_json::Value;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, RwLock};
+use tokio::task::AbortHandle;
+use tracing::{debug, info, warn};
+
+/// `async_tasks.task_type` of a command run in a container
+pub const CONTAINER_TASK_TYPE: &str = "container:exec";
+/// `async_tasks.task_type` of a prompt run by an agent; command `[prompt]` or `[prompt, agent_config_id]`
+pub const AGENT_TASK_TYPE: &str = "agent";
+/// `async_tasks.task_type` of a bundle execution; command `[bundle_hash]`
+pub const BUNDLE_TASK_TYPE: &str = "bundle";
+
+/// Container event recorded when a container task's command ends, with its
+/// `user_id`, `task_id`, `status` and `exit_code` as metadata
+pub const EXEC_FINISHED_EVENT: &str = "exec_finished";
+
+/// `task_dependencies.dependency_type` that waits for the dependency to complete successfully
+pub const DEPENDS_ON_SUCCESS: &str = "success";
+/// `task_dependencies.dependency_type` that waits for the dependency to end in any way
+pub const DEPENDS_ON_COMPLETION: &str = "completion";
+
+/// Tasks run at once when no limit is configured
+const DEFAULT_MAX_CONCURRENT_TASKS: usize = 4;
+/// How often quilt is asked about a running container task
+const CONTAINER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the queue is re-examined when nothing wakes the runner
+const DISPATCH_INTERVAL: Duration = Duration::from_secs(5);
+/// Failed dispatch attempts after which a task is failed instead of retried
+const MAX_DISPATCH_ATTEMPTS: u32 = 5;
+
+/// A task waiting to be dispatched
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct QueuedTask {
+    priority: i32,
+    /// Submission order, so equal priorities run first come, first served
+    seq: u64,
+    task_id: String,
+    user_id: String,
+    /// Dispatch attempts that have failed so far
+    failed_dispatches: u32,
+    /// No dispatch is attempted before this, after a failed one
+    retry_at: Option<Instant>,
+}
+
+impl Ord for QueuedTask {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.priority, Reverse(self.seq)).cmp(&(other.priority, Reverse(other.seq)))
+    }
+}
+
+impl PartialOrd for QueuedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Wait before retrying a task whose dispatch has failed `failures` times, doubling each time
+fn dispatch_backoff(failures: u32) -> Duration {
+    DISPATCH_INTERVAL.saturating_mul(1 << failures.saturating_sub(1).min(10))
+}
+
+/// Whether a task's dependencies let it run
+#[derive(Debug, Clone, PartialEq)]
+enum Gate {
+    Ready,
+    Waiting,
+    /// A dependency ended in a way the task cannot run after
+    Blocked(String),
+}
+
+/// Decide a task's gate from `(dependency_type, dependency_task_id, dependency_status)` entries
+fn gate(dependencies: &[(String, String, AsyncTaskStatus)]) -> Gate {
+    let mut waiting = false;
+    for (dependency_type, task_id, status) in dependencies {
+        if !status.is_terminal() {
+            waiting = true;
+        } else if dependency_type != DEPENDS_ON_COMPLETION && *status != AsyncTaskStatus::Completed {
+            return Gate::Blocked(format!("dependency {} ended as {}", task_id, status));
+        }
+    }
+    if waiting { Gate::Waiting } else { Gate::Ready }
+}
+
+/// Told about every task that reaches a terminal status
+#[async_trait::async_trait]
+pub trait TaskNotifier: Send + Sync {
+    async fn task_finished(&self, task: &AsyncTaskRecord) -> AriaResult<()>;
+}
+
+/// Queues tasks in their owners' databases and runs them
+pub struct TaskRunner {
+    runtime: AriaRuntime,
+    max_concurrent: usize,
+    queue: Mutex<BinaryHeap<QueuedTask>>,
+    running: Mutex<HashMap<String, AbortHandle>>,
+    wake: Notify,
+    seq: AtomicU64,
+    notifiers: RwLock<Vec<Arc<dyn TaskNotifier>>>,
+}
+
+impl TaskRunner {
+    pub fn new(runtime: AriaRuntime) -> Self {
+        Self {
+            runtime,
+            max_concurrent: DEFAULT_MAX_CONCURRENT_TASKS,
+            queue: Mutex::new(BinaryHeap::new()),
+            running: Mutex::new(HashMap::new()),
+            wake: Notify::new(),
+            seq: AtomicU64::new(0),
+            notifiers: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Run at most `max_concurrent` tasks at once
+    pub fn with_max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent.max(1);
+        self
+    }
+
+    /// Add a notifier that is told about every task that ends
+    pub async fn attach_notifier(&self, notifier: Arc<dyn TaskNotifier>) {
+        self.notifiers.write().await.push(notifier);
+    }
+
+    /// Start dispatching queued tasks in the background
+    pub fn start(self: &Arc<Self>) {
+        let runner = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                runner.dispatch().await;
+                let _ = tokio::time::timeout(DISPATCH_INTERVAL, runner.wake.notified()).await;
+            }
+        });
+    }
+
+    /// Queue again every unfinished task left by a previous process.
+    /// Tasks that were running start over, except container tasks, which
+    /// keep following the quilt task they started.
+    pub async fn recover(&self) -> AriaResult<usize> {
+        let system_pool = self.runtime.engines.database.get_system_database().await?;
+        let owners: HashSet<String> = TenantOps::list_resources(&system_pool, TenantResource::Task, None).await?
+            .into_iter()
+            .map(|record| record.user_id)
+            .collect();
+
+        let mut recovered = 0;
+        for user_id in owners {
+            let pool = self.runtime.engines.database.get_user_database(&user_id).await?;
+            for task in AsyncTaskOps::get_queued_tasks(&pool).await? {
+                self.enqueue(&task.task_id, &user_id, task.priority);
+                recovered += 1;
+            }
+        }
+
+        info!("Recovered {} unfinished tasks", recovered);
+        self.wake.notify_one();
+        Ok(recovered)
+    }
+
+    /// Validate and queue a task with the tasks it waits on, as
+    /// `(depends_on_task_id, dependency_type)` pairs. Tasks without a session
+    /// get one of their own. Returns the new task's ID.
+    pub async fn submit(&self, mut task: NewAsyncTask, depends_on: Vec<(String, String)>) -> AriaResult<String> {
+        let pool = self.runtime.engines.database.get_user_database(&task.user_id).await?;
+        self.validate(&pool, &task).await?;
+
+        if let Some(parent_task_id) = &task.parent_task_id {
+            Self::owned_task(&pool, parent_task_id, "Parent task").await?;
+        }
+        for (depends_on_task_id, dependency_type) in &depends_on {
+            if dependency_type != DEPENDS_ON_SUCCESS && dependency_type != DEPENDS_ON_COMPLETION {
+                return Err(invalid(format!("Unknown dependency condition '{}'", dependency_type)));
+            }
+            Self::owned_task(&pool, depends_on_task_id, "Dependency").await?;
+        }
+
+        if task.session_id.is_empty() {
+            task.session_id = SessionOps::create_session(&pool, &task.user_id, "task", None).await?;
+        }
+
+        let task_id = AsyncTaskOps::insert_task(&pool, &task).await?;
+        for (depends_on_task_id, dependency_type) in &depends_on {
+            AsyncTaskOps::add_dependency(&pool, &task_id, depends_on_task_id, dependency_type).await?;
+        }
+
+        let system_pool = self.runtime.engines.database.get_system_database().await?;
+        TenantOps::record_resource(
+            &system_pool,
+            TenantResource::Task,
+            &task_id,
+            &task.user_id,
+            Some(&task.session_id),
+            task.container_id.as_deref(),
+        ).await?;
+
+        info!("Queued {} task {} for user {} (priority {}, {} dependencies)",
+              task.task_type, task_id, task.user_id, task.priority, depends_on.len());
+        self.enqueue(&task_id, &task.user_id, task.priority);
+        self.wake.notify_one();
+        Ok(task_id)
+    }
+
+    /// Check that a task could be submitted, without queuing it
+    pub async fn validate_task(&self, task: &NewAsyncTask) -> AriaResult<()> {
+        let pool = self.runtime.engines.database.get_user_database(&task.user_id).await?;
+        self.validate(&pool, task).await
+    }
+
+    /// Cancel an unfinished task of `user_id` and every unfinished task created under it.
+    /// Returns false if the task had already ended.
+    pub async fn cancel(&self, user_id: &str, task_id: &str, reason: &str) -> AriaResult<bool> {
+        let pool = self.runtime.engines.database.get_user_database(user_id).await?;
+        let cancelled = self.end_tree(&pool, task_id, AsyncTaskStatus::Cancelled, reason, true).await?;
+        self.wake.notify_one();
+        Ok(cancelled)
+    }
+
+    /// End a task and its unfinished subtasks with `status`, stopping whatever runs them.
+    /// `abort` is false when the caller is the task's own work, which stops by itself.
+    fn end_tree<'a>(
+        &'a self,
+        pool: &'a sqlx::SqlitePool,
+        task_id: &'a str,
+        status: AsyncTaskStatus,
+        reason: &'a str,
+        abort: bool,
+    ) -> BoxFuture<'a, AriaResult<bool>> {
+        Box::pin(async move {
+            // Recorded first, so the work being stopped cannot record an outcome of its own
+            let ended = AsyncTaskOps::finish_task(pool, task_id, &TaskOutcome::ended(status.clone(), reason)).await?;
+
+            if abort {
+                let handle = self.running.lock().unwrap().remove(task_id);
+                if let Some(handle) = handle {
+                    handle.abort();
+                }
+            }
+
+            if ended {
+                let task = AsyncTaskOps::get_task(pool, task_id).await?;
+                self.announce(&task).await;
+                if let Some(external_id) = task.external_id.filter(|_| task.task_type.starts_with(CONTAINER_TASK_TYPE)) {
+                    if let Err(e) = self.runtime.engines.quilt_service.lock().await.cancel_task(external_id.clone()).await {
+                        warn!("Failed to cancel quilt task {} of task {}: {}", external_id, task_id, e);
+                    }
+                }
+            }
+
+            for subtask in AsyncTaskOps::get_subtasks(pool, task_id).await? {
+                if !subtask.status.is_terminal() {
+                    self.end_tree(pool, &subtask.task_id, status.clone(), reason, true).await?;
+                }
+            }
+            Ok(ended)
+        })
+    }
+
+    fn enqueue(&self, task_id: &str, user_id: &str, priority: i32) {
+        let seq = self.seq.fetch_add(1, AtomicOrdering::Relaxed);
+        self.queue.lock().unwrap().push(QueuedTask {
+            priority,
+            seq,
+            task_id: task_id.to_string(),
+            user_id: user_id.to_string(),
+            failed_dispatches: 0,
+            retry_at: None,
+        });
+    }
+
+    /// Start every queued task whose dependencies allow it, in priority order, while slots are free.
+    /// Tasks blocked by a dependency are skipped, and tasks that cannot be dispatched are
+    /// eventually failed; either may in turn block others, so the runner wakes itself
+    /// again whenever that happens.
+    async fn dispatch(self: &Arc<Self>) {
+        let mut queued = std::mem::take(&mut *self.queue.lock().unwrap()).into_sorted_vec();
+        let mut kept = Vec::new();
+        let mut settled = false;
+
+        while let Some(mut entry) = queued.pop() {
+            let backing_off = entry.retry_at.is_some_and(|retry_at| retry_at > Instant::now());
+            if backing_off || self.running.lock().unwrap().len() >= self.max_concurrent {
+                kept.push(entry);
+                continue;
+            }
+
+            match self.try_start(&entry).await {
+                Ok(Some(true)) => {}
+                Ok(Some(false)) => kept.push(entry),
+                Ok(None) => settled = true,
+                Err(e) => {
+                    entry.failed_dispatches += 1;
+                    if entry.failed_dispatches >= MAX_DISPATCH_ATTEMPTS {
+                        warn!("Giving up on task {} after {} failed dispatches: {}", entry.task_id, entry.failed_dispatches, e);
+                        self.fail_undispatchable(&entry, &e).await;
+                        settled = true;
+                    } else {
+                        warn!("Failed to dispatch task {} (attempt {}): {}", entry.task_id, entry.failed_dispatches, e);
+                        entry.retry_at = Some(Instant::now() + dispatch_backoff(entry.failed_dispatches));
+                        kept.push(entry);
+                    }
+                }
+            }
+        }
+
+        self.queue.lock().unwrap().extend(kept);
+        if settled {
+            self.wake.notify_one();
+        }
+    }
+
+    /// Fail a task that could not be dispatched, with its subtasks; tasks that depend on it
+    /// are then skipped by their gate. If even that cannot be recorded the task leaves the
+    /// queue anyway, and `recover` picks it up again on the next start.
+    async fn fail_undispatchable(&self, entry: &QueuedTask, error: &AriaError) {
+        let reason = format!("could not be dispatched: {}", error);
+        let result = match self.runtime.engines.database.get_user_database(&entry.user_id).await {
+            Ok(pool) => self.end_tree(&pool, &entry.task_id, AsyncTaskStatus::Failed, &reason, true).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!("Failed to record that task {} {}: {}", entry.task_id, reason, e);
+        }
+    }
+
+    /// Start a queued task if it may run: `Some(true)` if started, `Some(false)` if it
+    /// must wait, `None` if it left the queue without running
+    async fn try_start(self: &Arc<Self>, entry: &QueuedTask) -> AriaResult<Option<bool>> {
+        let pool = self.runtime.engines.database.get_user_database(&entry.user_id).await?;
+        let task = AsyncTaskOps::get_task(&pool, &entry.task_id).await?;
+        if task.status.is_terminal() || self.running.lock().unwrap().contains_key(&task.task_id) {
+            return Ok(None);
+        }
+
+        // Tasks recovered while running already passed their gate
+        if task.status == AsyncTaskStatus::Pending {
+            let mut dependencies = Vec::new();
+            for dependency in AsyncTaskOps::get_dependencies(&pool, &task.task_id).await? {
+                let status = match AsyncTaskOps::get_task(&pool, &dependency.depends_on_task_id).await {
+                    Ok(record) => record.status,
+                    Err(_) => AsyncTaskStatus::Cancelled,
+                };
+                dependencies.push((dependency.dependency_type, dependency.depends_on_task_id, status));
+            }
+
+            match gate(&dependencies) {
+                Gate::Waiting => return Ok(Some(false)),
+                Gate::Blocked(reason) => {
+                    debug!("Skipping task {}: {}", task.task_id, reason);
+                    self.end_tree(&pool, &task.task_id, AsyncTaskStatus::Skipped, &reason, true).await?;
+                    return Ok(None);
+                }
+                Gate::Ready => {}
+            }
+
+            if !AsyncTaskOps::claim_task(&pool, &task.task_id, &task.task_type).await? {
+                return Ok(None);
+            }
+        }
+
+        debug!("Starting {} task {} (priority {})", task.task_type, task.task_id, task.priority);
+        let runner = Arc::clone(self);
+        let task_id = task.task_id.clone();
+        let mut running = self.running.lock().unwrap();
+        let handle = tokio::spawn(async move {
+            let task_id = task.task_id.clone();
+            runner.finish(&pool, task).await;
+            runner.running.lock().unwrap().remove(&task_id);
+            runner.wake.notify_one();
+        });
+        running.insert(task_id, handle.abort_handle());
+        Ok(Some(true))
+    }
+
+    /// Run a claimed task within its timeout and record how it ended
+    async fn finish(&self, pool: &sqlx::SqlitePool, task: AsyncTaskRecord) {
+        let task_id = task.task_id.clone();
+        let limit = (task.timeout_seconds > 0).then(|| Duration::from_secs(task.timeout_seconds));
+
+        let work = self.execute(pool, &task);
+        let result = match limit {
+            Some(limit) => match tokio::time::timeout(limit, work).await {
+                Ok(result) => result,
+                Err(_) => {
+                    let reason = format!("timed out after {}s", limit.as_secs());
+                    if let Err(e) = self.end_tree(pool, &task_id, AsyncTaskStatus::Timeout, &reason, false).await {
+                        warn!("Failed to record timeout of task {}: {}", task_id, e);
+                    }
+                    return;
+                }
+            },
+            None => work.await,
+        };
+
+        let outcome = result.unwrap_or_else(|e| TaskOutcome::ended(AsyncTaskStatus::Failed, e.to_string()));
+        info!("Task {} finished: {}", task_id, outcome.status);
+        match AsyncTaskOps::finish_task(pool, &task_id, &outcome).await {
+            Ok(true) => match AsyncTaskOps::get_task(pool, &task_id).await {
+                Ok(finished) => self.announce(&finished).await,
+                Err(e) => warn!("Failed to read finished task {}: {}", task_id, e),
+            },
+            Ok(false) => {}
+            Err(e) => warn!("Failed to record outcome of task {}: {}", task_id, e),
+        }
+
+        // Announced so event triggers can react, e.g. to a command exiting non-zero
+        if let Some(container_id) = task.container_id.as_deref().filter(|_| task.task_type.starts_with(CONTAINER_TASK_TYPE)) {
+            let metadata = HashMap::from([
+                ("user_id".to_string(), task.user_id.clone()),
+                ("task_id".to_string(), task_id.clone()),
+                ("status".to_string(), outcome.status.to_string()),
+                ("exit_code".to_string(), outcome.exit_code.map(|code| code.to_string()).unwrap_or_default()),
+            ]);
+            if let Err(e) = self.runtime.engines.record_container_event(container_id, EXEC_FINISHED_EVENT, metadata).await {
+                warn!("Failed to record exec event of task {}: {}", task_id, e);
+            }
+        }
+    }
+
+    /// Tell the attached notifiers that a task ended
+    async fn announce(&self, task: &AsyncTaskRecord) {
+        let notifiers = self.notifiers.read().await.clone();
+        for notifier in &notifiers {
+            if let Err(e) = notifier.task_finished(task).await {
+                warn!("Failed to announce end of task {}: {}", task.task_id, e);
+            }
+        }
+    }
+
+    async fn execute(&self, pool: &sqlx::SqlitePool, task: &AsyncTaskRecord) -> AriaResult<TaskOutcome> {
+        // Model calls the task makes are charged to it, under its owner's budget
+        let attribution = CostAttribution {
+            user_id: Some(task.user_id.clone()),
+            session_id: (!task.session_id.is_empty()).then(|| task.session_id.clone()),
+            task_id: Some(task.task_id.clone()),
+            ..Default::default()
+        };
+        self.runtime.engines.costs.scoped(attribution, async {
+            match task.task_type.as_str() {
+                AGENT_TASK_TYPE => self.execute_agent(pool, task).await,
+                BUNDLE_TASK_TYPE => self.execute_bundle(task).await,
+                PIPELINE_TASK_TYPE => self.execute_pipeline(task).await,
+                task_type if task_type.starts_with(CONTAINER_TASK_TYPE) => self.execute_container(pool, task).await,
+                task_type => Err(invalid(format!("Unsupported task type '{}'", task_type))),
+            }
+        }).await
+    }
+
+    /// Run the command through quilt, or keep following the quilt task a previous process started
+    async fn execute_container(&self, pool: &sqlx::SqlitePool, task: &AsyncTaskRecord) -> AriaResult<TaskOutcome> {
+        let quilt_task_id = match &task.external_id {
+            Some(external_id) => external_id.clone(),
+            None => {
+                let container_id = task.container_id.clone()
+                    .ok_or_else(|| invalid("Container task has no container".to_string()))?;
+                let timeout = (task.timeout_seconds > 0).then_some(task.timeout_seconds as i32);
+                let quilt_task_id = self.runtime.engines.quilt_service.lock().await
+                    .exec_container_async(container_id, task.command.clone(), timeout).await?;
+                AsyncTaskOps::set_external_id(pool, &task.task_id, &quilt_task_id).await?;
+                quilt_task_id
+            }
+        };
+
+        loop {
+            let status = self.runtime.engines.quilt_service.lock().await
+                .get_task_status(quilt_task_id.clone()).await?;
+            if quilt_status(status.status).is_terminal() {
+                break;
+            }
+            AsyncTaskOps::update_progress(pool, &task.task_id, status.progress_percent, &status.current_operation).await?;
+            tokio::time::sleep(CONTAINER_POLL_INTERVAL).await;
+        }
+
+        let result = self.runtime.engines.quilt_service.lock().await
+            .get_task_result(quilt_task_id).await?;
+        Ok(TaskOutcome {
+            status: quilt_status(result.status),
+            exit_code: Some(result.exit_code),
+            stdout: Some(result.stdout),
+            stderr: Some(result.stderr),
+            error_message: (!result.error_message.is_empty()).then_some(result.error_message),
+        })
+    }
+
+    async fn execute_agent(&self, pool: &sqlx::SqlitePool, task: &AsyncTaskRecord) -> AriaResult<TaskOutcome> {
+        let prompt = task.command.first().cloned().unwrap_or_default();
+        let config_id = task.command.get(1).map(String::as_str);
+        let (_, agent) = load_stored_agent(pool, &self.runtime.engines.tool_registry, &task.user_id, config_id).await?;
+
+        let result = self.runtime.execute_for_user(&prompt, agent, &task.user_id).await?;
+        let output = match agent_output(&result) {
+            Value::String(text) => text,
+            Value::Null => String::new(),
+            value => value.to_string(),
+        };
+        Ok(TaskOutcome {
+            status: if result.success { AsyncTaskStatus::Completed } else { AsyncTaskStatus::Failed },
+            exit_code: Some(if result.success { 0 } else { 1 }),
+            stdout: Some(output),
+            stderr: None,
+            error_message: result.error,
+        })
+    }
+
+    async fn execute_bundle(&self, task: &AsyncTaskRecord) -> AriaResult<TaskOutcome> {
+        let bundle_hash = task.command.first().cloned().unwrap_or_default();
+        let session_id = uuid::Uuid::parse_str(&task.session_id).unwrap_or_else(|_| uuid::Uuid::new_v4());
+        let mut config = BundleExecutionConfig {
+            environment_variables: task.environment.clone(),
+            ..Default::default()
+        };
+        if task.timeout_seconds > 0 {
+            config.timeout_seconds = Some(task.timeout_seconds);
+        }
+
+        let result = self.runtime.execute_bundle_workload(&bundle_hash, DeepUuid(session_id), Some(config)).await?;
+        Ok(TaskOutcome {
+            status: if result.success { AsyncTaskStatus::Completed } else { AsyncTaskStatus::Failed },
+            exit_code: result.exit_code,
+            stdout: result.stdout,
+            stderr: result.stderr,
+            error_message: (!result.success).then(|| format!("Bundle '{}' failed", result.bundle_name)),
+        })
+    }
+
+    async fn execute_pipeline(&self, task: &AsyncTaskRecord) -> AriaResult<TaskOutcome> {
+        let result = PipelineExecutor::new(self.runtime.clone()).run_task(&task.task_id, &task.user_id).await?;
+        Ok(TaskOutcome {
+            status: if result.success { AsyncTaskStatus::Completed } else { AsyncTaskStatus::Failed },
+            exit_code: Some(if result.success { 0 } else { 1 }),
+            stdout: result.output.as_ref().map(Value::to_string),
+            stderr: None,
+            error_message: result.error,
+        })
+    }
+
+    /// Check a task's type and command before it is queued
+    async fn validate(&self, pool: &sqlx::SqlitePool, task: &NewAsyncTask) -> AriaResult<()> {
+        match task.task_type.as_str() {
+            AGENT_TASK_TYPE => {
+                if task.command.first().map(|prompt| prompt.trim()).unwrap_or_default().is_empty() {
+                    return Err(invalid("Agent tasks need a prompt".to_string()));
+                }
+                if let Some(config_id) = task.command.get(1) {
+                    if ConversationOps::get_agent_config(pool, config_id).await?.is_none() {
+                        return Err(invalid(format!("Agent configuration not found: {}", config_id)));
+                    }
+                }
+            }
+            BUNDLE_TASK_TYPE => {
+                if task.command.first().map(String::as_str).unwrap_or_default().is_empty() {
+                    return Err(invalid("Bundle tasks need a bundle hash".to_string()));
+                }
+            }
+            PIPELINE_TASK_TYPE => {
+                let [name, input] = task.command.as_slice() else {
+                    return Err(invalid("Pipeline tasks need a pipeline name and input".to_string()));
+                };
+                if self.runtime.pipelines.get(name).await.is_none() {
+                    return Err(invalid(format!("Pipeline not found: {}", name)));
+                }
+                if serde_json::from_str::<Value>(input).is_err() {
+                    return Err(invalid("Pipeline input is not valid JSON".to_string()));
+                }
+            }
+            task_type if task_type.starts_with(CONTAINER_TASK_TYPE) => {
+                if task.container_id.is_none() {
+                    return Err(invalid("Container tasks need a container".to_string()));
+                }
+                if task.command.is_empty() {
+                    return Err(invalid("Command cannot be empty".to_string()));
+                }
+            }
+            task_type => return Err(invalid(format!("Unsupported task type '{}'", task_type))),
+        }
+        Ok(())
+    }
+
+    /// A task the new task refers to must live in the same owner's database
+    async fn owned_task(pool: &sqlx::SqlitePool, task_id: &str, what: &str) -> AriaResult<AsyncTaskRecord> {
+        AsyncTaskOps::get_task(pool, task_id).await
+            .map_err(|_| invalid(format!("{} not found: {}", what, task_id)))
+    }
+}
+
+fn invalid(message: String) -> AriaError {
+    AriaError::new(
+        ErrorCode::TaskValidationError,
+        ErrorCategory::Execution,
+        ErrorSeverity::Low,
+        &message,
+    )
+}
+
+/// Map quilt's task status onto the task's own
+fn quilt_status(status: i32) -> AsyncTaskStatus {
+    match quilt_proto::TaskStatus::try_from(status) {
+        Ok(quilt_proto::TaskStatus::TaskCompleted) => AsyncTaskStatus::Completed,
+        Ok(quilt_proto::TaskStatus::TaskFailed) => AsyncTaskStatus::Failed,
+        Ok(quilt_proto::TaskStatus::TaskCancelled) => AsyncTaskStatus::Cancelled,
+        Ok(quilt_proto::TaskStatus::TaskTimeout) => AsyncTaskStatus::Timeout,
+        Ok(quilt_proto::TaskStatus::TaskRunning) => AsyncTaskStatus::Running,
+        _ => AsyncTaskStatus::Pending,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(task_id: &str, priority: i32, seq: u64) -> QueuedTask {
+        QueuedTask {
+            priority,
+            seq,
+            task_id: task_id.to_string(),
+            user_id: "user".to_string(),
+            failed_dispatches: 0,
+            retry_at: None,
+        }
+    }
+
+    fn dependency(dependency_type: &str, status: AsyncTaskStatus) -> (String, String, AsyncTaskStatus) {
+        (dependency_type.to_string(), "dep".to_string(), status)
+    }
+
+    #[test]
+    fn queue_pops_by_priority_then_submission_order() {
+        let mut queue = BinaryHeap::new();
+        queue.push(queued("low", 0, 0));
+        queue.push(queued("high-late", 5, 2));
+        queue.push(queued("high-early", 5, 1));
+        queue.push(queued("negative", -1, 3));
+
+        let order: Vec<String> = std::iter::from_fn(|| queue.pop().map(|task| task.task_id)).collect();
+        assert_eq!(order, vec!["high-early", "high-late", "low", "negative"]);
+    }
+
+    #[test]
+    fn dispatch_backoff_doubles_from_the_dispatch_interval() {
+        assert_eq!(dispatch_backoff(1), DISPATCH_INTERVAL);
+        assert_eq!(dispatch_backoff(2), DISPATCH_INTERVAL * 2);
+        assert_eq!(dispatch_backoff(MAX_DISPATCH_ATTEMPTS - 1), DISPATCH_INTERVAL * 8);
+    }
+
+    #[test]
+    fn gate_follows_dependency_conditions() {
+        assert_eq!(gate(&[]), Gate::Ready);
+        assert_eq!(gate(&[dependency(DEPENDS_ON_SUCCESS, AsyncTaskStatus::Completed)]), Gate::Ready);
+        assert_eq!(gate(&[dependency(DEPENDS_ON_SUCCESS, AsyncTaskStatus::Running)]), Gate::Waiting);
+        assert_eq!(gate(&[dependency(DEPENDS_ON_COMPLETION, AsyncTaskStatus::Failed)]), Gate::Ready);
+        assert!(matches!(
+            gate(&[
+                dependency(DEPENDS_ON_COMPLETION, AsyncTaskStatus::Pending),
+                dependency(DEPENDS_ON_SUCCESS, AsyncTaskStatus::Failed),
+            ]),
+            Gate::Blocked(_)
+        ));
+    }
+}
@@ -1,7 +1,25 @@
-use crate::errors::AriaResult;
-use crate::types::*;
+/*!
+# Teams
+
+Multi-agent teams executed on top of `AriaRuntime::execute`. Every member runs
+as an ordinary agent session; the team's `TeamStrategy` decides who runs, in
+what order, and what each member gets to see of the others' work.
+*/
+
 use crate::agents::{AgentConfig, AgentResult};
+use crate::deep_size::{DeepDuration, DeepSystemTime};
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::runtime::AriaRuntime;
+use crate::types::*;
+use futures::future::join_all;
+use pkg_store::bundle::{AgentManifest, AriaManifest, TeamManifest};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TeamConfig {
@@ -20,22 +38,823 @@ pub struct TeamResult {
     pub agent_results: Vec<AgentResult>,
 }
 
+impl FromStr for TeamStrategy {
+    type Err = AriaError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let normalized: String = name
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_' | ' '))
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        match normalized.as_str() {
+            "parallel" => Ok(TeamStrategy::Parallel),
+            "sequential" => Ok(TeamStrategy::Sequential),
+            "pipeline" => Ok(TeamStrategy::Pipeline),
+            "collaborative" => Ok(TeamStrategy::Collaborative),
+            "rolebased" => Ok(TeamStrategy::RoleBased),
+            "adaptive" => Ok(TeamStrategy::Adaptive),
+            _ => Err(AriaError::new(
+                ErrorCode::ConfigError,
+                ErrorCategory::Configuration,
+                ErrorSeverity::Medium,
+                &format!("Unknown team strategy '{}'", name),
+            )),
+        }
+    }
+}
+
+impl TeamConfig {
+    /// Build a team from a bundle's `TeamManifest`, resolving its members
+    /// against the agents declared in the same bundle
+    pub fn from_manifest(team: &TeamManifest, agents: &[AgentManifest]) -> AriaResult<Self> {
+        if team.members.is_empty() {
+            return Err(AriaError::new(
+                ErrorCode::BundleValidationError,
+                ErrorCategory::Bundle,
+                ErrorSeverity::Medium,
+                &format!("Team '{}' has no members", team.name),
+            ));
+        }
+
+        let members = team.members.iter()
+            .map(|member| {
+                agents.iter()
+                    .find(|agent| &agent.name == member)
+                    .map(AgentConfig::from)
+                    .ok_or_else(|| AriaError::new(
+                        ErrorCode::BundleValidationError,
+                        ErrorCategory::Bundle,
+                        ErrorSeverity::Medium,
+                        &format!("Team '{}' references unknown agent '{}'", team.name, member),
+                    ))
+            })
+            .collect::<AriaResult<Vec<_>>>()?;
+
+        let strategy = match team.strategy.as_deref() {
+            Some(name) => name.parse()?,
+            None => TeamStrategy::Adaptive,
+        };
+
+        Ok(Self {
+            name: team.name.clone(),
+            description: team.description.clone(),
+            agents: members,
+            strategy,
+        })
+    }
+}
+
+/// Teams known to the runtime, keyed by name
+#[derive(Default)]
+pub struct TeamRegistry {
+    teams: RwLock<HashMap<String, TeamConfig>>,
+}
+
+impl TeamRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a team, replacing any existing team with the same name
+    pub async fn register(&self, config: TeamConfig) {
+        debug!("Registering team '{}' ({} agents)", config.name, config.agents.len());
+        self.teams.write().await.insert(config.name.clone(), config);
+    }
+
+    /// Register every team declared in a bundle manifest and return their names.
+    /// Teams that fail validation are skipped so one bad entry doesn't hide the rest.
+    pub async fn register_manifest(&self, manifest: &AriaManifest) -> Vec<String> {
+        let mut registered = Vec::new();
+        for team in &manifest.teams {
+            match TeamConfig::from_manifest(team, &manifest.agents) {
+                Ok(config) => {
+                    registered.push(config.name.clone());
+                    self.register(config).await;
+                }
+                Err(e) => warn!("Skipping team '{}' from bundle '{}': {}", team.name, manifest.name, e),
+            }
+        }
+        registered
+    }
+
+    pub async fn get(&self, name: &str) -> Option<TeamConfig> {
+        self.teams.read().await.get(name).cloned()
+    }
+
+    pub async fn unregister(&self, name: &str) -> bool {
+        self.teams.write().await.remove(name).is_some()
+    }
+
+    pub async fn list(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.teams.read().await.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+/// What a strategy produced before metrics are aggregated
+struct TeamOutcome {
+    success: bool,
+    result: Option<Value>,
+    error: Option<String>,
+    agent_results: Vec<AgentResult>,
+}
+
+impl TeamOutcome {
+    fn failed(error: String, agent_results: Vec<AgentResult>) -> Self {
+        Self { success: false, result: None, error: Some(error), agent_results }
+    }
+}
+
 pub struct Team {
     config: TeamConfig,
+    runtime: AriaRuntime,
+    /// User the members run for; without one they run without memory
+    owner: Option<String>,
 }
 
 impl Team {
-    pub fn new(config: TeamConfig) -> Self {
-        Self { config }
+    pub fn new(config: TeamConfig, runtime: AriaRuntime) -> Self {
+        Self { config, runtime, owner: None }
+    }
+
+    /// Run the members on behalf of `user_id`, in their memory
+    pub fn with_owner(mut self, user_id: &str) -> Self {
+        self.owner = Some(user_id.to_string());
+        self
+    }
+
+    pub fn config(&self) -> &TeamConfig {
+        &self.config
     }
 
     pub async fn run(&self, task: &str) -> AriaResult<TeamResult> {
+        if self.config.agents.is_empty() {
+            return Err(AriaError::new(
+                ErrorCode::ConfigError,
+                ErrorCategory::Configuration,
+                ErrorSeverity::Medium,
+                &format!("Team '{}' has no agents", self.config.name),
+            ));
+        }
+
+        let started = SystemTime::now();
+        let strategy = match self.config.strategy {
+            TeamStrategy::Adaptive => choose_strategy(&self.config.agents, task),
+            ref strategy => strategy.clone(),
+        };
+        info!("Running team '{}' with {:?} strategy", self.config.name, strategy);
+
+        let outcome = match strategy {
+            TeamStrategy::Sequential => self.run_sequential(task).await,
+            TeamStrategy::Pipeline => self.run_pipeline(task).await,
+            TeamStrategy::Collaborative => self.run_collaborative(task).await,
+            TeamStrategy::RoleBased => self.run_role_based(task).await,
+            TeamStrategy::Parallel | TeamStrategy::Adaptive => self.run_parallel(task).await,
+        };
+
         Ok(TeamResult {
+            success: outcome.success,
+            result: outcome.result,
+            error: outcome.error,
+            metrics: aggregate_metrics(&outcome.agent_results, started),
+            agent_results: outcome.agent_results,
+        })
+    }
+
+    /// Every member works the same task at once; successful answers are merged
+    async fn run_parallel(&self, task: &str) -> TeamOutcome {
+        let agent_results = join_all(
+            self.config.agents.iter().map(|agent| self.run_agent(agent, task.to_string())),
+        ).await;
+
+        let mut responses = serde_json::Map::new();
+        let mut failures = serde_json::Map::new();
+        for result in &agent_results {
+            if result.success {
+                responses.insert(result.agent.clone(), result.result.clone().unwrap_or(Value::Null));
+            } else {
+                failures.insert(result.agent.clone(), json!(failure_message(result)));
+            }
+        }
+
+        if responses.is_empty() {
+            return TeamOutcome::failed(
+                format!("All {} agents failed", agent_results.len()),
+                agent_results,
+            );
+        }
+
+        TeamOutcome {
             success: true,
-            result: Some(serde_json::json!({"response": "Team task completed", "task": task})),
+            result: Some(json!({
+                "strategy": "parallel",
+                "responses": responses,
+                "failures": failures,
+            })),
             error: None,
-            metrics: ExecutionMetrics::default(),
-            agent_results: vec![],
+            agent_results,
+        }
+    }
+
+    /// Members run one after another, each seeing everything done before it
+    async fn run_sequential(&self, task: &str) -> TeamOutcome {
+        let mut agent_results = Vec::new();
+        let mut handoffs: Vec<(String, Value)> = Vec::new();
+
+        for agent in &self.config.agents {
+            let result = self.run_agent(agent, handoff_task(task, &handoffs)).await;
+            if !result.success {
+                let error = format!("Agent '{}' failed: {}", agent.name, failure_message(&result));
+                agent_results.push(result);
+                return TeamOutcome::failed(error, agent_results);
+            }
+            handoffs.push((agent.name.clone(), result.result.clone().unwrap_or(Value::Null)));
+            agent_results.push(result);
+        }
+
+        let output = handoffs.last().map(|(_, output)| output.clone()).unwrap_or(Value::Null);
+        TeamOutcome {
+            success: true,
+            result: Some(json!({
+                "strategy": "sequential",
+                "output": output,
+                "handoffs": handoffs.iter()
+                    .map(|(agent, output)| json!({"agent": agent, "output": output}))
+                    .collect::<Vec<_>>(),
+            })),
+            error: None,
+            agent_results,
+        }
+    }
+
+    /// Members are stages; each receives only the previous stage's output
+    async fn run_pipeline(&self, task: &str) -> TeamOutcome {
+        let mut agent_results = Vec::new();
+        let mut stages: Vec<(String, Value)> = Vec::new();
+
+        for (index, agent) in self.config.agents.iter().enumerate() {
+            let result = self.run_agent(agent, pipeline_task(task, index, stages.last())).await;
+            if !result.success {
+                let error = format!("Pipeline stage {} ('{}') failed: {}", index + 1, agent.name, failure_message(&result));
+                agent_results.push(result);
+                return TeamOutcome::failed(error, agent_results);
+            }
+            stages.push((agent.name.clone(), result.result.clone().unwrap_or(Value::Null)));
+            agent_results.push(result);
+        }
+
+        let output = stages.last().map(|(_, output)| output.clone()).unwrap_or(Value::Null);
+        TeamOutcome {
+            success: true,
+            result: Some(json!({
+                "strategy": "pipeline",
+                "output": output,
+                "stages": stages.iter()
+                    .map(|(agent, output)| json!({"agent": agent, "output": output}))
+                    .collect::<Vec<_>>(),
+            })),
+            error: None,
+            agent_results,
+        }
+    }
+
+    /// Members draft independently, then the first member synthesizes one answer
+    async fn run_collaborative(&self, task: &str) -> TeamOutcome {
+        let mut agent_results = join_all(
+            self.config.agents.iter().map(|agent| self.run_agent(agent, task.to_string())),
+        ).await;
+
+        let drafts: Vec<(String, Value)> = agent_results.iter()
+            .filter(|result| result.success)
+            .map(|result| (result.agent.clone(), result.result.clone().unwrap_or(Value::Null)))
+            .collect();
+
+        if drafts.is_empty() {
+            return TeamOutcome::failed("Every member failed to produce a draft".to_string(), agent_results);
+        }
+
+        let lead = &self.config.agents[0];
+        let output = if drafts.len() == 1 {
+            drafts[0].1.clone()
+        } else {
+            let synthesis = self.run_agent(lead, synthesis_task(task, &drafts)).await;
+            let succeeded = synthesis.success;
+            let output = synthesis.result.clone().unwrap_or(Value::Null);
+            let error = failure_message(&synthesis);
+            agent_results.push(synthesis);
+            if !succeeded {
+                return TeamOutcome::failed(format!("Lead agent '{}' failed to synthesize drafts: {}", lead.name, error), agent_results);
+            }
+            output
+        };
+
+        TeamOutcome {
+            success: true,
+            result: Some(json!({
+                "strategy": "collaborative",
+                "lead": lead.name,
+                "output": output,
+                "drafts": drafts.iter()
+                    .map(|(agent, draft)| json!({"agent": agent, "output": draft}))
+                    .collect::<Vec<_>>(),
+            })),
+            error: None,
+            agent_results,
+        }
+    }
+
+    /// The first member coordinates: it splits the task across the others by
+    /// role, the assignees run in parallel, and it combines their work
+    async fn run_role_based(&self, task: &str) -> TeamOutcome {
+        let coordinator = &self.config.agents[0];
+        let specialists = &self.config.agents[1..];
+        if specialists.is_empty() {
+            return self.run_sequential(task).await;
+        }
+
+        let mut agent_results = Vec::new();
+        let delegation = self.run_agent(coordinator, delegation_task(task, specialists)).await;
+        let mut assignments = if delegation.success {
+            delegation.result.as_ref()
+                .map(|plan| parse_assignments(plan, specialists))
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        agent_results.push(delegation);
+
+        if assignments.is_empty() {
+            let best = &specialists[best_role_match(specialists, task)];
+            warn!("Coordinator '{}' produced no usable assignments; delegating to '{}' by role", coordinator.name, best.name);
+            assignments.push((best.name.clone(), task.to_string()));
+        }
+
+        let assigned = join_all(assignments.iter().map(|(agent_name, subtask)| {
+            let agent = specialists.iter()
+                .find(|agent| &agent.name == agent_name)
+                .expect("assignments only name known specialists");
+            self.run_agent(agent, format!("{}\n\nOverall team task, for context:\n{}", subtask, task))
+        })).await;
+
+        let work: Vec<(String, Value)> = assigned.iter()
+            .filter(|result| result.success)
+            .map(|result| (result.agent.clone(), result.result.clone().unwrap_or(Value::Null)))
+            .collect();
+        let report: Vec<Value> = assignments.iter().zip(&assigned)
+            .map(|((agent, subtask), result)| json!({
+                "agent": agent,
+                "task": subtask,
+                "success": result.success,
+                "output": result.result,
+                "error": result.error,
+            }))
+            .collect();
+        agent_results.extend(assigned);
+
+        if work.is_empty() {
+            return TeamOutcome::failed("No delegated assignment succeeded".to_string(), agent_results);
+        }
+
+        let output = if work.len() == 1 {
+            work[0].1.clone()
+        } else {
+            let summary = self.run_agent(coordinator, synthesis_task(task, &work)).await;
+            let output = if summary.success {
+                summary.result.clone().unwrap_or(Value::Null)
+            } else {
+                warn!("Coordinator '{}' failed to combine results; returning them unmerged", coordinator.name);
+                Value::Object(work.iter().cloned().collect())
+            };
+            agent_results.push(summary);
+            output
+        };
+
+        TeamOutcome {
+            success: true,
+            result: Some(json!({
+                "strategy": "role_based",
+                "coordinator": coordinator.name,
+                "output": output,
+                "assignments": report,
+            })),
+            error: None,
+            agent_results,
+        }
+    }
+
+    async fn run_agent(&self, agent: &AgentConfig, task: String) -> AgentResult {
+        debug!("Team '{}' running agent '{}'", self.config.name, agent.name);
+        let started = SystemTime::now();
+
+        match self.runtime.execute_as(&task, agent.to_runtime_config(), self.owner.as_deref()).await {
+            Ok(result) => AgentResult {
+                agent: agent.name.clone(),
+                success: result.success,
+                result: Some(agent_output(&result)),
+                error: result.error.clone(),
+                metrics: metrics_from_runtime(&result, started),
+            },
+            Err(e) => AgentResult {
+                agent: agent.name.clone(),
+                success: false,
+                result: None,
+                error: Some(e.to_string()),
+                metrics: ExecutionMetrics {
+                    total_duration: DeepDuration(started.elapsed().unwrap_or_default()),
+                    error_count: 1,
+                    success_rate: 0.0,
+                    start_time: DeepSystemTime(started),
+                    end_time: Some(DeepSystemTime(SystemTime::now())),
+                    ..ExecutionMetrics::default()
+                },
+            },
+        }
+    }
+}
+
+/// Pick a concrete strategy for an adaptive team from its shape and the task
+fn choose_strategy(agents: &[AgentConfig], task: &str) -> TeamStrategy {
+    if agents.len() == 1 {
+        return TeamStrategy::Sequential;
+    }
+    if agents.len() > 2 && agents.iter().any(|agent| !agent.capabilities.is_empty()) {
+        return TeamStrategy::RoleBased;
+    }
+
+    let lower = task.to_lowercase();
+    let numbered_steps = task.lines()
+        .filter(|line| {
+            let line = line.trim_start();
+            line.chars().next().is_some_and(|c| c.is_ascii_digit())
+                && line.trim_start_matches(|c: char| c.is_ascii_digit()).starts_with(['.', ')'])
         })
+        .count();
+    if numbered_steps >= 2 || lower.contains(" then ") || lower.contains("after that") {
+        return TeamStrategy::Pipeline;
+    }
+
+    TeamStrategy::Collaborative
+}
+
+/// The value an agent hands to its teammates: the final response, parsed as
+/// JSON when the agent answered with structured data, otherwise the result of
+/// its last successful step
+pub(crate) fn agent_output(result: &RuntimeResult) -> Value {
+    if let Some(conversation) = &result.conversation {
+        let response = conversation.final_response.trim();
+        if !response.is_empty() {
+            return parse_structured(response).unwrap_or_else(|| Value::String(response.to_string()));
+        }
+    }
+
+    result.execution_details.step_results.iter()
+        .rev()
+        .filter(|step| step.success)
+        .find_map(|step| step.result.as_ref().map(|value| value.0.clone()))
+        .unwrap_or(Value::Null)
+}
+
+/// Parse a JSON object or array, including one wrapped in a fenced code block
+fn parse_structured(text: &str) -> Option<Value> {
+    let trimmed = text.trim();
+    let body = trimmed.strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.trim_end().strip_suffix("```"))
+        .unwrap_or(trimmed)
+        .trim();
+
+    match serde_json::from_str::<Value>(body) {
+        Ok(value @ (Value::Object(_) | Value::Array(_))) => Some(value),
+        _ => None,
+    }
+}
+
+fn render(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => serde_json::to_string_pretty(other).unwrap_or_default(),
+    }
+}
+
+fn failure_message(result: &AgentResult) -> String {
+    result.error.clone().unwrap_or_else(|| "agent reported failure".to_string())
+}
+
+fn handoff_task(task: &str, previous: &[(String, Value)]) -> String {
+    if previous.is_empty() {
+        return task.to_string();
+    }
+
+    let mut prompt = format!("{}\n\nWork so far from your teammates:\n", task);
+    for (agent, output) in previous {
+        prompt.push_str(&format!("\n[{}]\n{}\n", agent, render(output)));
     }
-} 
\ No newline at end of file
+    prompt.push_str("\nBuild on their work and continue the task.");
+    prompt
+}
+
+fn pipeline_task(task: &str, stage: usize, input: Option<&(String, Value)>) -> String {
+    match input {
+        None => task.to_string(),
+        Some((from, value)) => format!(
+            "{}\n\nYou are stage {} of a pipeline. Input from the previous stage ('{}'):\n{}\n\n\
+             Produce this stage's output; reply with JSON when the output is structured.",
+            task,
+            stage + 1,
+            from,
+            serde_json::to_string_pretty(value).unwrap_or_default(),
+        ),
+    }
+}
+
+fn synthesis_task(task: &str, drafts: &[(String, Value)]) -> String {
+    let mut prompt = format!("{}\n\nYour teammates each worked on this:\n", task);
+    for (agent, draft) in drafts {
+        prompt.push_str(&format!("\n[{}]\n{}\n", agent, render(draft)));
+    }
+    prompt.push_str("\nCombine their work into one final answer, resolving any disagreements.");
+    prompt
+}
+
+fn delegation_task(task: &str, specialists: &[AgentConfig]) -> String {
+    let mut prompt = format!(
+        "{}\n\nYou coordinate a team. Split the task across the members best suited to each part.\nMembers:\n",
+        task,
+    );
+    for agent in specialists {
+        prompt.push_str(&format!("- {}: {}", agent.name, agent.description));
+        if !agent.capabilities.is_empty() {
+            let capabilities: Vec<&str> = agent.capabilities.iter().map(|c| c.name.as_str()).collect();
+            prompt.push_str(&format!(" (capabilities: {})", capabilities.join(", ")));
+        }
+        prompt.push('\n');
+    }
+    prompt.push_str("\nReply with JSON only: {\"assignments\": [{\"agent\": \"<member name>\", \"task\": \"<what they should do>\"}]}");
+    prompt
+}
+
+/// Read a coordinator's delegation plan, keeping only assignments to known members
+fn parse_assignments(plan: &Value, specialists: &[AgentConfig]) -> Vec<(String, String)> {
+    let parsed;
+    let plan = match plan {
+        Value::String(text) => match parse_structured(text) {
+            Some(value) => {
+                parsed = value;
+                &parsed
+            }
+            None => return Vec::new(),
+        },
+        other => other,
+    };
+
+    let entries = match plan {
+        Value::Array(entries) => entries,
+        Value::Object(object) => match object.get("assignments") {
+            Some(Value::Array(entries)) => entries,
+            _ => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+
+    entries.iter()
+        .filter_map(|entry| {
+            let agent = entry.get("agent")?.as_str()?;
+            let subtask = entry.get("task")?.as_str()?;
+            specialists.iter()
+                .any(|known| known.name == agent)
+                .then(|| (agent.to_string(), subtask.to_string()))
+        })
+        .collect()
+}
+
+/// Index of the member whose description, capabilities and tools best overlap
+/// the task's words; ties go to the earlier member
+fn best_role_match(agents: &[AgentConfig], task: &str) -> usize {
+    let words: Vec<String> = task
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() > 3)
+        .map(|word| word.to_lowercase())
+        .collect();
+
+    let score = |agent: &AgentConfig| -> f32 {
+        let mut profile = agent.description.to_lowercase();
+        for capability in &agent.capabilities {
+            profile.push(' ');
+            profile.push_str(&capability.name.to_lowercase());
+            profile.push(' ');
+            profile.push_str(&capability.description.to_lowercase());
+        }
+        for tool in &agent.tools {
+            profile.push(' ');
+            profile.push_str(&tool.to_lowercase());
+        }
+
+        let overlap = words.iter().filter(|word| profile.contains(word.as_str())).count() as f32;
+        let confidence = agent.capabilities.iter().map(|c| c.confidence).fold(0.0, f32::max);
+        overlap + confidence * 0.5
+    };
+
+    let mut best = 0;
+    let mut best_score = f32::MIN;
+    for (index, agent) in agents.iter().enumerate() {
+        let agent_score = score(agent);
+        if agent_score > best_score {
+            best = index;
+            best_score = agent_score;
+        }
+    }
+    best
+}
+
+fn metrics_from_runtime(result: &RuntimeResult, started: SystemTime) -> ExecutionMetrics {
+    let details = &result.execution_details;
+    let llm_calls = details.step_results.iter()
+        .filter(|step| matches!(step.step_type, StepType::ReasoningStep))
+        .count() as u32;
+    let success_rate = if details.total_steps > 0 {
+        details.completed_steps as f32 / details.total_steps as f32
+    } else if result.success {
+        1.0
+    } else {
+        0.0
+    };
+
+    ExecutionMetrics {
+        total_duration: DeepDuration(started.elapsed().unwrap_or_default()),
+        planning_duration: DeepDuration(Duration::ZERO),
+        execution_duration: DeepDuration(Duration::from_millis(
+            details.step_results.iter().map(|step| step.duration).sum(),
+        )),
+        reflection_duration: DeepDuration(Duration::ZERO),
+        step_count: details.total_steps,
+        tool_call_count: result.metrics.tool_calls,
+        llm_call_count: llm_calls,
+        error_count: details.failed_steps,
+        recovery_count: result.metrics.adaptation_count,
+        cache_hit_rate: 0.0,
+        success_rate,
+        start_time: DeepSystemTime(started),
+        end_time: Some(DeepSystemTime(SystemTime::now())),
+    }
+}
+
+/// Team-level metrics: counts and phase durations summed across agent runs,
+/// wall-clock total, and success rate as the share of agent runs that succeeded
+fn aggregate_metrics(results: &[AgentResult], started: SystemTime) -> ExecutionMetrics {
+    let sum = |phase: fn(&ExecutionMetrics) -> Duration| -> DeepDuration {
+        DeepDuration(results.iter().map(|result| phase(&result.metrics)).sum())
+    };
+    let count = |field: fn(&ExecutionMetrics) -> u32| -> u32 {
+        results.iter().map(|result| field(&result.metrics)).sum()
+    };
+    let runs = results.len().max(1) as f32;
+
+    ExecutionMetrics {
+        total_duration: DeepDuration(started.elapsed().unwrap_or_default()),
+        planning_duration: sum(|m| m.planning_duration.0),
+        execution_duration: sum(|m| m.execution_duration.0),
+        reflection_duration: sum(|m| m.reflection_duration.0),
+        step_count: count(|m| m.step_count),
+        tool_call_count: count(|m| m.tool_call_count),
+        llm_call_count: count(|m| m.llm_call_count),
+        error_count: count(|m| m.error_count),
+        recovery_count: count(|m| m.recovery_count),
+        cache_hit_rate: results.iter().map(|result| result.metrics.cache_hit_rate).sum::<f32>() / runs,
+        success_rate: results.iter().filter(|result| result.success).count() as f32 / runs,
+        start_time: DeepSystemTime(started),
+        end_time: Some(DeepSystemTime(SystemTime::now())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent(name: &str, description: &str, capabilities: &[&str]) -> AgentConfig {
+        AgentConfig {
+            name: name.to_string(),
+            description: description.to_string(),
+            tools: Vec::new(),
+            llm: LLMConfig::default(),
+            capabilities: capabilities.iter()
+                .map(|capability| AgentCapability {
+                    name: capability.to_string(),
+                    description: String::new(),
+                    confidence: 0.5,
+                    prerequisites: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn agent_result(name: &str, success: bool, steps: u32) -> AgentResult {
+        AgentResult {
+            agent: name.to_string(),
+            success,
+            result: None,
+            error: None,
+            metrics: ExecutionMetrics {
+                step_count: steps,
+                tool_call_count: steps,
+                execution_duration: DeepDuration(Duration::from_millis(100)),
+                ..ExecutionMetrics::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_strategy_names() {
+        assert!(matches!("role_based".parse::<TeamStrategy>().unwrap(), TeamStrategy::RoleBased));
+        assert!(matches!("Role-Based".parse::<TeamStrategy>().unwrap(), TeamStrategy::RoleBased));
+        assert!(matches!("PIPELINE".parse::<TeamStrategy>().unwrap(), TeamStrategy::Pipeline));
+        assert!("round_robin".parse::<TeamStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_team_from_manifest() {
+        let agents = vec![
+            AgentManifest { name: "researcher".to_string(), description: "Finds sources".to_string(), tools: vec!["webSearchTool".to_string()] },
+            AgentManifest { name: "writer".to_string(), description: "Writes prose".to_string(), tools: Vec::new() },
+        ];
+        let team = TeamManifest {
+            name: "content".to_string(),
+            description: "Research and write".to_string(),
+            members: vec!["researcher".to_string(), "writer".to_string()],
+            strategy: Some("pipeline".to_string()),
+        };
+
+        let config = TeamConfig::from_manifest(&team, &agents).unwrap();
+        assert_eq!(config.agents.len(), 2);
+        assert_eq!(config.agents[0].tools, vec!["webSearchTool".to_string()]);
+        assert!(matches!(config.strategy, TeamStrategy::Pipeline));
+
+        let adaptive = TeamManifest { strategy: None, ..team.clone() };
+        assert!(matches!(TeamConfig::from_manifest(&adaptive, &agents).unwrap().strategy, TeamStrategy::Adaptive));
+
+        let unknown = TeamManifest { members: vec!["editor".to_string()], ..team };
+        assert!(TeamConfig::from_manifest(&unknown, &agents).is_err());
+    }
+
+    #[test]
+    fn test_choose_strategy() {
+        let solo = vec![agent("a", "", &[])];
+        assert!(matches!(choose_strategy(&solo, "anything"), TeamStrategy::Sequential));
+
+        let pair = vec![agent("a", "", &[]), agent("b", "", &[])];
+        assert!(matches!(choose_strategy(&pair, "Fetch the data, then summarize it"), TeamStrategy::Pipeline));
+        assert!(matches!(choose_strategy(&pair, "1. collect\n2. clean\n3. report"), TeamStrategy::Pipeline));
+        assert!(matches!(choose_strategy(&pair, "Write a haiku"), TeamStrategy::Collaborative));
+
+        let roles = vec![agent("lead", "", &[]), agent("b", "", &["search"]), agent("c", "", &["write"])];
+        assert!(matches!(choose_strategy(&roles, "Write a haiku"), TeamStrategy::RoleBased));
+    }
+
+    #[test]
+    fn test_parse_structured() {
+        assert_eq!(parse_structured("```json\n{\"a\": 1}\n```"), Some(json!({"a": 1})));
+        assert_eq!(parse_structured("[1, 2]"), Some(json!([1, 2])));
+        assert_eq!(parse_structured("42"), None);
+        assert_eq!(parse_structured("plain text"), None);
+    }
+
+    #[test]
+    fn test_parse_assignments_and_role_match() {
+        let specialists = vec![
+            agent("researcher", "Searches the web for sources", &["research"]),
+            agent("writer", "Drafts articles", &["writing"]),
+        ];
+
+        let plan = json!("```json\n{\"assignments\": [{\"agent\": \"writer\", \"task\": \"draft\"}, {\"agent\": \"ghost\", \"task\": \"haunt\"}]}\n```");
+        assert_eq!(parse_assignments(&plan, &specialists), vec![("writer".to_string(), "draft".to_string())]);
+        assert!(parse_assignments(&json!({"plan": "none"}), &specialists).is_empty());
+
+        assert_eq!(best_role_match(&specialists, "Research recent sources on fusion"), 0);
+        assert_eq!(best_role_match(&specialists, "Write some articles about fusion"), 1);
+    }
+
+    #[test]
+    fn test_prompts_carry_context() {
+        let handoffs = vec![("researcher".to_string(), json!("three sources"))];
+        assert_eq!(handoff_task("task", &[]), "task");
+        assert!(handoff_task("task", &handoffs).contains("[researcher]\nthree sources"));
+
+        let stage = ("extract".to_string(), json!({"rows": 3}));
+        let prompt = pipeline_task("task", 1, Some(&stage));
+        assert!(prompt.contains("stage 2"));
+        assert!(prompt.contains("\"rows\": 3"));
+    }
+
+    #[test]
+    fn test_aggregate_metrics() {
+        let results = vec![agent_result("a", true, 3), agent_result("b", false, 2)];
+        let metrics = aggregate_metrics(&results, SystemTime::now());
+        assert_eq!(metrics.step_count, 5);
+        assert_eq!(metrics.tool_call_count, 5);
+        assert_eq!(metrics.execution_duration.0, Duration::from_millis(200));
+        assert_eq!(metrics.success_rate, 0.5);
+        assert!(metrics.end_time.is_some());
+    }
+}
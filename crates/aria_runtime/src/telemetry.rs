@@ -0,0 +1,255 @@
+/*!
+# Telemetry
+
+Installs the process's `tracing` subscriber and, when an OTLP endpoint is
+configured, exports its spans to an OpenTelemetry collector over OTLP/HTTP.
+
+Trace context crosses process boundaries in W3C `traceparent` gRPC metadata:
+`TraceContextInterceptor` adds it to outgoing calls (the quilt client uses it),
+and `grpc_span` picks it up on incoming ones, so an agent run, its planning,
+steps and LLM calls, and the quilt RPCs and exec tasks they lead to all land
+in one trace.
+*/
+
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::Resource;
+use std::time::Duration;
+use tonic::codegen::http;
+use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// How long the collector has to accept a batch of spans
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where and as what spans are exported
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// Reported as the `service.name` resource attribute
+    pub service_name: String,
+    /// OTLP/HTTP traces URL, e.g. `http://localhost:4318/v1/traces`; export is off when `None`
+    pub otlp_endpoint: Option<String>,
+}
+
+impl TelemetryConfig {
+    /// Read the standard `OTEL_*` variables, falling back to `service_name`
+    pub fn from_env(service_name: &str) -> Self {
+        let otlp_endpoint = env_value("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT")
+            .or_else(|| env_value("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .map(|base| format!("{}/v1/traces", base.trim_end_matches('/'))));
+
+        Self {
+            service_name: env_value("OTEL_SERVICE_NAME").unwrap_or_else(|| service_name.to_string()),
+            otlp_endpoint,
+        }
+    }
+}
+
+fn env_value(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+/// Keeps span export running; flushes what is left and stops it when dropped
+pub struct TelemetryGuard {
+    provider: Option<TracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush traces: {}", e);
+            }
+        }
+    }
+}
+
+/// Build a provider batching spans to the configured OTLP endpoint; `None` if export is off
+pub fn tracer_provider(config: &TelemetryConfig) -> AriaResult<Option<TracerProvider>> {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint.clone())
+        .with_timeout(EXPORT_TIMEOUT)
+        .build()
+        .map_err(|e| AriaError::new(
+            ErrorCode::ConfigError,
+            ErrorCategory::Configuration,
+            ErrorSeverity::High,
+            &format!("Failed to create OTLP exporter for {}: {}", endpoint, e)
+        ))?;
+
+    Ok(Some(TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_resource(Resource::new(vec![KeyValue::new("service.name", config.service_name.clone())]))
+        .build()))
+}
+
+/// Install the global subscriber: `RUST_LOG`-filtered logs, plus span export when configured.
+/// Hold on to the returned guard until the process exits.
+pub fn init(config: &TelemetryConfig) -> AriaResult<TelemetryGuard> {
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let provider = tracer_provider(config)?;
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(config.service_name.clone()))
+            .with_filter(LevelFilter::INFO)
+    });
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(EnvFilter::from_default_env()))
+        .with(otel_layer)
+        .try_init()
+        .map_err(|e| AriaError::new(
+            ErrorCode::InitializationFailed,
+            ErrorCategory::System,
+            ErrorSeverity::High,
+            &format!("Failed to install tracing subscriber: {}", e)
+        ))?;
+
+    if let Some(endpoint) = &config.otlp_endpoint {
+        tracing::info!("Exporting traces for {} to {}", config.service_name, endpoint);
+    }
+    Ok(TelemetryGuard { provider })
+}
+
+/// Client interceptor adding the current span's trace context to outgoing requests
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceContextInterceptor;
+
+impl Interceptor for TraceContextInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        inject(&Span::current(), request.metadata_mut());
+        Ok(request)
+    }
+}
+
+/// Server `trace_fn`: a span per incoming request, continuing the caller's trace if it sent one
+pub fn grpc_span(request: &http::Request<()>) -> Span {
+    let span = tracing::info_span!("grpc.request", rpc = %request.uri().path());
+    span.set_parent(extract(request.headers()));
+    span
+}
+
+/// Write `span`'s trace context into request metadata
+pub fn inject(span: &Span, metadata: &mut MetadataMap) {
+    let context = span.context();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut MetadataInjector(metadata));
+    });
+}
+
+/// Read the trace context a caller sent in its request headers
+pub fn extract(headers: &http::HeaderMap) -> opentelemetry::Context {
+    opentelemetry::global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)))
+}
+
+struct MetadataInjector<'a>(&'a mut MetadataMap);
+
+impl Injector for MetadataInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(key), Ok(value)) = (MetadataKey::from_bytes(key.as_bytes()), MetadataValue::try_from(value)) {
+            self.0.insert(key, value);
+        }
+    }
+}
+
+struct HeaderExtractor<'a>(&'a http::HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{TraceContextExt, Tracer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn trace_context_round_trips_through_metadata() {
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let provider = TracerProvider::builder().build();
+        let tracer = provider.tracer("test");
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("caller");
+            let mut metadata = MetadataMap::new();
+            inject(&span, &mut metadata);
+            assert!(metadata.get("traceparent").is_some());
+
+            let headers = metadata.into_headers();
+            let extracted = extract(&headers);
+            assert_eq!(
+                extracted.span().span_context().trace_id(),
+                span.context().span().span_context().trace_id()
+            );
+        });
+    }
+
+    #[test]
+    fn missing_context_extracts_nothing() {
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let extracted = extract(&http::HeaderMap::new());
+        assert!(!extracted.span().span_context().is_valid());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn spans_are_exported_to_the_collector() {
+        // A stub collector that counts the batches it is sent
+        let received = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&received);
+        let router = axum::Router::new().route("/v1/traces", axum::routing::post(move || {
+            let counter = Arc::clone(&counter);
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                axum::http::StatusCode::OK
+            }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let config = TelemetryConfig {
+            service_name: "aria-test".to_string(),
+            otlp_endpoint: Some(format!("http://{}/v1/traces", address)),
+        };
+        let provider = tracer_provider(&config).unwrap().unwrap();
+        provider.tracer("test").in_span("exported", |_| {});
+
+        // Flushing blocks on the batch task, which runs on the runtime
+        tokio::task::spawn_blocking(move || provider.shutdown()).await.unwrap().unwrap();
+        assert_eq!(received.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn export_is_off_without_an_endpoint() {
+        let config = TelemetryConfig { service_name: "aria-test".to_string(), otlp_endpoint: None };
+        assert!(tracer_provider(&config).unwrap().is_none());
+    }
+}
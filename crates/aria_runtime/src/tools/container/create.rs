@@ -1,6 +1,6 @@
 // src/tools/container/create.rs
 
-use crate::engines::tool_registry::{RegistryEntry, ToolType, SecurityLevel, ToolScope};
+use crate::engines::tool_registry::{ContainerToolOptions, RegistryEntry, ToolType, SecurityLevel, ToolScope};
 use crate::types::ResourceRequirements;
 
 pub fn create_container_tool() -> RegistryEntry {
@@ -34,6 +34,7 @@ pub fn create_container_tool() -> RegistryEntry {
         tool_type: ToolType::Container {
             image: "".to_string(), // Placeholder, actual image is a parameter
             command: vec![],
+            options: ContainerToolOptions::default(),
         },
         scope: ToolScope::Primitive,
         bundle_id: None,
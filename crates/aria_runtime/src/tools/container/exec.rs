@@ -1,6 +1,6 @@
 // src/tools/container/exec.rs
 
-use crate::engines::tool_registry::{RegistryEntry, ToolType, SecurityLevel, ToolScope};
+use crate::engines::tool_registry::{ContainerToolOptions, RegistryEntry, ToolType, SecurityLevel, ToolScope};
 use crate::types::ResourceRequirements;
 
 pub fn exec_in_container_tool() -> RegistryEntry {
@@ -27,6 +27,7 @@ pub fn exec_in_container_tool() -> RegistryEntry {
         tool_type: ToolType::Container {
             image: "".to_string(),
             command: vec![],
+            options: ContainerToolOptions::default(),
         },
         scope: ToolScope::Primitive,
         bundle_id: None,
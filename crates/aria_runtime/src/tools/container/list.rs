@@ -1,6 +1,6 @@
 // src/tools/container/list.rs
 
-use crate::engines::tool_registry::{RegistryEntry, ToolType, SecurityLevel, ToolScope};
+use crate::engines::tool_registry::{ContainerToolOptions, RegistryEntry, ToolType, SecurityLevel, ToolScope};
 use crate::types::ResourceRequirements;
 
 pub fn list_containers_tool() -> RegistryEntry {
@@ -14,6 +14,7 @@ pub fn list_containers_tool() -> RegistryEntry {
         tool_type: ToolType::Container {
             image: "".to_string(),
             command: vec![],
+            options: ContainerToolOptions::default(),
         },
         scope: ToolScope::Primitive,
         bundle_id: None,
@@ -1,4 +1,4 @@
-use crate::engines::tool_registry::{RegistryEntry, ToolType, SecurityLevel, ToolScope};
+use crate::engines::tool_registry::{ContainerToolOptions, RegistryEntry, ToolType, SecurityLevel, ToolScope};
 use crate::types::ResourceRequirements;
 
 pub fn start_container_tool() -> RegistryEntry {
@@ -18,6 +18,7 @@ pub fn start_container_tool() -> RegistryEntry {
         tool_type: ToolType::Container {
             image: "".to_string(), // Not applicable for start operation
             command: vec![],
+            options: ContainerToolOptions::default(),
         },
         scope: ToolScope::Primitive,
         bundle_id: None,
@@ -1,4 +1,5 @@
 use crate::engines::llm::LLMHandler;
+use crate::engines::llm::context_window::{clip_to_tokens, ContextWindow, HeuristicTokenCounter};
 use crate::engines::llm::types::{LLMConfig, LLMMessage, LLMRequest};
 use crate::types::ToolResult;
 use crate::deep_size::DeepValue;
@@ -6,6 +7,9 @@ use crate::errors::AriaResult;
 use std::collections::HashMap;
 use serde_json::{json, Map};
 
+const PLAN_MODEL: &str = "gpt-4";
+const PLAN_MAX_TOKENS: u32 = 2048;
+
 pub async fn create_plan_tool_handler(parameters: DeepValue, llm_handler: &LLMHandler) -> AriaResult<ToolResult> {
     // Accept both 'objective' and 'query' for flexibility
     let mut params = HashMap::new();
@@ -129,32 +133,39 @@ Each object in the array represents a single, concrete step.
         .unwrap_or_else(|| vec!["calculator", "text_analyzer", "file_writer", "data_formatter"]);
 
     let user_content = format!(
-        "Create a JSON execution plan for the objective: \"{}\"\n\nAvailable Tools: {:?}\nConstraints: {}\nContext: {}",
+        "Create a JSON execution plan for the objective: \"{}\"\n\nAvailable Tools: {:?}\nConstraints: {}\nContext: ",
         objective_str,
         available_tools,
         serde_json::to_string(&constraints).unwrap_or_else(|_| "{}".to_string()),
-        serde_json::to_string(&context).unwrap_or_else(|_| "{}".to_string())
     );
+    let mut messages = vec![
+        LLMMessage {
+            role: "system".to_string(),
+            content: system_prompt.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+        },
+        LLMMessage {
+            role: "user".to_string(),
+            content: user_content,
+            tool_calls: None,
+            tool_call_id: None,
+        },
+    ];
+
+    // The execution context grows with the session; give it whatever budget the
+    // instructions and objective leave rather than overflowing the model
+    let window = ContextWindow::for_model(PLAN_MODEL, PLAN_MAX_TOKENS);
+    let context_budget = window.budget().saturating_sub(window.count(&messages));
+    let context_json = serde_json::to_string(&context).unwrap_or_else(|_| "{}".to_string());
+    messages[1].content.push_str(&clip_to_tokens(&context_json, context_budget, &HeuristicTokenCounter));
 
     let request = LLMRequest {
-        messages: vec![
-            LLMMessage {
-                role: "system".to_string(),
-                content: system_prompt.to_string(),
-                tool_calls: None,
-                tool_call_id: None,
-            },
-            LLMMessage {
-                role: "user".to_string(),
-                content: user_content,
-                tool_calls: None,
-                tool_call_id: None,
-            },
-        ],
+        messages,
         config: LLMConfig {
-            model: Some("gpt-4".to_string()),
+            model: Some(PLAN_MODEL.to_string()),
             temperature: 0.1,
-            max_tokens: 2048,
+            max_tokens: PLAN_MAX_TOKENS,
             top_p: None,
             frequency_penalty: None,
             presence_penalty: None,
@@ -163,6 +174,7 @@ Each object in the array represents a single, concrete step.
         tools: None,
         tool_choice: None,
         stream: Some(false),
+        cache: None,
     };
 
     let response = llm_handler.complete(request).await?;
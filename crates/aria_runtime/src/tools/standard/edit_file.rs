@@ -0,0 +1,359 @@
+use crate::types::ToolResult;
+use crate::deep_size::DeepValue;
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use std::collections::HashMap;
+use serde_json::{json, Value};
+use super::workspace::Workspace;
+
+/// Unchanged lines shown around each change in a diff
+const DIFF_CONTEXT: usize = 3;
+
+/// Largest line-comparison table built for a diff; bigger changes are shown as a full replacement
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// One search-and-replace edit
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub old_text: String,
+    pub new_text: String,
+    /// Replace every occurrence instead of requiring exactly one
+    pub replace_all: bool,
+}
+
+pub async fn edit_file_tool_handler(parameters: DeepValue, workspace: &Workspace) -> AriaResult<ToolResult> {
+    let file_path = parameters.get("filePath")
+        .or_else(|| parameters.get("path"))
+        .and_then(|v| v.as_str());
+    let dry_run = parameters.get("dryRun").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    let file_path = match file_path {
+        Some(path) => path,
+        None => return Ok(failure("filePath parameter is required".to_string(), 0)),
+    };
+
+    let edits = match parse_edits(parameters.get("edits")) {
+        Ok(edits) => edits,
+        Err(e) => return Ok(failure(e.message, 0)),
+    };
+
+    tracing::debug!("Applying {} edits to {}{}", edits.len(), file_path, if dry_run { " (dry run)" } else { "" });
+
+    let start_time = std::time::Instant::now();
+
+    let file = match workspace.read_text(file_path).await {
+        Ok(file) => file,
+        Err(e) => {
+            tracing::warn!("Failed to read {} for editing: {}", file_path, e);
+            return Ok(failure(
+                format!("Failed to read file {}: {}", file_path, e.message),
+                start_time.elapsed().as_millis() as u64,
+            ));
+        }
+    };
+
+    // Edits are applied in memory first so a failing edit leaves the file untouched
+    let (updated, replacements) = match apply_edits(&file.content, &edits) {
+        Ok(applied) => applied,
+        Err(e) => {
+            tracing::debug!("Edit to {} rejected: {}", file_path, e);
+            return Ok(failure(
+                format!("Failed to edit file {}: {}", file_path, e.message),
+                start_time.elapsed().as_millis() as u64,
+            ));
+        }
+    };
+
+    let diff = unified_diff(&file.path, &file.content, &updated);
+
+    if !dry_run && updated != file.content {
+        if let Err(e) = workspace.write_text(&file.path, &updated).await {
+            tracing::warn!("Failed to write edits to {}: {}", file_path, e);
+            return Ok(failure(
+                format!("Failed to write file {}: {}", file_path, e.message),
+                start_time.elapsed().as_millis() as u64,
+            ));
+        }
+    }
+
+    let execution_time = start_time.elapsed().as_millis() as u64;
+
+    tracing::debug!("{} {} replacements in {}",
+                    if dry_run { "Previewed" } else { "Applied" }, replacements, file.path);
+
+    let mut result_metadata = HashMap::new();
+    result_metadata.insert("path".to_string(), DeepValue(json!(file.path)));
+    result_metadata.insert("replacements".to_string(), DeepValue(json!(replacements)));
+    result_metadata.insert("dry_run".to_string(), DeepValue(json!(dry_run)));
+    result_metadata.insert("execution_time_ms".to_string(), DeepValue(json!(execution_time)));
+
+    let result = json!({
+        "path": file.path,
+        "replacements": replacements,
+        "diff": diff,
+        "dryRun": dry_run,
+        "applied": !dry_run,
+        "success": true,
+        "metadata": {
+            "previousSize": file.size,
+            "newSize": updated.len(),
+            "executionTime": execution_time
+        }
+    });
+
+    Ok(ToolResult {
+        success: true,
+        result: Some(result.into()),
+        error: None,
+        metadata: result_metadata,
+        execution_time_ms: execution_time,
+        resource_usage: None,
+    })
+}
+
+fn failure(error: String, execution_time_ms: u64) -> ToolResult {
+    ToolResult {
+        success: false,
+        result: None,
+        error: Some(error),
+        metadata: HashMap::new(),
+        execution_time_ms,
+        resource_usage: None,
+    }
+}
+
+fn parse_edits(edits: Option<&Value>) -> AriaResult<Vec<TextEdit>> {
+    let edits = edits
+        .and_then(|v| v.as_array())
+        .filter(|edits| !edits.is_empty())
+        .ok_or_else(|| edit_error("edits must be a non-empty array"))?;
+
+    edits.iter().enumerate().map(|(i, edit)| {
+        let old_text = edit.get("oldText").and_then(|v| v.as_str())
+            .ok_or_else(|| edit_error(&format!("edits[{}].oldText is required", i)))?;
+        let new_text = edit.get("newText").and_then(|v| v.as_str())
+            .ok_or_else(|| edit_error(&format!("edits[{}].newText is required", i)))?;
+        Ok(TextEdit {
+            old_text: old_text.to_string(),
+            new_text: new_text.to_string(),
+            replace_all: edit.get("replaceAll").and_then(|v| v.as_bool()).unwrap_or(false),
+        })
+    }).collect()
+}
+
+/// Apply edits in order, each against the output of the previous one.
+/// Either every edit applies or an error is returned; returns the new text and the number of replacements.
+pub fn apply_edits(content: &str, edits: &[TextEdit]) -> AriaResult<(String, usize)> {
+    let mut text = content.to_string();
+    let mut replacements = 0;
+
+    for (i, edit) in edits.iter().enumerate() {
+        if edit.old_text.is_empty() {
+            return Err(edit_error(&format!("edits[{}].oldText must not be empty", i)));
+        }
+
+        let occurrences = text.matches(edit.old_text.as_str()).count();
+        if occurrences == 0 {
+            return Err(edit_error(&format!("edits[{}].oldText was not found in the file", i)));
+        }
+        if occurrences > 1 && !edit.replace_all {
+            return Err(edit_error(&format!(
+                "edits[{}].oldText matches {} times; add surrounding context or set replaceAll",
+                i, occurrences
+            )));
+        }
+
+        text = text.replace(edit.old_text.as_str(), &edit.new_text);
+        replacements += occurrences;
+    }
+
+    Ok((text, replacements))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DiffOp {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Render a line-based unified diff between two versions of a file.
+/// Returns an empty string when the versions are identical.
+pub fn unified_diff(path: &str, old: &str, new: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    let changes: Vec<usize> = ops.iter().enumerate()
+        .filter(|(_, op)| **op != DiffOp::Equal)
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Group changes whose context windows touch into a single hunk
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &index in &changes {
+        match groups.last_mut() {
+            Some((_, last)) if index - *last <= 2 * DIFF_CONTEXT + 1 => *last = index,
+            _ => groups.push((index, index)),
+        }
+    }
+
+    let mut out = format!("--- a/{}\n+++ b/{}\n", path, path);
+    for (first, last) in groups {
+        let start = first.saturating_sub(DIFF_CONTEXT);
+        let end = (last + 1 + DIFF_CONTEXT).min(ops.len());
+
+        // Line positions reached before the hunk starts
+        let old_before = ops[..start].iter().filter(|op| **op != DiffOp::Insert).count();
+        let new_before = ops[..start].iter().filter(|op| **op != DiffOp::Delete).count();
+        let old_count = ops[start..end].iter().filter(|op| **op != DiffOp::Insert).count();
+        let new_count = ops[start..end].iter().filter(|op| **op != DiffOp::Delete).count();
+
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            if old_count == 0 { old_before } else { old_before + 1 }, old_count,
+            if new_count == 0 { new_before } else { new_before + 1 }, new_count,
+        ));
+
+        let (mut old_index, mut new_index) = (old_before, new_before);
+        for op in &ops[start..end] {
+            match op {
+                DiffOp::Equal => {
+                    out.push_str(&format!(" {}\n", old_lines[old_index]));
+                    old_index += 1;
+                    new_index += 1;
+                }
+                DiffOp::Delete => {
+                    out.push_str(&format!("-{}\n", old_lines[old_index]));
+                    old_index += 1;
+                }
+                DiffOp::Insert => {
+                    out.push_str(&format!("+{}\n", new_lines[new_index]));
+                    new_index += 1;
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Line-level edit script: common prefix and suffix are matched directly,
+/// the changed middle through a longest-common-subsequence table.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut ops = vec![DiffOp::Equal; prefix];
+
+    if a.len().saturating_mul(b.len()) > MAX_DIFF_CELLS {
+        ops.resize(ops.len() + a.len(), DiffOp::Delete);
+        ops.resize(ops.len() + b.len(), DiffOp::Insert);
+    } else {
+        // lcs[i][j] = length of the LCS of a[i..] and b[j..]
+        let width = b.len() + 1;
+        let mut lcs = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i * width + j] = if a[i] == b[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            if a[i] == b[j] {
+                ops.push(DiffOp::Equal);
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+                ops.push(DiffOp::Delete);
+                i += 1;
+            } else {
+                ops.push(DiffOp::Insert);
+                j += 1;
+            }
+        }
+        ops.resize(ops.len() + a.len() - i, DiffOp::Delete);
+        ops.resize(ops.len() + b.len() - j, DiffOp::Insert);
+    }
+
+    ops.resize(ops.len() + suffix, DiffOp::Equal);
+    ops
+}
+
+fn edit_error(message: &str) -> AriaError {
+    AriaError::new(ErrorCode::ToolInvalidParameters, ErrorCategory::Tool, ErrorSeverity::Low, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(old_text: &str, new_text: &str, replace_all: bool) -> TextEdit {
+        TextEdit { old_text: old_text.to_string(), new_text: new_text.to_string(), replace_all }
+    }
+
+    #[test]
+    fn test_edits_apply_in_order() {
+        let (text, count) = apply_edits(
+            "fn main() {\n    run();\n}\n",
+            &[edit("run()", "start()", false), edit("start();", "start();\n    stop();", false)],
+        ).unwrap();
+
+        assert_eq!(text, "fn main() {\n    start();\n    stop();\n}\n");
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_edits_are_all_or_nothing() {
+        assert!(apply_edits("a b a", &[edit("b", "c", false), edit("missing", "x", false)]).is_err());
+        assert!(apply_edits("a b a", &[edit("", "x", false)]).is_err());
+
+        let err = apply_edits("a b a", &[edit("a", "x", false)]).unwrap_err();
+        assert!(err.message.contains("2 times"));
+
+        let (text, count) = apply_edits("a b a", &[edit("a", "x", true)]).unwrap();
+        assert_eq!((text.as_str(), count), ("x b x", 2));
+    }
+
+    #[test]
+    fn test_unified_diff_single_change() {
+        let diff = unified_diff("f.txt", "a\nb\nc\n", "a\nB\nc\n");
+        assert_eq!(diff, "--- a/f.txt\n+++ b/f.txt\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n");
+        assert_eq!(unified_diff("f.txt", "same\n", "same\n"), "");
+    }
+
+    #[test]
+    fn test_unified_diff_splits_distant_hunks() {
+        let old: Vec<String> = (1..=20).map(|n| n.to_string()).collect();
+        let mut new = old.clone();
+        new[1] = "two".to_string();
+        new[17] = "eighteen".to_string();
+
+        let diff = unified_diff("n.txt", &old.join("\n"), &new.join("\n"));
+        assert!(diff.contains("@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n"));
+        assert!(diff.contains("@@ -15,6 +15,6 @@\n"));
+        assert_eq!(diff.matches("@@ -").count(), 2);
+    }
+
+    #[test]
+    fn test_unified_diff_into_empty_file() {
+        assert_eq!(unified_diff("new.txt", "", "x\n"), "--- a/new.txt\n+++ b/new.txt\n@@ -0,0 +1,1 @@\n+x\n");
+    }
+}
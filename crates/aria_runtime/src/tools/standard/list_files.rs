@@ -0,0 +1,60 @@
+use crate::types::ToolResult;
+use crate::deep_size::DeepValue;
+use crate::errors::AriaResult;
+use std::collections::HashMap;
+use serde_json::json;
+use super::workspace::Workspace;
+
+pub async fn list_files_tool_handler(parameters: DeepValue, workspace: &Workspace) -> AriaResult<ToolResult> {
+    let pattern = parameters.get("pattern")
+        .and_then(|v| v.as_str())
+        .filter(|p| !p.trim().is_empty());
+
+    tracing::debug!("Listing workspace files (pattern: {})", pattern.unwrap_or("*"));
+
+    let start_time = std::time::Instant::now();
+
+    match workspace.list(pattern).await {
+        Ok(listing) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+
+            tracing::debug!("Found {} files{}", listing.files.len(),
+                            if listing.truncated { " (truncated)" } else { "" });
+
+            let mut result_metadata = HashMap::new();
+            result_metadata.insert("count".to_string(), DeepValue(json!(listing.files.len())));
+            result_metadata.insert("truncated".to_string(), DeepValue(json!(listing.truncated)));
+            result_metadata.insert("execution_time_ms".to_string(), DeepValue(json!(execution_time)));
+
+            let result = json!({
+                "files": listing.files,
+                "count": listing.files.len(),
+                "truncated": listing.truncated,
+                "pattern": pattern,
+                "success": true
+            });
+
+            Ok(ToolResult {
+                success: true,
+                result: Some(result.into()),
+                error: None,
+                metadata: result_metadata,
+                execution_time_ms: execution_time,
+                resource_usage: None,
+            })
+        }
+        Err(e) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
+            tracing::warn!("Failed to list workspace files: {}", e);
+
+            Ok(ToolResult {
+                success: false,
+                result: None,
+                error: Some(format!("Failed to list files: {}", e.message)),
+                metadata: HashMap::new(),
+                execution_time_ms: execution_time,
+                resource_usage: None,
+            })
+        }
+    }
+}
@@ -5,6 +5,9 @@ pub mod write_file;
 pub mod read_file;
 pub mod parse_document;
 pub mod write_code;
+pub mod list_files;
+pub mod edit_file;
+pub mod workspace;
 
 pub use create_plan::create_plan_tool_handler;
 pub use ponder::ponder_tool_handler;
@@ -12,4 +15,7 @@ pub use web_search::web_search_tool_handler;
 pub use write_file::write_file_tool_handler;
 pub use read_file::read_file_tool_handler;
 pub use parse_document::parse_document_tool_handler;
-pub use write_code::write_code_tool_handler; 
\ No newline at end of file
+pub use write_code::write_code_tool_handler;
+pub use list_files::list_files_tool_handler;
+pub use edit_file::edit_file_tool_handler;
+pub use workspace::{Workspace, WorkspaceBackend, WorkspaceConfig, WorkspaceManager};
@@ -138,6 +138,7 @@ DETAILS:
         tools: None,
         tool_choice: None,
         stream: Some(false),
+        cache: None,
     };
 
     match llm_handler.complete(request).await {
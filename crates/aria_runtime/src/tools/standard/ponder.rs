@@ -225,6 +225,7 @@ pub async fn ponder_tool_handler(parameters: DeepValue, llm_handler: &LLMHandler
         tools: None,
         tool_choice: None,
         stream: Some(false),
+        cache: None,
     };
 
     let conclusion_response = llm_handler.complete(conclusion_request).await?;
@@ -364,6 +365,7 @@ fn think_deeply_send_safe<'a>(
             tools: None,
             tool_choice: None,
             stream: Some(false),
+            cache: None,
         };
 
         let response = match llm_handler.complete(request).await {
@@ -1,13 +1,12 @@
-use crate::engines::llm::LLMHandler;
 use crate::types::ToolResult;
 use crate::deep_size::DeepValue;
 use crate::errors::AriaResult;
 use std::collections::HashMap;
 use serde_json::json;
-use tokio::fs;
 use std::path::Path;
+use super::workspace::Workspace;
 
-pub async fn read_file_tool_handler(parameters: DeepValue, _llm_handler: &LLMHandler) -> AriaResult<ToolResult> {
+pub async fn read_file_tool_handler(parameters: DeepValue, workspace: &Workspace) -> AriaResult<ToolResult> {
     // Extract parameters
     let mut params: HashMap<String, DeepValue> = HashMap::new();
     if let Some(obj) = parameters.as_object() {
@@ -38,101 +37,61 @@ pub async fn read_file_tool_handler(parameters: DeepValue, _llm_handler: &LLMHan
 
     let start_time = std::time::Instant::now();
 
-    // Read file content
-    match fs::read_to_string(file_path).await {
-        Ok(content) => {
-            // Get file metadata
-            match fs::metadata(file_path).await {
-                Ok(file_metadata) => {
-                    let file_size = file_metadata.len();
-                    let execution_time = start_time.elapsed().as_millis() as u64;
-
-                    // Auto-detect format from file extension or use override
-                    let detected_format = if let Some(format) = format_override {
-                        format.to_string()
-                    } else {
-                        Path::new(file_path)
-                            .extension()
-                            .and_then(|ext| ext.to_str())
-                            .map(|ext| ext.to_lowercase())
-                            .unwrap_or_else(|| "text".to_string())
-                    };
-
-                    println!("[READFILE] Successfully read {} bytes from {} (format: {})", 
-                             file_size, file_path, detected_format);
-
-                    // Create metadata structure
-                    let metadata_obj = json!({
-                        "format": detected_format,
-                        "size": file_size,
-                        "path": file_path,
-                        "type": "file",
-                        "encoding": "utf-8",
-                        "lines": content.lines().count(),
-                        "characters": content.len(),
-                        "executionTime": execution_time
-                    });
-
-                    // Create result metadata for ToolResult
-                    let mut result_metadata = HashMap::new();
-                    result_metadata.insert("path".to_string(), DeepValue(json!(file_path)));
-                    result_metadata.insert("size".to_string(), DeepValue(json!(file_size)));
-                    result_metadata.insert("format".to_string(), DeepValue(json!(detected_format)));
-                    result_metadata.insert("execution_time_ms".to_string(), DeepValue(json!(execution_time)));
-
-                    let result = json!({
-                        "content": content,
-                        "metadata": metadata_obj,
-                        "success": true,
-                        "filePath": file_path,
-                        "fileSize": file_size,
-                        "format": detected_format
-                    });
-
-                    Ok(ToolResult {
-                        success: true,
-                        result: Some(result.into()),
-                        error: None,
-                        metadata: result_metadata,
-                        execution_time_ms: execution_time,
-                        resource_usage: None,
-                    })
-                }
-                Err(e) => {
-                    // File read but couldn't get metadata
-                    let execution_time = start_time.elapsed().as_millis() as u64;
-                    let detected_format = format_override.unwrap_or("text").to_string();
-
-                    println!("[READFILE] File read but failed to get metadata: {}", e);
-
-                    let metadata_obj = json!({
-                        "format": detected_format,
-                        "size": content.len(),
-                        "path": file_path,
-                        "type": "file",
-                        "encoding": "utf-8",
-                        "lines": content.lines().count(),
-                        "characters": content.len(),
-                        "warning": "File metadata unavailable"
-                    });
+    // Read file content, confined to the session workspace
+    match workspace.read_text(file_path).await {
+        Ok(file) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
 
-                    let result = json!({
-                        "content": content,
-                        "metadata": metadata_obj,
-                        "success": true,
-                        "warning": "Could not retrieve file system metadata"
-                    });
+            // Auto-detect format from file extension or use override
+            let detected_format = if let Some(format) = format_override {
+                format.to_string()
+            } else {
+                Path::new(&file.path)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| ext.to_lowercase())
+                    .unwrap_or_else(|| "text".to_string())
+            };
+
+            println!("[READFILE] Successfully read {} bytes from {} (format: {})",
+                     file.size, file.path, detected_format);
+
+            // Create metadata structure
+            let metadata_obj = json!({
+                "format": detected_format,
+                "size": file.size,
+                "path": file.path,
+                "type": "file",
+                "encoding": "utf-8",
+                "lines": file.content.lines().count(),
+                "characters": file.content.len(),
+                "executionTime": execution_time
+            });
+
+            // Create result metadata for ToolResult
+            let mut result_metadata = HashMap::new();
+            result_metadata.insert("path".to_string(), DeepValue(json!(file.path)));
+            result_metadata.insert("size".to_string(), DeepValue(json!(file.size)));
+            result_metadata.insert("format".to_string(), DeepValue(json!(detected_format)));
+            result_metadata.insert("execution_time_ms".to_string(), DeepValue(json!(execution_time)));
+
+            let result = json!({
+                "content": file.content,
+                "metadata": metadata_obj,
+                "success": true,
+                "filePath": file.path,
+                "fileSize": file.size,
+                "format": detected_format
+            });
 
-                    Ok(ToolResult {
-                        success: true,
-                        result: Some(result.into()),
-                        error: None,
-                        metadata: HashMap::new(),
-                        execution_time_ms: execution_time,
-                        resource_usage: None,
-                    })
-                }
-            }
+            Ok(ToolResult {
+                success: true,
+                result: Some(result.into()),
+                error: None,
+                metadata: result_metadata,
+                execution_time_ms: execution_time,
+                resource_usage: None,
+            })
         }
         Err(e) => {
             let execution_time = start_time.elapsed().as_millis() as u64;
@@ -141,11 +100,11 @@ pub async fn read_file_tool_handler(parameters: DeepValue, _llm_handler: &LLMHan
             Ok(ToolResult {
                 success: false,
                 result: None,
-                error: Some(format!("Failed to read file {}: {}", file_path, e)),
+                error: Some(format!("Failed to read file {}: {}", file_path, e.message)),
                 metadata: HashMap::new(),
                 execution_time_ms: execution_time,
                 resource_usage: None,
             })
         }
     }
-} 
\ No newline at end of file
+}
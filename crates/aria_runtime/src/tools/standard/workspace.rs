@@ -0,0 +1,675 @@
+/*!
+# Session Workspaces
+
+Filesystem tools never touch arbitrary host paths. Every call is scoped to a
+workspace root, one per user and session, and every path is checked before use:
+
+- paths are normalized lexically, so `..` cannot climb above the root and
+  absolute paths are only accepted when they already point inside it;
+- the deepest existing ancestor is canonicalized, so a symlink inside the
+  workspace cannot lead a read or write outside it;
+- reads and writes are capped in size and reads refuse binary content.
+
+Workspaces are namespaced by user: a session's workspace is
+`<base_dir>/<user>/<session>`, and calls outside any session share only the
+user's own `<base_dir>/<user>/shared`. Calls that cannot be traced to a user
+get no workspace at all, so one tenant's files are never reachable by another.
+
+A workspace normally lives on the host, under `WorkspaceConfig::base_dir`.
+When a session's files live in a quilt volume, the workspace can instead be
+backed by a container that has the volume mounted; the same checks then run
+inside the container.
+*/
+
+use crate::engines::container::quilt::QuiltService;
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use regex::Regex;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+
+/// Bytes inspected when deciding whether a file is binary
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+/// Largest piece of content passed to a single container write command
+const CONTAINER_WRITE_CHUNK: usize = 64 * 1024;
+
+/// Workspace of a user's calls that are not attributed to a session
+pub const SHARED_WORKSPACE: &str = "shared";
+
+/// Limits and location for session workspaces
+#[derive(Debug, Clone)]
+pub struct WorkspaceConfig {
+    /// Host directory holding a directory of workspaces per user
+    pub base_dir: PathBuf,
+    /// Largest file a tool may read
+    pub max_read_bytes: u64,
+    /// Largest content a tool may write in one call
+    pub max_write_bytes: u64,
+    /// Most paths a listing returns
+    pub max_list_entries: usize,
+}
+
+impl Default for WorkspaceConfig {
+    fn default() -> Self {
+        Self {
+            base_dir: PathBuf::from("/tmp/aria-workspaces"),
+            max_read_bytes: 1024 * 1024,
+            max_write_bytes: 1024 * 1024,
+            max_list_entries: 1000,
+        }
+    }
+}
+
+/// Where a workspace's files live
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkspaceBackend {
+    /// Directly on the host filesystem
+    Local,
+    /// In a quilt volume mounted into a running container
+    Container { container_id: String },
+}
+
+/// A text file read from a workspace
+#[derive(Debug, Clone)]
+pub struct WorkspaceFile {
+    /// Path relative to the workspace root
+    pub path: String,
+    pub content: String,
+    pub size: u64,
+}
+
+/// Result of writing a file into a workspace
+#[derive(Debug, Clone)]
+pub struct WriteOutcome {
+    /// Path relative to the workspace root
+    pub path: String,
+    pub bytes_written: u64,
+    pub created: bool,
+}
+
+/// Files matched by a listing
+#[derive(Debug, Clone)]
+pub struct FileListing {
+    /// Paths relative to the workspace root, sorted
+    pub files: Vec<String>,
+    /// More files matched than `max_list_entries`
+    pub truncated: bool,
+}
+
+/// A directory tree that filesystem tools are confined to
+#[derive(Clone)]
+pub struct Workspace {
+    root: PathBuf,
+    backend: WorkspaceBackend,
+    config: WorkspaceConfig,
+    quilt: Option<Arc<Mutex<QuiltService>>>,
+}
+
+impl Workspace {
+    /// Open (creating if needed) a workspace rooted at a host directory
+    pub async fn local(root: impl Into<PathBuf>, config: WorkspaceConfig) -> AriaResult<Self> {
+        let root = root.into();
+        tokio::fs::create_dir_all(&root).await.map_err(io_error)?;
+        let root = tokio::fs::canonicalize(&root).await.map_err(io_error)?;
+        Ok(Self { root, backend: WorkspaceBackend::Local, config, quilt: None })
+    }
+
+    /// A workspace whose files live at `mount_path` inside a running container
+    pub fn in_container(
+        container_id: &str,
+        mount_path: &str,
+        config: WorkspaceConfig,
+        quilt: Arc<Mutex<QuiltService>>,
+    ) -> Self {
+        Self {
+            root: PathBuf::from(mount_path),
+            backend: WorkspaceBackend::Container { container_id: container_id.to_string() },
+            config,
+            quilt: Some(quilt),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn backend(&self) -> &WorkspaceBackend {
+        &self.backend
+    }
+
+    pub fn config(&self) -> &WorkspaceConfig {
+        &self.config
+    }
+
+    /// Resolve a requested path to an absolute path that is guaranteed to lie inside the workspace
+    pub async fn resolve(&self, requested: &str) -> AriaResult<PathBuf> {
+        let candidate = self.lexical_path(requested)?;
+        match &self.backend {
+            WorkspaceBackend::Local => self.contain_local(requested, candidate).await,
+            WorkspaceBackend::Container { .. } => self.contain_in_container(requested, candidate).await,
+        }
+    }
+
+    /// Path relative to the workspace root, for reporting back to callers
+    pub fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| path.to_string_lossy().into_owned())
+    }
+
+    /// Read a text file, refusing files over the read limit and binary content
+    pub async fn read_text(&self, requested: &str) -> AriaResult<WorkspaceFile> {
+        let path = self.resolve(requested).await?;
+        let bytes = match &self.backend {
+            WorkspaceBackend::Local => {
+                let metadata = tokio::fs::metadata(&path).await.map_err(io_error)?;
+                if !metadata.is_file() {
+                    return Err(file_error(&format!("'{}' is not a regular file", requested)));
+                }
+                self.check_read_size(requested, metadata.len())?;
+                tokio::fs::read(&path).await.map_err(io_error)?
+            }
+            WorkspaceBackend::Container { .. } => {
+                let path_arg = path.to_string_lossy().into_owned();
+                let size = self.exec_checked(vec![
+                    "stat".to_string(), "-L".to_string(), "-c".to_string(), "%s".to_string(), "--".to_string(), path_arg.clone(),
+                ]).await?;
+                let size = size.trim().parse::<u64>()
+                    .map_err(|_| file_error(&format!("Could not determine the size of '{}'", requested)))?;
+                self.check_read_size(requested, size)?;
+                self.exec_checked(vec!["cat".to_string(), "--".to_string(), path_arg]).await?.into_bytes()
+            }
+        };
+
+        let size = bytes.len() as u64;
+        let content = decode_text(requested, bytes)?;
+        Ok(WorkspaceFile { path: self.relative(&path), content, size })
+    }
+
+    /// Write a text file, creating parent directories inside the workspace as needed
+    pub async fn write_text(&self, requested: &str, content: &str) -> AriaResult<WriteOutcome> {
+        if content.len() as u64 > self.config.max_write_bytes {
+            return Err(file_error(&format!(
+                "Content for '{}' is {} bytes, over the {} byte write limit",
+                requested, content.len(), self.config.max_write_bytes
+            )));
+        }
+
+        let path = self.resolve(requested).await?;
+        let created = match &self.backend {
+            WorkspaceBackend::Local => {
+                let created = tokio::fs::symlink_metadata(&path).await.is_err();
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+                }
+                tokio::fs::write(&path, content).await.map_err(io_error)?;
+                created
+            }
+            WorkspaceBackend::Container { .. } => {
+                let path_arg = path.to_string_lossy().into_owned();
+                let existing = self.exec(vec!["test".to_string(), "-e".to_string(), path_arg.clone()]).await?;
+                self.exec_checked(vec![
+                    "sh".to_string(), "-c".to_string(),
+                    "mkdir -p \"$(dirname \"$1\")\" && : > \"$1\"".to_string(),
+                    "sh".to_string(), path_arg.clone(),
+                ]).await?;
+                // Content travels as an argument, so large files are appended in chunks
+                for chunk in chunk_str(content, CONTAINER_WRITE_CHUNK) {
+                    self.exec_checked(vec![
+                        "sh".to_string(), "-c".to_string(),
+                        "printf '%s' \"$2\" >> \"$1\"".to_string(),
+                        "sh".to_string(), path_arg.clone(), chunk.to_string(),
+                    ]).await?;
+                }
+                existing.exit_code != 0
+            }
+        };
+
+        Ok(WriteOutcome { path: self.relative(&path), bytes_written: content.len() as u64, created })
+    }
+
+    /// List files whose workspace-relative path matches a glob (`*`, `**`, `?`, `{a,b}`)
+    pub async fn list(&self, pattern: Option<&str>) -> AriaResult<FileListing> {
+        let matcher = match pattern.map(str::trim).filter(|p| !p.is_empty()) {
+            Some(pattern) => Some(glob_to_regex(pattern)?),
+            None => None,
+        };
+
+        let mut files = match &self.backend {
+            WorkspaceBackend::Local => self.walk_local().await?,
+            WorkspaceBackend::Container { .. } => {
+                let output = self.exec_checked(vec![
+                    "find".to_string(), self.root.to_string_lossy().into_owned(), "-type".to_string(), "f".to_string(),
+                ]).await?;
+                output.lines().map(|line| self.relative(Path::new(line))).collect()
+            }
+        };
+        if let Some(matcher) = &matcher {
+            files.retain(|f| matcher.is_match(f));
+        }
+        files.sort();
+
+        let truncated = files.len() > self.config.max_list_entries;
+        files.truncate(self.config.max_list_entries);
+        Ok(FileListing { files, truncated })
+    }
+
+    fn check_read_size(&self, requested: &str, size: u64) -> AriaResult<()> {
+        if size > self.config.max_read_bytes {
+            return Err(file_error(&format!(
+                "'{}' is {} bytes, over the {} byte read limit",
+                requested, size, self.config.max_read_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    /// Join a requested path onto the root without touching the filesystem
+    fn lexical_path(&self, requested: &str) -> AriaResult<PathBuf> {
+        let requested_path = Path::new(requested.trim());
+        if requested_path.as_os_str().is_empty() {
+            return Err(file_error("A file path is required"));
+        }
+
+        let relative = if requested_path.is_absolute() {
+            requested_path.strip_prefix(&self.root).map_err(|_| outside_error(requested))?
+        } else {
+            requested_path
+        };
+
+        let mut normalized = PathBuf::new();
+        for component in relative.components() {
+            match component {
+                Component::Normal(part) => normalized.push(part),
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !normalized.pop() {
+                        return Err(outside_error(requested));
+                    }
+                }
+                Component::RootDir | Component::Prefix(_) => return Err(outside_error(requested)),
+            }
+        }
+        Ok(self.root.join(normalized))
+    }
+
+    /// Canonicalize the deepest existing ancestor so symlinks cannot escape the root
+    async fn contain_local(&self, requested: &str, candidate: PathBuf) -> AriaResult<PathBuf> {
+        let mut existing = candidate;
+        let mut missing: Vec<OsString> = Vec::new();
+        loop {
+            match tokio::fs::symlink_metadata(&existing).await {
+                Ok(_) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    match (existing.file_name(), existing.parent()) {
+                        (Some(name), Some(parent)) => {
+                            missing.push(name.to_os_string());
+                            existing = parent.to_path_buf();
+                        }
+                        _ => return Err(outside_error(requested)),
+                    }
+                }
+                Err(e) => return Err(io_error(e)),
+            }
+        }
+
+        // Dangling symlinks fail to canonicalize and are refused with everything else
+        let canonical = tokio::fs::canonicalize(&existing).await.map_err(|_| outside_error(requested))?;
+        if !canonical.starts_with(&self.root) {
+            return Err(outside_error(requested));
+        }
+        Ok(missing.into_iter().rev().fold(canonical, |path, part| path.join(part)))
+    }
+
+    async fn contain_in_container(&self, requested: &str, candidate: PathBuf) -> AriaResult<PathBuf> {
+        let output = self.exec_checked(vec![
+            "realpath".to_string(), "-m".to_string(), "--".to_string(),
+            self.root.to_string_lossy().into_owned(),
+            candidate.to_string_lossy().into_owned(),
+        ]).await?;
+
+        let mut lines = output.lines();
+        match (lines.next(), lines.next()) {
+            (Some(root), Some(resolved)) if Path::new(resolved).starts_with(root) => {
+                Ok(self.root.join(Path::new(resolved).strip_prefix(root).unwrap_or(Path::new(""))))
+            }
+            _ => Err(outside_error(requested)),
+        }
+    }
+
+    async fn walk_local(&self) -> AriaResult<Vec<String>> {
+        let mut files = Vec::new();
+        let mut pending = vec![self.root.clone()];
+        while let Some(dir) = pending.pop() {
+            let mut entries = tokio::fs::read_dir(&dir).await.map_err(io_error)?;
+            while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+                // file_type does not follow symlinks, so linked directories are never entered
+                let file_type = entry.file_type().await.map_err(io_error)?;
+                if file_type.is_dir() {
+                    pending.push(entry.path());
+                } else if file_type.is_file() {
+                    files.push(self.relative(&entry.path()));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    async fn exec(&self, command: Vec<String>) -> AriaResult<crate::types::ContainerExecutionResult> {
+        let (container_id, quilt) = match (&self.backend, &self.quilt) {
+            (WorkspaceBackend::Container { container_id }, Some(quilt)) => (container_id.clone(), quilt),
+            _ => return Err(AriaError::new(
+                ErrorCode::ContainerError,
+                ErrorCategory::Container,
+                ErrorSeverity::High,
+                "Workspace is not backed by a container",
+            )),
+        };
+        quilt.lock().await.run_in_container(container_id, command).await
+    }
+
+    async fn exec_checked(&self, command: Vec<String>) -> AriaResult<String> {
+        let program = command.first().cloned().unwrap_or_default();
+        let result = self.exec(command).await?;
+        if result.exit_code != 0 {
+            return Err(file_error(&format!("{} failed in workspace container: {}", program, result.stderr.trim())));
+        }
+        Ok(result.stdout)
+    }
+}
+
+/// Hands out per-user, per-session workspaces
+pub struct WorkspaceManager {
+    config: WorkspaceConfig,
+    quilt: Arc<Mutex<QuiltService>>,
+    /// Sessions whose workspace is a volume mounted into a container: (user, session) -> (container, mount path)
+    container_mounts: RwLock<HashMap<(String, String), (String, String)>>,
+}
+
+impl WorkspaceManager {
+    pub fn new(config: WorkspaceConfig, quilt: Arc<Mutex<QuiltService>>) -> Self {
+        Self { config, quilt, container_mounts: RwLock::new(HashMap::new()) }
+    }
+
+    pub fn config(&self) -> &WorkspaceConfig {
+        &self.config
+    }
+
+    /// Workspace of a user's session, or the user's shared workspace for calls outside a session.
+    /// Calls without a user are refused.
+    pub async fn workspace_for(&self, user_id: Option<&str>, session_id: Option<&str>) -> AriaResult<Workspace> {
+        let (user, name) = workspace_path(user_id, session_id)?;
+        let key = (user.to_string(), name.to_string());
+        if let Some((container_id, mount_path)) = self.container_mounts.read().await.get(&key) {
+            return Ok(Workspace::in_container(container_id, mount_path, self.config.clone(), self.quilt.clone()));
+        }
+
+        Workspace::local(self.config.base_dir.join(user).join(name), self.config.clone()).await
+    }
+
+    /// Serve a user's session workspace from a quilt volume mounted at `mount_path` in a container
+    pub async fn mount_container_volume(&self, user_id: &str, session_id: &str, container_id: &str, mount_path: &str) {
+        self.container_mounts.write().await.insert(
+            (user_id.to_string(), session_id.to_string()),
+            (container_id.to_string(), mount_path.to_string()),
+        );
+    }
+
+    /// Return a user's session to its host workspace
+    pub async fn unmount_container_volume(&self, user_id: &str, session_id: &str) -> bool {
+        self.container_mounts.write().await
+            .remove(&(user_id.to_string(), session_id.to_string()))
+            .is_some()
+    }
+}
+
+/// Decode file content as UTF-8 text, refusing binary files
+fn decode_text(requested: &str, bytes: Vec<u8>) -> AriaResult<String> {
+    let sniff = &bytes[..bytes.len().min(BINARY_SNIFF_BYTES)];
+    if sniff.contains(&0) {
+        return Err(file_error(&format!("'{}' appears to be a binary file", requested)));
+    }
+    String::from_utf8(bytes)
+        .map_err(|_| file_error(&format!("'{}' is not valid UTF-8 text", requested)))
+}
+
+/// Translate a glob over `/`-separated relative paths into an anchored regex
+fn glob_to_regex(pattern: &str) -> AriaResult<Regex> {
+    let mut regex = String::from("^");
+    let chars: Vec<char> = pattern.trim_start_matches("./").chars().collect();
+    let mut i = 0;
+    let mut in_group = false;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    i += 2;
+                } else {
+                    regex.push_str(".*");
+                    i += 1;
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '{' if !in_group => {
+                in_group = true;
+                regex.push_str("(?:");
+            }
+            '}' if in_group => {
+                in_group = false;
+                regex.push(')');
+            }
+            ',' if in_group => regex.push('|'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    regex.push('$');
+
+    Regex::new(&regex).map_err(|e| file_error(&format!("Invalid glob pattern '{}': {}", pattern, e)))
+}
+
+/// Split text into pieces of at most `max` bytes on character boundaries
+fn chunk_str(text: &str, max: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut end = rest.len().min(max);
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end == 0 {
+            // A single character wider than `max` still has to go somewhere
+            end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
+fn outside_error(requested: &str) -> AriaError {
+    AriaError::new(
+        ErrorCode::PermissionDenied,
+        ErrorCategory::Security,
+        ErrorSeverity::Medium,
+        &format!("Path '{}' is outside the workspace", requested),
+    )
+}
+
+/// Directory name of a session's workspace. Session ids are used verbatim, so ids that
+/// are not a plain directory name, or that would land in the shared workspace, are refused
+/// rather than rewritten into a name another session could also reach.
+fn workspace_name(session_id: Option<&str>) -> AriaResult<&str> {
+    let Some(session_id) = session_id else {
+        return Ok(SHARED_WORKSPACE);
+    };
+    let plain = !session_id.is_empty()
+        && session_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !plain || session_id == SHARED_WORKSPACE {
+        return Err(file_error(&format!(
+            "Session '{}' cannot have a workspace: ids must use letters, digits, '-' or '_' and not be '{}'",
+            session_id, SHARED_WORKSPACE
+        )));
+    }
+    Ok(session_id)
+}
+
+/// User and workspace directory names of a call; calls without a user have no workspace
+fn workspace_path<'a>(user_id: Option<&'a str>, session_id: Option<&'a str>) -> AriaResult<(&'a str, &'a str)> {
+    let Some(user_id) = user_id else {
+        return Err(AriaError::new(
+            ErrorCode::PermissionDenied,
+            ErrorCategory::Security,
+            ErrorSeverity::Medium,
+            "Filesystem tools need a caller: this call is not attributed to a user",
+        ));
+    };
+    Ok((user_dir_name(user_id)?, workspace_name(session_id)?))
+}
+
+/// Directory name of a user's workspaces. User ids are used verbatim like session ids,
+/// but may also hold '.' and '@' as long as they do not start with '.'.
+fn user_dir_name(user_id: &str) -> AriaResult<&str> {
+    let plain = !user_id.is_empty()
+        && !user_id.starts_with('.')
+        && user_id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'));
+    if !plain {
+        return Err(file_error(&format!(
+            "User '{}' cannot have a workspace: ids must use letters, digits, '-', '_', '.' or '@' and not start with '.'",
+            user_id
+        )));
+    }
+    Ok(user_id)
+}
+
+fn file_error(message: &str) -> AriaError {
+    AriaError::new(ErrorCode::ToolExecutionError, ErrorCategory::Tool, ErrorSeverity::Low, message)
+}
+
+fn io_error(e: std::io::Error) -> AriaError {
+    AriaError::new(ErrorCode::IoError, ErrorCategory::System, ErrorSeverity::Medium, &e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn workspace(name: &str) -> Workspace {
+        let root = std::env::temp_dir().join(format!("aria-workspace-test-{}-{}", name, uuid::Uuid::new_v4().simple()));
+        Workspace::local(root, WorkspaceConfig { max_read_bytes: 64, max_write_bytes: 64, ..WorkspaceConfig::default() })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_write_then_read_inside_workspace() {
+        let ws = workspace("roundtrip").await;
+
+        let outcome = ws.write_text("notes/todo.txt", "ship it").await.unwrap();
+        assert_eq!(outcome.path, "notes/todo.txt");
+        assert!(outcome.created);
+        assert!(!ws.write_text("./notes/../notes/todo.txt", "ship it today").await.unwrap().created);
+
+        let file = ws.read_text("notes/todo.txt").await.unwrap();
+        assert_eq!(file.content, "ship it today");
+
+        let absolute = ws.root().join("notes/todo.txt");
+        assert!(ws.read_text(&absolute.to_string_lossy()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_paths_cannot_leave_workspace() {
+        let ws = workspace("escape").await;
+
+        for path in ["../outside.txt", "notes/../../outside.txt", "/etc/shadow", ""] {
+            assert!(ws.resolve(path).await.is_err(), "{} should be refused", path);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_cannot_escape() {
+        let ws = workspace("symlink").await;
+        let outside = std::env::temp_dir().join(format!("aria-outside-{}", uuid::Uuid::new_v4().simple()));
+        tokio::fs::create_dir_all(&outside).await.unwrap();
+        tokio::fs::write(outside.join("secret.txt"), "secret").await.unwrap();
+        std::os::unix::fs::symlink(&outside, ws.root().join("link")).unwrap();
+        std::os::unix::fs::symlink(outside.join("missing"), ws.root().join("dangling")).unwrap();
+
+        assert!(ws.read_text("link/secret.txt").await.is_err());
+        assert!(ws.write_text("link/new.txt", "x").await.is_err());
+        assert!(ws.write_text("dangling", "x").await.is_err());
+        assert!(tokio::fs::metadata(outside.join("new.txt")).await.is_err());
+        assert!(tokio::fs::metadata(outside.join("missing")).await.is_err());
+        assert!(!ws.list(None).await.unwrap().files.iter().any(|f| f.starts_with("link")));
+    }
+
+    #[tokio::test]
+    async fn test_limits_and_binary_detection() {
+        let ws = workspace("limits").await;
+
+        assert!(ws.write_text("big.txt", &"x".repeat(65)).await.is_err());
+
+        tokio::fs::write(ws.root().join("big.txt"), "x".repeat(65)).await.unwrap();
+        assert!(ws.read_text("big.txt").await.is_err());
+
+        tokio::fs::write(ws.root().join("image.png"), [0x89, b'P', b'N', b'G', 0, 0]).await.unwrap();
+        let err = ws.read_text("image.png").await.unwrap_err();
+        assert!(err.message.contains("binary"));
+    }
+
+    #[tokio::test]
+    async fn test_glob_listing() {
+        let ws = workspace("glob").await;
+        for path in ["src/main.rs", "src/lib/mod.rs", "README.md", "docs/guide.md"] {
+            ws.write_text(path, "x").await.unwrap();
+        }
+
+        assert_eq!(ws.list(Some("**/*.rs")).await.unwrap().files, vec!["src/lib/mod.rs", "src/main.rs"]);
+        assert_eq!(ws.list(Some("*.md")).await.unwrap().files, vec!["README.md"]);
+        assert_eq!(ws.list(Some("**/*.{md,rs}")).await.unwrap().files.len(), 4);
+        assert_eq!(ws.list(None).await.unwrap().files.len(), 4);
+    }
+
+    #[test]
+    fn test_session_workspace_names() {
+        assert_eq!(workspace_name(None).unwrap(), SHARED_WORKSPACE);
+        assert_eq!(workspace_name(Some("3f2a-session_1")).unwrap(), "3f2a-session_1");
+
+        for session_id in ["", "a/b", "../escape", "a b", SHARED_WORKSPACE] {
+            assert!(workspace_name(Some(session_id)).is_err(), "{:?} should be refused", session_id);
+        }
+    }
+
+    #[test]
+    fn test_user_workspace_names() {
+        assert_eq!(user_dir_name("alice@example.com").unwrap(), "alice@example.com");
+        assert_eq!(user_dir_name("user_1-a").unwrap(), "user_1-a");
+
+        for user_id in ["", ".", "..", ".hidden", "a/b", "a b"] {
+            assert!(user_dir_name(user_id).is_err(), "{:?} should be refused", user_id);
+        }
+    }
+
+    #[test]
+    fn test_workspaces_are_per_user() {
+        assert!(workspace_path(None, Some("s1")).is_err());
+        assert!(workspace_path(None, None).is_err());
+        assert_eq!(workspace_path(Some("alice"), None).unwrap(), ("alice", SHARED_WORKSPACE));
+        assert_eq!(workspace_path(Some("bob"), Some("s1")).unwrap(), ("bob", "s1"));
+        assert!(workspace_path(Some("../alice"), Some("s1")).is_err());
+    }
+
+    #[test]
+    fn test_chunk_str_respects_char_boundaries() {
+        let chunks = chunk_str("aé b", 2);
+        assert_eq!(chunks.concat(), "aé b");
+        assert!(chunks.iter().all(|c| c.len() <= 2));
+    }
+}
@@ -5,10 +5,10 @@ use crate::deep_size::DeepValue;
 use crate::errors::AriaResult;
 use std::collections::HashMap;
 use serde_json::json;
-use tokio::fs;
 use std::path::Path;
+use super::workspace::Workspace;
 
-pub async fn write_code_tool_handler(parameters: DeepValue, llm_handler: &LLMHandler) -> AriaResult<ToolResult> {
+pub async fn write_code_tool_handler(parameters: DeepValue, llm_handler: &LLMHandler, workspace: &Workspace) -> AriaResult<ToolResult> {
     // Extract parameters
     let mut params: HashMap<String, DeepValue> = HashMap::new();
     if let Some(obj) = parameters.as_object() {
@@ -122,6 +122,7 @@ Provide working {} code that can be immediately used.", language, spec, context_
         tools: None,
         tool_choice: None,
         stream: Some(false),
+        cache: None,
     };
 
     match llm_handler.complete(request).await {
@@ -135,9 +136,9 @@ Provide working {} code that can be immediately used.", language, spec, context_
 
             // Save to file if filePath is provided
             let saved_to_file = if let Some(path) = file_path {
-                match save_code_to_file(path, &generated_code).await {
-                    Ok(()) => {
-                        println!("[WRITECODE] Saved code to {}", path);
+                match workspace.write_text(path, &generated_code).await {
+                    Ok(outcome) => {
+                        println!("[WRITECODE] Saved code to {}", outcome.path);
                         true
                     }
                     Err(e) => {
@@ -270,19 +271,6 @@ fn clean_markdown_code_blocks(code: String) -> String {
     }
 }
 
-// Helper function to save code to file
-async fn save_code_to_file(file_path: &str, code: &str) -> Result<(), std::io::Error> {
-    let path = Path::new(file_path);
-    
-    // Create directory if it doesn't exist
-    if let Some(dir) = path.parent() {
-        fs::create_dir_all(dir).await?;
-    }
-    
-    // Write file
-    fs::write(file_path, code).await
-}
-
 // Helper function to generate code explanation
 async fn generate_code_explanation(code: &str, language: &str, llm_handler: &LLMHandler) -> Option<String> {
     let request = LLMRequest {
@@ -312,6 +300,7 @@ async fn generate_code_explanation(code: &str, language: &str, llm_handler: &LLM
         tools: None,
         tool_choice: None,
         stream: Some(false),
+        cache: None,
     };
 
     match llm_handler.complete(request).await {
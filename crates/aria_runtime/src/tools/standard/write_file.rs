@@ -1,13 +1,11 @@
-use crate::engines::llm::LLMHandler;
 use crate::types::ToolResult;
 use crate::deep_size::DeepValue;
 use crate::errors::AriaResult;
 use std::collections::HashMap;
 use serde_json::json;
-use tokio::fs;
-use std::path::Path;
+use super::workspace::Workspace;
 
-pub async fn write_file_tool_handler(parameters: DeepValue, _llm_handler: &LLMHandler) -> AriaResult<ToolResult> {
+pub async fn write_file_tool_handler(parameters: DeepValue, workspace: &Workspace) -> AriaResult<ToolResult> {
     // Extract parameters
     let mut params: HashMap<String, DeepValue> = HashMap::new();
     if let Some(obj) = parameters.as_object() {
@@ -40,86 +38,42 @@ pub async fn write_file_tool_handler(parameters: DeepValue, _llm_handler: &LLMHa
 
     let start_time = std::time::Instant::now();
 
-    // Ensure directory exists
-    let path = Path::new(file_path);
-    if let Some(dir_path) = path.parent() {
-        if let Err(e) = fs::create_dir_all(dir_path).await {
-            return Ok(ToolResult {
-                success: false,
-                result: None,
-                error: Some(format!("Failed to create directory {}: {}", dir_path.display(), e)),
-                metadata: HashMap::new(),
-                execution_time_ms: start_time.elapsed().as_millis() as u64,
-                resource_usage: None,
-            });
-        }
-    }
-
-    // Write file
-    match fs::write(file_path, content).await {
-        Ok(()) => {
-            // Get file stats
-            match fs::metadata(file_path).await {
-                Ok(metadata) => {
-                    let file_size = metadata.len();
-                    let execution_time = start_time.elapsed().as_millis() as u64;
-
-                    println!("[WRITEFILE] Successfully wrote {} bytes to {}", file_size, file_path);
+    // Write file, confined to the session workspace
+    match workspace.write_text(file_path, content).await {
+        Ok(outcome) => {
+            let execution_time = start_time.elapsed().as_millis() as u64;
 
-                    // Create result metadata
-                    let mut result_metadata = HashMap::new();
-                    result_metadata.insert("path".to_string(), DeepValue(json!(file_path)));
-                    result_metadata.insert("size".to_string(), DeepValue(json!(file_size)));
-                    result_metadata.insert("encoding".to_string(), DeepValue(json!(encoding)));
-                    result_metadata.insert("execution_time_ms".to_string(), DeepValue(json!(execution_time)));
+            println!("[WRITEFILE] Successfully wrote {} bytes to {}", outcome.bytes_written, outcome.path);
 
-                    let result = json!({
-                        "path": file_path,
-                        "size": file_size,
-                        "encoding": encoding,
-                        "message": format!("File written successfully to {}", file_path),
-                        "success": true,
-                        "metadata": {
-                            "fileSize": file_size,
-                            "encoding": encoding,
-                            "created": true,
-                            "executionTime": execution_time
-                        }
-                    });
+            // Create result metadata
+            let mut result_metadata = HashMap::new();
+            result_metadata.insert("path".to_string(), DeepValue(json!(outcome.path)));
+            result_metadata.insert("size".to_string(), DeepValue(json!(outcome.bytes_written)));
+            result_metadata.insert("encoding".to_string(), DeepValue(json!(encoding)));
+            result_metadata.insert("execution_time_ms".to_string(), DeepValue(json!(execution_time)));
 
-                    Ok(ToolResult {
-                        success: true,
-                        result: Some(result.into()),
-                        error: None,
-                        metadata: result_metadata,
-                        execution_time_ms: execution_time,
-                        resource_usage: None,
-                    })
+            let result = json!({
+                "path": outcome.path,
+                "size": outcome.bytes_written,
+                "encoding": encoding,
+                "message": format!("File written successfully to {}", outcome.path),
+                "success": true,
+                "metadata": {
+                    "fileSize": outcome.bytes_written,
+                    "encoding": encoding,
+                    "created": outcome.created,
+                    "executionTime": execution_time
                 }
-                Err(e) => {
-                    // File was written but couldn't get stats
-                    let execution_time = start_time.elapsed().as_millis() as u64;
-                    println!("[WRITEFILE] File written but failed to get metadata: {}", e);
-
-                    let result = json!({
-                        "path": file_path,
-                        "size": content.len(),
-                        "encoding": encoding,
-                        "message": format!("File written successfully to {} (metadata unavailable)", file_path),
-                        "success": true,
-                        "warning": "Could not retrieve file metadata"
-                    });
+            });
 
-                    Ok(ToolResult {
-                        success: true,
-                        result: Some(result.into()),
-                        error: None,
-                        metadata: HashMap::new(),
-                        execution_time_ms: execution_time,
-                        resource_usage: None,
-                    })
-                }
-            }
+            Ok(ToolResult {
+                success: true,
+                result: Some(result.into()),
+                error: None,
+                metadata: result_metadata,
+                execution_time_ms: execution_time,
+                resource_usage: None,
+            })
         }
         Err(e) => {
             let execution_time = start_time.elapsed().as_millis() as u64;
@@ -128,11 +82,11 @@ pub async fn write_file_tool_handler(parameters: DeepValue, _llm_handler: &LLMHa
             Ok(ToolResult {
                 success: false,
                 result: None,
-                error: Some(format!("Failed to write file {}: {}", file_path, e)),
+                error: Some(format!("Failed to write file {}: {}", file_path, e.message)),
                 metadata: HashMap::new(),
                 execution_time_ms: execution_time,
                 resource_usage: None,
             })
         }
     }
-} 
\ No newline at end of file
+}
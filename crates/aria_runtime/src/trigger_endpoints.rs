@@ -0,0 +1,56 @@
+use crate::errors::ErrorCode;
+use crate::triggers::{TriggerEngine, TriggerOutcome};
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+    routing::post,
+    Router,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Create router for webhook trigger endpoints
+pub fn create_trigger_router(triggers: Arc<TriggerEngine>) -> Router {
+    Router::new()
+        // Signed deliveries to a webhook trigger
+        .route("/triggers/:trigger_id", post(handle_webhook))
+        .with_state(triggers)
+}
+
+/// Handle a webhook delivery
+async fn handle_webhook(
+    Path(trigger_id): Path<String>,
+    State(triggers): State<Arc<TriggerEngine>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let headers: HashMap<String, String> = headers.iter()
+        .filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+
+    match triggers.receive_webhook(&trigger_id, &headers, &body).await {
+        Ok(TriggerOutcome::Launched(task_id)) => {
+            info!("Webhook delivery to trigger {} launched task {}", trigger_id, task_id);
+            Ok((StatusCode::ACCEPTED, Json(serde_json::json!({ "task_id": task_id }))))
+        }
+        Ok(TriggerOutcome::Duplicate(task_id)) => {
+            Ok((StatusCode::OK, Json(serde_json::json!({ "task_id": task_id, "duplicate": true }))))
+        }
+        Ok(TriggerOutcome::NotMatched) => {
+            Ok((StatusCode::OK, Json(serde_json::json!({ "matched": false }))))
+        }
+        Ok(TriggerOutcome::RateLimited) => Err(StatusCode::TOO_MANY_REQUESTS),
+        Err(e) => match e.code {
+            ErrorCode::TriggerNotFound => Err(StatusCode::NOT_FOUND),
+            ErrorCode::AuthenticationFailed => Err(StatusCode::UNAUTHORIZED),
+            ErrorCode::TaskValidationError | ErrorCode::TriggerValidationError => Err(StatusCode::UNPROCESSABLE_ENTITY),
+            _ => {
+                error!("Failed to handle webhook delivery to trigger {}: {}", trigger_id, e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
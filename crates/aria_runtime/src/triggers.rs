@@ -0,0 +1,557 @@
+/*!
+# Triggers
+
+Launches tasks in reaction to the outside world instead of on request. A
+webhook trigger fires when a request signed with its secret is posted to its
+URL; an event trigger fires on observability events of its owner, such as a
+command in one of their containers exiting non-zero.
+
+Every fire renders the trigger's task template against the payload and
+submits the result to the `TaskRunner`. Templates refer to the payload with
+`{{path}}` placeholders: `{{body.pull_request.number}}` and
+`{{headers.x-github-event}}` for webhooks, `{{event.container_id}}` and
+`{{event.metadata.exit_code}}` for events. The same paths select what
+conditions compare and what dedupe keys are made of.
+
+Deliveries are signed with HMAC-SHA256 over `{timestamp}.{body}`, the
+timestamp being UNIX seconds sent in `x-aria-timestamp`. A delivery whose
+timestamp is more than `SIGNATURE_TOLERANCE_SECONDS` away from the clock is
+refused, so a captured delivery cannot be replayed later.
+
+Placeholders only ever fill in single arguments of the task's command, which
+is executed as an argv and never through `sh -c`. A template is refused when
+a placeholder names the program, or when a shell, script interpreter or
+`awk`/`sed` appears anywhere up to the last placeholder, which catches wrappers
+such as `env -S`, `timeout 5 sh -c` and `xargs sh` as well as `python3 -c`.
+Payload meant for such programs goes in through `environment` instead, where
+it is never parsed. This is a guard against the usual ways of evaluating text,
+not a sandbox: programs that run their arguments in other ways, such as
+`find -exec` or `git -c`, are not recognized.
+*/
+
+use crate::clock::unix_now;
+use crate::database::async_tasks::NewAsyncTask;
+use crate::database::tenancy::{TenantOps, TenantResource};
+use crate::database::triggers::{TriggerKind, TriggerOps, TriggerRecord};
+use crate::database::DatabaseManager;
+use crate::engines::observability::{EventFilter, ObservabilityEvent, ObservabilityManager};
+use crate::engines::streaming::event_data;
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::task_runner::{TaskRunner, CONTAINER_TASK_TYPE};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// Header carrying `sha256=<hex HMAC-SHA256 of "{timestamp}.{body}">`
+pub const SIGNATURE_HEADER: &str = "x-aria-signature";
+/// Header carrying the UNIX seconds a delivery was signed at
+pub const TIMESTAMP_HEADER: &str = "x-aria-timestamp";
+/// How far a delivery's timestamp may be from the receiver's clock, either way
+pub const SIGNATURE_TOLERANCE_SECONDS: u64 = 300;
+/// Programs that run text they are given as code, by name without path or version
+const INTERPRETERS: &[&str] = &[
+    "sh", "bash", "dash", "ash", "zsh", "ksh", "mksh", "csh", "tcsh", "fish", "busybox",
+    "python", "perl", "ruby", "irb", "node", "nodejs", "deno", "bun", "php", "lua", "luajit",
+    "tclsh", "wish", "expect", "pwsh", "powershell", "osascript", "rscript", "julia",
+    "awk", "gawk", "mawk", "nawk", "sed", "ssh", "su",
+];
+/// Span over which `max_fires_per_minute` is counted
+const RATE_WINDOW_SECONDS: u64 = 60;
+/// Observability event types whose events can be traced to a user
+pub const EVENT_TYPES: &[&str] = &["container", "tool", "agent", "error", "pattern", "context"];
+
+/// What a delivery to a trigger led to
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerOutcome {
+    /// A task was launched
+    Launched(String),
+    /// The delivery repeats one already handled within the dedupe window, which launched this task
+    Duplicate(String),
+    /// The trigger already fired as often as it may this minute
+    RateLimited,
+    /// The payload does not meet the trigger's conditions
+    NotMatched,
+}
+
+/// HMAC of `{timestamp}.{body}` under `secret`
+fn signature_mac(secret: &str, timestamp: u64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.", timestamp).as_bytes());
+    mac.update(body);
+    mac
+}
+
+/// `sha256=<hex>` signature of `body` sent at `timestamp` under `secret`
+pub fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    format!("sha256={}", hex::encode(signature_mac(secret, timestamp, body).finalize().into_bytes()))
+}
+
+/// Check a `sha256=<hex>` signature of `body` sent at `timestamp` in constant time,
+/// refusing timestamps more than `SIGNATURE_TOLERANCE_SECONDS` away from `now`
+pub fn verify_signature(secret: &str, timestamp: &str, body: &[u8], signature: &str, now: u64) -> bool {
+    let Ok(timestamp) = timestamp.trim().parse::<u64>() else {
+        return false;
+    };
+    if timestamp.abs_diff(now) > SIGNATURE_TOLERANCE_SECONDS {
+        return false;
+    }
+    let Some(digest) = signature.trim().strip_prefix("sha256=").and_then(|digest| hex::decode(digest).ok()) else {
+        return false;
+    };
+    signature_mac(secret, timestamp, body).verify_slice(&digest).is_ok()
+}
+
+/// A program's name without its directory or version suffix, e.g. `python` for `/usr/bin/python3.11`
+fn program_name(token: &str) -> &str {
+    let name = token.rsplit('/').next().unwrap_or(token);
+    name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.')
+}
+
+/// Why a command template could get payload text run as code, if it could
+fn code_injection(command: &[String]) -> Option<String> {
+    if command.first().is_some_and(|program| program.contains("{{")) {
+        return Some("its program is a placeholder".to_string());
+    }
+    // Arguments are split on whitespace too, to see into `env -S "sh -c ..."`
+    let last_placeholder = command.iter().rposition(|arg| arg.contains("{{"))?;
+    command[..=last_placeholder].iter()
+        .flat_map(|arg| arg.split_whitespace())
+        .map(program_name)
+        .find(|name| INTERPRETERS.contains(name))
+        .map(|name| format!("'{}' would run payload text as code", name))
+}
+
+/// The value at a dot-separated path; numeric segments index arrays
+fn lookup<'a>(payload: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').filter(|segment| !segment.is_empty()).try_fold(payload, |value, segment| match value {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// A payload value as template text: strings as they are, anything else as JSON
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// Replace every `{{path}}` in `template` with `resolve(path)`
+fn render_with(template: &str, resolve: impl Fn(&str) -> String) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(&resolve(rest[start + 2..start + end].trim()));
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Fill a template from a payload; missing paths render empty
+pub fn render(template: &str, payload: &Value) -> String {
+    render_with(template, |path| lookup(payload, path).map(value_text).unwrap_or_default())
+}
+
+/// Whether a payload meets every condition. A condition's expected value is
+/// compared as text; `*` only asks for the path to exist and `!value` for it to differ.
+fn matches(conditions: &HashMap<String, String>, payload: &Value) -> bool {
+    conditions.iter().all(|(path, expected)| {
+        let actual = lookup(payload, path).map(value_text);
+        match expected.as_str() {
+            "*" => actual.is_some(),
+            expected => match expected.strip_prefix('!') {
+                Some(excluded) => actual.as_deref() != Some(excluded),
+                None => actual.as_deref() == Some(expected),
+            },
+        }
+    })
+}
+
+/// Payload of a webhook delivery: its headers and its body, parsed if it is JSON
+fn webhook_payload(trigger: &TriggerRecord, headers: &HashMap<String, String>, body: &[u8]) -> Value {
+    let body = serde_json::from_slice(body).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()));
+    json!({
+        "trigger": { "id": trigger.trigger_id, "name": trigger.name },
+        "headers": headers,
+        "body": body,
+    })
+}
+
+/// The task a trigger launches, with its placeholders unrendered
+pub fn template(trigger: &TriggerRecord) -> NewAsyncTask {
+    NewAsyncTask {
+        user_id: trigger.user_id.clone(),
+        session_id: trigger.session_id.clone().unwrap_or_default(),
+        task_type: trigger.task_type.clone(),
+        command: trigger.command.clone(),
+        environment: trigger.environment.clone(),
+        container_id: trigger.container_id.clone(),
+        parent_task_id: None,
+        priority: trigger.priority,
+        timeout_seconds: (trigger.timeout_seconds > 0).then_some(trigger.timeout_seconds),
+    }
+}
+
+/// The task a trigger launches for a payload
+fn render_task(trigger: &TriggerRecord, payload: &Value) -> NewAsyncTask {
+    let mut task = template(trigger);
+    task.command = task.command.iter().map(|arg| render(arg, payload)).collect();
+    task.environment = task.environment.into_iter()
+        .map(|(name, value)| (name, render(&value, payload)))
+        .collect();
+    task
+}
+
+/// Receives webhook deliveries and observability events and launches the tasks they trigger
+pub struct TriggerEngine {
+    database: Arc<DatabaseManager>,
+    runner: Arc<TaskRunner>,
+    /// Held from the dedupe and rate checks until the fire is recorded
+    fire_lock: Mutex<()>,
+}
+
+impl TriggerEngine {
+    pub fn new(database: Arc<DatabaseManager>, runner: Arc<TaskRunner>) -> Self {
+        Self {
+            database,
+            runner,
+            fire_lock: Mutex::new(()),
+        }
+    }
+
+    /// Start firing event triggers on the events `observability` emits
+    pub async fn start(self: &Arc<Self>, observability: &ObservabilityManager) -> AriaResult<()> {
+        let mut events = observability.subscribe(EventFilter::default()).await?;
+        let engine = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        if let Err(e) = engine.handle_event(&event).await {
+                            warn!("Failed to run event triggers: {}", e);
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => warn!("Event triggers missed {} events", missed),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Validate and store a trigger, creating it if it has no ID yet.
+    /// Webhook triggers without a secret get a new one. Returns the stored trigger.
+    pub async fn save(&self, mut trigger: TriggerRecord) -> AriaResult<TriggerRecord> {
+        match trigger.kind {
+            TriggerKind::Webhook => {
+                trigger.event_type = None;
+                if trigger.secret.as_deref().unwrap_or_default().is_empty() {
+                    trigger.secret = Some(new_secret());
+                }
+            }
+            TriggerKind::Event => {
+                trigger.secret = None;
+                let event_type = trigger.event_type.as_deref().unwrap_or_default();
+                if !EVENT_TYPES.contains(&event_type) {
+                    return Err(invalid(format!("Event triggers listen to one of {}, not '{}'", EVENT_TYPES.join(", "), event_type)));
+                }
+            }
+        }
+
+        if trigger.task_type == CONTAINER_TASK_TYPE {
+            if let Some(reason) = code_injection(&trigger.command) {
+                return Err(invalid(format!(
+                    "Trigger command refused: {}; pass payload values through the environment instead", reason
+                )));
+            }
+        }
+
+        // Placeholders are checked as if they were filled with null
+        let sample = NewAsyncTask {
+            command: trigger.command.iter().map(|arg| render_with(arg, |_| "null".to_string())).collect(),
+            ..render_task(&trigger, &Value::Null)
+        };
+        self.runner.validate_task(&sample).await?;
+
+        let now = unix_now();
+        let created = trigger.trigger_id.is_empty();
+        if created {
+            trigger.trigger_id = uuid::Uuid::new_v4().to_string();
+            trigger.created_at = now;
+        }
+        trigger.updated_at = now;
+
+        let pool = self.database.get_user_database(&trigger.user_id).await?;
+        TriggerOps::save_trigger(&pool, &trigger).await?;
+        if created {
+            let system_pool = self.database.get_system_database().await?;
+            TenantOps::record_resource(
+                &system_pool,
+                TenantResource::Trigger,
+                &trigger.trigger_id,
+                &trigger.user_id,
+                trigger.session_id.as_deref(),
+                trigger.container_id.as_deref(),
+            ).await?;
+        }
+
+        info!("Saved {} trigger {} ({}) for user {}", trigger.kind.as_str(), trigger.trigger_id, trigger.name, trigger.user_id);
+        TriggerOps::get_trigger(&pool, &trigger.trigger_id).await?
+            .ok_or_else(|| not_found(&trigger.trigger_id))
+    }
+
+    /// Delete a trigger of `user_id`. Returns false if it did not exist.
+    pub async fn delete(&self, user_id: &str, trigger_id: &str) -> AriaResult<bool> {
+        let pool = self.database.get_user_database(user_id).await?;
+        let deleted = TriggerOps::delete_trigger(&pool, trigger_id).await?;
+
+        let system_pool = self.database.get_system_database().await?;
+        TenantOps::remove_resource(&system_pool, TenantResource::Trigger, trigger_id).await?;
+        Ok(deleted)
+    }
+
+    /// Handle a webhook delivery to `trigger_id` with its lowercased headers and raw body
+    pub async fn receive_webhook(
+        &self,
+        trigger_id: &str,
+        headers: &HashMap<String, String>,
+        body: &[u8],
+    ) -> AriaResult<TriggerOutcome> {
+        let system_pool = self.database.get_system_database().await?;
+        let owner = TenantOps::get_resource(&system_pool, TenantResource::Trigger, trigger_id).await?
+            .ok_or_else(|| not_found(trigger_id))?;
+        let pool = self.database.get_user_database(&owner.user_id).await?;
+        let trigger = TriggerOps::get_trigger(&pool, trigger_id).await?
+            .filter(|trigger| trigger.kind == TriggerKind::Webhook && trigger.enabled)
+            .ok_or_else(|| not_found(trigger_id))?;
+
+        let secret = trigger.secret.as_deref().unwrap_or_default();
+        let verified = match (headers.get(TIMESTAMP_HEADER), headers.get(SIGNATURE_HEADER)) {
+            (Some(timestamp), Some(signature)) => verify_signature(secret, timestamp, body, signature, unix_now()),
+            _ => false,
+        };
+        if secret.is_empty() || !verified {
+            return Err(AriaError::new(
+                ErrorCode::AuthenticationFailed,
+                ErrorCategory::Security,
+                ErrorSeverity::Medium,
+                &format!("Invalid signature for trigger {}", trigger_id),
+            ));
+        }
+
+        let payload = webhook_payload(&trigger, headers, body);
+        if !matches(&trigger.conditions, &payload) {
+            debug!("Webhook delivery to trigger {} did not match its conditions", trigger_id);
+            return Ok(TriggerOutcome::NotMatched);
+        }
+        self.fire(&pool, &trigger, &payload).await
+    }
+
+    /// Fire the event triggers of the user an event concerns
+    async fn handle_event(&self, event: &ObservabilityEvent) -> AriaResult<()> {
+        let (event_type, data, _) = event_data(event);
+        if !EVENT_TYPES.contains(&event_type) {
+            return Ok(());
+        }
+        let Some(user_id) = self.event_owner(&data).await? else {
+            return Ok(());
+        };
+
+        let pool = self.database.get_user_database(&user_id).await?;
+        let mut event = data;
+        if let Value::Object(map) = &mut event {
+            map.insert("type".to_string(), json!(event_type));
+        }
+
+        for trigger in TriggerOps::get_event_triggers(&pool, event_type).await? {
+            let payload = json!({
+                "trigger": { "id": trigger.trigger_id, "name": trigger.name },
+                "event": event,
+            });
+            // A trigger does not react to the tasks it launched itself
+            let own_task = match lookup(&payload, "event.metadata.task_id").and_then(Value::as_str) {
+                Some(task_id) => TriggerOps::find_task(&pool, &trigger.trigger_id, task_id).await?,
+                None => false,
+            };
+            if own_task || !matches(&trigger.conditions, &payload) {
+                continue;
+            }
+
+            match self.fire(&pool, &trigger, &payload).await {
+                Ok(outcome) => debug!("Event trigger {} on {} event: {:?}", trigger.trigger_id, event_type, outcome),
+                Err(e) => warn!("Event trigger {} failed to launch its task: {}", trigger.trigger_id, e),
+            }
+        }
+        Ok(())
+    }
+
+    /// The user an event concerns: named in it, or owning the session it names
+    async fn event_owner(&self, data: &Value) -> AriaResult<Option<String>> {
+        let named = ["user_id", "metadata.user_id", "context.user_id"].iter()
+            .find_map(|path| lookup(data, path).and_then(Value::as_str).filter(|id| !id.is_empty()));
+        if let Some(user_id) = named {
+            return Ok(Some(user_id.to_string()));
+        }
+
+        let session_id = ["session_id", "context.session_id"].iter()
+            .find_map(|path| lookup(data, path).and_then(Value::as_str).filter(|id| !id.is_empty()));
+        let Some(session_id) = session_id else {
+            return Ok(None);
+        };
+        let system_pool = self.database.get_system_database().await?;
+        Ok(TenantOps::get_resource(&system_pool, TenantResource::Session, session_id).await?
+            .map(|record| record.user_id))
+    }
+
+    /// Launch a trigger's task for a payload, unless it repeats an earlier delivery
+    /// or the trigger is over its rate limit
+    async fn fire(&self, pool: &sqlx::SqlitePool, trigger: &TriggerRecord, payload: &Value) -> AriaResult<TriggerOutcome> {
+        let _guard = self.fire_lock.lock().await;
+        let now = unix_now();
+
+        let dedupe_key = trigger.dedupe_key.as_deref()
+            .map(|template| render(template, payload))
+            .filter(|key| !key.is_empty());
+        if let Some(dedupe_key) = &dedupe_key {
+            // A window of 0 remembers keys for as long as the trigger exists
+            let since = if trigger.dedupe_window_seconds > 0 { now.saturating_sub(trigger.dedupe_window_seconds) } else { 0 };
+            if let Some(task_id) = TriggerOps::find_fire(pool, &trigger.trigger_id, dedupe_key, since).await? {
+                return Ok(TriggerOutcome::Duplicate(task_id));
+            }
+        }
+
+        if trigger.max_fires_per_minute > 0 {
+            let recent = TriggerOps::count_fires_since(pool, &trigger.trigger_id, now.saturating_sub(RATE_WINDOW_SECONDS)).await?;
+            if recent >= trigger.max_fires_per_minute as u64 {
+                warn!("Trigger {} is rate limited ({} fires in the last minute)", trigger.trigger_id, recent);
+                return Ok(TriggerOutcome::RateLimited);
+            }
+        }
+
+        let task_id = self.runner.submit(render_task(trigger, payload), Vec::new()).await?;
+        TriggerOps::record_fire(pool, &trigger.trigger_id, dedupe_key.as_deref(), &task_id, now).await?;
+        info!("Trigger {} launched task {}", trigger.trigger_id, task_id);
+        Ok(TriggerOutcome::Launched(task_id))
+    }
+}
+
+/// A random key for signing webhook payloads
+pub fn new_secret() -> String {
+    format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+fn not_found(trigger_id: &str) -> AriaError {
+    AriaError::new(
+        ErrorCode::TriggerNotFound,
+        ErrorCategory::Execution,
+        ErrorSeverity::Low,
+        &format!("Trigger not found: {}", trigger_id),
+    )
+}
+
+fn invalid(message: String) -> AriaError {
+    AriaError::new(
+        ErrorCode::TriggerValidationError,
+        ErrorCategory::Execution,
+        ErrorSeverity::Low,
+        &message,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_verify_only_the_signed_body_and_time() {
+        const AT: u64 = 1_700_000_000;
+        let signature = sign("secret", AT, b"{\"ok\":true}");
+        assert!(signature.starts_with("sha256="));
+        assert!(verify_signature("secret", "1700000000", b"{\"ok\":true}", &signature, AT));
+        assert!(!verify_signature("secret", "1700000000", b"{\"ok\":false}", &signature, AT));
+        assert!(!verify_signature("other", "1700000000", b"{\"ok\":true}", &signature, AT));
+        assert!(!verify_signature("secret", "1700000000", b"{\"ok\":true}", "sha256=zz", AT));
+        assert!(!verify_signature("secret", "1700000001", b"{\"ok\":true}", &signature, AT));
+        assert!(!verify_signature("secret", "soon", b"{\"ok\":true}", &signature, AT));
+
+        // Within the tolerance either way, refused once a replay comes later than that
+        assert!(verify_signature("secret", "1700000000", b"{\"ok\":true}", &signature, AT + SIGNATURE_TOLERANCE_SECONDS));
+        assert!(verify_signature("secret", "1700000000", b"{\"ok\":true}", &signature, AT - SIGNATURE_TOLERANCE_SECONDS));
+        assert!(!verify_signature("secret", "1700000000", b"{\"ok\":true}", &signature, AT + SIGNATURE_TOLERANCE_SECONDS + 1));
+    }
+
+    #[test]
+    fn placeholders_never_reach_interpreters() {
+        let command = |args: &[&str]| -> Vec<String> { args.iter().map(|arg| arg.to_string()).collect() };
+        let refused = |args: &[&str]| code_injection(&command(args)).is_some();
+
+        for args in [
+            &["sh", "-c", "echo {{body.ref}}"][..],
+            &["/bin/bash", "-lc", "deploy {{body.ref}}"],
+            &["python3", "-c", "{{body.x}}"],
+            &["/usr/bin/python3.11", "-c", "print('{{body.x}}')"],
+            &["perl", "-e", "{{body.x}}"],
+            &["node", "-e", "{{body.x}}"],
+            &["ruby", "-e", "{{body.x}}"],
+            &["nohup", "sh", "-c", "{{body.x}}"],
+            &["timeout", "5", "sh", "-c", "{{body.x}}"],
+            &["xargs", "sh", "-c", "{{body.x}}"],
+            &["env", "-S", "sh -c {{body.x}}"],
+            &["awk", "{{body.x}}"],
+            &["{{body.program}}", "--help"],
+        ] {
+            assert!(refused(args), "{:?} should be refused", args);
+        }
+
+        for args in [
+            &["git", "fetch", "origin", "{{body.ref}}"][..],
+            &["env", "CI=1", "make", "test", "TARGET={{body.target}}"],
+            &["sh", "-c", "make deploy"],
+            &["make", "deploy"],
+            &[],
+        ] {
+            assert!(!refused(args), "{:?} should be allowed", args);
+        }
+    }
+
+    #[test]
+    fn templates_render_payload_paths() {
+        let payload = json!({
+            "body": { "repo": "aria", "commits": [{ "id": "abc" }], "size": 3 },
+            "headers": { "x-github-event": "push" },
+        });
+        assert_eq!(
+            render("{{headers.x-github-event}} to {{ body.repo }}: {{body.commits.0.id}} ({{body.size}})", &payload),
+            "push to aria: abc (3)"
+        );
+        assert_eq!(render("missing: '{{body.nope}}'", &payload), "missing: ''");
+        assert_eq!(render("unclosed {{body.repo", &payload), "unclosed {{body.repo");
+    }
+
+    #[test]
+    fn conditions_compare_text_presence_and_exclusion() {
+        let payload = json!({ "event": { "event_type": "exec_finished", "metadata": { "exit_code": "2" } } });
+        let conditions = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(path, value)| (path.to_string(), value.to_string())).collect()
+        };
+
+        assert!(matches(&conditions(&[]), &payload));
+        assert!(matches(&conditions(&[("event.event_type", "exec_finished"), ("event.metadata.exit_code", "!0")]), &payload));
+        assert!(!matches(&conditions(&[("event.metadata.exit_code", "0")]), &payload));
+        assert!(matches(&conditions(&[("event.metadata", "*")]), &payload));
+        assert!(!matches(&conditions(&[("event.container_id", "*")]), &payload));
+    }
+}
@@ -285,6 +285,9 @@ pub struct MemoryUsage {
 #[serde(rename_all = "camelCase")]
 pub struct RuntimeContext {
     pub session_id: DeepUuid,
+    /// User the execution runs on behalf of, when known
+    #[serde(default)]
+    pub user_id: Option<String>,
     pub agent_config: AgentConfig,
     pub created_at: u64,
     pub conversation: Option<ConversationJSON>,
@@ -311,6 +314,7 @@ fn default_working_memory() -> Arc<RwLock<HashMap<String, DeepValue>>> {
 impl DeepSizeOf for RuntimeContext {
     fn deep_size_of_children(&self, context: &mut deepsize::Context) -> usize {
         self.session_id.deep_size_of_children(context)
+            + self.user_id.deep_size_of_children(context)
             + self.agent_config.deep_size_of_children(context)
             + self.created_at.deep_size_of_children(context)
             + self.conversation.deep_size_of_children(context)
@@ -334,6 +338,7 @@ impl RuntimeContext {
     pub fn default_for_session(session_id: DeepUuid) -> Self {
         Self {
             session_id,
+            user_id: None,
             agent_config: AgentConfig::default(),
             created_at: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64,
             conversation: None,
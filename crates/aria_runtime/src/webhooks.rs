@@ -0,0 +1,305 @@
+/*!
+# Webhooks
+
+Posts notifications to the URLs users subscribe to them at. Every notification
+is logged in the system database; subscriptions wanting its event type get a
+delivery row, and the dispatcher posts due deliveries as JSON signed with the
+subscription's secret, the same way webhook triggers expect to be signed:
+`x-aria-signature` covers `{timestamp}.{body}` with the timestamp sent in
+`x-aria-timestamp`, and receivers should refuse deliveries whose timestamp is
+more than `triggers::SIGNATURE_TOLERANCE_SECONDS` from their clock.
+
+Subscriptions may only point at public addresses. A URL whose host resolves
+to a loopback, private, link-local or otherwise internal address is refused
+when it is subscribed, unless the host is listed in `ARIA_WEBHOOK_ALLOWED_HOSTS`.
+The host is resolved and checked again for every attempt, and the attempt
+connects to exactly the addresses that were checked, so a DNS answer changing
+after the subscription cannot redirect deliveries inward. Redirects are not followed.
+
+A delivery that is not answered with a 2xx status is retried with exponential
+backoff. After `MAX_ATTEMPTS` it becomes a dead letter, which stays until it is
+redelivered or its subscription is deleted. Deliveries survive restarts, so a
+receiver that is down only sees its notifications late.
+*/
+
+use crate::clock::unix_now;
+use crate::database::notifications::{NotificationOps, PendingDelivery};
+use crate::database::DatabaseManager;
+use crate::errors::{AriaError, AriaResult, ErrorCategory, ErrorCode, ErrorSeverity};
+use crate::triggers::{self, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+/// Header naming the notification's event type
+pub const EVENT_HEADER: &str = "x-aria-event";
+/// Header with the delivery's ID, the same on every attempt
+pub const DELIVERY_HEADER: &str = "x-aria-delivery";
+
+/// Comma-separated hosts deliveries may reach even though they resolve to internal addresses
+pub const ALLOWED_HOSTS_ENV: &str = "ARIA_WEBHOOK_ALLOWED_HOSTS";
+
+/// Attempts before a delivery becomes a dead letter
+pub const MAX_ATTEMPTS: u32 = 8;
+/// Wait after the first failed attempt; doubled after each further one
+const BASE_BACKOFF_SECONDS: u64 = 10;
+const MAX_BACKOFF_SECONDS: u64 = 3600;
+/// How long a receiver has to answer
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often due deliveries are looked for when nothing wakes the dispatcher
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Deliveries attempted per pass
+const BATCH_SIZE: u32 = 100;
+/// How long notifications stay available for replay
+const RETENTION_SECONDS: u64 = 7 * 24 * 3600;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Seconds to wait before retrying a delivery that has failed `attempts` times
+pub fn backoff(attempts: u32) -> u64 {
+    let doublings = attempts.saturating_sub(1).min(16);
+    (BASE_BACKOFF_SECONDS << doublings).min(MAX_BACKOFF_SECONDS)
+}
+
+/// Whether deliveries must not reach an address: loopback, private, link-local,
+/// unspecified, carrier-grade NAT, broadcast, multicast and their IPv6 counterparts
+pub fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+                || ip.is_broadcast() || ip.is_multicast() || first == 0
+                || (first == 100 && (64..128).contains(&second))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_internal(IpAddr::V4(mapped)),
+            None => {
+                let first = ip.segments()[0];
+                ip.is_loopback() || ip.is_unspecified() || ip.is_multicast()
+                    || (first & 0xfe00) == 0xfc00 // unique local
+                    || (first & 0xffc0) == 0xfe80 // link-local
+            }
+        },
+    }
+}
+
+/// Posts queued notification deliveries to their subscriptions
+pub struct WebhookDispatcher {
+    database: Arc<DatabaseManager>,
+    /// Lowercased hosts exempt from the internal address check
+    allowed_hosts: Vec<String>,
+    wake: Notify,
+}
+
+impl WebhookDispatcher {
+    /// A dispatcher exempting the hosts listed in `ARIA_WEBHOOK_ALLOWED_HOSTS`
+    pub fn new(database: Arc<DatabaseManager>) -> Self {
+        let allowed_hosts = std::env::var(ALLOWED_HOSTS_ENV).unwrap_or_default()
+            .split(',')
+            .map(|host| host.trim().trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase())
+            .filter(|host| !host.is_empty())
+            .collect();
+        Self {
+            database,
+            allowed_hosts,
+            wake: Notify::new(),
+        }
+    }
+
+    /// Resolve a webhook URL to the addresses deliveries to it may connect to,
+    /// refusing hosts with an internal address unless they are allowed
+    pub async fn check_destination(&self, url: &reqwest::Url) -> AriaResult<Vec<SocketAddr>> {
+        let host = url.host_str()
+            .map(|host| host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase())
+            .ok_or_else(|| destination_error(ErrorCode::NetworkError, format!("Webhook URL {} has no host", url)))?;
+        let port = url.port_or_known_default().unwrap_or(443);
+
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port)).await
+            .map_err(|e| destination_error(ErrorCode::NetworkError, format!("Failed to resolve {}: {}", host, e)))?
+            .collect();
+        if addrs.is_empty() {
+            return Err(destination_error(ErrorCode::NetworkError, format!("{} does not resolve to any address", host)));
+        }
+
+        if !self.allowed_hosts.contains(&host) {
+            if let Some(addr) = addrs.iter().find(|addr| is_internal(addr.ip())) {
+                return Err(destination_error(
+                    ErrorCode::PermissionDenied,
+                    format!("{} resolves to internal address {}; list it in {} to allow it", host, addr.ip(), ALLOWED_HOSTS_ENV),
+                ));
+            }
+        }
+        Ok(addrs)
+    }
+
+    /// Start delivering in the background
+    pub fn start(self: &Arc<Self>) {
+        let dispatcher = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut last_prune: Option<Instant> = None;
+            loop {
+                if !matches!(last_prune, Some(at) if at.elapsed() < PRUNE_INTERVAL) {
+                    dispatcher.prune().await;
+                    last_prune = Some(Instant::now());
+                }
+                if let Err(e) = dispatcher.deliver_due().await {
+                    warn!("Webhook delivery pass failed: {}", e);
+                }
+                let _ = tokio::time::timeout(POLL_INTERVAL, dispatcher.wake.notified()).await;
+            }
+        });
+    }
+
+    /// Queue a logged notification for every subscription of `user_id` that wants `event_type`
+    pub async fn enqueue(&self, notification_id: &str, user_id: Option<&str>, event_type: &str) -> AriaResult<usize> {
+        let Some(user_id) = user_id else {
+            return Ok(0);
+        };
+
+        let pool = self.database.pool().await?;
+        let now = unix_now();
+        let mut queued = 0;
+        for subscription in NotificationOps::list_subscriptions(&pool, user_id).await? {
+            if subscription.wants(event_type) {
+                let delivery_id = uuid::Uuid::new_v4().to_string();
+                NotificationOps::enqueue_delivery(&pool, &delivery_id, &subscription.subscription_id, notification_id, now).await?;
+                queued += 1;
+            }
+        }
+
+        if queued > 0 {
+            self.wake.notify_one();
+        }
+        Ok(queued)
+    }
+
+    /// Queue a dead letter again, returning the new delivery's ID; `None` if there is no such dead letter
+    pub async fn redeliver(&self, delivery_id: &str) -> AriaResult<Option<String>> {
+        let pool = self.database.pool().await?;
+        let new_delivery_id = uuid::Uuid::new_v4().to_string();
+        if !NotificationOps::redeliver(&pool, delivery_id, &new_delivery_id, unix_now()).await? {
+            return Ok(None);
+        }
+
+        info!("Redelivering dead letter {} as {}", delivery_id, new_delivery_id);
+        self.wake.notify_one();
+        Ok(Some(new_delivery_id))
+    }
+
+    /// Attempt every due delivery at once
+    async fn deliver_due(&self) -> AriaResult<()> {
+        let pool = self.database.pool().await?;
+        let due = NotificationOps::due_deliveries(&pool, unix_now(), BATCH_SIZE).await?;
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        debug!("Attempting {} webhook deliveries", due.len());
+        futures::future::join_all(due.iter().map(|delivery| self.deliver(&pool, delivery))).await;
+
+        // A full batch may have left more behind
+        if due.len() as u32 == BATCH_SIZE {
+            self.wake.notify_one();
+        }
+        Ok(())
+    }
+
+    /// Post one delivery and record how it went
+    async fn deliver(&self, pool: &sqlx::SqlitePool, delivery: &PendingDelivery) {
+        let (error, status_code) = match self.post(delivery).await {
+            Ok(response) if response.status().is_success() => {
+                if let Err(e) = NotificationOps::complete_delivery(pool, &delivery.delivery_id).await {
+                    warn!("Failed to record webhook delivery {}: {}", delivery.delivery_id, e);
+                }
+                return;
+            }
+            Ok(response) => (format!("HTTP {}", response.status()), Some(response.status().as_u16())),
+            Err(error) => (error, None),
+        };
+
+        let attempts = delivery.attempts + 1;
+        let recorded = if attempts >= MAX_ATTEMPTS {
+            warn!("Webhook delivery {} to {} failed {} times, dead-lettering: {}",
+                  delivery.delivery_id, delivery.url, attempts, error);
+            NotificationOps::dead_letter(pool, &delivery.delivery_id, &error, status_code, unix_now()).await
+        } else {
+            let delay = backoff(attempts);
+            debug!("Webhook delivery {} to {} failed ({}), retrying in {}s",
+                   delivery.delivery_id, delivery.url, error, delay);
+            NotificationOps::retry_delivery(pool, &delivery.delivery_id, &error, status_code, unix_now() + delay).await
+        };
+        if let Err(e) = recorded {
+            warn!("Failed to record failed webhook delivery {}: {}", delivery.delivery_id, e);
+        }
+    }
+
+    /// Post a delivery to the addresses its URL resolves to right now, if they are still allowed
+    async fn post(&self, delivery: &PendingDelivery) -> Result<reqwest::Response, String> {
+        let url = reqwest::Url::parse(&delivery.url).map_err(|e| e.to_string())?;
+        let addrs = self.check_destination(&url).await.map_err(|e| e.message)?;
+        let mut client = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).no_proxy();
+        if let Some(domain) = url.domain() {
+            client = client.resolve_to_addrs(domain, &addrs);
+        }
+        let client = client.build().map_err(|e| e.to_string())?;
+
+        let timestamp = unix_now();
+        client.post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, triggers::sign(&delivery.secret, timestamp, delivery.body.as_bytes()))
+            .header(EVENT_HEADER, &delivery.event_type)
+            .header(DELIVERY_HEADER, &delivery.delivery_id)
+            .body(delivery.body.clone())
+            .timeout(DELIVERY_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Drop notifications past their retention
+    async fn prune(&self) {
+        let pruned = match self.database.pool().await {
+            Ok(pool) => NotificationOps::prune_notifications(&pool, unix_now().saturating_sub(RETENTION_SECONDS)).await,
+            Err(e) => Err(e),
+        };
+        match pruned {
+            Ok(0) => {}
+            Ok(count) => debug!("Pruned {} notifications", count),
+            Err(e) => warn!("Failed to prune notifications: {}", e),
+        }
+    }
+}
+
+fn destination_error(code: ErrorCode, message: String) -> AriaError {
+    AriaError::new(code, ErrorCategory::Security, ErrorSeverity::Medium, &message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff(1), 10);
+        assert_eq!(backoff(2), 20);
+        assert_eq!(backoff(3), 40);
+        assert_eq!(backoff(7), 640);
+        assert_eq!(backoff(12), MAX_BACKOFF_SECONDS);
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF_SECONDS);
+    }
+
+    #[test]
+    fn internal_addresses_are_recognized() {
+        let internal = |ip: &str| is_internal(ip.parse().unwrap());
+
+        for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "0.0.0.0",
+                   "100.64.0.1", "255.255.255.255", "::1", "::", "fd00::1", "fe80::1", "::ffff:127.0.0.1"] {
+            assert!(internal(ip), "{} should be internal", ip);
+        }
+        for ip in ["93.184.216.34", "100.128.0.1", "172.32.0.1", "2606:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(!internal(ip), "{} should be public", ip);
+        }
+    }
+}
@@ -44,13 +44,89 @@ pub struct TeamManifest {
     pub name: String,
     pub description: String,
     pub members: Vec<String>,
+    /// Team strategy name (e.g. "sequential", "role_based"); adaptive when absent
+    #[serde(default)]
+    pub strategy: Option<String>,
 }
 
-/// Pipeline manifest schema
+/// Pipeline manifest schema: a DAG of nodes joined by edges
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PipelineManifest {
     pub name: String,
     pub description: String,
+    #[serde(default)]
+    pub nodes: Vec<PipelineNodeManifest>,
+    #[serde(default)]
+    pub edges: Vec<PipelineEdgeManifest>,
+}
+
+/// What a pipeline node runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineNodeKind {
+    Tool,
+    Agent,
+    Team,
+}
+
+/// A single pipeline step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineNodeManifest {
+    pub id: String,
+    pub kind: PipelineNodeKind,
+    /// Name of the tool, agent or team to run
+    pub target: String,
+    /// Static input object; string values of the form `{{/json/pointer}}` are
+    /// resolved against `{"input": <pipeline input>, "nodes": {<id>: <output>}}`
+    #[serde(default)]
+    pub input: serde_json::Value,
+    /// Run the node only when this holds; otherwise it is skipped
+    #[serde(default)]
+    pub condition: Option<PipelineCondition>,
+    /// Extra attempts after the first failure
+    #[serde(default)]
+    pub retries: u32,
+    /// Per-attempt timeout
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+}
+
+/// Condition evaluated against the pipeline state before a node runs. With no
+/// comparison set, the value at `path` must be present and truthy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineCondition {
+    /// JSON pointer into the pipeline state, e.g. `/nodes/classify/label`
+    pub path: String,
+    #[serde(default)]
+    pub equals: Option<serde_json::Value>,
+    #[serde(default)]
+    pub not_equals: Option<serde_json::Value>,
+    #[serde(default)]
+    pub exists: Option<bool>,
+}
+
+/// When an edge lets its target run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineEdgeKind {
+    /// Target runs only if the source completed successfully
+    #[default]
+    Success,
+    /// Target runs once the source finished, whatever its outcome
+    Completion,
+}
+
+/// A dependency between two nodes, optionally mapping output into input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineEdgeManifest {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub kind: PipelineEdgeKind,
+    /// Target input field -> JSON pointer into the source output (`""` for all
+    /// of it). Without a mapping the whole output lands under the source's id.
+    #[serde(default)]
+    pub mapping: HashMap<String, String>,
 }
 
 /// Bundle metadata
@@ -219,6 +219,9 @@ pub async fn handle_container_command(
                 enable_ipc_namespace: ipc_ns,
                 enable_network_namespace: net_ns,
                 auto_start: true,  // CLI should auto-start containers
+                networks: vec![],
+                owner: String::new(),
+                ..Default::default()
             });
 
             match client.create_container(request).await {
@@ -443,6 +446,9 @@ pub async fn handle_container_command(
                 enable_uts_namespace: true,
                 enable_ipc_namespace: true,
                 auto_start: true,  // Production containers should auto-start
+                networks: vec![],
+                owner: String::new(),
+                ..Default::default()
             };
 
             match client.create_container(tonic::Request::new(create_request)).await {
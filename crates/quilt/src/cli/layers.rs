@@ -0,0 +1,79 @@
+// src/cli/layers.rs
+// Runtime layer cache CLI commands
+
+use clap::Subcommand;
+use tonic::transport::Channel;
+
+use crate::quilt::{
+    quilt_service_client::QuiltServiceClient,
+    ListRuntimeLayersRequest, PrewarmRuntimeLayerRequest, PruneRuntimeLayersRequest,
+};
+
+#[derive(Subcommand, Debug)]
+pub enum LayerCommands {
+    /// Build the runtime layer for an image and setup spec ahead of time
+    Prewarm {
+        #[clap(long, required_unless_present = "template", help = "Path to the container image tarball")]
+        image_path: Option<String>,
+        #[clap(long, help = "Setup commands (e.g., 'npm: typescript', 'pip: requests')")]
+        setup: Vec<String>,
+        #[clap(long, conflicts_with_all = ["image_path", "setup"], help = "Pre-warm the latest version of this template")]
+        template: Option<String>,
+    },
+
+    /// List cached runtime layers, most recently used first
+    List,
+
+    /// Evict least-recently-used unused layers
+    Prune {
+        #[clap(long, default_value = "0", help = "Keep the cache under this many megabytes (0 = remove all unused)")]
+        max_mb: u64,
+    },
+}
+
+pub async fn handle_layer_command(cmd: LayerCommands, mut client: QuiltServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    match cmd {
+        LayerCommands::Prewarm { image_path, setup, template } => {
+            println!("🔥 Pre-warming runtime layer (this runs the setup commands once)...");
+            let res = client.prewarm_runtime_layer(tonic::Request::new(PrewarmRuntimeLayerRequest {
+                image_path: image_path.unwrap_or_default(),
+                setup_commands: setup,
+                template: template.unwrap_or_default(),
+            })).await?.into_inner();
+
+            match (res.success, res.layer) {
+                (true, Some(layer)) => {
+                    println!("✅ Runtime layer ready!");
+                    println!("   Key:   {}", layer.key);
+                    println!("   Spec:  {}", layer.setup_spec.join("; "));
+                    println!("   Size:  {} bytes", layer.size_bytes);
+                    println!("   Build: {}ms", layer.build_duration_ms);
+                }
+                _ => {
+                    eprintln!("❌ Failed to pre-warm runtime layer: {}", res.error_message);
+                    std::process::exit(1);
+                }
+            }
+        }
+        LayerCommands::List => {
+            let res = client.list_runtime_layers(tonic::Request::new(ListRuntimeLayersRequest {})).await?.into_inner();
+            if res.layers.is_empty() {
+                println!("📋 No runtime layers cached");
+                return Ok(());
+            }
+
+            println!("{:<34} {:>12} {:<40} {}", "KEY", "SIZE", "IMAGE", "SETUP");
+            for layer in res.layers {
+                println!("{:<34} {:>12} {:<40} {}", layer.key, layer.size_bytes, layer.image_path, layer.setup_spec.join("; "));
+            }
+            println!("Total: {} bytes", res.total_size_bytes);
+        }
+        LayerCommands::Prune { max_mb } => {
+            let res = client.prune_runtime_layers(tonic::Request::new(PruneRuntimeLayersRequest {
+                max_size_bytes: max_mb * 1024 * 1024,
+            })).await?.into_inner();
+            println!("🧹 Evicted {} runtime layer(s); {} bytes remain", res.evicted_keys.len(), res.remaining_size_bytes);
+        }
+    }
+    Ok(())
+}
@@ -10,7 +10,7 @@ pub mod quilt {
 // Import CLI modules
 #[path = "../cli/mod.rs"]
 mod cli;
-use cli::IccCommands;
+use cli::{IccCommands, LayerCommands, NetworkCommands, StackCommands, TemplateCommands};
 
 use quilt::quilt_service_client::QuiltServiceClient;
 use quilt::{
@@ -42,8 +42,14 @@ struct Cli {
 enum Commands {
     /// Create a new container with advanced features
     Create {
-        #[clap(long, help = "Path to the container image tarball")]
-        image_path: String,
+        #[clap(long, required_unless_present = "template", help = "Path to the container image tarball")]
+        image_path: Option<String>,
+        
+        #[clap(long, help = "Template to create the container from (name or name@version)")]
+        template: Option<String>,
+        
+        #[clap(long, help = "Container name")]
+        name: Option<String>,
         
         #[arg(short, long, action = clap::ArgAction::Append, 
               help = "Environment variables in KEY=VALUE format",
@@ -83,9 +89,23 @@ enum Commands {
         #[clap(long, help = "Enable all namespace isolation features")]
         enable_all_namespaces: bool,
         
+        #[clap(long = "network", help = "Network to attach to (repeatable, first is primary; default network if omitted)")]
+        networks: Vec<String>,
+        
+        #[clap(long, help = "Owner charged for the container (owner quotas apply)")]
+        owner: Option<String>,
+        
+        #[arg(long = "label", action = clap::ArgAction::Append,
+              help = "Labels in KEY=VALUE format",
+              value_parser = InputValidator::parse_key_val)]
+        labels: Vec<(String, String)>,
+        
+        #[clap(short = 'v', long = "volume", help = "Bind mount as host_path:container_path[:ro] (repeatable)")]
+        volumes: Vec<String>,
+        
         /// The command and its arguments to run in the container
-        #[clap(required = true, num_args = 1.., 
-               help = "Command and its arguments (use -- to separate from CLI options)")]
+        #[clap(required_unless_present = "template", num_args = 1.., 
+               help = "Command and its arguments (use -- to separate from CLI options; optional with --template)")]
         command_and_args: Vec<String>,
     },
     
@@ -133,11 +153,29 @@ enum Commands {
         cpu: f64,
         #[clap(long, help = "Disable networking")]
         no_network: bool,
+        #[clap(long = "network", help = "Network to attach to (repeatable, first is primary)")]
+        networks: Vec<String>,
     },
 
     /// Inter-Container Communication commands
     #[clap(subcommand)]
     Icc(IccCommands),
+
+    /// User-defined network commands
+    #[clap(subcommand)]
+    Network(NetworkCommands),
+
+    /// Container template commands
+    #[clap(subcommand)]
+    Template(TemplateCommands),
+
+    /// Declarative multi-container stack commands
+    #[clap(subcommand)]
+    Stack(StackCommands),
+
+    /// Runtime layer cache commands (prebuilt setup commands)
+    #[clap(subcommand)]
+    Layers(LayerCommands),
 }
 
 #[tokio::main]
@@ -166,6 +204,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     match cli.command {
         Commands::Create { 
             image_path, 
+            template,
+            name,
             env, 
             setup,
             working_directory,
@@ -177,15 +217,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             enable_ipc_namespace,
             enable_network_namespace,
             enable_all_namespaces,
+            networks,
+            owner,
+            labels,
+            volumes,
             command_and_args 
         } => {
             println!("🚀 Creating container...");
             
-            if command_and_args.is_empty() {
+            if command_and_args.is_empty() && template.is_none() {
                 eprintln!("❌ Error: Command cannot be empty.");
                 std::process::exit(1);
             }
 
+            let (template, template_version) = match template.as_deref().map(cli::templates::parse_template_ref) {
+                Some(Ok((name, version))) => (name, version),
+                Some(Err(e)) => {
+                    eprintln!("❌ Error: {}", e);
+                    std::process::exit(1);
+                }
+                None => (String::new(), 0),
+            };
+
             let environment: HashMap<String, String> = env.into_iter().collect();
             
             // If enable_all_namespaces is true, enable all namespace options
@@ -202,7 +255,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             };
 
             let request = tonic::Request::new(CreateContainerRequest {
-                image_path,
+                image_path: image_path.unwrap_or_default(),
                 command: command_and_args,
                 environment,
                 working_directory: working_directory.unwrap_or_default(),
@@ -213,8 +266,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 enable_mount_namespace: mount_ns,
                 enable_uts_namespace: uts_ns,
                 enable_ipc_namespace: ipc_ns,
-                enable_network_namespace: net_ns,
+                enable_network_namespace: net_ns || !networks.is_empty(),
                 auto_start: true,  // CLI should auto-start containers
+                networks,
+                owner: owner.unwrap_or_default(),
+                template,
+                template_version,
+                name: name.unwrap_or_default(),
+                labels: labels.into_iter().collect(),
+                volumes,
             });
 
             match client.create_container(request).await {
@@ -358,7 +418,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
         
-        Commands::CreateProduction { image_path, name, setup, env, memory, cpu, no_network } => {
+        Commands::CreateProduction { image_path, name, setup, env, memory, cpu, no_network, networks } => {
             println!("🚀 Creating production container using the new event-driven readiness system...");
             
             // Parse environment variables
@@ -384,6 +444,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 enable_uts_namespace: true,
                 enable_ipc_namespace: true,
                 auto_start: true,  // Production containers should auto-start
+                networks: if no_network { vec![] } else { networks },
+                owner: String::new(),
+                ..Default::default()
             };
 
             match client.create_container(tonic::Request::new(create_request)).await {
@@ -415,6 +478,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Commands::Icc(icc_cmd) => {
             cli::icc::handle_icc_command(icc_cmd, client).await?
         }
+
+        Commands::Network(network_cmd) => {
+            cli::networks::handle_network_command(network_cmd, client).await?
+        }
+
+        Commands::Template(template_cmd) => {
+            cli::templates::handle_template_command(template_cmd, client).await?
+        }
+
+        Commands::Stack(stack_cmd) => {
+            cli::stacks::handle_stack_command(stack_cmd, client).await?
+        }
+
+        Commands::Layers(layer_cmd) => {
+            cli::layers::handle_layer_command(layer_cmd, client).await?
+        }
     }
 
     Ok(())
@@ -1,9 +1,17 @@
 pub mod containers;
 pub mod icc;
+pub mod layers;
+pub mod networks;
+pub mod stacks;
+pub mod templates;
 
 use clap::Subcommand;
 pub use containers::ContainerCommands;
 pub use icc::IccCommands;
+pub use layers::LayerCommands;
+pub use networks::NetworkCommands;
+pub use stacks::StackCommands;
+pub use templates::TemplateCommands;
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
@@ -12,4 +20,16 @@ pub enum Commands {
 
     #[clap(subcommand)]
     Icc(IccCommands),
+
+    #[clap(subcommand)]
+    Network(NetworkCommands),
+
+    #[clap(subcommand)]
+    Template(TemplateCommands),
+
+    #[clap(subcommand)]
+    Stack(StackCommands),
+
+    #[clap(subcommand)]
+    Layers(LayerCommands),
 } 
\ No newline at end of file
@@ -0,0 +1,95 @@
+// src/cli/networks.rs
+// User-defined network CLI commands
+
+use clap::Subcommand;
+use tonic::transport::Channel;
+
+use crate::quilt::{
+    quilt_service_client::QuiltServiceClient,
+    CreateNetworkRequest, ListNetworksRequest, RemoveNetworkRequest,
+};
+
+#[derive(Subcommand, Debug)]
+pub enum NetworkCommands {
+    /// Create a network with its own bridge, subnet and IP range
+    Create {
+        #[clap(help = "Network name")]
+        name: String,
+        #[clap(long, help = "Subnet in CIDR notation (e.g. 10.50.0.0/24)")]
+        subnet: String,
+        #[clap(long, help = "Bridge name (defaults to qbr-<name>, max 15 chars)")]
+        bridge: Option<String>,
+        #[clap(long, help = "Gateway address (defaults to the first host in the subnet)")]
+        gateway: Option<String>,
+        #[clap(long, help = "First allocatable address")]
+        ip_range_start: Option<String>,
+        #[clap(long, help = "Last allocatable address")]
+        ip_range_end: Option<String>,
+    },
+
+    /// List all networks
+    List,
+
+    /// Remove a network with no attached containers
+    Remove {
+        #[clap(help = "Network name")]
+        name: String,
+    },
+}
+
+pub async fn handle_network_command(cmd: NetworkCommands, mut client: QuiltServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    match cmd {
+        NetworkCommands::Create { name, subnet, bridge, gateway, ip_range_start, ip_range_end } => {
+            println!("🌐 Creating network {} ({})...", name, subnet);
+            let request = tonic::Request::new(CreateNetworkRequest {
+                name,
+                subnet_cidr: subnet,
+                bridge_name: bridge.unwrap_or_default(),
+                gateway_ip: gateway.unwrap_or_default(),
+                ip_range_start: ip_range_start.unwrap_or_default(),
+                ip_range_end: ip_range_end.unwrap_or_default(),
+            });
+
+            let res = client.create_network(request).await?.into_inner();
+            match (res.success, res.network) {
+                (true, Some(network)) => {
+                    println!("✅ Network created successfully!");
+                    println!("   Name:    {}", network.name);
+                    println!("   Bridge:  {}", network.bridge_name);
+                    println!("   Subnet:  {}", network.subnet_cidr);
+                    println!("   Gateway: {}", network.gateway_ip);
+                    println!("   Range:   {} - {}", network.ip_range_start, network.ip_range_end);
+                }
+                _ => {
+                    eprintln!("❌ Failed to create network: {}", res.error_message);
+                    std::process::exit(1);
+                }
+            }
+        }
+        NetworkCommands::List => {
+            let res = client.list_networks(tonic::Request::new(ListNetworksRequest {})).await?.into_inner();
+            if res.networks.is_empty() {
+                println!("📋 No networks defined");
+                return Ok(());
+            }
+
+            println!("{:<20} {:<16} {:<20} {:<16} {:>10}", "NAME", "BRIDGE", "SUBNET", "GATEWAY", "CONTAINERS");
+            for network in res.networks {
+                let name = if network.is_default { format!("{} (default)", network.name) } else { network.name };
+                println!("{:<20} {:<16} {:<20} {:<16} {:>10}",
+                         name, network.bridge_name, network.subnet_cidr, network.gateway_ip, network.attached_containers);
+            }
+        }
+        NetworkCommands::Remove { name } => {
+            println!("🗑️  Removing network {}...", name);
+            let res = client.remove_network(tonic::Request::new(RemoveNetworkRequest { name: name.clone() })).await?.into_inner();
+            if res.success {
+                println!("✅ Network {} removed successfully", name);
+            } else {
+                eprintln!("❌ Failed to remove network: {}", res.error_message);
+                std::process::exit(1);
+            }
+        }
+    }
+    Ok(())
+}
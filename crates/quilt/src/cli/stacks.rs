@@ -0,0 +1,670 @@
+// src/cli/stacks.rs
+// Declarative multi-container stacks: parse a YAML/TOML stack file, diff it
+// against the containers the daemon reports, and apply the difference.
+
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tonic::transport::Channel;
+
+use crate::cli::templates::parse_template_ref;
+use crate::quilt::{
+    quilt_service_client::QuiltServiceClient,
+    ContainerInfo, ContainerStatus, CreateContainerRequest, CreateNetworkRequest,
+    GetContainerStatusRequest, ListContainersRequest, ListNetworksRequest,
+    RemoveContainerRequest, RemoveNetworkRequest,
+};
+
+pub const STACK_LABEL: &str = "quilt.stack";
+pub const SERVICE_LABEL: &str = "quilt.service";
+pub const SPEC_HASH_LABEL: &str = "quilt.spec-hash";
+
+/// Host directory backing named stack volumes (`<root>/<stack>_<volume>`)
+pub const VOLUMES_ROOT: &str = "/var/lib/quilt/volumes";
+
+const DEPENDENCY_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Subcommand, Debug)]
+pub enum StackCommands {
+    /// Create or update a stack so running containers match the stack file
+    Up {
+        #[clap(short, long, default_value = "quilt-stack.yaml", help = "Stack file (.yaml, .yml or .toml)")]
+        file: PathBuf,
+        #[clap(long, help = "Override the stack name from the file")]
+        name: Option<String>,
+        #[clap(long, help = "Show the plan without applying it")]
+        dry_run: bool,
+    },
+
+    /// Stop and remove every container and network of a stack
+    Down {
+        #[clap(short, long, default_value = "quilt-stack.yaml", help = "Stack file (.yaml, .yml or .toml)")]
+        file: PathBuf,
+        #[clap(long, help = "Override the stack name from the file")]
+        name: Option<String>,
+    },
+
+    /// Compare a stack file with the containers currently running
+    Status {
+        #[clap(short, long, default_value = "quilt-stack.yaml", help = "Stack file (.yaml, .yml or .toml)")]
+        file: PathBuf,
+        #[clap(long, help = "Override the stack name from the file")]
+        name: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StackFile {
+    pub name: Option<String>,
+    pub networks: BTreeMap<String, StackNetwork>,
+    pub volumes: Vec<String>,
+    pub services: BTreeMap<String, ServiceSpec>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StackNetwork {
+    pub subnet: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServiceSpec {
+    /// Template reference (`name` or `name@version`); other fields override it
+    pub template: Option<String>,
+    pub image: Option<String>,
+    pub command: Vec<String>,
+    pub environment: BTreeMap<String, String>,
+    pub setup: Vec<String>,
+    pub working_directory: Option<String>,
+    pub memory_mb: Option<i32>,
+    pub cpu_percent: Option<f32>,
+    /// Stack network names, or existing daemon networks when not declared in the stack
+    pub networks: Vec<String>,
+    /// `host_path:container_path[:ro]` or `<named volume>:container_path[:ro]`
+    pub volumes: Vec<String>,
+    pub labels: BTreeMap<String, String>,
+    pub depends_on: Vec<String>,
+    pub owner: Option<String>,
+}
+
+impl StackFile {
+    /// Parse a stack file, choosing TOML for `.toml` files and YAML otherwise
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&content)
+                .map_err(|e| format!("Invalid stack file {}: {}", path.display(), e)),
+            _ => serde_yaml::from_str(&content)
+                .map_err(|e| format!("Invalid stack file {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Stack name from `--name`, the file's `name`, or the file stem
+    pub fn resolve_name(&self, path: &Path, override_name: Option<String>) -> Result<String, String> {
+        let name = override_name
+            .or_else(|| self.name.clone())
+            .or_else(|| path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string))
+            .unwrap_or_default();
+
+        let valid = !name.is_empty()
+            && name.len() <= 32
+            && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid {
+            return Err(format!("Invalid stack name '{}': use 1-32 characters from [a-z0-9_-]", name));
+        }
+        Ok(name)
+    }
+
+    /// Services ordered so every service comes after the services it depends on
+    pub fn startup_order(&self) -> Result<Vec<String>, String> {
+        let mut remaining: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for (name, service) in &self.services {
+            let mut deps = BTreeSet::new();
+            for dep in &service.depends_on {
+                if !self.services.contains_key(dep) {
+                    return Err(format!("Service {} depends on unknown service {}", name, dep));
+                }
+                deps.insert(dep.as_str());
+            }
+            remaining.insert(name.as_str(), deps);
+        }
+
+        let mut order = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            let ready: Vec<&str> = remaining.iter()
+                .filter(|(_, deps)| deps.is_empty())
+                .map(|(name, _)| *name)
+                .collect();
+
+            if ready.is_empty() {
+                let cycle: Vec<&str> = remaining.keys().copied().collect();
+                return Err(format!("Dependency cycle between services: {}", cycle.join(", ")));
+            }
+
+            for name in ready {
+                remaining.remove(name);
+                for deps in remaining.values_mut() {
+                    deps.remove(name);
+                }
+                order.push(name.to_string());
+            }
+        }
+
+        Ok(order)
+    }
+
+    /// Daemon-side name of a stack network
+    pub fn network_name(stack: &str, network: &str) -> String {
+        format!("{}-{}", stack, network)
+    }
+
+    /// Bridge names are capped at 15 characters, so derive a short stable one
+    fn bridge_name(stack: &str, network: &str) -> String {
+        let hash = blake3::hash(Self::network_name(stack, network).as_bytes()).to_hex();
+        format!("qs{}", &hash[..10])
+    }
+
+    fn resolve_volume(&self, stack: &str, volume: &str) -> Result<String, String> {
+        let (source, rest) = volume.split_once(':')
+            .ok_or_else(|| format!("Volume '{}' must be source:container_path[:ro]", volume))?;
+
+        if source.starts_with('/') {
+            return Ok(volume.to_string());
+        }
+        if !self.volumes.iter().any(|name| name == source) {
+            return Err(format!("Volume '{}' uses undeclared named volume '{}'", volume, source));
+        }
+        Ok(format!("{}/{}_{}:{}", VOLUMES_ROOT, stack, source, rest))
+    }
+
+    /// The create request for one service, labelled with the stack, service and
+    /// a hash of the resolved spec so later runs can detect drift
+    pub fn service_request(&self, stack: &str, service_name: &str) -> Result<CreateContainerRequest, String> {
+        let service = self.services.get(service_name)
+            .ok_or_else(|| format!("Unknown service {}", service_name))?;
+
+        if service.template.is_none() && service.image.is_none() {
+            return Err(format!("Service {} needs an image or a template", service_name));
+        }
+
+        let mut resolved = service.clone();
+        resolved.networks = service.networks.iter()
+            .map(|network| if self.networks.contains_key(network) {
+                Self::network_name(stack, network)
+            } else {
+                network.clone()
+            })
+            .collect();
+        resolved.volumes = service.volumes.iter()
+            .map(|volume| self.resolve_volume(stack, volume))
+            .collect::<Result<_, _>>()?;
+
+        let spec_json = serde_json::to_string(&resolved)
+            .map_err(|e| format!("Failed to serialize service {}: {}", service_name, e))?;
+        let spec_hash = blake3::hash(spec_json.as_bytes()).to_hex()[..16].to_string();
+
+        let (template, template_version) = match &resolved.template {
+            Some(reference) => parse_template_ref(reference)?,
+            None => (String::new(), 0),
+        };
+
+        let mut labels: HashMap<String, String> = resolved.labels.clone().into_iter().collect();
+        labels.insert(STACK_LABEL.to_string(), stack.to_string());
+        labels.insert(SERVICE_LABEL.to_string(), service_name.to_string());
+        labels.insert(SPEC_HASH_LABEL.to_string(), spec_hash);
+
+        Ok(CreateContainerRequest {
+            image_path: resolved.image.unwrap_or_default(),
+            command: resolved.command,
+            environment: resolved.environment.into_iter().collect(),
+            working_directory: resolved.working_directory.unwrap_or_default(),
+            setup_commands: resolved.setup,
+            memory_limit_mb: resolved.memory_mb.unwrap_or(0),
+            cpu_limit_percent: resolved.cpu_percent.unwrap_or(0.0),
+            enable_pid_namespace: true,
+            enable_mount_namespace: true,
+            enable_uts_namespace: true,
+            enable_ipc_namespace: true,
+            enable_network_namespace: true,
+            auto_start: true,
+            networks: resolved.networks,
+            owner: resolved.owner.unwrap_or_default(),
+            template,
+            template_version,
+            name: format!("{}_{}", stack, service_name),
+            labels,
+            volumes: resolved.volumes,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StackAction {
+    Create { service: String },
+    Recreate { service: String, container_id: String, reason: String },
+    Unchanged { service: String, container_id: String },
+    Remove { service: String, container_id: String },
+}
+
+fn is_live(container: &ContainerInfo) -> bool {
+    matches!(
+        ContainerStatus::from_i32(container.status),
+        Some(ContainerStatus::Pending) | Some(ContainerStatus::Running)
+    )
+}
+
+/// Diff the desired services against the stack's existing containers. Services
+/// are planned in startup order; removals of orphaned containers come first.
+pub fn plan(desired: &[(String, CreateContainerRequest)], existing: &[ContainerInfo]) -> Vec<StackAction> {
+    let mut by_service: BTreeMap<&str, Vec<&ContainerInfo>> = BTreeMap::new();
+    for container in existing {
+        let service = container.labels.get(SERVICE_LABEL).map(String::as_str).unwrap_or("");
+        by_service.entry(service).or_default().push(container);
+    }
+
+    let mut actions = Vec::new();
+
+    for (service, containers) in &by_service {
+        if !desired.iter().any(|(name, _)| name == service) {
+            for container in containers {
+                actions.push(StackAction::Remove {
+                    service: service.to_string(),
+                    container_id: container.container_id.clone(),
+                });
+            }
+        }
+    }
+
+    for (service, request) in desired {
+        let containers = by_service.remove(service.as_str()).unwrap_or_default();
+        let wanted_hash = request.labels.get(SPEC_HASH_LABEL);
+
+        // Keep the first live, up-to-date container; anything else is replaced
+        let keep = containers.iter()
+            .position(|c| is_live(c) && c.labels.get(SPEC_HASH_LABEL) == wanted_hash);
+
+        for (index, container) in containers.iter().enumerate() {
+            if Some(index) == keep {
+                continue;
+            }
+            if keep.is_some() || index > 0 {
+                actions.push(StackAction::Remove {
+                    service: service.clone(),
+                    container_id: container.container_id.clone(),
+                });
+            }
+        }
+
+        match (keep, containers.first()) {
+            (Some(index), _) => actions.push(StackAction::Unchanged {
+                service: service.clone(),
+                container_id: containers[index].container_id.clone(),
+            }),
+            (None, Some(container)) => actions.push(StackAction::Recreate {
+                service: service.clone(),
+                container_id: container.container_id.clone(),
+                reason: if is_live(container) { "spec changed".to_string() } else { "not running".to_string() },
+            }),
+            (None, None) => actions.push(StackAction::Create { service: service.clone() }),
+        }
+    }
+
+    actions
+}
+
+struct LoadedStack {
+    file: StackFile,
+    name: String,
+    order: Vec<String>,
+}
+
+fn load_stack(file: &Path, name: Option<String>) -> Result<LoadedStack, String> {
+    let stack = StackFile::load(file)?;
+    let name = stack.resolve_name(file, name)?;
+    let order = stack.startup_order()?;
+    Ok(LoadedStack { file: stack, name, order })
+}
+
+async fn stack_containers(client: &mut QuiltServiceClient<Channel>, stack: &str) -> Result<Vec<ContainerInfo>, Box<dyn std::error::Error>> {
+    let res = client.list_containers(tonic::Request::new(ListContainersRequest { state_filter: 0 })).await?.into_inner();
+    Ok(res.containers.into_iter()
+        .filter(|c| c.labels.get(STACK_LABEL).map(String::as_str) == Some(stack))
+        .collect())
+}
+
+async fn remove_container(client: &mut QuiltServiceClient<Channel>, container_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let res = client.remove_container(tonic::Request::new(RemoveContainerRequest {
+        container_id: container_id.to_string(),
+        force: true,
+    })).await?.into_inner();
+
+    if !res.success {
+        return Err(format!("Failed to remove container {}: {}", container_id, res.error_message).into());
+    }
+    Ok(())
+}
+
+async fn wait_until_running(client: &mut QuiltServiceClient<Channel>, service: &str, container_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let deadline = tokio::time::Instant::now() + DEPENDENCY_TIMEOUT;
+    loop {
+        let res = client.get_container_status(tonic::Request::new(GetContainerStatusRequest {
+            container_id: container_id.to_string(),
+        })).await?.into_inner();
+
+        match ContainerStatus::from_i32(res.status) {
+            Some(ContainerStatus::Running) => return Ok(()),
+            Some(ContainerStatus::Exited) | Some(ContainerStatus::Failed) => {
+                return Err(format!("Dependency {} stopped before becoming ready: {}", service, res.error_message).into());
+            }
+            _ => {}
+        }
+
+        if tokio::time::Instant::now() >= deadline {
+            return Err(format!("Timed out waiting for dependency {} to start", service).into());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+async fn ensure_networks(client: &mut QuiltServiceClient<Channel>, stack: &LoadedStack) -> Result<(), Box<dyn std::error::Error>> {
+    let existing = client.list_networks(tonic::Request::new(ListNetworksRequest {})).await?.into_inner();
+
+    for (network, spec) in &stack.file.networks {
+        let name = StackFile::network_name(&stack.name, network);
+        if existing.networks.iter().any(|n| n.name == name) {
+            continue;
+        }
+
+        println!("🌐 Creating network {} ({})...", name, spec.subnet);
+        let res = client.create_network(tonic::Request::new(CreateNetworkRequest {
+            name: name.clone(),
+            subnet_cidr: spec.subnet.clone(),
+            bridge_name: StackFile::bridge_name(&stack.name, network),
+            ..Default::default()
+        })).await?.into_inner();
+
+        if !res.success {
+            return Err(format!("Failed to create network {}: {}", name, res.error_message).into());
+        }
+    }
+    Ok(())
+}
+
+fn print_plan(actions: &[StackAction]) {
+    for action in actions {
+        match action {
+            StackAction::Create { service } => println!("   + {} (create)", service),
+            StackAction::Recreate { service, container_id, reason } => println!("   ~ {} (recreate {}: {})", service, container_id, reason),
+            StackAction::Unchanged { service, container_id } => println!("   = {} ({} up to date)", service, container_id),
+            StackAction::Remove { service, container_id } => println!("   - {} (remove {})", if service.is_empty() { "<unknown>" } else { service }, container_id),
+        }
+    }
+}
+
+pub async fn handle_stack_command(cmd: StackCommands, mut client: QuiltServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    match cmd {
+        StackCommands::Up { file, name, dry_run } => {
+            let stack = load_stack(&file, name)?;
+            let desired = stack.order.iter()
+                .map(|service| Ok((service.clone(), stack.file.service_request(&stack.name, service)?)))
+                .collect::<Result<Vec<_>, String>>()?;
+
+            let existing = stack_containers(&mut client, &stack.name).await?;
+            let actions = plan(&desired, &existing);
+
+            println!("📦 Stack {} plan:", stack.name);
+            print_plan(&actions);
+            if dry_run {
+                return Ok(());
+            }
+
+            ensure_networks(&mut client, &stack).await?;
+
+            let mut container_ids: HashMap<String, String> = HashMap::new();
+            for action in &actions {
+                match action {
+                    StackAction::Remove { container_id, .. } => remove_container(&mut client, container_id).await?,
+                    StackAction::Unchanged { service, container_id } => {
+                        container_ids.insert(service.clone(), container_id.clone());
+                    }
+                    _ => {}
+                }
+            }
+
+            for (service, request) in desired {
+                let replaced = actions.iter().find_map(|action| match action {
+                    StackAction::Recreate { service: s, container_id, .. } if *s == service => Some(container_id.clone()),
+                    _ => None,
+                });
+                let needs_create = replaced.is_some() || actions.contains(&StackAction::Create { service: service.clone() });
+                if !needs_create {
+                    continue;
+                }
+
+                for dep in &stack.file.services[&service].depends_on {
+                    if let Some(dep_id) = container_ids.get(dep) {
+                        wait_until_running(&mut client, dep, dep_id).await?;
+                    }
+                }
+
+                if let Some(old_id) = replaced {
+                    println!("🔄 Replacing {} ({})...", service, old_id);
+                    remove_container(&mut client, &old_id).await?;
+                } else {
+                    println!("🚀 Creating {}...", service);
+                }
+
+                let res = client.create_container(tonic::Request::new(request)).await?.into_inner();
+                if !res.success {
+                    eprintln!("❌ Failed to create {}: {}", service, res.error_message);
+                    std::process::exit(1);
+                }
+                container_ids.insert(service, res.container_id);
+            }
+
+            println!("✅ Stack {} is up ({} service(s))", stack.name, container_ids.len());
+        }
+        StackCommands::Down { file, name } => {
+            let stack = load_stack(&file, name)?;
+            let existing = stack_containers(&mut client, &stack.name).await?;
+
+            // Dependents first, then their dependencies, then anything unknown
+            let rank = |c: &ContainerInfo| {
+                let service = c.labels.get(SERVICE_LABEL).map(String::as_str).unwrap_or("");
+                stack.order.iter().position(|s| s == service).map_or(0, |i| i + 1)
+            };
+            let mut containers = existing;
+            containers.sort_by_key(|c| std::cmp::Reverse(rank(c)));
+
+            println!("🛑 Taking down stack {} ({} container(s))...", stack.name, containers.len());
+            for container in &containers {
+                let service = container.labels.get(SERVICE_LABEL).cloned().unwrap_or_default();
+                println!("🗑️  Removing {} ({})...", service, container.container_id);
+                remove_container(&mut client, &container.container_id).await?;
+            }
+
+            for network in stack.file.networks.keys() {
+                let name = StackFile::network_name(&stack.name, network);
+                let res = client.remove_network(tonic::Request::new(RemoveNetworkRequest { name: name.clone() })).await?.into_inner();
+                if res.success {
+                    println!("🌐 Removed network {}", name);
+                } else {
+                    println!("⚠️  Could not remove network {}: {}", name, res.error_message);
+                }
+            }
+
+            println!("✅ Stack {} is down (named volumes under {} are kept)", stack.name, VOLUMES_ROOT);
+        }
+        StackCommands::Status { file, name } => {
+            let stack = load_stack(&file, name)?;
+            let desired = stack.order.iter()
+                .map(|service| Ok((service.clone(), stack.file.service_request(&stack.name, service)?)))
+                .collect::<Result<Vec<_>, String>>()?;
+            let existing = stack_containers(&mut client, &stack.name).await?;
+            let actions = plan(&desired, &existing);
+
+            let status_of = |container_id: &str| {
+                existing.iter()
+                    .find(|c| c.container_id == container_id)
+                    .and_then(|c| ContainerStatus::from_i32(c.status))
+                    .map(|s| s.as_str_name().to_string())
+                    .unwrap_or_else(|| "UNKNOWN".to_string())
+            };
+
+            println!("📦 Stack {}:", stack.name);
+            println!("{:<20} {:<38} {:<10} {}", "SERVICE", "CONTAINER", "STATUS", "STATE");
+            for action in &actions {
+                match action {
+                    StackAction::Create { service } => println!("{:<20} {:<38} {:<10} {}", service, "-", "-", "missing"),
+                    StackAction::Recreate { service, container_id, reason } => println!("{:<20} {:<38} {:<10} drifted ({})", service, container_id, status_of(container_id), reason),
+                    StackAction::Unchanged { service, container_id } => println!("{:<20} {:<38} {:<10} {}", service, container_id, status_of(container_id), "in sync"),
+                    StackAction::Remove { service, container_id } => println!("{:<20} {:<38} {:<10} {}", service, container_id, status_of(container_id), "orphaned"),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STACK_YAML: &str = r#"
+name: shop
+networks:
+  backend:
+    subnet: 10.60.0.0/24
+volumes: [dbdata]
+services:
+  db:
+    image: /images/postgres.tar.gz
+    networks: [backend]
+    volumes: ["dbdata:/var/lib/postgresql"]
+  api:
+    template: node@2
+    command: [node, server.js]
+    environment:
+      DB_HOST: db
+    networks: [backend]
+    depends_on: [db]
+  web:
+    image: /images/nginx.tar.gz
+    depends_on: [api]
+"#;
+
+    fn container(id: &str, service: &str, hash: &str, status: ContainerStatus) -> ContainerInfo {
+        let mut labels = HashMap::new();
+        labels.insert(STACK_LABEL.to_string(), "shop".to_string());
+        labels.insert(SERVICE_LABEL.to_string(), service.to_string());
+        labels.insert(SPEC_HASH_LABEL.to_string(), hash.to_string());
+        ContainerInfo {
+            container_id: id.to_string(),
+            status: status as i32,
+            labels,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_yaml_and_toml_parse_identically() {
+        let yaml: StackFile = serde_yaml::from_str(STACK_YAML).unwrap();
+        let toml_stack: StackFile = toml::from_str(r#"
+name = "shop"
+volumes = ["dbdata"]
+
+[networks.backend]
+subnet = "10.60.0.0/24"
+
+[services.db]
+image = "/images/postgres.tar.gz"
+networks = ["backend"]
+volumes = ["dbdata:/var/lib/postgresql"]
+
+[services.api]
+template = "node@2"
+command = ["node", "server.js"]
+environment = { DB_HOST = "db" }
+networks = ["backend"]
+depends_on = ["db"]
+
+[services.web]
+image = "/images/nginx.tar.gz"
+depends_on = ["api"]
+"#).unwrap();
+
+        assert_eq!(yaml, toml_stack);
+        assert!(serde_yaml::from_str::<StackFile>("services:\n  a:\n    imgae: x\n").is_err());
+    }
+
+    #[test]
+    fn test_startup_order_and_cycles() {
+        let stack: StackFile = serde_yaml::from_str(STACK_YAML).unwrap();
+        assert_eq!(stack.startup_order().unwrap(), vec!["db", "api", "web"]);
+
+        let mut cyclic = stack.clone();
+        cyclic.services.get_mut("db").unwrap().depends_on = vec!["web".to_string()];
+        assert!(cyclic.startup_order().unwrap_err().contains("cycle"));
+
+        let mut unknown = stack;
+        unknown.services.get_mut("web").unwrap().depends_on = vec!["cache".to_string()];
+        assert!(unknown.startup_order().is_err());
+    }
+
+    #[test]
+    fn test_service_request_resolution() {
+        let stack: StackFile = serde_yaml::from_str(STACK_YAML).unwrap();
+
+        let db = stack.service_request("shop", "db").unwrap();
+        assert_eq!(db.networks, vec!["shop-backend"]);
+        assert_eq!(db.volumes, vec![format!("{}/shop_dbdata:/var/lib/postgresql", VOLUMES_ROOT)]);
+        assert_eq!(db.name, "shop_db");
+        assert_eq!(db.labels[STACK_LABEL], "shop");
+
+        let api = stack.service_request("shop", "api").unwrap();
+        assert_eq!((api.template.as_str(), api.template_version), ("node", 2));
+
+        // The hash changes with the spec and is stable otherwise
+        let mut changed = stack.clone();
+        changed.services.get_mut("db").unwrap().memory_mb = Some(1024);
+        let db_changed = changed.service_request("shop", "db").unwrap();
+        assert_ne!(db.labels[SPEC_HASH_LABEL], db_changed.labels[SPEC_HASH_LABEL]);
+        assert_eq!(db.labels[SPEC_HASH_LABEL], stack.service_request("shop", "db").unwrap().labels[SPEC_HASH_LABEL]);
+
+        let mut undeclared = stack;
+        undeclared.volumes.clear();
+        assert!(undeclared.service_request("shop", "db").is_err());
+    }
+
+    #[test]
+    fn test_plan_diff() {
+        let stack: StackFile = serde_yaml::from_str(STACK_YAML).unwrap();
+        let desired: Vec<_> = stack.startup_order().unwrap().into_iter()
+            .map(|s| { let r = stack.service_request("shop", &s).unwrap(); (s, r) })
+            .collect();
+        let db_hash = desired[0].1.labels[SPEC_HASH_LABEL].clone();
+
+        let existing = vec![
+            container("c-db", "db", &db_hash, ContainerStatus::Running),
+            container("c-api", "api", "stale", ContainerStatus::Running),
+            container("c-cache", "cache", "x", ContainerStatus::Running),
+        ];
+
+        let actions = plan(&desired, &existing);
+        assert_eq!(actions, vec![
+            StackAction::Remove { service: "cache".to_string(), container_id: "c-cache".to_string() },
+            StackAction::Unchanged { service: "db".to_string(), container_id: "c-db".to_string() },
+            StackAction::Recreate { service: "api".to_string(), container_id: "c-api".to_string(), reason: "spec changed".to_string() },
+            StackAction::Create { service: "web".to_string() },
+        ]);
+
+        let exited = vec![container("c-db", "db", &db_hash, ContainerStatus::Exited)];
+        assert!(matches!(&plan(&desired, &exited)[0], StackAction::Recreate { reason, .. } if reason == "not running"));
+    }
+}
@@ -0,0 +1,203 @@
+// src/cli/templates.rs
+// Container template CLI commands
+
+use clap::Subcommand;
+use tonic::transport::Channel;
+
+use crate::quilt::{
+    quilt_service_client::QuiltServiceClient,
+    ContainerTemplate, CreateContainerRequest, CreateTemplateRequest, DeleteTemplateRequest,
+    GetTemplateRequest, ListTemplatesRequest,
+};
+use crate::utils::validation::InputValidator;
+
+#[derive(Subcommand, Debug)]
+pub enum TemplateCommands {
+    /// Save a container spec as the next version of a template
+    Create {
+        #[clap(help = "Template name")]
+        name: String,
+        #[clap(long, help = "Human-readable description")]
+        description: Option<String>,
+        #[clap(long, help = "Path to the container image tarball")]
+        image_path: Option<String>,
+        #[arg(short, long, action = clap::ArgAction::Append,
+              help = "Environment variables in KEY=VALUE format",
+              value_parser = InputValidator::parse_key_val)]
+        env: Vec<(String, String)>,
+        #[clap(long, help = "Setup commands (e.g., 'npm: typescript')")]
+        setup: Vec<String>,
+        #[clap(long, help = "Working directory inside the container")]
+        working_directory: Option<String>,
+        #[clap(long, help = "Memory limit in megabytes (0 = default)", default_value = "0")]
+        memory_limit: i32,
+        #[clap(long, help = "CPU limit as percentage (0.0 = default)", default_value = "0.0")]
+        cpu_limit: f32,
+        #[clap(long, help = "Enable all namespace isolation features")]
+        enable_all_namespaces: bool,
+        #[clap(long = "network", help = "Network to attach to (repeatable, first is primary)")]
+        networks: Vec<String>,
+        #[clap(short = 'v', long = "volume", help = "Bind mount as host_path:container_path[:ro] (repeatable)")]
+        volumes: Vec<String>,
+        #[arg(long = "label", action = clap::ArgAction::Append,
+              help = "Labels in KEY=VALUE format",
+              value_parser = InputValidator::parse_key_val)]
+        labels: Vec<(String, String)>,
+        #[clap(num_args = 0.., help = "Default command and its arguments (after --)")]
+        command: Vec<String>,
+    },
+
+    /// List templates (latest versions, or every version of one template)
+    List {
+        #[clap(help = "Show every version of this template")]
+        name: Option<String>,
+    },
+
+    /// Show a template (name or name@version)
+    Show {
+        #[clap(help = "Template reference, e.g. web or web@2")]
+        template: String,
+    },
+
+    /// Delete a template version, or all versions when no version is given
+    Delete {
+        #[clap(help = "Template reference, e.g. web or web@2")]
+        template: String,
+    },
+}
+
+/// Split `name@version` into its parts; a bare name means the latest version (0)
+pub fn parse_template_ref(reference: &str) -> Result<(String, u32), String> {
+    match reference.split_once('@') {
+        Some((name, version)) => {
+            let version = version.parse::<u32>()
+                .map_err(|_| format!("Invalid template version in '{}'", reference))?;
+            Ok((name.to_string(), version))
+        }
+        None => Ok((reference.to_string(), 0)),
+    }
+}
+
+fn print_template(template: &ContainerTemplate) {
+    println!("   Name:        {}@{}", template.name, template.version);
+    if !template.description.is_empty() {
+        println!("   Description: {}", template.description);
+    }
+    if let Some(spec) = &template.spec {
+        if !spec.image_path.is_empty() {
+            println!("   Image:       {}", spec.image_path);
+        }
+        if !spec.command.is_empty() {
+            println!("   Command:     {}", spec.command.join(" "));
+        }
+        if spec.memory_limit_mb > 0 {
+            println!("   Memory:      {}MB", spec.memory_limit_mb);
+        }
+        if spec.cpu_limit_percent > 0.0 {
+            println!("   CPU:         {}%", spec.cpu_limit_percent);
+        }
+        for (key, value) in &spec.environment {
+            println!("   Env:         {}={}", key, value);
+        }
+        for setup in &spec.setup_commands {
+            println!("   Setup:       {}", setup);
+        }
+        for network in &spec.networks {
+            println!("   Network:     {}", network);
+        }
+        for volume in &spec.volumes {
+            println!("   Volume:      {}", volume);
+        }
+        for (key, value) in &spec.labels {
+            println!("   Label:       {}={}", key, value);
+        }
+    }
+}
+
+pub async fn handle_template_command(cmd: TemplateCommands, mut client: QuiltServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    match cmd {
+        TemplateCommands::Create {
+            name, description, image_path, env, setup, working_directory, memory_limit, cpu_limit,
+            enable_all_namespaces, networks, volumes, labels, command,
+        } => {
+            println!("🧩 Saving template {}...", name);
+            let spec = CreateContainerRequest {
+                image_path: image_path.unwrap_or_default(),
+                command,
+                environment: env.into_iter().collect(),
+                working_directory: working_directory.unwrap_or_default(),
+                setup_commands: setup,
+                memory_limit_mb: memory_limit,
+                cpu_limit_percent: cpu_limit,
+                enable_pid_namespace: enable_all_namespaces,
+                enable_mount_namespace: enable_all_namespaces,
+                enable_uts_namespace: enable_all_namespaces,
+                enable_ipc_namespace: enable_all_namespaces,
+                enable_network_namespace: enable_all_namespaces || !networks.is_empty(),
+                networks,
+                volumes,
+                labels: labels.into_iter().collect(),
+                ..Default::default()
+            };
+
+            let res = client.create_template(tonic::Request::new(CreateTemplateRequest {
+                name,
+                description: description.unwrap_or_default(),
+                spec: Some(spec),
+            })).await?.into_inner();
+
+            match (res.success, res.template) {
+                (true, Some(template)) => {
+                    println!("✅ Template saved successfully!");
+                    print_template(&template);
+                }
+                _ => {
+                    eprintln!("❌ Failed to save template: {}", res.error_message);
+                    std::process::exit(1);
+                }
+            }
+        }
+        TemplateCommands::List { name } => {
+            let res = client.list_templates(tonic::Request::new(ListTemplatesRequest {
+                name: name.unwrap_or_default(),
+            })).await?.into_inner();
+
+            if res.templates.is_empty() {
+                println!("📋 No templates defined");
+                return Ok(());
+            }
+
+            println!("{:<24} {:>8} {:<40} {}", "NAME", "VERSION", "IMAGE", "DESCRIPTION");
+            for template in res.templates {
+                let image = template.spec.as_ref().map(|spec| spec.image_path.clone()).unwrap_or_default();
+                println!("{:<24} {:>8} {:<40} {}", template.name, template.version, image, template.description);
+            }
+        }
+        TemplateCommands::Show { template } => {
+            let (name, version) = parse_template_ref(&template)?;
+            let res = client.get_template(tonic::Request::new(GetTemplateRequest { name, version })).await?.into_inner();
+            match (res.success, res.template) {
+                (true, Some(template)) => {
+                    println!("🧩 Template:");
+                    print_template(&template);
+                }
+                _ => {
+                    eprintln!("❌ Failed to get template: {}", res.error_message);
+                    std::process::exit(1);
+                }
+            }
+        }
+        TemplateCommands::Delete { template } => {
+            let (name, version) = parse_template_ref(&template)?;
+            println!("🗑️  Deleting template {}...", template);
+            let res = client.delete_template(tonic::Request::new(DeleteTemplateRequest { name, version })).await?.into_inner();
+            if res.success {
+                println!("✅ Deleted {} version(s) of {}", res.deleted_versions, template);
+            } else {
+                eprintln!("❌ Failed to delete template: {}", res.error_message);
+                std::process::exit(1);
+            }
+        }
+    }
+    Ok(())
+}
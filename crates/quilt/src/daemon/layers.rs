@@ -0,0 +1,378 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use crate::daemon::manager::RuntimeManager;
+use crate::daemon::namespace::{NamespaceConfig, NamespaceManager};
+use crate::utils::{CommandExecutor, ConsoleLogger, FileSystemUtils, ImageLayerCache, ImageManager};
+
+/// Where prebuilt setup layers live, one directory per layer key
+pub const RUNTIME_LAYERS_ROOT: &str = "/tmp/quilt-image-cache/runtime-layers";
+
+/// Total size the cache may grow to before least-recently-used layers are evicted
+pub const DEFAULT_MAX_CACHE_BYTES: u64 = 8 * 1024 * 1024 * 1024;
+
+const LAYER_DIR: &str = "layer";
+const METADATA_FILE: &str = "layer.json";
+const BUILD_PREFIX: &str = ".build-";
+
+static RUNTIME_LAYER_CACHE: once_cell::sync::Lazy<Arc<RuntimeLayerCache>> =
+    once_cell::sync::Lazy::new(|| {
+        let cache = RuntimeLayerCache::new(RUNTIME_LAYERS_ROOT, DEFAULT_MAX_CACHE_BYTES);
+        cache.clear_stale_builds();
+        Arc::new(cache)
+    });
+
+/// Metadata stored next to each layer as `layer.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeLayerInfo {
+    pub key: String,
+    pub image_path: String,
+    pub base_layer_hash: String,
+    /// Normalized setup spec the layer was built from
+    pub setup_spec: Vec<String>,
+    pub size_bytes: u64,
+    pub created_at: u64,
+    pub last_used_at: u64,
+    pub build_duration_ms: u64,
+}
+
+/// Content-addressed cache of overlay layers holding the result of running a
+/// container's setup commands on top of its base image. Layers are keyed by the
+/// base image plus the normalized setup spec, so any container with the same
+/// image and equivalent setup mounts the prebuilt layer instead of installing
+/// packages again.
+pub struct RuntimeLayerCache {
+    root: PathBuf,
+    max_bytes: u64,
+    build_locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl RuntimeLayerCache {
+    pub fn new(root: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            root: root.into(),
+            max_bytes,
+            build_locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn global() -> Arc<RuntimeLayerCache> {
+        RUNTIME_LAYER_CACHE.clone()
+    }
+
+    /// Canonical form of a setup spec: runtime aliases collapse to one name
+    /// ("npm" and "nodejs" are the same), packages are de-duplicated and sorted,
+    /// and lines are sorted by runtime. Installs for different runtimes don't
+    /// depend on each other, so ordering never changes the resulting layer.
+    pub fn normalize_setup_spec(setup_commands: &[String]) -> Result<Vec<String>, String> {
+        let manager = RuntimeManager::new();
+        let commands = manager.parse_setup_spec(&setup_commands.join("\n"))?;
+
+        let mut by_runtime: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for command in commands {
+            by_runtime.entry(command.runtime.get_name().to_lowercase())
+                .or_default()
+                .extend(command.packages);
+        }
+
+        Ok(by_runtime.into_iter()
+            .map(|(runtime, mut packages)| {
+                packages.sort();
+                packages.dedup();
+                format!("{}: {}", runtime, packages.join(" "))
+            })
+            .collect())
+    }
+
+    pub fn layer_key(base_layer_hash: &str, normalized_spec: &[String]) -> String {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(base_layer_hash.as_bytes());
+        for line in normalized_spec {
+            hasher.update(b"\n");
+            hasher.update(line.as_bytes());
+        }
+        hasher.finalize().to_hex()[..32].to_string()
+    }
+
+    /// Return the layer directory for this image and setup spec, building it
+    /// first if it isn't cached yet
+    pub fn acquire(&self, image_path: &str, setup_commands: &[String]) -> Result<RuntimeLayerInfo, String> {
+        let normalized = Self::normalize_setup_spec(setup_commands)?;
+        if normalized.is_empty() {
+            return Err("Setup spec is empty".to_string());
+        }
+
+        let base_layer_hash = ImageLayerCache::get_layer_hash(image_path)?;
+        let key = Self::layer_key(&base_layer_hash, &normalized);
+
+        // One build per key; concurrent creates with the same spec wait and reuse it
+        let build_lock = {
+            let mut locks = self.build_locks.lock().map_err(|_| "Failed to lock layer build table")?;
+            locks.entry(key.clone()).or_default().clone()
+        };
+        let _guard = build_lock.lock().map_err(|_| "Failed to lock layer build")?;
+
+        let info = match self.read_info(&key) {
+            Some(mut info) => {
+                info.last_used_at = Self::now();
+                self.write_info(&info)?;
+                ConsoleLogger::debug(&format!("Reusing runtime layer {} for {}", key, normalized.join("; ")));
+                info
+            }
+            None => {
+                let info = self.build(&key, image_path, &base_layer_hash, normalized)?;
+                let evicted = self.evict_to(self.max_bytes)?;
+                if !evicted.is_empty() {
+                    ConsoleLogger::info(&format!("Evicted {} runtime layer(s) to stay under {} bytes", evicted.len(), self.max_bytes));
+                }
+                info
+            }
+        };
+
+        Ok(info)
+    }
+
+    /// Build (or refresh the last-used time of) a layer ahead of time so the
+    /// first container with this spec starts without installing anything
+    pub fn prewarm(&self, image_path: &str, setup_commands: &[String]) -> Result<RuntimeLayerInfo, String> {
+        ConsoleLogger::progress(&format!("Pre-warming runtime layer for {}", image_path));
+        self.acquire(image_path, setup_commands)
+    }
+
+    pub fn layer_path(&self, key: &str) -> PathBuf {
+        self.root.join(key).join(LAYER_DIR)
+    }
+
+    /// All cached layers, most recently used first
+    pub fn list(&self) -> Vec<RuntimeLayerInfo> {
+        let mut layers: Vec<RuntimeLayerInfo> = match fs::read_dir(&self.root) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+                .filter(|name| !name.starts_with(BUILD_PREFIX))
+                .filter_map(|key| self.read_info(&key))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        layers.sort_by(|a, b| b.last_used_at.cmp(&a.last_used_at));
+        layers
+    }
+
+    /// Evict least-recently-used layers that no mounted overlay references until
+    /// the cache fits in `max_bytes`. Returns the evicted keys.
+    pub fn evict_to(&self, max_bytes: u64) -> Result<Vec<String>, String> {
+        let mounts = fs::read_to_string("/proc/mounts").unwrap_or_default();
+        let mut layers = self.list();
+        let mut total: u64 = layers.iter().map(|layer| layer.size_bytes).sum();
+        let mut evicted = Vec::new();
+
+        // Oldest last in `list`, so evict from the back
+        while total > max_bytes {
+            let Some(layer) = layers.pop() else { break };
+            let layer_path = self.layer_path(&layer.key);
+            if mounts.contains(layer_path.to_string_lossy().as_ref()) {
+                continue;
+            }
+
+            fs::remove_dir_all(self.root.join(&layer.key))
+                .map_err(|e| format!("Failed to evict runtime layer {}: {}", layer.key, e))?;
+            total = total.saturating_sub(layer.size_bytes);
+            ConsoleLogger::debug(&format!("Evicted runtime layer {} ({} bytes)", layer.key, layer.size_bytes));
+            evicted.push(layer.key);
+        }
+
+        Ok(evicted)
+    }
+
+    /// Remove build directories left behind by a daemon that died mid-build
+    pub fn clear_stale_builds(&self) {
+        let Ok(entries) = fs::read_dir(&self.root) else { return };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(BUILD_PREFIX) {
+                let rootfs = entry.path().join("rootfs");
+                let _ = CommandExecutor::execute_shell(&format!("umount -l {}", rootfs.display()));
+                let _ = fs::remove_dir_all(entry.path());
+                ConsoleLogger::debug(&format!("Removed stale runtime layer build {}", name));
+            }
+        }
+    }
+
+    fn build(&self, key: &str, image_path: &str, base_layer_hash: &str, normalized: Vec<String>) -> Result<RuntimeLayerInfo, String> {
+        if !ImageManager::is_overlay_supported()? {
+            return Err("Overlay filesystem not supported; runtime layers unavailable".to_string());
+        }
+
+        ConsoleLogger::progress(&format!("Building runtime layer {} ({})", key, normalized.join("; ")));
+        let started = std::time::Instant::now();
+
+        let base_layer = ImageManager::ensure_base_layer(image_path)?;
+        let build_dir = self.root.join(format!("{}{}", BUILD_PREFIX, key));
+        let rootfs = build_dir.join("rootfs");
+        let _ = fs::remove_dir_all(&build_dir);
+
+        ImageManager::create_overlay_mount(&build_dir.to_string_lossy(), &base_layer, &rootfs.to_string_lossy())?;
+
+        let result = Self::run_setup(&rootfs.to_string_lossy(), &normalized);
+        let _ = CommandExecutor::execute_shell(&format!("umount {}", rootfs.display()));
+
+        if let Err(e) = result {
+            let _ = fs::remove_dir_all(&build_dir);
+            return Err(format!("Runtime layer build failed: {}", e));
+        }
+
+        // The overlay's upper dir now holds exactly what setup changed
+        let layer_root = self.root.join(key);
+        FileSystemUtils::create_dir_all_with_logging(&layer_root.to_string_lossy(), "runtime layer")?;
+        fs::rename(build_dir.join("upper"), self.layer_path(key))
+            .map_err(|e| format!("Failed to publish runtime layer {}: {}", key, e))?;
+        let _ = fs::remove_dir_all(&build_dir);
+
+        let now = Self::now();
+        let info = RuntimeLayerInfo {
+            key: key.to_string(),
+            image_path: image_path.to_string(),
+            base_layer_hash: base_layer_hash.to_string(),
+            setup_spec: normalized,
+            size_bytes: ImageManager::calculate_directory_size(&self.layer_path(key).to_string_lossy())?,
+            created_at: now,
+            last_used_at: now,
+            build_duration_ms: started.elapsed().as_millis() as u64,
+        };
+        self.write_info(&info)?;
+
+        ConsoleLogger::success(&format!("Runtime layer {} built in {}ms ({} bytes)", key, info.build_duration_ms, info.size_bytes));
+        Ok(info)
+    }
+
+    /// Run the setup commands chrooted into `rootfs` in a throwaway process.
+    /// The host network is shared so package downloads work before any
+    /// container network exists.
+    fn run_setup(rootfs: &str, normalized: &[String]) -> Result<(), String> {
+        let commands = RuntimeManager::new().parse_setup_spec(&normalized.join("\n"))?;
+        let rootfs = rootfs.to_string();
+        let namespace_manager = NamespaceManager::new();
+        let namespace_config = NamespaceConfig {
+            pid: false,
+            mount: true,
+            uts: false,
+            ipc: false,
+            network: false,
+        };
+
+        let pid = namespace_manager.create_namespaced_process(&namespace_config, move || -> i32 {
+            let namespace_manager = NamespaceManager::new();
+            if let Err(e) = namespace_manager.setup_mount_namespace(&rootfs) {
+                eprintln!("Failed to setup mount namespace: {}", e);
+                return 1;
+            }
+            if let Err(e) = nix::unistd::chroot(rootfs.as_str()).and_then(|_| nix::unistd::chdir("/")) {
+                eprintln!("Failed to chroot to {}: {}", rootfs, e);
+                return 1;
+            }
+
+            let mut runtime_manager = RuntimeManager::new();
+            if let Err(e) = runtime_manager.initialize_container() {
+                eprintln!("Failed to initialize layer build environment: {}", e);
+                return 1;
+            }
+            if let Err(e) = runtime_manager.execute_setup_commands(&commands) {
+                eprintln!("Setup commands failed: {}", e);
+                return 1;
+            }
+            0
+        })?;
+
+        match namespace_manager.wait_for_process(pid)? {
+            0 => Ok(()),
+            code => Err(format!("setup exited with code {}", code)),
+        }
+    }
+
+    fn read_info(&self, key: &str) -> Option<RuntimeLayerInfo> {
+        if !self.layer_path(key).is_dir() {
+            return None;
+        }
+        let content = fs::read_to_string(self.root.join(key).join(METADATA_FILE)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn write_info(&self, info: &RuntimeLayerInfo) -> Result<(), String> {
+        let path = self.root.join(&info.key).join(METADATA_FILE);
+        let content = serde_json::to_string_pretty(info)
+            .map_err(|e| format!("Failed to serialize runtime layer metadata: {}", e))?;
+
+        // Write then rename so a crash never leaves a truncated metadata file
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, content).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+        fs::rename(&tmp, &path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    fn now() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    fn write_layer(cache: &RuntimeLayerCache, key: &str, size_bytes: u64, last_used_at: u64) {
+        fs::create_dir_all(cache.layer_path(key)).unwrap();
+        cache.write_info(&RuntimeLayerInfo {
+            key: key.to_string(),
+            image_path: "/images/base.tar.gz".to_string(),
+            base_layer_hash: "base".to_string(),
+            setup_spec: spec(&["nodejs: typescript"]),
+            size_bytes,
+            created_at: last_used_at,
+            last_used_at,
+            build_duration_ms: 0,
+        }).unwrap();
+    }
+
+    #[test]
+    fn test_normalize_setup_spec() {
+        let a = RuntimeLayerCache::normalize_setup_spec(&spec(&["pip: requests", "npm: typescript ts-node"])).unwrap();
+        let b = RuntimeLayerCache::normalize_setup_spec(&spec(&["nodejs:  ts-node typescript typescript", "python: requests"])).unwrap();
+
+        assert_eq!(a, b);
+        assert_eq!(a, spec(&["nodejs: ts-node typescript", "python: requests"]));
+
+        let key_a = RuntimeLayerCache::layer_key("image-1", &a);
+        assert_eq!(key_a, RuntimeLayerCache::layer_key("image-1", &b));
+        assert_ne!(key_a, RuntimeLayerCache::layer_key("image-2", &a));
+
+        assert!(RuntimeLayerCache::normalize_setup_spec(&spec(&["no runtime here"])).is_err());
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = RuntimeLayerCache::new(dir.path(), 250);
+
+        write_layer(&cache, "old", 100, 1);
+        write_layer(&cache, "mid", 100, 2);
+        write_layer(&cache, "new", 100, 3);
+        fs::create_dir_all(dir.path().join(format!("{}abandoned", BUILD_PREFIX))).unwrap();
+
+        let keys: Vec<String> = cache.list().into_iter().map(|layer| layer.key).collect();
+        assert_eq!(keys, vec!["new", "mid", "old"]);
+
+        assert_eq!(cache.evict_to(250).unwrap(), vec!["old"]);
+        assert_eq!(cache.evict_to(100).unwrap(), vec!["mid"]);
+        assert!(cache.read_info("new").is_some());
+
+        cache.clear_stale_builds();
+        assert!(!dir.path().join(format!("{}abandoned", BUILD_PREFIX)).exists());
+    }
+}
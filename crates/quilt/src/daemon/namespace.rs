@@ -5,6 +5,7 @@ use nix::sys::wait::{waitpid, WaitStatus};
 use std::path::Path;
 use crate::utils::{ConsoleLogger, ProcessUtils};
 use crate::utils::CommandExecutor;
+use crate::utils::validation::InputValidator;
 use crate::icc::network::ContainerNetworkConfig;
 
 #[derive(Debug, Clone)]
@@ -250,6 +251,56 @@ impl NamespaceManager {
         Ok(())
     }
 
+    /// Bind mount volumes (`host_path:container_path[:ro]`) into the rootfs. Must run
+    /// inside the container's mount namespace, before chroot. Missing host
+    /// directories are created so named volumes work on first use.
+    pub fn mount_volumes(&self, rootfs_path: &str, volumes: &[String]) -> Result<(), String> {
+        for volume in volumes {
+            let (host_path, container_path, read_only) = InputValidator::parse_volume(volume)?;
+            
+            if !Path::new(&host_path).exists() {
+                std::fs::create_dir_all(&host_path)
+                    .map_err(|e| format!("Failed to create volume source {}: {}", host_path, e))?;
+            }
+            
+            let target = format!("{}{}", rootfs_path.trim_end_matches('/'), container_path);
+            if Path::new(&host_path).is_dir() {
+                std::fs::create_dir_all(&target)
+                    .map_err(|e| format!("Failed to create mount point {}: {}", target, e))?;
+            } else if !Path::new(&target).exists() {
+                if let Some(parent) = Path::new(&target).parent() {
+                    std::fs::create_dir_all(parent)
+                        .map_err(|e| format!("Failed to create mount point {}: {}", target, e))?;
+                }
+                std::fs::File::create(&target)
+                    .map_err(|e| format!("Failed to create mount point {}: {}", target, e))?;
+            }
+            
+            mount(
+                Some(host_path.as_str()),
+                target.as_str(),
+                None::<&str>,
+                MsFlags::MS_BIND | MsFlags::MS_REC,
+                None::<&str>,
+            ).map_err(|e| format!("Failed to bind mount {} to {}: {}", host_path, container_path, e))?;
+            
+            // Bind mounts ignore MS_RDONLY on the first call; it only applies on remount
+            if read_only {
+                mount(
+                    None::<&str>,
+                    target.as_str(),
+                    None::<&str>,
+                    MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY,
+                    None::<&str>,
+                ).map_err(|e| format!("Failed to make {} read-only: {}", container_path, e))?;
+            }
+            
+            ConsoleLogger::debug(&format!("Mounted volume {} at {}{}", host_path, container_path, if read_only { " (ro)" } else { "" }));
+        }
+        
+        Ok(())
+    }
+
     /// Setup the network for a container with a veth pair
     pub fn setup_container_network(&self, config: &ContainerNetworkConfig) -> Result<(), String> {
         ConsoleLogger::debug(&format!("Configuring container network for {}", config.container_id));
@@ -1,6 +1,7 @@
 use crate::daemon::namespace::{NamespaceManager, NamespaceConfig};
 use crate::daemon::cgroup::{CgroupManager, CgroupLimits};
 use crate::daemon::manager::RuntimeManager;
+use crate::daemon::layers::RuntimeLayerCache;
 use crate::daemon::readiness::{ContainerReadinessManager, ReadinessConfig, cleanup_readiness_signal};
 use crate::utils::{ConsoleLogger, FileSystemUtils, CommandExecutor, ProcessUtils, ImageManager, ConcurrentContainerRegistry};
 use crate::icc::network::{ContainerNetworkConfig, NetworkManager};
@@ -37,6 +38,7 @@ pub struct ContainerConfig {
     pub command: Vec<String>,
     pub environment: HashMap<String, String>,
     pub setup_commands: Vec<String>,  // Setup commands specification
+    pub volumes: Vec<String>,  // Bind mounts as host_path:container_path[:ro]
     pub resource_limits: Option<CgroupLimits>,
     pub namespace_config: Option<NamespaceConfig>,
     #[allow(dead_code)]
@@ -50,6 +52,7 @@ impl Default for ContainerConfig {
             command: vec!["/bin/sh".to_string()],
             environment: HashMap::new(),
             setup_commands: vec![],
+            volumes: vec![],
             resource_limits: Some(CgroupLimits::default()),
             namespace_config: Some(NamespaceConfig::default()),
             working_directory: None,
@@ -215,6 +218,7 @@ impl ContainerRuntime {
         let environment_clone = config.environment.clone();
         let rootfs_path_clone = rootfs_path.clone();
         let setup_commands_clone = setup_commands.clone();
+        let volumes_clone = config.volumes.clone();
         let network_enabled = namespace_config.network; // Capture network flag for child process
 
         // Create new lightweight runtime manager for child (not clone of existing)
@@ -229,6 +233,12 @@ impl ContainerRuntime {
                 return 1;
             }
 
+            // Bind mount volumes before the host paths disappear behind chroot
+            if let Err(e) = namespace_manager.mount_volumes(&rootfs_path_clone, &volumes_clone) {
+                eprintln!("Failed to mount volumes: {}", e);
+                return 1;
+            }
+
             // Setup basic network namespace ONLY if networking is enabled
             if network_enabled {
                 if let Err(e) = namespace_manager.setup_network_namespace() {
@@ -486,13 +496,37 @@ impl ContainerRuntime {
 
     fn setup_rootfs(&self, container_id: &str) -> Result<(), String> {
         // Lock-free read of container configuration
-        let image_path = self.containers.with_container(container_id, |container| {
-            container.config.image_path.clone()
+        let (image_path, setup_commands) = self.containers.with_container(container_id, |container| {
+            (container.config.image_path.clone(), container.config.setup_commands.clone())
         }).ok_or_else(|| format!("Container {} not found", container_id))?;
 
         // Use ImageManager for efficient copy-on-write setup
         if FileSystemUtils::is_file(&image_path) {
-            let rootfs_path = ImageManager::setup_container_rootfs(container_id, &image_path)?;
+            // Reuse (or build once) a layer with the setup commands already applied
+            let runtime_layer = if setup_commands.is_empty() {
+                None
+            } else {
+                match RuntimeLayerCache::global().acquire(&image_path, &setup_commands) {
+                    Ok(layer) => Some(layer),
+                    Err(e) => {
+                        ConsoleLogger::warning(&format!("Runtime layer unavailable, setup will run in container: {}", e));
+                        None
+                    }
+                }
+            };
+
+            let layers: Vec<String> = runtime_layer.iter()
+                .map(|layer| RuntimeLayerCache::global().layer_path(&layer.key).to_string_lossy().to_string())
+                .collect();
+            let (rootfs_path, layers_applied) = ImageManager::setup_container_rootfs_with_layers(container_id, &image_path, &layers)?;
+
+            if let (Some(layer), true) = (&runtime_layer, layers_applied) {
+                // Setup results are already in the rootfs; don't install them again at start
+                self.containers.update(container_id, |container| {
+                    container.config.setup_commands.clear();
+                    container.add_log(format!("Using prebuilt runtime layer {} ({})", layer.key, layer.setup_spec.join("; ")));
+                });
+            }
             
             // Fix broken symlinks and ensure working binaries
             self.fix_container_binaries(&rootfs_path)?;
@@ -6,6 +6,6 @@ pub mod dns;
 pub mod messaging;
 
 // Re-export key components for easier access
-pub use network::{NetworkManager, NetworkConfig, ContainerNetworkConfig};
+pub use network::{NetworkManager, NetworkConfig, ContainerNetworkConfig, NetworkRegistry};
 pub use dns::DnsServer;
 pub use messaging::MessageBroker; 
\ No newline at end of file
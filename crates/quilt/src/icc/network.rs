@@ -2,10 +2,13 @@
 // Optimized Inter-Container Communication using Linux Bridge
 
 use crate::utils::{CommandExecutor, ConsoleLogger};
+use crate::sync::network::MAX_NETWORK_ATTACHMENTS;
 use std::sync::{Arc};
 use std::thread;
 use std::time::Duration;
 use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicU32, AtomicBool, Ordering};
 use scopeguard;
 
@@ -14,6 +17,7 @@ pub struct NetworkConfig {
     pub bridge_name: String,
     pub subnet_cidr: String,
     pub bridge_ip: String,
+    pub prefix_len: u8,
     pub next_ip: Arc<AtomicU32>,
 }
 
@@ -25,6 +29,10 @@ pub struct ContainerNetworkConfig {
     pub container_id: String,
     pub veth_host_name: String,
     pub veth_container_name: String,
+    /// Interface name inside the container
+    pub interface_name: String,
+    /// Only the primary attachment installs the default route
+    pub default_route: bool,
 }
 
 // ELITE: Network state management with atomic operations
@@ -72,11 +80,28 @@ pub struct NetworkManager {
 }
 
 impl NetworkManager {
+    /// Create a manager for `bridge_name`; the gateway is the first host of the subnet
     pub fn new(bridge_name: &str, subnet_cidr: &str) -> Result<Self, String> {
+        let (network, _) = parse_cidr(subnet_cidr)?;
+        let gateway = Ipv4Addr::from(u32::from(network) + 1).to_string();
+        Self::with_gateway(bridge_name, subnet_cidr, &gateway)
+    }
+    
+    /// Create a manager for a network with an explicit gateway address
+    pub fn with_gateway(bridge_name: &str, subnet_cidr: &str, gateway_ip: &str) -> Result<Self, String> {
+        let (_, prefix_len) = parse_cidr(subnet_cidr)?;
+        gateway_ip.parse::<Ipv4Addr>()
+            .map_err(|_| format!("Invalid gateway address: {}", gateway_ip))?;
+        
+        if bridge_name.is_empty() || bridge_name.len() > 15 {
+            return Err(format!("Bridge name '{}' must be 1-15 characters", bridge_name));
+        }
+        
         let config = NetworkConfig {
             bridge_name: bridge_name.to_string(),
             subnet_cidr: subnet_cidr.to_string(),
-            bridge_ip: "10.42.0.1".to_string(),
+            bridge_ip: gateway_ip.to_string(),
+            prefix_len,
             next_ip: Arc::new(AtomicU32::new(2)),
         };
         
@@ -85,6 +110,10 @@ impl NetworkManager {
             state_cache: NetworkStateCache::new(),
         })
     }
+    
+    pub fn bridge_name(&self) -> &str {
+        &self.config.bridge_name
+    }
 
     pub fn ensure_bridge_ready(&self) -> Result<(), String> {
         // ELITE: Fast path - check if bridge is already ready
@@ -158,14 +187,79 @@ impl NetworkManager {
         
         Ok(ContainerNetworkConfig {
             ip_address,
-            subnet_mask: "16".to_string(),
+            subnet_mask: self.config.prefix_len.to_string(),
+            gateway_ip: self.config.bridge_ip.clone(),
+            container_id: container_id.to_string(),
+            veth_host_name,
+            veth_container_name,
+            interface_name: format!("quilt{}", &container_id[..8]),
+            default_route: true,
+        })
+    }
+
+    /// Build the interface plan for an address already allocated by the sync engine.
+    /// `attachment_index` 0 is the primary network; later attachments get their own
+    /// veth pair and interface so one container can sit on several bridges.
+    pub fn attach_container_network(&self, container_id: &str, ip_address: &str, attachment_index: usize) -> Result<ContainerNetworkConfig, String> {
+        if container_id.len() < 8 {
+            return Err(format!("Container ID '{}' is too short for interface naming", container_id));
+        }
+        if attachment_index >= MAX_NETWORK_ATTACHMENTS {
+            return Err(format!("Container {} has more than {} network attachments", container_id, MAX_NETWORK_ATTACHMENTS));
+        }
+        let short_id = &container_id[..8];
+        
+        // Interface names must stay within the 15 character kernel limit,
+        // which two-digit attachment indexes reach with the `vc` peer prefix
+        let (veth_host_name, veth_container_name, interface_name) = if attachment_index == 0 {
+            (format!("veth-{}", short_id), format!("vethc-{}", short_id), format!("quilt{}", short_id))
+        } else {
+            (
+                format!("veth{}-{}", attachment_index, short_id),
+                format!("vc{}-{}", attachment_index, short_id),
+                format!("q{}-{}", attachment_index, short_id),
+            )
+        };
+        
+        Ok(ContainerNetworkConfig {
+            ip_address: ip_address.to_string(),
+            subnet_mask: self.config.prefix_len.to_string(),
             gateway_ip: self.config.bridge_ip.clone(),
             container_id: container_id.to_string(),
             veth_host_name,
             veth_container_name,
+            interface_name,
+            default_route: attachment_index == 0,
         })
     }
 
+    /// Tear down the bridge and its NAT rules when the network is removed
+    pub fn remove_bridge(&self) -> Result<(), String> {
+        ConsoleLogger::progress(&format!("Removing network bridge: {}", self.config.bridge_name));
+        
+        let nat_cleanup = format!(
+            "iptables -t nat -D POSTROUTING -s {} ! -d {} -j MASQUERADE 2>/dev/null || true",
+            self.config.subnet_cidr, self.config.subnet_cidr
+        );
+        let forward_cleanup = format!(
+            "iptables -D FORWARD -i {} -o {} -j ACCEPT 2>/dev/null || true && iptables -D FORWARD -i {} ! -o {} -j ACCEPT 2>/dev/null || true",
+            self.config.bridge_name, self.config.bridge_name, self.config.bridge_name, self.config.bridge_name
+        );
+        let _ = CommandExecutor::execute_shell(&nat_cleanup);
+        let _ = CommandExecutor::execute_shell(&forward_cleanup);
+        
+        if self.bridge_exists_fast() {
+            let result = CommandExecutor::execute_shell(&format!("ip link delete {} type bridge", self.config.bridge_name))?;
+            if !result.success {
+                return Err(format!("Failed to delete bridge {}: {}", self.config.bridge_name, result.stderr.trim()));
+            }
+        }
+        
+        self.state_cache.set_bridge_ready(false);
+        ConsoleLogger::success(&format!("Network bridge '{}' removed", self.config.bridge_name));
+        Ok(())
+    }
+
     pub fn setup_container_network(&self, config: &ContainerNetworkConfig, container_pid: i32) -> Result<(), String> {
         ConsoleLogger::progress(&format!("Setting up network for container {} (PID: {})", 
             config.container_id, container_pid));
@@ -181,7 +275,7 @@ impl NetworkManager {
     // ELITE: Ultra-batched network setup - maximum performance optimization
     fn setup_container_network_ultra_batched(&self, config: &ContainerNetworkConfig, container_pid: i32) -> Result<(), String> {
         // ELITE: Pre-generate all interface names and commands
-        let interface_name = format!("quilt{}", &config.container_id[..8]);
+        let interface_name = config.interface_name.clone();
         let ip_with_mask = format!("{}/{}", config.ip_address, config.subnet_mask);
         
         // ELITE: Step 1 - Ultra-batched host operations (single command)
@@ -202,13 +296,19 @@ impl NetworkManager {
         }
         
         // ELITE: Step 2 - Ultra-batched container operations (single nsenter)
+        // Secondary attachments only get their connected subnet route
+        let route_cmd = if config.default_route {
+            format!("(ip route add default via {} dev {} 2>/dev/null || true) && ", config.gateway_ip, interface_name)
+        } else {
+            String::new()
+        };
         let container_batch_cmd = format!(
-            "nsenter -t {} -n sh -c 'ip link set {} name {} && ip addr add {} dev {} && ip link set {} up && ip link set lo up && (ip route add default via {} dev {} 2>/dev/null || true) && ip route show'",
+            "nsenter -t {} -n sh -c 'ip link set {} name {} && ip addr add {} dev {} && ip link set {} up && ip link set lo up && {}ip route show'",
             container_pid, 
             config.veth_container_name, interface_name,         // Rename interface
             ip_with_mask, interface_name,                       // Assign IP
             interface_name,                                     // Bring interface up
-            config.gateway_ip, interface_name                   // Add default route
+            route_cmd                                           // Add default route
         );
         
         ConsoleLogger::debug(&format!("Executing ultra-batched container setup: {}", container_batch_cmd));
@@ -227,7 +327,7 @@ impl NetworkManager {
     
     // ELITE: Production-grade network readiness verification with exec testing
     fn verify_container_network_ready(&self, config: &ContainerNetworkConfig, container_pid: i32) -> Result<(), String> {
-        let interface_name = format!("quilt{}", &config.container_id[..8]);
+        let interface_name = &config.interface_name;
         
         ConsoleLogger::debug(&format!("🔍 Production network verification for container {} (interface: {})", config.container_id, interface_name));
         
@@ -254,7 +354,7 @@ impl NetworkManager {
             
             // Check 2: Bridge connectivity
             let bridge_check_cmd = format!("ip link show {} | grep 'master {}'", 
-                                         format!("veth-{}", &config.container_id[..8]), self.config.bridge_name);
+                                         config.veth_host_name, self.config.bridge_name);
             match CommandExecutor::execute_shell(&bridge_check_cmd) {
                 Ok(result) if result.success => {
                     ConsoleLogger::debug(&format!("✅ Bridge connectivity verified"));
@@ -314,7 +414,7 @@ impl NetworkManager {
         ConsoleLogger::debug(&format!("🔍 Testing container {} network connectivity", config.container_id));
         let gateway_ping_cmd = format!(
             "nsenter -t {} -n -- ping -c 1 -W 2 {} > /dev/null 2>&1",
-            container_pid, self.config.bridge_ip
+            container_pid, config.gateway_ip
         );
         
         match CommandExecutor::execute_shell(&gateway_ping_cmd) {
@@ -345,7 +445,7 @@ impl NetworkManager {
         ConsoleLogger::debug(&format!("Creating bridge atomically: {}", self.config.bridge_name));
         
         // ELITE: Single compound command for complete bridge setup WITH INTERNET ACCESS
-        let bridge_cidr = format!("{}/16", self.config.bridge_ip);
+        let bridge_cidr = format!("{}/{}", self.config.bridge_ip, self.config.prefix_len);
         let atomic_bridge_cmd = format!(
             "ip link add name {} type bridge && ip addr add {} dev {} && ip link set {} up",
             self.config.bridge_name, bridge_cidr, self.config.bridge_name, self.config.bridge_name
@@ -521,7 +621,7 @@ impl NetworkManager {
     }
     
     fn configure_bridge_ip(&self) -> Result<(), String> {
-        let bridge_cidr = format!("{}/16", self.config.bridge_ip);
+        let bridge_cidr = format!("{}/{}", self.config.bridge_ip, self.config.prefix_len);
         let check_cmd = format!("ip addr show {} | grep {}", self.config.bridge_name, self.config.bridge_ip);
         
         ConsoleLogger::debug(&format!("Checking if bridge IP already assigned: {}", check_cmd));
@@ -684,7 +784,7 @@ impl NetworkManager {
         let ns_exec = format!("nsenter -t {} -n", container_pid);
         
         // Use consistent interface naming to avoid eth0 conflicts
-        let interface_name = format!("quilt{}", &config.container_id[..8]);
+        let interface_name = config.interface_name.clone();
         
         ConsoleLogger::debug(&format!("Configuring container interface for {}", config.container_id));
         
@@ -713,7 +813,11 @@ impl NetworkManager {
             ConsoleLogger::warning(&format!("Failed to bring loopback up: {}", lo_result.stderr));
         }
 
-        // Add default route
+        // Add default route (primary attachment only)
+        if !config.default_route {
+            ConsoleLogger::success(&format!("Container interface configured: {} = {}/{}", interface_name, config.ip_address, config.subnet_mask));
+            return Ok(());
+        }
         let route_result = CommandExecutor::execute_shell(&format!("{} ip route add default via {} dev {}", ns_exec, config.gateway_ip, interface_name))?;
         if !route_result.success {
             // Check if route already exists
@@ -728,4 +832,89 @@ impl NetworkManager {
         ConsoleLogger::success(&format!("Container interface configured: {} = {}/{}", interface_name, config.ip_address, config.subnet_mask));
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+/// Parse an IPv4 CIDR into its network address and prefix length
+fn parse_cidr(cidr: &str) -> Result<(Ipv4Addr, u8), String> {
+    let (addr, prefix) = cidr.split_once('/')
+        .ok_or_else(|| format!("Subnet '{}' must be in CIDR notation", cidr))?;
+    let addr: Ipv4Addr = addr.parse().map_err(|_| format!("Invalid subnet address: {}", addr))?;
+    let prefix: u8 = prefix.parse().map_err(|_| format!("Invalid subnet prefix: {}", prefix))?;
+    
+    if !(8..=30).contains(&prefix) {
+        return Err(format!("Subnet prefix /{} out of range (/8 - /30)", prefix));
+    }
+    
+    let mask = u32::MAX << (32 - prefix);
+    Ok((Ipv4Addr::from(u32::from(addr) & mask), prefix))
+}
+
+/// One `NetworkManager` per user-defined network, keyed by network name, so each
+/// network keeps its own bridge readiness cache across container starts
+#[derive(Default)]
+pub struct NetworkRegistry {
+    managers: Mutex<HashMap<String, Arc<NetworkManager>>>,
+}
+
+impl NetworkRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Get the manager for a network, creating it on first use
+    pub fn get_or_create(&self, network_name: &str, bridge_name: &str, subnet_cidr: &str, gateway_ip: &str) -> Result<Arc<NetworkManager>, String> {
+        let mut managers = self.managers.lock()
+            .map_err(|_| "Network registry lock poisoned".to_string())?;
+        
+        if let Some(manager) = managers.get(network_name) {
+            return Ok(manager.clone());
+        }
+        
+        let manager = Arc::new(NetworkManager::with_gateway(bridge_name, subnet_cidr, gateway_ip)?);
+        managers.insert(network_name.to_string(), manager.clone());
+        Ok(manager)
+    }
+    
+    /// Forget a network's manager, returning it so the caller can tear down the bridge
+    pub fn remove(&self, network_name: &str) -> Option<Arc<NetworkManager>> {
+        self.managers.lock().ok()?.remove(network_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_gateway_derived_from_subnet() {
+        let manager = NetworkManager::new("qbr-test", "10.80.0.0/24").unwrap();
+        let config = manager.attach_container_network("0123456789abcdef", "10.80.0.5", 0).unwrap();
+        assert_eq!(config.gateway_ip, "10.80.0.1");
+        assert_eq!(config.subnet_mask, "24");
+        assert_eq!(config.interface_name, "quilt01234567");
+        assert!(config.default_route);
+    }
+    
+    #[test]
+    fn test_secondary_attachment_naming() {
+        let manager = NetworkManager::with_gateway("qbr-test", "10.80.0.0/24", "10.80.0.254").unwrap();
+        let config = manager.attach_container_network("0123456789abcdef", "10.80.0.5", 1).unwrap();
+        assert_eq!(config.veth_host_name, "veth1-01234567");
+        assert_eq!(config.veth_container_name, "vc1-01234567");
+        assert!(config.veth_container_name.len() <= 15);
+
+        let last = MAX_NETWORK_ATTACHMENTS - 1;
+        let config = manager.attach_container_network("0123456789abcdef", "10.80.0.5", last).unwrap();
+        assert_eq!(config.veth_host_name, "veth99-01234567");
+        assert_eq!(config.veth_container_name, "vc99-01234567");
+        assert_eq!(config.interface_name, "q99-01234567");
+        for name in [&config.veth_host_name, &config.veth_container_name, &config.interface_name] {
+            assert!(name.len() <= 15, "{} is too long for an interface name", name);
+        }
+        assert!(manager.attach_container_network("0123456789abcdef", "10.80.0.5", MAX_NETWORK_ATTACHMENTS).is_err());
+        assert_eq!(config.gateway_ip, "10.80.0.254");
+        assert!(!config.default_route);
+        
+        assert!(NetworkManager::new("this-bridge-name-is-too-long", "10.80.0.0/24").is_err());
+        assert!(NetworkManager::new("qbr", "10.80.0.0").is_err());
+    }
+}
@@ -4,8 +4,11 @@ mod icc;
 mod sync;
 
 use daemon::{ContainerConfig, CgroupLimits, NamespaceConfig};
+use daemon::layers::RuntimeLayerCache;
 use utils::console::ConsoleLogger;
-use sync::{SyncEngine, containers::ContainerState};
+use sync::{SyncEngine, containers::ContainerState, NetworkDefinition, NetworkSpec, MetricsQuery, OwnerQuota, TemplateSpec};
+use utils::validation::InputValidator;
+use icc::network::{NetworkManager, NetworkRegistry};
 
 use std::collections::HashMap;
 use std::time::Duration;
@@ -32,7 +35,15 @@ use quilt::{
     ExecContainerRequest, ExecContainerResponse,
     ContainerStatus, ListContainersRequest, ListContainersResponse, ContainerInfo,
     GetSystemMetricsRequest, GetSystemMetricsResponse, GetNetworkTopologyRequest, GetNetworkTopologyResponse, NetworkNode,
-    GetContainerNetworkInfoRequest, GetContainerNetworkInfoResponse,
+    GetContainerNetworkInfoRequest, GetContainerNetworkInfoResponse, NetworkAttachment,
+    CreateNetworkRequest, CreateNetworkResponse, ListNetworksRequest, ListNetworksResponse,
+    RemoveNetworkRequest, RemoveNetworkResponse, NetworkInfo,
+    GetContainerMetricsRequest, GetContainerMetricsResponse, ContainerMetricsPoint, MetricsResolution,
+    SetOwnerQuotaRequest, SetOwnerQuotaResponse, GetOwnerQuotaRequest, GetOwnerQuotaResponse, OwnerQuotaInfo,
+    CreateTemplateRequest, CreateTemplateResponse, GetTemplateRequest, GetTemplateResponse,
+    ListTemplatesRequest, ListTemplatesResponse, DeleteTemplateRequest, DeleteTemplateResponse,
+    PrewarmRuntimeLayerRequest, PrewarmRuntimeLayerResponse, ListRuntimeLayersRequest, ListRuntimeLayersResponse,
+    PruneRuntimeLayersRequest, PruneRuntimeLayersResponse,
     ExecContainerAsyncRequest, ExecContainerAsyncResponse,
     GetTaskStatusRequest, GetTaskStatusResponse,
     GetTaskResultRequest, GetTaskResultResponse,
@@ -56,6 +67,7 @@ use tempfile::NamedTempFile;
 pub struct QuiltServiceImpl {
     sync_engine: Arc<SyncEngine>,
     package_store: Arc<tokio::sync::Mutex<PackageStore>>,
+    network_registry: Arc<NetworkRegistry>,
 }
 
 impl QuiltServiceImpl {
@@ -74,25 +86,207 @@ impl QuiltServiceImpl {
         // Initialize package store
         let package_store = Arc::new(tokio::sync::Mutex::new(PackageStore::new().await?));
         
-        // Start background services for monitoring and cleanup
-        sync_engine.start_background_services().await?;
+        // Reconcile state left by a previous daemon, then start monitoring and cleanup
+        let report = sync_engine.start_background_services().await?;
+        if report.is_clean() {
+            ConsoleLogger::info(&format!("State reconciliation: adopted {} running container(s)", report.adopted.len()));
+        } else {
+            ConsoleLogger::warning(&format!("State reconciliation: {}", report.summary()));
+            for exited in &report.exited {
+                ConsoleLogger::warning(&format!("  {} marked exited: {}", exited.container_id, exited.reason));
+            }
+        }
+        
+        let runtime_layers = RuntimeLayerCache::global().list();
+        ConsoleLogger::info(&format!("Runtime layer cache: {} prebuilt layer(s), {} bytes",
+            runtime_layers.len(), runtime_layers.iter().map(|layer| layer.size_bytes).sum::<u64>()));
         
         ConsoleLogger::success("Sync engine initialized with background services");
         
         Ok(Self {
             sync_engine,
             package_store,
+            network_registry: Arc::new(NetworkRegistry::new()),
         })
     }
 }
 
+fn network_info(network: NetworkDefinition, attached_containers: u32) -> NetworkInfo {
+    NetworkInfo {
+        name: network.name,
+        bridge_name: network.bridge_name,
+        subnet_cidr: network.subnet_cidr,
+        gateway_ip: network.gateway_ip,
+        ip_range_start: network.ip_range_start,
+        ip_range_end: network.ip_range_end,
+        is_default: network.is_default,
+        created_at: network.created_at as u64,
+        attached_containers,
+    }
+}
+
+fn owner_quota_info(quota: OwnerQuota) -> OwnerQuotaInfo {
+    OwnerQuotaInfo {
+        owner: quota.owner,
+        max_containers: quota.max_containers.unwrap_or(0),
+        max_memory_mb: quota.max_memory_mb.unwrap_or(0),
+        updated_at: quota.updated_at as u64,
+    }
+}
+
+fn metrics_resolution_to_proto(resolution: sync::MetricsResolution) -> MetricsResolution {
+    match resolution {
+        sync::MetricsResolution::Raw => MetricsResolution::MetricsRaw,
+        sync::MetricsResolution::Minute => MetricsResolution::MetricsMinute,
+        sync::MetricsResolution::Hour => MetricsResolution::MetricsHour,
+    }
+}
+
+fn template_spec_from_request(req: CreateContainerRequest) -> TemplateSpec {
+    TemplateSpec {
+        image_path: if req.image_path.is_empty() { None } else { Some(req.image_path) },
+        command: req.command,
+        environment: req.environment,
+        working_directory: if req.working_directory.is_empty() { None } else { Some(req.working_directory) },
+        setup_commands: req.setup_commands,
+        memory_limit_mb: if req.memory_limit_mb > 0 { Some(req.memory_limit_mb as i64) } else { None },
+        cpu_limit_percent: if req.cpu_limit_percent > 0.0 { Some(req.cpu_limit_percent as f64) } else { None },
+        enable_network_namespace: req.enable_network_namespace,
+        enable_pid_namespace: req.enable_pid_namespace,
+        enable_mount_namespace: req.enable_mount_namespace,
+        enable_uts_namespace: req.enable_uts_namespace,
+        enable_ipc_namespace: req.enable_ipc_namespace,
+        networks: req.networks,
+        volumes: req.volumes,
+        labels: req.labels,
+    }
+}
+
+fn template_info(template: sync::ContainerTemplate) -> quilt::ContainerTemplate {
+    let spec = template.spec;
+    quilt::ContainerTemplate {
+        name: template.name,
+        version: template.version as u32,
+        description: template.description.unwrap_or_default(),
+        spec: Some(CreateContainerRequest {
+            image_path: spec.image_path.unwrap_or_default(),
+            command: spec.command,
+            environment: spec.environment,
+            working_directory: spec.working_directory.unwrap_or_default(),
+            setup_commands: spec.setup_commands,
+            memory_limit_mb: spec.memory_limit_mb.unwrap_or(0) as i32,
+            cpu_limit_percent: spec.cpu_limit_percent.unwrap_or(0.0) as f32,
+            enable_pid_namespace: spec.enable_pid_namespace,
+            enable_mount_namespace: spec.enable_mount_namespace,
+            enable_uts_namespace: spec.enable_uts_namespace,
+            enable_ipc_namespace: spec.enable_ipc_namespace,
+            enable_network_namespace: spec.enable_network_namespace,
+            networks: spec.networks,
+            volumes: spec.volumes,
+            labels: spec.labels,
+            ..Default::default()
+        }),
+        created_at: template.created_at as u64,
+    }
+}
+
+fn runtime_layer_info(layer: daemon::layers::RuntimeLayerInfo) -> quilt::RuntimeLayerInfo {
+    quilt::RuntimeLayerInfo {
+        key: layer.key,
+        image_path: layer.image_path,
+        setup_spec: layer.setup_spec,
+        size_bytes: layer.size_bytes,
+        created_at: layer.created_at,
+        last_used_at: layer.last_used_at,
+        build_duration_ms: layer.build_duration_ms,
+    }
+}
+
+/// Fill in the request from a template. Anything the request sets wins; maps are
+/// merged key by key and namespace flags are enabled if either side enables them.
+fn apply_template(mut req: CreateContainerRequest, template: &sync::ContainerTemplate) -> CreateContainerRequest {
+    let spec = &template.spec;
+    
+    if req.image_path.is_empty() {
+        req.image_path = spec.image_path.clone().unwrap_or_default();
+    }
+    if req.command.is_empty() {
+        req.command = spec.command.clone();
+    }
+    if req.working_directory.is_empty() {
+        req.working_directory = spec.working_directory.clone().unwrap_or_default();
+    }
+    if req.setup_commands.is_empty() {
+        req.setup_commands = spec.setup_commands.clone();
+    }
+    if req.memory_limit_mb <= 0 {
+        req.memory_limit_mb = spec.memory_limit_mb.unwrap_or(0) as i32;
+    }
+    if req.cpu_limit_percent <= 0.0 {
+        req.cpu_limit_percent = spec.cpu_limit_percent.unwrap_or(0.0) as f32;
+    }
+    if req.networks.is_empty() {
+        req.networks = spec.networks.clone();
+    }
+    if req.volumes.is_empty() {
+        req.volumes = spec.volumes.clone();
+    }
+    
+    req.enable_network_namespace |= spec.enable_network_namespace;
+    req.enable_pid_namespace |= spec.enable_pid_namespace;
+    req.enable_mount_namespace |= spec.enable_mount_namespace;
+    req.enable_uts_namespace |= spec.enable_uts_namespace;
+    req.enable_ipc_namespace |= spec.enable_ipc_namespace;
+    
+    for (key, value) in &spec.environment {
+        req.environment.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+    for (key, value) in &spec.labels {
+        req.labels.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+    req.labels.insert("quilt.template".to_string(), format!("{}@{}", template.name, template.version));
+    
+    req
+}
+
 #[tonic::async_trait]
 impl QuiltService for QuiltServiceImpl {
     async fn create_container(
         &self,
         request: Request<CreateContainerRequest>,
     ) -> Result<Response<CreateContainerResponse>, Status> {
-        let req = request.into_inner();
+        let mut req = request.into_inner();
+        
+        if !req.template.is_empty() {
+            let version = if req.template_version > 0 { Some(req.template_version as i64) } else { None };
+            match self.sync_engine.get_template(&req.template, version).await {
+                Ok(template) => req = apply_template(req, &template),
+                Err(e) => {
+                    return Ok(Response::new(CreateContainerResponse {
+                        container_id: String::new(),
+                        success: false,
+                        error_message: e.to_string(),
+                    }));
+                }
+            }
+        }
+        
+        if req.image_path.is_empty() {
+            return Ok(Response::new(CreateContainerResponse {
+                container_id: String::new(),
+                success: false,
+                error_message: "image_path is required (directly or via a template)".to_string(),
+            }));
+        }
+        
+        if let Some(error) = req.volumes.iter().find_map(|v| InputValidator::parse_volume(v).err()) {
+            return Ok(Response::new(CreateContainerResponse {
+                container_id: String::new(),
+                success: false,
+                error_message: format!("Invalid volume: {}", error),
+            }));
+        }
+        
         let container_id = Uuid::new_v4().to_string();
 
         ConsoleLogger::container_created(&container_id);
@@ -100,7 +294,7 @@ impl QuiltService for QuiltServiceImpl {
         // Convert gRPC request to sync engine container config
         let config = sync::containers::ContainerConfig {
             id: container_id.clone(),
-            name: None, // gRPC request doesn't have name field
+            name: if req.name.is_empty() { None } else { Some(req.name) },
             image_path: req.image_path,
             command: if req.command.is_empty() { 
                     "sleep 86400".to_string() // Default for long-running agents (24 hours)
@@ -110,6 +304,10 @@ impl QuiltService for QuiltServiceImpl {
             environment: req.environment,
             memory_limit_mb: if req.memory_limit_mb > 0 { Some(req.memory_limit_mb as i64) } else { None },
             cpu_limit_percent: if req.cpu_limit_percent > 0.0 { Some(req.cpu_limit_percent as f64) } else { None },
+            owner: if req.owner.is_empty() { None } else { Some(req.owner) },
+            labels: req.labels,
+            volumes: req.volumes,
+            setup_commands: req.setup_commands,
             enable_network_namespace: req.enable_network_namespace,
             enable_pid_namespace: req.enable_pid_namespace,
             enable_mount_namespace: req.enable_mount_namespace,
@@ -118,7 +316,7 @@ impl QuiltService for QuiltServiceImpl {
         };
 
         // ✅ NON-BLOCKING: Create container with coordinated network allocation
-        match self.sync_engine.create_container(config).await {
+        match self.sync_engine.create_container_on_networks(config, &req.networks).await {
             Ok(_network_config) => {
                 // ✅ INSTANT RETURN: Container creation is coordinated but non-blocking
                 ConsoleLogger::success(&format!("Container {} created with network config", container_id));
@@ -127,9 +325,10 @@ impl QuiltService for QuiltServiceImpl {
                 if req.auto_start {
                     // Start the actual container process in background
                     let sync_engine = self.sync_engine.clone();
+                    let network_registry = self.network_registry.clone();
                     let container_id_clone = container_id.clone();
                     tokio::spawn(async move {
-                        if let Err(e) = start_container_process(sync_engine.clone(), container_id_clone.clone()).await {
+                        if let Err(e) = start_container_process(sync_engine.clone(), network_registry, container_id_clone.clone()).await {
                             ConsoleLogger::error(&format!("Failed to start container process {}: {}", container_id_clone, e));
                             let _ = sync_engine.update_container_state(&container_id_clone, ContainerState::Error).await;
                         }
@@ -145,6 +344,11 @@ impl QuiltService for QuiltServiceImpl {
                     error_message: String::new(),
                 }))
             }
+            Err(e @ sync::SyncError::QuotaExceeded { .. }) => {
+                // A status rather than a failed response, so callers can tell quota apart from other failures
+                ConsoleLogger::warning(&format!("Refused container creation: {}", e));
+                Err(Status::resource_exhausted(e.to_string()))
+            }
             Err(e) => {
                 ConsoleLogger::error(&format!("Failed to create container: {}", e));
                 Ok(Response::new(CreateContainerResponse {
@@ -190,12 +394,13 @@ impl QuiltService for QuiltServiceImpl {
 
                 // ✅ BACKGROUND PROCESS STARTUP: Don't block the gRPC call
                 let sync_engine = self.sync_engine.clone();
+                let network_registry = self.network_registry.clone();
                 let container_id = req.container_id.clone();
                 
                 tokio::spawn(async move {
                     let sync_engine_clone = sync_engine.clone();
                     let container_id_clone = container_id.clone();
-                    match start_container_process(sync_engine, container_id).await {
+                    match start_container_process(sync_engine, network_registry, container_id).await {
                         Ok(()) => {
                             ConsoleLogger::success(&format!("Container {} startup completed successfully", container_id_clone));
                         }
@@ -333,6 +538,7 @@ impl QuiltService for QuiltServiceImpl {
         }
     }
 
+    #[tracing::instrument(name = "quilt.exec", skip_all, fields(container_id = %request.get_ref().container_id))]
     async fn exec_container(
         &self,
         request: Request<ExecContainerRequest>,
@@ -421,6 +627,7 @@ impl QuiltService for QuiltServiceImpl {
         }
     }
 
+    #[tracing::instrument(name = "quilt.exec_async", skip_all, fields(container_id = %request.get_ref().container_id))]
     async fn exec_container_async(
         &self,
         request: Request<ExecContainerAsyncRequest>,
@@ -696,6 +903,8 @@ impl QuiltService for QuiltServiceImpl {
                         image_path: details.0,
                         command: details.1,
                         created_at: status.created_at as u64,
+                        name: status.name.unwrap_or_default(),
+                        labels: status.labels,
                     });
                 }
                 Ok(Response::new(ListContainersResponse { containers }))
@@ -734,12 +943,22 @@ impl QuiltService for QuiltServiceImpl {
     ) -> Result<Response<GetNetworkTopologyResponse>, Status> {
         match self.sync_engine.list_network_allocations().await {
             Ok(allocations) => {
-                let nodes = allocations
+                let live: Vec<_> = allocations
                     .into_iter()
+                    .filter(|alloc| alloc.status != sync::network::NetworkStatus::Cleaned)
+                    .collect();
+                
+                // Containers can reach each other only when they share a network segment
+                let nodes = live
+                    .iter()
                     .map(|alloc| NetworkNode {
-                        container_id: alloc.container_id,
-                        ip_address: alloc.ip_address,
-                        connections: vec![], // Not tracked yet
+                        container_id: alloc.container_id.clone(),
+                        ip_address: alloc.ip_address.clone(),
+                        connections: live
+                            .iter()
+                            .filter(|peer| peer.network_name == alloc.network_name && peer.container_id != alloc.container_id)
+                            .map(|peer| peer.container_id.clone())
+                            .collect(),
                     })
                     .collect();
                 Ok(Response::new(GetNetworkTopologyResponse { nodes }))
@@ -753,23 +972,395 @@ impl QuiltService for QuiltServiceImpl {
         request: Request<GetContainerNetworkInfoRequest>,
     ) -> Result<Response<GetContainerNetworkInfoResponse>, Status> {
         let req = request.into_inner();
-        match self.sync_engine.get_network_allocation(&req.container_id).await {
-            Ok(alloc) => {
+        match self.sync_engine.list_container_networks(&req.container_id).await {
+            Ok(allocations) if !allocations.is_empty() => {
+                let attachments: Vec<NetworkAttachment> = allocations
+                    .into_iter()
+                    .map(|alloc| NetworkAttachment {
+                        network_name: alloc.network_name,
+                        ip_address: alloc.ip_address,
+                        bridge_interface: alloc.bridge_interface.unwrap_or_default(),
+                        veth_host: alloc.veth_host.unwrap_or_default(),
+                        veth_container: alloc.veth_container.unwrap_or_default(),
+                        setup_completed: alloc.setup_completed,
+                        status: alloc.status.to_string(),
+                    })
+                    .collect();
+                
+                // Top-level fields describe the primary attachment
+                let primary = attachments[0].clone();
                 let response = GetContainerNetworkInfoResponse {
-                    container_id: alloc.container_id,
-                    ip_address: alloc.ip_address,
-                    bridge_interface: alloc.bridge_interface.unwrap_or_default(),
-                    veth_host: alloc.veth_host.unwrap_or_default(),
-                    veth_container: alloc.veth_container.unwrap_or_default(),
-                    setup_completed: alloc.setup_completed,
-                    status: alloc.status.to_string(),
+                    container_id: req.container_id,
+                    ip_address: primary.ip_address,
+                    bridge_interface: primary.bridge_interface,
+                    veth_host: primary.veth_host,
+                    veth_container: primary.veth_container,
+                    setup_completed: primary.setup_completed,
+                    status: primary.status,
+                    attachments,
                 };
                 Ok(Response::new(response))
             }
+            Ok(_) => Err(Status::not_found(format!("Container {} has no network attachments", req.container_id))),
             Err(e) => Err(Status::not_found(format!("Network info not found for container {}: {}", req.container_id, e))),
         }
     }
 
+    async fn get_container_metrics(
+        &self,
+        request: Request<GetContainerMetricsRequest>,
+    ) -> Result<Response<GetContainerMetricsResponse>, Status> {
+        let req = request.into_inner();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| Status::internal(e.to_string()))?
+            .as_secs() as i64;
+        
+        let end_time = if req.end_time == 0 { now } else { req.end_time as i64 };
+        let start_time = if req.start_time == 0 { end_time - 3600 } else { req.start_time as i64 };
+        let resolution = match MetricsResolution::from_i32(req.resolution).unwrap_or(MetricsResolution::MetricsAuto) {
+            MetricsResolution::MetricsAuto => sync::MetricsResolution::for_range(start_time, now),
+            MetricsResolution::MetricsRaw => sync::MetricsResolution::Raw,
+            MetricsResolution::MetricsMinute => sync::MetricsResolution::Minute,
+            MetricsResolution::MetricsHour => sync::MetricsResolution::Hour,
+        };
+        
+        let query = MetricsQuery {
+            container_id: req.container_id.clone(),
+            start_time,
+            end_time,
+            resolution: Some(resolution),
+            limit: if req.limit > 0 { Some(req.limit as i64) } else { None },
+        };
+        
+        let points = self.sync_engine.get_container_metrics(&query).await
+            .map_err(|e| Status::invalid_argument(format!("Failed to query metrics: {}", e)))?;
+        
+        Ok(Response::new(GetContainerMetricsResponse {
+            container_id: req.container_id,
+            resolution: metrics_resolution_to_proto(resolution) as i32,
+            points: points.into_iter().map(|point| ContainerMetricsPoint {
+                timestamp: point.bucket_start as u64,
+                samples: point.samples as u32,
+                cpu_percent_avg: point.cpu_percent_avg,
+                cpu_percent_max: point.cpu_percent_max,
+                cpu_usage_usec: point.cpu_usage_usec as u64,
+                memory_bytes_avg: point.memory_bytes_avg as u64,
+                memory_bytes_max: point.memory_bytes_max as u64,
+                io_read_bytes: point.io_read_bytes as u64,
+                io_write_bytes: point.io_write_bytes as u64,
+                net_rx_bytes: point.net_rx_bytes as u64,
+                net_tx_bytes: point.net_tx_bytes as u64,
+                pids_max: point.pids_max as u32,
+            }).collect(),
+        }))
+    }
+
+    async fn set_owner_quota(
+        &self,
+        request: Request<SetOwnerQuotaRequest>,
+    ) -> Result<Response<SetOwnerQuotaResponse>, Status> {
+        let req = request.into_inner();
+        let limit = |value: i64| if value > 0 { Some(value) } else { None };
+        
+        match self.sync_engine.set_owner_quota(&req.owner, limit(req.max_containers), limit(req.max_memory_mb)).await {
+            Ok(quota) => Ok(Response::new(SetOwnerQuotaResponse {
+                success: true,
+                error_message: String::new(),
+                quota: Some(owner_quota_info(quota)),
+            })),
+            Err(e) => Ok(Response::new(SetOwnerQuotaResponse {
+                success: false,
+                error_message: e.to_string(),
+                quota: None,
+            })),
+        }
+    }
+
+    async fn get_owner_quota(
+        &self,
+        request: Request<GetOwnerQuotaRequest>,
+    ) -> Result<Response<GetOwnerQuotaResponse>, Status> {
+        let req = request.into_inner();
+        
+        let quota = self.sync_engine.get_owner_quota(&req.owner).await
+            .map_err(|e| Status::internal(format!("Failed to get quota: {}", e)))?;
+        let usage = self.sync_engine.get_owner_usage(&req.owner).await
+            .map_err(|e| Status::internal(format!("Failed to get usage: {}", e)))?;
+        
+        Ok(Response::new(GetOwnerQuotaResponse {
+            has_quota: quota.is_some(),
+            quota: quota.map(owner_quota_info),
+            live_containers: usage.containers as u32,
+            memory_mb: usage.memory_mb,
+        }))
+    }
+
+    async fn create_template(
+        &self,
+        request: Request<CreateTemplateRequest>,
+    ) -> Result<Response<CreateTemplateResponse>, Status> {
+        let req = request.into_inner();
+        let spec = template_spec_from_request(req.spec.unwrap_or_default());
+        let description = if req.description.is_empty() { None } else { Some(req.description) };
+        
+        if let Some(error) = spec.volumes.iter().find_map(|v| InputValidator::parse_volume(v).err()) {
+            return Ok(Response::new(CreateTemplateResponse {
+                success: false,
+                error_message: format!("Invalid volume: {}", error),
+                template: None,
+            }));
+        }
+        
+        match self.sync_engine.create_template(&req.name, description, spec).await {
+            Ok(template) => {
+                ConsoleLogger::success(&format!("Template {} saved as version {}", template.name, template.version));
+                Ok(Response::new(CreateTemplateResponse {
+                    success: true,
+                    error_message: String::new(),
+                    template: Some(template_info(template)),
+                }))
+            }
+            Err(e) => Ok(Response::new(CreateTemplateResponse {
+                success: false,
+                error_message: e.to_string(),
+                template: None,
+            })),
+        }
+    }
+
+    async fn get_template(
+        &self,
+        request: Request<GetTemplateRequest>,
+    ) -> Result<Response<GetTemplateResponse>, Status> {
+        let req = request.into_inner();
+        let version = if req.version > 0 { Some(req.version as i64) } else { None };
+        
+        match self.sync_engine.get_template(&req.name, version).await {
+            Ok(template) => Ok(Response::new(GetTemplateResponse {
+                success: true,
+                error_message: String::new(),
+                template: Some(template_info(template)),
+            })),
+            Err(e) => Ok(Response::new(GetTemplateResponse {
+                success: false,
+                error_message: e.to_string(),
+                template: None,
+            })),
+        }
+    }
+
+    async fn list_templates(
+        &self,
+        request: Request<ListTemplatesRequest>,
+    ) -> Result<Response<ListTemplatesResponse>, Status> {
+        let req = request.into_inner();
+        
+        let templates = if req.name.is_empty() {
+            self.sync_engine.list_templates().await
+        } else {
+            self.sync_engine.list_template_versions(&req.name).await
+        }.map_err(|e| Status::internal(format!("Failed to list templates: {}", e)))?;
+        
+        Ok(Response::new(ListTemplatesResponse {
+            templates: templates.into_iter().map(template_info).collect(),
+        }))
+    }
+
+    async fn delete_template(
+        &self,
+        request: Request<DeleteTemplateRequest>,
+    ) -> Result<Response<DeleteTemplateResponse>, Status> {
+        let req = request.into_inner();
+        let version = if req.version > 0 { Some(req.version as i64) } else { None };
+        
+        match self.sync_engine.delete_template(&req.name, version).await {
+            Ok(deleted) => Ok(Response::new(DeleteTemplateResponse {
+                success: true,
+                error_message: String::new(),
+                deleted_versions: deleted as u32,
+            })),
+            Err(e) => Ok(Response::new(DeleteTemplateResponse {
+                success: false,
+                error_message: e.to_string(),
+                deleted_versions: 0,
+            })),
+        }
+    }
+
+    async fn prewarm_runtime_layer(
+        &self,
+        request: Request<PrewarmRuntimeLayerRequest>,
+    ) -> Result<Response<PrewarmRuntimeLayerResponse>, Status> {
+        let req = request.into_inner();
+        
+        let (image_path, setup_commands) = if req.template.is_empty() {
+            (req.image_path, req.setup_commands)
+        } else {
+            match self.sync_engine.get_template(&req.template, None).await {
+                Ok(template) => (template.spec.image_path.unwrap_or_default(), template.spec.setup_commands),
+                Err(e) => {
+                    return Ok(Response::new(PrewarmRuntimeLayerResponse {
+                        success: false,
+                        error_message: e.to_string(),
+                        layer: None,
+                    }));
+                }
+            }
+        };
+        
+        if image_path.is_empty() || setup_commands.is_empty() {
+            return Ok(Response::new(PrewarmRuntimeLayerResponse {
+                success: false,
+                error_message: "image_path and setup_commands are required".to_string(),
+                layer: None,
+            }));
+        }
+        
+        // Building forks and waits on the setup process; keep it off the async workers
+        let result = tokio::task::spawn_blocking(move || {
+            RuntimeLayerCache::global().prewarm(&image_path, &setup_commands)
+        }).await.map_err(|e| Status::internal(format!("Prewarm task failed: {}", e)))?;
+        
+        match result {
+            Ok(layer) => Ok(Response::new(PrewarmRuntimeLayerResponse {
+                success: true,
+                error_message: String::new(),
+                layer: Some(runtime_layer_info(layer)),
+            })),
+            Err(e) => {
+                ConsoleLogger::error(&format!("Failed to pre-warm runtime layer: {}", e));
+                Ok(Response::new(PrewarmRuntimeLayerResponse {
+                    success: false,
+                    error_message: e,
+                    layer: None,
+                }))
+            }
+        }
+    }
+
+    async fn list_runtime_layers(
+        &self,
+        _request: Request<ListRuntimeLayersRequest>,
+    ) -> Result<Response<ListRuntimeLayersResponse>, Status> {
+        let layers = RuntimeLayerCache::global().list();
+        let total_size_bytes = layers.iter().map(|layer| layer.size_bytes).sum();
+        
+        Ok(Response::new(ListRuntimeLayersResponse {
+            layers: layers.into_iter().map(runtime_layer_info).collect(),
+            total_size_bytes,
+        }))
+    }
+
+    async fn prune_runtime_layers(
+        &self,
+        request: Request<PruneRuntimeLayersRequest>,
+    ) -> Result<Response<PruneRuntimeLayersResponse>, Status> {
+        let req = request.into_inner();
+        let cache = RuntimeLayerCache::global();
+        
+        let evicted_keys = cache.evict_to(req.max_size_bytes)
+            .map_err(|e| Status::internal(format!("Failed to prune runtime layers: {}", e)))?;
+        let remaining_size_bytes = cache.list().iter().map(|layer| layer.size_bytes).sum();
+        
+        ConsoleLogger::info(&format!("Pruned {} runtime layer(s)", evicted_keys.len()));
+        Ok(Response::new(PruneRuntimeLayersResponse {
+            evicted_keys,
+            remaining_size_bytes,
+        }))
+    }
+
+    async fn create_network(
+        &self,
+        request: Request<CreateNetworkRequest>,
+    ) -> Result<Response<CreateNetworkResponse>, Status> {
+        let req = request.into_inner();
+        let non_empty = |value: String| if value.is_empty() { None } else { Some(value) };
+        
+        let spec = NetworkSpec {
+            name: req.name,
+            subnet_cidr: req.subnet_cidr,
+            bridge_name: non_empty(req.bridge_name),
+            gateway_ip: non_empty(req.gateway_ip),
+            ip_range_start: non_empty(req.ip_range_start),
+            ip_range_end: non_empty(req.ip_range_end),
+        };
+        
+        match self.sync_engine.create_network(spec).await {
+            Ok(network) => {
+                ConsoleLogger::success(&format!("Network {} created ({} on {})", network.name, network.subnet_cidr, network.bridge_name));
+                Ok(Response::new(CreateNetworkResponse {
+                    success: true,
+                    error_message: String::new(),
+                    network: Some(network_info(network, 0)),
+                }))
+            }
+            Err(e) => {
+                ConsoleLogger::error(&format!("Failed to create network: {}", e));
+                Ok(Response::new(CreateNetworkResponse {
+                    success: false,
+                    error_message: e.to_string(),
+                    network: None,
+                }))
+            }
+        }
+    }
+
+    async fn list_networks(
+        &self,
+        _request: Request<ListNetworksRequest>,
+    ) -> Result<Response<ListNetworksResponse>, Status> {
+        let networks = self.sync_engine.list_networks().await
+            .map_err(|e| Status::internal(format!("Failed to list networks: {}", e)))?;
+        
+        let mut infos = Vec::with_capacity(networks.len());
+        for network in networks {
+            let attached = self.sync_engine.list_allocations_on_network(&network.name).await
+                .map(|allocations| allocations.len() as u32)
+                .unwrap_or(0);
+            infos.push(network_info(network, attached));
+        }
+        
+        Ok(Response::new(ListNetworksResponse { networks: infos }))
+    }
+
+    async fn remove_network(
+        &self,
+        request: Request<RemoveNetworkRequest>,
+    ) -> Result<Response<RemoveNetworkResponse>, Status> {
+        let req = request.into_inner();
+        
+        match self.sync_engine.remove_network(&req.name).await {
+            Ok(network) => {
+                // Tear down the bridge off the async runtime; it shells out to ip/iptables
+                let manager = match self.network_registry.remove(&network.name) {
+                    Some(manager) => Some(manager),
+                    None => NetworkManager::with_gateway(&network.bridge_name, &network.subnet_cidr, &network.gateway_ip)
+                        .ok()
+                        .map(Arc::new),
+                };
+                if let Some(manager) = manager {
+                    let teardown = tokio::task::spawn_blocking(move || manager.remove_bridge()).await;
+                    if let Ok(Err(e)) = teardown {
+                        ConsoleLogger::warning(&format!("Network {} removed but bridge teardown failed: {}", req.name, e));
+                    }
+                }
+                
+                ConsoleLogger::success(&format!("Network {} removed", req.name));
+                Ok(Response::new(RemoveNetworkResponse {
+                    success: true,
+                    error_message: String::new(),
+                }))
+            }
+            Err(e) => {
+                ConsoleLogger::error(&format!("Failed to remove network {}: {}", req.name, e));
+                Ok(Response::new(RemoveNetworkResponse {
+                    success: false,
+                    error_message: e.to_string(),
+                }))
+            }
+        }
+    }
+
     // Bundle management methods (Phase 2.1 stubs - will be fully implemented in Phase 2.2)
     async fn upload_bundle(
         &self,
@@ -865,14 +1456,53 @@ impl QuiltService for QuiltServiceImpl {
 }
 
 // ✅ BACKGROUND CONTAINER PROCESS STARTUP
-async fn start_container_process(sync_engine: Arc<SyncEngine>, container_id: String) -> Result<(), String> {
+/// Plumb a veth pair per network attachment into a running container and record
+/// each completed attachment in the sync engine
+async fn attach_container_networks(
+    sync_engine: &Arc<SyncEngine>,
+    network_registry: &Arc<NetworkRegistry>,
+    container_id: &str,
+    pid: i32,
+) -> Result<(), String> {
+    let attachments = sync_engine.list_container_networks(container_id).await
+        .map_err(|e| format!("Failed to load network attachments: {}", e))?;
+    
+    for (index, attachment) in attachments.into_iter().enumerate() {
+        let network = sync_engine.get_network(&attachment.network_name).await
+            .map_err(|e| format!("Failed to load network {}: {}", attachment.network_name, e))?;
+        let manager = network_registry.get_or_create(&network.name, &network.bridge_name, &network.subnet_cidr, &network.gateway_ip)?;
+        let config = manager.attach_container_network(container_id, &attachment.ip_address, index)?;
+        
+        let setup_config = config.clone();
+        tokio::task::spawn_blocking(move || {
+            manager.ensure_bridge_ready()?;
+            manager.setup_container_network(&setup_config, pid)
+        })
+        .await
+        .map_err(|e| format!("Network setup task failed: {}", e))??;
+        
+        sync_engine.mark_attachment_setup_complete(
+            container_id,
+            &network.name,
+            &network.bridge_name,
+            &config.veth_host_name,
+            &config.interface_name,
+        ).await.map_err(|e| format!("Failed to record network setup: {}", e))?;
+        
+        ConsoleLogger::success(&format!("🌐 [STARTUP] Container {} attached to network {} at {}", container_id, network.name, config.ip_address));
+    }
+    
+    Ok(())
+}
+
+async fn start_container_process(sync_engine: Arc<SyncEngine>, network_registry: Arc<NetworkRegistry>, container_id: String) -> Result<(), String> {
     use daemon::runtime::ContainerRuntime;
     use std::collections::HashMap;
     
     ConsoleLogger::info(&format!("🔄 [STARTUP] Beginning container process startup for: {}", container_id));
     
     // Get container configuration from sync engine
-    let container_record = sqlx::query("SELECT image_path, command FROM containers WHERE id = ?")
+    let container_record = sqlx::query("SELECT image_path, command, environment, volumes, setup_commands FROM containers WHERE id = ?")
         .bind(&container_id)
         .fetch_one(sync_engine.pool())
         .await
@@ -880,13 +1510,29 @@ async fn start_container_process(sync_engine: Arc<SyncEngine>, container_id: Str
     
     let image_path: String = container_record.get("image_path");
     let command: String = container_record.get("command");
+    let json_column = |column: &str| -> Result<Option<String>, String> {
+        container_record.try_get(column).map_err(|e| format!("Failed to read {}: {}", column, e))
+    };
+    let environment: HashMap<String, String> = match json_column("environment")? {
+        Some(json) => serde_json::from_str(&json).map_err(|e| format!("Invalid environment: {}", e))?,
+        None => HashMap::new(),
+    };
+    let volumes: Vec<String> = match json_column("volumes")? {
+        Some(json) => serde_json::from_str(&json).map_err(|e| format!("Invalid volumes: {}", e))?,
+        None => vec![],
+    };
+    let setup_commands: Vec<String> = match json_column("setup_commands")? {
+        Some(json) => serde_json::from_str(&json).map_err(|e| format!("Invalid setup commands: {}", e))?,
+        None => vec![],
+    };
 
     // Convert sync engine config back to legacy format for actual container startup
     let legacy_config = ContainerConfig {
         image_path,
         command: vec!["/bin/sh".to_string(), "-c".to_string(), command],
-        environment: HashMap::new(),
-        setup_commands: vec![],
+        environment,
+        setup_commands,
+        volumes,
         resource_limits: Some(CgroupLimits::default()),
         namespace_config: Some(NamespaceConfig::default()),
         working_directory: None,
@@ -921,6 +1567,11 @@ async fn start_container_process(sync_engine: Arc<SyncEngine>, container_id: Str
                     
                     ConsoleLogger::success(&format!("🏃 [STARTUP] Container {} is now Running (PID: {})", container_id, pid.as_raw()));
                     
+                    // Attach every requested network now that the namespace exists
+                    if let Err(e) = attach_container_networks(&sync_engine, &network_registry, &container_id, pid.as_raw()).await {
+                        ConsoleLogger::error(&format!("❌ [STARTUP] Network attachment failed for {}: {}", container_id, e));
+                    }
+                    
                     // ✅ WAIT FOR COMPLETION: Monitor process and handle exit atomically
                     let sync_engine_clone = sync_engine.clone();
                     let container_id_clone = container_id.clone();
@@ -1023,6 +1674,12 @@ async fn start_container_process(sync_engine: Arc<SyncEngine>, container_id: Str
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // ✅ TRACING - logs per RUST_LOG, spans exported when an OTLP endpoint is set
+    let _telemetry = utils::telemetry::init()?;
+    if let Some(endpoint) = utils::telemetry::otlp_endpoint() {
+        ConsoleLogger::info(&format!("Exporting traces to {}", endpoint));
+    }
+
     // ✅ SYNC ENGINE INITIALIZATION
     let service = Arc::new(QuiltServiceImpl::new().await
         .map_err(|e| format!("Failed to initialize sync engine: {}", e))?);
@@ -1070,6 +1727,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     tokio::select! {
         result = tonic::transport::Server::builder()
+            .trace_fn(utils::telemetry::grpc_span)
             .add_service(QuiltServiceServer::new((*service).clone()))
             .serve_with_incoming(uds_stream) => {
             result?;
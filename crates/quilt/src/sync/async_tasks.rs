@@ -5,6 +5,7 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::{RwLock, oneshot};
 use tokio::process::Command;
+use tracing::Instrument;
 use uuid::Uuid;
 use crate::sync::error::{SyncError, SyncResult};
 
@@ -135,10 +136,12 @@ impl AsyncTaskManager {
         // Create cancellation channel
         let (cancel_sender, cancel_receiver) = oneshot::channel::<()>();
         
-        // Spawn the actual execution task
+        // Spawn the actual execution task, under the submitting request's span so
+        // it stays in the caller's trace after the request returns
+        let span = tracing::info_span!("quilt.exec_task", task_id = %task.task_id, container_id = %task.container_id);
         let abort_handle = tokio::spawn(async move {
             Self::execute_task_impl(pool, task_clone, cancel_receiver).await
-        }).abort_handle();
+        }.instrument(span)).abort_handle();
         
         // Store task handle for cancellation
         {
@@ -5,6 +5,9 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::fs;
 use crate::sync::error::{SyncError, SyncResult};
 
+/// Directory holding per-container rootfs (and their overlay mount points)
+pub const CONTAINERS_ROOT: &str = "/tmp/quilt-containers";
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CleanupStatus {
     Pending,
@@ -209,6 +212,17 @@ impl CleanupService {
         Ok(tasks)
     }
     
+    /// Return tasks left `in_progress` by a daemon that died mid-cleanup to the queue
+    pub async fn requeue_interrupted_tasks(&self) -> SyncResult<usize> {
+        let result = sqlx::query("UPDATE cleanup_tasks SET status = ? WHERE status = ?")
+            .bind(CleanupStatus::Pending.to_string())
+            .bind(CleanupStatus::InProgress.to_string())
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.rows_affected() as usize)
+    }
+    
     pub async fn get_task_status(&self, task_id: i64) -> SyncResult<CleanupTask> {
         let row = sqlx::query(r#"
             SELECT id, container_id, resource_type, resource_path, status, 
@@ -279,9 +293,9 @@ impl CleanupService {
         
         let result = match task.resource_type {
             ResourceType::Rootfs => Self::cleanup_rootfs(&task.resource_path).await,
-            ResourceType::Network => Self::cleanup_network(&task.container_id).await,
+            ResourceType::Network => Self::cleanup_network(pool, &task.container_id, &task.resource_path).await,
             ResourceType::Cgroup => Self::cleanup_cgroup(&task.resource_path).await,
-            ResourceType::Mounts => Self::cleanup_mounts(&task.container_id).await,
+            ResourceType::Mounts => Self::cleanup_mounts(&task.container_id, &task.resource_path).await,
         };
         
         match result {
@@ -339,6 +353,9 @@ impl CleanupService {
             return Ok(());
         }
         
+        // Never recurse into a still-mounted overlay or proc/sys bind
+        Self::unmount_under(rootfs_path).await?;
+        
         tracing::debug!("Removing rootfs directory: {}", rootfs_path);
         fs::remove_dir_all(rootfs_path).await.map_err(|e| {
             SyncError::CleanupFailed {
@@ -351,17 +368,25 @@ impl CleanupService {
         Ok(())
     }
     
-    async fn cleanup_network(container_id: &str) -> SyncResult<()> {
-        // This would integrate with the network manager to clean up
-        // network interfaces, IP allocations, etc.
-        // For now, we'll just log the operation
+    /// `resource_path` is either the container id (release its allocations) or the
+    /// name of a leaked host interface found during reconciliation (delete it).
+    async fn cleanup_network(pool: &SqlitePool, container_id: &str, resource_path: &str) -> SyncResult<()> {
+        if resource_path != container_id {
+            return Self::delete_interface(resource_path).await;
+        }
+        
         tracing::debug!("Cleaning up network resources for container: {}", container_id);
         
-        // In real implementation, this would:
-        // 1. Remove veth interfaces
-        // 2. Update iptables rules
-        // 3. Mark network allocation as cleaned
-        // 4. Remove bridge assignments
+        // Veth pairs die with the container's network namespace; releasing the
+        // allocations on every attached network frees their addresses for reuse
+        let result = sqlx::query("UPDATE network_allocations SET status = 'cleaned' WHERE container_id = ? AND status != 'cleaned'")
+            .bind(container_id)
+            .execute(pool)
+            .await?;
+        
+        if result.rows_affected() > 0 {
+            tracing::debug!("Released {} network allocation(s) for container {}", result.rows_affected(), container_id);
+        }
         
         Ok(())
     }
@@ -384,20 +409,96 @@ impl CleanupService {
         Ok(())
     }
     
-    async fn cleanup_mounts(container_id: &str) -> SyncResult<()> {
-        // This would check for any remaining mounts related to the container
-        // and unmount them safely
-        tracing::debug!("Cleaning up mounts for container: {}", container_id);
+    async fn delete_interface(interface: &str) -> SyncResult<()> {
+        if !Path::new(&format!("/sys/class/net/{}", interface)).exists() {
+            tracing::debug!("Interface {} does not exist, skipping cleanup", interface);
+            return Ok(());
+        }
         
-        // In real implementation, this would:
-        // 1. Check /proc/mounts for container-related entries
-        // 2. Unmount any remaining filesystems
-        // 3. Remove mount points
+        tracing::debug!("Deleting leaked interface: {}", interface);
+        let name = interface.to_string();
+        let output = tokio::task::spawn_blocking(move || {
+            std::process::Command::new("ip").args(["link", "delete", &name]).output()
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r.map_err(|e| e.to_string()));
+        
+        match output {
+            Ok(out) if out.status.success() => Ok(()),
+            Ok(out) => Err(SyncError::CleanupFailed {
+                resource_type: "network".to_string(),
+                path: interface.to_string(),
+                message: String::from_utf8_lossy(&out.stderr).trim().to_string(),
+            }),
+            Err(message) => Err(SyncError::CleanupFailed {
+                resource_type: "network".to_string(),
+                path: interface.to_string(),
+                message,
+            }),
+        }
+    }
+    
+    /// `resource_path` is the mount root to release; tasks scheduled with the
+    /// container id fall back to the container's directory under `CONTAINERS_ROOT`.
+    async fn cleanup_mounts(container_id: &str, resource_path: &str) -> SyncResult<()> {
+        let root = if Path::new(resource_path).is_absolute() {
+            resource_path.to_string()
+        } else {
+            format!("{}/{}", CONTAINERS_ROOT, container_id)
+        };
+        
+        tracing::debug!("Cleaning up mounts for container {} under {}", container_id, root);
+        Self::unmount_under(&root).await
+    }
+    
+    /// Lazily unmount every mount point at or below `root`, deepest first
+    async fn unmount_under(root: &str) -> SyncResult<()> {
+        let mounts = match fs::read_to_string("/proc/mounts").await {
+            Ok(contents) => mount_points_under(&contents, root),
+            Err(e) => {
+                tracing::debug!("Unable to read /proc/mounts, skipping unmount of {}: {}", root, e);
+                return Ok(());
+            }
+        };
+        
+        for mount_point in mounts {
+            tracing::debug!("Unmounting {}", mount_point);
+            if let Err(e) = nix::mount::umount2(mount_point.as_str(), nix::mount::MntFlags::MNT_DETACH) {
+                // EINVAL: already gone (e.g. released together with its parent)
+                if e != nix::errno::Errno::EINVAL {
+                    return Err(SyncError::CleanupFailed {
+                        resource_type: "mounts".to_string(),
+                        path: mount_point,
+                        message: e.to_string(),
+                    });
+                }
+            }
+        }
         
         Ok(())
     }
 }
 
+/// Mount points from a `/proc/mounts` listing that live at or below `root`,
+/// ordered deepest first so children are released before their parents.
+pub fn mount_points_under(proc_mounts: &str, root: &str) -> Vec<String> {
+    let root = root.trim_end_matches('/');
+    let prefix = format!("{}/", root);
+    
+    let mut mounts: Vec<String> = proc_mounts
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        // /proc/mounts escapes spaces as octal; container paths never contain them
+        .filter(|mount_point| *mount_point == root || mount_point.starts_with(&prefix))
+        .map(|mount_point| mount_point.to_string())
+        .collect();
+    
+    mounts.sort_by(|a, b| b.matches('/').count().cmp(&a.matches('/').count()).then_with(|| b.cmp(a)));
+    mounts.dedup();
+    mounts
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -492,4 +593,33 @@ mod tests {
         assert_eq!(updated_task.status, CleanupStatus::Completed);
         assert!(updated_task.completed_at.is_some());
     }
-} 
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_requeue_interrupted_tasks() {
+        let (_conn, cleanup_service) = setup_test_db().await;
+        
+        let task_id = cleanup_service.schedule_cleanup("test-container", ResourceType::Mounts, "test-container").await.unwrap();
+        CleanupService::update_task_status(&cleanup_service.pool, task_id, CleanupStatus::InProgress, None).await.unwrap();
+        
+        assert_eq!(cleanup_service.requeue_interrupted_tasks().await.unwrap(), 1);
+        let task = cleanup_service.get_task_status(task_id).await.unwrap();
+        assert_eq!(task.status, CleanupStatus::Pending);
+    }
+    
+    #[test]
+    fn test_mount_points_under() {
+        let proc_mounts = "\
+overlay /tmp/quilt-containers/abc overlay rw 0 0
+proc /tmp/quilt-containers/abc/proc proc rw 0 0
+sysfs /tmp/quilt-containers/abc/sys sysfs rw 0 0
+overlay /tmp/quilt-containers/abcdef overlay rw 0 0
+tmpfs /tmp tmpfs rw 0 0
+";
+        let mounts = mount_points_under(proc_mounts, "/tmp/quilt-containers/abc/");
+        assert_eq!(mounts, vec![
+            "/tmp/quilt-containers/abc/sys".to_string(),
+            "/tmp/quilt-containers/abc/proc".to_string(),
+            "/tmp/quilt-containers/abc".to_string(),
+        ]);
+    }
+}
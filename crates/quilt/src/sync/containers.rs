@@ -74,6 +74,13 @@ pub struct ContainerConfig {
     pub environment: HashMap<String, String>,
     pub memory_limit_mb: Option<i64>,
     pub cpu_limit_percent: Option<f64>,
+    /// Tenant charged for this container; quotas apply per owner
+    pub owner: Option<String>,
+    /// Free-form metadata, e.g. stack membership
+    pub labels: HashMap<String, String>,
+    /// Bind mounts as `host_path:container_path[:ro]`
+    pub volumes: Vec<String>,
+    pub setup_commands: Vec<String>,
     
     // Namespace configuration
     pub enable_network_namespace: bool,
@@ -95,6 +102,8 @@ pub struct ContainerStatus {
     pub started_at: Option<i64>,
     pub exited_at: Option<i64>,
     pub rootfs_path: Option<String>,
+    pub owner: Option<String>,
+    pub labels: HashMap<String, String>,
 }
 
 pub struct ContainerManager {
@@ -118,11 +127,11 @@ impl ContainerManager {
         sqlx::query(r#"
             INSERT INTO containers (
                 id, name, image_path, command, environment, state,
-                memory_limit_mb, cpu_limit_percent,
+                memory_limit_mb, cpu_limit_percent, owner, labels, volumes, setup_commands,
                 enable_network_namespace, enable_pid_namespace, enable_mount_namespace,
                 enable_uts_namespace, enable_ipc_namespace,
                 created_at, updated_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#)
         .bind(&config.id)
         .bind(&config.name)
@@ -132,6 +141,10 @@ impl ContainerManager {
         .bind(ContainerState::Created.to_string())
         .bind(config.memory_limit_mb)
         .bind(config.cpu_limit_percent)
+        .bind(&config.owner)
+        .bind(serde_json::to_string(&config.labels)?)
+        .bind(serde_json::to_string(&config.volumes)?)
+        .bind(serde_json::to_string(&config.setup_commands)?)
         .bind(config.enable_network_namespace)
         .bind(config.enable_pid_namespace)
         .bind(config.enable_mount_namespace)
@@ -309,10 +322,11 @@ impl ContainerManager {
         let row = sqlx::query(r#"
             SELECT 
                 c.id, c.name, c.state, c.pid, c.exit_code, c.created_at, 
-                c.started_at, c.exited_at, c.rootfs_path,
-                n.ip_address
+                c.started_at, c.exited_at, c.rootfs_path, c.owner, c.labels,
+                (SELECT n.ip_address FROM network_allocations n
+                 WHERE n.container_id = c.id
+                 ORDER BY n.allocation_time ASC, n.rowid ASC LIMIT 1) AS ip_address
             FROM containers c 
-            LEFT JOIN network_allocations n ON c.id = n.container_id 
             WHERE c.id = ?
         "#)
         .bind(container_id)
@@ -372,6 +386,8 @@ impl ContainerManager {
                     started_at: row.get("started_at"),
                     exited_at: row.get("exited_at"),
                     rootfs_path: row.get("rootfs_path"),
+                    owner: row.get("owner"),
+                    labels: Self::labels_from_row(&row)?,
                 })
             }
             None => Err(SyncError::NotFound {
@@ -380,6 +396,14 @@ impl ContainerManager {
         }
     }
     
+    fn labels_from_row(row: &sqlx::sqlite::SqliteRow) -> SyncResult<HashMap<String, String>> {
+        let labels: Option<String> = row.get("labels");
+        match labels {
+            Some(json) => Ok(serde_json::from_str(&json)?),
+            None => Ok(HashMap::new()),
+        }
+    }
+    
     pub async fn get_container_state(&self, container_id: &str) -> SyncResult<ContainerState> {
         let state_str: Option<String> = sqlx::query_scalar("SELECT state FROM containers WHERE id = ?")
             .bind(container_id)
@@ -398,10 +422,11 @@ impl ContainerManager {
         let mut query = "
             SELECT 
                 c.id, c.name, c.state, c.pid, c.exit_code, c.created_at, 
-                c.started_at, c.exited_at, c.rootfs_path,
-                n.ip_address
-            FROM containers c 
-            LEFT JOIN network_allocations n ON c.id = n.container_id
+                c.started_at, c.exited_at, c.rootfs_path, c.owner, c.labels,
+                (SELECT n.ip_address FROM network_allocations n
+                 WHERE n.container_id = c.id
+                 ORDER BY n.allocation_time ASC, n.rowid ASC LIMIT 1) AS ip_address
+            FROM containers c
         ".to_string();
         
         if let Some(state) = state_filter {
@@ -428,6 +453,8 @@ impl ContainerManager {
                 started_at: row.get("started_at"),
                 exited_at: row.get("exited_at"),
                 rootfs_path: row.get("rootfs_path"),
+                owner: row.get("owner"),
+                labels: Self::labels_from_row(&row)?,
             });
         }
         
@@ -501,6 +528,10 @@ mod tests {
             environment: HashMap::new(),
             memory_limit_mb: Some(1024),
             cpu_limit_percent: Some(50.0),
+            owner: None,
+            labels: HashMap::from([("quilt.stack".to_string(), "shop".to_string())]),
+            volumes: vec![],
+            setup_commands: vec![],
             enable_network_namespace: true,
             enable_pid_namespace: true,
             enable_mount_namespace: true,
@@ -514,6 +545,7 @@ mod tests {
         // Check initial state
         let status = container_manager.get_container_status("test-container").await.unwrap();
         assert_eq!(status.state, ContainerState::Created);
+        assert_eq!(status.labels.get("quilt.stack").map(String::as_str), Some("shop"));
         
         // Transition to starting
         container_manager.update_container_state("test-container", ContainerState::Starting).await.unwrap();
@@ -549,6 +581,10 @@ mod tests {
             environment: HashMap::new(),
             memory_limit_mb: None,
             cpu_limit_percent: None,
+            owner: None,
+            labels: HashMap::new(),
+            volumes: vec![],
+            setup_commands: vec![],
             enable_network_namespace: false,
             enable_pid_namespace: false,
             enable_mount_namespace: false,
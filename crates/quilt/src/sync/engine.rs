@@ -5,10 +5,14 @@ use crate::sync::{
     connection::ConnectionManager,
     schema::SchemaManager,
     containers::{ContainerManager, ContainerConfig, ContainerStatus, ContainerState},
-    network::{NetworkManager, NetworkConfig, NetworkAllocation},
+    network::{NetworkManager, NetworkConfig, NetworkAllocation, NetworkDefinition, NetworkSpec},
     monitor::ProcessMonitorService,
     cleanup::CleanupService,
     async_tasks::{AsyncTaskManager, AsyncTask, AsyncTaskStatus},
+    reconcile::{ReconcileConfig, ReconciliationReport, StateReconciler},
+    metrics::{MetricsManager, MetricsPoint, MetricsQuery, ResourceSampler, DEFAULT_SAMPLE_INTERVAL},
+    quotas::{OwnerQuota, OwnerUsage, QuotaManager},
+    templates::{ContainerTemplate, TemplateManager, TemplateSpec},
     error::{SyncError, SyncResult},
 };
 use std::collections::HashSet;
@@ -21,6 +25,11 @@ pub struct SyncEngine {
     monitor_service: Arc<ProcessMonitorService>,
     cleanup_service: Arc<CleanupService>,
     async_task_manager: Arc<AsyncTaskManager>,
+    metrics_manager: Arc<MetricsManager>,
+    quota_manager: Arc<QuotaManager>,
+    template_manager: Arc<TemplateManager>,
+    reconciler: StateReconciler,
+    last_reconciliation: Arc<RwLock<Option<ReconciliationReport>>>,
     
     // Background services control
     background_tasks: Arc<RwLock<Vec<tokio::task::JoinHandle<()>>>>,
@@ -42,6 +51,17 @@ impl SyncEngine {
         let monitor_service = Arc::new(ProcessMonitorService::new(connection_manager.pool().clone()));
         let cleanup_service = Arc::new(CleanupService::new(connection_manager.pool().clone()));
         let async_task_manager = Arc::new(AsyncTaskManager::new(connection_manager.pool().clone()));
+        let metrics_manager = Arc::new(MetricsManager::new(connection_manager.pool().clone()));
+        let quota_manager = Arc::new(QuotaManager::new(connection_manager.pool().clone()));
+        let template_manager = Arc::new(TemplateManager::new(connection_manager.pool().clone()));
+        let reconciler = StateReconciler::new(
+            connection_manager.pool().clone(),
+            container_manager.clone(),
+            network_manager.clone(),
+            monitor_service.clone(),
+            cleanup_service.clone(),
+            ReconcileConfig::default(),
+        );
         
         let engine = Self {
             connection_manager,
@@ -50,6 +70,11 @@ impl SyncEngine {
             monitor_service,
             cleanup_service,
             async_task_manager,
+            metrics_manager,
+            quota_manager,
+            template_manager,
+            reconciler,
+            last_reconciliation: Arc::new(RwLock::new(None)),
             background_tasks: Arc::new(RwLock::new(Vec::new())),
         };
         
@@ -57,8 +82,12 @@ impl SyncEngine {
         Ok(engine)
     }
     
-    /// Start background services for monitoring and cleanup
-    pub async fn start_background_services(&self) -> SyncResult<()> {
+    /// Reconcile persisted state with the host, then start background services
+    /// for monitoring and cleanup. Cleanup scheduled by reconciliation is picked
+    /// up by the cleanup worker started here.
+    pub async fn start_background_services(&self) -> SyncResult<ReconciliationReport> {
+        let report = self.reconcile().await?;
+        
         let mut tasks = self.background_tasks.write().await;
         
         // Start cleanup worker
@@ -96,8 +125,70 @@ impl SyncEngine {
         });
         tasks.push(async_cleanup_task);
         
+        // Start resource sampling of running containers (prunes expired history hourly)
+        let container_manager = self.container_manager.clone();
+        let network_manager = self.network_manager.clone();
+        let metrics_manager = self.metrics_manager.clone();
+        let metrics_task = tokio::spawn(async move {
+            let sampler = ResourceSampler::default();
+            let mut interval = tokio::time::interval(DEFAULT_SAMPLE_INTERVAL);
+            let mut last_prune = std::time::Instant::now();
+            loop {
+                interval.tick().await;
+                if let Err(e) = Self::sample_running_containers(&container_manager, &network_manager, &metrics_manager, &sampler).await {
+                    tracing::warn!("Failed to sample container metrics: {}", e);
+                }
+                if last_prune.elapsed() >= Duration::from_secs(3600) {
+                    last_prune = std::time::Instant::now();
+                    if let Err(e) = metrics_manager.prune().await {
+                        tracing::warn!("Failed to prune container metrics: {}", e);
+                    }
+                }
+            }
+        });
+        tasks.push(metrics_task);
+        
         tracing::info!("Started {} background services", tasks.len());
-        Ok(())
+        Ok(report)
+    }
+    
+    async fn sample_running_containers(
+        container_manager: &ContainerManager,
+        network_manager: &NetworkManager,
+        metrics_manager: &MetricsManager,
+        sampler: &ResourceSampler,
+    ) -> SyncResult<usize> {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs() as i64;
+        let mut sampled = 0;
+        
+        for container in container_manager.list_containers(Some(ContainerState::Running)).await? {
+            let veths: Vec<String> = network_manager
+                .list_container_allocations(&container.id)
+                .await?
+                .into_iter()
+                .filter_map(|allocation| allocation.veth_host)
+                .collect();
+            
+            if let Some(sample) = sampler.sample(&container.id, &veths, now) {
+                metrics_manager.record_sample(&sample).await?;
+                sampled += 1;
+            }
+        }
+        
+        Ok(sampled)
+    }
+    
+    /// Adopt surviving container processes, mark dead ones exited and schedule
+    /// cleanup for resources leaked by a previous daemon
+    pub async fn reconcile(&self) -> SyncResult<ReconciliationReport> {
+        let report = self.reconciler.reconcile().await?;
+        *self.last_reconciliation.write().await = Some(report.clone());
+        Ok(report)
+    }
+    
+    /// Report from the most recent reconciliation pass, if one has run
+    pub async fn last_reconciliation_report(&self) -> Option<ReconciliationReport> {
+        self.last_reconciliation.read().await.clone()
     }
     
     /// Stop all background services
@@ -120,10 +211,23 @@ impl SyncEngine {
     
     // === Container Management ===
     
-    /// Create a new container with coordinated network allocation
+    /// Create a new container with coordinated network allocation on the default network
     pub async fn create_container(&self, config: ContainerConfig) -> SyncResult<NetworkConfig> {
+        self.create_container_on_networks(config, &[]).await
+    }
+    
+    /// Create a new container attached to each of `networks` (default network when empty).
+    /// Returns the primary attachment; the rest are listed via `list_container_networks`.
+    pub async fn create_container_on_networks(&self, config: ContainerConfig, networks: &[String]) -> SyncResult<NetworkConfig> {
         let container_id = config.id.clone();
         
+        // Resolve networks up front so unknown names fail before anything is written
+        let networks = if config.enable_network_namespace {
+            self.network_manager.resolve_networks(networks).await?
+        } else {
+            Vec::new()
+        };
+        
         // Use explicit transaction to ensure container is committed before network allocation
         let mut tx = self.pool().begin().await?;
         
@@ -134,11 +238,11 @@ impl SyncEngine {
         sqlx::query(r#"
             INSERT INTO containers (
                 id, name, image_path, command, environment, state,
-                memory_limit_mb, cpu_limit_percent,
+                memory_limit_mb, cpu_limit_percent, owner, labels, volumes, setup_commands,
                 enable_network_namespace, enable_pid_namespace, enable_mount_namespace,
                 enable_uts_namespace, enable_ipc_namespace,
                 created_at, updated_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#)
         .bind(&config.id)
         .bind(&config.name)
@@ -148,6 +252,10 @@ impl SyncEngine {
         .bind("created")
         .bind(config.memory_limit_mb)
         .bind(config.cpu_limit_percent)
+        .bind(&config.owner)
+        .bind(serde_json::to_string(&config.labels)?)
+        .bind(serde_json::to_string(&config.volumes)?)
+        .bind(serde_json::to_string(&config.setup_commands)?)
         .bind(config.enable_network_namespace)
         .bind(config.enable_pid_namespace)
         .bind(config.enable_mount_namespace)
@@ -157,70 +265,28 @@ impl SyncEngine {
         .bind(now)
         .execute(&mut *tx)
         .await?;
+        
+        // Enforce the owner's quota with the new row counted; failing rolls it back
+        if let Some(owner) = &config.owner {
+            self.quota_manager.enforce_in(&mut *tx, owner).await?;
+        }
 
-        // 2. Allocate network resources if networking is enabled
-        let network_config = if config.enable_network_namespace {
-            // Find available IP
-            let allocated_ips: Vec<(String,)> = sqlx::query_as(
-                "SELECT ip_address FROM network_allocations WHERE status != 'cleaned'"
-            ).fetch_all(&mut *tx).await?;
-            
-            let allocated_set: std::collections::HashSet<String> = allocated_ips
-                .into_iter()
-                .map(|(ip,)| ip)
-                .collect();
-            
-            // Find first available IP in range (172.16.0.10 to 172.16.0.250)
-            let start_int = u32::from(std::net::Ipv4Addr::new(172, 16, 0, 10));
-            let end_int = u32::from(std::net::Ipv4Addr::new(172, 16, 0, 250));
-            
-            let mut available_ip = None;
-            for ip_int in start_int..=end_int {
-                let ip = std::net::Ipv4Addr::from(ip_int);
-                let ip_str = ip.to_string();
-                
-                if !allocated_set.contains(&ip_str) {
-                    available_ip = Some(ip_str);
-                    break;
-                }
-            }
-            
-            let ip = available_ip.ok_or_else(|| SyncError::NoAvailableIp)?;
-            
-            // Insert network allocation within same transaction
-            sqlx::query(r#"
-                INSERT INTO network_allocations (
-                    container_id, ip_address, allocation_time, setup_completed, status
-                ) VALUES (?, ?, ?, ?, ?)
-            "#)
-            .bind(&container_id)
-            .bind(&ip)
-            .bind(now)
-            .bind(false)
-            .bind("allocated")
-            .execute(&mut *tx)
-            .await?;
-            
-            Some(NetworkConfig {
-                container_id: container_id.clone(),
-                ip_address: ip,
-                bridge_interface: None,
-                veth_host: None,
-                veth_container: None,
-                setup_required: true,
-            })
-        } else {
-            None
-        };
+        // 2. Allocate an address on every requested network within the same transaction
+        let mut primary = None;
+        for network in &networks {
+            let network_config = self.network_manager.allocate_in(&mut *tx, &container_id, network, now).await?;
+            primary.get_or_insert(network_config);
+        }
         
         // 3. Commit transaction to ensure atomicity
         tx.commit().await?;
         
-        tracing::info!("Created container {} in database with network config", config.id);
+        tracing::info!("Created container {} in database on {} network(s)", config.id, networks.len());
         
         // 4. Return network configuration for setup
-        Ok(network_config.unwrap_or(NetworkConfig {
+        Ok(primary.unwrap_or(NetworkConfig {
             container_id,
+            network_name: String::new(),
             ip_address: String::new(),
             bridge_interface: None,
             veth_host: None,
@@ -332,11 +398,46 @@ impl SyncEngine {
         self.network_manager.mark_network_setup_complete(container_id, bridge_interface, veth_host, veth_container).await
     }
     
-    /// Get network allocation for container
+    /// Mark setup complete for one of a container's network attachments
+    pub async fn mark_attachment_setup_complete(&self, container_id: &str, network_name: &str, bridge_interface: &str, veth_host: &str, veth_container: &str) -> SyncResult<()> {
+        self.network_manager.mark_attachment_setup_complete(container_id, network_name, bridge_interface, veth_host, veth_container).await
+    }
+    
+    /// Get the primary network allocation for container
     pub async fn get_network_allocation(&self, container_id: &str) -> SyncResult<NetworkAllocation> {
         self.network_manager.get_network_allocation(container_id).await
     }
     
+    /// List every network attachment of a container, primary first
+    pub async fn list_container_networks(&self, container_id: &str) -> SyncResult<Vec<NetworkAllocation>> {
+        self.network_manager.list_container_allocations(container_id).await
+    }
+    
+    /// Create a user-defined network
+    pub async fn create_network(&self, spec: NetworkSpec) -> SyncResult<NetworkDefinition> {
+        self.network_manager.create_network(spec).await
+    }
+    
+    /// Get a network definition by name
+    pub async fn get_network(&self, name: &str) -> SyncResult<NetworkDefinition> {
+        self.network_manager.get_network(name).await
+    }
+    
+    /// List all networks, default first
+    pub async fn list_networks(&self) -> SyncResult<Vec<NetworkDefinition>> {
+        self.network_manager.list_networks().await
+    }
+    
+    /// List live allocations on a network
+    pub async fn list_allocations_on_network(&self, network_name: &str) -> SyncResult<Vec<NetworkAllocation>> {
+        self.network_manager.list_network_allocations(network_name).await
+    }
+    
+    /// Remove a network that no container is attached to
+    pub async fn remove_network(&self, name: &str) -> SyncResult<NetworkDefinition> {
+        self.network_manager.remove_network(name).await
+    }
+    
     /// List all network allocations
     pub async fn list_network_allocations(&self) -> SyncResult<Vec<NetworkAllocation>> {
         self.network_manager.list_allocations(None).await
@@ -359,6 +460,54 @@ impl SyncEngine {
         self.monitor_service.stop_monitoring(container_id).await
     }
     
+    // === Metrics and Quotas ===
+    
+    /// Query a container's resource history
+    pub async fn get_container_metrics(&self, query: &MetricsQuery) -> SyncResult<Vec<MetricsPoint>> {
+        self.metrics_manager.query(query).await
+    }
+    
+    /// Set aggregate limits for an owner's live containers (`None` = unlimited)
+    pub async fn set_owner_quota(&self, owner: &str, max_containers: Option<i64>, max_memory_mb: Option<i64>) -> SyncResult<OwnerQuota> {
+        self.quota_manager.set_quota(owner, max_containers, max_memory_mb).await
+    }
+    
+    pub async fn get_owner_quota(&self, owner: &str) -> SyncResult<Option<OwnerQuota>> {
+        self.quota_manager.get_quota(owner).await
+    }
+    
+    pub async fn remove_owner_quota(&self, owner: &str) -> SyncResult<bool> {
+        self.quota_manager.remove_quota(owner).await
+    }
+    
+    pub async fn get_owner_usage(&self, owner: &str) -> SyncResult<OwnerUsage> {
+        self.quota_manager.get_usage(owner).await
+    }
+    
+    // === Templates ===
+    
+    /// Save `spec` as the next version of template `name`
+    pub async fn create_template(&self, name: &str, description: Option<String>, spec: TemplateSpec) -> SyncResult<ContainerTemplate> {
+        self.template_manager.create_template(name, description, spec).await
+    }
+    
+    /// Get a template version, or the latest when `version` is `None`
+    pub async fn get_template(&self, name: &str, version: Option<i64>) -> SyncResult<ContainerTemplate> {
+        self.template_manager.get_template(name, version).await
+    }
+    
+    pub async fn list_templates(&self) -> SyncResult<Vec<ContainerTemplate>> {
+        self.template_manager.list_templates().await
+    }
+    
+    pub async fn list_template_versions(&self, name: &str) -> SyncResult<Vec<ContainerTemplate>> {
+        self.template_manager.list_template_versions(name).await
+    }
+    
+    pub async fn delete_template(&self, name: &str, version: Option<i64>) -> SyncResult<u64> {
+        self.template_manager.delete_template(name, version).await
+    }
+    
     // === Cleanup Management ===
     
     /// Trigger cleanup for a container
@@ -498,6 +647,10 @@ mod tests {
             environment: HashMap::new(),
             memory_limit_mb: Some(1024),
             cpu_limit_percent: Some(50.0),
+            owner: None,
+            labels: HashMap::new(),
+            volumes: vec![],
+            setup_commands: vec![],
             enable_network_namespace: true,
             enable_pid_namespace: true,
             enable_mount_namespace: true,
@@ -541,6 +694,101 @@ mod tests {
         engine.close().await;
     }
     
+    #[tokio::test]
+    async fn test_container_on_multiple_networks() {
+        let engine = setup_test_engine().await;
+        
+        engine.create_network(NetworkSpec {
+            name: "backend".to_string(),
+            subnet_cidr: "10.70.0.0/24".to_string(),
+            ..Default::default()
+        }).await.unwrap();
+        
+        let config = ContainerConfig { id: "multi-net".to_string(), ..engine_test_config() };
+        
+        let networks = vec!["backend".to_string(), "default".to_string()];
+        let primary = engine.create_container_on_networks(config, &networks).await.unwrap();
+        assert_eq!(primary.network_name, "backend");
+        assert_eq!(primary.ip_address, "10.70.0.2");
+        
+        let attachments = engine.list_container_networks("multi-net").await.unwrap();
+        assert_eq!(attachments.len(), 2);
+        
+        // Status reports the primary address
+        let status = engine.get_container_status("multi-net").await.unwrap();
+        assert_eq!(status.ip_address, Some("10.70.0.2".to_string()));
+        
+        // Unknown networks fail without creating the container
+        let bad = ContainerConfig { id: "bad-net".to_string(), ..engine_test_config() };
+        let result = engine.create_container_on_networks(bad, &["missing".to_string()]).await;
+        assert!(matches!(result, Err(SyncError::NetworkNotFound { .. })));
+        assert!(!engine.container_exists("bad-net").await.unwrap());
+        
+        engine.close().await;
+    }
+    
+    #[tokio::test]
+    async fn test_owner_quota_enforced_at_create() {
+        let engine = setup_test_engine().await;
+        
+        engine.set_owner_quota("team-a", Some(2), Some(1024)).await.unwrap();
+        
+        let owned = |id: &str, memory: i64| ContainerConfig {
+            id: id.to_string(),
+            owner: Some("team-a".to_string()),
+            memory_limit_mb: Some(memory),
+            enable_network_namespace: false,
+            ..engine_test_config()
+        };
+        
+        engine.create_container(owned("quota-1", 512)).await.unwrap();
+        
+        // Memory over the aggregate limit is rejected and nothing is written
+        let result = engine.create_container(owned("quota-2", 768)).await;
+        assert!(matches!(result, Err(SyncError::QuotaExceeded { .. })));
+        assert!(!engine.container_exists("quota-2").await.unwrap());
+        
+        engine.create_container(owned("quota-2", 256)).await.unwrap();
+        
+        // Container count limit
+        let result = engine.create_container(owned("quota-3", 64)).await;
+        assert!(matches!(result, Err(SyncError::QuotaExceeded { .. })));
+        
+        // Exited containers no longer count
+        engine.update_container_state("quota-1", ContainerState::Exited).await.unwrap();
+        engine.create_container(owned("quota-3", 64)).await.unwrap();
+        
+        let usage = engine.get_owner_usage("team-a").await.unwrap();
+        assert_eq!(usage.containers, 2);
+        assert_eq!(usage.memory_mb, 320);
+        
+        // Unowned containers are not limited
+        engine.create_container(ContainerConfig { id: "unowned".to_string(), enable_network_namespace: false, ..engine_test_config() }).await.unwrap();
+        
+        engine.close().await;
+    }
+    
+    fn engine_test_config() -> ContainerConfig {
+        ContainerConfig {
+            id: String::new(),
+            name: None,
+            image_path: "/path/to/image".to_string(),
+            command: "echo hello".to_string(),
+            environment: HashMap::new(),
+            memory_limit_mb: None,
+            cpu_limit_percent: None,
+            owner: None,
+            labels: HashMap::new(),
+            volumes: vec![],
+            setup_commands: vec![],
+            enable_network_namespace: true,
+            enable_pid_namespace: true,
+            enable_mount_namespace: true,
+            enable_uts_namespace: true,
+            enable_ipc_namespace: true,
+        }
+    }
+    
     #[tokio::test]
     async fn test_network_disabled_container() {
         let engine = setup_test_engine().await;
@@ -553,6 +801,10 @@ mod tests {
             environment: HashMap::new(),
             memory_limit_mb: None,
             cpu_limit_percent: None,
+            owner: None,
+            labels: HashMap::new(),
+            volumes: vec![],
+            setup_commands: vec![],
             enable_network_namespace: false, // Networking disabled
             enable_pid_namespace: true,
             enable_mount_namespace: true,
@@ -588,6 +840,10 @@ mod tests {
                 environment: HashMap::new(),
                 memory_limit_mb: None,
                 cpu_limit_percent: None,
+                owner: None,
+                labels: HashMap::new(),
+                volumes: vec![],
+                setup_commands: vec![],
                 enable_network_namespace: i % 2 == 0, // Half with networking
                 enable_pid_namespace: true,
                 enable_mount_namespace: true,
@@ -28,6 +28,18 @@ pub enum SyncError {
     #[error("No available IP addresses in range")]
     NoAvailableIp,
     
+    #[error("Network not found: {name}")]
+    NetworkNotFound { name: String },
+    
+    #[error("Network {name} is still in use by {} container(s)", .containers.len())]
+    NetworkInUse { name: String, containers: Vec<String> },
+    
+    #[error("Template not found: {name}")]
+    TemplateNotFound { name: String },
+    
+    #[error("Quota exceeded for owner {owner}: {message}")]
+    QuotaExceeded { owner: String, message: String },
+    
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
     
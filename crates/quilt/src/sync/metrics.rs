@@ -0,0 +1,512 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{SqlitePool, Row};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::sync::error::{SyncError, SyncResult};
+
+/// Interval between resource samples of running containers
+pub const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MetricsResolution {
+    Raw,
+    Minute,
+    Hour,
+}
+
+impl MetricsResolution {
+    pub const ALL: [MetricsResolution; 3] = [MetricsResolution::Raw, MetricsResolution::Minute, MetricsResolution::Hour];
+
+    pub fn to_string(&self) -> String {
+        match self {
+            MetricsResolution::Raw => "raw".to_string(),
+            MetricsResolution::Minute => "minute".to_string(),
+            MetricsResolution::Hour => "hour".to_string(),
+        }
+    }
+
+    pub fn from_string(s: &str) -> SyncResult<Self> {
+        match s {
+            "raw" => Ok(MetricsResolution::Raw),
+            "minute" => Ok(MetricsResolution::Minute),
+            "hour" => Ok(MetricsResolution::Hour),
+            _ => Err(SyncError::ValidationFailed {
+                message: format!("Invalid metrics resolution: {}", s),
+            }),
+        }
+    }
+
+    /// Width of one bucket in seconds (raw samples are their own bucket)
+    pub fn bucket_secs(&self) -> i64 {
+        match self {
+            MetricsResolution::Raw => 1,
+            MetricsResolution::Minute => 60,
+            MetricsResolution::Hour => 3600,
+        }
+    }
+
+    /// How long buckets of this resolution are kept
+    pub fn retention(&self) -> Duration {
+        match self {
+            MetricsResolution::Raw => Duration::from_secs(3600),
+            MetricsResolution::Minute => Duration::from_secs(86400),
+            MetricsResolution::Hour => Duration::from_secs(30 * 86400),
+        }
+    }
+
+    /// Finest resolution whose retention still covers a range starting at `start`
+    pub fn for_range(start: i64, now: i64) -> Self {
+        let age = (now - start).max(0) as u64;
+        Self::ALL
+            .into_iter()
+            .find(|resolution| age <= resolution.retention().as_secs())
+            .unwrap_or(MetricsResolution::Hour)
+    }
+
+    pub fn bucket_start(&self, timestamp: i64) -> i64 {
+        timestamp - timestamp.rem_euclid(self.bucket_secs())
+    }
+}
+
+/// One reading of a container's cumulative counters and gauges
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceSample {
+    pub container_id: String,
+    pub timestamp: i64,
+    /// Cumulative CPU time in microseconds
+    pub cpu_usage_usec: i64,
+    pub memory_bytes: i64,
+    /// Cumulative block I/O
+    pub io_read_bytes: i64,
+    pub io_write_bytes: i64,
+    /// Cumulative traffic as seen from inside the container
+    pub net_rx_bytes: i64,
+    pub net_tx_bytes: i64,
+    pub pids: i64,
+}
+
+/// A (possibly downsampled) bucket of samples. Cumulative counters hold the last
+/// value seen in the bucket; gauges are averaged, with maxima kept alongside.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsPoint {
+    pub container_id: String,
+    pub resolution: MetricsResolution,
+    pub bucket_start: i64,
+    pub samples: i64,
+    pub cpu_percent_avg: f64,
+    pub cpu_percent_max: f64,
+    pub cpu_usage_usec: i64,
+    pub memory_bytes_avg: i64,
+    pub memory_bytes_max: i64,
+    pub io_read_bytes: i64,
+    pub io_write_bytes: i64,
+    pub net_rx_bytes: i64,
+    pub net_tx_bytes: i64,
+    pub pids_max: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetricsQuery {
+    pub container_id: String,
+    pub start_time: i64,
+    pub end_time: i64,
+    /// Pick from the range when unset
+    pub resolution: Option<MetricsResolution>,
+    pub limit: Option<i64>,
+}
+
+pub struct MetricsManager {
+    pool: SqlitePool,
+}
+
+impl MetricsManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Store a sample at every resolution. CPU percentage is derived from the
+    /// previous raw sample so it survives daemon restarts.
+    pub async fn record_sample(&self, sample: &ResourceSample) -> SyncResult<f64> {
+        let mut tx = self.pool.begin().await?;
+
+        let previous = sqlx::query(r#"
+            SELECT bucket_start, cpu_usage_usec FROM container_metrics
+            WHERE container_id = ? AND resolution = 'raw' AND bucket_start < ?
+            ORDER BY bucket_start DESC LIMIT 1
+        "#)
+        .bind(&sample.container_id)
+        .bind(sample.timestamp)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let cpu_percent = match previous {
+            Some(row) => {
+                let prev_time: i64 = row.get("bucket_start");
+                let prev_usage: i64 = row.get("cpu_usage_usec");
+                cpu_percent_between(prev_usage, sample.cpu_usage_usec, sample.timestamp - prev_time)
+            }
+            None => 0.0,
+        };
+
+        for resolution in MetricsResolution::ALL {
+            sqlx::query(r#"
+                INSERT INTO container_metrics (
+                    container_id, resolution, bucket_start, samples,
+                    cpu_percent_avg, cpu_percent_max, cpu_usage_usec,
+                    memory_bytes_avg, memory_bytes_max,
+                    io_read_bytes, io_write_bytes, net_rx_bytes, net_tx_bytes, pids_max
+                ) VALUES (?, ?, ?, 1, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(container_id, resolution, bucket_start) DO UPDATE SET
+                    cpu_percent_avg = (cpu_percent_avg * samples + excluded.cpu_percent_avg) / (samples + 1),
+                    cpu_percent_max = MAX(cpu_percent_max, excluded.cpu_percent_max),
+                    cpu_usage_usec = excluded.cpu_usage_usec,
+                    memory_bytes_avg = (memory_bytes_avg * samples + excluded.memory_bytes_avg) / (samples + 1),
+                    memory_bytes_max = MAX(memory_bytes_max, excluded.memory_bytes_max),
+                    io_read_bytes = excluded.io_read_bytes,
+                    io_write_bytes = excluded.io_write_bytes,
+                    net_rx_bytes = excluded.net_rx_bytes,
+                    net_tx_bytes = excluded.net_tx_bytes,
+                    pids_max = MAX(pids_max, excluded.pids_max),
+                    samples = samples + 1
+            "#)
+            .bind(&sample.container_id)
+            .bind(resolution.to_string())
+            .bind(resolution.bucket_start(sample.timestamp))
+            .bind(cpu_percent)
+            .bind(cpu_percent)
+            .bind(sample.cpu_usage_usec)
+            .bind(sample.memory_bytes)
+            .bind(sample.memory_bytes)
+            .bind(sample.io_read_bytes)
+            .bind(sample.io_write_bytes)
+            .bind(sample.net_rx_bytes)
+            .bind(sample.net_tx_bytes)
+            .bind(sample.pids)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(cpu_percent)
+    }
+
+    pub async fn query(&self, query: &MetricsQuery) -> SyncResult<Vec<MetricsPoint>> {
+        if query.end_time < query.start_time {
+            return Err(SyncError::ValidationFailed {
+                message: format!("Metrics range end {} is before start {}", query.end_time, query.start_time),
+            });
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let resolution = query.resolution.unwrap_or_else(|| MetricsResolution::for_range(query.start_time, now));
+
+        let rows = sqlx::query(r#"
+            SELECT container_id, resolution, bucket_start, samples,
+                   cpu_percent_avg, cpu_percent_max, cpu_usage_usec,
+                   memory_bytes_avg, memory_bytes_max,
+                   io_read_bytes, io_write_bytes, net_rx_bytes, net_tx_bytes, pids_max
+            FROM container_metrics
+            WHERE container_id = ? AND resolution = ? AND bucket_start >= ? AND bucket_start <= ?
+            ORDER BY bucket_start ASC
+            LIMIT ?
+        "#)
+        .bind(&query.container_id)
+        .bind(resolution.to_string())
+        .bind(resolution.bucket_start(query.start_time))
+        .bind(query.end_time)
+        .bind(query.limit.filter(|l| *l > 0).unwrap_or(-1))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::point_from_row).collect()
+    }
+
+    /// Drop buckets older than each resolution's retention window
+    pub async fn prune(&self) -> SyncResult<u64> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let mut removed = 0;
+
+        for resolution in MetricsResolution::ALL {
+            let cutoff = now - resolution.retention().as_secs() as i64;
+            let result = sqlx::query("DELETE FROM container_metrics WHERE resolution = ? AND bucket_start < ?")
+                .bind(resolution.to_string())
+                .bind(cutoff)
+                .execute(&self.pool)
+                .await?;
+            removed += result.rows_affected();
+        }
+
+        if removed > 0 {
+            tracing::debug!("Pruned {} expired metrics buckets", removed);
+        }
+
+        Ok(removed)
+    }
+
+    fn point_from_row(row: &sqlx::sqlite::SqliteRow) -> SyncResult<MetricsPoint> {
+        let resolution: String = row.get("resolution");
+
+        Ok(MetricsPoint {
+            container_id: row.get("container_id"),
+            resolution: MetricsResolution::from_string(&resolution)?,
+            bucket_start: row.get("bucket_start"),
+            samples: row.get("samples"),
+            cpu_percent_avg: row.get("cpu_percent_avg"),
+            cpu_percent_max: row.get("cpu_percent_max"),
+            cpu_usage_usec: row.get("cpu_usage_usec"),
+            memory_bytes_avg: row.get("memory_bytes_avg"),
+            memory_bytes_max: row.get("memory_bytes_max"),
+            io_read_bytes: row.get("io_read_bytes"),
+            io_write_bytes: row.get("io_write_bytes"),
+            net_rx_bytes: row.get("net_rx_bytes"),
+            net_tx_bytes: row.get("net_tx_bytes"),
+            pids_max: row.get("pids_max"),
+        })
+    }
+}
+
+fn cpu_percent_between(prev_usage_usec: i64, usage_usec: i64, elapsed_secs: i64) -> f64 {
+    // A counter reset means the cgroup was recreated; skip rather than report garbage
+    if elapsed_secs <= 0 || usage_usec < prev_usage_usec {
+        return 0.0;
+    }
+    (usage_usec - prev_usage_usec) as f64 / (elapsed_secs as f64 * 1_000_000.0) * 100.0
+}
+
+/// Reads container counters from cgroups (v2, falling back to v1) and host-side veths
+pub struct ResourceSampler {
+    cgroup_root: PathBuf,
+    sys_class_net: PathBuf,
+}
+
+impl Default for ResourceSampler {
+    fn default() -> Self {
+        Self::new(PathBuf::from("/sys/fs/cgroup"), PathBuf::from("/sys/class/net"))
+    }
+}
+
+impl ResourceSampler {
+    pub fn new(cgroup_root: PathBuf, sys_class_net: PathBuf) -> Self {
+        Self { cgroup_root, sys_class_net }
+    }
+
+    /// Sample a container, or None when it has no cgroup (not started or already gone)
+    pub fn sample(&self, container_id: &str, host_veths: &[String], timestamp: i64) -> Option<ResourceSample> {
+        let mut sample = if self.cgroup_root.join("cgroup.controllers").exists() {
+            self.sample_cgroup_v2(container_id)?
+        } else {
+            self.sample_cgroup_v1(container_id)?
+        };
+
+        // Host side of the veth pair: what the host receives the container transmitted
+        for veth in host_veths {
+            let stats = self.sys_class_net.join(veth).join("statistics");
+            sample.net_rx_bytes += read_i64(&stats.join("tx_bytes")).unwrap_or(0);
+            sample.net_tx_bytes += read_i64(&stats.join("rx_bytes")).unwrap_or(0);
+        }
+
+        sample.container_id = container_id.to_string();
+        sample.timestamp = timestamp;
+        Some(sample)
+    }
+
+    fn sample_cgroup_v2(&self, container_id: &str) -> Option<ResourceSample> {
+        let cgroup = self.cgroup_root.join("quilt").join(container_id);
+        if !cgroup.is_dir() {
+            return None;
+        }
+
+        let (io_read_bytes, io_write_bytes) = std::fs::read_to_string(cgroup.join("io.stat"))
+            .map(|content| parse_io_stat(&content))
+            .unwrap_or((0, 0));
+
+        Some(ResourceSample {
+            cpu_usage_usec: std::fs::read_to_string(cgroup.join("cpu.stat"))
+                .ok()
+                .and_then(|content| parse_keyed_value(&content, "usage_usec"))
+                .unwrap_or(0),
+            memory_bytes: read_i64(&cgroup.join("memory.current")).unwrap_or(0),
+            io_read_bytes,
+            io_write_bytes,
+            pids: read_i64(&cgroup.join("pids.current")).unwrap_or(0),
+            ..Default::default()
+        })
+    }
+
+    fn sample_cgroup_v1(&self, container_id: &str) -> Option<ResourceSample> {
+        let memory = self.cgroup_root.join("memory/quilt").join(container_id);
+        let cpuacct = self.cgroup_root.join("cpuacct/quilt").join(container_id);
+        let blkio = self.cgroup_root.join("blkio/quilt").join(container_id);
+        let pids = self.cgroup_root.join("pids/quilt").join(container_id);
+        if !memory.is_dir() && !cpuacct.is_dir() {
+            return None;
+        }
+
+        let (io_read_bytes, io_write_bytes) = std::fs::read_to_string(blkio.join("blkio.throttle.io_service_bytes"))
+            .map(|content| parse_blkio_service_bytes(&content))
+            .unwrap_or((0, 0));
+
+        Some(ResourceSample {
+            // cpuacct.usage is in nanoseconds
+            cpu_usage_usec: read_i64(&cpuacct.join("cpuacct.usage")).unwrap_or(0) / 1000,
+            memory_bytes: read_i64(&memory.join("memory.usage_in_bytes")).unwrap_or(0),
+            io_read_bytes,
+            io_write_bytes,
+            pids: read_i64(&pids.join("pids.current")).unwrap_or(0),
+            ..Default::default()
+        })
+    }
+}
+
+fn read_i64(path: &std::path::Path) -> Option<i64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Value of `key` in a flat-keyed file such as cgroup v2 `cpu.stat`
+fn parse_keyed_value(content: &str, key: &str) -> Option<i64> {
+    content.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        if parts.next()? == key {
+            parts.next()?.parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// Sum `rbytes`/`wbytes` over every device in cgroup v2 `io.stat`
+fn parse_io_stat(content: &str) -> (i64, i64) {
+    let mut read = 0;
+    let mut write = 0;
+    for field in content.split_whitespace() {
+        if let Some(value) = field.strip_prefix("rbytes=") {
+            read += value.parse::<i64>().unwrap_or(0);
+        } else if let Some(value) = field.strip_prefix("wbytes=") {
+            write += value.parse::<i64>().unwrap_or(0);
+        }
+    }
+    (read, write)
+}
+
+/// Sum Read/Write over every device in cgroup v1 `blkio.throttle.io_service_bytes`
+fn parse_blkio_service_bytes(content: &str) -> (i64, i64) {
+    let mut read = 0;
+    let mut write = 0;
+    for line in content.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if let [_, op, value] = parts.as_slice() {
+            match *op {
+                "Read" => read += value.parse::<i64>().unwrap_or(0),
+                "Write" => write += value.parse::<i64>().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+    (read, write)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::connection::ConnectionManager;
+    use crate::sync::schema::SchemaManager;
+    use tempfile::{NamedTempFile, TempDir};
+
+    async fn setup_test_db() -> (ConnectionManager, MetricsManager) {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = temp_file.path().to_str().unwrap();
+
+        let conn_manager = ConnectionManager::new(db_path).await.unwrap();
+        let schema_manager = SchemaManager::new(conn_manager.pool().clone());
+        schema_manager.initialize_schema().await.unwrap();
+
+        let metrics_manager = MetricsManager::new(conn_manager.pool().clone());
+
+        (conn_manager, metrics_manager)
+    }
+
+    fn sample_at(timestamp: i64, cpu_usage_usec: i64, memory_bytes: i64) -> ResourceSample {
+        ResourceSample {
+            container_id: "metrics-container".to_string(),
+            timestamp,
+            cpu_usage_usec,
+            memory_bytes,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_and_downsample() {
+        let (_conn, metrics_manager) = setup_test_db().await;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let base = MetricsResolution::Minute.bucket_start(now) - 120;
+
+        metrics_manager.record_sample(&sample_at(base, 0, 100)).await.unwrap();
+        // 0.5s of CPU over 10s = 5%
+        let cpu = metrics_manager.record_sample(&sample_at(base + 10, 500_000, 300)).await.unwrap();
+        assert!((cpu - 5.0).abs() < 1e-9);
+
+        let query = MetricsQuery {
+            container_id: "metrics-container".to_string(),
+            start_time: base,
+            end_time: now,
+            resolution: Some(MetricsResolution::Raw),
+            limit: None,
+        };
+        let raw = metrics_manager.query(&query).await.unwrap();
+        assert_eq!(raw.len(), 2);
+        assert_eq!(raw[1].cpu_usage_usec, 500_000);
+
+        let minute = metrics_manager
+            .query(&MetricsQuery { resolution: Some(MetricsResolution::Minute), ..query.clone() })
+            .await
+            .unwrap();
+        assert_eq!(minute.len(), 1);
+        assert_eq!(minute[0].samples, 2);
+        assert_eq!(minute[0].memory_bytes_avg, 200);
+        assert_eq!(minute[0].memory_bytes_max, 300);
+        assert!((minute[0].cpu_percent_max - 5.0).abs() < 1e-9);
+
+        // Auto resolution for a recent range is raw
+        let auto = metrics_manager.query(&MetricsQuery { resolution: None, ..query }).await.unwrap();
+        assert_eq!(auto.len(), 2);
+    }
+
+    #[test]
+    fn test_resolution_for_range() {
+        let now = 1_000_000;
+        assert_eq!(MetricsResolution::for_range(now - 60, now), MetricsResolution::Raw);
+        assert_eq!(MetricsResolution::for_range(now - 7200, now), MetricsResolution::Minute);
+        assert_eq!(MetricsResolution::for_range(now - 7 * 86400, now), MetricsResolution::Hour);
+        assert_eq!(MetricsResolution::Hour.bucket_start(7201), 7200);
+    }
+
+    #[test]
+    fn test_sample_cgroup_v2() {
+        let cgroup_root = TempDir::new().unwrap();
+        let net_root = TempDir::new().unwrap();
+        std::fs::write(cgroup_root.path().join("cgroup.controllers"), "cpu memory io pids").unwrap();
+
+        let cgroup = cgroup_root.path().join("quilt").join("c1");
+        std::fs::create_dir_all(&cgroup).unwrap();
+        std::fs::write(cgroup.join("cpu.stat"), "usage_usec 1234\nuser_usec 1000\nsystem_usec 234\n").unwrap();
+        std::fs::write(cgroup.join("memory.current"), "4096\n").unwrap();
+        std::fs::write(cgroup.join("io.stat"), "8:0 rbytes=10 wbytes=20 rios=1 wios=2\n8:16 rbytes=5 wbytes=5\n").unwrap();
+        std::fs::write(cgroup.join("pids.current"), "3\n").unwrap();
+
+        let stats = net_root.path().join("veth-c1").join("statistics");
+        std::fs::create_dir_all(&stats).unwrap();
+        std::fs::write(stats.join("rx_bytes"), "100\n").unwrap();
+        std::fs::write(stats.join("tx_bytes"), "700\n").unwrap();
+
+        let sampler = ResourceSampler::new(cgroup_root.path().to_path_buf(), net_root.path().to_path_buf());
+        let sample = sampler.sample("c1", &["veth-c1".to_string()], 42).unwrap();
+
+        assert_eq!(sample.cpu_usage_usec, 1234);
+        assert_eq!(sample.memory_bytes, 4096);
+        assert_eq!((sample.io_read_bytes, sample.io_write_bytes), (15, 25));
+        assert_eq!(sample.pids, 3);
+        assert_eq!((sample.net_rx_bytes, sample.net_tx_bytes), (700, 100));
+        assert!(sampler.sample("missing", &[], 42).is_none());
+    }
+}
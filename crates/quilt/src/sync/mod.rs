@@ -6,12 +6,20 @@ pub mod network;
 pub mod monitor;
 pub mod cleanup;
 pub mod async_tasks;
+pub mod reconcile;
+pub mod metrics;
+pub mod quotas;
+pub mod templates;
 pub mod error;
 
 pub use engine::SyncEngine;
 pub use error::SyncError;
 pub use containers::ContainerState;
-pub use network::NetworkConfig;
+pub use network::{NetworkConfig, NetworkDefinition, NetworkSpec};
 pub use monitor::ProcessMonitorService;
-pub use cleanup::CleanupService; 
+pub use cleanup::CleanupService;
+pub use reconcile::ReconciliationReport;
+pub use metrics::{MetricsPoint, MetricsQuery, MetricsResolution};
+pub use quotas::{OwnerQuota, OwnerUsage};
+pub use templates::{ContainerTemplate, TemplateSpec};
 pub use async_tasks::{AsyncTaskManager, AsyncTask, AsyncTaskStatus}; 
\ No newline at end of file
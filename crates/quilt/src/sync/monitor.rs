@@ -79,6 +79,17 @@ impl ProcessMonitorService {
     }
     
     pub async fn start_monitoring(&self, container_id: &str, pid: Pid) -> SyncResult<()> {
+        self.spawn_monitor(container_id, pid, false).await
+    }
+    
+    /// Monitor a process the daemon did not spawn itself (e.g. one that survived a
+    /// daemon restart). Such processes cannot be reaped, so their exit code is
+    /// unknown; when they disappear the container row is marked exited directly.
+    pub async fn adopt_process(&self, container_id: &str, pid: Pid) -> SyncResult<()> {
+        self.spawn_monitor(container_id, pid, true).await
+    }
+    
+    async fn spawn_monitor(&self, container_id: &str, pid: Pid, adopted: bool) -> SyncResult<()> {
         // Check if already monitoring
         {
             let active = self.active_monitors.lock().await;
@@ -104,7 +115,8 @@ impl ProcessMonitorService {
         let check_interval = self.check_interval;
         
         tokio::spawn(async move {
-            tracing::info!("Started background monitoring for container {} (PID: {})", container_id, pid);
+            tracing::info!("Started background monitoring for container {} (PID: {}{})",
+                          container_id, pid, if adopted { ", adopted" } else { "" });
             
             loop {
                 match Self::check_process_status(pid).await {
@@ -124,6 +136,12 @@ impl ProcessMonitorService {
                             tracing::error!("Failed to mark process monitor completed for {}: {}", container_id, e);
                         }
                         
+                        if adopted {
+                            if let Err(e) = Self::mark_adopted_container_exited(&pool, &container_id).await {
+                                tracing::error!("Failed to mark adopted container {} exited: {}", container_id, e);
+                            }
+                        }
+                        
                         // Remove from active monitors
                         {
                             let mut active = active_monitors.lock().await;
@@ -292,6 +310,33 @@ impl ProcessMonitorService {
         Ok(())
     }
     
+    async fn mark_adopted_container_exited(pool: &SqlitePool, container_id: &str) -> SyncResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        
+        let result = sqlx::query(r#"
+            UPDATE containers SET state = 'exited', exited_at = ?, pid = NULL, updated_at = ?
+            WHERE id = ? AND state = 'running'
+        "#)
+        .bind(now)
+        .bind(now)
+        .bind(container_id)
+        .execute(pool)
+        .await?;
+        
+        if result.rows_affected() > 0 {
+            sqlx::query(r#"
+                INSERT INTO container_logs (container_id, timestamp, level, message)
+                VALUES (?, ?, 'warn', 'Adopted process exited; exit code unavailable after daemon restart')
+            "#)
+            .bind(container_id)
+            .bind(now)
+            .execute(pool)
+            .await?;
+        }
+        
+        Ok(())
+    }
+    
     async fn fail_process_monitor(pool: &SqlitePool, container_id: &str, error_message: &str) -> SyncResult<()> {
         sqlx::query("UPDATE process_monitors SET status = ? WHERE container_id = ?")
             .bind(MonitorStatus::Failed.to_string())
@@ -326,8 +371,12 @@ impl ProcessMonitorService {
                 ProcessStatus::Exited(1) // Treat other statuses as generic failure
             },
             Err(nix::errno::Errno::ECHILD) => {
-                // Process doesn't exist or is not a child
-                ProcessStatus::Exited(0) // Assume it exited normally
+                // Not our child (adopted after a restart) - fall back to procfs
+                if std::path::Path::new(&format!("/proc/{}", pid)).exists() {
+                    ProcessStatus::Running
+                } else {
+                    ProcessStatus::Exited(0) // Assume it exited normally
+                }
             },
             Err(e) => {
                 tracing::error!("Error checking process status for {}: {}", pid, e);
@@ -35,9 +35,83 @@ impl NetworkStatus {
     }
 }
 
+/// Name of the network every container joins when no networks are requested
+pub const DEFAULT_NETWORK_NAME: &str = "default";
+
+/// Bridge backing the default network
+pub const DEFAULT_BRIDGE_NAME: &str = "quilt0";
+
+/// Networks one container may join; keeps per-attachment interface names within 15 characters
+pub const MAX_NETWORK_ATTACHMENTS: usize = 100;
+
+/// A user-defined L2 segment with its own bridge, subnet and IPAM range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkDefinition {
+    pub name: String,
+    pub bridge_name: String,
+    pub subnet_cidr: String,
+    pub gateway_ip: String,
+    pub ip_range_start: String,
+    pub ip_range_end: String,
+    pub is_default: bool,
+    pub created_at: i64,
+}
+
+/// Parameters for creating a network; unset fields are derived from the subnet
+#[derive(Debug, Clone, Default)]
+pub struct NetworkSpec {
+    pub name: String,
+    pub subnet_cidr: String,
+    pub bridge_name: Option<String>,
+    pub gateway_ip: Option<String>,
+    pub ip_range_start: Option<String>,
+    pub ip_range_end: Option<String>,
+}
+
+impl NetworkDefinition {
+    /// Prefix length of the subnet, used for bridge and container addresses
+    pub fn prefix_len(&self) -> SyncResult<u8> {
+        parse_subnet(&self.subnet_cidr).map(|(_, prefix)| prefix)
+    }
+}
+
+/// Parse an IPv4 CIDR into its network address and prefix length
+pub fn parse_subnet(cidr: &str) -> SyncResult<(Ipv4Addr, u8)> {
+    let (addr, prefix) = cidr.split_once('/').ok_or_else(|| SyncError::ValidationFailed {
+        message: format!("Subnet '{}' must be in CIDR notation (e.g. 10.50.0.0/24)", cidr),
+    })?;
+    
+    let addr: Ipv4Addr = addr.parse().map_err(|_| SyncError::ValidationFailed {
+        message: format!("Invalid subnet address: {}", addr),
+    })?;
+    let prefix: u8 = prefix.parse().map_err(|_| SyncError::ValidationFailed {
+        message: format!("Invalid subnet prefix: {}", prefix),
+    })?;
+    
+    if !(8..=30).contains(&prefix) {
+        return Err(SyncError::ValidationFailed {
+            message: format!("Subnet prefix /{} out of range (/8 - /30)", prefix),
+        });
+    }
+    
+    let mask = u32::MAX << (32 - prefix);
+    Ok((Ipv4Addr::from(u32::from(addr) & mask), prefix))
+}
+
+fn subnet_contains(network: Ipv4Addr, prefix: u8, ip: Ipv4Addr) -> bool {
+    let mask = u32::MAX << (32 - prefix);
+    u32::from(ip) & mask == u32::from(network)
+}
+
+fn subnets_overlap(a: (Ipv4Addr, u8), b: (Ipv4Addr, u8)) -> bool {
+    let prefix = a.1.min(b.1);
+    subnet_contains(a.0, prefix, b.0) || subnet_contains(b.0, prefix, a.0)
+}
+
 #[derive(Debug, Clone)]
 pub struct NetworkConfig {
     pub container_id: String,
+    pub network_name: String,
     pub ip_address: String,
     pub bridge_interface: Option<String>,
     pub veth_host: Option<String>,
@@ -48,6 +122,7 @@ pub struct NetworkConfig {
 #[derive(Debug, Clone)]
 pub struct NetworkAllocation {
     pub container_id: String,
+    pub network_name: String,
     pub ip_address: String,
     pub bridge_interface: Option<String>,
     pub veth_host: Option<String>,
@@ -72,6 +147,7 @@ impl NetworkManager {
         }
     }
     
+    /// Override the allocation range of the default network
     pub fn with_ip_range(pool: SqlitePool, start: Ipv4Addr, end: Ipv4Addr) -> Self {
         Self {
             pool,
@@ -80,12 +156,233 @@ impl NetworkManager {
         }
     }
     
+    // === Network Definitions ===
+    
+    /// Create a new network with its own bridge, subnet and IPAM range
+    pub async fn create_network(&self, spec: NetworkSpec) -> SyncResult<NetworkDefinition> {
+        if spec.name.is_empty() || !spec.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(SyncError::ValidationFailed {
+                message: format!("Invalid network name '{}': use letters, digits, '-' or '_'", spec.name),
+            });
+        }
+        
+        if self.get_network(&spec.name).await.is_ok() {
+            return Err(SyncError::ValidationFailed {
+                message: format!("Network '{}' already exists", spec.name),
+            });
+        }
+        
+        let (network_addr, prefix) = parse_subnet(&spec.subnet_cidr)?;
+        let network_int = u32::from(network_addr);
+        let broadcast_int = network_int | !(u32::MAX << (32 - prefix));
+        
+        // Reject subnets that overlap an existing network - bridges would fight over routes
+        for existing in self.list_networks().await? {
+            if subnets_overlap((network_addr, prefix), parse_subnet(&existing.subnet_cidr)?) {
+                return Err(SyncError::ValidationFailed {
+                    message: format!("Subnet {} overlaps network '{}' ({})", spec.subnet_cidr, existing.name, existing.subnet_cidr),
+                });
+            }
+        }
+        
+        let parse_in_subnet = |value: &str, field: &str| -> SyncResult<Ipv4Addr> {
+            let ip: Ipv4Addr = value.parse().map_err(|_| SyncError::ValidationFailed {
+                message: format!("Invalid {}: {}", field, value),
+            })?;
+            if !subnet_contains(network_addr, prefix, ip) || u32::from(ip) == network_int || u32::from(ip) == broadcast_int {
+                return Err(SyncError::ValidationFailed {
+                    message: format!("{} {} is not a usable address in {}", field, value, spec.subnet_cidr),
+                });
+            }
+            Ok(ip)
+        };
+        
+        let gateway = match &spec.gateway_ip {
+            Some(gw) => parse_in_subnet(gw, "gateway")?,
+            None => Ipv4Addr::from(network_int + 1),
+        };
+        let range_start = match &spec.ip_range_start {
+            Some(ip) => parse_in_subnet(ip, "range start")?,
+            None => Ipv4Addr::from(network_int + 2),
+        };
+        let range_end = match &spec.ip_range_end {
+            Some(ip) => parse_in_subnet(ip, "range end")?,
+            None => Ipv4Addr::from(broadcast_int - 1),
+        };
+        
+        if u32::from(range_start) > u32::from(range_end) {
+            return Err(SyncError::ValidationFailed {
+                message: format!("IP range {} - {} is empty", range_start, range_end),
+            });
+        }
+        
+        // Linux interface names are limited to 15 characters
+        let bridge_name = spec.bridge_name.clone().unwrap_or_else(|| {
+            let mut name = format!("qbr-{}", spec.name);
+            name.truncate(15);
+            name
+        });
+        if bridge_name.is_empty() || bridge_name.len() > 15 {
+            return Err(SyncError::ValidationFailed {
+                message: format!("Bridge name '{}' must be 1-15 characters", bridge_name),
+            });
+        }
+        
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let definition = NetworkDefinition {
+            name: spec.name.clone(),
+            bridge_name,
+            subnet_cidr: format!("{}/{}", network_addr, prefix),
+            gateway_ip: gateway.to_string(),
+            ip_range_start: range_start.to_string(),
+            ip_range_end: range_end.to_string(),
+            is_default: false,
+            created_at: now,
+        };
+        
+        sqlx::query(r#"
+            INSERT INTO networks (
+                name, bridge_name, subnet_cidr, gateway_ip, ip_range_start, ip_range_end, is_default, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#)
+        .bind(&definition.name)
+        .bind(&definition.bridge_name)
+        .bind(&definition.subnet_cidr)
+        .bind(&definition.gateway_ip)
+        .bind(&definition.ip_range_start)
+        .bind(&definition.ip_range_end)
+        .bind(definition.is_default)
+        .bind(definition.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db) if db.message().contains("UNIQUE") => SyncError::ValidationFailed {
+                message: format!("Bridge '{}' or subnet {} is already in use", definition.bridge_name, definition.subnet_cidr),
+            },
+            other => SyncError::Database(other),
+        })?;
+        
+        tracing::info!("Created network {} ({} on bridge {})", definition.name, definition.subnet_cidr, definition.bridge_name);
+        Ok(definition)
+    }
+    
+    pub async fn get_network(&self, name: &str) -> SyncResult<NetworkDefinition> {
+        let row = sqlx::query(r#"
+            SELECT name, bridge_name, subnet_cidr, gateway_ip, ip_range_start, ip_range_end, is_default, created_at
+            FROM networks WHERE name = ?
+        "#)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        match row {
+            Some(row) => Ok(Self::network_from_row(&row)),
+            None => Err(SyncError::NetworkNotFound {
+                name: name.to_string(),
+            }),
+        }
+    }
+    
+    pub async fn list_networks(&self) -> SyncResult<Vec<NetworkDefinition>> {
+        let rows = sqlx::query(r#"
+            SELECT name, bridge_name, subnet_cidr, gateway_ip, ip_range_start, ip_range_end, is_default, created_at
+            FROM networks ORDER BY is_default DESC, created_at ASC
+        "#)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(rows.iter().map(Self::network_from_row).collect())
+    }
+    
+    /// Remove a network; fails while containers still hold allocations on it
+    pub async fn remove_network(&self, name: &str) -> SyncResult<NetworkDefinition> {
+        let network = self.get_network(name).await?;
+        
+        if network.is_default {
+            return Err(SyncError::ValidationFailed {
+                message: "The default network cannot be removed".to_string(),
+            });
+        }
+        
+        let attached: Vec<(String,)> = sqlx::query_as(
+            "SELECT container_id FROM network_allocations WHERE network_name = ? AND status != 'cleaned'"
+        )
+        .bind(name)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        if !attached.is_empty() {
+            return Err(SyncError::NetworkInUse {
+                name: name.to_string(),
+                containers: attached.into_iter().map(|(id,)| id).collect(),
+            });
+        }
+        
+        // Cleaned allocations only exist for bookkeeping - drop them with the network
+        sqlx::query("DELETE FROM network_allocations WHERE network_name = ?")
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM networks WHERE name = ?")
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        
+        tracing::info!("Removed network {}", name);
+        Ok(network)
+    }
+    
+    fn network_from_row(row: &sqlx::sqlite::SqliteRow) -> NetworkDefinition {
+        NetworkDefinition {
+            name: row.get("name"),
+            bridge_name: row.get("bridge_name"),
+            subnet_cidr: row.get("subnet_cidr"),
+            gateway_ip: row.get("gateway_ip"),
+            ip_range_start: row.get("ip_range_start"),
+            ip_range_end: row.get("ip_range_end"),
+            is_default: row.get("is_default"),
+            created_at: row.get("created_at"),
+        }
+    }
+    
+    /// Resolve requested network names, falling back to the default network
+    pub async fn resolve_networks(&self, names: &[String]) -> SyncResult<Vec<NetworkDefinition>> {
+        let names: Vec<&str> = if names.is_empty() {
+            vec![DEFAULT_NETWORK_NAME]
+        } else {
+            names.iter().map(|n| n.as_str()).collect()
+        };
+        
+        let mut networks = Vec::with_capacity(names.len());
+        for name in names {
+            if networks.iter().any(|n: &NetworkDefinition| n.name == name) {
+                continue; // Attaching twice to one segment is a no-op
+            }
+            networks.push(self.get_network(name).await?);
+        }
+        if networks.len() > MAX_NETWORK_ATTACHMENTS {
+            return Err(SyncError::ValidationFailed {
+                message: format!("A container can join at most {} networks", MAX_NETWORK_ATTACHMENTS),
+            });
+        }
+        Ok(networks)
+    }
+    
+    // === Allocations ===
+    
+    /// Allocate an address on the default network
     pub async fn allocate_network(&self, container_id: &str) -> SyncResult<NetworkConfig> {
+        self.allocate_on_network(container_id, DEFAULT_NETWORK_NAME).await
+    }
+    
+    /// Allocate an address for a container on a specific network
+    pub async fn allocate_on_network(&self, container_id: &str, network_name: &str) -> SyncResult<NetworkConfig> {
         // Check if already allocated
-        if let Ok(existing) = self.get_network_allocation(container_id).await {
-            tracing::debug!("Container {} already has network allocation: {}", container_id, existing.ip_address);
+        if let Ok(existing) = self.get_attachment(container_id, network_name).await {
+            tracing::debug!("Container {} already has allocation on {}: {}", container_id, network_name, existing.ip_address);
             return Ok(NetworkConfig {
                 container_id: container_id.to_string(),
+                network_name: existing.network_name,
                 ip_address: existing.ip_address,
                 bridge_interface: existing.bridge_interface,
                 veth_host: existing.veth_host,
@@ -94,34 +391,86 @@ impl NetworkManager {
             });
         }
         
-        let ip = self.find_available_ip().await?;
+        let network = self.get_network(network_name).await?;
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let mut conn = self.pool.acquire().await?;
+        
+        self.allocate_in(&mut *conn, container_id, &network, now).await
+    }
+    
+    /// Allocate the next free address on `network` using the caller's connection,
+    /// so container creation can allocate inside its own transaction
+    pub async fn allocate_in(
+        &self,
+        conn: &mut sqlx::SqliteConnection,
+        container_id: &str,
+        network: &NetworkDefinition,
+        now: i64,
+    ) -> SyncResult<NetworkConfig> {
+        let allocated_ips: Vec<(String,)> = sqlx::query_as(
+            "SELECT ip_address FROM network_allocations WHERE network_name = ? AND status != 'cleaned'"
+        )
+        .bind(&network.name)
+        .fetch_all(&mut *conn)
+        .await?;
+        
+        let allocated_set: std::collections::HashSet<String> = allocated_ips
+            .into_iter()
+            .map(|(ip,)| ip)
+            .collect();
+        
+        let (start, end) = self.effective_range(network)?;
+        let ip = (u32::from(start)..=u32::from(end))
+            .map(|ip_int| Ipv4Addr::from(ip_int).to_string())
+            .find(|ip| !allocated_set.contains(ip) && *ip != network.gateway_ip)
+            .ok_or(SyncError::NoAvailableIp)?;
         
         sqlx::query(r#"
             INSERT INTO network_allocations (
-                container_id, ip_address, allocation_time, setup_completed, status
-            ) VALUES (?, ?, ?, ?, ?)
+                container_id, network_name, ip_address, bridge_interface, allocation_time, setup_completed, status
+            ) VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(container_id, network_name) DO UPDATE SET
+                ip_address = excluded.ip_address,
+                allocation_time = excluded.allocation_time,
+                setup_completed = 0,
+                status = excluded.status,
+                veth_host = NULL,
+                veth_container = NULL
         "#)
         .bind(container_id)
+        .bind(&network.name)
         .bind(&ip)
+        .bind(&network.bridge_name)
         .bind(now)
         .bind(false)
         .bind(NetworkStatus::Allocated.to_string())
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
         
-        tracing::info!("Allocated IP {} for container {}", ip, container_id);
+        tracing::info!("Allocated IP {} on network {} for container {}", ip, network.name, container_id);
         
         Ok(NetworkConfig {
             container_id: container_id.to_string(),
+            network_name: network.name.clone(),
             ip_address: ip,
-            bridge_interface: None,
+            bridge_interface: Some(network.bridge_name.clone()),
             veth_host: None,
             veth_container: None,
             setup_required: true,
         })
     }
     
+    fn effective_range(&self, network: &NetworkDefinition) -> SyncResult<(Ipv4Addr, Ipv4Addr)> {
+        if network.is_default {
+            return Ok((self.ip_range_start, self.ip_range_end));
+        }
+        
+        let parse = |value: &str| value.parse::<Ipv4Addr>().map_err(|_| SyncError::ValidationFailed {
+            message: format!("Network {} has invalid range address {}", network.name, value),
+        });
+        Ok((parse(&network.ip_range_start)?, parse(&network.ip_range_end)?))
+    }
+    
     pub async fn mark_network_disabled(&self, container_id: &str) -> SyncResult<()> {
         // For containers with networking disabled, we don't allocate IPs
         // This is tracked by the absence of entries in network_allocations table
@@ -140,11 +489,24 @@ impl NetworkManager {
         Ok(count > 0)
     }
     
+    /// Mark setup complete on the container's primary (first) attachment
     pub async fn mark_network_setup_complete(&self, container_id: &str, bridge_interface: &str, veth_host: &str, veth_container: &str) -> SyncResult<()> {
+        let primary = self.get_network_allocation(container_id).await?;
+        self.mark_attachment_setup_complete(container_id, &primary.network_name, bridge_interface, veth_host, veth_container).await
+    }
+    
+    pub async fn mark_attachment_setup_complete(
+        &self,
+        container_id: &str,
+        network_name: &str,
+        bridge_interface: &str,
+        veth_host: &str,
+        veth_container: &str,
+    ) -> SyncResult<()> {
         let result = sqlx::query(r#"
             UPDATE network_allocations 
             SET setup_completed = ?, status = ?, bridge_interface = ?, veth_host = ?, veth_container = ?
-            WHERE container_id = ?
+            WHERE container_id = ? AND network_name = ?
         "#)
         .bind(true)
         .bind(NetworkStatus::Active.to_string())
@@ -152,6 +514,7 @@ impl NetworkManager {
         .bind(veth_host)
         .bind(veth_container)
         .bind(container_id)
+        .bind(network_name)
         .execute(&self.pool)
         .await?;
         
@@ -161,44 +524,76 @@ impl NetworkManager {
             });
         }
         
-        tracing::info!("Marked network setup complete for container {}", container_id);
+        tracing::info!("Marked network {} setup complete for container {}", network_name, container_id);
         Ok(())
     }
     
+    /// Get the container's primary allocation (the first network it was attached to)
     pub async fn get_network_allocation(&self, container_id: &str) -> SyncResult<NetworkAllocation> {
+        self.list_container_allocations(container_id)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| SyncError::NotFound {
+                container_id: container_id.to_string(),
+            })
+    }
+    
+    /// Get the container's allocation on a specific network
+    pub async fn get_attachment(&self, container_id: &str, network_name: &str) -> SyncResult<NetworkAllocation> {
         let row = sqlx::query(r#"
-            SELECT container_id, ip_address, bridge_interface, veth_host, veth_container,
+            SELECT container_id, network_name, ip_address, bridge_interface, veth_host, veth_container,
                    allocation_time, setup_completed, status
-            FROM network_allocations WHERE container_id = ?
+            FROM network_allocations WHERE container_id = ? AND network_name = ?
         "#)
         .bind(container_id)
+        .bind(network_name)
         .fetch_optional(&self.pool)
         .await?;
         
         match row {
-            Some(row) => {
-                let status_str: String = row.get("status");
-                let status = NetworkStatus::from_string(&status_str)?;
-                
-                Ok(NetworkAllocation {
-                    container_id: row.get("container_id"),
-                    ip_address: row.get("ip_address"),
-                    bridge_interface: row.get("bridge_interface"),
-                    veth_host: row.get("veth_host"),
-                    veth_container: row.get("veth_container"),
-                    allocation_time: row.get("allocation_time"),
-                    setup_completed: row.get("setup_completed"),
-                    status,
-                })
-            }
+            Some(row) => Self::allocation_from_row(&row),
             None => Err(SyncError::NotFound {
                 container_id: container_id.to_string(),
             }),
         }
     }
     
+    /// List every network attachment of a container, primary first
+    pub async fn list_container_allocations(&self, container_id: &str) -> SyncResult<Vec<NetworkAllocation>> {
+        let rows = sqlx::query(r#"
+            SELECT container_id, network_name, ip_address, bridge_interface, veth_host, veth_container,
+                   allocation_time, setup_completed, status
+            FROM network_allocations WHERE container_id = ?
+            ORDER BY allocation_time ASC, rowid ASC
+        "#)
+        .bind(container_id)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        rows.iter().map(Self::allocation_from_row).collect()
+    }
+    
+    fn allocation_from_row(row: &sqlx::sqlite::SqliteRow) -> SyncResult<NetworkAllocation> {
+        let status_str: String = row.get("status");
+        let status = NetworkStatus::from_string(&status_str)?;
+        
+        Ok(NetworkAllocation {
+            container_id: row.get("container_id"),
+            network_name: row.get("network_name"),
+            ip_address: row.get("ip_address"),
+            bridge_interface: row.get("bridge_interface"),
+            veth_host: row.get("veth_host"),
+            veth_container: row.get("veth_container"),
+            allocation_time: row.get("allocation_time"),
+            setup_completed: row.get("setup_completed"),
+            status,
+        })
+    }
+    
+    /// Mark all of a container's attachments for cleanup
     pub async fn mark_network_cleanup_pending(&self, container_id: &str) -> SyncResult<()> {
-        let result = sqlx::query("UPDATE network_allocations SET status = ? WHERE container_id = ?")
+        let result = sqlx::query("UPDATE network_allocations SET status = ? WHERE container_id = ? AND status != 'cleaned'")
             .bind(NetworkStatus::CleanupPending.to_string())
             .bind(container_id)
             .execute(&self.pool)
@@ -213,6 +608,7 @@ impl NetworkManager {
         Ok(())
     }
     
+    /// Mark all of a container's attachments cleaned, releasing their addresses
     pub async fn mark_network_cleaned(&self, container_id: &str) -> SyncResult<()> {
         let result = sqlx::query("UPDATE network_allocations SET status = ? WHERE container_id = ?")
             .bind(NetworkStatus::Cleaned.to_string())
@@ -242,13 +638,13 @@ impl NetworkManager {
             });
         }
         
-        tracing::info!("Deleted network allocation for container {}", container_id);
+        tracing::info!("Deleted network allocations for container {}", container_id);
         Ok(())
     }
     
     pub async fn list_allocations(&self, status_filter: Option<NetworkStatus>) -> SyncResult<Vec<NetworkAllocation>> {
         let mut query = "
-            SELECT container_id, ip_address, bridge_interface, veth_host, veth_container,
+            SELECT container_id, network_name, ip_address, bridge_interface, veth_host, veth_container,
                    allocation_time, setup_completed, status
             FROM network_allocations
         ".to_string();
@@ -260,58 +656,28 @@ impl NetworkManager {
         query.push_str(" ORDER BY allocation_time ASC");
         
         let rows = sqlx::query(&query).fetch_all(&self.pool).await?;
+        rows.iter().map(Self::allocation_from_row).collect()
+    }
+    
+    /// List live allocations on a single network
+    pub async fn list_network_allocations(&self, network_name: &str) -> SyncResult<Vec<NetworkAllocation>> {
+        let rows = sqlx::query(r#"
+            SELECT container_id, network_name, ip_address, bridge_interface, veth_host, veth_container,
+                   allocation_time, setup_completed, status
+            FROM network_allocations WHERE network_name = ? AND status != 'cleaned'
+            ORDER BY allocation_time ASC
+        "#)
+        .bind(network_name)
+        .fetch_all(&self.pool)
+        .await?;
         
-        let mut allocations = Vec::new();
-        for row in rows {
-            let status_str: String = row.get("status");
-            let status = NetworkStatus::from_string(&status_str)?;
-            
-            allocations.push(NetworkAllocation {
-                container_id: row.get("container_id"),
-                ip_address: row.get("ip_address"),
-                bridge_interface: row.get("bridge_interface"),
-                veth_host: row.get("veth_host"),
-                veth_container: row.get("veth_container"),
-                allocation_time: row.get("allocation_time"),
-                setup_completed: row.get("setup_completed"),
-                status,
-            });
-        }
-        
-        Ok(allocations)
+        rows.iter().map(Self::allocation_from_row).collect()
     }
     
     pub async fn get_networks_needing_cleanup(&self) -> SyncResult<Vec<NetworkAllocation>> {
         self.list_allocations(Some(NetworkStatus::CleanupPending)).await
     }
     
-    async fn find_available_ip(&self) -> SyncResult<String> {
-        // Get all allocated IPs
-        let allocated_ips: Vec<(String,)> = sqlx::query_as(
-            "SELECT ip_address FROM network_allocations WHERE status != 'cleaned'"
-        ).fetch_all(&self.pool).await?;
-        
-        let allocated_set: std::collections::HashSet<String> = allocated_ips
-            .into_iter()
-            .map(|(ip,)| ip)
-            .collect();
-        
-        // Find first available IP in range
-        let start_int = u32::from(self.ip_range_start);
-        let end_int = u32::from(self.ip_range_end);
-        
-        for ip_int in start_int..=end_int {
-            let ip = Ipv4Addr::from(ip_int);
-            let ip_str = ip.to_string();
-            
-            if !allocated_set.contains(&ip_str) {
-                return Ok(ip_str);
-            }
-        }
-        
-        Err(SyncError::NoAvailableIp)
-    }
-    
     pub async fn set_network_state(&self, key: &str, value: &str) -> SyncResult<()> {
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
         
@@ -423,4 +789,60 @@ mod tests {
         let result = network_manager.allocate_network("container3").await;
         assert!(matches!(result, Err(SyncError::NoAvailableIp)));
     }
+    
+    #[tokio::test]
+    async fn test_user_defined_network_allocation() {
+        let (conn, network_manager) = setup_test_db().await;
+        
+        sqlx::query("INSERT INTO containers (id, image_path, command, state, created_at, updated_at) VALUES ('multi', '/img', 'sh', 'created', 0, 0)")
+            .execute(conn.pool())
+            .await
+            .unwrap();
+        
+        let network = network_manager.create_network(NetworkSpec {
+            name: "team-a".to_string(),
+            subnet_cidr: "10.60.0.0/24".to_string(),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(network.gateway_ip, "10.60.0.1");
+        assert_eq!(network.ip_range_start, "10.60.0.2");
+        assert_eq!(network.ip_range_end, "10.60.0.254");
+        assert_eq!(network.bridge_name, "qbr-team-a");
+        
+        // Overlapping subnets are rejected
+        let overlap = network_manager.create_network(NetworkSpec {
+            name: "team-b".to_string(),
+            subnet_cidr: "10.60.0.128/25".to_string(),
+            ..Default::default()
+        }).await;
+        assert!(matches!(overlap, Err(SyncError::ValidationFailed { .. })));
+        
+        // A container can hold one allocation per network
+        let primary = network_manager.allocate_network("multi").await.unwrap();
+        let secondary = network_manager.allocate_on_network("multi", "team-a").await.unwrap();
+        assert_eq!(primary.network_name, DEFAULT_NETWORK_NAME);
+        assert_eq!(secondary.ip_address, "10.60.0.2");
+        
+        let allocations = network_manager.list_container_allocations("multi").await.unwrap();
+        assert_eq!(allocations.len(), 2);
+        assert_eq!(allocations[0].network_name, DEFAULT_NETWORK_NAME);
+        assert_eq!(allocations[1].network_name, "team-a");
+        
+        // Networks with live allocations cannot be removed
+        let result = network_manager.remove_network("team-a").await;
+        assert!(matches!(result, Err(SyncError::NetworkInUse { .. })));
+        
+        network_manager.mark_network_cleaned("multi").await.unwrap();
+        network_manager.remove_network("team-a").await.unwrap();
+        assert!(matches!(network_manager.get_network("team-a").await, Err(SyncError::NetworkNotFound { .. })));
+    }
+    
+    #[test]
+    fn test_parse_subnet() {
+        assert_eq!(parse_subnet("10.1.2.3/16").unwrap(), (Ipv4Addr::new(10, 1, 0, 0), 16));
+        assert!(parse_subnet("10.1.2.0").is_err());
+        assert!(parse_subnet("10.1.2.0/31").is_err());
+        assert!(subnets_overlap(parse_subnet("10.0.0.0/8").unwrap(), parse_subnet("10.42.0.0/16").unwrap()));
+        assert!(!subnets_overlap(parse_subnet("10.1.0.0/16").unwrap(), parse_subnet("10.2.0.0/16").unwrap()));
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{SqlitePool, Row};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::sync::error::{SyncError, SyncResult};
+
+/// Memory charged for containers created without an explicit limit; matches the
+/// runtime's default cgroup memory limit.
+pub const DEFAULT_MEMORY_LIMIT_MB: i64 = 512;
+
+/// Aggregate limits for all live containers of one owner. `None` means unlimited.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OwnerQuota {
+    pub owner: String,
+    pub max_containers: Option<i64>,
+    pub max_memory_mb: Option<i64>,
+    pub updated_at: i64,
+}
+
+/// Resources currently held by an owner's live (created/starting/running) containers
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OwnerUsage {
+    pub owner: String,
+    pub containers: i64,
+    pub memory_mb: i64,
+}
+
+pub struct QuotaManager {
+    pool: SqlitePool,
+}
+
+impl QuotaManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn set_quota(&self, owner: &str, max_containers: Option<i64>, max_memory_mb: Option<i64>) -> SyncResult<OwnerQuota> {
+        if owner.is_empty() {
+            return Err(SyncError::ValidationFailed {
+                message: "Quota owner cannot be empty".to_string(),
+            });
+        }
+        if max_containers.is_some_and(|v| v < 0) || max_memory_mb.is_some_and(|v| v < 0) {
+            return Err(SyncError::ValidationFailed {
+                message: format!("Quota limits for {} cannot be negative", owner),
+            });
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+
+        sqlx::query(r#"
+            INSERT INTO owner_quotas (owner, max_containers, max_memory_mb, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(owner) DO UPDATE SET
+                max_containers = excluded.max_containers,
+                max_memory_mb = excluded.max_memory_mb,
+                updated_at = excluded.updated_at
+        "#)
+        .bind(owner)
+        .bind(max_containers)
+        .bind(max_memory_mb)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        tracing::info!("Set quota for owner {}: max_containers={:?}, max_memory_mb={:?}", owner, max_containers, max_memory_mb);
+
+        Ok(OwnerQuota {
+            owner: owner.to_string(),
+            max_containers,
+            max_memory_mb,
+            updated_at: now,
+        })
+    }
+
+    pub async fn get_quota(&self, owner: &str) -> SyncResult<Option<OwnerQuota>> {
+        let row = sqlx::query("SELECT owner, max_containers, max_memory_mb, updated_at FROM owner_quotas WHERE owner = ?")
+            .bind(owner)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| OwnerQuota {
+            owner: row.get("owner"),
+            max_containers: row.get("max_containers"),
+            max_memory_mb: row.get("max_memory_mb"),
+            updated_at: row.get("updated_at"),
+        }))
+    }
+
+    pub async fn remove_quota(&self, owner: &str) -> SyncResult<bool> {
+        let result = sqlx::query("DELETE FROM owner_quotas WHERE owner = ?")
+            .bind(owner)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_usage(&self, owner: &str) -> SyncResult<OwnerUsage> {
+        let mut conn = self.pool.acquire().await?;
+        Self::usage_in(&mut *conn, owner).await
+    }
+
+    async fn usage_in(conn: &mut sqlx::SqliteConnection, owner: &str) -> SyncResult<OwnerUsage> {
+        let row = sqlx::query(r#"
+            SELECT COUNT(*) AS containers,
+                   COALESCE(SUM(COALESCE(memory_limit_mb, ?)), 0) AS memory_mb
+            FROM containers
+            WHERE owner = ? AND state IN ('created', 'starting', 'running')
+        "#)
+        .bind(DEFAULT_MEMORY_LIMIT_MB)
+        .bind(owner)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(OwnerUsage {
+            owner: owner.to_string(),
+            containers: row.get("containers"),
+            memory_mb: row.get("memory_mb"),
+        })
+    }
+
+    /// Verify the owner's live containers, including any rows already written on
+    /// `conn`, fit within their quota. Run inside the creating transaction after the
+    /// container row is inserted so concurrent creates cannot both slip under the limit.
+    pub async fn enforce_in(&self, conn: &mut sqlx::SqliteConnection, owner: &str) -> SyncResult<()> {
+        let row = sqlx::query("SELECT max_containers, max_memory_mb FROM owner_quotas WHERE owner = ?")
+            .bind(owner)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        let (max_containers, max_memory_mb): (Option<i64>, Option<i64>) = match row {
+            Some(row) => (row.get("max_containers"), row.get("max_memory_mb")),
+            None => return Ok(()),
+        };
+
+        let usage = Self::usage_in(conn, owner).await?;
+
+        if let Some(max) = max_containers {
+            if usage.containers > max {
+                return Err(SyncError::QuotaExceeded {
+                    owner: owner.to_string(),
+                    message: format!("container limit {} reached", max),
+                });
+            }
+        }
+
+        if let Some(max) = max_memory_mb {
+            if usage.memory_mb > max {
+                return Err(SyncError::QuotaExceeded {
+                    owner: owner.to_string(),
+                    message: format!("memory would reach {}MB of {}MB allowed", usage.memory_mb, max),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::connection::ConnectionManager;
+    use crate::sync::schema::SchemaManager;
+    use tempfile::NamedTempFile;
+
+    async fn setup_test_db() -> (ConnectionManager, QuotaManager) {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = temp_file.path().to_str().unwrap();
+
+        let conn_manager = ConnectionManager::new(db_path).await.unwrap();
+        let schema_manager = SchemaManager::new(conn_manager.pool().clone());
+        schema_manager.initialize_schema().await.unwrap();
+
+        let quota_manager = QuotaManager::new(conn_manager.pool().clone());
+
+        (conn_manager, quota_manager)
+    }
+
+    #[tokio::test]
+    async fn test_quota_crud() {
+        let (_conn, quota_manager) = setup_test_db().await;
+
+        assert!(quota_manager.get_quota("team-a").await.unwrap().is_none());
+
+        quota_manager.set_quota("team-a", Some(2), None).await.unwrap();
+        quota_manager.set_quota("team-a", Some(3), Some(1024)).await.unwrap();
+
+        let quota = quota_manager.get_quota("team-a").await.unwrap().unwrap();
+        assert_eq!(quota.max_containers, Some(3));
+        assert_eq!(quota.max_memory_mb, Some(1024));
+
+        assert!(quota_manager.set_quota("team-a", Some(-1), None).await.is_err());
+        assert!(quota_manager.remove_quota("team-a").await.unwrap());
+        assert!(quota_manager.get_quota("team-a").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,508 @@
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use nix::unistd::Pid;
+use crate::sync::{
+    containers::{ContainerManager, ContainerState, ContainerStatus},
+    network::NetworkManager,
+    monitor::ProcessMonitorService,
+    cleanup::{mount_points_under, CleanupService, ResourceType, CONTAINERS_ROOT},
+    error::{SyncError, SyncResult},
+};
+
+/// Clock ticks per second used by `/proc/<pid>/stat` (USER_HZ, fixed at 100 on Linux ABIs)
+const CLOCK_TICKS_PER_SEC: i64 = 100;
+
+/// Slack allowed between a process' start time and the recorded `started_at`;
+/// the row is only marked running after the process has been spawned.
+const START_TIME_TOLERANCE_SECS: i64 = 5;
+
+/// Host paths inspected during reconciliation
+#[derive(Debug, Clone)]
+pub struct ReconcileConfig {
+    pub containers_root: PathBuf,
+    pub overlays_root: PathBuf,
+    pub proc_root: PathBuf,
+    pub sys_class_net: PathBuf,
+}
+
+impl Default for ReconcileConfig {
+    fn default() -> Self {
+        Self {
+            containers_root: PathBuf::from(CONTAINERS_ROOT),
+            overlays_root: PathBuf::from("/tmp/quilt-image-cache/overlays"),
+            proc_root: PathBuf::from("/proc"),
+            sys_class_net: PathBuf::from("/sys/class/net"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExitedContainer {
+    pub container_id: String,
+    pub pid: Option<i64>,
+    pub reason: String,
+}
+
+/// What a reconciliation pass found and did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReconciliationReport {
+    /// Containers whose process survived the restart and is monitored again
+    pub adopted: Vec<String>,
+    /// Containers recorded as running/starting whose process is gone
+    pub exited: Vec<ExitedContainer>,
+    /// Monitor rows left `monitoring` by the previous daemon
+    pub aborted_monitors: usize,
+    /// Cleanup tasks interrupted mid-flight and put back in the queue
+    pub requeued_cleanup_tasks: usize,
+    /// Rootfs / overlay directories with no container record
+    pub leaked_rootfs: Vec<String>,
+    /// Mount points under the containers root with no live container
+    pub leaked_mounts: Vec<String>,
+    /// Host veth interfaces with no live container
+    pub leaked_interfaces: Vec<String>,
+    pub cleanup_tasks_scheduled: usize,
+}
+
+impl ReconciliationReport {
+    pub fn is_clean(&self) -> bool {
+        self.exited.is_empty()
+            && self.aborted_monitors == 0
+            && self.requeued_cleanup_tasks == 0
+            && self.leaked_rootfs.is_empty()
+            && self.leaked_mounts.is_empty()
+            && self.leaked_interfaces.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "adopted {} container(s), marked {} exited, aborted {} stale monitor(s), requeued {} cleanup task(s), \
+             found {} leaked rootfs, {} leaked mount(s), {} leaked interface(s); scheduled {} cleanup task(s)",
+            self.adopted.len(),
+            self.exited.len(),
+            self.aborted_monitors,
+            self.requeued_cleanup_tasks,
+            self.leaked_rootfs.len(),
+            self.leaked_mounts.len(),
+            self.leaked_interfaces.len(),
+            self.cleanup_tasks_scheduled,
+        )
+    }
+}
+
+/// Reconciles persisted state with the host after a daemon restart
+pub struct StateReconciler {
+    pool: SqlitePool,
+    container_manager: Arc<ContainerManager>,
+    network_manager: Arc<NetworkManager>,
+    monitor_service: Arc<ProcessMonitorService>,
+    cleanup_service: Arc<CleanupService>,
+    config: ReconcileConfig,
+}
+
+impl StateReconciler {
+    pub fn new(
+        pool: SqlitePool,
+        container_manager: Arc<ContainerManager>,
+        network_manager: Arc<NetworkManager>,
+        monitor_service: Arc<ProcessMonitorService>,
+        cleanup_service: Arc<CleanupService>,
+        config: ReconcileConfig,
+    ) -> Self {
+        Self {
+            pool,
+            container_manager,
+            network_manager,
+            monitor_service,
+            cleanup_service,
+            config,
+        }
+    }
+
+    pub async fn reconcile(&self) -> SyncResult<ReconciliationReport> {
+        let mut report = ReconciliationReport {
+            requeued_cleanup_tasks: self.cleanup_service.requeue_interrupted_tasks().await?,
+            ..Default::default()
+        };
+
+        // Monitor tasks died with the previous daemon; adopted processes get fresh rows below
+        let result = sqlx::query("UPDATE process_monitors SET status = 'aborted' WHERE status = 'monitoring'")
+            .execute(&self.pool)
+            .await?;
+        report.aborted_monitors = result.rows_affected() as usize;
+
+        let containers = self.container_manager.list_containers(None).await?;
+        let known: HashSet<String> = containers.iter().map(|c| c.id.clone()).collect();
+        let mut active: HashSet<String> = HashSet::new();
+
+        for container in &containers {
+            match container.state {
+                ContainerState::Running | ContainerState::Starting => {
+                    match self.check_process(container) {
+                        Ok(pid) => {
+                            self.monitor_service.adopt_process(&container.id, Pid::from_raw(pid as i32)).await?;
+                            tracing::info!("Adopted container {} (PID: {})", container.id, pid);
+                            report.adopted.push(container.id.clone());
+                            active.insert(container.id.clone());
+                        }
+                        Err(reason) => {
+                            report.cleanup_tasks_scheduled += self.mark_exited(container, &reason).await?;
+                            report.exited.push(ExitedContainer {
+                                container_id: container.id.clone(),
+                                pid: container.pid,
+                                reason,
+                            });
+                        }
+                    }
+                }
+                ContainerState::Created => {
+                    active.insert(container.id.clone());
+                }
+                ContainerState::Exited | ContainerState::Error => {}
+            }
+        }
+
+        // Rootfs and overlay directories nobody owns any more
+        let mut scheduled_mount_roots: HashSet<String> = HashSet::new();
+        for root in [&self.config.containers_root, &self.config.overlays_root] {
+            for (id, path) in Self::list_dirs(root).await {
+                if known.contains(&id) {
+                    continue;
+                }
+                if root == &self.config.containers_root {
+                    self.cleanup_service.schedule_cleanup(&id, ResourceType::Mounts, &path).await?;
+                    report.cleanup_tasks_scheduled += 1;
+                    scheduled_mount_roots.insert(path.clone());
+                }
+                self.cleanup_service.schedule_cleanup(&id, ResourceType::Rootfs, &path).await?;
+                report.cleanup_tasks_scheduled += 1;
+                report.leaked_rootfs.push(path);
+            }
+        }
+
+        // Mounts left behind by containers that are no longer running
+        let containers_root = self.config.containers_root.to_string_lossy().to_string();
+        for (id, mount_points) in self.leaked_mounts(&containers_root).await {
+            if active.contains(&id) {
+                continue;
+            }
+            let root = format!("{}/{}", containers_root.trim_end_matches('/'), id);
+            if !scheduled_mount_roots.contains(&root) {
+                self.cleanup_service.schedule_cleanup(&id, ResourceType::Mounts, &root).await?;
+                report.cleanup_tasks_scheduled += 1;
+            }
+            report.leaked_mounts.extend(mount_points);
+        }
+
+        // Host-side veths whose container is gone
+        let active_short_ids: HashSet<&str> = active.iter().filter_map(|id| id.get(..8)).collect();
+        for interface in self.list_quilt_interfaces().await {
+            let short_id = interface.rsplit('-').next().unwrap_or_default();
+            if active_short_ids.contains(short_id) {
+                continue;
+            }
+            let owner = known
+                .iter()
+                .find(|id| id.starts_with(short_id))
+                .cloned()
+                .unwrap_or_else(|| short_id.to_string());
+            self.cleanup_service.schedule_cleanup(&owner, ResourceType::Network, &interface).await?;
+            report.cleanup_tasks_scheduled += 1;
+            report.leaked_interfaces.push(interface);
+        }
+
+        if report.is_clean() {
+            tracing::info!("State reconciliation found nothing to repair ({} adopted)", report.adopted.len());
+        } else {
+            tracing::warn!("State reconciliation: {}", report.summary());
+        }
+
+        Ok(report)
+    }
+
+    /// Returns the PID to adopt, or the reason the container is considered dead
+    fn check_process(&self, container: &ContainerStatus) -> Result<i64, String> {
+        let pid = match container.pid {
+            Some(pid) if pid > 0 => pid,
+            _ => return Err("Daemon restarted before the container process was recorded".to_string()),
+        };
+
+        let start_time = match self.process_start_time(pid) {
+            Some(start_time) => start_time,
+            None => return Err(format!("Process {} no longer exists after daemon restart", pid)),
+        };
+
+        // A live PID that started after the container did belongs to someone else
+        if let Some(started_at) = container.started_at {
+            if start_time > started_at + START_TIME_TOLERANCE_SECS {
+                return Err(format!("PID {} was reused by another process after daemon restart", pid));
+            }
+        }
+
+        Ok(pid)
+    }
+
+    /// Process start time in seconds since the epoch, or None if it is gone or a zombie
+    fn process_start_time(&self, pid: i64) -> Option<i64> {
+        let stat = std::fs::read_to_string(self.config.proc_root.join(pid.to_string()).join("stat")).ok()?;
+        // Fields after the parenthesised command name start at field 3 (state)
+        let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+        if fields.first() == Some(&"Z") {
+            return None;
+        }
+        let start_ticks: i64 = fields.get(19)?.parse().ok()?;
+
+        let proc_stat = std::fs::read_to_string(self.config.proc_root.join("stat")).ok()?;
+        let boot_time: i64 = proc_stat
+            .lines()
+            .find_map(|line| line.strip_prefix("btime "))?
+            .trim()
+            .parse()
+            .ok()?;
+
+        Some(boot_time + start_ticks / CLOCK_TICKS_PER_SEC)
+    }
+
+    async fn mark_exited(&self, container: &ContainerStatus, reason: &str) -> SyncResult<usize> {
+        self.container_manager
+            .update_container_state_with_details(&container.id, ContainerState::Exited, None, None)
+            .await?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        sqlx::query("INSERT INTO container_logs (container_id, timestamp, level, message) VALUES (?, ?, 'warn', ?)")
+            .bind(&container.id)
+            .bind(now)
+            .bind(reason)
+            .execute(&self.pool)
+            .await?;
+
+        tracing::warn!("Marked container {} exited: {}", container.id, reason);
+
+        let tasks = self
+            .cleanup_service
+            .schedule_container_cleanup(&container.id, container.rootfs_path.as_deref())
+            .await?;
+
+        match self.network_manager.mark_network_cleanup_pending(&container.id).await {
+            Ok(()) | Err(SyncError::NotFound { .. }) => {}
+            Err(e) => return Err(e),
+        }
+
+        Ok(tasks.len())
+    }
+
+    async fn list_dirs(root: &PathBuf) -> Vec<(String, String)> {
+        let mut dirs = Vec::new();
+        let mut entries = match tokio::fs::read_dir(root).await {
+            Ok(entries) => entries,
+            Err(_) => return dirs,
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if entry.file_type().await.map(|t| t.is_dir()).unwrap_or(false) {
+                dirs.push((
+                    entry.file_name().to_string_lossy().to_string(),
+                    entry.path().to_string_lossy().to_string(),
+                ));
+            }
+        }
+
+        dirs.sort();
+        dirs
+    }
+
+    /// Mount points under the containers root, grouped by container directory
+    async fn leaked_mounts(&self, containers_root: &str) -> BTreeMap<String, Vec<String>> {
+        let mut grouped: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let proc_mounts = match tokio::fs::read_to_string(self.config.proc_root.join("mounts")).await {
+            Ok(contents) => contents,
+            Err(_) => return grouped,
+        };
+
+        let prefix = format!("{}/", containers_root.trim_end_matches('/'));
+        for mount_point in mount_points_under(&proc_mounts, containers_root) {
+            if let Some(id) = mount_point.strip_prefix(&prefix).and_then(|rest| rest.split('/').next()) {
+                grouped.entry(id.to_string()).or_default().push(mount_point.clone());
+            }
+        }
+
+        grouped
+    }
+
+    async fn list_quilt_interfaces(&self) -> Vec<String> {
+        // /sys/class/net entries are symlinks, so match on names only
+        let mut interfaces = Vec::new();
+        let mut entries = match tokio::fs::read_dir(&self.config.sys_class_net).await {
+            Ok(entries) => entries,
+            Err(_) => return interfaces,
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if is_quilt_veth(&name) {
+                interfaces.push(name);
+            }
+        }
+
+        interfaces.sort();
+        interfaces
+    }
+}
+
+/// Matches the host/peer veth names quilt creates: `veth-<id8>`, `vethc-<id8>`,
+/// `veth<n>-<id8>`, `vethc<n>-<id8>` (from before peers were shortened) and
+/// `vc<n>-<id8>`. Other runtimes' veths never contain a dash.
+pub fn is_quilt_veth(name: &str) -> bool {
+    let rest = match name.strip_prefix("veth") {
+        Some(rest) => rest.strip_prefix('c').unwrap_or(rest),
+        None => match name.strip_prefix("vc") {
+            Some(rest) if !rest.starts_with('-') => rest,
+            _ => return false,
+        },
+    };
+    let (index, short_id) = match rest.split_once('-') {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    index.chars().all(|c| c.is_ascii_digit())
+        && short_id.len() == 8
+        && short_id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::connection::ConnectionManager;
+    use crate::sync::containers::ContainerConfig;
+    use crate::sync::schema::SchemaManager;
+    use std::collections::HashMap;
+    use std::process::Command;
+    use tempfile::{NamedTempFile, TempDir};
+
+    async fn setup_reconciler(config: ReconcileConfig) -> (ConnectionManager, StateReconciler) {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = temp_file.path().to_str().unwrap();
+
+        let conn_manager = ConnectionManager::new(db_path).await.unwrap();
+        let schema_manager = SchemaManager::new(conn_manager.pool().clone());
+        schema_manager.initialize_schema().await.unwrap();
+
+        let pool = conn_manager.pool().clone();
+        let reconciler = StateReconciler::new(
+            pool.clone(),
+            Arc::new(ContainerManager::new(pool.clone())),
+            Arc::new(NetworkManager::new(pool.clone())),
+            Arc::new(ProcessMonitorService::new(pool.clone())),
+            Arc::new(CleanupService::new(pool)),
+            config,
+        );
+
+        (conn_manager, reconciler)
+    }
+
+    async fn insert_running(reconciler: &StateReconciler, id: &str, pid: i64) {
+        reconciler.container_manager.create_container(ContainerConfig {
+            id: id.to_string(),
+            name: None,
+            image_path: "/path/to/image".to_string(),
+            command: "sleep 10".to_string(),
+            environment: HashMap::new(),
+            memory_limit_mb: None,
+            cpu_limit_percent: None,
+            owner: None,
+            labels: HashMap::new(),
+            volumes: vec![],
+            setup_commands: vec![],
+            enable_network_namespace: false,
+            enable_pid_namespace: true,
+            enable_mount_namespace: true,
+            enable_uts_namespace: true,
+            enable_ipc_namespace: true,
+        }).await.unwrap();
+        reconciler.container_manager
+            .update_container_state_with_details(id, ContainerState::Running, Some(pid), None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_adopts_live_and_exits_dead() {
+        let containers_root = TempDir::new().unwrap();
+        let empty = TempDir::new().unwrap();
+        let (_conn, reconciler) = setup_reconciler(ReconcileConfig {
+            containers_root: containers_root.path().to_path_buf(),
+            overlays_root: empty.path().join("overlays"),
+            proc_root: PathBuf::from("/proc"),
+            sys_class_net: empty.path().join("net"),
+        }).await;
+
+        let mut live = Command::new("sleep").arg("10").spawn().unwrap();
+        let mut dead = Command::new("true").spawn().unwrap();
+        let dead_pid = dead.id() as i64;
+        dead.wait().unwrap();
+
+        insert_running(&reconciler, "live-container", live.id() as i64).await;
+        insert_running(&reconciler, "dead-container", dead_pid).await;
+
+        // Rootfs left behind by a container the database no longer knows about
+        std::fs::create_dir(containers_root.path().join("orphan")).unwrap();
+
+        let report = reconciler.reconcile().await.unwrap();
+
+        assert_eq!(report.adopted, vec!["live-container".to_string()]);
+        assert_eq!(report.exited.len(), 1);
+        assert_eq!(report.exited[0].container_id, "dead-container");
+        assert_eq!(report.leaked_rootfs.len(), 1);
+        assert!(report.leaked_rootfs[0].ends_with("/orphan"));
+        assert!(report.cleanup_tasks_scheduled >= 2);
+        assert!(!report.is_clean());
+
+        let dead_status = reconciler.container_manager.get_container_status("dead-container").await.unwrap();
+        assert_eq!(dead_status.state, ContainerState::Exited);
+        assert_eq!(dead_status.pid, None);
+
+        let live_status = reconciler.container_manager.get_container_status("live-container").await.unwrap();
+        assert_eq!(live_status.state, ContainerState::Running);
+        let monitor = reconciler.monitor_service.get_monitor_status("live-container").await.unwrap();
+        assert_eq!(monitor.pid, live.id() as i64);
+
+        let orphan_tasks = reconciler.cleanup_service.list_container_cleanup_tasks("orphan").await.unwrap();
+        assert!(orphan_tasks.iter().any(|t| t.resource_type == ResourceType::Rootfs));
+
+        // The adopted monitor may reap the child first
+        let _ = live.kill();
+        let _ = live.wait();
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_clean_state() {
+        let empty = TempDir::new().unwrap();
+        let (_conn, reconciler) = setup_reconciler(ReconcileConfig {
+            containers_root: empty.path().join("containers"),
+            overlays_root: empty.path().join("overlays"),
+            proc_root: PathBuf::from("/proc"),
+            sys_class_net: empty.path().join("net"),
+        }).await;
+
+        let report = reconciler.reconcile().await.unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.cleanup_tasks_scheduled, 0);
+    }
+
+    #[test]
+    fn test_is_quilt_veth() {
+        assert!(is_quilt_veth("veth-1a2b3c4d"));
+        assert!(is_quilt_veth("vethc-1a2b3c4d"));
+        assert!(is_quilt_veth("veth2-1a2b3c4d"));
+        assert!(is_quilt_veth("vethc2-1a2b3c4d"));
+        assert!(is_quilt_veth("vc12-1a2b3c4d"));
+        assert!(!is_quilt_veth("vc-1a2b3c4d"));
+        assert!(!is_quilt_veth("veth1a2b3c4"));
+        assert!(!is_quilt_veth("quilt0"));
+        assert!(!is_quilt_veth("veth-xyz"));
+    }
+}
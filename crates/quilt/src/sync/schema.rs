@@ -1,5 +1,23 @@
-use sqlx::SqlitePool;
+use sqlx::{SqlitePool, Row};
 use crate::sync::error::{SyncError, SyncResult};
+use crate::sync::network::{DEFAULT_NETWORK_NAME, DEFAULT_BRIDGE_NAME};
+
+const NETWORK_ALLOCATIONS_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS network_allocations (
+        container_id TEXT NOT NULL,
+        network_name TEXT NOT NULL DEFAULT 'default',
+        ip_address TEXT NOT NULL,
+        bridge_interface TEXT,
+        veth_host TEXT,
+        veth_container TEXT,
+        allocation_time INTEGER NOT NULL,
+        setup_completed BOOLEAN DEFAULT 0,
+        status TEXT CHECK(status IN ('allocated', 'active', 'cleanup_pending', 'cleaned')) NOT NULL,
+        PRIMARY KEY(container_id, network_name),
+        FOREIGN KEY(container_id) REFERENCES containers(id) ON DELETE CASCADE,
+        FOREIGN KEY(network_name) REFERENCES networks(name)
+    )
+"#;
 
 pub struct SchemaManager {
     pool: SqlitePool,
@@ -12,12 +30,18 @@ impl SchemaManager {
     
     pub async fn initialize_schema(&self) -> SyncResult<()> {
         self.create_containers_table().await?;
+        self.migrate_containers_columns().await?;
+        self.create_networks_table().await?;
         self.create_network_allocations_table().await?;
+        self.migrate_network_allocations().await?;
         self.create_network_state_table().await?;
         self.create_process_monitors_table().await?;
         self.create_container_logs_table().await?;
         self.create_cleanup_tasks_table().await?;
         self.create_async_tasks_table().await?;
+        self.create_container_metrics_table().await?;
+        self.create_owner_quotas_table().await?;
+        self.create_container_templates_table().await?;
         self.create_indexes().await?;
         
         tracing::info!("Database schema initialized successfully");
@@ -41,6 +65,10 @@ impl SchemaManager {
                 exited_at INTEGER,
                 memory_limit_mb INTEGER,
                 cpu_limit_percent REAL,
+                owner TEXT,
+                labels TEXT, -- JSON blob
+                volumes TEXT, -- JSON array of host:container[:ro]
+                setup_commands TEXT, -- JSON array
                 
                 -- Resource configuration
                 enable_network_namespace BOOLEAN NOT NULL DEFAULT 1,
@@ -57,21 +85,109 @@ impl SchemaManager {
         Ok(())
     }
     
-    async fn create_network_allocations_table(&self) -> SyncResult<()> {
+    async fn create_networks_table(&self) -> SyncResult<()> {
         sqlx::query(r#"
-            CREATE TABLE IF NOT EXISTS network_allocations (
-                container_id TEXT PRIMARY KEY,
-                ip_address TEXT NOT NULL,
-                bridge_interface TEXT,
-                veth_host TEXT,
-                veth_container TEXT,
-                allocation_time INTEGER NOT NULL,
-                setup_completed BOOLEAN DEFAULT 0,
-                status TEXT CHECK(status IN ('allocated', 'active', 'cleanup_pending', 'cleaned')) NOT NULL,
-                FOREIGN KEY(container_id) REFERENCES containers(id) ON DELETE CASCADE
+            CREATE TABLE IF NOT EXISTS networks (
+                name TEXT PRIMARY KEY,
+                bridge_name TEXT NOT NULL UNIQUE,
+                subnet_cidr TEXT NOT NULL UNIQUE,
+                gateway_ip TEXT NOT NULL,
+                ip_range_start TEXT NOT NULL,
+                ip_range_end TEXT NOT NULL,
+                is_default BOOLEAN NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL
             )
         "#).execute(&self.pool).await?;
         
+        // Seed the default network that containers join when none are requested
+        sqlx::query(r#"
+            INSERT OR IGNORE INTO networks (
+                name, bridge_name, subnet_cidr, gateway_ip, ip_range_start, ip_range_end, is_default, created_at
+            ) VALUES (?, ?, '172.16.0.0/24', '172.16.0.1', '172.16.0.10', '172.16.0.250', 1, 0)
+        "#)
+        .bind(DEFAULT_NETWORK_NAME)
+        .bind(DEFAULT_BRIDGE_NAME)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    async fn create_network_allocations_table(&self) -> SyncResult<()> {
+        sqlx::query(NETWORK_ALLOCATIONS_TABLE).execute(&self.pool).await?;
+        Ok(())
+    }
+    
+    /// Databases created before multi-network support keyed allocations by container only.
+    /// Rebuild that table so every existing allocation lands on the default network.
+    /// The whole rebuild is one transaction, so an interrupted migration leaves the old
+    /// table in place and simply runs again on the next start.
+    async fn migrate_network_allocations(&self) -> SyncResult<()> {
+        let mut tx = self.pool.begin().await?;
+        
+        let columns = sqlx::query("PRAGMA table_info(network_allocations)")
+            .fetch_all(&mut *tx)
+            .await?;
+        let has_network_name = columns.iter().any(|row| row.get::<String, _>("name") == "network_name");
+        let legacy_exists = sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'network_allocations_legacy'")
+            .fetch_optional(&mut *tx)
+            .await?
+            .is_some();
+        
+        if has_network_name && !legacy_exists {
+            return Ok(());
+        }
+        
+        if has_network_name {
+            // Left behind by an earlier, non-atomic version of this migration
+            tracing::info!("Recovering allocations stranded in network_allocations_legacy");
+        } else {
+            tracing::info!("Migrating network_allocations to per-network allocations");
+            sqlx::query("ALTER TABLE network_allocations RENAME TO network_allocations_legacy")
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DROP INDEX IF EXISTS idx_network_allocations_status").execute(&mut *tx).await?;
+            sqlx::query("DROP INDEX IF EXISTS idx_network_allocations_ip").execute(&mut *tx).await?;
+            sqlx::query(NETWORK_ALLOCATIONS_TABLE).execute(&mut *tx).await?;
+        }
+        
+        sqlx::query(r#"
+            INSERT OR IGNORE INTO network_allocations (
+                container_id, network_name, ip_address, bridge_interface, veth_host, veth_container,
+                allocation_time, setup_completed, status
+            )
+            SELECT container_id, ?, ip_address, bridge_interface, veth_host, veth_container,
+                   allocation_time, setup_completed, status
+            FROM network_allocations_legacy
+        "#)
+        .bind(DEFAULT_NETWORK_NAME)
+        .execute(&mut *tx)
+        .await?;
+        
+        sqlx::query("DROP TABLE network_allocations_legacy").execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+    
+    /// Columns added to `containers` after its first release; older databases get
+    /// them appended so existing rows read back as NULL.
+    async fn migrate_containers_columns(&self) -> SyncResult<()> {
+        let columns = sqlx::query("PRAGMA table_info(containers)")
+            .fetch_all(&self.pool)
+            .await?;
+        let existing: Vec<String> = columns.iter().map(|row| row.get::<String, _>("name")).collect();
+        
+        for column in ["owner", "labels", "volumes", "setup_commands"] {
+            if existing.iter().any(|name| name == column) {
+                continue;
+            }
+            
+            tracing::info!("Adding {} column to containers", column);
+            sqlx::query(&format!("ALTER TABLE containers ADD COLUMN {} TEXT", column))
+                .execute(&self.pool)
+                .await?;
+        }
+        
         Ok(())
     }
     
@@ -156,13 +272,69 @@ impl SchemaManager {
         Ok(())
     }
     
+    /// Downsampled resource history. No foreign key: usage must outlive the container
+    /// for capacity planning and chargeback; retention pruning removes old buckets.
+    async fn create_container_metrics_table(&self) -> SyncResult<()> {
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS container_metrics (
+                container_id TEXT NOT NULL,
+                resolution TEXT CHECK(resolution IN ('raw', 'minute', 'hour')) NOT NULL,
+                bucket_start INTEGER NOT NULL,
+                samples INTEGER NOT NULL,
+                cpu_percent_avg REAL NOT NULL,
+                cpu_percent_max REAL NOT NULL,
+                cpu_usage_usec INTEGER NOT NULL,
+                memory_bytes_avg INTEGER NOT NULL,
+                memory_bytes_max INTEGER NOT NULL,
+                io_read_bytes INTEGER NOT NULL,
+                io_write_bytes INTEGER NOT NULL,
+                net_rx_bytes INTEGER NOT NULL,
+                net_tx_bytes INTEGER NOT NULL,
+                pids_max INTEGER NOT NULL,
+                PRIMARY KEY(container_id, resolution, bucket_start)
+            )
+        "#).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
+    async fn create_owner_quotas_table(&self) -> SyncResult<()> {
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS owner_quotas (
+                owner TEXT PRIMARY KEY,
+                max_containers INTEGER,
+                max_memory_mb INTEGER,
+                updated_at INTEGER NOT NULL
+            )
+        "#).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
+    async fn create_container_templates_table(&self) -> SyncResult<()> {
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS container_templates (
+                name TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                description TEXT,
+                spec TEXT NOT NULL, -- JSON blob
+                created_at INTEGER NOT NULL,
+                PRIMARY KEY(name, version)
+            )
+        "#).execute(&self.pool).await?;
+        
+        Ok(())
+    }
+    
     async fn create_indexes(&self) -> SyncResult<()> {
         // Performance indexes as specified in the documentation
         let indexes = [
             "CREATE INDEX IF NOT EXISTS idx_containers_state ON containers(state)",
             "CREATE INDEX IF NOT EXISTS idx_containers_updated_at ON containers(updated_at)",
+            "CREATE INDEX IF NOT EXISTS idx_containers_owner_state ON containers(owner, state)",
+            "CREATE INDEX IF NOT EXISTS idx_container_metrics_pruning ON container_metrics(resolution, bucket_start)",
             "CREATE INDEX IF NOT EXISTS idx_network_allocations_status ON network_allocations(status)",
-            "CREATE INDEX IF NOT EXISTS idx_network_allocations_ip ON network_allocations(ip_address)",
+            "CREATE INDEX IF NOT EXISTS idx_network_allocations_ip ON network_allocations(network_name, ip_address)",
             "CREATE INDEX IF NOT EXISTS idx_process_monitors_status ON process_monitors(status)",
             "CREATE INDEX IF NOT EXISTS idx_process_monitors_pid ON process_monitors(pid)",
             "CREATE INDEX IF NOT EXISTS idx_container_logs_container_time ON container_logs(container_id, timestamp)",
@@ -221,7 +393,51 @@ mod tests {
         
         assert!(table_names.contains(&"containers".to_string()));
         assert!(table_names.contains(&"network_allocations".to_string()));
+        assert!(table_names.contains(&"networks".to_string()));
         assert!(table_names.contains(&"process_monitors".to_string()));
+        assert!(table_names.contains(&"container_metrics".to_string()));
+        assert!(table_names.contains(&"owner_quotas".to_string()));
+        assert!(table_names.contains(&"container_templates".to_string()));
+        
+        conn_manager.close().await;
+    }
+    
+    #[tokio::test]
+    async fn test_network_allocations_migration() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = temp_file.path().to_str().unwrap();
+        
+        let conn_manager = ConnectionManager::new(db_path).await.unwrap();
+        let pool = conn_manager.pool().clone();
+        let schema_manager = SchemaManager::new(pool.clone());
+        schema_manager.create_containers_table().await.unwrap();
+        schema_manager.create_networks_table().await.unwrap();
+        
+        sqlx::query("INSERT INTO containers (id, image_path, command, state, created_at, updated_at) VALUES ('c1', '/img', 'sh', 'running', 0, 0)")
+            .execute(&pool).await.unwrap();
+        sqlx::query(r#"
+            CREATE TABLE network_allocations (
+                container_id TEXT PRIMARY KEY,
+                ip_address TEXT NOT NULL,
+                bridge_interface TEXT,
+                veth_host TEXT,
+                veth_container TEXT,
+                allocation_time INTEGER NOT NULL,
+                setup_completed BOOLEAN DEFAULT 0,
+                status TEXT NOT NULL
+            )
+        "#).execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO network_allocations (container_id, ip_address, allocation_time, status) VALUES ('c1', '172.16.0.10', 0, 'active')")
+            .execute(&pool).await.unwrap();
+        
+        schema_manager.initialize_schema().await.unwrap();
+        
+        let migrated: Vec<(String, String)> = sqlx::query_as("SELECT container_id, network_name FROM network_allocations")
+            .fetch_all(&pool).await.unwrap();
+        assert_eq!(migrated, vec![("c1".to_string(), DEFAULT_NETWORK_NAME.to_string())]);
+        let legacy: Option<(String,)> = sqlx::query_as("SELECT name FROM sqlite_master WHERE name = 'network_allocations_legacy'")
+            .fetch_optional(&pool).await.unwrap();
+        assert!(legacy.is_none());
         
         conn_manager.close().await;
     }
@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{SqlitePool, Row};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::sync::error::{SyncError, SyncResult};
+
+/// Container settings captured by a template. Every field is optional so a
+/// create request only has to fill in what it wants to override.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemplateSpec {
+    pub image_path: Option<String>,
+    pub command: Vec<String>,
+    pub environment: HashMap<String, String>,
+    pub working_directory: Option<String>,
+    pub setup_commands: Vec<String>,
+    pub memory_limit_mb: Option<i64>,
+    pub cpu_limit_percent: Option<f64>,
+    pub enable_network_namespace: bool,
+    pub enable_pid_namespace: bool,
+    pub enable_mount_namespace: bool,
+    pub enable_uts_namespace: bool,
+    pub enable_ipc_namespace: bool,
+    pub networks: Vec<String>,
+    pub volumes: Vec<String>,
+    pub labels: HashMap<String, String>,
+}
+
+/// One immutable version of a named template. Saving a template under an
+/// existing name creates the next version rather than editing the old one, so
+/// containers created from a version can always be traced back to its spec.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContainerTemplate {
+    pub name: String,
+    pub version: i64,
+    pub description: Option<String>,
+    pub spec: TemplateSpec,
+    pub created_at: i64,
+}
+
+pub struct TemplateManager {
+    pool: SqlitePool,
+}
+
+impl TemplateManager {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Store `spec` as the next version of `name`
+    pub async fn create_template(&self, name: &str, description: Option<String>, spec: TemplateSpec) -> SyncResult<ContainerTemplate> {
+        Self::validate_name(name)?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let spec_json = serde_json::to_string(&spec)?;
+
+        let mut tx = self.pool.begin().await?;
+
+        let version: i64 = sqlx::query("SELECT COALESCE(MAX(version), 0) + 1 AS next FROM container_templates WHERE name = ?")
+            .bind(name)
+            .fetch_one(&mut *tx)
+            .await?
+            .get("next");
+
+        sqlx::query(r#"
+            INSERT INTO container_templates (name, version, description, spec, created_at)
+            VALUES (?, ?, ?, ?, ?)
+        "#)
+        .bind(name)
+        .bind(version)
+        .bind(&description)
+        .bind(&spec_json)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info!("Created template {} version {}", name, version);
+
+        Ok(ContainerTemplate {
+            name: name.to_string(),
+            version,
+            description,
+            spec,
+            created_at: now,
+        })
+    }
+
+    /// Fetch a specific version, or the latest when `version` is `None`
+    pub async fn get_template(&self, name: &str, version: Option<i64>) -> SyncResult<ContainerTemplate> {
+        let row = match version {
+            Some(version) => {
+                sqlx::query("SELECT name, version, description, spec, created_at FROM container_templates WHERE name = ? AND version = ?")
+                    .bind(name)
+                    .bind(version)
+                    .fetch_optional(&self.pool)
+                    .await?
+            }
+            None => {
+                sqlx::query("SELECT name, version, description, spec, created_at FROM container_templates WHERE name = ? ORDER BY version DESC LIMIT 1")
+                    .bind(name)
+                    .fetch_optional(&self.pool)
+                    .await?
+            }
+        };
+
+        match row {
+            Some(row) => Self::template_from_row(&row),
+            None => Err(SyncError::TemplateNotFound {
+                name: match version {
+                    Some(version) => format!("{}@{}", name, version),
+                    None => name.to_string(),
+                },
+            }),
+        }
+    }
+
+    /// Latest version of every template, ordered by name
+    pub async fn list_templates(&self) -> SyncResult<Vec<ContainerTemplate>> {
+        let rows = sqlx::query(r#"
+            SELECT t.name, t.version, t.description, t.spec, t.created_at
+            FROM container_templates t
+            WHERE t.version = (SELECT MAX(version) FROM container_templates WHERE name = t.name)
+            ORDER BY t.name
+        "#)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::template_from_row).collect()
+    }
+
+    pub async fn list_template_versions(&self, name: &str) -> SyncResult<Vec<ContainerTemplate>> {
+        let rows = sqlx::query("SELECT name, version, description, spec, created_at FROM container_templates WHERE name = ? ORDER BY version")
+            .bind(name)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(Self::template_from_row).collect()
+    }
+
+    /// Delete one version, or every version when `version` is `None`. Returns the
+    /// number of versions removed.
+    pub async fn delete_template(&self, name: &str, version: Option<i64>) -> SyncResult<u64> {
+        let result = match version {
+            Some(version) => {
+                sqlx::query("DELETE FROM container_templates WHERE name = ? AND version = ?")
+                    .bind(name)
+                    .bind(version)
+                    .execute(&self.pool)
+                    .await?
+            }
+            None => {
+                sqlx::query("DELETE FROM container_templates WHERE name = ?")
+                    .bind(name)
+                    .execute(&self.pool)
+                    .await?
+            }
+        };
+
+        if result.rows_affected() == 0 {
+            return Err(SyncError::TemplateNotFound { name: name.to_string() });
+        }
+
+        tracing::info!("Deleted {} version(s) of template {}", result.rows_affected(), name);
+        Ok(result.rows_affected())
+    }
+
+    fn validate_name(name: &str) -> SyncResult<()> {
+        let valid = !name.is_empty()
+            && name.len() <= 64
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+        if !valid {
+            return Err(SyncError::ValidationFailed {
+                message: format!("Invalid template name '{}': use 1-64 characters from [A-Za-z0-9._-]", name),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn template_from_row(row: &sqlx::sqlite::SqliteRow) -> SyncResult<ContainerTemplate> {
+        let spec: String = row.get("spec");
+
+        Ok(ContainerTemplate {
+            name: row.get("name"),
+            version: row.get("version"),
+            description: row.get("description"),
+            spec: serde_json::from_str(&spec)?,
+            created_at: row.get("created_at"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::connection::ConnectionManager;
+    use crate::sync::schema::SchemaManager;
+    use tempfile::NamedTempFile;
+
+    async fn setup_test_db() -> (ConnectionManager, TemplateManager) {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db_path = temp_file.path().to_str().unwrap();
+
+        let conn_manager = ConnectionManager::new(db_path).await.unwrap();
+        let schema_manager = SchemaManager::new(conn_manager.pool().clone());
+        schema_manager.initialize_schema().await.unwrap();
+
+        let template_manager = TemplateManager::new(conn_manager.pool().clone());
+
+        (conn_manager, template_manager)
+    }
+
+    #[tokio::test]
+    async fn test_template_versions() {
+        let (_conn, template_manager) = setup_test_db().await;
+
+        let mut spec = TemplateSpec {
+            image_path: Some("/images/node.tar.gz".to_string()),
+            command: vec!["node".to_string(), "server.js".to_string()],
+            memory_limit_mb: Some(256),
+            ..Default::default()
+        };
+        spec.labels.insert("tier".to_string(), "web".to_string());
+
+        let v1 = template_manager.create_template("web", None, spec.clone()).await.unwrap();
+        assert_eq!(v1.version, 1);
+
+        spec.memory_limit_mb = Some(512);
+        let v2 = template_manager.create_template("web", Some("more memory".to_string()), spec.clone()).await.unwrap();
+        assert_eq!(v2.version, 2);
+
+        let latest = template_manager.get_template("web", None).await.unwrap();
+        assert_eq!(latest.version, 2);
+        assert_eq!(latest.spec, spec);
+
+        let first = template_manager.get_template("web", Some(1)).await.unwrap();
+        assert_eq!(first.spec.memory_limit_mb, Some(256));
+
+        assert_eq!(template_manager.list_templates().await.unwrap().len(), 1);
+        assert_eq!(template_manager.list_template_versions("web").await.unwrap().len(), 2);
+
+        assert_eq!(template_manager.delete_template("web", Some(2)).await.unwrap(), 1);
+        assert_eq!(template_manager.get_template("web", None).await.unwrap().version, 1);
+        assert!(matches!(
+            template_manager.get_template("web", Some(2)).await,
+            Err(SyncError::TemplateNotFound { .. })
+        ));
+
+        assert!(template_manager.create_template("bad name", None, TemplateSpec::default()).await.is_err());
+    }
+}
@@ -32,7 +32,7 @@ impl ImageLayerCache {
         }
     }
 
-    fn get_layer_hash(image_path: &str) -> Result<String, String> {
+    pub fn get_layer_hash(image_path: &str) -> Result<String, String> {
         // Create a simple hash from image path and file size for layer identification
         let metadata = fs::metadata(image_path)
             .map_err(|e| format!("Failed to get image metadata: {}", e))?;
@@ -80,6 +80,14 @@ impl ImageManager {
 
     /// Setup container rootfs using copy-on-write overlay
     pub fn setup_container_rootfs(container_id: &str, image_path: &str) -> Result<String, String> {
+        Self::setup_container_rootfs_with_layers(container_id, image_path, &[])
+            .map(|(path, _)| path)
+    }
+
+    /// Setup container rootfs with extra read-only layers stacked above the image
+    /// (first layer is topmost). Returns the rootfs path and whether the extra
+    /// layers were applied; they are dropped if overlay falls back to extraction.
+    pub fn setup_container_rootfs_with_layers(container_id: &str, image_path: &str, layers: &[String]) -> Result<(String, bool), String> {
         ConsoleLogger::progress(&format!("Setting up efficient rootfs for container: {}", container_id));
         
         // Initialize cache if needed
@@ -88,20 +96,36 @@ impl ImageManager {
         let rootfs_path = format!("/tmp/quilt-containers/{}", container_id);
         
         // Try overlay approach first, fallback to direct extraction if unsupported
-        match Self::setup_overlay_rootfs(container_id, image_path, &rootfs_path) {
+        match Self::setup_overlay_rootfs(container_id, image_path, &rootfs_path, layers) {
             Ok(path) => {
                 ConsoleLogger::success(&format!("Overlay rootfs created for {}", container_id));
-                Ok(path)
+                Ok((path, true))
             }
             Err(overlay_err) => {
                 ConsoleLogger::warning(&format!("Overlay failed, using direct extraction: {}", overlay_err));
                 Self::setup_direct_rootfs(container_id, image_path, &rootfs_path)
+                    .map(|path| (path, layers.is_empty()))
             }
         }
     }
 
     /// Setup rootfs using overlay filesystem (efficient)
-    fn setup_overlay_rootfs(container_id: &str, image_path: &str, rootfs_path: &str) -> Result<String, String> {
+    fn setup_overlay_rootfs(container_id: &str, image_path: &str, rootfs_path: &str, layers: &[String]) -> Result<String, String> {
+        let base_layer_path = Self::ensure_base_layer(image_path)?;
+        
+        // Create overlay structure
+        let overlay_dir = format!("/tmp/quilt-image-cache/overlays/{}", container_id);
+        let lower_dirs: Vec<&str> = layers.iter().map(String::as_str)
+            .chain(std::iter::once(base_layer_path.as_str()))
+            .collect();
+        Self::create_overlay_mount(&overlay_dir, &lower_dirs.join(":"), rootfs_path)?;
+        
+        ConsoleLogger::success(&format!("Overlay mounted for container {}", container_id));
+        Ok(rootfs_path.to_string())
+    }
+
+    /// Extract the image into the shared base layer cache (once) and return its path
+    pub fn ensure_base_layer(image_path: &str) -> Result<String, String> {
         let cache = Self::cache();
         let mut cache_guard = cache.lock()
             .map_err(|_| "Failed to lock image cache")?;
@@ -144,14 +168,12 @@ impl ImageManager {
         
         drop(cache_guard); // Release lock early
         
-        // Create overlay structure
-        Self::create_overlay_mount(container_id, &base_layer_path, rootfs_path)
+        Ok(base_layer_path)
     }
 
-    /// Create overlay mount for container
-    fn create_overlay_mount(container_id: &str, base_layer: &str, rootfs_path: &str) -> Result<String, String> {
-        let overlay_dir = format!("/tmp/quilt-image-cache/overlays/{}", container_id);
-        
+    /// Mount an overlay at `rootfs_path` with `lower_dirs` (colon-separated, topmost
+    /// first) and a writable upper/work pair under `overlay_dir`
+    pub fn create_overlay_mount(overlay_dir: &str, lower_dirs: &str, rootfs_path: &str) -> Result<(), String> {
         // Create overlay directories
         let upper_dir = format!("{}/upper", overlay_dir);
         let work_dir = format!("{}/work", overlay_dir);
@@ -168,7 +190,7 @@ impl ImageManager {
         // Create overlay mount
         let mount_cmd = format!(
             "mount -t overlay overlay -o lowerdir={},upperdir={},workdir={} {}",
-            base_layer, upper_dir, work_dir, rootfs_path
+            lower_dirs, upper_dir, work_dir, rootfs_path
         );
         
         ConsoleLogger::debug(&format!("Creating overlay mount: {}", mount_cmd));
@@ -178,8 +200,7 @@ impl ImageManager {
             return Err(format!("Failed to create overlay mount: {}", result.stderr));
         }
         
-        ConsoleLogger::success(&format!("Overlay mounted for container {}", container_id));
-        Ok(rootfs_path.to_string())
+        Ok(())
     }
 
     /// Setup rootfs using direct extraction (fallback)
@@ -207,7 +228,7 @@ impl ImageManager {
     }
 
     /// Check if overlay filesystem is supported
-    fn is_overlay_supported() -> Result<bool, String> {
+    pub fn is_overlay_supported() -> Result<bool, String> {
         // Check if overlay module is available
         let result = CommandExecutor::execute_shell("grep -q overlay /proc/filesystems")?;
         if result.success {
@@ -225,7 +246,7 @@ impl ImageManager {
     }
 
     /// Calculate directory size recursively
-    fn calculate_directory_size(path: &str) -> Result<u64, String> {
+    pub fn calculate_directory_size(path: &str) -> Result<u64, String> {
         let mut total_size = 0u64;
         
         fn visit_dir(dir: &Path, total: &mut u64) -> Result<(), String> {
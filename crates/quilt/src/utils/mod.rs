@@ -5,6 +5,7 @@ pub mod filesystem;
 pub mod image;
 pub mod locking;
 pub mod process;
+pub mod telemetry;
 pub mod validation;
 
 // Re-export only currently used utilities to avoid warnings
@@ -0,0 +1,140 @@
+// Trace export and context propagation for the daemon.
+//
+// Requests from aria carry W3C `traceparent` metadata; `grpc_span` continues
+// that trace, so handler and exec task spans show up under the agent run that
+// caused them. Spans are exported over OTLP/HTTP when an endpoint is set in
+// `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` or `OTEL_EXPORTER_OTLP_ENDPOINT`.
+
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::Resource;
+use std::time::Duration;
+use tonic::codegen::http;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+const DEFAULT_SERVICE_NAME: &str = "quilt";
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Keeps span export running; flushes what is left and stops it when dropped
+pub struct TelemetryGuard {
+    provider: Option<TracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush traces: {}", e);
+            }
+        }
+    }
+}
+
+/// The OTLP/HTTP traces URL from the environment, if export is configured
+pub fn otlp_endpoint() -> Option<String> {
+    env_value("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT")
+        .or_else(|| env_value("OTEL_EXPORTER_OTLP_ENDPOINT")
+            .map(|base| format!("{}/v1/traces", base.trim_end_matches('/'))))
+}
+
+fn env_value(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+/// Install the global subscriber: `RUST_LOG`-filtered logs, plus span export when configured.
+/// Hold on to the returned guard until the daemon exits.
+pub fn init() -> Result<TelemetryGuard, String> {
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let service_name = env_value("OTEL_SERVICE_NAME").unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string());
+    let provider = match otlp_endpoint() {
+        Some(endpoint) => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .with_endpoint(endpoint.clone())
+                .with_timeout(EXPORT_TIMEOUT)
+                .build()
+                .map_err(|e| format!("Failed to create OTLP exporter for {}: {}", endpoint, e))?;
+            Some(TracerProvider::builder()
+                .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+                .with_resource(Resource::new(vec![KeyValue::new("service.name", service_name.clone())]))
+                .build())
+        }
+        None => None,
+    };
+
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(service_name.clone()))
+            .with_filter(LevelFilter::INFO)
+    });
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(EnvFilter::from_default_env()))
+        .with(otel_layer)
+        .try_init()
+        .map_err(|e| format!("Failed to install tracing subscriber: {}", e))?;
+
+    Ok(TelemetryGuard { provider })
+}
+
+/// Server `trace_fn`: a span per incoming request, continuing the caller's trace if it sent one
+pub fn grpc_span(request: &http::Request<()>) -> Span {
+    let span = tracing::info_span!("quilt.request", rpc = %request.uri().path());
+    span.set_parent(extract(request.headers()));
+    span
+}
+
+/// Read the trace context a caller sent in its request headers
+pub fn extract(headers: &http::HeaderMap) -> opentelemetry::Context {
+    opentelemetry::global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)))
+}
+
+struct HeaderExtractor<'a>(&'a http::HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+
+    #[test]
+    fn extracts_the_callers_trace() {
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            http::HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+        );
+
+        let context = extract(&headers);
+        let span_context = context.span().span_context().clone();
+        assert!(span_context.is_remote());
+        assert_eq!(span_context.trace_id().to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(span_context.span_id().to_string(), "00f067aa0ba902b7");
+    }
+
+    #[test]
+    fn requests_without_context_start_a_new_trace() {
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        assert!(!extract(&http::HeaderMap::new()).span().span_context().is_valid());
+    }
+}
@@ -197,6 +197,26 @@ impl InputValidator {
         Ok((host_path, container_path))
     }
 
+    /// Parse a volume as `host_path:container_path[:ro|:rw]`, returning the two
+    /// paths and whether the mount is read-only
+    pub fn parse_volume(volume: &str) -> Result<(String, String, bool), String> {
+        let (mount, read_only) = match volume.rsplit_once(':') {
+            Some((rest, "ro")) => (rest, true),
+            Some((rest, "rw")) => (rest, false),
+            _ => (volume, false),
+        };
+        
+        let (host_path, container_path) = InputValidator::validate_mount_point(mount)?;
+        if !host_path.starts_with('/') {
+            return Err(format!("Volume host path must be absolute: '{}'", host_path));
+        }
+        if container_path == "/" {
+            return Err("Cannot mount a volume over the container root".to_string());
+        }
+        
+        Ok((host_path, container_path, read_only))
+    }
+
     /// Sanitize string for safe usage
     pub fn sanitize_string(input: &str, max_length: usize) -> String {
         let sanitized = input